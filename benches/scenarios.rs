@@ -0,0 +1,160 @@
+//! Standard scenarios for tracking simulation performance. Run with `cargo bench`; a
+//! single scenario with e.g. `cargo bench -- sandpile`.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use sand_engine::{AdvancedPhysicsEngine, MaterialRenderer, MaterialType, Simulation};
+use std::hint::black_box;
+
+const FRAME: f32 = 1.0 / 60.0;
+
+/// A grid with its bottom rows solid stone, seeded so every run does the same work
+fn floored_simulation(width: usize, height: usize) -> Simulation {
+    let mut simulation = Simulation::new(width, height);
+    simulation.set_seed(1);
+    for y in height - 4..height {
+        for x in 0..width {
+            simulation.add_particle(x, y, MaterialType::Stone, None);
+        }
+    }
+    simulation
+}
+
+/// About 100k particles: alternating bands of sand and water over most of a 400x300 grid
+fn hundred_thousand_particles() -> Simulation {
+    let mut simulation = floored_simulation(400, 300);
+    for y in 40..290 {
+        let material = if (y / 10) % 2 == 0 { MaterialType::Sand } else { MaterialType::Water };
+        for x in 0..400 {
+            simulation.add_particle(x, y, material, None);
+        }
+    }
+    simulation
+}
+
+/// Sand poured onto one spot, settling into a pile
+fn sandpile(c: &mut Criterion) {
+    c.bench_function("sandpile", |b| {
+        b.iter_batched(
+            || floored_simulation(200, 150),
+            |mut simulation| {
+                for _ in 0..120 {
+                    for x in 98..102 {
+                        simulation.add_particle(x, 0, MaterialType::Sand, None);
+                    }
+                    simulation.update(FRAME);
+                }
+                simulation
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+/// Water poured off a ledge, spreading across the floor below
+fn waterfall(c: &mut Criterion) {
+    let ledged = || {
+        let mut simulation = floored_simulation(200, 150);
+        for x in 0..80 {
+            simulation.add_particle(x, 50, MaterialType::Stone, None);
+        }
+        simulation
+    };
+
+    c.bench_function("waterfall", |b| {
+        b.iter_batched(
+            ledged,
+            |mut simulation| {
+                for _ in 0..120 {
+                    for y in 40..50 {
+                        simulation.add_particle(0, y, MaterialType::Water, None);
+                    }
+                    simulation.update(FRAME);
+                }
+                simulation
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+fn hundred_thousand(c: &mut Criterion) {
+    c.bench_function("100k_particles_update", |b| {
+        b.iter_batched(
+            hundred_thousand_particles,
+            |mut simulation| {
+                simulation.update(FRAME);
+                simulation
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    #[cfg(feature = "parallel")]
+    c.bench_function("100k_particles_update_parallel", |b| {
+        b.iter_batched(
+            hundred_thousand_particles,
+            |mut simulation| {
+                simulation.update_parallel(FRAME);
+                simulation
+            },
+            BatchSize::LargeInput,
+        );
+    });
+
+    // Packed sand that has settled, so each update only runs the grains dropped on it
+    let mut settled = floored_simulation(400, 300);
+    for y in 40..290 {
+        for x in 0..400 {
+            settled.add_particle(x, y, MaterialType::Sand, None);
+        }
+    }
+    while settled.active_cell_count() > 0 {
+        settled.update(FRAME);
+    }
+    let mut drop_x = 0;
+    c.bench_function("100k_settled_update", |b| {
+        b.iter(|| {
+            drop_x = (drop_x + 37) % 400;
+            settled.add_particle(drop_x, 0, MaterialType::Sand, None);
+            settled.update(FRAME);
+        });
+    });
+
+    let start = hundred_thousand_particles();
+    let renderer = MaterialRenderer::new();
+    let mut frame = vec![0; MaterialRenderer::frame_len(start.width, start.height)];
+    c.bench_function("100k_particles_render", |b| {
+        b.iter(|| renderer.render(black_box(&start), &mut frame));
+    });
+}
+
+/// The chunked engine on the same sandpile, without rigid bodies so the timings are
+/// comparable across runs
+fn engine_sandpile(c: &mut Criterion) {
+    c.bench_function("engine_sandpile", |b| {
+        b.iter_batched(
+            || {
+                let mut engine = AdvancedPhysicsEngine::new();
+                engine.set_seed(1);
+                engine.enable_rigid_bodies = false;
+                for x in -100..100 {
+                    engine.add_particle(x, 100, MaterialType::Stone, None);
+                }
+                engine
+            },
+            |mut engine| {
+                for _ in 0..120 {
+                    for x in -2..2 {
+                        engine.add_particle(x, 0, MaterialType::Sand, None);
+                    }
+                    engine.update_with_delta(FRAME);
+                }
+                engine
+            },
+            BatchSize::LargeInput,
+        );
+    });
+}
+
+criterion_group!(benches, sandpile, waterfall, hundred_thousand, engine_sandpile);
+criterion_main!(benches);
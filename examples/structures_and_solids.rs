@@ -89,10 +89,11 @@ fn main() {
     println!("\n6. Running physics simulation...");
     
     // Run a few physics steps
+    let materials = MaterialRegistry::new();
     for step in 0..5 {
         // Update tile entities
         let nearby_particles = |_pos: (i64, i64)| Vec::new();
-        let _effects = tile_entity_manager.update_all(0.016, nearby_particles);
+        let _effects = tile_entity_manager.update_all(0.016, &materials, nearby_particles);
         
         // Update rigid bodies
         rigid_body_manager.step();
@@ -122,6 +123,9 @@ fn main() {
         game_mode: GameMode::Creative,
         last_played: "2025-01-01T00:00:00Z".to_string(),
         seed: 12345,
+        rules: WorldRules::default(),
+        camera_bookmarks: CameraBookmarks::default(),
+        weather: WeatherState::default(),
     };
     
     let ecs = ECS::new();
@@ -129,10 +133,12 @@ fn main() {
     match save_manager.save_world(
         "structures_demo",
         &chunk_manager,
+        &MaterialRegistry::new(),
         &ecs,
         &tile_entity_manager,
         &world_generator,
         metadata,
+        None,
     ) {
         Ok(_) => println!("   ✓ World saved successfully"),
         Err(e) => println!("   ✗ Save failed: {}", e),
@@ -31,6 +31,8 @@ class SandEngineApp {
         window.wsManager = new WebSocketManager();
         window.materialManager = new MaterialManager();
         window.structureManager = new StructureManager();
+        window.measureManager = new MeasureManager();
+        window.playerManager = new PlayerManager();
         window.canvasManager = new CanvasManager();
         window.brushManager = new BrushManager();
         window.uiManager = new UIManager();
@@ -46,6 +48,21 @@ class SandEngineApp {
             window.structureManager.setStructures(message.structures);
         });
 
+        window.wsManager.onMessage('welcome', (message) => {
+            window.wsManager.session = message;
+            window.playerManager.setClientId(message.client_id);
+            console.log(`Negotiated protocol v${message.protocol_version}`, message.capabilities);
+        });
+
+        window.wsManager.onMessage('handshake_rejected', (message) => {
+            window.uiManager.showNotification(`Server rejected this client: ${message.reason}`, 'error');
+        });
+
+        window.wsManager.onMessage('world_rules', (message) => {
+            window.worldRules = message.rules;
+            console.log('World rules:', message.rules);
+        });
+
         window.wsManager.onMessage('simulation_state', (message) => {
             window.canvasManager.handleSimulationState(message);
         });
@@ -54,6 +71,18 @@ class SandEngineApp {
             window.canvasManager.handleDeltaUpdate(message);
         });
 
+        window.wsManager.onMessage('signs', (message) => {
+            window.canvasManager.handleSigns(message);
+        });
+
+        window.wsManager.onMessage('effects', (message) => {
+            window.canvasManager.handleEffects(message);
+        });
+
+        window.wsManager.onMessage('critters', (message) => {
+            window.canvasManager.handleCritters(message);
+        });
+
         window.wsManager.onMessage('particle_info', (message) => {
             window.canvasManager.handleParticleInfo(message);
         });
@@ -66,6 +95,26 @@ class SandEngineApp {
             }
         });
 
+        window.wsManager.onMessage('distance', (message) => {
+            window.measureManager.handleDistance(message);
+        });
+
+        window.wsManager.onMessage('region_count', (message) => {
+            window.measureManager.handleRegionCount(message);
+        });
+
+        window.wsManager.onMessage('volume_update', (message) => {
+            window.measureManager.handleVolumeUpdate(message);
+        });
+
+        window.wsManager.onMessage('telemetry', (message) => {
+            window.measureManager.handleTelemetry(message);
+        });
+
+        window.wsManager.onMessage('players', (message) => {
+            window.playerManager.handlePlayers(message);
+        });
+
         window.wsManager.onMessage('error', (message) => {
             window.uiManager.showNotification(`Error: ${message.message}`, 'error');
         });
@@ -4,6 +4,18 @@ class CanvasManager {
         this.canvas = document.getElementById('simulationCanvas');
         this.ctx = this.canvas.getContext('2d', { alpha: false });
         this.simulationData = new Map();
+        this.signs = [];
+        this.effects = [];
+        this.EFFECT_COLORS = {
+            spark: [255, 230, 120],
+            splash: [170, 210, 255],
+            ember: [255, 110, 30],
+        };
+        this.critters = [];
+        this.CRITTER_COLORS = {
+            fish: [255, 150, 40],
+            bird: [60, 40, 30],
+        };
         
         // Constants
         this.CELL_SIZE = 4;
@@ -41,6 +53,16 @@ class CanvasManager {
             if (window.structureManager && window.structureManager.handleCanvasClick(gridX, gridY)) {
                 return;
             }
+
+            if (window.measureManager && window.measureManager.handleMouseDown(gridX, gridY)) {
+                return;
+            }
+
+            // Shift-click places, edits or (with empty text) removes a sign
+            if (e.shiftKey) {
+                this.editSign(gridX, gridY);
+                return;
+            }
             
             // Regular painting
             window.isDrawing = true;
@@ -49,6 +71,15 @@ class CanvasManager {
     }
 
     handleMouseMove(e) {
+        const pos = this.getMousePos(e);
+        if (window.playerManager) {
+            window.playerManager.reportCursor(Math.floor(pos.x / this.CELL_SIZE), Math.floor(pos.y / this.CELL_SIZE));
+        }
+        if (window.measureManager && window.measureManager.handleMouseMove(
+            Math.floor(pos.x / this.CELL_SIZE), Math.floor(pos.y / this.CELL_SIZE))) {
+            return;
+        }
+
         if (window.isDrawing && window.paintingEnabled !== false) {
             this.handleDraw(e);
         } else {
@@ -58,12 +89,28 @@ class CanvasManager {
 
     handleMouseUp(e) {
         if (e.button === 0) {
-            window.isDrawing = false;
+            const pos = this.getMousePos(e);
+            if (window.measureManager) {
+                window.measureManager.handleMouseUp(
+                    Math.floor(pos.x / this.CELL_SIZE), Math.floor(pos.y / this.CELL_SIZE));
+            }
+            this.endStroke();
         }
     }
 
-    handleMouseLeave() {
+    // Each stroke, from mouse down to up, is undone in one step
+    endStroke() {
+        if (window.isDrawing && window.wsManager) {
+            window.wsManager.send({ type: 'end_stroke' });
+        }
         window.isDrawing = false;
+    }
+
+    handleMouseLeave() {
+        this.endStroke();
+        if (window.playerManager) {
+            window.playerManager.reportCursor(null, null);
+        }
         const coordsText = document.getElementById('coords-text');
         coordsText.textContent = 'Coords: (--, --)';
     }
@@ -100,6 +147,34 @@ class CanvasManager {
         }
     }
 
+    editSign(gridX, gridY) {
+        const existing = this.signs.find((sign) => sign.x === gridX && sign.y === gridY);
+        const text = window.prompt('Sign text (leave empty to remove):', existing ? existing.text : '');
+        if (text === null || !window.wsManager) {
+            return;
+        }
+        if (text.trim() === '') {
+            window.wsManager.send({ type: 'remove_sign', x: gridX, y: gridY });
+        } else {
+            window.wsManager.send({ type: 'place_sign', x: gridX, y: gridY, text });
+        }
+    }
+
+    handleSigns(message) {
+        this.signs = message.signs;
+        this.draw();
+    }
+
+    handleEffects(message) {
+        this.effects = message.effects;
+        this.draw();
+    }
+
+    handleCritters(message) {
+        this.critters = message.critters;
+        this.draw();
+    }
+
     updateCoordsDisplay(event) {
         const pos = this.getMousePos(event);
         const gridX = Math.floor(pos.x / this.CELL_SIZE);
@@ -137,6 +212,15 @@ class CanvasManager {
     }
 
     handleDeltaUpdate(delta) {
+        // Keyframed chunks are re-sent in full, so drop whatever we had there
+        for (const region of delta.keyframes || []) {
+            for (let y = region.y; y < region.y + region.height; y++) {
+                for (let x = region.x; x < region.x + region.width; x++) {
+                    this.simulationData.delete(`${x},${y}`);
+                }
+            }
+        }
+
         // Apply removed particles
         for (const coords of delta.removed) {
             this.simulationData.delete(coords);
@@ -153,6 +237,10 @@ class CanvasManager {
     handleParticleInfo(info) {
         const coordsText = document.getElementById('coords-text');
         
+        const moving = info.vx || info.vy;
+        const velocityText = moving ? ` | Velocity: ${info.vx.toFixed(2)}, ${info.vy.toFixed(2)}` : '';
+        const chunkText = info.chunk_status ? `Chunk (${info.chunk_x}, ${info.chunk_y}) ${info.chunk_status}` : '';
+
         if (info.material && window.materialManager) {
             const material = window.materialManager.getMaterial(info.material);
             const materialName = material ? material.name : 'Unknown';
@@ -177,10 +265,11 @@ class CanvasManager {
             coordsText.innerHTML = `
                 <div>Coords: (${info.x}, ${info.y})</div>
                 <div>${materialName}${burningText}${propertiesText}</div>
-                <div>Density: ${material ? material.density.toFixed(2) : 'N/A'}${tempText}${lifeText}</div>
+                <div>Density: ${material ? material.density.toFixed(2) : 'N/A'}${tempText}${lifeText}${velocityText}</div>
+                <div>${chunkText}</div>
             `;
         } else {
-            coordsText.textContent = `Coords: (${info.x}, ${info.y}) | Empty`;
+            coordsText.textContent = `Coords: (${info.x}, ${info.y}) | Empty | ${chunkText}`;
         }
     }
 
@@ -198,5 +287,50 @@ class CanvasManager {
             this.ctx.fillStyle = `rgb(${particle.color.join(',')})`;
             this.ctx.fillRect(canvasX, canvasY, this.CELL_SIZE, this.CELL_SIZE);
         }
+
+        this.drawEffects();
+        this.drawCritters();
+        this.drawSigns();
+
+        if (window.measureManager) {
+            window.measureManager.drawOverlay(this.ctx, this.CELL_SIZE);
+        }
+
+        if (window.playerManager) {
+            window.playerManager.drawOverlay(this.ctx, this.CELL_SIZE);
+        }
+    }
+
+    drawEffects() {
+        for (const effect of this.effects) {
+            const color = this.EFFECT_COLORS[effect.kind] || [255, 255, 255];
+            const alpha = effect.lifetime > 0 ? Math.max(0, Math.min(1, effect.life / effect.lifetime)) : 0;
+            this.ctx.fillStyle = `rgba(${color.join(',')},${0.9 * alpha})`;
+            this.ctx.fillRect(Math.floor(effect.x) * this.CELL_SIZE, Math.floor(effect.y) * this.CELL_SIZE, this.CELL_SIZE, this.CELL_SIZE);
+        }
+    }
+
+    drawCritters() {
+        for (const critter of this.critters) {
+            const color = this.CRITTER_COLORS[critter.kind] || [255, 255, 255];
+            this.ctx.fillStyle = `rgb(${color.join(',')})`;
+            this.ctx.fillRect(Math.floor(critter.x) * this.CELL_SIZE, Math.floor(critter.y) * this.CELL_SIZE, this.CELL_SIZE, this.CELL_SIZE);
+        }
+    }
+
+    drawSigns() {
+        this.ctx.font = '10px monospace';
+        this.ctx.textAlign = 'center';
+        this.ctx.textBaseline = 'bottom';
+        for (const sign of this.signs) {
+            const canvasX = sign.x * this.CELL_SIZE + this.CELL_SIZE / 2;
+            const canvasY = sign.y * this.CELL_SIZE;
+            this.ctx.fillStyle = '#c8a060';
+            this.ctx.fillRect(sign.x * this.CELL_SIZE, canvasY, this.CELL_SIZE, this.CELL_SIZE);
+            this.ctx.fillStyle = 'black';
+            this.ctx.fillText(sign.text, canvasX + 1, canvasY - 1);
+            this.ctx.fillStyle = 'white';
+            this.ctx.fillText(sign.text, canvasX, canvasY - 2);
+        }
     }
 }
\ No newline at end of file
@@ -2,6 +2,7 @@
 class MaterialManager {
     constructor() {
         this.materials = new Map();
+        this.materialsByCode = new Map();
         this.currentMaterial = 'Sand';
         this.currentMaterialType = 1;
         this.paletteDiv = document.getElementById('palette');
@@ -10,8 +11,10 @@ class MaterialManager {
 
     setMaterials(materialsArray) {
         this.materials.clear();
+        this.materialsByCode.clear();
         for (const material of materialsArray) {
             this.materials.set(material.id, material);
+            this.materialsByCode.set(material.code, material);
         }
         this.populatePalette();
         this.updateUIText();
@@ -21,6 +24,11 @@ class MaterialManager {
         return this.materials.get(id);
     }
 
+    // Material with the given id in binary grid frames; Empty isn't listed, so it is undefined
+    getMaterialByCode(code) {
+        return this.materialsByCode.get(code);
+    }
+
     getCurrentMaterial() {
         return {
             name: this.currentMaterial,
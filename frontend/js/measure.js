@@ -0,0 +1,215 @@
+// Measurement tools: ruler, region counts and cavity volume tracking
+class MeasureManager {
+    constructor() {
+        this.mode = null; // 'ruler', 'count' or 'volume'
+        this.dragStart = null;
+        this.dragEnd = null;
+        this.dragging = false;
+        this.trackedCell = null;
+        this.setupMeasurePanel();
+    }
+
+    setupMeasurePanel() {
+        this.measurePanel = document.createElement('div');
+        this.measurePanel.id = 'measure-panel';
+        this.measurePanel.className = 'panel-section';
+        this.measurePanel.innerHTML = `
+            <h3>Measure</h3>
+            <button class="mode-button" data-measure-mode="ruler">Ruler</button>
+            <button class="mode-button" data-measure-mode="count">Count Region</button>
+            <button class="mode-button" data-measure-mode="volume">Track Volume</button>
+            <button id="stop-volume-tracking" class="mode-button">Stop Tracking</button>
+            <button id="export-telemetry" class="mode-button">Export CSV</button>
+            <div id="measure-result"></div>
+        `;
+
+        const rightPanel = document.getElementById('right-panel');
+        rightPanel.appendChild(this.measurePanel);
+
+        this.setupEventListeners();
+    }
+
+    setupEventListeners() {
+        this.measurePanel.querySelectorAll('[data-measure-mode]').forEach(button => {
+            button.addEventListener('click', () => this.toggleMode(button.dataset.measureMode));
+        });
+
+        document.getElementById('stop-volume-tracking').addEventListener('click', () => {
+            this.trackedCell = null;
+            this.send({ type: 'stop_tracking_volume' });
+            this.showResult('Volume tracking stopped');
+        });
+
+        document.getElementById('export-telemetry').addEventListener('click', () => {
+            this.send({ type: 'export_telemetry' });
+        });
+    }
+
+    toggleMode(mode) {
+        if (this.mode === mode) {
+            this.exitMeasureMode();
+        } else {
+            this.enterMeasureMode(mode);
+        }
+    }
+
+    // Step through the tools and back to painting
+    cycleMode() {
+        const modes = ['ruler', 'count', 'volume'];
+        const index = modes.indexOf(this.mode);
+        if (index === modes.length - 1) {
+            this.exitMeasureMode();
+        } else {
+            this.enterMeasureMode(modes[index + 1]);
+        }
+    }
+
+    enterMeasureMode(mode) {
+        if (window.structureManager && window.structureManager.isInStructureMode()) {
+            window.structureManager.exitStructureMode();
+        }
+        this.mode = mode;
+        this.dragStart = null;
+        this.dragEnd = null;
+        this.dragging = false;
+        this.updateButtons();
+        window.paintingEnabled = false;
+    }
+
+    exitMeasureMode() {
+        this.mode = null;
+        this.dragStart = null;
+        this.dragEnd = null;
+        this.dragging = false;
+        this.updateButtons();
+        window.paintingEnabled = true;
+        if (window.canvasManager) {
+            window.canvasManager.draw();
+        }
+    }
+
+    isInMeasureMode() {
+        return this.mode !== null;
+    }
+
+    updateButtons() {
+        this.measurePanel.querySelectorAll('[data-measure-mode]').forEach(button => {
+            button.classList.toggle('active', button.dataset.measureMode === this.mode);
+        });
+        this.measurePanel.classList.toggle('active', this.mode !== null);
+    }
+
+    // Canvas hooks; each returns true when the event was used for measuring
+
+    handleMouseDown(gridX, gridY) {
+        if (!this.mode) {
+            return false;
+        }
+        if (this.mode === 'volume') {
+            this.trackedCell = { x: gridX, y: gridY };
+            this.send({ type: 'track_volume', x: gridX, y: gridY });
+        } else {
+            this.dragStart = { x: gridX, y: gridY };
+            this.dragEnd = { x: gridX, y: gridY };
+            this.dragging = true;
+        }
+        return true;
+    }
+
+    handleMouseMove(gridX, gridY) {
+        if (!this.mode || !this.dragging) {
+            return false;
+        }
+        this.dragEnd = { x: gridX, y: gridY };
+        window.canvasManager.draw();
+        return true;
+    }
+
+    handleMouseUp(gridX, gridY) {
+        if (!this.mode || !this.dragging) {
+            return false;
+        }
+        this.dragging = false;
+        this.dragEnd = { x: gridX, y: gridY };
+        const { x: x0, y: y0 } = this.dragStart;
+        if (this.mode === 'ruler') {
+            this.send({ type: 'measure_distance', x0, y0, x1: gridX, y1: gridY });
+        } else {
+            this.send({
+                type: 'count_region',
+                x: Math.max(0, Math.min(x0, gridX)),
+                y: Math.max(0, Math.min(y0, gridY)),
+                width: Math.abs(gridX - x0) + 1,
+                height: Math.abs(gridY - y0) + 1
+            });
+        }
+        // The overlay stays up until the next measurement
+        return true;
+    }
+
+    drawOverlay(ctx, cellSize) {
+        if (!this.mode) {
+            return;
+        }
+        ctx.strokeStyle = '#ffeb3b';
+        ctx.lineWidth = 1;
+        const start = this.dragStart;
+        if (start && this.dragEnd && this.mode === 'ruler') {
+            ctx.beginPath();
+            ctx.moveTo((start.x + 0.5) * cellSize, (start.y + 0.5) * cellSize);
+            ctx.lineTo((this.dragEnd.x + 0.5) * cellSize, (this.dragEnd.y + 0.5) * cellSize);
+            ctx.stroke();
+        } else if (start && this.dragEnd && this.mode === 'count') {
+            const x = Math.min(start.x, this.dragEnd.x);
+            const y = Math.min(start.y, this.dragEnd.y);
+            const width = Math.abs(this.dragEnd.x - start.x) + 1;
+            const height = Math.abs(this.dragEnd.y - start.y) + 1;
+            ctx.strokeRect(x * cellSize, y * cellSize, width * cellSize, height * cellSize);
+        } else if (this.trackedCell && this.mode === 'volume') {
+            ctx.strokeRect(this.trackedCell.x * cellSize, this.trackedCell.y * cellSize, cellSize, cellSize);
+        }
+    }
+
+    handleDistance(message) {
+        this.showResult(`
+            <div>dx ${message.dx}, dy ${message.dy}</div>
+            <div>Distance: ${message.euclidean.toFixed(2)} cells (${message.manhattan} along the grid)</div>
+        `);
+    }
+
+    handleRegionCount(message) {
+        const rows = message.counts.map(({ material, count }) => {
+            const info = window.materialManager ? window.materialManager.getMaterial(material) : null;
+            return `<div>${info ? info.name : material}: ${count}</div>`;
+        });
+        const { width, height } = message.region;
+        this.showResult(`<div>${message.total} particles in ${width}×${height}</div>${rows.join('')}`);
+    }
+
+    handleVolumeUpdate(message) {
+        const flow = message.flow_rate === null ? '--' : message.flow_rate.toFixed(2);
+        this.showResult(`
+            <div>Filled: ${message.filled} / ${message.capacity} cells</div>
+            <div>Flow: ${flow} cells/s</div>
+        `);
+    }
+
+    handleTelemetry(message) {
+        const blob = new Blob([message.csv], { type: 'text/csv' });
+        const link = document.createElement('a');
+        link.href = URL.createObjectURL(blob);
+        link.download = 'telemetry.csv';
+        link.click();
+        URL.revokeObjectURL(link.href);
+    }
+
+    showResult(html) {
+        document.getElementById('measure-result').innerHTML = html;
+    }
+
+    send(message) {
+        if (window.wsManager) {
+            window.wsManager.send(message);
+        }
+    }
+}
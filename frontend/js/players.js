@@ -0,0 +1,108 @@
+// Other people connected to the same world: their cursors, brushes and materials
+class PlayerManager {
+    constructor() {
+        this.clientId = null;
+        this.players = [];
+        this.lastCursorSent = 0;
+        this.pendingCursor = undefined;
+        this.CURSOR_INTERVAL_MS = 50;
+        this.setupPlayerList();
+    }
+
+    setupPlayerList() {
+        this.playerList = document.createElement('p');
+        this.playerList.id = 'player-list';
+        document.getElementById('ui-info').appendChild(this.playerList);
+        this.updatePlayerList();
+    }
+
+    setClientId(id) {
+        this.clientId = id;
+    }
+
+    // Tell the server where this client points; sent at most every CURSOR_INTERVAL_MS
+    reportCursor(gridX, gridY) {
+        this.pendingCursor = gridX === null ? null : [gridX, gridY];
+        const now = performance.now();
+        if (now - this.lastCursorSent >= this.CURSOR_INTERVAL_MS) {
+            this.flushCursor();
+        } else if (!this.cursorTimer) {
+            this.cursorTimer = setTimeout(() => this.flushCursor(), this.CURSOR_INTERVAL_MS);
+        }
+    }
+
+    flushCursor() {
+        clearTimeout(this.cursorTimer);
+        this.cursorTimer = null;
+        if (this.pendingCursor === undefined || !window.wsManager) {
+            return;
+        }
+        const material = window.materialManager ? window.materialManager.getCurrentMaterial() : null;
+        window.wsManager.send({
+            type: 'cursor',
+            position: this.pendingCursor,
+            material: material ? material.id : null,
+            brush_size: window.brushManager ? window.brushManager.getBrushSize() : null
+        });
+        this.pendingCursor = undefined;
+        this.lastCursorSent = performance.now();
+    }
+
+    handlePlayers(message) {
+        this.players = message.players;
+        this.updatePlayerList();
+        if (window.canvasManager) {
+            window.canvasManager.draw();
+        }
+    }
+
+    remotePlayers() {
+        return this.players.filter(player => player.id !== this.clientId);
+    }
+
+    updatePlayerList() {
+        const others = this.remotePlayers();
+        if (others.length === 0) {
+            this.playerList.textContent = 'Playing alone';
+            return;
+        }
+        // Names come from other clients, so they go in as text, never markup
+        this.playerList.textContent = 'Also here: ';
+        others.forEach((player, index) => {
+            if (index > 0) {
+                this.playerList.append(', ');
+            }
+            const name = document.createElement('span');
+            name.style.color = `rgb(${player.color.join(',')})`;
+            name.textContent = player.name;
+            this.playerList.append(name);
+        });
+    }
+
+    drawOverlay(ctx, cellSize) {
+        ctx.font = '10px monospace';
+        ctx.textAlign = 'left';
+        ctx.textBaseline = 'bottom';
+        for (const player of this.remotePlayers()) {
+            if (!player.cursor) {
+                continue;
+            }
+            const [x, y] = player.cursor;
+            const centerX = (x + 0.5) * cellSize;
+            const centerY = (y + 0.5) * cellSize;
+            const color = `rgb(${player.color.join(',')})`;
+
+            ctx.strokeStyle = color;
+            ctx.lineWidth = player.painting ? 2 : 1;
+            ctx.beginPath();
+            ctx.arc(centerX, centerY, Math.max(player.brush_size, 1) * cellSize, 0, Math.PI * 2);
+            ctx.stroke();
+
+            const info = player.material && window.materialManager
+                ? window.materialManager.getMaterial(player.material) : null;
+            const label = info ? `${player.name} (${info.name})` : player.name;
+            ctx.fillStyle = color;
+            ctx.fillText(label, centerX + cellSize * 2, centerY - cellSize);
+        }
+    }
+}
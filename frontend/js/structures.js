@@ -7,6 +7,9 @@ class StructureManager {
         this.selectedStructure = null;
         this.previewCanvas = null;
         this.previewCtx = null;
+        // Corner picked so far while capturing a region to save
+        this.isCaptureMode = false;
+        this.captureStart = null;
         this.setupStructurePanel();
     }
 
@@ -18,6 +21,7 @@ class StructureManager {
         this.structurePanel.innerHTML = `
             <h3>Structures</h3>
             <button id="toggle-structure-mode" class="mode-button">Structure Mode: OFF</button>
+            <button id="capture-structure" class="mode-button">Save Region</button>
             <div id="structure-list"></div>
             <div id="structure-preview"></div>
         `;
@@ -36,11 +40,22 @@ class StructureManager {
             this.toggleStructureMode();
         });
 
+        document.getElementById('capture-structure').addEventListener('click', () => {
+            if (this.isCaptureMode) {
+                this.exitCaptureMode();
+            } else {
+                this.enterCaptureMode();
+            }
+        });
+
         // Listen for escape key to exit structure mode
         document.addEventListener('keydown', (e) => {
             if (e.key === 'Escape' && this.isStructureMode) {
                 this.exitStructureMode();
             }
+            if (e.key === 'Escape' && this.isCaptureMode) {
+                this.exitCaptureMode();
+            }
         });
     }
 
@@ -132,6 +147,9 @@ class StructureManager {
     }
 
     enterStructureMode() {
+        if (window.measureManager && window.measureManager.isInMeasureMode()) {
+            window.measureManager.exitMeasureMode();
+        }
         this.isStructureMode = true;
         this.updateStructureModeStatus();
         
@@ -197,7 +215,54 @@ class StructureManager {
         }
     }
 
+    enterCaptureMode() {
+        if (this.isStructureMode) {
+            this.exitStructureMode();
+        }
+        this.isCaptureMode = true;
+        this.captureStart = null;
+        window.paintingEnabled = false;
+        document.getElementById('capture-structure').classList.add('active');
+        document.getElementById('material-text').textContent = 'Click two corners of the region to save';
+    }
+
+    exitCaptureMode() {
+        this.isCaptureMode = false;
+        this.captureStart = null;
+        window.paintingEnabled = true;
+        document.getElementById('capture-structure').classList.remove('active');
+        if (window.materialManager) {
+            window.materialManager.updateUIText();
+        }
+    }
+
+    // Two clicks pick the corners, then the region is saved under a prompted name
+    handleCaptureClick(gridX, gridY) {
+        if (!this.captureStart) {
+            this.captureStart = { x: gridX, y: gridY };
+            document.getElementById('material-text').textContent = 'Click the opposite corner';
+            return;
+        }
+        const start = this.captureStart;
+        const name = window.prompt('Structure name (letters, digits, - and _)');
+        if (name && window.wsManager) {
+            window.wsManager.send({
+                type: 'save_structure',
+                name,
+                min_x: start.x,
+                min_y: start.y,
+                max_x: gridX,
+                max_y: gridY
+            });
+        }
+        this.exitCaptureMode();
+    }
+
     handleCanvasClick(gridX, gridY) {
+        if (this.isCaptureMode) {
+            this.handleCaptureClick(gridX, gridY);
+            return true;
+        }
         if (!this.isStructureMode || !this.selectedStructure) {
             return false;
         }
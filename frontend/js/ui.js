@@ -11,6 +11,23 @@ class UIManager {
             this.clearSimulation();
         });
 
+        // Save slots live on the server
+        document.getElementById('save-button').addEventListener('click', () => {
+            const name = prompt('Save as:', this.saveName || 'quicksave');
+            if (name) {
+                this.saveName = name;
+                window.wsManager.send({ type: 'save_simulation', name });
+                this.showNotification(`Saving '${name}'`);
+            }
+        });
+        document.getElementById('load-button').addEventListener('click', () => {
+            const name = prompt('Load save:', this.saveName || 'quicksave');
+            if (name) {
+                this.saveName = name;
+                window.wsManager.send({ type: 'load_simulation', name });
+            }
+        });
+
         // Keyboard shortcuts
         window.addEventListener('keydown', (e) => {
             this.handleKeyPress(e);
@@ -18,6 +35,11 @@ class UIManager {
     }
 
     handleKeyPress(e) {
+        if (e.ctrlKey || e.metaKey) {
+            this.handleShortcut(e);
+            return;
+        }
+
         switch (e.key.toLowerCase()) {
             case 'c':
                 this.clearSimulation();
@@ -26,6 +48,14 @@ class UIManager {
                 if (window.structureManager && window.structureManager.isInStructureMode()) {
                     window.structureManager.exitStructureMode();
                 }
+                if (window.measureManager && window.measureManager.isInMeasureMode()) {
+                    window.measureManager.exitMeasureMode();
+                }
+                break;
+            case 'm':
+                if (window.measureManager) {
+                    window.measureManager.cycleMode();
+                }
                 break;
             case 's':
                 if (window.structureManager) {
@@ -46,6 +76,23 @@ class UIManager {
         }
     }
 
+    handleShortcut(e) {
+        const key = e.key.toLowerCase();
+        if (key === 'z' && !e.shiftKey) {
+            e.preventDefault();
+            this.sendCommand('undo');
+        } else if (key === 'y' || (key === 'z' && e.shiftKey)) {
+            e.preventDefault();
+            this.sendCommand('redo');
+        }
+    }
+
+    sendCommand(type) {
+        if (window.wsManager) {
+            window.wsManager.send({ type });
+        }
+    }
+
     selectMaterialByNumber(number) {
         if (!window.materialManager) return;
         
@@ -1,9 +1,24 @@
+// Protocol version and optional features this client understands
+const PROTOCOL_VERSION = 1;
+const CLIENT_CAPABILITIES = ['binary_deltas', 'effects'];
+// Browsers can only inflate gzip natively; zstd is left to native clients
+const CLIENT_CODECS = typeof DecompressionStream === 'undefined' ? [] : ['gzip'];
+// First byte of a compressed binary frame
+const FRAME_TAG_GZIP = 2;
+// First byte of a binary grid frame, followed by its kind
+const FRAME_TAG_GRID = 3;
+const GRID_FRAME_FULL = 0;
+const GRID_FRAME_DELTA = 1;
+
 // WebSocket connection management
 class WebSocketManager {
     constructor() {
         this.socket = null;
         this.connectionStatus = document.getElementById('connection-status');
         this.messageHandlers = new Map();
+        this.session = null;
+        // Compressed frames inflate asynchronously; chain them to keep message order
+        this.pending = Promise.resolve();
     }
 
     connect() {
@@ -11,20 +26,29 @@ class WebSocketManager {
         const wsUrl = `${protocol}//${window.location.host}/ws`;
         
         this.socket = new WebSocket(wsUrl);
+        this.socket.binaryType = 'arraybuffer';
         
         this.socket.onopen = () => {
             console.log('Connected to sand engine server');
             this.connectionStatus.textContent = 'Status: Connected';
             this.connectionStatus.className = 'connected';
+            this.session = null;
+            this.send({
+                type: 'hello',
+                protocol_version: PROTOCOL_VERSION,
+                capabilities: CLIENT_CAPABILITIES,
+                codecs: CLIENT_CODECS,
+                name: localStorage.getItem('playerName') || undefined,
+            });
+            // Deltas only carry changes, so start from a full snapshot
+            this.send({ type: 'request_snapshot' });
         };
         
         this.socket.onmessage = (event) => {
-            try {
-                const message = JSON.parse(event.data);
-                this.handleMessage(message);
-            } catch (error) {
-                console.error('Failed to parse server message:', error);
-            }
+            this.pending = this.pending
+                .then(() => this.decodeFrame(event.data))
+                .then((message) => this.handleMessage(message))
+                .catch((error) => console.error('Failed to parse server message:', error));
         };
         
         this.socket.onclose = () => {
@@ -43,6 +67,74 @@ class WebSocketManager {
         };
     }
 
+    async decodeFrame(data) {
+        if (typeof data === 'string') {
+            return JSON.parse(data);
+        }
+        const bytes = new Uint8Array(data);
+        if (bytes[0] === FRAME_TAG_GRID) {
+            return this.decodeGridFrame(new DataView(data));
+        }
+        if (bytes[0] !== FRAME_TAG_GZIP) {
+            throw new Error(`Unsupported frame codec ${bytes[0]}`);
+        }
+        const stream = new Blob([bytes.subarray(1)]).stream().pipeThrough(new DecompressionStream('gzip'));
+        return JSON.parse(await new Response(stream).text());
+    }
+
+    // Turn a binary grid frame into the simulation_state or delta_update message it stands for
+    decodeGridFrame(view) {
+        let offset = 2;
+        const u16 = () => { offset += 2; return view.getUint16(offset - 2, true); };
+        const u32 = () => { offset += 4; return view.getUint32(offset - 4, true); };
+        const material = () => window.materialManager.getMaterialByCode(u16());
+
+        if (view.getUint8(1) === GRID_FRAME_FULL) {
+            const width = u16();
+            const height = u16();
+            const particles = {};
+            for (let y = 0; y < height; y++) {
+                let x = 0;
+                for (let runs = u16(); runs > 0; runs--) {
+                    const info = material();
+                    const length = u16();
+                    if (info) {
+                        for (let i = 0; i < length; i++) {
+                            particles[`${x + i},${y}`] = { material: info.id, temp: null, color: info.color };
+                        }
+                    }
+                    x += length;
+                }
+            }
+            return { type: 'simulation_state', width, height, particles };
+        }
+
+        const addedCount = u32();
+        const removedCount = u32();
+        const keyframeCount = u16();
+        const added = {};
+        for (let i = 0; i < addedCount; i++) {
+            const x = u16();
+            const y = u16();
+            const code = u16();
+            const temp = view.getFloat32(offset, true);
+            const color = [view.getUint8(offset + 4), view.getUint8(offset + 5), view.getUint8(offset + 6)];
+            offset += 7;
+            const info = window.materialManager.getMaterialByCode(code);
+            added[`${x},${y}`] = { material: info ? info.id : code, temp, color };
+        }
+        const removed = [];
+        for (let i = 0; i < removedCount; i++) {
+            const x = u16();
+            removed.push(`${x},${u16()}`);
+        }
+        const keyframes = [];
+        for (let i = 0; i < keyframeCount; i++) {
+            keyframes.push({ x: u16(), y: u16(), width: u16(), height: u16() });
+        }
+        return { type: 'delta_update', added, removed, keyframes };
+    }
+
     handleMessage(message) {
         const handler = this.messageHandlers.get(message.type);
         if (handler) {
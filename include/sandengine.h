@@ -0,0 +1,100 @@
+#ifndef SANDENGINE_H
+#define SANDENGINE_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of a call
+typedef enum SandEngineStatus {
+  SAND_ENGINE_STATUS_OK = 0,
+  SAND_ENGINE_STATUS_NULL_POINTER = 1,
+  // A material code that names no material, or a position outside the grid
+  SAND_ENGINE_STATUS_INVALID_ARGUMENT = 2,
+  // The output buffer holds fewer than width × height cells
+  SAND_ENGINE_STATUS_BUFFER_TOO_SMALL = 3,
+  SAND_ENGINE_STATUS_PANICKED = 4,
+} SandEngineStatus;
+
+// A grid simulation embedded through the C API in `include/sandengine.h`, which is
+// generated from this module with `cbindgen --config cbindgen.toml --output include/sandengine.h`.
+//
+// Ownership: `sandengine_create` hands the caller an engine that it owns until it passes
+// it to `sandengine_destroy`, exactly once. Every other function borrows the engine for
+// the length of the call and keeps no pointer to it or to any buffer passed in, so
+// buffers stay owned by the caller. An engine may move between threads but must not be
+// used from two at once. Panics never unwind into the host: the call returns
+// `Panicked` (or a null engine) instead, after which the engine should be destroyed.
+typedef struct SandEngine SandEngine;
+
+// One grid cell as copied out by `sandengine_get_cells`
+typedef struct SandCell {
+  // Material code as in `sandengine_material_code`; 0 for an empty cell
+  uint16_t material;
+  // Degrees Celsius; 0 for an empty cell
+  float temperature;
+} SandCell;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// A new empty `width` × `height` grid seeded with `seed`, or null if either side is zero
+SandEngine *sandengine_create(size_t width, size_t height, uint64_t seed);
+
+// Free an engine from `sandengine_create`; null is ignored
+//
+// # Safety
+// `engine` must be null or an engine from `sandengine_create` not destroyed before.
+void sandengine_destroy(SandEngine *engine);
+
+// Advance the simulation by `delta_time` seconds
+//
+// # Safety
+// `engine` must be null or a live engine from `sandengine_create`.
+SandEngineStatus sandengine_step(SandEngine *engine, float delta_time);
+
+// Fill a disc of `radius` cells around `(x, y)` with `material`; the Eraser's code
+// clears cells instead
+//
+// # Safety
+// `engine` must be null or a live engine from `sandengine_create`.
+SandEngineStatus sandengine_paint(SandEngine *engine, size_t x, size_t y, uint16_t material, size_t radius);
+
+// Copy every cell, row by row from the top left, into `cells`, which must have room
+// for `capacity` cells and at least width × height of them
+//
+// # Safety
+// `engine` must be null or a live engine from `sandengine_create`, and `cells` null or
+// valid for writing `capacity` cells.
+SandEngineStatus sandengine_get_cells(const SandEngine *engine, SandCell *cells, size_t capacity);
+
+// Width of the grid in cells; 0 for a null engine
+//
+// # Safety
+// `engine` must be null or a live engine from `sandengine_create`.
+size_t sandengine_width(const SandEngine *engine);
+
+// Height of the grid in cells; 0 for a null engine
+//
+// # Safety
+// `engine` must be null or a live engine from `sandengine_create`.
+size_t sandengine_height(const SandEngine *engine);
+
+// Code of the material called `name` (e.g. "Sand", ignoring case and spaces) for
+// `sandengine_paint`, or -1 if there is none
+//
+// # Safety
+// `engine` must be null or a live engine from `sandengine_create`, and `name` null or
+// a NUL-terminated string.
+int32_t sandengine_material_code(const SandEngine *engine, const char *name);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SANDENGINE_H */
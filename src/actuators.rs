@@ -0,0 +1,203 @@
+use crate::circuits::SensorWorld;
+use crate::explosion::debris_of;
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::tile_entity::{TileEffectReport, TileEntityEffect, EFFECT_SEARCH_RADIUS};
+
+/// Most particles in a row an extending piston moves along; a longer row is crushed
+pub const PISTON_PUSH_LIMIT: usize = 12;
+
+/// The cells piston arms and gate panels sweep
+pub trait ActuatorWorld: SensorWorld {
+    /// Whether a cell holds something actuators neither move nor crush, such as a machine,
+    /// or lies past the edge of the world
+    fn is_anchored(&self, position: (i64, i64)) -> bool;
+    fn take_particle(&mut self, position: (i64, i64)) -> Option<Particle>;
+    /// Put a particle into an empty cell
+    fn put_particle(&mut self, position: (i64, i64), particle: Particle) -> bool;
+    /// Create a particle of `material` in an empty cell
+    fn place_material(&mut self, position: (i64, i64), material: MaterialType, temp: Option<f32>) -> bool;
+}
+
+impl TileEntityEffect {
+    /// Extend a piston's arm cell by cell from its base, pushing each row of particles in
+    /// the way one cell further and crushing the nearest particle when the row is
+    /// anchored or longer than `PISTON_PUSH_LIMIT`; the arm stops short at anything
+    /// anchored. A retracted arm is drawn back in.
+    pub fn apply_piston(world: &mut impl ActuatorWorld, position: (i64, i64), direction: (i64, i64), length: u32, extended: bool, report: &mut TileEffectReport) {
+        let cells: Vec<_> = (1..=length as i64).map(|i| (position.0 + direction.0 * i, position.1 + direction.1 * i)).collect();
+        if !extended {
+            retract(world, &cells, MaterialType::Piston, report);
+            return;
+        }
+        for &cell in &cells {
+            if !fill(world, cell, Some(direction), &cells, MaterialType::Piston, report) {
+                break;
+            }
+        }
+    }
+
+    /// Fill a gate's panel with Door cells, crushing anything in them, or clear it
+    pub fn apply_gate(world: &mut impl ActuatorWorld, position: (i64, i64), length: u32, solid: bool, report: &mut TileEffectReport) {
+        let cells: Vec<_> = (1..=length as i64).map(|i| (position.0, position.1 + i)).collect();
+        if !solid {
+            retract(world, &cells, MaterialType::Door, report);
+            return;
+        }
+        for &cell in &cells {
+            fill(world, cell, None, &cells, MaterialType::Door, report);
+        }
+    }
+}
+
+/// Put `material` into one of the `swept` cells, pushing what is there along `direction`
+/// or else crushing it; false when the cell is anchored
+fn fill(world: &mut impl ActuatorWorld, cell: (i64, i64), direction: Option<(i64, i64)>, swept: &[(i64, i64)], material: MaterialType, report: &mut TileEffectReport) -> bool {
+    if world.material_at(cell) == Some(material) {
+        return true;
+    }
+    if world.is_anchored(cell) {
+        return false;
+    }
+    if !world.is_cell_empty(cell) {
+        match direction.and_then(|direction| push(world, cell, direction)) {
+            Some(pushed) => report.pushed += pushed,
+            None => crush(world, cell, swept, report),
+        }
+    }
+    let placed = world.place_material(cell, material, None);
+    if placed {
+        report.actuated += 1;
+    }
+    placed
+}
+
+/// Move the row of particles starting at `cell` one cell along `direction`, returning
+/// how many moved, if the cell past its end is free
+fn push(world: &mut impl ActuatorWorld, cell: (i64, i64), direction: (i64, i64)) -> Option<usize> {
+    let step = |(x, y): (i64, i64), sign: i64| (x + direction.0 * sign, y + direction.1 * sign);
+    let mut end = cell;
+    let mut row = 0;
+    while !world.is_cell_empty(end) {
+        if row == PISTON_PUSH_LIMIT || world.is_anchored(end) {
+            return None;
+        }
+        end = step(end, 1);
+        row += 1;
+    }
+    if world.is_anchored(end) {
+        return None;
+    }
+    while end != cell {
+        let from = step(end, -1);
+        let particle = world.take_particle(from)?;
+        world.put_particle(end, particle);
+        end = from;
+    }
+    Some(row)
+}
+
+/// Grind the particle in a cell into debris squeezed into the nearest empty cell off the
+/// `swept` ones, or lost when there is none
+fn crush(world: &mut impl ActuatorWorld, cell: (i64, i64), swept: &[(i64, i64)], report: &mut TileEffectReport) {
+    let Some(particle) = world.take_particle(cell) else {
+        return;
+    };
+    report.crushed += 1;
+    let debris = debris_of(particle.material_type);
+    let spot = TileEntityEffect::offsets_by_distance(EFFECT_SEARCH_RADIUS).into_iter()
+        .map(|(dx, dy)| (cell.0 + dx, cell.1 + dy))
+        .find(|spot| !swept.contains(spot) && world.is_cell_empty(*spot));
+    if let Some(spot) = spot {
+        world.place_material(spot, debris, Some(particle.temp));
+    }
+}
+
+/// Clear the cells of an arm or panel that still hold `material`
+fn retract(world: &mut impl ActuatorWorld, cells: &[(i64, i64)], material: MaterialType, report: &mut TileEffectReport) {
+    for &cell in cells {
+        if world.material_at(cell) == Some(material) && world.take_particle(cell).is_some() {
+            report.actuated += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::circuits::SensorCondition;
+    use crate::materials::MaterialType;
+    use crate::simulation::Simulation;
+    use crate::tile_entity::{TileEffectReport, TileEntity, TileEntityManager};
+
+    fn material(simulation: &Simulation, x: usize, y: usize) -> Option<MaterialType> {
+        simulation.get_particle(x, y).map(|particle| particle.material_type)
+    }
+
+    fn count(simulation: &Simulation, material_type: MaterialType) -> usize {
+        simulation.iter_particles().filter(|particle| particle.material_type == material_type).count()
+    }
+
+    /// Tile entities with a switch at `switch`: a sensor that is on while the cell holds
+    /// a particle, wired into the actuator at `actuator`
+    fn wired(actuator: TileEntity, switch: (i64, i64)) -> TileEntityManager {
+        let mut tile_entities = TileEntityManager::new();
+        let mut actuator = actuator;
+        actuator.connect_input(switch);
+        tile_entities.add_tile_entity(actuator);
+        tile_entities.add_tile_entity(TileEntity::new_sensor(switch, (0, 0), SensorCondition::Occupied));
+        tile_entities
+    }
+
+    /// Two ticks, for the switch to be read and then acted on
+    fn settle(simulation: &mut Simulation, tile_entities: &mut TileEntityManager) -> TileEffectReport {
+        simulation.update_tile_entities(tile_entities, 1.0 / 60.0);
+        simulation.update_tile_entities(tile_entities, 1.0 / 60.0)
+    }
+
+    #[test]
+    fn test_piston_pushes_then_crushes() {
+        let mut simulation = Simulation::new(12, 4);
+        let mut tile_entities = wired(TileEntity::new_piston((0, 1), (1, 0), 5), (11, 3));
+        simulation.add_particle(2, 1, MaterialType::Sand, None);
+        simulation.add_particle(4, 1, MaterialType::Stone, None);
+        simulation.add_particle(6, 1, MaterialType::Generator, None);
+        assert_eq!(settle(&mut simulation, &mut tile_entities).actuated, 0);
+
+        simulation.add_particle(11, 3, MaterialType::Stone, None);
+        let report = settle(&mut simulation, &mut tile_entities);
+        assert_eq!(report.actuated, 5);
+        assert!((1..=5).all(|x| material(&simulation, x, 1) == Some(MaterialType::Piston)));
+        // Sand and stone were pushed up against the generator, then crushed, the stone
+        // into sand
+        assert_eq!((report.pushed, report.crushed), (3, 2));
+        assert_eq!(material(&simulation, 6, 1), Some(MaterialType::Generator));
+        assert_eq!(count(&simulation, MaterialType::Sand), 2);
+        assert_eq!(count(&simulation, MaterialType::Stone), 1);
+
+        simulation.remove_particle(11, 3);
+        assert_eq!(settle(&mut simulation, &mut tile_entities).actuated, 5);
+        assert_eq!(count(&simulation, MaterialType::Piston), 0);
+    }
+
+    #[test]
+    fn test_gate_toggles_on_each_pulse() {
+        let mut simulation = Simulation::new(8, 8);
+        let mut tile_entities = wired(TileEntity::new_gate((3, 0), 3), (6, 6));
+        simulation.add_particle(3, 2, MaterialType::Stone, None);
+
+        let report = simulation.update_tile_entities(&mut tile_entities, 1.0 / 60.0);
+        assert_eq!((report.actuated, report.crushed), (3, 1));
+        assert_eq!(count(&simulation, MaterialType::Door), 3);
+        assert_eq!(count(&simulation, MaterialType::Sand), 1);
+
+        simulation.add_particle(6, 6, MaterialType::Stone, None);
+        assert_eq!(settle(&mut simulation, &mut tile_entities).actuated, 3);
+        assert_eq!(count(&simulation, MaterialType::Door), 0);
+        // Passable until the next pulse, not just while the switch is on
+        simulation.remove_particle(6, 6);
+        assert_eq!(settle(&mut simulation, &mut tile_entities).actuated, 0);
+        simulation.add_particle(6, 6, MaterialType::Stone, None);
+        assert_eq!(settle(&mut simulation, &mut tile_entities).actuated, 3);
+        assert!((1..=3).all(|y| material(&simulation, 3, y) == Some(MaterialType::Door)));
+    }
+}
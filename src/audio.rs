@@ -0,0 +1,155 @@
+use crate::event_bus::SimulationEvent;
+use crate::materials::MaterialType;
+use rodio::decoder::DecoderError;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, PlayError, Source, StreamError};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+/// Shortest gap between two plays of the same sound, so a boiling pot or a long brush
+/// stroke doesn't stack up hundreds of copies
+const MIN_REPEAT: Duration = Duration::from_millis(80);
+/// Impulse of an impact played at full volume
+const LOUDEST_IMPACT: f32 = 500.0;
+/// Explosion radius played at full volume
+const LOUDEST_EXPLOSION: f32 = 12.0;
+
+#[derive(Debug)]
+pub enum AudioError {
+    StreamError(StreamError),
+    PlayError(PlayError),
+    DecoderError(DecoderError),
+}
+
+impl From<StreamError> for AudioError {
+    fn from(error: StreamError) -> Self {
+        AudioError::StreamError(error)
+    }
+}
+
+impl From<PlayError> for AudioError {
+    fn from(error: PlayError) -> Self {
+        AudioError::PlayError(error)
+    }
+}
+
+impl From<DecoderError> for AudioError {
+    fn from(error: DecoderError) -> Self {
+        AudioError::DecoderError(error)
+    }
+}
+
+impl std::fmt::Display for AudioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AudioError::StreamError(e) => write!(f, "Audio output error: {}", e),
+            AudioError::PlayError(e) => write!(f, "Playback error: {}", e),
+            AudioError::DecoderError(e) => write!(f, "Sound decoding error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AudioError {}
+
+/// Sound effects bundled with the engine, from the `sounds` directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sound {
+    Explosion,
+    SteamHiss,
+    Place,
+    Impact,
+}
+
+impl Sound {
+    /// The sound's WAV file
+    pub fn bytes(self) -> &'static [u8] {
+        match self {
+            Sound::Explosion => include_bytes!("../sounds/explosion.wav"),
+            Sound::SteamHiss => include_bytes!("../sounds/steam_hiss.wav"),
+            Sound::Place => include_bytes!("../sounds/place.wav"),
+            Sound::Impact => include_bytes!("../sounds/impact.wav"),
+        }
+    }
+
+    /// The sound an event makes and its volume from 0 to 1; erasing is silent
+    pub fn for_event(event: &SimulationEvent) -> Option<(Sound, f32)> {
+        match *event {
+            SimulationEvent::ExplosionAt { radius, .. } => {
+                Some((Sound::Explosion, (radius / LOUDEST_EXPLOSION).clamp(0.3, 1.0)))
+            }
+            SimulationEvent::SteamHiss { .. } => Some((Sound::SteamHiss, 0.4)),
+            SimulationEvent::LightningStrike { .. } => Some((Sound::Explosion, 0.8)),
+            SimulationEvent::MaterialPlaced { material: MaterialType::Eraser, .. } => None,
+            SimulationEvent::MaterialPlaced { count, .. } => Some((Sound::Place, (count as f32 / 50.0).clamp(0.1, 0.4))),
+            SimulationEvent::RigidBodyImpact { impulse, .. } => {
+                Some((Sound::Impact, (impulse / LOUDEST_IMPACT).clamp(0.1, 1.0)))
+            }
+        }
+    }
+}
+
+/// Plays the bundled sound for each `SimulationEvent` on the default output device.
+/// Feed it `Simulation::drain_events` after every update.
+pub struct AudioSink {
+    // Playback stops when the stream is dropped
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    last_played: HashMap<Sound, Instant>,
+    /// Scales every sound, from 0 (muted) to 1
+    pub volume: f32,
+}
+
+impl AudioSink {
+    /// Open the default output device
+    pub fn new() -> Result<Self, AudioError> {
+        let (stream, handle) = OutputStream::try_default()?;
+        Ok(Self { _stream: stream, handle, last_played: HashMap::new(), volume: 1.0 })
+    }
+
+    /// Play the loudest event of each sound in `events`, skipping sounds played within
+    /// the last few frames
+    pub fn play_events(&mut self, events: &[SimulationEvent]) -> Result<(), AudioError> {
+        let mut loudest: HashMap<Sound, f32> = HashMap::new();
+        for (sound, volume) in events.iter().filter_map(Sound::for_event) {
+            let entry = loudest.entry(sound).or_insert(volume);
+            *entry = entry.max(volume);
+        }
+
+        let now = Instant::now();
+        for (sound, volume) in loudest {
+            if self.last_played.get(&sound).is_some_and(|&played| now.duration_since(played) < MIN_REPEAT) {
+                continue;
+            }
+            self.play(sound, volume)?;
+            self.last_played.insert(sound, now);
+        }
+        Ok(())
+    }
+
+    /// Play `sound` at `volume`, scaled by the sink's volume
+    pub fn play(&self, sound: Sound, volume: f32) -> Result<(), AudioError> {
+        let source = Decoder::new(Cursor::new(sound.bytes()))?;
+        self.handle.play_raw(source.amplify(volume * self.volume).convert_samples())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_map_to_bundled_sounds() {
+        for sound in [Sound::Explosion, Sound::SteamHiss, Sound::Place, Sound::Impact] {
+            let decoder = Decoder::new(Cursor::new(sound.bytes())).unwrap();
+            assert!(decoder.total_duration().is_some_and(|duration| duration > Duration::ZERO));
+        }
+
+        let small = Sound::for_event(&SimulationEvent::ExplosionAt { x: 0, y: 0, radius: 2.0 }).unwrap();
+        let large = Sound::for_event(&SimulationEvent::ExplosionAt { x: 0, y: 0, radius: 20.0 }).unwrap();
+        assert_eq!((small.0, large), (Sound::Explosion, (Sound::Explosion, 1.0)));
+        assert!(small.1 < large.1);
+        let erase = SimulationEvent::MaterialPlaced { x: 0, y: 0, material: MaterialType::Eraser, count: 5 };
+        assert_eq!(Sound::for_event(&erase), None);
+    }
+}
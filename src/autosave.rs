@@ -0,0 +1,377 @@
+use crate::brush::Brush;
+use crate::materials::MaterialType;
+use crate::save_load::{SaveLoadError, SaveLoadManager, SimulationSave};
+use crate::simulation::Simulation;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+
+/// Seconds of play between autosaves unless configured otherwise
+pub const DEFAULT_AUTOSAVE_INTERVAL: f32 = 300.0;
+/// Older autosaves kept next to the newest one unless configured otherwise
+pub const DEFAULT_AUTOSAVE_BACKUPS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AutosaveConfig {
+    /// Seconds of play between saves
+    pub interval: f32,
+    /// How many older saves to keep; the oldest is deleted once there would be more
+    pub backups: usize,
+}
+
+impl Default for AutosaveConfig {
+    fn default() -> Self {
+        Self {
+            interval: DEFAULT_AUTOSAVE_INTERVAL,
+            backups: DEFAULT_AUTOSAVE_BACKUPS,
+        }
+    }
+}
+
+/// An edit made since the last autosave. After a crash the journal is replayed on top of
+/// the newest autosave; the simulation ran on between edits, so the result is the saved
+/// world with every edit redone rather than an exact copy of the lost one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum JournalEntry {
+    Paint { x: usize, y: usize, material: MaterialType, brush: Brush },
+    /// Cells set by placing a structure, with their temperatures
+    Place { cells: Vec<(usize, usize, MaterialType, Option<f32>)> },
+    Clear,
+}
+
+impl JournalEntry {
+    pub fn apply(&self, simulation: &mut Simulation) {
+        match self {
+            JournalEntry::Paint { x, y, material, brush } => {
+                simulation.apply_brush(brush, (*x, *y), *material);
+            }
+            JournalEntry::Place { cells } => {
+                for &(x, y, material, temp) in cells {
+                    if x < simulation.width && y < simulation.height {
+                        simulation.add_particle(x, y, material, temp);
+                    }
+                }
+            }
+            JournalEntry::Clear => simulation.clear(),
+        }
+    }
+}
+
+/// The newest readable autosave and the edits journaled since it was taken
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    pub save: SimulationSave,
+    pub journal: Vec<JournalEntry>,
+}
+
+impl Recovery {
+    /// Load the save into `simulation` and replay the journal on top
+    pub fn apply_to(&self, simulation: &mut Simulation) -> Result<(), SaveLoadError> {
+        self.save.apply_to(simulation)?;
+        for entry in &self.journal {
+            entry.apply(simulation);
+        }
+        Ok(())
+    }
+}
+
+/// Periodic background saves of one simulation with rotating backups, plus an
+/// append-only journal of edits since the last save. Files live in the save directory:
+/// `<name>.autosave.sim` is the newest save, `<name>.autosave.1.sim` and up the backups,
+/// and `<name>.journal` the edits made since, one JSON object per line.
+pub struct Autosave {
+    directory: PathBuf,
+    name: String,
+    config: AutosaveConfig,
+    compression: Compression,
+    elapsed: f32,
+    journal: File,
+    saving: Option<JoinHandle<Result<(), SaveLoadError>>>,
+}
+
+impl Autosave {
+    fn open(directory: &Path, name: &str, config: AutosaveConfig, compression: Compression) -> Result<Self, SaveLoadError> {
+        let journal = open_journal(&journal_path(directory, name))?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            name: name.to_string(),
+            config,
+            compression,
+            elapsed: 0.0,
+            journal,
+            saving: None,
+        })
+    }
+
+    pub fn config(&self) -> AutosaveConfig {
+        self.config
+    }
+
+    pub fn set_config(&mut self, config: AutosaveConfig) {
+        self.config = config;
+    }
+
+    /// Append an edit to the journal. Call it for every paint or structure placed so the
+    /// edit survives a crash before the next save.
+    pub fn record(&mut self, entry: &JournalEntry) -> Result<(), SaveLoadError> {
+        // One JSON line per entry, written at once so a crash can only cut off the last
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.journal.write_all(&line)?;
+        Ok(())
+    }
+
+    /// Per-frame hook: advances the clock by `delta_time` seconds and, once a save is due
+    /// and none is still being written, starts one from `capture`. Returns whether a save
+    /// started, or the error of a background save that failed since the last call.
+    pub fn tick(&mut self, delta_time: f32, capture: impl FnOnce() -> SimulationSave) -> Result<bool, SaveLoadError> {
+        let finished = self.poll();
+        self.elapsed += delta_time;
+        let due = self.elapsed >= self.config.interval && self.saving.is_none();
+        if due {
+            self.save_now(capture())?;
+        }
+        finished.map(|()| due)
+    }
+
+    /// Save at the next tick, e.g. after an edit the journal can't replay such as an undo
+    pub fn save_soon(&mut self) {
+        self.elapsed = self.elapsed.max(self.config.interval);
+    }
+
+    /// Start writing `save` in the background right away, once any save in progress is done
+    pub fn save_now(&mut self, save: SimulationSave) -> Result<(), SaveLoadError> {
+        self.wait()?;
+        self.elapsed = 0.0;
+        self.start_journal_segment()?;
+
+        let (directory, name, backups, compression) = (self.directory.clone(), self.name.clone(), self.config.backups, self.compression);
+        self.saving = Some(thread::spawn(move || {
+            let temporary = directory.join(format!("{}.autosave.tmp", name));
+            save.write_to_file(&temporary, compression)?;
+            rotate_backups(&directory, &name, backups)?;
+            fs::rename(&temporary, save_path(&directory, &name, 0))?;
+            Ok(())
+        }));
+        Ok(())
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.saving.is_some()
+    }
+
+    /// Block until the save in progress, if any, is written
+    pub fn wait(&mut self) -> Result<(), SaveLoadError> {
+        match self.saving.take() {
+            Some(handle) => self.finish(handle),
+            None => Ok(()),
+        }
+    }
+
+    fn poll(&mut self) -> Result<(), SaveLoadError> {
+        match self.saving.take() {
+            Some(handle) if handle.is_finished() => self.finish(handle),
+            unfinished => {
+                self.saving = unfinished;
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(&mut self, handle: JoinHandle<Result<(), SaveLoadError>>) -> Result<(), SaveLoadError> {
+        let result = handle.join().unwrap_or_else(|_| {
+            Err(SaveLoadError::CorruptedData("autosave thread panicked".to_string()))
+        });
+        // The edits the save covered are only dropped once it is safely on disk
+        if result.is_ok() {
+            let covered = covered_journal_path(&self.directory, &self.name);
+            if covered.exists() {
+                fs::remove_file(covered)?;
+            }
+        }
+        result
+    }
+
+    /// Set the journal so far aside for the save being started and begin a fresh one
+    fn start_journal_segment(&mut self) -> Result<(), SaveLoadError> {
+        let current = journal_path(&self.directory, &self.name);
+        let covered = covered_journal_path(&self.directory, &self.name);
+        if covered.exists() {
+            // An earlier save failed, so its edits still wait for one to succeed
+            let edits = fs::read(&current)?;
+            OpenOptions::new().append(true).open(&covered)?.write_all(&edits)?;
+            self.journal.set_len(0)?;
+        } else {
+            fs::rename(&current, &covered)?;
+            self.journal = open_journal(&current)?;
+        }
+        Ok(())
+    }
+}
+
+impl SaveLoadManager {
+    /// Autosave a simulation as `name` in this manager's directory. Edits already in the
+    /// journal are kept, so start it after recovering from the same name.
+    pub fn autosave(&self, name: &str, config: AutosaveConfig) -> Result<Autosave, SaveLoadError> {
+        Autosave::open(&self.save_directory, name, config, self.compression_level)
+    }
+
+    /// The newest autosave of `name` that can still be read, falling back to older
+    /// backups, with every edit journaled after it; None if there is no readable save
+    pub fn recover_autosave(&self, name: &str, backups: usize) -> Result<Option<Recovery>, SaveLoadError> {
+        let Some(save) = (0..=backups)
+            .map(|generation| save_path(&self.save_directory, name, generation))
+            .filter(|path| path.exists())
+            .find_map(|path| SimulationSave::read_from_file(path).ok())
+        else {
+            return Ok(None);
+        };
+        let mut journal = read_journal(&covered_journal_path(&self.save_directory, name))?;
+        journal.extend(read_journal(&journal_path(&self.save_directory, name))?);
+        Ok(Some(Recovery { save, journal }))
+    }
+}
+
+/// Newest save for generation 0, backups from 1 up
+fn save_path(directory: &Path, name: &str, generation: usize) -> PathBuf {
+    match generation {
+        0 => directory.join(format!("{}.autosave.sim", name)),
+        backup => directory.join(format!("{}.autosave.{}.sim", name, backup)),
+    }
+}
+
+fn journal_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.journal", name))
+}
+
+/// Edits covered by the save being written; removed once it succeeds
+fn covered_journal_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.journal.saving", name))
+}
+
+fn open_journal(path: &Path) -> Result<File, SaveLoadError> {
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+/// Shift every save one generation older, dropping the one beyond `backups`
+fn rotate_backups(directory: &Path, name: &str, backups: usize) -> Result<(), SaveLoadError> {
+    let oldest = save_path(directory, name, backups);
+    if backups > 0 && oldest.exists() {
+        fs::remove_file(&oldest)?;
+    }
+    for generation in (0..backups).rev() {
+        let path = save_path(directory, name, generation);
+        if path.exists() {
+            fs::rename(&path, save_path(directory, name, generation + 1))?;
+        }
+    }
+    Ok(())
+}
+
+/// Every complete entry in a journal file; a line cut short by a crash ends it
+fn read_journal(path: &Path) -> Result<Vec<JournalEntry>, SaveLoadError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let bytes = fs::read(path)?;
+    Ok(bytes
+        .split(|&byte| byte == b'\n')
+        .map_while(|line| serde_json::from_slice(line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_manager(label: &str) -> (PathBuf, SaveLoadManager) {
+        let dir = std::env::temp_dir().join(format!("sand_engine_autosave_{}_{}", label, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let manager = SaveLoadManager::new(&dir).unwrap();
+        (dir, manager)
+    }
+
+    fn paint(simulation: &mut Simulation, autosave: &mut Autosave, x: usize, material: MaterialType) {
+        let entry = JournalEntry::Paint { x, y: 10, material, brush: Brush::circle(1) };
+        entry.apply(simulation);
+        autosave.record(&entry).unwrap();
+    }
+
+    #[test]
+    fn test_saves_rotate_backups() {
+        let (dir, manager) = temp_manager("rotate");
+        let config = AutosaveConfig { interval: 1.0, backups: 2 };
+        let mut autosave = manager.autosave("world", config).unwrap();
+        let mut simulation = Simulation::new(16, 16);
+
+        let mut started = 0;
+        for _ in 0..5 {
+            simulation.update(0.5);
+            if autosave.tick(0.5, || SimulationSave::capture(&simulation)).unwrap() {
+                started += 1;
+            }
+            autosave.wait().unwrap();
+        }
+        assert_eq!(started, 2);
+        assert!(save_path(&dir, "world", 0).exists());
+        assert!(save_path(&dir, "world", 1).exists());
+
+        for _ in 0..3 {
+            autosave.save_now(SimulationSave::capture(&simulation)).unwrap();
+        }
+        autosave.wait().unwrap();
+        assert!(save_path(&dir, "world", 2).exists());
+        assert!(!save_path(&dir, "world", 3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recover_replays_journal_after_crash() {
+        let (dir, manager) = temp_manager("recover");
+        let config = AutosaveConfig { interval: 10.0, backups: 1 };
+        let mut simulation = Simulation::new(32, 16);
+        {
+            let mut autosave = manager.autosave("world", config).unwrap();
+            paint(&mut simulation, &mut autosave, 4, MaterialType::Stone);
+            autosave.save_now(SimulationSave::capture(&simulation)).unwrap();
+            autosave.wait().unwrap();
+            paint(&mut simulation, &mut autosave, 12, MaterialType::Wood);
+            paint(&mut simulation, &mut autosave, 20, MaterialType::Iron);
+            // Dropped without saving again, as in a crash
+        }
+
+        let recovery = manager.recover_autosave("world", config.backups).unwrap().unwrap();
+        // The first paint is in the save, so only the later two are replayed
+        assert_eq!(recovery.journal.len(), 2);
+        let mut recovered = Simulation::new(32, 16);
+        recovery.apply_to(&mut recovered).unwrap();
+        let cells = |simulation: &Simulation| -> Vec<_> {
+            simulation.iter_particles().map(|p| (p.x, p.y, p.material_type)).collect()
+        };
+        assert_eq!(cells(&recovered), cells(&simulation));
+        assert!(manager.recover_autosave("missing", 1).unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_torn_journal_record_is_ignored() {
+        let (dir, manager) = temp_manager("torn");
+        let mut autosave = manager.autosave("world", AutosaveConfig::default()).unwrap();
+        autosave.save_now(SimulationSave::capture(&Simulation::new(8, 8))).unwrap();
+        autosave.wait().unwrap();
+        autosave.record(&JournalEntry::Clear).unwrap();
+        autosave.record(&JournalEntry::Place { cells: vec![(1, 1, MaterialType::Sand, None)] }).unwrap();
+        drop(autosave);
+
+        // Cut the last line short
+        let path = journal_path(&dir, "world");
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        let recovery = manager.recover_autosave("world", 0).unwrap().unwrap();
+        assert_eq!(recovery.journal, vec![JournalEntry::Clear]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
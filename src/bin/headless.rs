@@ -0,0 +1,95 @@
+use sand_engine::{ImagePalette, MaterialRegistry, Scenario};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Instant;
+
+const USAGE: &str = "usage: headless <scenario.toml> [--out <dir>] [--frames <n>] [--materials <pack.toml>]";
+
+struct Options {
+    scenario: PathBuf,
+    out: PathBuf,
+    frames: Option<u64>,
+    materials: Option<PathBuf>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut scenario = None;
+    let mut out = PathBuf::from(".");
+    let mut frames = None;
+    let mut materials = None;
+    while let Some(arg) = args.next() {
+        let mut value = |flag: &str| args.next().ok_or_else(|| format!("{} needs a value", flag));
+        match arg.as_str() {
+            "--out" => out = PathBuf::from(value("--out")?),
+            "--frames" => {
+                let value = value("--frames")?;
+                frames = Some(value.parse().map_err(|_| format!("--frames expects a number, got '{}'", value))?);
+            }
+            "--materials" => materials = Some(PathBuf::from(value("--materials")?)),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            path if scenario.is_none() => scenario = Some(PathBuf::from(path)),
+            extra => return Err(format!("unexpected argument '{}'", extra)),
+        }
+    }
+    let scenario = scenario.ok_or("no scenario file given")?;
+    Ok(Options { scenario, out, frames, materials })
+}
+
+fn run(options: &Options) -> Result<bool, String> {
+    let materials = match &options.materials {
+        Some(path) => MaterialRegistry::from_file(path).map_err(|e| format!("{}: {}", path.display(), e))?,
+        None => MaterialRegistry::new(),
+    };
+    let mut scenario = Scenario::from_file(&options.scenario, &materials)
+        .map_err(|e| format!("{}: {}", options.scenario.display(), e))?;
+    if let Some(frames) = options.frames {
+        scenario.frames = frames;
+    }
+
+    let started = Instant::now();
+    let outcome = scenario.run(Arc::new(materials));
+    println!(
+        "{}: {} frames of {}x{} in {:.2?}, {} particles at the end",
+        options.scenario.display(), scenario.frames, scenario.width, scenario.height,
+        started.elapsed(), outcome.simulation.iter_particles().count()
+    );
+
+    let stem = options.scenario.file_stem().map_or("scenario".into(), |stem| stem.to_string_lossy());
+    std::fs::create_dir_all(&options.out).map_err(|e| format!("{}: {}", options.out.display(), e))?;
+    let image = options.out.join(format!("{}.png", stem));
+    let palette = ImagePalette::from_registry(outcome.simulation.materials());
+    outcome.simulation.save_image(&image, &palette).map_err(|e| format!("{}: {}", image.display(), e))?;
+    let metrics = options.out.join(format!("{}.csv", stem));
+    outcome.telemetry.save_csv(&metrics).map_err(|e| format!("{}: {}", metrics.display(), e))?;
+    println!("Wrote {} and {}", image.display(), metrics.display());
+
+    for failure in &outcome.failures {
+        println!("FAILED {}", failure);
+    }
+    if !scenario.expectations.is_empty() && outcome.passed() {
+        println!("All {} expectations met", scenario.expectations.len());
+    }
+    Ok(outcome.passed())
+}
+
+/// Runs a scenario file without a window and writes its final grid as a PNG and its
+/// per-frame metrics as CSV. Exits with 1 if an `[[expect]]` fails and 2 on errors, so CI
+/// can run physics regression checks.
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n{}", error, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&options) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::from(2)
+        }
+    }
+}
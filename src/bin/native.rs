@@ -1,150 +1,396 @@
 use pixels::{Error, Pixels, SurfaceTexture};
 use std::time::{Duration, Instant};
 use winit::dpi::LogicalSize;
-use winit::event::{Event, VirtualKeyCode, WindowEvent, ElementState, MouseButton};
+use winit::event::{Event, VirtualKeyCode, WindowEvent, ElementState, ModifiersState, MouseButton, MouseScrollDelta};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::{Window, WindowBuilder};
-use sand_engine::{Simulation, MaterialType};
+use winit::window::WindowBuilder;
+use sand_engine::{Action, Brush, CellInspector, KeyBindings, MaterialPalette, MaterialType, SaveLoadManager, TimeControl, WeatherKind, WeatherState, World};
+#[cfg(feature = "audio")]
+use sand_engine::AudioSink;
 
 const WIDTH: usize = 400;
 const HEIGHT: usize = 300;
 const TARGET_FPS: u64 = 60;
 const FRAME_DURATION: Duration = Duration::from_millis(1000 / TARGET_FPS);
+const CAMERA_PAN_CELLS: i32 = 10;
+/// Quick save slot, kept in ./saves
+const QUICKSAVE_NAME: &str = "quicksave";
+const SPRAY_DENSITY: f32 = 0.15;
+/// Key bindings read at startup unless SAND_KEYBINDINGS names another file
+const KEYBINDINGS_FILE: &str = "keybindings.toml";
+/// Gap in pixels between the material palette and the window edges
+const PALETTE_MARGIN: usize = 4;
+
+/// Paint tools, cycled with S
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    Circle,
+    Square,
+    Spray,
+    /// Drag to draw a straight line, painted on release
+    Line,
+    /// Click to fill the region under the cursor
+    Fill,
+}
+
+impl Tool {
+    fn next(self) -> Self {
+        match self {
+            Tool::Circle => Tool::Square,
+            Tool::Square => Tool::Spray,
+            Tool::Spray => Tool::Line,
+            Tool::Line => Tool::Fill,
+            Tool::Fill => Tool::Circle,
+        }
+    }
+
+    fn brush(self, size: usize, stroke_start: (usize, usize)) -> Brush {
+        match self {
+            Tool::Circle => Brush::circle(size),
+            Tool::Square => Brush::Square { radius: size },
+            Tool::Spray => Brush::Spray { radius: size * 2, density: SPRAY_DENSITY },
+            Tool::Line => Brush::Line { from: stroke_start, radius: size / 2 },
+            Tool::Fill => Brush::fill(),
+        }
+    }
+}
 
 struct App {
-    simulation: Simulation,
+    world: World,
     current_material: MaterialType,
     brush_size: usize,
+    tool: Tool,
+    // Cell the mouse was pressed on, where line strokes start
+    stroke_start: (usize, usize),
     mouse_pressed: bool,
+    // Held modifiers, for Ctrl chords
+    modifiers: ModifiersState,
+    bindings: KeyBindings,
     mouse_x: f32,
     mouse_y: f32,
+    // Pause, single steps and speed
+    time: TimeControl,
+    // Material palette; typing searches it while it is open
+    palette: MaterialPalette,
+    palette_open: bool,
+    // Shows details of the cell under the cursor: toggled, or while the right button
+    // is held
+    inspecting: bool,
+    right_pressed: bool,
+    // Sounds for simulation events; None without an output device
+    #[cfg(feature = "audio")]
+    audio: Option<AudioSink>,
 }
 
 impl App {
     fn new() -> Self {
+        let mut world = World::new(WIDTH, HEIGHT);
+        world.simulation_mut().enable_effects();
+        world.simulation_mut().enable_radioactivity();
+        #[cfg(feature = "audio")]
+        let audio = match AudioSink::new() {
+            Ok(audio) => {
+                world.simulation_mut().enable_event_bus();
+                Some(audio)
+            }
+            Err(e) => {
+                eprintln!("Sound disabled: {}", e);
+                None
+            }
+        };
+        let time = TimeControl::new(1.0 / world.time_step());
         Self {
-            simulation: Simulation::new(WIDTH, HEIGHT),
+            world,
             current_material: MaterialType::Sand,
             brush_size: 3,
+            tool: Tool::Circle,
+            stroke_start: (0, 0),
             mouse_pressed: false,
+            modifiers: ModifiersState::empty(),
+            bindings: load_bindings(),
             mouse_x: 0.0,
             mouse_y: 0.0,
+            time,
+            palette: MaterialPalette::new(PALETTE_MARGIN, PALETTE_MARGIN, HEIGHT - 2 * PALETTE_MARGIN),
+            palette_open: false,
+            inspecting: false,
+            right_pressed: false,
+            #[cfg(feature = "audio")]
+            audio,
         }
     }
 
-    fn update(&mut self, delta_time: f32) {
-        // Handle painting
-        if self.mouse_pressed {
-            let x = (self.mouse_x as usize).min(WIDTH - 1);
-            let y = (self.mouse_y as usize).min(HEIGHT - 1);
-            self.paint_particles(x, y);
+    fn cursor_cell(&self) -> Option<(usize, usize)> {
+        let (x, y) = self.world.camera().screen_to_cell(self.mouse_x as usize, self.mouse_y as usize);
+        (x >= 0 && y >= 0 && (x as usize) < WIDTH && (y as usize) < HEIGHT).then_some((x as usize, y as usize))
+    }
+
+    fn paint_at(&mut self, cell: (usize, usize)) {
+        let brush = self.tool.brush(self.brush_size, self.stroke_start);
+        self.world.apply_brush(&brush, cell, self.current_material);
+    }
+
+    fn press(&mut self) {
+        let (x, y) = (self.mouse_x as usize, self.mouse_y as usize);
+        if self.palette_open && self.palette.contains(x, y) {
+            if let Some(material) = self.palette.click(self.world.simulation().materials(), x, y) {
+                self.current_material = material;
+                println!("Material: {}", self.world.simulation().materials().get(material).name);
+            }
+            return;
         }
+        self.mouse_pressed = true;
+        if let Some(cell) = self.cursor_cell() {
+            self.stroke_start = cell;
+            if self.tool == Tool::Fill {
+                self.paint_at(cell);
+            }
+        }
+    }
 
-        // Update simulation
-        self.simulation.update(delta_time);
+    fn release(&mut self) {
+        self.mouse_pressed = false;
+        if self.tool == Tool::Line {
+            if let Some(cell) = self.cursor_cell() {
+                self.paint_at(cell);
+            }
+        }
+        self.world.end_stroke();
     }
 
-    fn paint_particles(&mut self, center_x: usize, center_y: usize) {
-        let start_x = center_x.saturating_sub(self.brush_size);
-        let end_x = (center_x + self.brush_size).min(WIDTH - 1);
-        let start_y = center_y.saturating_sub(self.brush_size);
-        let end_y = (center_y + self.brush_size).min(HEIGHT - 1);
-        let brush_size_sq = self.brush_size * self.brush_size;
+    fn update(&mut self, elapsed: f32) {
+        // Handle painting; lines and fills paint once per click instead
+        if self.mouse_pressed && matches!(self.tool, Tool::Circle | Tool::Square | Tool::Spray) {
+            if let Some(cell) = self.cursor_cell() {
+                self.paint_at(cell);
+            }
+        }
 
-        for x in start_x..=end_x {
-            for y in start_y..=end_y {
-                let dx = x as i32 - center_x as i32;
-                let dy = y as i32 - center_y as i32;
-                let dist_sq = (dx * dx + dy * dy) as usize;
+        // Advance the fixed ticks this frame is worth at the current speed
+        self.world.step_n(self.time.advance(elapsed));
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &mut self.audio {
+            let events = self.world.simulation_mut().drain_events();
+            if let Err(e) = audio.play_events(&events) {
+                eprintln!("Sound failed: {}", e);
+            }
+        }
+    }
 
-                if dist_sq <= brush_size_sq {
-                    self.simulation.add_particle(x, y, self.current_material, None);
-                }
+    fn render(&mut self, frame: &mut [u8]) {
+        self.world.render(frame);
+        if self.palette_open {
+            self.palette.draw(frame, WIDTH, HEIGHT, self.world.simulation().materials(), self.current_material);
+        }
+        if self.inspecting || self.right_pressed {
+            if let Some(info) = self.cursor_cell().and_then(|(x, y)| self.world.inspect(x, y)) {
+                let cursor = (self.mouse_x as usize, self.mouse_y as usize);
+                CellInspector::new().draw(frame, WIDTH, HEIGHT, &info, self.world.simulation().materials(), cursor);
             }
         }
     }
 
-    fn render(&self, frame: &mut [u8]) {
-        // Clear frame to black
-        for pixel in frame.chunks_exact_mut(4) {
-            pixel[0] = 0;   // R
-            pixel[1] = 0;   // G
-            pixel[2] = 0;   // B
-            pixel[3] = 255; // A
+    fn move_mouse(&mut self, x: f32, y: f32) {
+        self.mouse_x = x;
+        self.mouse_y = y;
+        let inside = x >= 0.0 && y >= 0.0 && (x as usize) < WIDTH && (y as usize) < HEIGHT;
+        self.palette.set_hover(inside.then_some((x as usize, y as usize)));
+    }
+
+    fn scroll_palette(&mut self, rows: i32) {
+        if self.palette_open {
+            self.palette.scroll_by(rows, self.world.simulation().materials());
         }
+    }
 
-        // Draw particles
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                if let Some(particle_data) = self.simulation.get_particle_data(x, y) {
-                    let (material, temp, _, _) = particle_data;
-                    if material != MaterialType::Empty {
-                        let color = get_material_color(material, temp);
-                        let index = (y * WIDTH + x) * 4;
-                        
-                        if index + 3 < frame.len() {
-                            frame[index] = color[0];     // R
-                            frame[index + 1] = color[1]; // G
-                            frame[index + 2] = color[2]; // B
-                            frame[index + 3] = 255;      // A
-                        }
-                    }
-                }
-            }
+    fn type_char(&mut self, c: char) {
+        if self.palette_open {
+            self.palette.push_search(c);
         }
     }
 
     fn handle_key(&mut self, key: VirtualKeyCode) {
-        match key {
-            VirtualKeyCode::Key1 => self.current_material = MaterialType::Sand,
-            VirtualKeyCode::Key2 => self.current_material = MaterialType::Water,
-            VirtualKeyCode::Key3 => self.current_material = MaterialType::Stone,
-            VirtualKeyCode::Key4 => self.current_material = MaterialType::Fire,
-            VirtualKeyCode::Key5 => self.current_material = MaterialType::Oil,
-            VirtualKeyCode::Key6 => self.current_material = MaterialType::Eraser,
-            VirtualKeyCode::C => self.simulation.clear(),
-            VirtualKeyCode::Equals | VirtualKeyCode::Plus => {
+        let action = self.bindings.action(&key_name(key), self.modifiers.ctrl());
+        if action == Some(Action::TogglePalette) {
+            self.palette_open = !self.palette_open;
+            return;
+        }
+        // Keys type into the palette's search while it is open
+        if self.palette_open {
+            match key {
+                VirtualKeyCode::Escape => self.palette_open = false,
+                VirtualKeyCode::Back => self.palette.pop_search(),
+                VirtualKeyCode::Up => self.scroll_palette(-1),
+                VirtualKeyCode::Down => self.scroll_palette(1),
+                _ => {}
+            }
+            return;
+        }
+        if let Some(action) = action {
+            self.perform(action);
+        }
+    }
+
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::SelectMaterial(slot) => match self.bindings.material(slot, self.world.simulation().materials()) {
+                Some(material) => self.current_material = material,
+                None => eprintln!("No material in slot {}", slot),
+            },
+            Action::Clear => self.world.clear(),
+            Action::ToggleInspector => {
+                self.inspecting = !self.inspecting;
+                println!("Inspector {}", if self.inspecting { "on" } else { "off" });
+            }
+            Action::CycleTool => {
+                self.tool = self.tool.next();
+                println!("Tool: {:?}", self.tool);
+            }
+            Action::QuickSave => {
+                let result = SaveLoadManager::new("saves")
+                    .map_err(|e| e.to_string())
+                    .and_then(|saves| saves.save_simulation(QUICKSAVE_NAME, &self.world.save()).map_err(|e| e.to_string()));
+                match result {
+                    Ok(()) => println!("Saved to saves/{}.sim", QUICKSAVE_NAME),
+                    Err(e) => eprintln!("Quick save failed: {}", e),
+                }
+            }
+            Action::QuickLoad => {
+                let result = SaveLoadManager::new("saves")
+                    .map_err(|e| e.to_string())
+                    .and_then(|saves| saves.load_simulation(QUICKSAVE_NAME).map_err(|e| e.to_string()))
+                    .and_then(|save| self.world.load(&save).map_err(|e| e.to_string()));
+                match result {
+                    Ok(()) => println!("Loaded saves/{}.sim", QUICKSAVE_NAME),
+                    Err(e) => eprintln!("Quick load failed: {}", e),
+                }
+            }
+            Action::ToggleChunkOverlay => {
+                let shown = self.world.toggle_chunk_overlay();
+                println!("Chunk overlay {}", if shown { "on" } else { "off" });
+            }
+            Action::CycleRenderMode => {
+                let mode = self.world.cycle_render_mode();
+                println!("Render mode: {}", mode.label());
+            }
+            Action::ToggleLighting => {
+                let simulation = self.world.simulation_mut();
+                if simulation.light_map().is_some() {
+                    simulation.disable_lighting();
+                } else {
+                    simulation.enable_lighting(sand_engine::lighting::DEFAULT_AMBIENT_LIGHT);
+                }
+            }
+            Action::ToggleEffects => {
+                let simulation = self.world.simulation_mut();
+                if simulation.effects().is_some() {
+                    simulation.disable_effects();
+                } else {
+                    simulation.enable_effects();
+                }
+            }
+            Action::CycleWeather => {
+                let simulation = self.world.simulation_mut();
+                let next = match simulation.weather().map(|weather| weather.kind) {
+                    None | Some(WeatherKind::Clear) => Some(WeatherKind::Rain),
+                    Some(WeatherKind::Rain) => Some(WeatherKind::Snow),
+                    Some(WeatherKind::Snow) => Some(WeatherKind::Storm),
+                    Some(WeatherKind::Storm) => None,
+                };
+                match next {
+                    Some(kind) => simulation.enable_weather(WeatherState::new(kind, 0.5)),
+                    None => simulation.disable_weather(),
+                }
+            }
+            Action::PanLeft => self.world.camera_mut().x -= CAMERA_PAN_CELLS,
+            Action::PanRight => self.world.camera_mut().x += CAMERA_PAN_CELLS,
+            Action::PanUp => self.world.camera_mut().y -= CAMERA_PAN_CELLS,
+            Action::PanDown => self.world.camera_mut().y += CAMERA_PAN_CELLS,
+            Action::Undo => {
+                self.world.undo();
+            }
+            Action::Redo => {
+                self.world.redo();
+            }
+            Action::ZoomIn => {
+                let zoom = self.world.camera().zoom * 2.0;
+                self.world.camera_mut().set_zoom(zoom);
+            }
+            Action::ZoomOut => {
+                let zoom = self.world.camera().zoom / 2.0;
+                self.world.camera_mut().set_zoom(zoom);
+            }
+            Action::BookmarkCamera => {
+                let name = format!("Bookmark {}", self.world.bookmarks().len() + 1);
+                println!("Saved camera as '{}'", name);
+                self.world.bookmark_camera(name);
+            }
+            Action::NextBookmark => {
+                if let Some(name) = self.world.cycle_bookmark(1) {
+                    println!("Camera: {}", name);
+                }
+            }
+            Action::PreviousBookmark => {
+                if let Some(name) = self.world.cycle_bookmark(-1) {
+                    println!("Camera: {}", name);
+                }
+            }
+            Action::ToggleTour => {
+                if self.world.is_touring() {
+                    self.world.stop_tour();
+                } else if !self.world.start_tour(false) {
+                    println!("No bookmarks to tour; bind bookmark_camera to add one");
+                }
+            }
+            Action::TogglePause => {
+                self.time.toggle_pause();
+                println!("Simulation {}", if self.time.is_paused() { "paused" } else { "resumed" });
+            }
+            Action::StepTick => self.time.step(1),
+            Action::SlowDown => {
+                self.time.set_speed(self.time.speed() / 2.0);
+                println!("Speed: {}x", self.time.speed());
+            }
+            Action::SpeedUp => {
+                self.time.set_speed(self.time.speed() * 2.0);
+                println!("Speed: {}x", self.time.speed());
+            }
+            Action::BrushLarger => {
                 self.brush_size = (self.brush_size + 1).min(10);
             }
-            VirtualKeyCode::Minus => {
+            Action::BrushSmaller => {
                 self.brush_size = self.brush_size.saturating_sub(1).max(1);
             }
-            _ => {}
+            Action::TogglePalette => self.palette_open = !self.palette_open,
         }
     }
 }
 
-fn get_material_color(material: MaterialType, temp: f32) -> [u8; 3] {
-    match material {
-        MaterialType::Sand => [194, 178, 128],
-        MaterialType::Water => [64, 164, 223],
-        MaterialType::Stone => [128, 128, 128],
-        MaterialType::Fire => {
-            // Animate fire color based on temperature
-            let intensity = (temp / 1000.0).clamp(0.0, 1.0);
-            [255, (100.0 + intensity * 155.0) as u8, (intensity * 50.0) as u8]
-        }
-        MaterialType::Oil => [101, 67, 33],
-        MaterialType::Lava => [255, 69, 0],
-        MaterialType::Steam => [200, 200, 255],
-        MaterialType::Smoke => [64, 64, 64],
-        MaterialType::Ice => [173, 216, 230],
-        MaterialType::Wood => [139, 69, 19],
-        MaterialType::Plant => [34, 139, 34],
-        MaterialType::Glass => [173, 216, 230],
-        MaterialType::Acid => [0, 255, 0],
-        MaterialType::Coal => [36, 36, 36],
-        MaterialType::Gunpowder => [64, 64, 64],
-        MaterialType::ToxicGas => [128, 255, 0],
-        MaterialType::Slime => [0, 255, 127],
-        MaterialType::Gasoline => [255, 20, 147],
-        MaterialType::Fuse => [139, 69, 19],
-        MaterialType::Ash => [128, 128, 128],
-        MaterialType::Gold => [255, 215, 0],
-        MaterialType::Iron => [139, 139, 139],
-        MaterialType::Generator => [255, 255, 0],
-        MaterialType::Eraser => [0, 0, 0],
-        MaterialType::Empty => [0, 0, 0],
+/// A key's name as `KeyBindings` takes it: winit's name, with the digit keys plain
+fn key_name(key: VirtualKeyCode) -> String {
+    let name = format!("{:?}", key);
+    match name.strip_prefix("Key") {
+        Some(digit) if digit.len() == 1 => digit.to_string(),
+        _ => name,
+    }
+}
+
+/// The key bindings from the file named by `SAND_KEYBINDINGS`, or ./keybindings.toml,
+/// falling back to the defaults
+fn load_bindings() -> KeyBindings {
+    let path = std::env::var("SAND_KEYBINDINGS").unwrap_or_else(|_| KEYBINDINGS_FILE.to_string());
+    if !std::path::Path::new(&path).exists() {
+        return KeyBindings::new();
+    }
+    match KeyBindings::from_file(&path) {
+        Ok(bindings) => {
+            println!("Key bindings from {}", path);
+            bindings
+        }
+        Err(e) => {
+            eprintln!("Using default key bindings, {} is invalid: {}", path, e);
+            KeyBindings::new()
+        }
     }
 }
 
@@ -171,9 +417,18 @@ fn main() -> Result<(), Error> {
 
     println!("Sand Engine - Native");
     println!("Controls:");
-    println!("1-6: Select material (Sand, Water, Stone, Fire, Oil, Eraser)");
-    println!("C: Clear simulation");
-    println!("+/-: Adjust brush size");
+    for action in app.bindings.actions() {
+        let keys = app.bindings.keys_for(action).join("/");
+        match action {
+            Action::SelectMaterial(slot) => {
+                let material = app.bindings.materials.get(slot - 1).map_or("nothing", String::as_str);
+                println!("{}: Select {}", keys, material);
+            }
+            action => println!("{}: {}", keys, action.to_string().replace('_', " ")),
+        }
+    }
+    println!("In the palette: click a tab or material, type to search, wheel or Up/Down to scroll, Esc to close");
+    println!("Hold right mouse button: Inspect the cell under the cursor");
     println!("Mouse: Paint particles");
 
     event_loop.run(move |event, _, control_flow| {
@@ -191,21 +446,38 @@ fn main() -> Result<(), Error> {
                 }
                 WindowEvent::MouseInput { state, button, .. } => {
                     if button == MouseButton::Left {
-                        app.mouse_pressed = state == ElementState::Pressed;
+                        if state == ElementState::Pressed {
+                            app.press();
+                        } else {
+                            app.release();
+                        }
+                    } else if button == MouseButton::Right {
+                        app.right_pressed = state == ElementState::Pressed;
                     }
                 }
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    app.modifiers = modifiers;
+                }
+                WindowEvent::ReceivedCharacter(c) => {
+                    app.type_char(c);
+                }
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let rows = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => -y.round() as i32,
+                        MouseScrollDelta::PixelDelta(position) => -(position.y / 16.0).round() as i32,
+                    };
+                    app.scroll_palette(rows);
+                }
                 WindowEvent::CursorMoved { position, .. } => {
-                    app.mouse_x = position.x as f32;
-                    app.mouse_y = position.y as f32;
+                    app.move_mouse(position.x as f32, position.y as f32);
                 }
                 _ => {}
             },
             Event::MainEventsCleared => {
                 let now = Instant::now();
-                let delta_time = now.duration_since(last_update).as_secs_f32();
-                
-                if delta_time >= FRAME_DURATION.as_secs_f32() {
-                    app.update(delta_time);
+
+                if now.duration_since(last_update) >= FRAME_DURATION {
+                    app.update(now.duration_since(last_update).as_secs_f32());
                     app.render(pixels.frame_mut());
                     
                     if let Err(err) = pixels.render() {
@@ -1,7 +1,11 @@
 use futures_util::{SinkExt, StreamExt};
-use sand_engine::{Simulation, MaterialType, Particle};
+use sand_engine::critters;
+use sand_engine::protocol::{self, Capability, CellUpdate, CompressionCodec, GridFrame, Hello, Payload, ServerFeatures, Session};
+use sand_engine::{Autosave, AutosaveConfig, Blueprint, Brush, CellInfo, ChunkActivity, Clipboard, ChunkSaver, Cavity, CritterKind, Distance, ECS, Effect, ExportError, GridRect, JournalEntry, LightSource, RegionCount, Telemetry, UndoManager, VolumeTracker, MaterialRegistry, MaterialType, Particle, PasteMode, PlantRegistry, RecipeRegistry, SaveLoadManager, Simulation, SimulationSave, Structure, StructureLibrary, TileEntity, TileEntityManager, TimeControl, TimeControlState, TimelapseFormat, WorldMetadata, WorldRules};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::time;
@@ -14,6 +18,14 @@ const TARGET_FPS: u64 = 60;
 const FRAME_DURATION: Duration = Duration::from_millis(1000 / TARGET_FPS);
 const BROADCAST_FPS: u64 = 30; // Broadcast at 30 FPS for smoother updates
 const BROADCAST_INTERVAL: u64 = TARGET_FPS / BROADCAST_FPS;
+const KEYFRAME_CHUNK_SIZE: usize = 16;
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 30;
+/// Broadcasts it takes for the rotating keyframes to re-sync every chunk once
+const KEYFRAME_CYCLE_BROADCASTS: usize = 60;
+/// Frames between samples of a tracked cavity's fill
+const VOLUME_SAMPLE_FRAMES: u64 = TARGET_FPS;
+/// Ticks between time-lapse frames unless `start_capture` says otherwise
+const TIMELAPSE_FRAME_TICKS: u32 = 30;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -24,6 +36,9 @@ pub enum ClientMessage {
         y: usize,
         material: MaterialType,
         brush_size: usize,
+        /// Shape to paint with; a circle of `brush_size` when absent
+        #[serde(default)]
+        brush: Option<Brush>,
     },
     #[serde(rename = "clear")]
     Clear,
@@ -31,6 +46,115 @@ pub enum ClientMessage {
     GetParticle { x: usize, y: usize },
     #[serde(rename = "place_structure")]
     PlaceStructure { structure_name: String, x: usize, y: usize },
+    /// Capture the cells from `(min_x, min_y)` to `(max_x, max_y)`, inclusive, with their
+    /// tile entities as a user structure placeable by `name`
+    #[serde(rename = "save_structure")]
+    SaveStructure { name: String, min_x: usize, min_y: usize, max_x: usize, max_y: usize },
+    #[serde(rename = "hello")]
+    Hello(Hello),
+    /// Ask for a full snapshot, e.g. right after connecting
+    #[serde(rename = "request_snapshot")]
+    RequestSnapshot,
+    /// Place a sign, or rewrite the text of the one already there
+    #[serde(rename = "place_sign")]
+    PlaceSign { x: usize, y: usize, text: String },
+    #[serde(rename = "remove_sign")]
+    RemoveSign { x: usize, y: usize },
+    #[serde(rename = "measure_distance")]
+    MeasureDistance { x0: usize, y0: usize, x1: usize, y1: usize },
+    /// Count particles by material inside a rectangle
+    #[serde(rename = "count_region")]
+    CountRegion { x: usize, y: usize, width: usize, height: usize },
+    /// Start sampling the fill of the cavity around a cell, replacing any tracked one
+    #[serde(rename = "track_volume")]
+    TrackVolume { x: usize, y: usize },
+    #[serde(rename = "stop_tracking_volume")]
+    StopTrackingVolume,
+    /// Ask for every measurement so far as CSV
+    #[serde(rename = "export_telemetry")]
+    ExportTelemetry,
+    /// Finish the current paint stroke, making it one undo step
+    #[serde(rename = "end_stroke")]
+    EndStroke,
+    #[serde(rename = "undo")]
+    Undo,
+    #[serde(rename = "redo")]
+    Redo,
+    /// Save the grid to `<name>.sim` in SAND_SAVE_DIR
+    #[serde(rename = "save_simulation")]
+    SaveSimulation { name: String },
+    #[serde(rename = "load_simulation")]
+    LoadSimulation { name: String },
+    /// Where this client points and what it would paint; `None` position once the
+    /// pointer leaves the canvas
+    #[serde(rename = "cursor")]
+    Cursor {
+        position: Option<(usize, usize)>,
+        material: Option<MaterialType>,
+        #[serde(default)]
+        brush_size: Option<usize>,
+    },
+    /// Only receive delta updates for the cells from `(min_x, min_y)` to `(max_x, max_y)`,
+    /// inclusive, e.g. the part of a large world in view
+    #[serde(rename = "subscribe_region")]
+    SubscribeRegion { min_x: usize, min_y: usize, max_x: usize, max_y: usize },
+    /// Go back to receiving the whole world, starting with a fresh snapshot
+    #[serde(rename = "unsubscribe_region")]
+    UnsubscribeRegion,
+    /// Copy the cells from `(min_x, min_y)` to `(max_x, max_y)`, inclusive, with their tile
+    /// entities onto this client's clipboard; `cut` empties them afterwards and `name`
+    /// also saves the copy as `<name>.blueprint` in SAND_SAVE_DIR
+    #[serde(rename = "copy")]
+    Copy {
+        min_x: usize,
+        min_y: usize,
+        max_x: usize,
+        max_y: usize,
+        #[serde(default)]
+        cut: bool,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Stamp this client's clipboard, or the blueprint saved as `name`, with its top-left
+    /// corner at `(x, y)`
+    #[serde(rename = "paste")]
+    Paste {
+        x: usize,
+        y: usize,
+        #[serde(default)]
+        mode: PasteMode,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Stop the simulation clock for everyone connected
+    #[serde(rename = "pause")]
+    Pause,
+    #[serde(rename = "resume")]
+    Resume,
+    /// Pause, then run `ticks` more ticks, one by default
+    #[serde(rename = "step")]
+    Step {
+        #[serde(default = "default_step_ticks")]
+        ticks: u32,
+    },
+    /// Simulated seconds per real second, clamped to 0.25-8
+    #[serde(rename = "set_speed")]
+    SetSpeed { speed: f32 },
+    /// Start recording a time-lapse, a frame every `every_ticks` ticks, to download from
+    /// `/timelapse/gif` or `/timelapse/apng`
+    #[serde(rename = "start_capture")]
+    StartCapture {
+        #[serde(default = "default_capture_ticks")]
+        every_ticks: u32,
+    },
+}
+
+fn default_step_ticks() -> u32 {
+    1
+}
+
+fn default_capture_ticks() -> u32 {
+    TIMELAPSE_FRAME_TICKS
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +170,10 @@ pub enum ServerMessage {
     DeltaUpdate {
         added: HashMap<String, ParticleData>,
         removed: Vec<String>,
+        /// Chunks being re-synced: clients clear them before applying `added`, which
+        /// then holds every particle inside them
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        keyframes: Vec<ChunkRegion>,
     },
     #[serde(rename = "particle_info")]
     ParticleInfo {
@@ -55,6 +183,11 @@ pub enum ServerMessage {
         temp: Option<f32>,
         life: Option<f32>,
         burning: Option<bool>,
+        vx: Option<f32>,
+        vy: Option<f32>,
+        chunk_x: usize,
+        chunk_y: usize,
+        chunk_status: ChunkActivity,
     },
     #[serde(rename = "materials")]
     Materials { materials: Vec<MaterialInfo> },
@@ -62,6 +195,112 @@ pub enum ServerMessage {
     Structures { structures: Vec<StructureInfo> },
     #[serde(rename = "structure_placed")]
     StructurePlaced { success: bool, structure_name: String, error: Option<String> },
+    #[serde(rename = "world_rules")]
+    WorldRules { rules: WorldRules },
+    #[serde(rename = "welcome")]
+    Welcome {
+        /// Id of this client in `players` messages
+        client_id: u64,
+        protocol_version: u32,
+        capabilities: Vec<Capability>,
+        codec: Option<CompressionCodec>,
+    },
+    #[serde(rename = "handshake_rejected")]
+    HandshakeRejected { reason: String, min_version: u32, max_version: u32 },
+    /// Every sign in the world, sent on connect and whenever one changes
+    #[serde(rename = "signs")]
+    Signs { signs: Vec<SignInfo> },
+    #[serde(rename = "distance")]
+    Distance { dx: i64, dy: i64, euclidean: f64, manhattan: u64 },
+    #[serde(rename = "region_count")]
+    RegionCount { region: ChunkRegion, total: usize, counts: Vec<MaterialCount> },
+    /// Latest sample of the tracked cavity, broadcast about once a second
+    #[serde(rename = "volume_update")]
+    VolumeUpdate { capacity: usize, filled: usize, flow_rate: Option<f32> },
+    #[serde(rename = "telemetry")]
+    Telemetry { csv: String },
+    /// Everyone connected, broadcast whenever a cursor, material or stroke changes
+    #[serde(rename = "players")]
+    Players { players: Vec<PlayerInfo> },
+    /// Effects alive on the server, for clients that negotiated `effects`
+    #[serde(rename = "effects")]
+    Effects { effects: Vec<Effect> },
+    /// Fish and birds living in the world, e.g. from a placed aquarium
+    #[serde(rename = "critters")]
+    Critters { critters: Vec<CritterInfo> },
+    /// What a copy or cut put on the client's clipboard
+    #[serde(rename = "clipboard")]
+    Clipboard { width: usize, height: usize, particle_count: usize, tile_entity_count: usize },
+    /// Whether the simulation is paused and how fast it runs, sent on connect and broadcast
+    /// whenever anyone changes it
+    #[serde(rename = "time_control")]
+    TimeControl(TimeControlState),
+    #[serde(rename = "error")]
+    Error { message: String },
+}
+
+impl ServerMessage {
+    /// Lowest negotiated protocol version that understands this message
+    fn min_protocol_version(&self) -> u32 {
+        match self {
+            ServerMessage::WorldRules { .. } | ServerMessage::Welcome { .. } | ServerMessage::Players { .. }
+            | ServerMessage::Effects { .. } | ServerMessage::Critters { .. } => 1,
+            _ => protocol::LEGACY_PROTOCOL_VERSION,
+        }
+    }
+
+    /// Capability a client must have negotiated to be sent this message
+    fn required_capability(&self) -> Option<Capability> {
+        match self {
+            ServerMessage::Effects { .. } => Some(Capability::Effects),
+            _ => None,
+        }
+    }
+
+    /// Changes and effects across the whole grid, which clients subscribed to a region get
+    /// separately, clipped to it
+    fn is_world_wide(&self) -> bool {
+        matches!(self, ServerMessage::DeltaUpdate { .. } | ServerMessage::Effects { .. })
+    }
+
+    /// Large state payloads worth compressing for clients that negotiated a codec
+    fn is_compressible(&self) -> bool {
+        matches!(self, ServerMessage::SimulationState { .. } | ServerMessage::DeltaUpdate { .. })
+    }
+
+    /// Binary form of grid state for clients that negotiated `binary_deltas`
+    fn grid_frame(&self) -> Option<GridFrame> {
+        match self {
+            ServerMessage::SimulationState { width, height, particles } => {
+                let mut cells = vec![MaterialType::Empty; width * height];
+                for (key, data) in particles {
+                    if let Some((x, y)) = parse_cell_key(key).filter(|&(x, y)| x < *width && y < *height) {
+                        cells[y * width + x] = data.material;
+                    }
+                }
+                Some(GridFrame::Full { width: *width, height: *height, cells })
+            }
+            ServerMessage::DeltaUpdate { added, removed, keyframes } => Some(GridFrame::Delta {
+                added: added.iter()
+                    .filter_map(|(key, data)| {
+                        let (x, y) = parse_cell_key(key)?;
+                        Some(CellUpdate { x, y, material: data.material, temp: data.temp, color: data.color })
+                    })
+                    .collect(),
+                removed: removed.iter().filter_map(|key| parse_cell_key(key)).collect(),
+                keyframes: keyframes.iter()
+                    .map(|region| GridRect::new(region.x, region.y, region.width, region.height))
+                    .collect(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Cell coordinates from an `"x,y"` particle key
+fn parse_cell_key(key: &str) -> Option<(usize, usize)> {
+    let (x, y) = key.split_once(',')?;
+    Some((x.parse().ok()?, y.parse().ok()?))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -71,9 +310,20 @@ pub struct ParticleData {
     pub color: [u8; 3],
 }
 
+/// A rectangle of cells, in grid coordinates
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ChunkRegion {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MaterialInfo {
     pub id: MaterialType,
+    /// Id of the material in binary grid frames
+    pub code: u16,
     pub name: String,
     pub color: [u8; 3],
     pub density: f32,
@@ -84,6 +334,19 @@ pub struct MaterialInfo {
     pub is_stationary: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MaterialCount {
+    pub material: MaterialType,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignInfo {
+    pub x: i64,
+    pub y: i64,
+    pub text: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StructureInfo {
     pub name: String,
@@ -93,12 +356,225 @@ pub struct StructureInfo {
     pub tile_entity_count: usize,
 }
 
-type Clients = Arc<Mutex<Vec<tokio::sync::mpsc::UnboundedSender<String>>>>;
+/// Where a critter is and what it is, for clients to draw
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CritterInfo {
+    pub x: f64,
+    pub y: f64,
+    pub kind: CritterKind,
+}
+
+/// What other clients are told about a connected one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PlayerInfo {
+    pub id: u64,
+    pub name: String,
+    pub color: [u8; 3],
+    pub cursor: Option<(usize, usize)>,
+    pub material: Option<MaterialType>,
+    pub brush_size: usize,
+    /// Whether a paint stroke is in progress
+    pub painting: bool,
+}
+
+/// Cursor colors, handed out by client id
+const PLAYER_COLORS: [[u8; 3]; 8] = [
+    [230, 80, 80],
+    [80, 180, 230],
+    [120, 210, 90],
+    [240, 190, 60],
+    [200, 110, 220],
+    [60, 200, 180],
+    [250, 140, 70],
+    [220, 220, 220],
+];
+
+impl PlayerInfo {
+    fn new(id: u64) -> Self {
+        Self {
+            id,
+            name: format!("Player {}", id),
+            color: PLAYER_COLORS[id as usize % PLAYER_COLORS.len()],
+            cursor: None,
+            material: None,
+            brush_size: 1,
+            painting: false,
+        }
+    }
+}
+
+/// A connected WebSocket client, the protocol it negotiated and its player state
+struct Client {
+    id: u64,
+    tx: tokio::sync::mpsc::UnboundedSender<Payload>,
+    session: Session,
+    player: PlayerInfo,
+    /// Region the client asked to be kept in sync, with its own delta state
+    subscription: Option<Subscription>,
+}
 
-#[derive(Debug)]
+struct Subscription {
+    region: GridRect,
+    sync: SimulationState,
+    /// Whether the last effects sent had any, so one empty update clears them
+    effects_shown: bool,
+}
+
+type Clients = Arc<Mutex<Vec<Client>>>;
+/// Tile entities placed by clients; currently only signs
+type TileEntities = Arc<Mutex<TileEntityManager>>;
+
+/// Measurements shared by every client
+#[derive(Default)]
+struct Measurements {
+    telemetry: Telemetry,
+    tracker: Option<VolumeTracker>,
+    /// Simulated seconds since the server started; the time base of every sample
+    time: f32,
+}
+
+type SharedMeasurements = Arc<Mutex<Measurements>>;
+/// Edit history shared by every client, so anyone can undo the latest edit
+type History = Arc<Mutex<UndoManager>>;
+/// Autosave of the running world, when SAND_AUTOSAVE_INTERVAL turns it on
+type SharedAutosave = Arc<Mutex<Option<Autosave>>>;
+/// Entities living on the grid; currently only the critters structures bring
+type Critters = Arc<Mutex<ECS>>;
+type SharedChunkSaver = Arc<Mutex<ChunkSaver>>;
+/// Pause, steps and speed, shared by every client
+type SharedTimeControl = Arc<Mutex<TimeControl>>;
+
+/// Everything a connection shares with the rest of the server
+#[derive(Clone)]
+struct SharedState {
+    simulation: Arc<Mutex<Simulation>>,
+    clients: Clients,
+    tile_entities: TileEntities,
+    measurements: SharedMeasurements,
+    history: History,
+    autosave: SharedAutosave,
+    critters: Critters,
+    chunk_saver: SharedChunkSaver,
+    rules: Arc<WorldRules>,
+    client_connected: Arc<tokio::sync::Notify>,
+    time: SharedTimeControl,
+}
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(0);
+/// Set when anyone joins, leaves or moves, so the next broadcast includes `players`
+static PLAYERS_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// Optional protocol features this server can produce
+fn server_features() -> ServerFeatures {
+    ServerFeatures {
+        capabilities: vec![Capability::BinaryDeltas, Capability::Effects],
+        codecs: vec![CompressionCodec::Zstd, CompressionCodec::Gzip],
+    }
+}
+
+/// Messages smaller than this are sent as plain JSON even to clients with a codec
+fn compression_threshold() -> usize {
+    std::env::var("SAND_COMPRESSION_THRESHOLD")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(protocol::DEFAULT_COMPRESSION_THRESHOLD)
+}
+
+/// What the server does once nobody has been connected for `timeout`
+#[derive(Debug, Clone)]
+struct IdlePolicy {
+    /// None keeps simulating forever
+    timeout: Option<Duration>,
+    /// Where the world is persisted when the server goes idle, and restored from on start
+    save_path: Option<PathBuf>,
+}
+
+impl IdlePolicy {
+    /// SAND_IDLE_TIMEOUT (seconds, 0 disables) and SAND_IDLE_SAVE
+    fn from_env() -> Self {
+        let timeout_secs = std::env::var("SAND_IDLE_TIMEOUT")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS);
+        Self {
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+            save_path: std::env::var_os("SAND_IDLE_SAVE").map(PathBuf::from),
+        }
+    }
+
+    fn persist(&self, simulation: &Simulation, tile_entities: &TileEntityManager) {
+        let Some(path) = &self.save_path else {
+            return;
+        };
+        let save = SimulationSave::capture(simulation).with_tile_entities(tile_entities);
+        match save.write_to_file(path, flate2::Compression::default()) {
+            Ok(()) => info!("Saved idle world to {}", path.display()),
+            Err(e) => warn!("Failed to save idle world to {}: {}", path.display(), e),
+        }
+    }
+
+    fn restore(&self, simulation: &mut Simulation, tile_entities: &mut TileEntityManager) {
+        let Some(path) = self.save_path.as_ref().filter(|path| path.exists()) else {
+            return;
+        };
+        if let Ok(save) = SimulationSave::read_from_file(path) {
+            match save.apply_to(simulation) {
+                Ok(()) => {
+                    for tile_entity in save.tile_entities {
+                        tile_entities.add_tile_entity(tile_entity);
+                    }
+                    info!("Restored world from {}", path.display());
+                }
+                Err(e) => warn!("Ignoring {}: {}", path.display(), e),
+            }
+            return;
+        }
+        // Older servers saved only particles (and later tile entities)
+        let save = std::fs::read(path).map_err(|e| e.to_string()).and_then(|compressed| {
+            let mut bytes = Vec::new();
+            std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(compressed.as_slice()), &mut bytes)
+                .map_err(|e| e.to_string())?;
+            // Saves from before tile entities were persisted hold only the simulation
+            bincode::deserialize::<IdleSave>(&bytes).or_else(|_| {
+                bincode::deserialize::<sand_engine::simulation::SimulationState>(&bytes)
+                    .map(|simulation| IdleSave { simulation, tile_entities: Vec::new() })
+                    .map_err(|e| e.to_string())
+            })
+        });
+        match save {
+            Ok(save) if (save.simulation.width, save.simulation.height) == (simulation.width, simulation.height) => {
+                for ((x, y), particle) in save.simulation.particles {
+                    if simulation.is_material_enabled(particle.material_type) {
+                        simulation.set_particle(x, y, particle);
+                    }
+                }
+                for tile_entity in save.tile_entities {
+                    tile_entities.add_tile_entity(tile_entity);
+                }
+                info!("Restored world from {}", path.display());
+            }
+            Ok(save) => warn!("Ignoring {}: saved as {}x{}", path.display(), save.simulation.width, save.simulation.height),
+            Err(e) => warn!("Failed to restore world from {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// What the idle policy wrote to `save_path` before it used `SimulationSave`
+#[derive(Serialize, Deserialize)]
+struct IdleSave {
+    simulation: sand_engine::simulation::SimulationState,
+    tile_entities: Vec<TileEntity>,
+}
+
+#[derive(Debug, Default)]
 struct SimulationState {
     last_state: HashMap<String, ParticleData>,
-    full_update_counter: u64,
+    /// Simulation tick of the last broadcast; later changes go into the next delta
+    last_tick: u64,
+    /// Next chunk, in row-major order, due for a keyframe
+    keyframe_cursor: usize,
+    /// Send the whole region as one keyframe next, e.g. right after subscribing
+    resync: bool,
 }
 
 #[tokio::main]
@@ -106,21 +582,60 @@ async fn main() {
     // Initialize tracing
     tracing_subscriber::fmt::init();
     
-    let simulation = Arc::new(Mutex::new(Simulation::new(SIMULATION_WIDTH, SIMULATION_HEIGHT)));
+    let materials = load_material_registry();
+    let mut simulation = Simulation::with_materials(SIMULATION_WIDTH, SIMULATION_HEIGHT, materials);
+    let metadata = load_world_metadata();
+    let rules = Arc::new(metadata.as_ref().map(|metadata| metadata.rules.clone()).unwrap_or_default());
+    // A save's rules replace the local material pack so every host simulates it alike
+    if !rules.is_default() {
+        if let Err(e) = rules.apply_to_simulation(&mut simulation) {
+            warn!("Failed to apply world rules: {}", e);
+        }
+    }
+    // SAND_LIGHTING shades particle colors by the light map
+    if std::env::var_os("SAND_LIGHTING").is_some() {
+        simulation.enable_lighting(sand_engine::lighting::DEFAULT_AMBIENT_LIGHT);
+    }
+    simulation.enable_effects();
+    simulation.enable_radioactivity();
+    // The save's weather carries on where it left off
+    if let Some(weather) = metadata.map(|metadata| metadata.weather).filter(|weather| !weather.is_clear()) {
+        simulation.enable_weather(weather);
+    }
+    let plants = load_plant_registry(simulation.materials());
+    simulation.set_plants(plants);
+    let idle_policy = IdlePolicy::from_env();
+    let mut tile_entities = TileEntityManager::new();
+    tile_entities.set_recipes(load_recipe_registry(simulation.materials()));
+    idle_policy.restore(&mut simulation, &mut tile_entities);
+    let autosave: SharedAutosave = Arc::new(Mutex::new(start_autosave(&mut simulation, &mut tile_entities)));
+    let simulation = Arc::new(Mutex::new(simulation));
+    let tile_entities: TileEntities = Arc::new(Mutex::new(tile_entities));
+    let measurements: SharedMeasurements = Arc::new(Mutex::new(Measurements::default()));
+    // Signalled on every new connection so an idle loop resumes at once
+    let client_connected = Arc::new(tokio::sync::Notify::new());
     let clients: Clients = Arc::new(Mutex::new(Vec::new()));
-    let sim_state = Arc::new(Mutex::new(SimulationState {
-        last_state: HashMap::new(),
-        full_update_counter: 0,
-    }));
+    let sim_state = Arc::new(Mutex::new(SimulationState::default()));
     
-    // Clone for the simulation loop
-    let sim_for_loop = Arc::clone(&simulation);
-    let clients_for_loop = Arc::clone(&clients);
-    let state_for_loop = Arc::clone(&sim_state);
+    let shared = SharedState {
+        simulation,
+        clients,
+        tile_entities,
+        measurements,
+        history: Arc::new(Mutex::new(UndoManager::new())),
+        autosave,
+        critters: Arc::new(Mutex::new(ECS::new())),
+        chunk_saver: start_chunk_saver(),
+        rules,
+        client_connected,
+        // One tick per frame at normal speed
+        time: Arc::new(Mutex::new(TimeControl::new(TARGET_FPS as f32))),
+    };
     
     // Start simulation loop
+    let shared_for_loop = shared.clone();
     tokio::spawn(async move {
-        simulation_loop(sim_for_loop, clients_for_loop, state_for_loop).await;
+        simulation_loop(shared_for_loop, sim_state, idle_policy).await;
     });
     
     // Static file serving
@@ -189,6 +704,26 @@ async fn main() {
             )
         });
     
+    let js_players = warp::path!("js" / "players.js")
+        .and(warp::get())
+        .map(|| {
+            warp::reply::with_header(
+                include_str!("../../frontend/js/players.js"),
+                "content-type",
+                "application/javascript"
+            )
+        });
+
+    let js_measure = warp::path!("js" / "measure.js")
+        .and(warp::get())
+        .map(|| {
+            warp::reply::with_header(
+                include_str!("../../frontend/js/measure.js"),
+                "content-type",
+                "application/javascript"
+            )
+        });
+
     let js_ui = warp::path!("js" / "ui.js")
         .and(warp::get())
         .map(|| {
@@ -222,22 +757,24 @@ async fn main() {
             )
         });
     
-    // WebSocket endpoint
-    let simulation_for_ws = Arc::clone(&simulation);
-    let clients_for_ws = Arc::clone(&clients);
+    // Ends the time-lapse started with `start_capture` and downloads it
+    let timelapse_simulation = Arc::clone(&shared.simulation);
+    let timelapse = warp::path!("timelapse" / String)
+        .and(warp::get())
+        .and_then(move |format: String| download_timelapse(format, Arc::clone(&timelapse_simulation)));
     
+    // WebSocket endpoint
     let websocket = warp::path("ws")
         .and(warp::ws())
         .map(move |ws: warp::ws::Ws| {
-            let simulation = Arc::clone(&simulation_for_ws);
-            let clients = Arc::clone(&clients_for_ws);
-            ws.on_upgrade(move |websocket| handle_websocket(websocket, simulation, clients))
+            let shared = shared.clone();
+            ws.on_upgrade(move |websocket| handle_websocket(websocket, shared))
         });
     
     let routes = static_files.or(css)
-        .or(js_websocket).or(js_materials).or(js_structures)
+        .or(js_websocket).or(js_materials).or(js_structures).or(js_measure).or(js_players)
         .or(js_canvas).or(js_brush).or(js_ui).or(js_app)
-        .or(favicon).or(websocket);
+        .or(favicon).or(timelapse).or(websocket);
     
     
     warp::serve(routes)
@@ -245,13 +782,144 @@ async fn main() {
         .await;
 }
 
-async fn simulation_loop(simulation: Arc<Mutex<Simulation>>, clients: Clients, sim_state: Arc<Mutex<SimulationState>>) {
+/// Stop recording the time-lapse and encode it as `gif` or `apng`
+async fn download_timelapse(format: String, simulation: Arc<Mutex<Simulation>>) -> Result<warp::reply::Response, warp::Rejection> {
+    use warp::http::StatusCode;
+    use warp::Reply;
+
+    let format = match format.as_str() {
+        "gif" => TimelapseFormat::Gif,
+        "apng" => TimelapseFormat::Apng,
+        _ => return Err(warp::reject::not_found()),
+    };
+    let Some(timelapse) = simulation.lock().unwrap().stop_capture() else {
+        return Ok(warp::reply::with_status("No time-lapse is being captured", StatusCode::NOT_FOUND).into_response());
+    };
+    // Quantizing hundreds of frames takes a while, so it stays off the async workers
+    match tokio::task::spawn_blocking(move || timelapse.encode(format)).await {
+        Ok(Ok(bytes)) => {
+            let disposition = format!("attachment; filename=\"timelapse.{}\"", format.extension());
+            let reply = warp::reply::with_header(bytes, "content-type", format.mime_type());
+            Ok(warp::reply::with_header(reply, "content-disposition", disposition).into_response())
+        }
+        Ok(Err(e @ ExportError::NoFrames)) => Ok(warp::reply::with_status(e.to_string(), StatusCode::NOT_FOUND).into_response()),
+        Ok(Err(e)) => {
+            error!("Time-lapse encoding failed: {}", e);
+            Ok(warp::reply::with_status(e.to_string(), StatusCode::INTERNAL_SERVER_ERROR).into_response())
+        }
+        Err(e) => {
+            error!("Time-lapse encoding failed: {}", e);
+            Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
+
+/// Built-in materials, plus the TOML pack named by SAND_MATERIALS if set
+fn load_material_registry() -> Arc<MaterialRegistry> {
+    let Ok(path) = std::env::var("SAND_MATERIALS") else {
+        return Arc::new(MaterialRegistry::new());
+    };
+
+    match MaterialRegistry::from_file(&path) {
+        Ok(registry) => {
+            info!("Loaded material pack {} ({} custom materials)", path, registry.custom_materials().count());
+            Arc::new(registry)
+        }
+        Err(e) => {
+            warn!("Failed to load material pack {}: {}", path, e);
+            Arc::new(MaterialRegistry::new())
+        }
+    }
+}
+
+/// Built-in furnace recipes, plus the TOML recipe file named by SAND_RECIPES if set
+fn load_recipe_registry(materials: &MaterialRegistry) -> Arc<RecipeRegistry> {
+    let Ok(path) = std::env::var("SAND_RECIPES") else {
+        return Arc::new(RecipeRegistry::with_defaults());
+    };
+
+    match RecipeRegistry::from_file(&path, materials) {
+        Ok(registry) => {
+            info!("Loaded recipes {} ({} recipes)", path, registry.len());
+            Arc::new(registry)
+        }
+        Err(e) => {
+            warn!("Failed to load recipes {}: {}", path, e);
+            Arc::new(RecipeRegistry::with_defaults())
+        }
+    }
+}
+
+/// Built-in plant species, plus the TOML plant file named by SAND_PLANTS if set
+fn load_plant_registry(materials: &MaterialRegistry) -> Arc<PlantRegistry> {
+    let Ok(path) = std::env::var("SAND_PLANTS") else {
+        return Arc::new(PlantRegistry::with_defaults());
+    };
+
+    match PlantRegistry::from_file(&path, materials) {
+        Ok(registry) => {
+            info!("Loaded plant species {} ({} species)", path, registry.len());
+            Arc::new(registry)
+        }
+        Err(e) => {
+            warn!("Failed to load plant species {}: {}", path, e);
+            Arc::new(PlantRegistry::with_defaults())
+        }
+    }
+}
+
+/// The world save metadata.json named by SAND_WORLD_METADATA, for its physics rules
+/// and weather
+fn load_world_metadata() -> Option<WorldMetadata> {
+    let path = std::env::var("SAND_WORLD_METADATA").ok()?;
+
+    let metadata = std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str::<WorldMetadata>(&json).map_err(|e| e.to_string()));
+    match metadata {
+        Ok(metadata) => {
+            info!("Loaded rules and weather for world {} from {}", metadata.world_name, path);
+            Some(metadata)
+        }
+        Err(e) => {
+            warn!("Failed to load world metadata {}: {}", path, e);
+            None
+        }
+    }
+}
+
+async fn simulation_loop(shared: SharedState, sim_state: Arc<Mutex<SimulationState>>, idle_policy: IdlePolicy) {
+    let SharedState { simulation, clients, tile_entities, measurements, autosave, critters, client_connected, time, .. } = shared;
     let mut last_time = Instant::now();
     let mut interval = time::interval(FRAME_DURATION);
     let mut frame_count = 0u64;
+    let mut idle_since: Option<Instant> = None;
+    // Whether clients were last sent any effects or critters, so they hear once when the
+    // last one dies
+    let mut effects_shown = false;
+    let mut critters_shown = false;
     
     loop {
         interval.tick().await;
+        
+        // Stop simulating for nobody; the world is kept in memory and saved to disk
+        if let Some(timeout) = idle_policy.timeout {
+            if !clients.lock().unwrap().is_empty() {
+                idle_since = None;
+            } else if idle_since.get_or_insert_with(Instant::now).elapsed() >= timeout {
+                info!("No clients for {}s, pausing simulation", timeout.as_secs());
+                idle_policy.persist(&simulation.lock().unwrap(), &tile_entities.lock().unwrap());
+                // A permit may be left over from an earlier connection, so re-check
+                while clients.lock().unwrap().is_empty() {
+                    client_connected.notified().await;
+                }
+                info!("Client connected, resuming simulation");
+                idle_since = None;
+                last_time = Instant::now();
+                interval.reset();
+                continue;
+            }
+        }
         frame_count += 1;
         
         let now = Instant::now();
@@ -261,10 +929,42 @@ async fn simulation_loop(simulation: Arc<Mutex<Simulation>>, clients: Clients, s
         // Clamp delta time to avoid large jumps
         let delta_time = delta_time.min(0.1);
         
-        // Update simulation
-        {
+        // Run the fixed ticks this much wall-clock time is worth at the current speed;
+        // broadcasts and autosaves below keep to real time
+        let (ticks, tick_delta) = {
+            let mut time = time.lock().unwrap();
+            (time.advance(delta_time), time.tick_interval())
+        };
+        for _ in 0..ticks {
             let mut sim = simulation.lock().unwrap();
-            sim.update(delta_time);
+            let mut tile_entities = tile_entities.lock().unwrap();
+            sim.update_tile_entities(&mut tile_entities, tick_delta);
+            if sim.light_map().is_some() {
+                sim.set_light_sources(LightSource::from_tile_entities(&tile_entities));
+            }
+            drop(tile_entities);
+            sim.update(tick_delta);
+            update_critters(&sim, &mut critters.lock().unwrap(), tick_delta);
+            measurements.lock().unwrap().time += tick_delta;
+        }
+
+        // Snapshots are taken here under the locks; the writing happens in the background
+        {
+            let sim = simulation.lock().unwrap();
+            let tile_entities = tile_entities.lock().unwrap();
+            if let Some(autosave) = autosave.lock().unwrap().as_mut() {
+                let capture = || SimulationSave::capture(&sim).with_tile_entities(&tile_entities);
+                if let Err(e) = autosave.tick(delta_time, capture) {
+                    warn!("Autosave failed: {}", e);
+                }
+            }
+        }
+
+        if frame_count.is_multiple_of(VOLUME_SAMPLE_FRAMES) {
+            let message = sample_volume(&simulation.lock().unwrap(), &mut measurements.lock().unwrap());
+            if let Some(message) = message {
+                broadcast_to_clients(&clients, &message).await;
+            }
         }
         
         // Only broadcast every BROADCAST_INTERVAL frames to reduce network load
@@ -280,49 +980,79 @@ async fn simulation_loop(simulation: Arc<Mutex<Simulation>>, clients: Clients, s
                 let message = {
                     let sim = simulation.lock().unwrap();
                     let mut state = sim_state.lock().unwrap();
-                    create_delta_update(&*sim, &mut state)
+                    create_delta_update(&sim, &mut state, GridRect::new(0, 0, sim.width, sim.height))
                 };
                 
                 if let Some(msg) = message {
                     broadcast_to_clients(&clients, &msg).await;
                 }
+                send_subscribed_deltas(&clients, &simulation.lock().unwrap());
+                let effects: Vec<Effect> = simulation.lock().unwrap().effects()
+                    .map_or_else(Vec::new, |layer| layer.iter().copied().collect());
+                send_subscribed_effects(&clients, &effects);
+                if !effects.is_empty() || effects_shown {
+                    effects_shown = !effects.is_empty();
+                    broadcast_to_clients(&clients, &ServerMessage::Effects { effects }).await;
+                }
+                let critters = critter_infos(&critters.lock().unwrap());
+                if !critters.is_empty() || critters_shown {
+                    critters_shown = !critters.is_empty();
+                    broadcast_to_clients(&clients, &ServerMessage::Critters { critters }).await;
+                }
+                if PLAYERS_CHANGED.swap(false, Ordering::Relaxed) {
+                    broadcast_to_clients(&clients, &players_message(&clients)).await;
+                }
             }
         }
     }
 }
 
+/// Step the critters against the grid, removing those that died
+fn update_critters(simulation: &Simulation, critters: &mut ECS, delta_time: f32) {
+    let materials = Arc::clone(simulation.materials());
+    let killed = critters::critter_system(critters, delta_time, &materials, simulation, &mut rand::thread_rng());
+    for entity_id in killed {
+        critters.remove_entity(entity_id);
+    }
+}
+
+fn critter_infos(critters: &ECS) -> Vec<CritterInfo> {
+    critters.get_active_entities().iter()
+        .filter_map(|&entity_id| {
+            let (position, critter) = (critters.get_position(entity_id)?, critters.get_critter(entity_id)?);
+            Some(CritterInfo { x: position.x, y: position.y, kind: critter.kind })
+        })
+        .collect()
+}
+
 fn create_simulation_state_message(simulation: &Simulation) -> ServerMessage {
     let mut particles = HashMap::new();
     
-    // Only scan a smaller area or use sparse representation for better performance
-    for y in 0..simulation.height {
-        for x in 0..simulation.width {
-            if let Some(particle_data) = simulation.get_particle_data(x, y) {
-                let (material, temp, _life, _burning) = particle_data;
-                if material != MaterialType::Empty {
-                    // Create color only once per material type for performance
-                    let color = match material {
-                        MaterialType::Sand => [194, 178, 128],
-                        MaterialType::Water => [64, 164, 223],
-                        MaterialType::Fire => [255, 100, 0],
-                        MaterialType::Stone => [128, 128, 128],
-                        MaterialType::Lava => [255, 69, 0],
-                        _ => {
-                            let mut temp_particle = Particle::new(x, y, material, Some(temp));
-                            temp_particle.get_color()
-                        }
-                    };
-                    
-                    particles.insert(
-                        format!("{},{}", x, y),
-                        ParticleData {
-                            material,
-                            temp,
-                            color,
-                        }
-                    );
+    for particle in simulation.iter_particles() {
+        let (x, y, material, temp) = (particle.x, particle.y, particle.material_type, particle.temp);
+        if material != MaterialType::Empty {
+            // Create color only once per material type for performance
+            let color = match material {
+                MaterialType::Sand => [194, 178, 128],
+                MaterialType::Water => [64, 164, 223],
+                MaterialType::Fire => [255, 100, 0],
+                MaterialType::Stone => [128, 128, 128],
+                MaterialType::Lava => [255, 69, 0],
+                MaterialType::Custom(_) => simulation.materials().get(material).base_color,
+                _ => {
+                    let mut temp_particle = Particle::new(x, y, material, Some(temp));
+                    temp_particle.get_color()
                 }
-            }
+            };
+            
+            particles.insert(
+                format!("{},{}", x, y),
+                ParticleData {
+                    material,
+                    temp,
+                    color: lit_color(simulation, x, y, color),
+                }
+            );
         }
     }
     
@@ -333,107 +1063,98 @@ fn create_simulation_state_message(simulation: &Simulation) -> ServerMessage {
     }
 }
 
-fn create_delta_update(simulation: &Simulation, state: &mut SimulationState) -> Option<ServerMessage> {
-    // Send full update every 60 frames (2 seconds at 30 FPS) to sync
-    if state.full_update_counter % 60 == 0 {
-        state.full_update_counter += 1;
-        let full_state = create_simulation_state_message(simulation);
-        
-        // Update last_state to current state
-        if let ServerMessage::SimulationState { particles, .. } = &full_state {
-            state.last_state = particles.clone();
-        }
-        
-        return Some(full_state);
-    }
-    
-    state.full_update_counter += 1;
-    
-    // Get current particles - optimized to only scan dirty regions
-    let mut current_particles = HashMap::new();
-    
-    // Use dirty region optimization: only scan areas that likely changed
-    let chunk_size = 16; // Match simulation chunk size
-    let chunks_x = (simulation.width + chunk_size - 1) / chunk_size;
-    let chunks_y = (simulation.height + chunk_size - 1) / chunk_size;
-    
-    // Quick scan to find regions with particles (sparse grid optimization)
-    let mut active_regions = Vec::new();
-    for chunk_y in 0..chunks_y {
-        for chunk_x in 0..chunks_x {
-            let start_x = chunk_x * chunk_size;
-            let end_x = ((chunk_x + 1) * chunk_size).min(simulation.width);
-            let start_y = chunk_y * chunk_size;
-            let end_y = ((chunk_y + 1) * chunk_size).min(simulation.height);
-            
-            // Quick check if chunk has any particles
-            let mut has_particles = false;
-            'chunk_check: for y in start_y..end_y {
-                for x in start_x..end_x {
-                    if simulation.get_particle_data(x, y).is_some() {
-                        has_particles = true;
-                        break 'chunk_check;
-                    }
-                }
-            }
-            
-            if has_particles {
-                active_regions.push((start_x, start_y, end_x, end_y));
-            }
-        }
+fn particle_data(particle: &Particle, simulation: &Simulation) -> Option<ParticleData> {
+    (particle.material_type != MaterialType::Empty).then(|| ParticleData {
+        material: particle.material_type,
+        temp: particle.temp,
+        color: lit_color(simulation, particle.x, particle.y, get_fast_material_color(particle.material_type, simulation.materials())),
+    })
+}
+
+/// Blend the simulation's light map, if lighting is enabled, into a particle color
+fn lit_color(simulation: &Simulation, x: usize, y: usize, color: [u8; 3]) -> [u8; 3] {
+    match simulation.light_map() {
+        Some(light_map) => light_map.shade(x, y, color),
+        None => color,
     }
+}
+
+/// Changes inside `region` since the last delta made with `state`, plus rotating
+/// keyframes of the chunks overlapping it
+fn create_delta_update(simulation: &Simulation, state: &mut SimulationState, region: GridRect) -> Option<ServerMessage> {
+    let region = region.intersection(&GridRect::new(0, 0, simulation.width, simulation.height))?;
+    let chunk_size = KEYFRAME_CHUNK_SIZE;
+    let (first_chunk_x, first_chunk_y) = (region.x / chunk_size, region.y / chunk_size);
+    let chunks_x = (region.x + region.width).div_ceil(chunk_size) - first_chunk_x;
+    let chunks_y = (region.y + region.height).div_ceil(chunk_size) - first_chunk_y;
     
-    // Only scan active regions
-    for (start_x, start_y, end_x, end_y) in active_regions {
-        for y in start_y..end_y {
-            for x in start_x..end_x {
-                if let Some(particle_data) = simulation.get_particle_data(x, y) {
-                    let (material, temp, _life, _burning) = particle_data;
-                    if material != MaterialType::Empty {
-                        let color = get_fast_material_color(material);
-                        let key = format!("{},{}", x, y);
-                        current_particles.insert(key, ParticleData {
-                            material,
-                            temp,
-                            color,
-                        });
-                    }
-                }
+    // Re-sync a few chunks per broadcast instead of the whole region at once
+    let chunk_count = chunks_x * chunks_y;
+    let keyframes_per_broadcast = chunk_count.div_ceil(KEYFRAME_CYCLE_BROADCASTS).max(1).min(chunk_count);
+    let mut keyframes = Vec::with_capacity(keyframes_per_broadcast);
+    let resync = std::mem::take(&mut state.resync);
+    if resync {
+        keyframes.push(ChunkRegion { x: region.x, y: region.y, width: region.width, height: region.height });
+    } else {
+        for i in 0..keyframes_per_broadcast {
+            let chunk = (state.keyframe_cursor + i) % chunk_count;
+            let x = (first_chunk_x + chunk % chunks_x) * chunk_size;
+            let y = (first_chunk_y + chunk / chunks_x) * chunk_size;
+            if let Some(rect) = GridRect::new(x, y, chunk_size, chunk_size).intersection(&region) {
+                keyframes.push(ChunkRegion { x: rect.x, y: rect.y, width: rect.width, height: rect.height });
             }
         }
+        state.keyframe_cursor = (state.keyframe_cursor + keyframes_per_broadcast) % chunk_count.max(1);
     }
     
-    // Calculate deltas
     let mut added = HashMap::new();
     let mut removed = Vec::new();
     
-    // Find added/changed particles
-    for (key, particle) in &current_particles {
-        if !state.last_state.contains_key(key) || 
-           state.last_state.get(key) != Some(particle) {
-            added.insert(key.clone(), particle.clone());
+    // Loading a save can move the tick backwards; every cell is then stamped as changed
+    state.last_tick = state.last_tick.min(simulation.tick());
+    // Only visit cells the simulation reports as changed since the last broadcast
+    for (x, y, particle) in simulation.iter_changed_since(state.last_tick).filter(|&(x, y, _)| region.contains(x, y)) {
+        let key = format!("{},{}", x, y);
+        match particle.and_then(|particle| particle_data(particle, simulation)) {
+            Some(data) => {
+                if state.last_state.get(&key) != Some(&data) {
+                    state.last_state.insert(key.clone(), data.clone());
+                    added.insert(key, data);
+                }
+            }
+            None => {
+                if state.last_state.remove(&key).is_some() {
+                    removed.push(key);
+                }
+            }
         }
     }
+    state.last_tick = simulation.tick();
     
-    // Find removed particles
-    for key in state.last_state.keys() {
-        if !current_particles.contains_key(key) {
-            removed.push(key.clone());
+    // Keyframed chunks carry all of their particles, changed or not
+    let mut keyframe_particles = false;
+    for region in &keyframes {
+        let rect = GridRect::new(region.x, region.y, region.width, region.height);
+        for particle in simulation.iter_region(rect) {
+            if let Some(data) = particle_data(particle, simulation) {
+                let key = format!("{},{}", particle.x, particle.y);
+                state.last_state.insert(key.clone(), data.clone());
+                added.insert(key, data);
+                keyframe_particles = true;
+            }
         }
     }
     
-    // Update last state
-    state.last_state = current_particles;
-    
-    // Only send delta if there are changes
-    if !added.is_empty() || !removed.is_empty() {
-        Some(ServerMessage::DeltaUpdate { added, removed })
+    // Only send delta if there are changes; keyframes of empty chunks ride along with
+    // other changes rather than forcing a message of their own
+    if resync || keyframe_particles || !added.is_empty() || !removed.is_empty() {
+        Some(ServerMessage::DeltaUpdate { added, removed, keyframes })
     } else {
         None
     }
 }
 
-fn get_fast_material_color(material: MaterialType) -> [u8; 3] {
+fn get_fast_material_color(material: MaterialType, materials: &MaterialRegistry) -> [u8; 3] {
     // Optimized color lookup without temperature calculation
     match material {
         MaterialType::Sand => [194, 178, 128],
@@ -458,9 +1179,29 @@ fn get_fast_material_color(material: MaterialType) -> [u8; 3] {
         MaterialType::Ash => [128, 128, 128],
         MaterialType::Gold => [255, 215, 0],
         MaterialType::Iron => [139, 139, 139],
+        MaterialType::Sandstone => [176, 150, 102],
+        MaterialType::MoltenIron => [255, 110, 30],
+        MaterialType::MoltenGold => [255, 170, 40],
+        MaterialType::Seed => [150, 120, 60],
+        MaterialType::Stem => [70, 140, 40],
+        MaterialType::Root => [120, 90, 50],
+        MaterialType::Snow => [240, 245, 255],
+        MaterialType::Door => [95, 105, 120],
+        MaterialType::Piston => [150, 120, 70],
+        MaterialType::Dirt => [110, 80, 50],
+        MaterialType::WetSand => [150, 130, 85],
+        MaterialType::Mud => [85, 60, 40],
+        MaterialType::Salt => [235, 235, 228],
+        MaterialType::SaltWater => [70, 120, 190],
+        MaterialType::Firework => [200, 40, 60],
+        MaterialType::Spark => [255, 220, 120],
+        MaterialType::Thermite => [140, 90, 80],
+        MaterialType::Uranium => [90, 170, 60],
+        MaterialType::Lead => [95, 100, 115],
         MaterialType::Generator => [255, 255, 0],
         MaterialType::Eraser => [0, 0, 0],
         MaterialType::Empty => [0, 0, 0],
+        MaterialType::Custom(_) => materials.get(material).base_color,
     }
 }
 
@@ -470,12 +1211,35 @@ async fn broadcast_to_clients(clients: &Clients, message: &ServerMessage) {
         Err(_) => return,
     };
     
+    let threshold = compression_threshold();
+    
     let mut clients_lock = clients.lock().unwrap();
     let client_count = clients_lock.len();
     let mut to_remove = Vec::new();
+    // Each codec compresses the message at most once, however many clients use it
+    let mut encoded: HashMap<Option<CompressionCodec>, Payload> = HashMap::new();
+    let mut binary: Option<Option<Payload>> = None;
     
     for (i, client) in clients_lock.iter().enumerate() {
-        if let Err(_) = client.send(message_json.clone()) {
+        // Skip clients whose negotiated protocol predates this message
+        if message.min_protocol_version() > client.session.protocol_version {
+            continue;
+        }
+        if client.subscription.is_some() && message.is_world_wide() {
+            continue;
+        }
+        if message.required_capability().is_some_and(|capability| !client.session.supports(capability)) {
+            continue;
+        }
+        let grid_frame = client.session.supports(Capability::BinaryDeltas)
+            .then(|| binary.get_or_insert_with(|| encode_grid_frame(message)).clone())
+            .flatten();
+        let codec = client.session.codec.filter(|_| message.is_compressible());
+        let payload = grid_frame.unwrap_or_else(|| encoded
+            .entry(codec)
+            .or_insert_with(|| Payload::encode(message_json.clone(), codec, threshold))
+            .clone());
+        if client.tx.send(payload).is_err() {
             to_remove.push(i);
         }
     }
@@ -486,26 +1250,38 @@ async fn broadcast_to_clients(clients: &Clients, message: &ServerMessage) {
     }
 }
 
-async fn handle_websocket(
-    websocket: warp::ws::WebSocket,
-    simulation: Arc<Mutex<Simulation>>,
-    clients: Clients,
-) {
+async fn handle_websocket(websocket: warp::ws::WebSocket, shared: SharedState) {
+    let SharedState { simulation, clients, tile_entities, measurements, history, autosave, critters, chunk_saver, rules, client_connected, time } = shared;
     let (mut ws_sender, mut ws_receiver) = websocket.split();
+    // Each connection copies and pastes through its own clipboard
+    let mut clipboard = Clipboard::new();
     
     // Create a channel for this client
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Payload>();
     
-    // Add this client to the list
+    // Add this client to the list; it speaks the legacy protocol until it says hello
+    let client_id = NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed);
     {
         let mut clients_lock = clients.lock().unwrap();
-        clients_lock.push(tx.clone());
+        clients_lock.push(Client {
+            id: client_id,
+            tx: tx.clone(),
+            session: Session::legacy(),
+            player: PlayerInfo::new(client_id),
+            subscription: None,
+        });
     }
+    PLAYERS_CHANGED.store(true, Ordering::Relaxed);
+    client_connected.notify_one();
     
     // Spawn a task to handle outgoing messages for this client
     let outgoing_task = tokio::spawn(async move {
-        while let Some(message) = rx.recv().await {
-            if let Err(_) = ws_sender.send(warp::ws::Message::text(message)).await {
+        while let Some(payload) = rx.recv().await {
+            let message = match payload {
+                Payload::Text(text) => warp::ws::Message::text(text),
+                Payload::Binary(frame) => warp::ws::Message::binary(frame),
+            };
+            if let Err(_) = ws_sender.send(message).await {
                 break;
             }
         }
@@ -513,22 +1289,19 @@ async fn handle_websocket(
     
     // Send initial materials list
     let materials_message = ServerMessage::Materials {
-        materials: get_materials_info(),
+        materials: get_materials_info(&simulation.lock().unwrap()),
     };
     
-    if let Ok(json) = serde_json::to_string(&materials_message) {
-        let _ = tx.send(json);
-    }
-    
+    send_message(&tx, &materials_message);
+
     // Send structures list
     let structures_message = ServerMessage::Structures {
         structures: get_structures_info(),
     };
     
-    if let Ok(json) = serde_json::to_string(&structures_message) {
-        let _ = tx.send(json);
-    }
-    
+    send_message(&tx, &structures_message);
+    send_message(&tx, &signs_message(&tile_entities.lock().unwrap()));
+    send_message(&tx, &ServerMessage::TimeControl(time.lock().unwrap().state()));
     
     // Handle incoming messages
     while let Some(result) = ws_receiver.next().await {
@@ -536,8 +1309,181 @@ async fn handle_websocket(
             Ok(msg) => {
                 if let Ok(text) = msg.to_str() {
                     match serde_json::from_str::<ClientMessage>(text) {
+                        Ok(ClientMessage::Hello(hello)) => {
+                            handle_hello(&hello, client_id, &tx, &clients, &rules);
+                        }
+                        Ok(ClientMessage::RequestSnapshot) => {
+                            send_snapshot(client_id, &clients, &simulation);
+                            send_message(&tx, &signs_message(&tile_entities.lock().unwrap()));
+                        }
+                        Ok(ClientMessage::PlaceSign { x, y, text }) => {
+                            let in_bounds = {
+                                let sim = simulation.lock().unwrap();
+                                x < sim.width && y < sim.height
+                            };
+                            if in_bounds {
+                                place_sign(&tile_entities, x, y, &text);
+                                broadcast_signs(&clients, &tile_entities).await;
+                            }
+                        }
+                        Ok(ClientMessage::RemoveSign { x, y }) => {
+                            let removed = tile_entities.lock().unwrap().remove_tile_entity((x as i64, y as i64)).is_some();
+                            if removed {
+                                broadcast_signs(&clients, &tile_entities).await;
+                            }
+                        }
+                        Ok(message @ (ClientMessage::MeasureDistance { .. }
+                        | ClientMessage::CountRegion { .. }
+                        | ClientMessage::TrackVolume { .. }
+                        | ClientMessage::StopTrackingVolume
+                        | ClientMessage::ExportTelemetry)) => {
+                            handle_measurement(message, &tx, &simulation.lock().unwrap(), &mut measurements.lock().unwrap());
+                        }
+                        Ok(ClientMessage::GetParticle { x, y }) => {
+                            let sim = simulation.lock().unwrap();
+                            // Dirty chunks are those written during the latest tick
+                            if let Some(info) = CellInfo::capture(&sim, x, y, sim.tick().saturating_sub(1)) {
+                                send_message(&tx, &ServerMessage::ParticleInfo {
+                                    x,
+                                    y,
+                                    material: info.material,
+                                    temp: info.temp,
+                                    life: info.life,
+                                    burning: info.burning,
+                                    vx: info.velocity.map(|velocity| velocity.0),
+                                    vy: info.velocity.map(|velocity| velocity.1),
+                                    chunk_x: info.chunk.0,
+                                    chunk_y: info.chunk.1,
+                                    chunk_status: info.chunk_activity,
+                                });
+                            }
+                        }
+                        Ok(ClientMessage::SaveSimulation { name }) => {
+                            match save_manager(&name) {
+                                Ok(_) => {
+                                    // Only copying the changed chunks holds up the simulation;
+                                    // they are compressed and written in the background
+                                    let sim = simulation.lock().unwrap();
+                                    let tile_entities = tile_entities.lock().unwrap();
+                                    chunk_saver.lock().unwrap().save(&name, &sim, &tile_entities, |report| {
+                                        info!("Saved '{}' at tick {}: {} changed chunks in {:?}", report.name, report.tick, report.chunks, report.elapsed);
+                                    });
+                                }
+                                Err(e) => send_message(&tx, &ServerMessage::Error { message: format!("Could not save '{}': {}", name, e) }),
+                            }
+                        }
+                        Ok(ClientMessage::SaveStructure { name, min_x, min_y, max_x, max_y }) => {
+                            let region = GridRect::new(
+                                min_x.min(max_x),
+                                min_y.min(max_y),
+                                min_x.abs_diff(max_x).saturating_add(1),
+                                min_y.abs_diff(max_y).saturating_add(1),
+                            );
+                            match save_structure(&name, region, &simulation, &tile_entities) {
+                                Ok(()) => {
+                                    let message = ServerMessage::Structures { structures: get_structures_info() };
+                                    broadcast_to_clients(&clients, &message).await;
+                                }
+                                Err(e) => send_message(&tx, &ServerMessage::Error { message: format!("Could not save structure '{}': {}", name, e) }),
+                            }
+                        }
+                        Ok(ClientMessage::Copy { min_x, min_y, max_x, max_y, cut, name }) => {
+                            let region = GridRect::new(
+                                min_x.min(max_x),
+                                min_y.min(max_y),
+                                min_x.abs_diff(max_x).saturating_add(1),
+                                min_y.abs_diff(max_y).saturating_add(1),
+                            );
+                            match copy_region(&mut clipboard, region, cut, name.as_deref(), &simulation, &tile_entities, &history) {
+                                Ok(blueprint) => {
+                                    send_message(&tx, &ServerMessage::Clipboard {
+                                        width: blueprint.width,
+                                        height: blueprint.height,
+                                        particle_count: blueprint.particles.len(),
+                                        tile_entity_count: blueprint.tile_entities.len(),
+                                    });
+                                    if cut {
+                                        save_autosave_soon(&autosave);
+                                        broadcast_signs(&clients, &tile_entities).await;
+                                    }
+                                }
+                                Err(e) => send_message(&tx, &ServerMessage::Error { message: format!("Could not copy: {}", e) }),
+                            }
+                        }
+                        Ok(ClientMessage::Paste { x, y, mode, name }) => {
+                            match paste_blueprint(&mut clipboard, (x, y), mode, name.as_deref(), &simulation, &tile_entities, &history) {
+                                Ok(()) => {
+                                    // The journal can't replay a paste, so snapshot the new world
+                                    save_autosave_soon(&autosave);
+                                    broadcast_signs(&clients, &tile_entities).await;
+                                }
+                                Err(e) => send_message(&tx, &ServerMessage::Error { message: format!("Could not paste: {}", e) }),
+                            }
+                        }
+                        Ok(ClientMessage::LoadSimulation { name }) => {
+                            match load_simulation(&name, &simulation, &tile_entities, &history) {
+                                Ok(()) => {
+                                    // The journal can't replay a load, so snapshot the new world
+                                    save_autosave_soon(&autosave);
+                                    broadcast_signs(&clients, &tile_entities).await;
+                                }
+                                Err(e) => send_message(&tx, &ServerMessage::Error { message: format!("Could not load '{}': {}", name, e) }),
+                            }
+                        }
+                        Ok(ClientMessage::Cursor { position, material, brush_size }) => {
+                            update_player(&clients, client_id, |player| {
+                                player.cursor = position;
+                                player.material = material.or(player.material);
+                                player.brush_size = brush_size.unwrap_or(player.brush_size);
+                            });
+                        }
+                        Ok(message @ ClientMessage::Paint { x, y, material, brush_size, .. }) => {
+                            update_player(&clients, client_id, |player| {
+                                player.cursor = Some((x, y));
+                                player.material = Some(material);
+                                player.brush_size = brush_size;
+                                player.painting = true;
+                            });
+                            handle_client_message(message, client_id, &simulation, &history, &autosave, &critters).await;
+                        }
+                        Ok(ClientMessage::EndStroke) => {
+                            update_player(&clients, client_id, |player| player.painting = false);
+                            handle_client_message(ClientMessage::EndStroke, client_id, &simulation, &history, &autosave, &critters).await;
+                        }
+                        Ok(ClientMessage::SubscribeRegion { min_x, min_y, max_x, max_y }) => {
+                            let region = GridRect::new(
+                                min_x.min(max_x),
+                                min_y.min(max_y),
+                                min_x.abs_diff(max_x).saturating_add(1),
+                                min_y.abs_diff(max_y).saturating_add(1),
+                            );
+                            let tick = simulation.lock().unwrap().tick();
+                            subscribe_region(&clients, client_id, region, tick);
+                        }
+                        Ok(ClientMessage::UnsubscribeRegion) => {
+                            let subscribed = clients.lock().unwrap().iter_mut()
+                                .find(|client| client.id == client_id)
+                                .and_then(|client| client.subscription.take())
+                                .is_some();
+                            if subscribed {
+                                send_snapshot(client_id, &clients, &simulation);
+                            }
+                        }
+                        Ok(message @ (ClientMessage::Pause | ClientMessage::Resume
+                        | ClientMessage::Step { .. } | ClientMessage::SetSpeed { .. })) => {
+                            let state = handle_time_control(message, &mut time.lock().unwrap());
+                            broadcast_to_clients(&clients, &ServerMessage::TimeControl(state)).await;
+                        }
+                        Ok(ClientMessage::StartCapture { every_ticks }) => {
+                            simulation.lock().unwrap().start_capture(every_ticks);
+                        }
+                        Ok(ClientMessage::Clear) => {
+                            handle_client_message(ClientMessage::Clear, client_id, &simulation, &history, &autosave, &critters).await;
+                            tile_entities.lock().unwrap().clear();
+                            broadcast_signs(&clients, &tile_entities).await;
+                        }
                         Ok(client_message) => {
-                            handle_client_message(client_message, &simulation).await;
+                            handle_client_message(client_message, client_id, &simulation, &history, &autosave, &critters).await;
                         }
                         Err(_) => {}
                     }
@@ -552,79 +1498,573 @@ async fn handle_websocket(
     }
     
     outgoing_task.abort();
+    // A stroke the client was still painting becomes an undo step of its own
+    handle_client_message(ClientMessage::EndStroke, client_id, &simulation, &history, &autosave, &critters).await;
+    clients.lock().unwrap().retain(|client| client.id != client_id);
+    PLAYERS_CHANGED.store(true, Ordering::Relaxed);
 }
 
-async fn handle_client_message(
-    message: ClientMessage,
+/// Change one client's player state, flagging a `players` broadcast if anything moved
+fn update_player(clients: &Clients, client_id: u64, change: impl FnOnce(&mut PlayerInfo)) {
+    let mut clients_lock = clients.lock().unwrap();
+    if let Some(client) = clients_lock.iter_mut().find(|client| client.id == client_id) {
+        let before = client.player.clone();
+        change(&mut client.player);
+        if client.player != before {
+            PLAYERS_CHANGED.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn players_message(clients: &Clients) -> ServerMessage {
+    ServerMessage::Players {
+        players: clients.lock().unwrap().iter().map(|client| client.player.clone()).collect(),
+    }
+}
+
+/// Saves directory from SAND_SAVE_DIR (default `saves`), refusing names that could
+/// escape it
+fn save_manager(name: &str) -> Result<SaveLoadManager, String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err("names may only use letters, digits, '-' and '_'".to_string());
+    }
+    SaveLoadManager::new(save_directory()).map_err(|e| e.to_string())
+}
+
+fn save_directory() -> PathBuf {
+    std::env::var_os("SAND_SAVE_DIR").map_or_else(|| PathBuf::from("saves"), PathBuf::from)
+}
+
+/// Saves `save_simulation` writes in the background, logging any that fail
+fn start_chunk_saver() -> SharedChunkSaver {
+    let (saver, mut errors) = ChunkSaver::new(save_directory());
+    tokio::spawn(async move {
+        while let Some(failure) = errors.recv().await {
+            error!("Could not save '{}': {}", failure.name, failure.error);
+        }
+    });
+    Arc::new(Mutex::new(saver))
+}
+
+/// Name of the autosave and its journal in SAND_SAVE_DIR
+const AUTOSAVE_NAME: &str = "autosave";
+
+/// Autosave every SAND_AUTOSAVE_INTERVAL seconds (unset or 0 disables), keeping
+/// SAND_AUTOSAVE_BACKUPS older saves, after recovering what the last run left behind
+fn start_autosave(simulation: &mut Simulation, tile_entities: &mut TileEntityManager) -> Option<Autosave> {
+    let interval = std::env::var("SAND_AUTOSAVE_INTERVAL")
+        .ok()
+        .and_then(|value| value.parse::<f32>().ok())
+        .filter(|&interval| interval > 0.0)?;
+    let backups = std::env::var("SAND_AUTOSAVE_BACKUPS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(AutosaveConfig::default().backups);
+    let saves = match save_manager(AUTOSAVE_NAME) {
+        Ok(saves) => saves,
+        Err(e) => {
+            warn!("Autosave disabled: {}", e);
+            return None;
+        }
+    };
+
+    let mut recovered = false;
+    match saves.recover_autosave(AUTOSAVE_NAME, backups) {
+        Ok(Some(recovery)) => match recovery.apply_to(simulation) {
+            Ok(()) => {
+                for tile_entity in recovery.save.tile_entities.iter().cloned() {
+                    tile_entities.add_tile_entity(tile_entity);
+                }
+                info!("Recovered world from autosave, replaying {} journaled edits", recovery.journal.len());
+                recovered = true;
+            }
+            Err(e) => warn!("Ignoring autosave: {}", e),
+        },
+        Ok(None) => {}
+        Err(e) => warn!("Failed to recover autosave: {}", e),
+    }
+
+    match saves.autosave(AUTOSAVE_NAME, AutosaveConfig { interval, backups }) {
+        Ok(mut autosave) => {
+            // Fold the replayed edits into a fresh save
+            if recovered {
+                autosave.save_soon();
+            }
+            info!("Autosaving every {}s, keeping {} backups", interval, backups);
+            Some(autosave)
+        }
+        Err(e) => {
+            warn!("Autosave disabled: {}", e);
+            None
+        }
+    }
+}
+
+/// Journal an edit so it can be replayed if the server dies before the next autosave
+fn journal(autosave: &SharedAutosave, entry: JournalEntry) {
+    if let Some(autosave) = autosave.lock().unwrap().as_mut() {
+        if let Err(e) = autosave.record(&entry) {
+            warn!("Failed to journal edit: {}", e);
+        }
+    }
+}
+
+/// Snapshot the world at the next autosave tick, after edits the journal can't replay
+fn save_autosave_soon(autosave: &SharedAutosave) {
+    if let Some(autosave) = autosave.lock().unwrap().as_mut() {
+        autosave.save_soon();
+    }
+}
+
+/// Library in SAND_SAVE_DIR holding the structures clients saved
+const USER_STRUCTURES: &str = "user";
+
+fn user_structures() -> StructureLibrary {
+    match save_manager(USER_STRUCTURES).and_then(|saves| saves.load_structures(USER_STRUCTURES).map_err(|e| e.to_string())) {
+        Ok(library) => library,
+        Err(e) => {
+            warn!("Failed to load user structures: {}", e);
+            StructureLibrary::default()
+        }
+    }
+}
+
+/// Capture `region` into the user structure library as `name`, replacing an older
+/// structure of that name
+fn save_structure(name: &str, region: GridRect, simulation: &Arc<Mutex<Simulation>>, tile_entities: &TileEntities) -> Result<(), String> {
+    let saves = save_manager(name)?;
+    if Structure::get_by_name(name).is_some() {
+        return Err("that name belongs to a built-in structure".to_string());
+    }
+    let mut structure = Structure::from_region(&simulation.lock().unwrap(), region)
+        .with_tile_entities(&tile_entities.lock().unwrap(), region);
+    if structure.particles.is_empty() && structure.tile_entities.is_empty() {
+        return Err("the region is empty".to_string());
+    }
+    structure.name = name.to_string();
+    let mut library = saves.load_structures(USER_STRUCTURES).map_err(|e| e.to_string())?;
+    library.insert(structure);
+    saves.save_structures(USER_STRUCTURES, &library).map_err(|e| e.to_string())?;
+    info!("Saved structure '{}' from {:?}", name, region);
+    Ok(())
+}
+
+/// Copy or cut `region` onto `clipboard` as one undo step, saving it as a blueprint file
+/// too when `name` is given
+fn copy_region(
+    clipboard: &mut Clipboard,
+    region: GridRect,
+    cut: bool,
+    name: Option<&str>,
+    simulation: &Arc<Mutex<Simulation>>,
+    tile_entities: &TileEntities,
+    history: &History,
+) -> Result<Blueprint, String> {
+    let saves = name.map(save_manager).transpose()?;
+    let mut sim = simulation.lock().unwrap();
+    let mut tile_entities = tile_entities.lock().unwrap();
+    let blueprint = if cut {
+        let cells = region_cells(region, &sim);
+        history.lock().unwrap().edit(&mut sim, "cut", cells, |sim| {
+            clipboard.cut_region(sim, &mut tile_entities, region).clone()
+        })
+    } else {
+        clipboard.copy_region(&sim, &tile_entities, region).clone()
+    };
+    if let (Some(saves), Some(name)) = (saves, name) {
+        saves.save_blueprint(name, &blueprint).map_err(|e| e.to_string())?;
+        info!("Saved blueprint '{}' from {:?}", name, region);
+    }
+    Ok(blueprint)
+}
+
+/// Paste the blueprint saved as `name`, which then replaces the clipboard, or else
+/// whatever is on the clipboard, as one undo step
+fn paste_blueprint(
+    clipboard: &mut Clipboard,
+    position: (usize, usize),
+    mode: PasteMode,
+    name: Option<&str>,
     simulation: &Arc<Mutex<Simulation>>,
+    tile_entities: &TileEntities,
+    history: &History,
+) -> Result<(), String> {
+    if let Some(name) = name {
+        let blueprint = save_manager(name)?.load_blueprint(name).map_err(|e| e.to_string())?;
+        clipboard.set_blueprint(blueprint);
+    }
+    let blueprint = clipboard.blueprint().ok_or("the clipboard is empty")?;
+    let mut sim = simulation.lock().unwrap();
+    if position.0 >= sim.width || position.1 >= sim.height {
+        return Err(format!("({}, {}) is outside the world", position.0, position.1));
+    }
+    let mut tile_entities = tile_entities.lock().unwrap();
+    let cells = region_cells(blueprint.footprint(position.0, position.1), &sim);
+    let placed = history.lock().unwrap().edit(&mut sim, "paste", cells, |sim| {
+        blueprint.paste(sim, &mut tile_entities, position, mode)
+    });
+    info!("Pasted {} particles at {:?}", placed, position);
+    Ok(())
+}
+
+/// Cells of `region` that lie on the grid, as clients can send any corners they like
+fn region_cells(region: GridRect, simulation: &Simulation) -> impl Iterator<Item = (usize, usize)> {
+    let region = region.intersection(&GridRect::new(0, 0, simulation.width, simulation.height))
+        .unwrap_or(GridRect::new(0, 0, 0, 0));
+    (region.y..region.y + region.height)
+        .flat_map(move |y| (region.x..region.x + region.width).map(move |x| (x, y)))
+}
+
+fn load_simulation(name: &str, simulation: &Arc<Mutex<Simulation>>, tile_entities: &TileEntities, history: &History) -> Result<(), String> {
+    let save = save_manager(name)?.load_simulation(name).map_err(|e| e.to_string())?;
+    let mut sim = simulation.lock().unwrap();
+    save.apply_to(&mut sim).map_err(|e| e.to_string())?;
+    history.lock().unwrap().clear();
+    let mut tile_entities = tile_entities.lock().unwrap();
+    tile_entities.clear();
+    for tile_entity in save.tile_entities {
+        tile_entities.add_tile_entity(tile_entity);
+    }
+    info!("Loaded simulation '{}' at tick {}", name, sim.tick());
+    Ok(())
+}
+
+/// Negotiate the protocol for a client and reply with the agreed session
+fn handle_hello(
+    hello: &Hello,
+    client_id: u64,
+    tx: &tokio::sync::mpsc::UnboundedSender<Payload>,
+    clients: &Clients,
+    rules: &WorldRules,
 ) {
+    let session = match protocol::negotiate(hello, &server_features()) {
+        Ok(session) => session,
+        Err(e) => {
+            warn!("Rejected client {}: {}", client_id, e);
+            send_message(tx, &ServerMessage::HandshakeRejected {
+                reason: e.to_string(),
+                min_version: protocol::MIN_PROTOCOL_VERSION,
+                max_version: protocol::PROTOCOL_VERSION,
+            });
+            return;
+        }
+    };
+
+    info!("Client {} negotiated protocol v{} ({:?})", client_id, session.protocol_version, session.capabilities);
+    send_message(tx, &ServerMessage::Welcome {
+        client_id,
+        protocol_version: session.protocol_version,
+        capabilities: session.capabilities.clone(),
+        codec: session.codec,
+    });
+    // Advertise the world's physics rules
+    send_message(tx, &ServerMessage::WorldRules { rules: rules.clone() });
+
+    let mut clients_lock = clients.lock().unwrap();
+    if let Some(client) = clients_lock.iter_mut().find(|client| client.id == client_id) {
+        client.session = session;
+        if let Some(name) = hello.name.as_deref().and_then(player_name) {
+            client.player.name = name;
+        }
+    }
+    // Newly upgraded clients need the player list too
+    PLAYERS_CHANGED.store(true, Ordering::Relaxed);
+}
+
+/// The name a client asked for, cut to 32 characters with markup and control
+/// characters dropped, as other clients show it in their pages; None if nothing is left
+fn player_name(requested: &str) -> Option<String> {
+    let name: String = requested.chars()
+        .filter(|c| !c.is_control() && !matches!(c, '<' | '>' | '&' | '"' | '\''))
+        .collect::<String>()
+        .trim()
+        .chars()
+        .take(32)
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Send the full world to one client, as a binary grid frame or compressed with its
+/// negotiated codec
+fn send_snapshot(client_id: u64, clients: &Clients, simulation: &Arc<Mutex<Simulation>>) {
+    let Some((tx, session)) = clients.lock().unwrap().iter()
+        .find(|client| client.id == client_id)
+        .map(|client| (client.tx.clone(), client.session.clone()))
+    else {
+        return;
+    };
+    if session.supports(Capability::BinaryDeltas) {
+        if let Ok(frame) = GridFrame::full(&simulation.lock().unwrap()).encode() {
+            let _ = tx.send(Payload::Binary(frame));
+            return;
+        }
+    }
+    let message = create_simulation_state_message(&simulation.lock().unwrap());
+    if let Ok(json) = serde_json::to_string(&message) {
+        let _ = tx.send(Payload::encode(json, session.codec, compression_threshold()));
+    }
+}
+
+/// Answer a measurement request, logging each result to the telemetry
+fn handle_measurement(
+    message: ClientMessage,
+    tx: &tokio::sync::mpsc::UnboundedSender<Payload>,
+    simulation: &Simulation,
+    measurements: &mut Measurements,
+) {
+    let (tick, time) = (simulation.tick(), measurements.time);
     match message {
-        ClientMessage::Paint { x, y, material, brush_size } => {
-            
-            let mut sim = simulation.lock().unwrap();
-            
-            let start_x = x.saturating_sub(brush_size);
-            let end_x = (x + brush_size).min(sim.width.saturating_sub(1));
-            let start_y = y.saturating_sub(brush_size);
-            let end_y = (y + brush_size).min(sim.height.saturating_sub(1));
-            let brush_size_sq = brush_size * brush_size;
-            
-            let mut placed_count = 0;
-            for px in start_x..=end_x {
-                for py in start_y..=end_y {
-                    let dx = px as i32 - x as i32;
-                    let dy = py as i32 - y as i32;
-                    let dist_sq = (dx * dx + dy * dy) as usize;
-                    
-                    if dist_sq <= brush_size_sq {
-                        // Check if we can paint here (don't overwrite generators unless erasing)
-                        if let Some(existing_data) = sim.get_particle_data(px, py) {
-                            if existing_data.0 == MaterialType::Generator && material != MaterialType::Eraser {
-                                continue;
-                            }
-                        }
-                        
-                        if sim.add_particle(px, py, material, None) {
-                            placed_count += 1;
-                        }
+        ClientMessage::MeasureDistance { x0, y0, x1, y1 } => {
+            let distance = Distance::between((x0, y0), (x1, y1));
+            measurements.telemetry.record(tick, time, "ruler.distance", distance.euclidean());
+            send_message(tx, &ServerMessage::Distance {
+                dx: distance.dx,
+                dy: distance.dy,
+                euclidean: distance.euclidean(),
+                manhattan: distance.manhattan(),
+            });
+        }
+        ClientMessage::CountRegion { x, y, width, height } => {
+            let count = RegionCount::of(simulation, GridRect::new(x, y, width, height));
+            count.record(simulation, &mut measurements.telemetry, "region", time);
+            send_message(tx, &ServerMessage::RegionCount {
+                region: ChunkRegion { x, y, width, height },
+                total: count.total,
+                counts: count.by_material.iter()
+                    .map(|&(material, count)| MaterialCount { material, count })
+                    .collect(),
+            });
+        }
+        ClientMessage::TrackVolume { x, y } => {
+            match Cavity::find(simulation, x, y, sand_engine::measure::DEFAULT_MAX_CAVITY_CELLS) {
+                Some(cavity) => {
+                    measurements.tracker = Some(VolumeTracker::new(cavity));
+                    if let Some(message) = sample_volume(simulation, measurements) {
+                        send_message(tx, &message);
                     }
                 }
+                None => send_message(tx, &ServerMessage::Error {
+                    message: "No enclosed cavity there; click inside a container".to_string(),
+                }),
             }
-            
+        }
+        ClientMessage::StopTrackingVolume => {
+            measurements.tracker = None;
+        }
+        ClientMessage::ExportTelemetry => {
+            if let Some(path) = std::env::var_os("SAND_TELEMETRY_CSV") {
+                match measurements.telemetry.save_csv(&path) {
+                    Ok(()) => info!("Saved telemetry to {}", PathBuf::from(&path).display()),
+                    Err(e) => warn!("Failed to save telemetry to {}: {}", PathBuf::from(&path).display(), e),
+                }
+            }
+            send_message(tx, &ServerMessage::Telemetry { csv: measurements.telemetry.to_csv() });
+        }
+        _ => {}
+    }
+}
+
+/// Sample the tracked cavity, if any, into the telemetry
+fn sample_volume(simulation: &Simulation, measurements: &mut Measurements) -> Option<ServerMessage> {
+    let tracker = measurements.tracker.as_mut()?;
+    let filled = tracker.sample(simulation, measurements.time);
+    tracker.record(simulation.tick(), &mut measurements.telemetry, "volume");
+    Some(ServerMessage::VolumeUpdate {
+        capacity: tracker.cavity.capacity(),
+        filled,
+        flow_rate: tracker.flow_rate(),
+    })
+}
+
+fn signs_message(tile_entities: &TileEntityManager) -> ServerMessage {
+    ServerMessage::Signs {
+        signs: tile_entities.signs()
+            .map(|((x, y), text)| SignInfo { x, y, text: text.to_string() })
+            .collect(),
+    }
+}
+
+async fn broadcast_signs(clients: &Clients, tile_entities: &TileEntities) {
+    let message = signs_message(&tile_entities.lock().unwrap());
+    broadcast_to_clients(clients, &message).await;
+}
+
+/// Put a sign at `(x, y)`, rewriting an existing one; anything else there is replaced
+fn place_sign(tile_entities: &TileEntities, x: usize, y: usize, text: &str) {
+    let position = (x as i64, y as i64);
+    let mut tile_entities = tile_entities.lock().unwrap();
+    if let Some(sign) = tile_entities.get_tile_entity_mut(position) {
+        if sign.set_sign_text(text) {
+            return;
+        }
+    }
+    tile_entities.add_tile_entity(TileEntity::new_sign(position, text));
+}
+
+/// Send each client subscribed to a region the changes inside it
+fn send_subscribed_deltas(clients: &Clients, simulation: &Simulation) {
+    let threshold = compression_threshold();
+    let mut clients_lock = clients.lock().unwrap();
+    for client in clients_lock.iter_mut() {
+        let Some(subscription) = &mut client.subscription else {
+            continue;
+        };
+        let Some(message) = create_delta_update(simulation, &mut subscription.sync, subscription.region) else {
+            continue;
+        };
+        let payload = client.session.supports(Capability::BinaryDeltas)
+            .then(|| encode_grid_frame(&message))
+            .flatten()
+            .or_else(|| serde_json::to_string(&message).ok().map(|json| Payload::encode(json, client.session.codec, threshold)));
+        if let Some(payload) = payload {
+            // Disconnected clients are dropped by the next broadcast
+            let _ = client.tx.send(payload);
+        }
+    }
+}
+
+/// Send each client subscribed to a region that negotiated `effects` the ones inside it
+fn send_subscribed_effects(clients: &Clients, effects: &[Effect]) {
+    let mut clients_lock = clients.lock().unwrap();
+    for client in clients_lock.iter_mut() {
+        if !client.session.supports(Capability::Effects) {
+            continue;
+        }
+        let Some(subscription) = &mut client.subscription else {
+            continue;
+        };
+        let effects: Vec<Effect> = effects.iter()
+            .filter(|effect| {
+                let (x, y) = effect.cell();
+                x >= 0 && y >= 0 && subscription.region.contains(x as usize, y as usize)
+            })
+            .copied()
+            .collect();
+        if effects.is_empty() && !subscription.effects_shown {
+            continue;
+        }
+        subscription.effects_shown = !effects.is_empty();
+        if let Ok(json) = serde_json::to_string(&ServerMessage::Effects { effects }) {
+            // Disconnected clients are dropped by the next broadcast
+            let _ = client.tx.send(Payload::Text(json));
+        }
+    }
+}
+
+/// Keep `client_id` in sync with only `region`, starting with a keyframe of all of it
+fn subscribe_region(clients: &Clients, client_id: u64, region: GridRect, tick: u64) {
+    let mut clients_lock = clients.lock().unwrap();
+    if let Some(client) = clients_lock.iter_mut().find(|client| client.id == client_id) {
+        client.subscription = Some(Subscription {
+            region,
+            sync: SimulationState { last_tick: tick, resync: true, ..SimulationState::default() },
+            effects_shown: false,
+        });
+    }
+}
+
+/// The message as a binary grid frame, if it carries grid state that fits the format
+fn encode_grid_frame(message: &ServerMessage) -> Option<Payload> {
+    let frame = message.grid_frame()?.encode();
+    if let Err(e) = &frame {
+        warn!("Falling back to JSON: {}", e);
+    }
+    frame.ok().map(Payload::Binary)
+}
+
+/// Apply a pause, resume, step or speed change; returns the resulting state
+fn handle_time_control(message: ClientMessage, time: &mut TimeControl) -> TimeControlState {
+    match message {
+        ClientMessage::Pause => time.pause(),
+        ClientMessage::Resume => time.resume(),
+        ClientMessage::Step { ticks } => time.step(ticks),
+        ClientMessage::SetSpeed { speed } => time.set_speed(speed),
+        _ => {}
+    }
+    time.state()
+}
+
+fn send_message(tx: &tokio::sync::mpsc::UnboundedSender<Payload>, message: &ServerMessage) {
+    if let Ok(json) = serde_json::to_string(message) {
+        let _ = tx.send(Payload::Text(json));
+    }
+}
+
+async fn handle_client_message(
+    message: ClientMessage,
+    client_id: u64,
+    simulation: &Arc<Mutex<Simulation>>,
+    history: &History,
+    autosave: &SharedAutosave,
+    critters: &Critters,
+) {
+    match message {
+        ClientMessage::Paint { x, y, material, brush_size, brush } => {
+            let mut sim = simulation.lock().unwrap();
+            let brush = brush.unwrap_or(Brush::circle(brush_size));
+            // Paints join the client's open stroke until it sends end_stroke
+            history.lock().unwrap().apply_brush_as(client_id, &mut sim, &brush, (x, y), material);
+            journal(autosave, JournalEntry::Paint { x, y, material, brush });
+        }
+        ClientMessage::EndStroke => {
+            let sim = simulation.lock().unwrap();
+            history.lock().unwrap().end_stroke(client_id, &sim);
+        }
+        ClientMessage::Undo => {
+            let mut sim = simulation.lock().unwrap();
+            let mut history = history.lock().unwrap();
+            history.end_stroke(client_id, &sim);
+            history.undo(&mut sim);
+            save_autosave_soon(autosave);
+        }
+        ClientMessage::Redo => {
+            let mut sim = simulation.lock().unwrap();
+            let mut history = history.lock().unwrap();
+            history.end_stroke(client_id, &sim);
+            history.redo(&mut sim);
+            save_autosave_soon(autosave);
         }
         ClientMessage::Clear => {
             let mut sim = simulation.lock().unwrap();
+            let mut history = history.lock().unwrap();
+            let everything = GridRect::new(0, 0, sim.width, sim.height);
+            history.begin_edit(&sim, "clear");
+            history.record_region(&sim, everything);
             sim.clear();
-        }
-        ClientMessage::GetParticle { x: _, y: _ } => {
-            // For now, we'll just ignore this since we're broadcasting full state
-            // In a more optimized version, we'd send individual particle info
+            history.end_edit(&sim);
+            journal(autosave, JournalEntry::Clear);
+            *critters.lock().unwrap() = ECS::new();
         }
         ClientMessage::PlaceStructure { structure_name, x, y } => {
             let mut sim = simulation.lock().unwrap();
             
             // Try to place the structure
-            match sand_engine::Structure::get_by_name(&structure_name) {
+            match Structure::get_by_name(&structure_name).or_else(|| user_structures().get(&structure_name).cloned()) {
                 Some(structure) => {
-                    // Convert coordinates to world coordinates
-                    let world_x = x as i64;
-                    let world_y = y as i64;
-                    
                     // For now, we'll just add the structure particles to the simulation
                     // In a more complete implementation, we'd use the chunk manager
-                    let mut particles_placed = 0;
+                    let cells: Vec<(usize, usize)> = structure.particles.iter()
+                        .map(|particle_data| ((x as i64 + particle_data.x as i64) as usize, (y as i64 + particle_data.y as i64) as usize))
+                        .collect();
                     
-                    for particle_data in &structure.particles {
-                        let particle_x = (world_x + particle_data.x as i64) as usize;
-                        let particle_y = (world_y + particle_data.y as i64) as usize;
-                        
-                        // Check bounds
-                        if particle_x < sim.width && particle_y < sim.height {
-                            if sim.add_particle(particle_x, particle_y, particle_data.material, particle_data.temp) {
-                                particles_placed += 1;
-                            }
-                        }
-                    }
+                    let particles_placed = history.lock().unwrap().edit(&mut sim, "structure", cells.clone(), |sim| {
+                        cells.iter().zip(&structure.particles)
+                            .filter(|&(&(particle_x, particle_y), particle_data)| {
+                                particle_x < sim.width && particle_y < sim.height
+                                    && sim.add_particle(particle_x, particle_y, particle_data.material, particle_data.temp)
+                            })
+                            .count()
+                    });
+                    let placed = cells.iter().zip(&structure.particles)
+                        .map(|(&(particle_x, particle_y), particle_data)| (particle_x, particle_y, particle_data.material, particle_data.temp))
+                        .collect();
+                    journal(autosave, JournalEntry::Place { cells: placed });
+                    // Centred on the same cells the particles went into
+                    let (center_x, center_y) = (x as i64 + structure.width as i64 / 2, y as i64 + structure.height as i64 / 2);
+                    structure.spawn_critters(center_x, center_y, &mut critters.lock().unwrap());
                     
                     println!("Placed structure '{}' at ({}, {}) with {} particles", 
                              structure_name, x, y, particles_placed);
@@ -634,25 +2074,33 @@ async fn handle_client_message(
                 }
             }
         }
+        ClientMessage::Hello(_) | ClientMessage::RequestSnapshot
+        | ClientMessage::PlaceSign { .. } | ClientMessage::RemoveSign { .. }
+        | ClientMessage::MeasureDistance { .. } | ClientMessage::CountRegion { .. }
+        | ClientMessage::TrackVolume { .. } | ClientMessage::StopTrackingVolume
+        | ClientMessage::ExportTelemetry
+        | ClientMessage::SaveSimulation { .. } | ClientMessage::LoadSimulation { .. }
+        | ClientMessage::SaveStructure { .. } | ClientMessage::GetParticle { .. }
+        | ClientMessage::Cursor { .. }
+        | ClientMessage::SubscribeRegion { .. } | ClientMessage::UnsubscribeRegion
+        | ClientMessage::Copy { .. } | ClientMessage::Paste { .. }
+        | ClientMessage::Pause | ClientMessage::Resume
+        | ClientMessage::Step { .. } | ClientMessage::SetSpeed { .. }
+        | ClientMessage::StartCapture { .. } => {
+            // Handshakes, snapshots, signs, measurements, saves, cursors, subscriptions and the clipboard are handled per connection in handle_websocket
+        }
     }
 }
 
-fn get_materials_info() -> Vec<MaterialInfo> {
-    use sand_engine::materials::get_material_properties;
-    
-    let materials = [
-        MaterialType::Sand, MaterialType::Water, MaterialType::Stone, MaterialType::Plant,
-        MaterialType::Fire, MaterialType::Lava, MaterialType::Glass, MaterialType::Steam,
-        MaterialType::Oil, MaterialType::Acid, MaterialType::Coal, MaterialType::Gunpowder,
-        MaterialType::Ice, MaterialType::Wood, MaterialType::Smoke, MaterialType::ToxicGas,
-        MaterialType::Slime, MaterialType::Gasoline, MaterialType::Generator, MaterialType::Fuse,
-        MaterialType::Ash, MaterialType::Gold, MaterialType::Iron, MaterialType::Eraser,
-    ];
-    
-    materials.iter().map(|&material_type| {
-        let props = get_material_properties(material_type);
+fn get_materials_info(simulation: &Simulation) -> Vec<MaterialInfo> {
+    let materials = simulation.materials();
+    materials.material_types().filter(|&material_type| {
+        material_type != MaterialType::Empty && simulation.is_material_enabled(material_type)
+    }).map(|material_type| {
+        let props = materials.get(material_type);
         MaterialInfo {
             id: material_type,
+            code: protocol::material_code(material_type),
             name: props.name.clone(),
             color: props.base_color,
             density: props.density,
@@ -665,10 +2113,9 @@ fn get_materials_info() -> Vec<MaterialInfo> {
     }).collect()
 }
 
+/// Built-in structures followed by the ones clients saved
 fn get_structures_info() -> Vec<StructureInfo> {
-    use sand_engine::Structure;
-    
-    Structure::get_all_structures().iter().map(|structure| {
+    Structure::get_all_structures().iter().chain(&user_structures().structures).map(|structure| {
         StructureInfo {
             name: structure.name.clone(),
             width: structure.width,
@@ -677,4 +2124,161 @@ fn get_structures_info() -> Vec<StructureInfo> {
             tile_entity_count: structure.tile_entities.len(),
         }
     }).collect()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sand_engine::EffectKind;
+
+    #[test]
+    fn test_cutting_an_oversized_region_only_touches_the_grid() {
+        let simulation = Arc::new(Mutex::new(Simulation::new(16, 16)));
+        let tile_entities: TileEntities = Arc::new(Mutex::new(TileEntityManager::new()));
+        let history: History = Arc::new(Mutex::new(UndoManager::new()));
+        simulation.lock().unwrap().add_particle(4, 4, MaterialType::Stone, None);
+
+        let mut clipboard = Clipboard::new();
+        let region = GridRect::new(2, 2, usize::MAX, usize::MAX);
+        let blueprint = copy_region(&mut clipboard, region, true, None, &simulation, &tile_entities, &history).unwrap();
+        assert_eq!(blueprint.particles.len(), 1);
+        assert!(simulation.lock().unwrap().get_particle(4, 4).is_none());
+
+        let mut sim = simulation.lock().unwrap();
+        assert!(history.lock().unwrap().undo(&mut sim));
+        assert!(sim.get_particle(4, 4).is_some());
+    }
+
+    #[test]
+    fn test_pasting_off_the_grid_is_rejected() {
+        let simulation = Arc::new(Mutex::new(Simulation::new(16, 16)));
+        let tile_entities: TileEntities = Arc::new(Mutex::new(TileEntityManager::new()));
+        let history: History = Arc::new(Mutex::new(UndoManager::new()));
+        simulation.lock().unwrap().add_particle(0, 0, MaterialType::Stone, None);
+        let mut clipboard = Clipboard::new();
+        copy_region(&mut clipboard, GridRect::new(0, 0, 2, 2), false, None, &simulation, &tile_entities, &history).unwrap();
+
+        let far = (usize::MAX - 1, 3);
+        assert!(paste_blueprint(&mut clipboard, far, PasteMode::Replace, None, &simulation, &tile_entities, &history).is_err());
+        assert!(!simulation.is_poisoned() && !history.is_poisoned());
+        paste_blueprint(&mut clipboard, (15, 15), PasteMode::Merge, None, &simulation, &tile_entities, &history).unwrap();
+        assert!(simulation.lock().unwrap().get_particle(15, 15).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_placed_aquariums_bring_their_fish() {
+        let simulation = Arc::new(Mutex::new(Simulation::new(32, 32)));
+        let history: History = Arc::new(Mutex::new(UndoManager::new()));
+        let autosave: SharedAutosave = Arc::new(Mutex::new(None));
+        let critters: Critters = Arc::new(Mutex::new(ECS::new()));
+        let place = ClientMessage::PlaceStructure { structure_name: "Aquarium".to_string(), x: 10, y: 20 };
+        handle_client_message(place, 0, &simulation, &history, &autosave, &critters).await;
+        assert_eq!(critter_infos(&critters.lock().unwrap()).len(), 3);
+
+        for _ in 0..120 {
+            let mut sim = simulation.lock().unwrap();
+            sim.update(1.0 / 60.0);
+            update_critters(&sim, &mut critters.lock().unwrap(), 1.0 / 60.0);
+        }
+        let fish = critter_infos(&critters.lock().unwrap());
+        assert_eq!(fish.len(), 3);
+        let sim = simulation.lock().unwrap();
+        for critter in fish {
+            assert_eq!(critter.kind, CritterKind::Fish);
+            let cell = sim.get_particle(critter.x as usize, critter.y as usize);
+            assert_eq!(cell.map(|particle| particle.material_type), Some(MaterialType::Water));
+        }
+
+        drop(sim);
+        handle_client_message(ClientMessage::Clear, 0, &simulation, &history, &autosave, &critters).await;
+        assert!(critter_infos(&critters.lock().unwrap()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clients_only_hear_about_their_subscribed_region() {
+        let mut simulation = Simulation::new(32, 8);
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let mut receivers = Vec::new();
+        for id in 0..2 {
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            clients.lock().unwrap().push(Client { id, tx, session: Session::legacy(), player: PlayerInfo::new(id), subscription: None });
+            receivers.push(rx);
+        }
+        subscribe_region(&clients, 0, GridRect::new(0, 0, 8, 8), simulation.tick());
+        subscribe_region(&clients, 1, GridRect::new(24, 0, 8, 8), simulation.tick());
+        send_subscribed_deltas(&clients, &simulation);
+        for rx in &mut receivers {
+            assert!(rx.try_recv().is_ok());
+        }
+
+        simulation.add_particle(2, 2, MaterialType::Stone, None);
+        simulation.update(1.0 / 60.0);
+        let mut state = SimulationState::default();
+        let world_delta = create_delta_update(&simulation, &mut state, GridRect::new(0, 0, 32, 8)).unwrap();
+        broadcast_to_clients(&clients, &world_delta).await;
+        send_subscribed_deltas(&clients, &simulation);
+        assert!(receivers[0].try_recv().is_ok());
+        assert!(receivers[0].try_recv().is_err());
+        // Subscribed elsewhere, so neither the world delta nor the other region reaches it
+        assert!(receivers[1].try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_clients_only_see_effects_in_their_region() {
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let session = Session { protocol_version: 1, capabilities: vec![Capability::Effects], codec: None };
+        let mut receivers = Vec::new();
+        for id in 0..3 {
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            clients.lock().unwrap().push(Client { id, tx, session: session.clone(), player: PlayerInfo::new(id), subscription: None });
+            receivers.push(rx);
+        }
+        subscribe_region(&clients, 0, GridRect::new(0, 0, 8, 8), 0);
+        subscribe_region(&clients, 1, GridRect::new(24, 0, 8, 8), 0);
+        let spark = Effect { kind: EffectKind::Spark, x: 3.5, y: 2.5, vx: 0.0, vy: 0.0, life: 1.0, lifetime: 1.0 };
+
+        send_subscribed_effects(&clients, &[spark]);
+        broadcast_to_clients(&clients, &ServerMessage::Effects { effects: vec![spark] }).await;
+        // Client 0 gets the spark in its region and client 2, subscribed nowhere, the broadcast
+        for id in [0, 2] {
+            assert!(matches!(receivers[id].try_recv(), Ok(Payload::Text(json)) if json.contains("spark")));
+            assert!(receivers[id].try_recv().is_err());
+        }
+        // The spark is outside client 1's region, so it hears nothing
+        assert!(receivers[1].try_recv().is_err());
+
+        // Once the spark dies client 0 is told to clear it, and only once
+        send_subscribed_effects(&clients, &[]);
+        send_subscribed_effects(&clients, &[]);
+        assert!(matches!(receivers[0].try_recv(), Ok(Payload::Text(json)) if json.contains("[]")));
+        assert!(receivers[0].try_recv().is_err());
+        assert!(receivers[1].try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ending_a_stroke_leaves_other_clients_painting() {
+        let simulation = Arc::new(Mutex::new(Simulation::new(16, 16)));
+        let history: History = Arc::new(Mutex::new(UndoManager::new()));
+        let autosave: SharedAutosave = Arc::new(Mutex::new(None));
+        let critters: Critters = Arc::new(Mutex::new(ECS::new()));
+        let paint = |x| ClientMessage::Paint { x, y: 8, material: MaterialType::Stone, brush_size: 0, brush: None };
+        handle_client_message(paint(2), 1, &simulation, &history, &autosave, &critters).await;
+        handle_client_message(paint(10), 2, &simulation, &history, &autosave, &critters).await;
+        handle_client_message(ClientMessage::EndStroke, 1, &simulation, &history, &autosave, &critters).await;
+        handle_client_message(paint(11), 2, &simulation, &history, &autosave, &critters).await;
+
+        // Client 2's undo takes back its whole stroke and leaves client 1's finished one
+        handle_client_message(ClientMessage::Undo, 2, &simulation, &history, &autosave, &critters).await;
+        let sim = simulation.lock().unwrap();
+        assert!(sim.get_particle(2, 8).is_some());
+        assert!(sim.get_particle(10, 8).is_none() && sim.get_particle(11, 8).is_none());
+    }
+
+    #[test]
+    fn test_player_names_drop_markup() {
+        assert_eq!(player_name("  <img src=x onerror=alert(1)>Bob "), Some("img src=x onerror=alert(1)Bob".to_string()));
+        assert_eq!(player_name("Tom & \"Jerry\""), Some("Tom  Jerry".to_string()));
+        assert_eq!(player_name(" <> "), None);
+        assert_eq!(player_name(&"x".repeat(40)).unwrap().len(), 32);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::materials::MaterialType;
+use crate::simulation::{Simulation, SIM_CHUNK_SIZE};
+use crate::world_generation::{BiomeType, WorldGenerator};
+use rand::Rng;
+
+/// Simulated seconds between background biome passes
+pub const BIOME_PASS_INTERVAL: f32 = 2.0;
+/// Chance per second that a surface cell of a shallow desert pool evaporates
+pub const DESERT_EVAPORATION_PER_SEC: f32 = 0.05;
+/// Deepest pool, in cells, that desert heat dries up
+pub const DESERT_PUDDLE_DEPTH: usize = 2;
+/// Chance per second that open water in the tundra freezes
+pub const TUNDRA_FREEZE_PER_SEC: f32 = 0.1;
+/// Temperature tundra ice forms at
+pub const TUNDRA_ICE_TEMP: f32 = -10.0;
+/// How many times faster plants grow in the jungle
+pub const JUNGLE_GROWTH_MULTIPLIER: f32 = 2.0;
+
+/// One biome per simulation chunk, queried by cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct BiomeMap {
+    chunks_x: usize,
+    chunks_y: usize,
+    biomes: Vec<BiomeType>,
+}
+
+impl BiomeMap {
+    /// A `width` x `height` grid's worth of chunks all in `biome`
+    pub fn new(width: usize, height: usize, biome: BiomeType) -> Self {
+        let chunks_x = width.div_ceil(SIM_CHUNK_SIZE);
+        let chunks_y = height.div_ceil(SIM_CHUNK_SIZE);
+        Self { chunks_x, chunks_y, biomes: vec![biome; chunks_x * chunks_y] }
+    }
+
+    /// Biomes the generator would give a `width` x `height` grid, sampled at the
+    /// centre of each chunk
+    pub fn from_generator(generator: &WorldGenerator, width: usize, height: usize) -> Self {
+        let mut map = Self::new(width, height, BiomeType::Plains);
+        for chunk_y in 0..map.chunks_y {
+            for chunk_x in 0..map.chunks_x {
+                let centre = |chunk: usize| (chunk * SIM_CHUNK_SIZE + SIM_CHUNK_SIZE / 2) as i64;
+                map.biomes[chunk_y * map.chunks_x + chunk_x] = generator.biome_at(centre(chunk_x), centre(chunk_y));
+            }
+        }
+        map
+    }
+
+    /// Chunks across and down
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.chunks_x, self.chunks_y)
+    }
+
+    pub fn chunk_biome(&self, chunk_x: usize, chunk_y: usize) -> Option<BiomeType> {
+        (chunk_x < self.chunks_x && chunk_y < self.chunks_y).then(|| self.biomes[chunk_y * self.chunks_x + chunk_x])
+    }
+
+    /// Change one chunk's biome, returning false if it is outside the map
+    pub fn set_chunk_biome(&mut self, chunk_x: usize, chunk_y: usize, biome: BiomeType) -> bool {
+        if chunk_x >= self.chunks_x || chunk_y >= self.chunks_y {
+            return false;
+        }
+        self.biomes[chunk_y * self.chunks_x + chunk_x] = biome;
+        true
+    }
+
+    /// Biome of the chunk holding cell `(x, y)`
+    pub fn biome_at(&self, x: usize, y: usize) -> Option<BiomeType> {
+        self.chunk_biome(x / SIM_CHUNK_SIZE, y / SIM_CHUNK_SIZE)
+    }
+}
+
+impl Simulation {
+    /// Give every chunk a biome and let it shape the world on a slow background pass:
+    /// deserts dry up shallow pools, the tundra freezes open water and jungle plants
+    /// grow faster
+    pub fn enable_biomes(&mut self, biomes: BiomeMap) {
+        self.biomes = Some(biomes);
+    }
+
+    pub fn disable_biomes(&mut self) {
+        self.biomes = None;
+    }
+
+    /// The biome map; None while biomes are off
+    pub fn biome_map(&self) -> Option<&BiomeMap> {
+        self.biomes.as_ref()
+    }
+
+    /// Biome of the chunk holding `(x, y)`; None while biomes are off
+    pub fn biome_at(&self, x: usize, y: usize) -> Option<BiomeType> {
+        self.biomes.as_ref()?.biome_at(x, y)
+    }
+
+    /// Runs every `BIOME_PASS_INTERVAL` seconds with the time since the last pass,
+    /// including over sleeping chunks
+    pub(crate) fn apply_biomes(&mut self, elapsed: f32) {
+        let Some(biomes) = self.biomes.take() else {
+            return;
+        };
+
+        // Collected up front so cells changed this pass wait for the next one
+        let open_water: Vec<_> = self.iter_particles()
+            .filter(|particle| particle.material_type == MaterialType::Water)
+            .filter(|particle| particle.y == 0 || self.get_particle(particle.x, particle.y - 1).is_none())
+            .filter_map(|particle| Some((particle.x, particle.y, biomes.biome_at(particle.x, particle.y)?)))
+            .collect();
+
+        for (x, y, biome) in open_water {
+            match biome {
+                BiomeType::Desert if self.pool_depth(x, y) <= DESERT_PUDDLE_DEPTH
+                    && self.rng.gen::<f32>() < DESERT_EVAPORATION_PER_SEC * elapsed => {
+                    self.remove_particle(x, y);
+                }
+                BiomeType::Tundra if self.rng.gen::<f32>() < TUNDRA_FREEZE_PER_SEC * elapsed => {
+                    self.remove_particle(x, y);
+                    self.add_particle(x, y, MaterialType::Ice, Some(TUNDRA_ICE_TEMP));
+                }
+                _ => {}
+            }
+        }
+
+        // Ordinary growth already ran; jungle plants get the rest of their share
+        let extra = (JUNGLE_GROWTH_MULTIPLIER - 1.0) * elapsed;
+        if extra > 0.0 && biomes.biomes.contains(&BiomeType::Jungle) {
+            self.grow_plants_where(extra, |x, y| biomes.biome_at(x, y) == Some(BiomeType::Jungle));
+        }
+
+        self.biomes = Some(biomes);
+    }
+
+    /// Cells of water from `(x, y)` straight down, stopping once it is deeper than a puddle
+    fn pool_depth(&self, x: usize, y: usize) -> usize {
+        (y..self.height)
+            .take(DESERT_PUDDLE_DEPTH + 1)
+            .take_while(|&y| self.get_particle(x, y).is_some_and(|p| p.material_type == MaterialType::Water))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_biome_map_follows_the_generator() {
+        let generator = WorldGenerator::new(11);
+        let map = BiomeMap::from_generator(&generator, 40, 20);
+        assert_eq!(map.dimensions(), (3, 2));
+        assert_eq!(map.biome_at(20, 5), Some(generator.biome_at(24, 8)));
+        assert_eq!(map.biome_at(48, 0), None);
+
+        let mut map = BiomeMap::new(40, 20, BiomeType::Plains);
+        assert!(map.set_chunk_biome(2, 1, BiomeType::Tundra));
+        assert!(!map.set_chunk_biome(3, 0, BiomeType::Tundra));
+        assert_eq!(map.biome_at(39, 19), Some(BiomeType::Tundra));
+        assert_eq!(map.chunk_biome(0, 0), Some(BiomeType::Plains));
+    }
+
+    #[test]
+    fn test_deserts_dry_puddles_and_tundra_freezes_water() {
+        let mut simulation = Simulation::new(32, 16);
+        simulation.set_seed(5);
+        for x in 0..32 {
+            simulation.add_particle(x, 15, MaterialType::Stone, None);
+        }
+        // A puddle in the desert, a deep tank beside it, and a pond in the tundra
+        for x in 2..6 {
+            simulation.add_particle(x, 14, MaterialType::Water, None);
+        }
+        for y in 6..15 {
+            simulation.add_particle(10, y, MaterialType::Stone, None);
+            simulation.add_particle(12, y, MaterialType::Water, None);
+            simulation.add_particle(14, y, MaterialType::Stone, None);
+        }
+        for x in 20..28 {
+            simulation.add_particle(x, 14, MaterialType::Water, None);
+        }
+
+        let mut biomes = BiomeMap::new(32, 16, BiomeType::Desert);
+        biomes.set_chunk_biome(1, 0, BiomeType::Tundra);
+        simulation.enable_biomes(biomes);
+        assert_eq!(simulation.biome_at(20, 14), Some(BiomeType::Tundra));
+
+        for _ in 0..240 {
+            simulation.apply_biomes(BIOME_PASS_INTERVAL);
+        }
+        let water_in = |simulation: &Simulation, xs: std::ops::Range<usize>| {
+            simulation.iter_particles().filter(|p| p.material_type == MaterialType::Water && xs.contains(&p.x)).count()
+        };
+        assert_eq!(water_in(&simulation, 0..10), 0);
+        assert_eq!(water_in(&simulation, 11..14), 9);
+        let ice = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Ice).count();
+        assert_eq!(ice, 8);
+
+        simulation.disable_biomes();
+        assert_eq!(simulation.biome_at(20, 14), None);
+    }
+}
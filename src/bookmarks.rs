@@ -0,0 +1,229 @@
+use crate::render::Camera;
+use serde::{Deserialize, Serialize};
+
+/// Seconds a tour spends moving between two bookmarks
+pub const DEFAULT_TOUR_LEG_SECONDS: f32 = 3.0;
+/// Seconds a tour rests at each bookmark before moving on
+pub const DEFAULT_TOUR_HOLD_SECONDS: f32 = 1.5;
+
+/// A named camera position and zoom
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+}
+
+impl CameraBookmark {
+    pub fn new(name: impl Into<String>, x: f32, y: f32, zoom: f32) -> Self {
+        Self { name: name.into(), x, y, zoom }
+    }
+
+    /// Bookmark where `camera` is looking now
+    pub fn from_camera(name: impl Into<String>, camera: &Camera) -> Self {
+        Self::new(name, camera.x as f32, camera.y as f32, camera.zoom)
+    }
+
+    /// Move `camera` to the bookmark
+    pub fn apply(&self, camera: &mut Camera) {
+        camera.x = self.x.round() as i32;
+        camera.y = self.y.round() as i32;
+        camera.set_zoom(self.zoom);
+    }
+
+    /// The view `t` (0..1) of the way from `self` to `other`
+    fn lerp(&self, other: &CameraBookmark, t: f32) -> CameraBookmark {
+        CameraBookmark {
+            name: other.name.clone(),
+            x: self.x + (other.x - self.x) * t,
+            y: self.y + (other.y - self.y) * t,
+            zoom: self.zoom + (other.zoom - self.zoom) * t,
+        }
+    }
+}
+
+/// A world's bookmarks, in the order they were added; names are unique
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CameraBookmarks {
+    bookmarks: Vec<CameraBookmark>,
+}
+
+impl CameraBookmarks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a bookmark, replacing any with the same name in place
+    pub fn set(&mut self, bookmark: CameraBookmark) {
+        match self.bookmarks.iter_mut().find(|existing| existing.name == bookmark.name) {
+            Some(existing) => *existing = bookmark,
+            None => self.bookmarks.push(bookmark),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<CameraBookmark> {
+        let index = self.index_of(name)?;
+        Some(self.bookmarks.remove(index))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CameraBookmark> {
+        self.bookmarks.iter().find(|bookmark| bookmark.name == name)
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.bookmarks.iter().position(|bookmark| bookmark.name == name)
+    }
+
+    /// Bookmark `step` places after `name` (negative goes back), wrapping around; the
+    /// first bookmark when `name` is None or unknown
+    pub fn cycle(&self, name: Option<&str>, step: isize) -> Option<&CameraBookmark> {
+        if self.bookmarks.is_empty() {
+            return None;
+        }
+        let Some(index) = name.and_then(|name| self.index_of(name)) else {
+            return self.bookmarks.first();
+        };
+        let len = self.bookmarks.len() as isize;
+        self.bookmarks.get((index as isize + step).rem_euclid(len) as usize)
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &CameraBookmark> + '_ {
+        self.bookmarks.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.bookmarks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+    }
+}
+
+/// Moves a camera through a list of bookmarks, easing in and out of each and resting
+/// at it for `hold_seconds`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CameraTour {
+    stops: Vec<CameraBookmark>,
+    pub leg_seconds: f32,
+    pub hold_seconds: f32,
+    /// Go back to the first stop after the last instead of finishing
+    pub looping: bool,
+    elapsed: f32,
+}
+
+impl CameraTour {
+    /// Tour over `stops` in order; None when there are none
+    pub fn new(stops: Vec<CameraBookmark>) -> Option<Self> {
+        (!stops.is_empty()).then_some(Self {
+            stops,
+            leg_seconds: DEFAULT_TOUR_LEG_SECONDS,
+            hold_seconds: DEFAULT_TOUR_HOLD_SECONDS,
+            looping: false,
+            elapsed: 0.0,
+        })
+    }
+
+    /// Tour over every bookmark in `bookmarks`
+    pub fn through(bookmarks: &CameraBookmarks) -> Option<Self> {
+        Self::new(bookmarks.iter().cloned().collect())
+    }
+
+    fn legs(&self) -> usize {
+        if self.looping { self.stops.len() } else { self.stops.len() - 1 }
+    }
+
+    fn total_seconds(&self) -> f32 {
+        self.hold_seconds + self.legs() as f32 * (self.leg_seconds + self.hold_seconds)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.elapsed >= self.total_seconds()
+    }
+
+    /// The view at the current point of the tour
+    pub fn current(&self) -> CameraBookmark {
+        let segment = self.leg_seconds + self.hold_seconds;
+        // The tour opens with a hold at the first stop, then repeats leg + hold
+        let mut into_tour = (self.elapsed.min(self.total_seconds()) - self.hold_seconds).max(0.0);
+        if self.looping && segment > 0.0 {
+            into_tour = (self.elapsed - self.hold_seconds).max(0.0) % (self.legs() as f32 * segment);
+        }
+        let leg = if segment > 0.0 { (into_tour / segment) as usize } else { self.legs() };
+        if leg >= self.legs() {
+            return self.stops[self.legs() % self.stops.len()].clone();
+        }
+
+        let from = &self.stops[leg];
+        let to = &self.stops[(leg + 1) % self.stops.len()];
+        let t = ((into_tour - leg as f32 * segment) / self.leg_seconds.max(f32::EPSILON)).min(1.0);
+        from.lerp(to, smoothstep(t))
+    }
+
+    /// Advance the tour and move `camera` along it; returns false once it has finished
+    pub fn update(&mut self, delta_time: f32, camera: &mut Camera) -> bool {
+        self.elapsed += delta_time;
+        self.current().apply(camera);
+        !self.is_finished()
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bookmarks() -> CameraBookmarks {
+        let mut bookmarks = CameraBookmarks::new();
+        bookmarks.set(CameraBookmark::new("start", 0.0, 0.0, 1.0));
+        bookmarks.set(CameraBookmark::new("castle", 100.0, 40.0, 2.0));
+        bookmarks
+    }
+
+    #[test]
+    fn test_bookmarks_replace_and_cycle() {
+        let mut bookmarks = bookmarks();
+        bookmarks.set(CameraBookmark::new("start", 10.0, 5.0, 1.0));
+        assert_eq!(bookmarks.len(), 2);
+        assert_eq!(bookmarks.get("start").unwrap().x, 10.0);
+
+        assert_eq!(bookmarks.cycle(None, 1).unwrap().name, "start");
+        assert_eq!(bookmarks.cycle(Some("start"), 1).unwrap().name, "castle");
+        assert_eq!(bookmarks.cycle(Some("start"), -1).unwrap().name, "castle");
+        assert_eq!(bookmarks.cycle(Some("castle"), 1).unwrap().name, "start");
+
+        let json = serde_json::to_string(&bookmarks).unwrap();
+        assert!(json.starts_with('['));
+        assert_eq!(serde_json::from_str::<CameraBookmarks>(&json).unwrap(), bookmarks);
+    }
+
+    #[test]
+    fn test_tour_interpolates_smoothly() {
+        let mut tour = CameraTour::through(&bookmarks()).unwrap();
+        tour.leg_seconds = 2.0;
+        tour.hold_seconds = 1.0;
+        let mut camera = Camera::new();
+
+        // Holding at the first stop
+        assert!(tour.update(0.5, &mut camera));
+        assert_eq!((camera.x, camera.y), (0, 0));
+
+        // Halfway through the leg, on the straight line between the stops
+        assert!(tour.update(1.5, &mut camera));
+        assert_eq!((camera.x, camera.y, camera.zoom), (50, 20, 1.5));
+
+        // Easing means a quarter of the way in time is less than a quarter in distance
+        let mut early = tour.clone();
+        early.elapsed = 1.5;
+        assert!(early.current().x < 25.0);
+
+        assert!(!tour.update(10.0, &mut camera));
+        assert_eq!((camera.x, camera.y, camera.zoom), (100, 40, 2.0));
+        assert!(CameraTour::new(Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,164 @@
+use crate::particle::Particle;
+use crate::reactions::NEIGHBOR_OFFSETS;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+
+/// What lies past the edges of a simulation's grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorldBounds {
+    /// Solid walls held at the ambient temperature: particles pile up against them and
+    /// edge cells trade heat with them as with open air
+    #[default]
+    Closed,
+    /// Empty space: particles that fall or are flung past an edge are deleted and
+    /// counted, and no heat crosses the edge
+    Void,
+    /// The grid is a torus: movement, blasts and heat carry on from the opposite edge
+    Wrap,
+}
+
+impl Simulation {
+    pub fn bounds(&self) -> WorldBounds {
+        self.bounds
+    }
+
+    pub fn set_bounds(&mut self, bounds: WorldBounds) {
+        self.bounds = bounds;
+    }
+
+    /// Particles lost past the edges of a `WorldBounds::Void` world since it was created
+    /// or cleared
+    pub fn voided_particles(&self) -> u64 {
+        self.voided
+    }
+
+    /// The cell a position lands on: itself inside the grid, the cell across the edge in a
+    /// wrapped world, otherwise none
+    pub(crate) fn bounded_cell(&self, x: i64, y: i64) -> Option<(usize, usize)> {
+        if self.bounds == WorldBounds::Wrap && self.width > 0 && self.height > 0 {
+            return Some((x.rem_euclid(self.width as i64) as usize, y.rem_euclid(self.height as i64) as usize));
+        }
+        (x >= 0 && y >= 0 && (x as u64) < self.width as u64 && (y as u64) < self.height as u64)
+            .then_some((x as usize, y as usize))
+    }
+
+    /// `bounded_cell` of the cell `(dx, dy)` away from `(x, y)`
+    #[inline(always)]
+    pub(crate) fn offset_cell(&self, x: usize, y: usize, dx: i32, dy: i32) -> Option<(usize, usize)> {
+        self.bounded_cell(x as i64 + dx as i64, y as i64 + dy as i64)
+    }
+
+    /// Whether a particle about to move drops out of a `WorldBounds::Void` world: it falls,
+    /// or rises for gases, from the edge row, or its impulse carries it past an edge
+    pub(crate) fn falls_out(&self, particle: &Particle) -> bool {
+        if self.bounds != WorldBounds::Void || self.is_immovable(particle) {
+            return false;
+        }
+        let (x, y) = (particle.x as i64, particle.y as i64);
+        let outside = |dx: f32, dy: f32| self.bounded_cell(x + dx.signum() as i64, y + dy.signum() as i64).is_none();
+        if particle.has_velocity() {
+            let (vx, vy) = (particle.vx, particle.vy);
+            return (vx != 0.0 && outside(vx, 0.0)) || (vy != 0.0 && outside(0.0, vy));
+        }
+        let rises = self.materials().get(particle.material_type).density < 0.0;
+        outside(0.0, if rises { -1.0 } else { 1.0 })
+    }
+
+    /// Count a particle that `falls_out` of `(x, y)` as lost, waking what it leaves behind.
+    /// `update_cells` has already taken it out of the grid and takes it off `particle_count`.
+    pub(crate) fn void_particle(&mut self, x: usize, y: usize) {
+        self.voided += 1;
+        self.wake_cell(x, y);
+        self.mark_changed(x, y);
+    }
+
+    /// The neighbours heat flows between when `(x, y)` is on an edge, in the order of
+    /// `NEIGHBOR_OFFSETS`: those across the edge in a wrapped world, while the void
+    /// drops them. None away from the edges and in a closed world, whose walls conduct
+    /// like open air.
+    pub(crate) fn edge_thermal_neighbors(&self, x: usize, y: usize) -> Option<Vec<Option<&Particle>>> {
+        let on_edge = x == 0 || y == 0 || x + 1 >= self.width || y + 1 >= self.height;
+        if !on_edge || self.bounds == WorldBounds::Closed {
+            return None;
+        }
+        Some(NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| match self.offset_cell(x, y, dx, dy) {
+                Some((nx, ny)) => Some(self.get_particle(nx, ny)),
+                None if self.bounds == WorldBounds::Void => None,
+                None => Some(None),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    fn run(simulation: &mut Simulation, ticks: usize) {
+        for _ in 0..ticks {
+            simulation.update(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn test_bounds_decide_where_falling_sand_ends_up() {
+        let pour = |bounds: WorldBounds| {
+            let mut simulation = Simulation::new(8, 10);
+            simulation.set_seed(3);
+            simulation.set_bounds(bounds);
+            for x in 2..6 {
+                simulation.add_particle(x, 2, MaterialType::Sand, None);
+                simulation.add_particle(x, 4, MaterialType::Oil, None);
+            }
+            simulation
+        };
+
+        let mut closed = pour(WorldBounds::Closed);
+        run(&mut closed, 60);
+        assert_eq!(closed.iter_particles().count(), 8);
+        assert_eq!(closed.particle_count(), 8);
+        assert!(closed.iter_particles().all(|particle| particle.y >= 8));
+
+        let mut void = pour(WorldBounds::Void);
+        run(&mut void, 60);
+        assert_eq!(void.iter_particles().count(), 0);
+        assert_eq!(void.particle_count(), 0);
+        assert_eq!(void.voided_particles(), 8);
+        void.clear();
+        assert_eq!(void.voided_particles(), 0);
+
+        // Falling forever around the torus, never settling
+        let mut wrap = pour(WorldBounds::Wrap);
+        run(&mut wrap, 61);
+        assert_eq!(wrap.iter_particles().count(), 8);
+        assert_eq!(wrap.particle_count(), 8);
+        assert_eq!(wrap.voided_particles(), 0);
+        assert!(wrap.iter_particles().any(|particle| particle.y < 8));
+    }
+
+    #[test]
+    fn test_wrapped_edges_carry_heat_and_blasts() {
+        let mut simulation = Simulation::new(6, 6);
+        simulation.set_bounds(WorldBounds::Wrap);
+        assert_eq!(simulation.offset_cell(0, 5, -1, 1), Some((5, 0)));
+        simulation.add_particle(0, 3, MaterialType::Iron, Some(20.0));
+        simulation.add_particle(5, 3, MaterialType::Iron, Some(500.0));
+        let neighbors = simulation.edge_thermal_neighbors(0, 3).unwrap();
+        assert_eq!(neighbors.iter().flatten().map(|particle| particle.temp).sum::<f32>(), 500.0);
+
+        simulation.set_bounds(WorldBounds::Void);
+        assert_eq!(simulation.edge_thermal_neighbors(0, 3).unwrap().len(), 5);
+        simulation.set_bounds(WorldBounds::Closed);
+        assert!(simulation.edge_thermal_neighbors(0, 3).is_none());
+        assert_eq!(simulation.offset_cell(0, 5, -1, 1), None);
+
+        // A blast in one corner reaches round to the opposite one
+        simulation.set_bounds(WorldBounds::Wrap);
+        simulation.add_particle(5, 5, MaterialType::Iron, Some(20.0));
+        simulation.explode(0, 0, 3.0);
+        assert!(simulation.get_particle(5, 5).unwrap().temp > 20.0);
+    }
+}
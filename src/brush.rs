@@ -0,0 +1,201 @@
+use crate::event_bus::SimulationEvent;
+use crate::materials::MaterialType;
+use crate::simulation::Simulation;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+/// Most cells a fill brush changes when no limit is given
+pub const DEFAULT_FILL_LIMIT: usize = 4096;
+
+fn default_fill_limit() -> usize {
+    DEFAULT_FILL_LIMIT
+}
+
+/// Shape of cells a paint tool covers around the cursor, shared by every frontend.
+/// Serialized with a `shape` tag, e.g. `{"shape": "spray", "radius": 4, "density": 0.2}`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum Brush {
+    /// Filled disc; the brush `Simulation::paint` uses
+    Circle { radius: usize },
+    /// Filled square reaching `radius` cells out from the centre
+    Square { radius: usize },
+    /// Discs of `radius` stamped along the Bresenham line from `from` to the cursor
+    Line { from: (usize, usize), radius: usize },
+    /// The 4-connected region of cells holding the same material as the cursor cell,
+    /// stopping after `limit` cells so filling open air stays bounded
+    Fill {
+        #[serde(default = "default_fill_limit")]
+        limit: usize,
+    },
+    /// Each empty cell of the disc is painted with chance `density`
+    Spray { radius: usize, density: f32 },
+}
+
+impl Brush {
+    pub fn circle(radius: usize) -> Self {
+        Brush::Circle { radius }
+    }
+
+    pub fn fill() -> Self {
+        Brush::Fill { limit: DEFAULT_FILL_LIMIT }
+    }
+}
+
+/// Cells of the Bresenham line from `from` to `to`, both ends included
+pub fn line_cells(from: (usize, usize), to: (usize, usize)) -> Vec<(usize, usize)> {
+    let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+    let (x1, y1) = (to.0 as i64, to.1 as i64);
+    let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+    let (sx, sy) = (if x < x1 { 1 } else { -1 }, if y < y1 { 1 } else { -1 });
+    let mut error = dx + dy;
+    let mut cells = Vec::with_capacity(dx.max(-dy) as usize + 1);
+    loop {
+        cells.push((x as usize, y as usize));
+        if x == x1 && y == y1 {
+            return cells;
+        }
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+impl Simulation {
+    /// In-bounds cells `brush` covers at `pos`, each listed once. Spray brushes roll
+    /// their cells from the simulation's RNG.
+    pub fn brush_cells(&mut self, brush: &Brush, pos: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        match *brush {
+            Brush::Circle { radius } => self.push_disc(&mut cells, pos, radius),
+            Brush::Square { radius } => {
+                for y in pos.1.saturating_sub(radius)..=(pos.1 + radius).min(self.height.saturating_sub(1)) {
+                    for x in pos.0.saturating_sub(radius)..=(pos.0 + radius).min(self.width.saturating_sub(1)) {
+                        cells.push((x, y));
+                    }
+                }
+            }
+            Brush::Line { from, radius } => {
+                // Keep lines from far outside the grid from walking millions of cells
+                let clamp = |(x, y): (usize, usize)| (x.min(self.width), y.min(self.height));
+                let mut seen = HashSet::new();
+                let mut disc = Vec::new();
+                for point in line_cells(clamp(from), clamp(pos)) {
+                    disc.clear();
+                    self.push_disc(&mut disc, point, radius);
+                    cells.extend(disc.iter().filter(|&&cell| seen.insert(cell)));
+                }
+            }
+            Brush::Fill { limit } => return self.fill_region(pos, limit),
+            Brush::Spray { radius, density } => {
+                let mut disc = Vec::new();
+                self.push_disc(&mut disc, pos, radius);
+                disc.retain(|&(x, y)| self.get_particle(x, y).is_none());
+                cells = disc.into_iter().filter(|_| self.rng.gen::<f32>() < density).collect();
+            }
+        }
+        cells
+    }
+
+    /// Paint `material` over the cells `brush` covers at `pos`, returning the number of
+    /// cells written
+    pub fn apply_brush(&mut self, brush: &Brush, pos: (usize, usize), material: MaterialType) -> usize {
+        let cells = self.brush_cells(brush, pos);
+        self.paint_cells(&cells, material)
+    }
+
+    /// Paint `material` into each of `cells`, e.g. ones picked by `brush_cells`
+    pub fn paint_cells(&mut self, cells: &[(usize, usize)], material: MaterialType) -> usize {
+        let painted: Vec<(usize, usize)> = cells.iter().copied()
+            .filter(|&(x, y)| self.add_particle(x, y, material, None))
+            .collect();
+        if let Some(&(x, y)) = painted.get(painted.len() / 2) {
+            self.emit_event(SimulationEvent::MaterialPlaced { x: x as i64, y: y as i64, material, count: painted.len() });
+        }
+        painted.len()
+    }
+
+    fn push_disc(&self, cells: &mut Vec<(usize, usize)>, (cx, cy): (usize, usize), radius: usize) {
+        let radius_sq = radius * radius;
+        for y in cy.saturating_sub(radius)..=(cy + radius).min(self.height.saturating_sub(1)) {
+            for x in cx.saturating_sub(radius)..=(cx + radius).min(self.width.saturating_sub(1)) {
+                let (dx, dy) = (x.abs_diff(cx), y.abs_diff(cy));
+                if dx * dx + dy * dy <= radius_sq {
+                    cells.push((x, y));
+                }
+            }
+        }
+    }
+
+    fn fill_region(&self, start: (usize, usize), limit: usize) -> Vec<(usize, usize)> {
+        if start.0 >= self.width || start.1 >= self.height || limit == 0 {
+            return Vec::new();
+        }
+        let material_at = |x: usize, y: usize| self.get_particle(x, y).map(|particle| particle.material_type);
+        let target = material_at(start.0, start.1);
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        let mut cells = Vec::new();
+        while let Some((x, y)) = queue.pop_front() {
+            cells.push((x, y));
+            if cells.len() >= limit {
+                break;
+            }
+            let neighbours = [
+                (x.wrapping_sub(1), y), (x + 1, y), (x, y.wrapping_sub(1)), (x, y + 1),
+            ];
+            for (nx, ny) in neighbours {
+                if nx < self.width && ny < self.height && material_at(nx, ny) == target && seen.insert((nx, ny)) {
+                    queue.push_back((nx, ny));
+                }
+            }
+        }
+        cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_and_shapes() {
+        assert_eq!(line_cells((0, 0), (4, 2)), vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+        assert_eq!(line_cells((3, 3), (3, 3)), vec![(3, 3)]);
+
+        let mut simulation = Simulation::new(20, 20);
+        assert_eq!(simulation.brush_cells(&Brush::Square { radius: 1 }, (0, 0)).len(), 4);
+        assert_eq!(simulation.brush_cells(&Brush::circle(1), (5, 5)).len(), 5);
+        let line = simulation.brush_cells(&Brush::Line { from: (2, 10), radius: 0 }, (12, 10));
+        assert_eq!(line.len(), 11);
+        assert_eq!(simulation.apply_brush(&Brush::Line { from: (2, 10), radius: 1 }, (12, 10), MaterialType::Stone), 35);
+    }
+
+    #[test]
+    fn test_fill_stays_inside_walls() {
+        let mut simulation = Simulation::new(20, 20);
+        // A 5x5 box of Generators, which never move, with a 3x3 hollow inside
+        for i in 0..5 {
+            for (x, y) in [(i, 0), (i, 4), (0, i), (4, i)] {
+                simulation.add_particle(x, y, MaterialType::Generator, None);
+            }
+        }
+        assert_eq!(simulation.apply_brush(&Brush::fill(), (2, 2), MaterialType::Water), 9);
+        assert_eq!(simulation.brush_cells(&Brush::Fill { limit: 50 }, (10, 10)).len(), 50);
+
+        // Spray never overwrites and paints roughly its density
+        simulation.set_seed(5);
+        let sprayed = simulation.apply_brush(&Brush::Spray { radius: 6, density: 0.25 }, (12, 12), MaterialType::Sand);
+        assert!((10..50).contains(&sprayed), "sprayed {}", sprayed);
+        let json = serde_json::to_string(&Brush::Spray { radius: 6, density: 0.25 }).unwrap();
+        assert_eq!(serde_json::from_str::<Brush>(&json).unwrap(), Brush::Spray { radius: 6, density: 0.25 });
+        assert_eq!(serde_json::from_str::<Brush>(r#"{"shape":"fill"}"#).unwrap(), Brush::fill());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::simulation::{Simulation, SIM_CHUNK_SIZE};
+use std::time::{Duration, Instant};
+
+/// A tick `Simulation::update_budgeted` has started but not finished: the cells it took
+/// for the tick, in update order, and how far through them it has got
+#[derive(Debug, Clone)]
+pub(crate) struct BudgetedTick {
+    cells: Vec<(usize, usize)>,
+    next: usize,
+    delta_time: f32,
+}
+
+impl Simulation {
+    /// `update` spread over as many calls as it takes to fit each in `max_micros`
+    /// microseconds. A call starts a tick of `delta_time` if none is under way, then
+    /// updates the tick's queued cells a chunk at a time until the budget runs out, always
+    /// finishing at least one chunk so every call makes progress. The next call resumes
+    /// with the next chunk, and the call that updates the last one finishes the tick,
+    /// returning true. `update` and `update_parallel` finish a tick left part way first.
+    pub fn update_budgeted(&mut self, delta_time: f32, max_micros: u64) -> bool {
+        let started = Instant::now();
+        let budget = Duration::from_micros(max_micros);
+        let mut tick = match self.budgeted.take() {
+            Some(tick) => tick,
+            None => {
+                self.step_surroundings(delta_time);
+                let cells = self.begin_particle_update(delta_time);
+                BudgetedTick { cells, next: 0, delta_time }
+            }
+        };
+
+        while tick.next < tick.cells.len() {
+            let chunk_of = |(x, y): (usize, usize)| (x / SIM_CHUNK_SIZE, y / SIM_CHUNK_SIZE);
+            let chunk = chunk_of(tick.cells[tick.next]);
+            let end = tick.cells[tick.next..].iter()
+                .position(|&cell| chunk_of(cell) != chunk)
+                .map_or(tick.cells.len(), |offset| tick.next + offset);
+            self.update_cells(&tick.cells[tick.next..end], tick.delta_time);
+            tick.next = end;
+            if started.elapsed() >= budget && tick.next < tick.cells.len() {
+                self.budgeted = Some(tick);
+                return false;
+            }
+        }
+
+        self.finish_particle_update(tick.cells);
+        self.step_overlays(tick.delta_time);
+        true
+    }
+
+    /// Whether `update_budgeted` left a tick part way through
+    pub fn tick_in_progress(&self) -> bool {
+        self.budgeted.is_some()
+    }
+
+    /// Cells of the tick under way that `update_budgeted` has yet to update
+    pub fn pending_cell_count(&self) -> usize {
+        self.budgeted.as_ref().map_or(0, |tick| tick.cells.len() - tick.next)
+    }
+
+    /// Update what is left of a tick `update_budgeted` started
+    pub(crate) fn finish_budgeted_tick(&mut self) {
+        if self.budgeted.is_some() {
+            self.update_budgeted(0.0, u64::MAX);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    /// Sand falling in every chunk of a 64 by 64 grid
+    fn rain(seed: u64) -> Simulation {
+        let mut simulation = Simulation::new(64, 64);
+        simulation.set_seed(seed);
+        for x in (0..64).step_by(2) {
+            for y in 0..32 {
+                simulation.add_particle(x, y, MaterialType::Sand, None);
+            }
+        }
+        simulation
+    }
+
+    fn cells(simulation: &Simulation) -> Vec<(usize, usize, MaterialType)> {
+        simulation.iter_particles().map(|p| (p.x, p.y, p.material_type)).collect()
+    }
+
+    #[test]
+    fn test_budgeted_ticks_resume_and_match_whole_updates() {
+        let mut whole = rain(4);
+        let mut sliced = rain(4);
+        for _ in 0..20 {
+            whole.update(1.0 / 60.0);
+
+            // A zero budget updates a single chunk per call
+            let mut calls = 1;
+            while !sliced.update_budgeted(1.0 / 60.0, 0) {
+                assert!(sliced.tick_in_progress());
+                calls += 1;
+            }
+            assert!(calls > 1);
+            assert!(!sliced.tick_in_progress());
+            assert_eq!(sliced.pending_cell_count(), 0);
+        }
+        assert_eq!(sliced.tick(), whole.tick());
+        assert_eq!(cells(&sliced), cells(&whole));
+    }
+
+    #[test]
+    fn test_update_finishes_a_tick_left_part_way() {
+        let mut simulation = rain(5);
+        assert!(!simulation.update_budgeted(1.0 / 60.0, 0));
+        let pending = simulation.pending_cell_count();
+        assert!(pending > 0);
+
+        simulation.update(1.0 / 60.0);
+        assert!(!simulation.tick_in_progress());
+        assert_eq!(simulation.tick(), 2);
+        assert_eq!(simulation.iter_particles().count(), 32 * 32);
+        // A generous budget fits a whole tick in one call
+        assert!(simulation.update_budgeted(1.0 / 60.0, 1_000_000));
+        assert_eq!(simulation.tick(), 3);
+    }
+}
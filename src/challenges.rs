@@ -0,0 +1,403 @@
+use crate::materials::MaterialType;
+use crate::oracle::{Assertion, AssertionFailure};
+use crate::simulation::{GridRect, Simulation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// Material filled into a rectangle of a challenge's starting scene
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneFill {
+    pub rect: GridRect,
+    pub material: MaterialType,
+    pub temp: Option<f32>,
+}
+
+impl SceneFill {
+    pub fn new(rect: GridRect, material: MaterialType) -> Self {
+        Self { rect, material, temp: None }
+    }
+
+    /// Add the fill's particles to `simulation`, clipped to the grid
+    pub fn apply_to(&self, simulation: &mut Simulation) {
+        for y in self.rect.y..(self.rect.y + self.rect.height).min(simulation.height) {
+            for x in self.rect.x..(self.rect.x + self.rect.width).min(simulation.width) {
+                simulation.add_particle(x, y, self.material, self.temp);
+            }
+        }
+    }
+}
+
+/// A tool the player may paint with, optionally limited to a number of cells
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AllowedTool {
+    pub material: MaterialType,
+    pub budget: Option<usize>,
+}
+
+/// A sandbox puzzle: a starting scene, the tools allowed, and assertions that must all
+/// hold at once to win
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub width: usize,
+    pub height: usize,
+    pub scene: Vec<SceneFill>,
+    pub tools: Vec<AllowedTool>,
+    pub objective: Vec<Assertion>,
+    /// Simulated seconds an expert needs
+    pub par_time: f32,
+    /// Seconds after which an attempt fails; None allows unlimited time
+    pub time_limit: Option<f32>,
+}
+
+impl Challenge {
+    /// "Extinguish the forest fire using only 200 water particles"
+    pub fn forest_fire() -> Self {
+        let mut scene = vec![SceneFill::new(GridRect::new(0, 70, 120, 10), MaterialType::Stone)];
+        for x in (10..110).step_by(12) {
+            scene.push(SceneFill::new(GridRect::new(x, 50, 2, 20), MaterialType::Wood));
+            scene.push(SceneFill::new(GridRect::new(x - 3, 46, 8, 4), MaterialType::Plant));
+        }
+        scene.push(SceneFill::new(GridRect::new(56, 44, 6, 3), MaterialType::Fire));
+        Self {
+            id: "forest_fire".to_string(),
+            name: "Forest Fire".to_string(),
+            description: "Put the fire out using only 200 water particles".to_string(),
+            width: 120,
+            height: 80,
+            scene,
+            tools: vec![AllowedTool { material: MaterialType::Water, budget: Some(200) }],
+            // Letting the forest burn down doesn't count
+            objective: vec![
+                Assertion::NoParticlesOf { material: MaterialType::Fire },
+                Assertion::RegionContains { region: GridRect::new(0, 40, 120, 30), material: MaterialType::Wood, min_count: 300 },
+            ],
+            par_time: 10.0,
+            time_limit: Some(60.0),
+        }
+    }
+
+    /// Fill a stone basin with water using sand to dam the flow
+    pub fn fill_the_basin() -> Self {
+        let basin = GridRect::new(60, 50, 30, 20);
+        Self {
+            id: "fill_the_basin".to_string(),
+            name: "Fill the Basin".to_string(),
+            description: "Redirect the spring into the basin before it drains away".to_string(),
+            width: 120,
+            height: 80,
+            scene: vec![
+                SceneFill::new(GridRect::new(0, 70, 120, 10), MaterialType::Stone),
+                SceneFill::new(GridRect::new(59, 50, 1, 20), MaterialType::Stone),
+                SceneFill::new(GridRect::new(90, 50, 1, 20), MaterialType::Stone),
+                SceneFill::new(GridRect::new(10, 10, 30, 8), MaterialType::Water),
+            ],
+            tools: vec![
+                AllowedTool { material: MaterialType::Sand, budget: Some(400) },
+                AllowedTool { material: MaterialType::Eraser, budget: None },
+            ],
+            objective: vec![Assertion::RegionContains { region: basin, material: MaterialType::Water, min_count: 150 }],
+            par_time: 20.0,
+            time_limit: None,
+        }
+    }
+
+    /// The built-in challenge list, in the order frontends should present it
+    pub fn builtin() -> Vec<Challenge> {
+        vec![Self::forest_fire(), Self::fill_the_basin()]
+    }
+
+    /// A fresh simulation holding the starting scene
+    pub fn build_scene(&self) -> Simulation {
+        let mut simulation = Simulation::new(self.width, self.height);
+        for fill in &self.scene {
+            fill.apply_to(&mut simulation);
+        }
+        simulation
+    }
+
+    pub fn tool(&self, material: MaterialType) -> Option<&AllowedTool> {
+        self.tools.iter().find(|tool| tool.material == material)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeError {
+    ToolNotAllowed(MaterialType),
+    BudgetSpent(MaterialType),
+    /// The attempt has already been won or lost
+    Finished,
+}
+
+impl std::fmt::Display for ChallengeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChallengeError::ToolNotAllowed(material) => write!(f, "{:?} is not allowed in this challenge", material),
+            ChallengeError::BudgetSpent(material) => write!(f, "No {:?} left to place", material),
+            ChallengeError::Finished => write!(f, "The challenge is over"),
+        }
+    }
+}
+
+impl std::error::Error for ChallengeError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ChallengeStatus {
+    InProgress,
+    Completed { time: f32, under_par: bool },
+    Failed { time: f32 },
+}
+
+/// One attempt at a challenge. Painting goes through the attempt so tool rules and budgets
+/// are enforced; `update` steps the simulation and checks the objective.
+pub struct ChallengeAttempt {
+    challenge: Challenge,
+    simulation: Simulation,
+    elapsed: f32,
+    used: HashMap<MaterialType, usize>,
+    status: ChallengeStatus,
+}
+
+impl ChallengeAttempt {
+    pub fn new(challenge: Challenge) -> Self {
+        Self {
+            simulation: challenge.build_scene(),
+            challenge,
+            elapsed: 0.0,
+            used: HashMap::new(),
+            status: ChallengeStatus::InProgress,
+        }
+    }
+
+    pub fn challenge(&self) -> &Challenge {
+        &self.challenge
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.elapsed
+    }
+
+    pub fn status(&self) -> ChallengeStatus {
+        self.status
+    }
+
+    /// Cells left for `material`; None when it is unlimited or not allowed
+    pub fn remaining(&self, material: MaterialType) -> Option<usize> {
+        let budget = self.challenge.tool(material)?.budget?;
+        Some(budget.saturating_sub(self.used.get(&material).copied().unwrap_or(0)))
+    }
+
+    /// Paint a filled circle with an allowed tool, stopping once its budget runs out.
+    /// Returns the number of cells written.
+    pub fn paint(&mut self, x: usize, y: usize, material: MaterialType, radius: usize) -> Result<usize, ChallengeError> {
+        if self.status != ChallengeStatus::InProgress {
+            return Err(ChallengeError::Finished);
+        }
+        if self.challenge.tool(material).is_none() {
+            return Err(ChallengeError::ToolNotAllowed(material));
+        }
+        let mut remaining = self.remaining(material).unwrap_or(usize::MAX);
+        if remaining == 0 {
+            return Err(ChallengeError::BudgetSpent(material));
+        }
+
+        let radius_sq = radius * radius;
+        let mut placed = 0;
+        'paint: for py in y.saturating_sub(radius)..=(y + radius).min(self.simulation.height.saturating_sub(1)) {
+            for px in x.saturating_sub(radius)..=(x + radius).min(self.simulation.width.saturating_sub(1)) {
+                let (dx, dy) = (px.abs_diff(x), py.abs_diff(y));
+                if dx * dx + dy * dy > radius_sq {
+                    continue;
+                }
+                // Erasing empty cells and repainting a cell with its own material are free
+                let current = self.simulation.get_particle(px, py).map(|particle| particle.material_type);
+                if current == Some(material) || (material == MaterialType::Eraser && current.is_none()) {
+                    continue;
+                }
+                if self.simulation.add_particle(px, py, material, None) {
+                    placed += 1;
+                    remaining -= 1;
+                    if remaining == 0 {
+                        break 'paint;
+                    }
+                }
+            }
+        }
+        *self.used.entry(material).or_default() += placed;
+        Ok(placed)
+    }
+
+    /// Advance the simulation and re-check the objective
+    pub fn update(&mut self, delta_time: f32) -> ChallengeStatus {
+        if self.status != ChallengeStatus::InProgress {
+            return self.status;
+        }
+        self.simulation.update(delta_time);
+        self.elapsed += delta_time;
+
+        if self.unmet_objectives().is_empty() {
+            self.status = ChallengeStatus::Completed {
+                time: self.elapsed,
+                under_par: self.elapsed <= self.challenge.par_time,
+            };
+        } else if self.challenge.time_limit.is_some_and(|limit| self.elapsed >= limit) {
+            self.status = ChallengeStatus::Failed { time: self.elapsed };
+        }
+        self.status
+    }
+
+    /// Objectives that don't hold yet, for showing progress
+    pub fn unmet_objectives(&self) -> Vec<AssertionFailure> {
+        self.challenge.objective.iter().filter_map(|assertion| assertion.evaluate(&self.simulation)).collect()
+    }
+}
+
+/// A profile's results for one challenge
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeRecord {
+    pub attempts: u32,
+    /// Fastest completion in simulated seconds; None until completed
+    pub best_time: Option<f32>,
+    pub beat_par: bool,
+}
+
+impl ChallengeRecord {
+    pub fn is_completed(&self) -> bool {
+        self.best_time.is_some()
+    }
+}
+
+/// Completion state per player profile, keyed by profile name then challenge id, saved
+/// as JSON
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChallengeProgress {
+    profiles: HashMap<String, HashMap<String, ChallengeRecord>>,
+}
+
+impl ChallengeProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, profile: &str, challenge_id: &str) -> Option<&ChallengeRecord> {
+        self.profiles.get(profile)?.get(challenge_id)
+    }
+
+    pub fn is_completed(&self, profile: &str, challenge_id: &str) -> bool {
+        self.record(profile, challenge_id).is_some_and(ChallengeRecord::is_completed)
+    }
+
+    /// Note the outcome of a finished attempt; attempts still in progress are ignored
+    pub fn record_attempt(&mut self, profile: &str, challenge_id: &str, status: ChallengeStatus) {
+        if status == ChallengeStatus::InProgress {
+            return;
+        }
+        let record = self.profiles
+            .entry(profile.to_string())
+            .or_default()
+            .entry(challenge_id.to_string())
+            .or_default();
+        record.attempts += 1;
+        if let ChallengeStatus::Completed { time, under_par } = status {
+            record.best_time = Some(record.best_time.map_or(time, |best| best.min(time)));
+            record.beat_par |= under_par;
+        }
+    }
+
+    /// Challenges from `challenges` the profile has completed
+    pub fn completed_count(&self, profile: &str, challenges: &[Challenge]) -> usize {
+        challenges.iter().filter(|challenge| self.is_completed(profile, &challenge.id)).count()
+    }
+
+    #[cfg(feature = "files")]
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// Load saved progress; a missing file is an empty history
+    #[cfg(feature = "files")]
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Ok(serde_json::from_str(&json)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiny_challenge() -> Challenge {
+        Challenge {
+            id: "drop".to_string(),
+            name: "Drop".to_string(),
+            description: "Get ten grains of sand onto the floor".to_string(),
+            width: 20,
+            height: 20,
+            scene: vec![SceneFill::new(GridRect::new(0, 19, 20, 1), MaterialType::Stone)],
+            tools: vec![AllowedTool { material: MaterialType::Sand, budget: Some(12) }],
+            objective: vec![Assertion::RegionContains {
+                region: GridRect::new(0, 16, 20, 3),
+                material: MaterialType::Sand,
+                min_count: 10,
+            }],
+            par_time: 2.0,
+            time_limit: Some(5.0),
+        }
+    }
+
+    #[test]
+    fn test_tools_and_budgets() {
+        let mut attempt = ChallengeAttempt::new(tiny_challenge());
+        assert_eq!(attempt.paint(5, 5, MaterialType::Water, 1), Err(ChallengeError::ToolNotAllowed(MaterialType::Water)));
+
+        // A radius 2 circle has 13 cells; only 12 grains are allowed
+        assert_eq!(attempt.paint(10, 5, MaterialType::Sand, 2), Ok(12));
+        assert_eq!(attempt.remaining(MaterialType::Sand), Some(0));
+        assert_eq!(attempt.paint(3, 3, MaterialType::Sand, 0), Err(ChallengeError::BudgetSpent(MaterialType::Sand)));
+    }
+
+    #[test]
+    fn test_completion_is_tracked_per_profile() {
+        let mut attempt = ChallengeAttempt::new(tiny_challenge());
+        attempt.paint(10, 5, MaterialType::Sand, 2).unwrap();
+        let mut status = ChallengeStatus::InProgress;
+        for _ in 0..300 {
+            status = attempt.update(1.0 / 60.0);
+            if status != ChallengeStatus::InProgress {
+                break;
+            }
+        }
+        assert!(matches!(status, ChallengeStatus::Completed { under_par: true, .. }), "{:?}", status);
+        assert_eq!(attempt.paint(1, 1, MaterialType::Sand, 0), Err(ChallengeError::Finished));
+
+        let mut progress = ChallengeProgress::new();
+        progress.record_attempt("ada", "drop", ChallengeStatus::Failed { time: 5.0 });
+        assert!(!progress.is_completed("ada", "drop"));
+        progress.record_attempt("ada", "drop", status);
+        assert!(progress.is_completed("ada", "drop"));
+        assert!(!progress.is_completed("bo", "drop"));
+        assert_eq!(progress.record("ada", "drop").unwrap().attempts, 2);
+
+        let json = serde_json::to_string(&progress).unwrap();
+        assert_eq!(serde_json::from_str::<ChallengeProgress>(&json).unwrap(), progress);
+    }
+
+    #[test]
+    fn test_builtin_scenes_start_unsolved() {
+        for challenge in Challenge::builtin() {
+            let attempt = ChallengeAttempt::new(challenge);
+            assert!(!attempt.unmet_objectives().is_empty(), "{}", attempt.challenge().id);
+        }
+    }
+}
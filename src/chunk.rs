@@ -1,6 +1,8 @@
 use crate::particle::Particle;
 use crate::materials::MaterialType;
+use crate::physics::AMBIENT_TEMP;
 use ahash::AHashMap;
+use std::ops::{Deref, DerefMut};
 
 // Chunk size - smaller chunks for better performance
 pub const CHUNK_SIZE: usize = 64;
@@ -8,15 +10,221 @@ pub const CHUNK_AREA: usize = CHUNK_SIZE * CHUNK_SIZE;
 
 pub type ChunkKey = (i32, i32);
 
+// Cell flags
+const OCCUPIED: u8 = 1;
+const PROCESSED: u8 = 1 << 1;
+const MOVED: u8 = 1 << 2;
+const BURNING: u8 = 1 << 3;
+const DYNAMIC: u8 = 1 << 4;
+const WET: u8 = 1 << 5;
+
+/// Material code of a custom material, whose id lives in the side table
+const CUSTOM_CODE: u8 = u8::MAX;
+
+/// Per-particle state most particles leave at its default, kept off the per-cell arrays
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ParticleExtra {
+    custom_id: u16,
+    initial_temp: f32,
+    life: Option<f32>,
+    vx: f32,
+    vy: f32,
+    buried_time: f32,
+    payload: f32,
+    stress: f32,
+    latent_heat: f32,
+}
+
+impl Default for ParticleExtra {
+    fn default() -> Self {
+        Self {
+            custom_id: 0,
+            initial_temp: AMBIENT_TEMP,
+            life: None,
+            vx: 0.0,
+            vy: 0.0,
+            buried_time: 0.0,
+            payload: 0.0,
+            stress: 0.0,
+            latent_heat: 0.0,
+        }
+    }
+}
+
+/// Built-in materials are stored as their index in `MaterialType::BUILTIN`, which is in
+/// discriminant order with Eraser last
+fn encode_material(material_type: MaterialType) -> u8 {
+    match material_type {
+        MaterialType::Custom(_) => CUSTOM_CODE,
+        MaterialType::Eraser => (MaterialType::BUILTIN.len() - 1) as u8,
+        builtin => builtin.id() as u8,
+    }
+}
+
+/// Most distinct cells a chunk holds and still compresses; see `Chunk::compress`
+pub const MAX_PALETTE_SIZE: usize = 16;
+
+/// One cell as the per-cell arrays hold it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Cell {
+    material: u8,
+    flags: u8,
+    temp: f32,
+    time_in_state: f32,
+    settled_frames: u8,
+    variant: u8,
+}
+
+const EMPTY_CELL: Cell = Cell { material: 0, flags: 0, temp: 0.0, time_in_state: 0.0, settled_frames: 0, variant: 0 };
+
+/// Every cell's fields, one array each
+#[derive(Debug, Clone)]
+struct DenseCells {
+    materials: Box<[u8; CHUNK_AREA]>,
+    flags: Box<[u8; CHUNK_AREA]>,
+    temps: Box<[f32; CHUNK_AREA]>,
+    times_in_state: Box<[f32; CHUNK_AREA]>,
+    settled_frames: Box<[u8; CHUNK_AREA]>,
+    variants: Box<[u8; CHUNK_AREA]>,
+}
+
+impl DenseCells {
+    fn new() -> Self {
+        Self {
+            materials: Box::new([0; CHUNK_AREA]),
+            flags: Box::new([0; CHUNK_AREA]),
+            temps: Box::new([0.0; CHUNK_AREA]),
+            times_in_state: Box::new([0.0; CHUNK_AREA]),
+            settled_frames: Box::new([0; CHUNK_AREA]),
+            variants: Box::new([0; CHUNK_AREA]),
+        }
+    }
+
+    #[inline(always)]
+    fn get(&self, index: usize) -> Cell {
+        Cell {
+            material: self.materials[index],
+            flags: self.flags[index],
+            temp: self.temps[index],
+            time_in_state: self.times_in_state[index],
+            settled_frames: self.settled_frames[index],
+            variant: self.variants[index],
+        }
+    }
+
+    #[inline(always)]
+    fn set(&mut self, index: usize, cell: Cell) {
+        self.materials[index] = cell.material;
+        self.flags[index] = cell.flags;
+        self.temps[index] = cell.temp;
+        self.times_in_state[index] = cell.time_in_state;
+        self.settled_frames[index] = cell.settled_frames;
+        self.variants[index] = cell.variant;
+    }
+}
+
+/// A distinct cell of a compressed chunk. Particles keep the texture variant they were
+/// created with, which varies cell to cell; `positional_variant` entries stand for every
+/// particle still showing the one `Particle::new` gives its cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PaletteEntry {
+    cell: Cell,
+    positional_variant: bool,
+}
+
+/// A chunk with few distinct cells: each one once, and a bit-packed index into them per
+/// cell. A single entry, such as all stone or all air, needs no indices at all.
+#[derive(Debug, Clone)]
+struct PaletteCells {
+    palette: Vec<PaletteEntry>,
+    bits: usize,
+    indices: Box<[u64]>,
+}
+
+impl PaletteCells {
+    /// Every cell empty
+    fn empty() -> Self {
+        Self {
+            palette: vec![PaletteEntry { cell: EMPTY_CELL, positional_variant: false }],
+            bits: 0,
+            indices: Box::new([]),
+        }
+    }
+
+    /// Bits per index for a palette of `len` entries; always divides 64, so no index
+    /// straddles two words
+    fn bits_for(len: usize) -> usize {
+        match len {
+            0..=1 => 0,
+            2 => 1,
+            3..=4 => 2,
+            _ => 4,
+        }
+    }
+
+    #[inline(always)]
+    fn entry(&self, index: usize) -> &PaletteEntry {
+        if self.bits == 0 {
+            return &self.palette[0];
+        }
+        let bit = index * self.bits;
+        let slot = (self.indices[bit / 64] >> (bit % 64)) & ((1 << self.bits) - 1);
+        &self.palette[slot as usize]
+    }
+}
+
+#[derive(Debug, Clone)]
+enum CellStorage {
+    Dense(DenseCells),
+    Palette(PaletteCells),
+}
+
+/// A chunk's particles as structure-of-arrays: material, flags, temperature and the
+/// other fields every particle changes are one array each, while rarer state such as
+/// life, velocity and payload sits in a side table holding only particles that use it.
+/// Reads build a `Particle` from the arrays; `get_particle_mut` writes it back.
+///
+/// Chunks of only a few distinct cells, such as solid stone or open air, can instead be
+/// held as a palette (`compress`); new chunks start out that way, empty. The first
+/// write expands them back into arrays.
 #[derive(Debug, Clone)]
 pub struct Chunk {
     pub x: i32,
     pub y: i32,
-    // Use flat array for better cache performance
-    pub particles: Box<[Option<Particle>; CHUNK_AREA]>,
+    cells: CellStorage,
+    extras: AHashMap<u16, ParticleExtra>,
+    count: usize,
     pub dirty: bool,
     pub active_particles: Vec<(usize, usize)>, // Local coordinates within chunk
     pub settled_particles: usize, // Count of particles that haven't moved
+    pub changed_at: u64, // ChunkManager tick + 1 of the last write through the manager
+}
+
+/// A particle read out of a chunk for editing, written back when dropped
+pub struct ParticleMut<'a> {
+    chunk: &'a mut Chunk,
+    index: usize,
+    particle: Particle,
+}
+
+impl Deref for ParticleMut<'_> {
+    type Target = Particle;
+
+    fn deref(&self) -> &Particle {
+        &self.particle
+    }
+}
+
+impl DerefMut for ParticleMut<'_> {
+    fn deref_mut(&mut self) -> &mut Particle {
+        &mut self.particle
+    }
+}
+
+impl Drop for ParticleMut<'_> {
+    fn drop(&mut self) {
+        self.chunk.store(self.index, &self.particle);
+    }
 }
 
 impl Chunk {
@@ -24,10 +232,13 @@ impl Chunk {
         Self {
             x,
             y,
-            particles: Box::new([const { None }; CHUNK_AREA]),
+            cells: CellStorage::Palette(PaletteCells::empty()),
+            extras: AHashMap::new(),
+            count: 0,
             dirty: false,
             active_particles: Vec::new(),
             settled_particles: 0,
+            changed_at: 0,
         }
     }
 
@@ -36,36 +247,265 @@ impl Chunk {
         y * CHUNK_SIZE + x
     }
 
+    /// Variant `Particle::new` gives a particle created at a cell
+    fn positional_variant(&self, index: usize) -> u8 {
+        let (x, y) = self.world_pos(index % CHUNK_SIZE, index / CHUNK_SIZE);
+        Particle::creation_variant(x as usize, y as usize)
+    }
+
     #[inline(always)]
-    pub fn get_particle(&self, x: usize, y: usize) -> Option<&Particle> {
-        if x < CHUNK_SIZE && y < CHUNK_SIZE {
-            self.particles[Self::get_index(x, y)].as_ref()
+    fn cell(&self, index: usize) -> Cell {
+        match &self.cells {
+            CellStorage::Dense(cells) => cells.get(index),
+            CellStorage::Palette(cells) => self.palette_cell(cells, index),
+        }
+    }
+
+    #[inline(never)]
+    fn palette_cell(&self, cells: &PaletteCells, index: usize) -> Cell {
+        let entry = cells.entry(index);
+        if entry.positional_variant {
+            Cell { variant: self.positional_variant(index), ..entry.cell }
         } else {
-            None
+            entry.cell
         }
     }
 
     #[inline(always)]
-    pub fn get_particle_mut(&mut self, x: usize, y: usize) -> Option<&mut Particle> {
-        if x < CHUNK_SIZE && y < CHUNK_SIZE {
-            self.particles[Self::get_index(x, y)].as_mut()
+    fn flags(&self, index: usize) -> u8 {
+        match &self.cells {
+            CellStorage::Dense(cells) => cells.flags[index],
+            CellStorage::Palette(cells) => cells.entry(index).cell.flags,
+        }
+    }
+
+    /// The arrays to write into, expanding a compressed chunk first
+    #[inline(always)]
+    fn dense_mut(&mut self) -> &mut DenseCells {
+        if let CellStorage::Palette(_) = self.cells {
+            self.expand();
+        }
+        match &mut self.cells {
+            CellStorage::Dense(cells) => cells,
+            CellStorage::Palette(_) => unreachable!("expanded above"),
+        }
+    }
+
+    #[cold]
+    fn expand(&mut self) {
+        let mut dense = DenseCells::new();
+        for index in 0..CHUNK_AREA {
+            dense.set(index, self.cell(index));
+        }
+        self.cells = CellStorage::Dense(dense);
+    }
+
+    /// Whether the chunk is held as a palette rather than per-cell arrays
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.cells, CellStorage::Palette(_))
+    }
+
+    /// Hold the cells as a palette of their distinct states if there are at most
+    /// `MAX_PALETTE_SIZE`; returns whether the chunk is now compressed. The next write
+    /// expands it again.
+    pub fn compress(&mut self) -> bool {
+        let CellStorage::Dense(dense) = &self.cells else {
+            return true;
+        };
+        let mut palette: Vec<PaletteEntry> = Vec::new();
+        let mut slots = vec![0u8; CHUNK_AREA];
+        for (index, slot) in slots.iter_mut().enumerate() {
+            let cell = dense.get(index);
+            // Empty cells keep whatever their last particle left behind
+            let entry = if cell.flags & OCCUPIED == 0 {
+                PaletteEntry { cell: EMPTY_CELL, positional_variant: false }
+            } else if cell.variant == self.positional_variant(index) {
+                PaletteEntry { cell: Cell { variant: 0, ..cell }, positional_variant: true }
+            } else {
+                PaletteEntry { cell, positional_variant: false }
+            };
+            *slot = match palette.iter().position(|existing| *existing == entry) {
+                Some(position) => position as u8,
+                None if palette.len() < MAX_PALETTE_SIZE => {
+                    palette.push(entry);
+                    (palette.len() - 1) as u8
+                }
+                None => return false,
+            };
+        }
+
+        palette.shrink_to_fit();
+        let bits = PaletteCells::bits_for(palette.len());
+        let mut indices = vec![0u64; (CHUNK_AREA * bits).div_ceil(64)].into_boxed_slice();
+        if bits > 0 {
+            for (index, &slot) in slots.iter().enumerate() {
+                let bit = index * bits;
+                indices[bit / 64] |= (slot as u64) << (bit % 64);
+            }
+        }
+        self.cells = CellStorage::Palette(PaletteCells { palette, bits, indices });
+        true
+    }
+
+    #[inline(always)]
+    fn occupied_index(&self, x: usize, y: usize) -> Option<usize> {
+        let index = Self::get_index(x, y);
+        (x < CHUNK_SIZE && y < CHUNK_SIZE && self.flags(index) & OCCUPIED != 0).then_some(index)
+    }
+
+    #[inline(always)]
+    fn material(&self, index: usize) -> MaterialType {
+        let code = match &self.cells {
+            CellStorage::Dense(cells) => cells.materials[index],
+            CellStorage::Palette(cells) => cells.entry(index).cell.material,
+        };
+        self.decode_material(index, code)
+    }
+
+    #[inline(always)]
+    fn temp(&self, index: usize) -> f32 {
+        match &self.cells {
+            CellStorage::Dense(cells) => cells.temps[index],
+            CellStorage::Palette(cells) => cells.entry(index).cell.temp,
+        }
+    }
+
+    #[inline(always)]
+    fn decode_material(&self, index: usize, code: u8) -> MaterialType {
+        match code {
+            CUSTOM_CODE => MaterialType::Custom(self.extras.get(&(index as u16)).map_or(0, |extra| extra.custom_id)),
+            code => MaterialType::BUILTIN[code as usize],
+        }
+    }
+
+    /// The particle at a cell, built from the arrays
+    pub fn get_particle(&self, x: usize, y: usize) -> Option<Particle> {
+        self.occupied_index(x, y).map(|index| self.load(index))
+    }
+
+    /// The particle at a cell to edit in place; changes are written back when the
+    /// returned guard drops
+    pub fn get_particle_mut(&mut self, x: usize, y: usize) -> Option<ParticleMut<'_>> {
+        let index = self.occupied_index(x, y)?;
+        let particle = self.load(index);
+        Some(ParticleMut { chunk: self, index, particle })
+    }
+
+    /// Material at a cell without building the whole particle
+    #[inline(always)]
+    pub fn material_at(&self, x: usize, y: usize) -> Option<MaterialType> {
+        self.occupied_index(x, y).map(|index| self.material(index))
+    }
+
+    #[inline(always)]
+    pub fn temp_at(&self, x: usize, y: usize) -> Option<f32> {
+        self.occupied_index(x, y).map(|index| self.temp(index))
+    }
+
+    /// Whether the particle at a cell has been updated this step
+    #[inline(always)]
+    pub fn processed_at(&self, x: usize, y: usize) -> Option<bool> {
+        self.occupied_index(x, y).map(|index| self.flags(index) & PROCESSED != 0)
+    }
+
+    /// Material, temperature and whether it is burning, for a filled cell
+    #[inline(always)]
+    pub fn sample(&self, x: usize, y: usize) -> Option<(MaterialType, f32, bool)> {
+        self.occupied_index(x, y)
+            .map(|index| (self.material(index), self.temp(index), self.flags(index) & BURNING != 0))
+    }
+
+    fn load(&self, index: usize) -> Particle {
+        let (x, y) = self.world_pos(index % CHUNK_SIZE, index / CHUNK_SIZE);
+        let cell = self.cell(index);
+        let mut particle = Particle::blank(x as usize, y as usize, self.decode_material(index, cell.material));
+        particle.processed = cell.flags & PROCESSED != 0;
+        particle.moved_this_step = cell.flags & MOVED != 0;
+        particle.burning = cell.flags & BURNING != 0;
+        particle.dynamic = cell.flags & DYNAMIC != 0;
+        particle.wet = cell.flags & WET != 0;
+        particle.temp = cell.temp;
+        particle.time_in_state = cell.time_in_state;
+        particle.settled_frames = cell.settled_frames;
+        particle.variant = cell.variant;
+        if let Some(extra) = self.extras.get(&(index as u16)) {
+            particle.initial_temp = extra.initial_temp;
+            particle.life = extra.life;
+            particle.vx = extra.vx;
+            particle.vy = extra.vy;
+            particle.buried_time = extra.buried_time;
+            particle.payload = extra.payload;
+            particle.stress = extra.stress;
+            particle.latent_heat = extra.latent_heat;
+        }
+        particle
+    }
+
+    /// Write a particle into a cell, filling it if it was empty
+    fn store(&mut self, index: usize, particle: &Particle) {
+        if self.flags(index) & OCCUPIED == 0 {
+            self.count += 1;
+        }
+        let flags = OCCUPIED
+            | if particle.processed { PROCESSED } else { 0 }
+            | if particle.moved_this_step { MOVED } else { 0 }
+            | if particle.burning { BURNING } else { 0 }
+            | if particle.dynamic { DYNAMIC } else { 0 }
+            | if particle.wet { WET } else { 0 };
+        self.dense_mut().set(index, Cell {
+            material: encode_material(particle.material_type),
+            flags,
+            temp: particle.temp,
+            time_in_state: particle.time_in_state,
+            settled_frames: particle.settled_frames,
+            variant: particle.variant,
+        });
+
+        let extra = ParticleExtra {
+            custom_id: match particle.material_type {
+                MaterialType::Custom(id) => id,
+                _ => 0,
+            },
+            initial_temp: particle.initial_temp,
+            life: particle.life,
+            vx: particle.vx,
+            vy: particle.vy,
+            buried_time: particle.buried_time,
+            payload: particle.payload,
+            stress: particle.stress,
+            latent_heat: particle.latent_heat,
+        };
+        if extra == ParticleExtra::default() {
+            self.extras.remove(&(index as u16));
         } else {
-            None
+            self.extras.insert(index as u16, extra);
         }
     }
 
+    fn take(&mut self, index: usize) -> Option<Particle> {
+        if self.flags(index) & OCCUPIED == 0 {
+            return None;
+        }
+        let particle = self.load(index);
+        self.dense_mut().set(index, EMPTY_CELL);
+        self.extras.remove(&(index as u16));
+        self.count -= 1;
+        Some(particle)
+    }
+
     pub fn set_particle(&mut self, x: usize, y: usize, particle: Particle) -> Option<Particle> {
         if x < CHUNK_SIZE && y < CHUNK_SIZE {
             let index = Self::get_index(x, y);
-            let old = self.particles[index].replace(particle);
-            
+            let old = self.take(index);
+            self.store(index, &particle);
+
             if old.is_none() {
                 // New particle added
-                if self.particles[index].as_ref().unwrap().dynamic {
+                if particle.dynamic {
                     self.active_particles.push((x, y));
                 }
             }
-            
+
             self.dirty = true;
             old
         } else {
@@ -73,17 +513,26 @@ impl Chunk {
         }
     }
 
+    /// Overwrite the particle already at a cell, as an edit through `get_particle_mut`
+    /// would; returns false if the cell is empty
+    pub fn write_particle(&mut self, x: usize, y: usize, particle: &Particle) -> bool {
+        let Some(index) = self.occupied_index(x, y) else {
+            return false;
+        };
+        self.store(index, particle);
+        true
+    }
+
     pub fn remove_particle(&mut self, x: usize, y: usize) -> Option<Particle> {
         if x < CHUNK_SIZE && y < CHUNK_SIZE {
-            let index = Self::get_index(x, y);
-            let removed = self.particles[index].take();
-            
+            let removed = self.take(Self::get_index(x, y));
+
             if removed.is_some() {
                 self.dirty = true;
                 // Remove from active particles list
                 self.active_particles.retain(|(px, py)| *px != x || *py != y);
             }
-            
+
             removed
         } else {
             None
@@ -91,15 +540,36 @@ impl Chunk {
     }
 
     pub fn is_empty(&self) -> bool {
-        self.particles.iter().all(|p| p.is_none())
+        self.count == 0
     }
 
     pub fn particle_count(&self) -> usize {
-        self.particles.iter().filter(|p| p.is_some()).count()
+        self.count
+    }
+
+    /// Every particle with its local coordinates, in row order
+    pub fn particles(&self) -> impl Iterator<Item = ((usize, usize), Particle)> + '_ {
+        (0..CHUNK_AREA)
+            .filter(|&index| self.flags(index) & OCCUPIED != 0)
+            .map(|index| ((index % CHUNK_SIZE, index / CHUNK_SIZE), self.load(index)))
+    }
+
+    /// Bytes the chunk's particle storage takes, side table included
+    pub fn memory_bytes(&self) -> usize {
+        let cells = match &self.cells {
+            CellStorage::Dense(_) => CHUNK_AREA * (2 * std::mem::size_of::<f32>() + 4 * std::mem::size_of::<u8>()),
+            CellStorage::Palette(cells) => {
+                cells.palette.capacity() * std::mem::size_of::<PaletteEntry>() + std::mem::size_of_val(&*cells.indices)
+            }
+        };
+        let per_extra = std::mem::size_of::<(u16, ParticleExtra)>();
+        cells + self.extras.capacity() * per_extra
     }
 
     pub fn clear(&mut self) {
-        self.particles.fill(None);
+        self.cells = CellStorage::Palette(PaletteCells::empty());
+        self.extras.clear();
+        self.count = 0;
         self.active_particles.clear();
         self.settled_particles = 0;
         self.dirty = true;
@@ -130,8 +600,9 @@ impl Chunk {
         let mut to_remove = Vec::new();
         
         for (i, (x, y)) in self.active_particles.iter().enumerate() {
-            if let Some(particle) = self.get_particle(*x, *y) {
-                if !particle.dynamic || particle.settled_frames >= 10 {
+            if let Some(index) = self.occupied_index(*x, *y) {
+                let cell = self.cell(index);
+                if cell.flags & DYNAMIC == 0 || cell.settled_frames >= 10 {
                     to_remove.push(i);
                 }
             } else {
@@ -151,6 +622,7 @@ pub struct ChunkManager {
     chunks: AHashMap<ChunkKey, Chunk>,
     active_chunks: Vec<ChunkKey>,
     pub chunk_size: usize,
+    tick: u64,
 }
 
 impl ChunkManager {
@@ -159,6 +631,7 @@ impl ChunkManager {
             chunks: AHashMap::new(),
             active_chunks: Vec::new(),
             chunk_size: CHUNK_SIZE,
+            tick: 0,
         }
     }
 
@@ -192,32 +665,71 @@ impl ChunkManager {
         })
     }
 
-    pub fn get_particle(&self, world_x: i64, world_y: i64) -> Option<&Particle> {
+    pub fn get_particle(&self, world_x: i64, world_y: i64) -> Option<Particle> {
         let chunk_key = Self::world_to_chunk_pos(world_x, world_y);
         let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
         
         self.get_chunk(chunk_key)?.get_particle(local_x, local_y)
     }
 
-    pub fn get_particle_mut(&mut self, world_x: i64, world_y: i64) -> Option<&mut Particle> {
+    /// Material at a world position without building the whole particle
+    pub fn material_at(&self, world_x: i64, world_y: i64) -> Option<MaterialType> {
+        let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
+        self.get_chunk(Self::world_to_chunk_pos(world_x, world_y))?.material_at(local_x, local_y)
+    }
+
+    pub fn temp_at(&self, world_x: i64, world_y: i64) -> Option<f32> {
+        let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
+        self.get_chunk(Self::world_to_chunk_pos(world_x, world_y))?.temp_at(local_x, local_y)
+    }
+
+    pub fn processed_at(&self, world_x: i64, world_y: i64) -> Option<bool> {
+        let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
+        self.get_chunk(Self::world_to_chunk_pos(world_x, world_y))?.processed_at(local_x, local_y)
+    }
+
+    /// `Chunk::sample` at a world position
+    pub fn sample(&self, world_x: i64, world_y: i64) -> Option<(MaterialType, f32, bool)> {
+        let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
+        self.get_chunk(Self::world_to_chunk_pos(world_x, world_y))?.sample(local_x, local_y)
+    }
+
+    pub fn get_particle_mut(&mut self, world_x: i64, world_y: i64) -> Option<ParticleMut<'_>> {
         let chunk_key = Self::world_to_chunk_pos(world_x, world_y);
         let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
         
         self.get_chunk_mut(chunk_key)?.get_particle_mut(local_x, local_y)
     }
 
+    /// `Chunk::write_particle` at a world position; like `get_particle_mut`, not tracked
+    /// by `iter_changed_since`
+    pub fn write_particle(&mut self, world_x: i64, world_y: i64, particle: &Particle) -> bool {
+        let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
+        self.get_chunk_mut(Self::world_to_chunk_pos(world_x, world_y))
+            .is_some_and(|chunk| chunk.write_particle(local_x, local_y, particle))
+    }
+
     pub fn set_particle(&mut self, world_x: i64, world_y: i64, particle: Particle) -> Option<Particle> {
         let chunk_key = Self::world_to_chunk_pos(world_x, world_y);
         let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
         
-        self.get_or_create_chunk(chunk_key).set_particle(local_x, local_y, particle)
+        let stamp = self.tick + 1;
+        let chunk = self.get_or_create_chunk(chunk_key);
+        chunk.changed_at = stamp;
+        chunk.set_particle(local_x, local_y, particle)
     }
 
     pub fn remove_particle(&mut self, world_x: i64, world_y: i64) -> Option<Particle> {
         let chunk_key = Self::world_to_chunk_pos(world_x, world_y);
         let (local_x, local_y) = Self::world_to_local_pos(world_x, world_y);
         
-        self.get_chunk_mut(chunk_key)?.remove_particle(local_x, local_y)
+        let stamp = self.tick + 1;
+        let chunk = self.get_chunk_mut(chunk_key)?;
+        let removed = chunk.remove_particle(local_x, local_y);
+        if removed.is_some() {
+            chunk.changed_at = stamp;
+        }
+        removed
     }
 
     pub fn add_particle(&mut self, world_x: i64, world_y: i64, material_type: MaterialType, temp: Option<f32>) -> bool {
@@ -226,10 +738,8 @@ impl ChunkManager {
         
         // Check if we can place here
         if let Some(chunk) = self.get_chunk(chunk_key) {
-            if let Some(existing) = chunk.get_particle(local_x, local_y) {
-                if existing.material_type == MaterialType::Generator && material_type != MaterialType::Eraser {
-                    return false; // Can't overwrite generators unless erasing
-                }
+            if chunk.material_at(local_x, local_y) == Some(MaterialType::Generator) && material_type != MaterialType::Eraser {
+                return false; // Can't overwrite generators unless erasing
             }
         }
         
@@ -247,7 +757,7 @@ impl ChunkManager {
         true
     }
 
-    pub fn get_neighbors(&self, world_x: i64, world_y: i64) -> Vec<Option<&Particle>> {
+    pub fn get_neighbors(&self, world_x: i64, world_y: i64) -> Vec<Option<Particle>> {
         let mut neighbors = Vec::with_capacity(8);
         
         for dy in -1..=1 {
@@ -300,6 +810,21 @@ impl ChunkManager {
         });
     }
 
+    /// `Chunk::compress` one chunk; false if there is no such chunk or its cells are too
+    /// varied
+    pub fn compress_chunk(&mut self, chunk_key: ChunkKey) -> bool {
+        self.chunks.get_mut(&chunk_key).is_some_and(Chunk::compress)
+    }
+
+    /// Compress every chunk with nothing left to update, neither dirty nor holding active
+    /// particles; returns how many chunks are compressed afterwards
+    pub fn compress_idle_chunks(&mut self) -> usize {
+        self.chunks.values_mut()
+            .map(|chunk| chunk.is_compressed() || (!chunk.is_dirty() && chunk.active_particles.is_empty() && chunk.compress()))
+            .filter(|&compressed| compressed)
+            .count()
+    }
+
     pub fn total_particles(&self) -> usize {
         self.chunks.values().map(|c| c.particle_count()).sum()
     }
@@ -308,6 +833,13 @@ impl ChunkManager {
         self.chunks.len()
     }
 
+    /// Drop a chunk entirely, returning it
+    pub fn remove_chunk(&mut self, chunk_key: ChunkKey) -> Option<Chunk> {
+        let chunk = self.chunks.remove(&chunk_key)?;
+        self.active_chunks.retain(|key| *key != chunk_key);
+        Some(chunk)
+    }
+
     pub fn clear_chunk(&mut self, chunk_key: ChunkKey) {
         if let Some(chunk) = self.get_chunk_mut(chunk_key) {
             chunk.clear();
@@ -326,6 +858,52 @@ impl ChunkManager {
     pub fn chunks_iter_mut(&mut self) -> impl Iterator<Item = (&ChunkKey, &mut Chunk)> {
         self.chunks.iter_mut()
     }
+
+    /// Number of completed engine steps, advanced by the owner once per update
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn advance_tick(&mut self) {
+        self.tick += 1;
+    }
+
+    /// Every particle with its world position, chunk by chunk in no particular order
+    pub fn iter_particles(&self) -> impl Iterator<Item = ((i64, i64), Particle)> + '_ {
+        self.chunks.values().flat_map(Self::chunk_particles)
+    }
+
+    /// Particles with world positions in `min..max` (max exclusive), visiting only the
+    /// chunks that overlap the region
+    pub fn iter_region(&self, min: (i64, i64), max: (i64, i64)) -> impl Iterator<Item = ((i64, i64), Particle)> + '_ {
+        let (min_key, max_key) = (Self::world_to_chunk_pos(min.0, min.1), Self::world_to_chunk_pos(max.0 - 1, max.1 - 1));
+        (min_key.1..=max_key.1)
+            .flat_map(move |cy| (min_key.0..=max_key.0).map(move |cx| (cx, cy)))
+            .filter_map(move |key| self.chunks.get(&key))
+            .flat_map(Self::chunk_particles)
+            .filter(move |&((x, y), _)| x >= min.0 && x < max.0 && y >= min.1 && y < max.1)
+    }
+
+    /// Every cell of the chunks written through the manager at or after `tick`, with
+    /// `None` for empty cells. Tracking is per chunk, so unchanged cells of a changed chunk
+    /// are included; edits made through `get_particle_mut` are not tracked.
+    pub fn iter_changed_since(&self, tick: u64) -> impl Iterator<Item = ((i64, i64), Option<Particle>)> + '_ {
+        self.chunks.values().filter(move |chunk| chunk.changed_at > tick).flat_map(|chunk| {
+            (0..CHUNK_AREA).map(move |index| {
+                let (x, y) = (index % CHUNK_SIZE, index / CHUNK_SIZE);
+                (chunk.world_pos(x, y), chunk.get_particle(x, y))
+            })
+        })
+    }
+
+    /// Bytes every chunk's particle storage takes together
+    pub fn memory_bytes(&self) -> usize {
+        self.chunks.values().map(Chunk::memory_bytes).sum()
+    }
+
+    fn chunk_particles(chunk: &Chunk) -> impl Iterator<Item = ((i64, i64), Particle)> + '_ {
+        chunk.particles().map(move |((x, y), particle)| (chunk.world_pos(x, y), particle))
+    }
 }
 
 impl Default for ChunkManager {
@@ -380,6 +958,105 @@ mod tests {
         assert!(chunk2.get_particle(0, 0).is_some());
     }
 
+    #[test]
+    fn test_chunk_manager_iterators() {
+        let mut manager = ChunkManager::new();
+        manager.add_particle(-1, -1, MaterialType::Sand, None);
+        manager.add_particle(10, 5, MaterialType::Water, None);
+        manager.add_particle(200, 5, MaterialType::Stone, None);
+        assert_eq!(manager.iter_particles().count(), 3);
+
+        let region: Vec<_> = manager.iter_region((-5, -5), (100, 100)).map(|(pos, _)| pos).collect();
+        assert_eq!(region.len(), 2);
+        assert!(region.contains(&(-1, -1)) && region.contains(&(10, 5)));
+
+        manager.advance_tick();
+        let since = manager.tick();
+        assert_eq!(manager.iter_changed_since(since).count(), 0);
+        manager.remove_particle(200, 5);
+        let changed: Vec<_> = manager.iter_changed_since(since).collect();
+        assert_eq!(changed.len(), CHUNK_AREA);
+        assert!(changed.iter().all(|(_, particle)| particle.is_none()));
+    }
+
+    #[test]
+    fn test_chunk_storage_round_trips_particles() {
+        let mut chunk = Chunk::new(-1, 2);
+        let mut particle = Particle::new(0, 0, MaterialType::Custom(7), Some(300.0));
+        particle.life = Some(1.5);
+        particle.vx = -2.0;
+        particle.payload = 0.25;
+        particle.burning = true;
+        particle.wet = true;
+        particle.settled_frames = 4;
+        particle.variant = 200;
+        assert!(chunk.set_particle(3, 5, particle).is_none());
+
+        let stored = chunk.get_particle(3, 5).unwrap();
+        assert_eq!((stored.x as i64, stored.y as i64), (-61, 133));
+        assert_eq!(stored.material_type, MaterialType::Custom(7));
+        assert_eq!((stored.temp, stored.initial_temp, stored.life), (300.0, 300.0, Some(1.5)));
+        assert_eq!((stored.vx, stored.payload, stored.settled_frames, stored.variant), (-2.0, 0.25, 4, 200));
+        assert!(stored.burning && stored.wet && !stored.processed);
+        assert_eq!(chunk.sample(3, 5), Some((MaterialType::Custom(7), 300.0, true)));
+
+        // Edits through the guard land when it drops
+        chunk.get_particle_mut(3, 5).unwrap().temp = 50.0;
+        assert_eq!(chunk.temp_at(3, 5), Some(50.0));
+        assert_eq!(chunk.remove_particle(3, 5).map(|particle| particle.vx), Some(-2.0));
+        assert!(chunk.is_empty() && chunk.extras.is_empty());
+        assert_eq!(chunk.material_at(3, 5), None);
+    }
+
+    #[test]
+    fn test_chunk_storage_is_compact() {
+        let mut chunk = Chunk::new(0, 0);
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                chunk.set_particle(x, y, Particle::new(x, y, MaterialType::Sand, None));
+            }
+        }
+        assert_eq!(chunk.particle_count(), CHUNK_AREA);
+        // Plain sand leaves the side table empty: 12 bytes a cell against 96 for a
+        // flat array of `Option<Particle>`
+        assert_eq!(chunk.memory_bytes(), CHUNK_AREA * 12);
+        assert!(chunk.memory_bytes() * 8 <= CHUNK_AREA * std::mem::size_of::<Option<Particle>>());
+    }
+
+    #[test]
+    fn test_homogeneous_chunks_compress_and_expand_on_write() {
+        let mut chunk = Chunk::new(1, 0);
+        assert!(chunk.is_compressed() && chunk.is_empty());
+        for y in CHUNK_SIZE / 2..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                let (world_x, world_y) = chunk.world_pos(x, y);
+                chunk.set_particle(x, y, Particle::new(world_x as usize, world_y as usize, MaterialType::Stone, None));
+            }
+        }
+        assert!(!chunk.is_compressed());
+        let before: Vec<_> = chunk.particles().collect();
+
+        // Stone under air: two palette entries, a bit per cell
+        assert!(chunk.compress());
+        assert_eq!(chunk.memory_bytes(), 2 * std::mem::size_of::<PaletteEntry>() + CHUNK_AREA / 8);
+        assert_eq!(chunk.sample(5, 40), Some((MaterialType::Stone, 20.0, false)));
+        assert_eq!(chunk.material_at(5, 4), None);
+        let after: Vec<_> = chunk.particles().collect();
+        assert_eq!(after.len(), before.len());
+        assert!(before.iter().zip(&after).all(|(a, b)| a.0 == b.0 && a.1.variant == b.1.variant));
+
+        chunk.remove_particle(0, CHUNK_SIZE - 1);
+        assert!(!chunk.is_compressed());
+        assert_eq!(chunk.particle_count(), CHUNK_AREA / 2 - 1);
+        assert_eq!(chunk.get_particle(1, CHUNK_SIZE - 1).unwrap().variant, before[CHUNK_AREA / 2 - CHUNK_SIZE + 1].1.variant);
+
+        // Too many distinct cells stay as arrays
+        for x in 0..=MAX_PALETTE_SIZE {
+            chunk.set_particle(x, 0, Particle::new(0, 0, MaterialType::Sand, Some(x as f32)));
+        }
+        assert!(!chunk.compress());
+    }
+
     #[test]
     fn test_chunk_manager_performance() {
         let mut manager = ChunkManager::new();
@@ -400,4 +1077,4 @@ mod tests {
         manager.cleanup_empty_chunks();
         assert!(manager.chunk_count() > 0); // Should still have chunks with particles
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,324 @@
+use crate::particle::Particle;
+use crate::save_load::{SaveLoadError, SaveLoadManager, SimulationSave};
+use crate::simulation::{GridRect, Simulation, SIM_CHUNK_SIZE};
+use crate::tile_entity::TileEntityManager;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+#[cfg(feature = "threads")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "threads")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "threads")]
+use std::time::{Duration, Instant};
+#[cfg(feature = "threads")]
+use tokio::sync::mpsc;
+#[cfg(feature = "threads")]
+use tokio::task::JoinHandle;
+
+/// Everything but the particles, written after the chunks so it never names a tick
+/// whose chunks are not on disk yet
+const HEADER_FILE: &str = "header.dat";
+
+/// The chunks of a grid simulation written since its last save, copied out so the
+/// simulation can be unlocked while they are compressed and written
+#[derive(Debug, Clone)]
+pub struct ChunkSnapshot {
+    /// Size, tick, random and update state and tile entities, without particles
+    header: SimulationSave,
+    chunks: Vec<((usize, usize), Vec<Particle>)>,
+    /// Whether every chunk of the grid is included
+    complete: bool,
+}
+
+impl ChunkSnapshot {
+    /// Copy the chunks of `simulation` written after tick `since`, or every chunk for None
+    pub fn capture(simulation: &Simulation, tile_entities: &TileEntityManager, since: Option<u64>) -> Self {
+        let (chunks_x, chunks_y) = simulation.chunk_dimensions();
+        let chunks = (0..chunks_y)
+            .flat_map(|cy| (0..chunks_x).map(move |cx| (cx, cy)))
+            .filter(|&(cx, cy)| since.is_none_or(|tick| simulation.chunk_changed_since(cx, cy, tick)))
+            .map(|(cx, cy)| {
+                let region = GridRect::new(cx * SIM_CHUNK_SIZE, cy * SIM_CHUNK_SIZE, SIM_CHUNK_SIZE, SIM_CHUNK_SIZE);
+                ((cx, cy), simulation.iter_region(region).cloned().collect())
+            })
+            .collect();
+        let header = SimulationSave {
+            width: simulation.width,
+            height: simulation.height,
+            tick: simulation.tick(),
+            particles: Vec::new(),
+            rng: simulation.rng_state(),
+            update: simulation.update_state(),
+            tile_entities: Vec::new(),
+            generators: simulation.generators().map(|(cell, config)| (cell, *config)).collect(),
+        };
+        Self {
+            header: header.with_tile_entities(tile_entities),
+            chunks,
+            complete: since.is_none(),
+        }
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.header.tick
+    }
+
+    /// Number of chunks copied
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Write into `directory` as gzip-compressed bincode, each chunk to `chunk_<x>_<y>.dat`
+    /// and the rest to `header.dat`. Every file goes through a temporary one, so a crash
+    /// leaves either the old or the new copy of it.
+    pub fn write_to(&self, directory: &Path, compression: Compression) -> Result<(), SaveLoadError> {
+        fs::create_dir_all(directory)?;
+        for ((cx, cy), particles) in &self.chunks {
+            write_replacing(&directory.join(chunk_file_name(*cx, *cy)), compression, particles)?;
+        }
+        if self.complete {
+            // Chunks left over from a larger grid saved under the same name
+            let chunks = (self.header.width.div_ceil(SIM_CHUNK_SIZE), self.header.height.div_ceil(SIM_CHUNK_SIZE));
+            for entry in fs::read_dir(directory)? {
+                let path = entry?.path();
+                if parse_chunk_file_name(&path).is_some_and(|(cx, cy)| cx >= chunks.0 || cy >= chunks.1) {
+                    fs::remove_file(path)?;
+                }
+            }
+        }
+        write_replacing(&directory.join(HEADER_FILE), compression, &self.header)
+    }
+}
+
+/// A background save that finished
+#[cfg(feature = "threads")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkSaveReport {
+    pub name: String,
+    pub tick: u64,
+    /// Chunks written; the rest were unchanged since the previous save
+    pub chunks: usize,
+    /// From the snapshot being taken to the last file being written
+    pub elapsed: Duration,
+}
+
+/// A background save that failed. The next save of the same name writes every chunk.
+#[cfg(feature = "threads")]
+#[derive(Debug)]
+pub struct ChunkSaveError {
+    pub name: String,
+    pub error: SaveLoadError,
+}
+
+/// Saves grid simulations chunk by chunk without keeping them locked: `save` only copies
+/// out the chunks written since the last save of the same name, and a tokio blocking task
+/// compresses and writes them to `<name>.chunks` in the save directory. Saves are written
+/// in the order they were started; failures arrive on the channel returned by `new`.
+#[cfg(feature = "threads")]
+pub struct ChunkSaver {
+    save_directory: PathBuf,
+    compression: Compression,
+    /// Tick and grid size of the last save started under each name
+    saved: HashMap<String, (u64, usize, usize)>,
+    /// Names whose last save failed, so their next one must be complete
+    failed: Arc<Mutex<HashSet<String>>>,
+    errors: mpsc::UnboundedSender<ChunkSaveError>,
+    writing: Option<JoinHandle<()>>,
+}
+
+#[cfg(feature = "threads")]
+impl ChunkSaver {
+    pub fn new(save_directory: impl AsRef<Path>) -> (Self, mpsc::UnboundedReceiver<ChunkSaveError>) {
+        let (errors, receiver) = mpsc::unbounded_channel();
+        let saver = Self {
+            save_directory: save_directory.as_ref().to_path_buf(),
+            compression: Compression::default(),
+            saved: HashMap::new(),
+            failed: Arc::new(Mutex::new(HashSet::new())),
+            errors,
+            writing: None,
+        };
+        (saver, receiver)
+    }
+
+    /// Copy out the chunks of `simulation` changed since the last save of `name` (all of
+    /// them the first time, after a failure or when the grid was resized or rewound) and
+    /// write them in the background, calling `on_complete` once they are on disk. Must be
+    /// called from within a tokio runtime.
+    pub fn save(
+        &mut self,
+        name: &str,
+        simulation: &Simulation,
+        tile_entities: &TileEntityManager,
+        on_complete: impl FnOnce(ChunkSaveReport) + Send + 'static,
+    ) {
+        let started = Instant::now();
+        let size = (simulation.width, simulation.height);
+        let failed = self.failed.lock().unwrap().remove(name);
+        let since = match self.saved.get(name) {
+            Some(&(tick, width, height)) if !failed && (width, height) == size && tick <= simulation.tick() => Some(tick),
+            _ => None,
+        };
+        let snapshot = ChunkSnapshot::capture(simulation, tile_entities, since);
+        self.saved.insert(name.to_string(), (simulation.tick(), size.0, size.1));
+
+        let directory = chunked_simulation_path(&self.save_directory, name);
+        let (name, compression, previous) = (name.to_string(), self.compression, self.writing.take());
+        let (failed, errors) = (Arc::clone(&self.failed), self.errors.clone());
+        self.writing = Some(tokio::spawn(async move {
+            // A later save must not be overwritten by an earlier one finishing after it
+            if let Some(previous) = previous {
+                let _ = previous.await;
+            }
+            let (tick, chunks) = (snapshot.tick(), snapshot.chunk_count());
+            let result = tokio::task::spawn_blocking(move || snapshot.write_to(&directory, compression))
+                .await
+                .unwrap_or_else(|e| Err(SaveLoadError::CorruptedData(format!("save task failed: {}", e))));
+            match result {
+                Ok(()) => on_complete(ChunkSaveReport { name, tick, chunks, elapsed: started.elapsed() }),
+                Err(error) => {
+                    failed.lock().unwrap().insert(name.clone());
+                    let _ = errors.send(ChunkSaveError { name, error });
+                }
+            }
+        }));
+    }
+
+    /// Whether a save is still being written
+    pub fn is_saving(&self) -> bool {
+        self.writing.as_ref().is_some_and(|writing| !writing.is_finished())
+    }
+
+    /// Wait until every save started so far is written or has failed
+    pub async fn flush(&mut self) {
+        if let Some(writing) = self.writing.take() {
+            let _ = writing.await;
+        }
+    }
+}
+
+impl SaveLoadManager {
+    /// Read a grid simulation a `ChunkSaver` wrote as `<name>.chunks`
+    pub fn load_chunked_simulation(&self, name: &str) -> Result<SimulationSave, SaveLoadError> {
+        let directory = chunked_simulation_path(&self.save_directory, name);
+        let header = directory.join(HEADER_FILE);
+        if !header.exists() {
+            return Err(SaveLoadError::WorldNotFound(name.to_string()));
+        }
+        let mut save: SimulationSave = read_compressed(&header)?;
+        let chunks = (save.width.div_ceil(SIM_CHUNK_SIZE), save.height.div_ceil(SIM_CHUNK_SIZE));
+        for entry in fs::read_dir(&directory)? {
+            let path = entry?.path();
+            if parse_chunk_file_name(&path).is_some_and(|(cx, cy)| cx < chunks.0 && cy < chunks.1) {
+                save.particles.extend(read_compressed::<Vec<Particle>>(&path)?);
+            }
+        }
+        Ok(save)
+    }
+}
+
+pub(crate) fn chunked_simulation_path(directory: &Path, name: &str) -> PathBuf {
+    directory.join(format!("{}.chunks", name))
+}
+
+fn chunk_file_name(cx: usize, cy: usize) -> String {
+    format!("chunk_{}_{}.dat", cx, cy)
+}
+
+fn parse_chunk_file_name(path: &Path) -> Option<(usize, usize)> {
+    let stem = path.file_name()?.to_str()?.strip_prefix("chunk_")?.strip_suffix(".dat")?;
+    let (cx, cy) = stem.split_once('_')?;
+    Some((cx.parse().ok()?, cy.parse().ok()?))
+}
+
+fn write_replacing<T: Serialize>(path: &Path, compression: Compression, value: &T) -> Result<(), SaveLoadError> {
+    let temporary = path.with_extension("tmp");
+    let mut writer = BufWriter::new(GzEncoder::new(File::create(&temporary)?, compression));
+    bincode::serialize_into(&mut writer, value)?;
+    writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+fn read_compressed<T: DeserializeOwned>(path: &Path) -> Result<T, SaveLoadError> {
+    let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+    Ok(bincode::deserialize_from(reader)?)
+}
+
+#[cfg(all(test, feature = "threads"))]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("sand_engine_chunk_saver_{}_{}", label, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn save(saver: &mut ChunkSaver, simulation: &Simulation) -> std::sync::mpsc::Receiver<ChunkSaveReport> {
+        let (reports, received) = std::sync::mpsc::channel();
+        saver.save("world", simulation, &TileEntityManager::new(), move |report| reports.send(report).unwrap());
+        received
+    }
+
+    #[tokio::test]
+    async fn test_saves_write_only_changed_chunks() {
+        let dir = temp_dir("incremental");
+        let (mut saver, _errors) = ChunkSaver::new(&dir);
+        let mut simulation = Simulation::new(64, 64);
+        simulation.set_seed(3);
+        for x in 0..64 {
+            simulation.add_particle(x, 63, MaterialType::Stone, None);
+        }
+        simulation.add_particle(5, 5, MaterialType::Sand, None);
+        simulation.update(1.0 / 60.0);
+
+        let first = save(&mut saver, &simulation);
+        saver.flush().await;
+        assert_eq!(first.recv().unwrap().chunks, 16);
+
+        simulation.add_particle(40, 40, MaterialType::Stone, None);
+        let second = save(&mut saver, &simulation);
+        saver.flush().await;
+        assert_eq!(second.recv().unwrap().chunks, 1);
+
+        let manager = SaveLoadManager::new(&dir).unwrap();
+        let loaded = manager.load_chunked_simulation("world").unwrap().restore();
+        assert_eq!(loaded.tick(), simulation.tick());
+        let cells = |simulation: &Simulation| {
+            let mut cells: Vec<_> = simulation.iter_particles().map(|p| (p.x, p.y, p.material_type)).collect();
+            cells.sort_by_key(|&(x, y, _)| (y, x));
+            cells
+        };
+        assert_eq!(cells(&loaded), cells(&simulation));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_save_is_reported_and_redone_in_full() {
+        let dir = temp_dir("failed");
+        let (mut saver, mut errors) = ChunkSaver::new(&dir);
+        let simulation = Simulation::new(32, 32);
+        // A file where the save directory should be
+        fs::create_dir_all(&dir).unwrap();
+        File::create(chunked_simulation_path(&dir, "world")).unwrap();
+
+        let failed = save(&mut saver, &simulation);
+        saver.flush().await;
+        assert_eq!(errors.recv().await.unwrap().name, "world");
+        assert!(failed.try_recv().is_err());
+
+        fs::remove_file(chunked_simulation_path(&dir, "world")).unwrap();
+        let retried = save(&mut saver, &simulation);
+        saver.flush().await;
+        assert_eq!(retried.recv().unwrap().chunks, 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use crate::materials::MaterialType;
+use crate::tile_entity::{FluidWorld, ThermalWorld, TileEntityData, TileEntityManager, TILE_AMBIENT_TEMP};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// What a sensor tile entity checks the cell it watches for
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorCondition {
+    /// The cell holds this material
+    Material(MaterialType),
+    /// The cell holds any liquid, e.g. a fluid level reached
+    Liquid,
+    /// The cell holds anything at all
+    Occupied,
+    /// The cell is hotter than this many degrees, empty cells reading as `TILE_AMBIENT_TEMP`
+    HotterThan(f32),
+    /// The cell is colder than this many degrees, empty cells reading as `TILE_AMBIENT_TEMP`
+    ColderThan(f32),
+}
+
+impl SensorCondition {
+    pub fn holds(&self, world: &impl SensorWorld, position: (i64, i64)) -> bool {
+        let temperature = || world.temperature_at(position).unwrap_or(TILE_AMBIENT_TEMP);
+        match *self {
+            SensorCondition::Material(material) => world.material_at(position) == Some(material),
+            SensorCondition::Liquid => world.liquid_at(position).is_some(),
+            SensorCondition::Occupied => world.material_at(position).is_some(),
+            SensorCondition::HotterThan(degrees) => temperature() > degrees,
+            SensorCondition::ColderThan(degrees) => temperature() < degrees,
+        }
+    }
+}
+
+/// How a logic tile entity combines the signals of its inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogicGate {
+    /// On while it has inputs and every one is on
+    And,
+    /// On while any input is on
+    Or,
+    /// On while no input is on
+    Not,
+    /// Repeats whether any input was on this many ticks ago
+    Delay(u32),
+}
+
+impl LogicGate {
+    /// The gate's output for this tick; a delay keeps what it has yet to repeat in `history`
+    pub fn evaluate(&self, inputs: &[bool], history: &mut VecDeque<bool>) -> bool {
+        match *self {
+            LogicGate::And => !inputs.is_empty() && inputs.iter().all(|&on| on),
+            LogicGate::Or => inputs.iter().any(|&on| on),
+            LogicGate::Not => !inputs.iter().any(|&on| on),
+            LogicGate::Delay(ticks) => {
+                history.push_back(inputs.iter().any(|&on| on));
+                if history.len() > ticks as usize {
+                    history.pop_front().unwrap_or(false)
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// The cells sensors read
+pub trait SensorWorld: ThermalWorld + FluidWorld {
+    /// The material in a cell, None for empty cells
+    fn material_at(&self, position: (i64, i64)) -> Option<MaterialType>;
+}
+
+impl TileEntityManager {
+    /// Pass signals one hop along the tile entity network: sensors read the cell they
+    /// watch, logic gates combine their inputs, and spawners and doors take on whether any
+    /// input is on. Every entity reads its inputs as they were before this call, so a
+    /// signal takes a tick per hop and loops settle instead of racing. Run this before
+    /// `update_all`, which acts on the new signals.
+    pub fn update_signals(&mut self, world: &impl SensorWorld) {
+        let previous: HashMap<(i64, i64), bool> = self.entities.iter()
+            .map(|(&position, entity)| (position, entity.active && entity.signal))
+            .collect();
+        for (&position, entity) in self.entities.iter_mut() {
+            let inputs: Vec<bool> = entity.inputs.iter()
+                .map(|source| previous.get(source).copied().unwrap_or(false))
+                .collect();
+            entity.signal = match &mut entity.data {
+                TileEntityData::Sensor { target, condition } => {
+                    condition.holds(world, (position.0 + target.0, position.1 + target.1))
+                }
+                TileEntityData::Logic { gate, history } => gate.evaluate(&inputs, history),
+                _ => inputs.iter().any(|&on| on),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+    use crate::tile_entity::TileEntity;
+
+    #[test]
+    fn test_gates_combine_inputs() {
+        let mut history = VecDeque::new();
+        assert!(!LogicGate::And.evaluate(&[], &mut history));
+        assert!(LogicGate::And.evaluate(&[true, true], &mut history));
+        assert!(!LogicGate::And.evaluate(&[true, false], &mut history));
+        assert!(LogicGate::Or.evaluate(&[false, true], &mut history));
+        assert!(LogicGate::Not.evaluate(&[false], &mut history));
+
+        let delay = LogicGate::Delay(2);
+        let outputs: Vec<bool> = [true, false, false, true, false, false]
+            .iter()
+            .map(|&on| delay.evaluate(&[on], &mut history))
+            .collect();
+        assert_eq!(outputs, [false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_fluid_level_sensor_opens_a_door() {
+        let mut simulation = Simulation::new(12, 12);
+        let mut tile_entities = TileEntityManager::new();
+        // Sensor at (1, 1) watches (1, 6); the door at (8, 2) hangs a panel down to (8, 5)
+        tile_entities.add_tile_entity(TileEntity::new_sensor((1, 1), (0, 5), SensorCondition::Liquid));
+        tile_entities.add_tile_entity(TileEntity::new_logic_gate((2, 1), LogicGate::Or, vec![(1, 1)]));
+        let mut door = TileEntity::new_door((8, 2), 3);
+        door.connect_input((2, 1));
+        tile_entities.add_tile_entity(door);
+
+        let report = simulation.update_tile_entities(&mut tile_entities, 1.0 / 60.0);
+        assert_eq!(report.door_cells, 3);
+        assert!((3..6).all(|y| simulation.get_particle(8, y).map(|p| p.material_type) == Some(MaterialType::Door)));
+
+        // One tick for the gate to hear the sensor, one for the door to hear the gate
+        simulation.add_particle(1, 6, MaterialType::Water, None);
+        for _ in 0..2 {
+            simulation.update_tile_entities(&mut tile_entities, 1.0 / 60.0);
+            assert!(simulation.get_particle(8, 3).is_some());
+        }
+        let report = simulation.update_tile_entities(&mut tile_entities, 1.0 / 60.0);
+        assert_eq!(report.door_cells, 3);
+        assert!((3..6).all(|y| simulation.get_particle(8, y).is_none()));
+    }
+}
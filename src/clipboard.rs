@@ -0,0 +1,263 @@
+use crate::particle::Particle;
+use crate::simulation::{GridRect, Simulation};
+use crate::tile_entity::{TileEntity, TileEntityManager};
+use serde::{Deserialize, Serialize};
+
+/// What pasting does where the blueprint meets cells that are already filled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteMode {
+    /// Empty the whole footprint first, so the paste comes out exactly as copied
+    #[default]
+    Replace,
+    /// Overwrite cells the blueprint fills and leave the rest of the footprint alone
+    Merge,
+    /// Only fill cells that are empty
+    SkipNonEmpty,
+}
+
+/// A copied region: particles with their full state and tile entities, positioned
+/// relative to its top-left corner. Serializes to a shareable `.blueprint` file with
+/// `SaveLoadManager::save_blueprint`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub width: usize,
+    pub height: usize,
+    pub particles: Vec<Particle>,
+    pub tile_entities: Vec<TileEntity>,
+}
+
+impl Blueprint {
+    /// Capture everything inside `rect`, clipped to the grid
+    pub fn capture(simulation: &Simulation, tile_entity_manager: &TileEntityManager, rect: GridRect) -> Self {
+        let rect = rect.intersection(&GridRect::new(0, 0, simulation.width, simulation.height))
+            .unwrap_or(GridRect::new(0, 0, 0, 0));
+        let mut particles = Vec::new();
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                if let Some(particle) = simulation.get_particle(x, y) {
+                    let mut particle = particle.clone();
+                    particle.x -= rect.x;
+                    particle.y -= rect.y;
+                    particles.push(particle);
+                }
+            }
+        }
+
+        let (left, top) = (rect.x as i64, rect.y as i64);
+        let tile_entities = tile_entity_manager.get_tile_entities()
+            .filter(|tile_entity| within(tile_entity.position, rect))
+            .map(|tile_entity| {
+                let mut tile_entity = tile_entity.clone();
+                tile_entity.position = (tile_entity.position.0 - left, tile_entity.position.1 - top);
+                tile_entity
+            })
+            .collect();
+
+        Self { width: rect.width, height: rect.height, particles, tile_entities }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty() && self.tile_entities.is_empty()
+    }
+
+    /// Cells the blueprint covers with its top-left corner at `(x, y)`
+    pub fn footprint(&self, x: usize, y: usize) -> GridRect {
+        GridRect::new(x, y, self.width, self.height)
+    }
+
+    /// Stamp the blueprint with its top-left corner at `(x, y)`; returns how many
+    /// particles landed inside the grid
+    pub fn paste(
+        &self,
+        simulation: &mut Simulation,
+        tile_entity_manager: &mut TileEntityManager,
+        (x, y): (usize, usize),
+        mode: PasteMode,
+    ) -> usize {
+        let footprint = self.footprint(x, y);
+        if mode == PasteMode::Replace {
+            clear_region(simulation, tile_entity_manager, footprint);
+        }
+
+        let mut placed = 0;
+        for particle in &self.particles {
+            let (Some(particle_x), Some(particle_y)) = (x.checked_add(particle.x), y.checked_add(particle.y)) else {
+                continue;
+            };
+            if particle_x >= simulation.width || particle_y >= simulation.height {
+                continue;
+            }
+            if mode == PasteMode::SkipNonEmpty && simulation.get_particle(particle_x, particle_y).is_some() {
+                continue;
+            }
+            simulation.set_particle(particle_x, particle_y, particle.clone());
+            placed += 1;
+        }
+
+        for tile_entity in &self.tile_entities {
+            let offset = |origin: usize, offset: i64| {
+                i64::try_from(origin).ok()?.checked_add(offset).filter(|&cell| cell >= 0)
+            };
+            let (Some(position_x), Some(position_y)) = (offset(x, tile_entity.position.0), offset(y, tile_entity.position.1)) else {
+                continue;
+            };
+            let position = (position_x, position_y);
+            let occupied = tile_entity_manager.get_tile_entity(position).is_some();
+            if position.0 as usize >= simulation.width || position.1 as usize >= simulation.height
+                || (mode == PasteMode::SkipNonEmpty && occupied)
+            {
+                continue;
+            }
+            let mut tile_entity = tile_entity.clone();
+            tile_entity.position = position;
+            tile_entity_manager.add_tile_entity(tile_entity);
+        }
+        placed
+    }
+}
+
+/// Holds the last region copied or cut, ready to paste any number of times
+#[derive(Debug, Clone, Default)]
+pub struct Clipboard {
+    blueprint: Option<Blueprint>,
+}
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy the particles and tile entities inside `rect` onto the clipboard
+    pub fn copy_region(&mut self, simulation: &Simulation, tile_entity_manager: &TileEntityManager, rect: GridRect) -> &Blueprint {
+        self.blueprint.insert(Blueprint::capture(simulation, tile_entity_manager, rect))
+    }
+
+    /// Copy `rect` onto the clipboard, then empty it
+    pub fn cut_region(&mut self, simulation: &mut Simulation, tile_entity_manager: &mut TileEntityManager, rect: GridRect) -> &Blueprint {
+        let blueprint = Blueprint::capture(simulation, tile_entity_manager, rect);
+        clear_region(simulation, tile_entity_manager, rect);
+        self.blueprint.insert(blueprint)
+    }
+
+    /// Stamp the clipboard with its top-left corner at `pos`; returns how many particles
+    /// were placed, or None if nothing has been copied
+    pub fn paste(
+        &self,
+        simulation: &mut Simulation,
+        tile_entity_manager: &mut TileEntityManager,
+        pos: (usize, usize),
+        mode: PasteMode,
+    ) -> Option<usize> {
+        Some(self.blueprint.as_ref()?.paste(simulation, tile_entity_manager, pos, mode))
+    }
+
+    pub fn blueprint(&self) -> Option<&Blueprint> {
+        self.blueprint.as_ref()
+    }
+
+    /// Put a blueprint on the clipboard, e.g. one loaded from a shared file
+    pub fn set_blueprint(&mut self, blueprint: Blueprint) {
+        self.blueprint = Some(blueprint);
+    }
+
+    pub fn clear(&mut self) {
+        self.blueprint = None;
+    }
+}
+
+fn within((x, y): (i64, i64), rect: GridRect) -> bool {
+    x >= rect.x as i64 && y >= rect.y as i64
+        && x < rect.x.saturating_add(rect.width) as i64 && y < rect.y.saturating_add(rect.height) as i64
+}
+
+/// Remove every particle and tile entity inside `rect`
+fn clear_region(simulation: &mut Simulation, tile_entity_manager: &mut TileEntityManager, rect: GridRect) {
+    for y in rect.y..rect.y.saturating_add(rect.height).min(simulation.height) {
+        for x in rect.x..rect.x.saturating_add(rect.width).min(simulation.width) {
+            simulation.remove_particle(x, y);
+        }
+    }
+    let inside: Vec<_> = tile_entity_manager.get_tile_entities()
+        .map(|tile_entity| tile_entity.position)
+        .filter(|&position| within(position, rect))
+        .collect();
+    for position in inside {
+        tile_entity_manager.remove_tile_entity(position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    fn material_at(simulation: &Simulation, x: usize, y: usize) -> Option<MaterialType> {
+        simulation.get_particle(x, y).map(|particle| particle.material_type)
+    }
+
+    #[test]
+    fn test_cut_and_paste_moves_a_region() {
+        let mut simulation = Simulation::new(30, 30);
+        let mut tile_entities = TileEntityManager::new();
+        simulation.add_particle(2, 2, MaterialType::Stone, Some(400.0));
+        simulation.add_particle(4, 3, MaterialType::Wood, None);
+        tile_entities.add_tile_entity(TileEntity::new_torch((3, 3)));
+
+        let mut clipboard = Clipboard::new();
+        assert_eq!(clipboard.paste(&mut simulation, &mut tile_entities, (0, 0), PasteMode::Merge), None);
+        let blueprint = clipboard.cut_region(&mut simulation, &mut tile_entities, GridRect::new(2, 2, 4, 4));
+        assert_eq!((blueprint.width, blueprint.height, blueprint.particles.len()), (4, 4, 2));
+        assert_eq!(blueprint.tile_entities[0].position, (1, 1));
+        assert_eq!(simulation.iter_particles().count(), 0);
+        assert_eq!(tile_entities.get_tile_entities().len(), 0);
+
+        assert_eq!(clipboard.paste(&mut simulation, &mut tile_entities, (20, 10), PasteMode::Replace), Some(2));
+        assert_eq!(material_at(&simulation, 20, 10), Some(MaterialType::Stone));
+        assert_eq!(simulation.get_particle(20, 10).unwrap().temp, 400.0);
+        assert_eq!(material_at(&simulation, 22, 11), Some(MaterialType::Wood));
+        assert!(tile_entities.get_tile_entity((21, 11)).is_some());
+
+        // Clipped at the edge of the grid
+        assert_eq!(clipboard.paste(&mut simulation, &mut tile_entities, (28, 28), PasteMode::Merge), Some(1));
+        // Origins so far out that the offsets overflow land nothing
+        let far = (usize::MAX - 1, usize::MAX - 1);
+        assert_eq!(clipboard.paste(&mut simulation, &mut tile_entities, far, PasteMode::Replace), Some(0));
+        assert_eq!(simulation.iter_particles().count(), 3);
+        assert_eq!(tile_entities.get_tile_entities().len(), 2);
+
+        let json = serde_json::to_string(clipboard.blueprint().unwrap()).unwrap();
+        let shared: Blueprint = serde_json::from_str(&json).unwrap();
+        assert_eq!(shared.particles.len(), 2);
+        assert_eq!(shared.tile_entities.len(), 1);
+    }
+
+    #[test]
+    fn test_paste_modes_handle_filled_cells() {
+        let mut simulation = Simulation::new(20, 20);
+        let mut tile_entities = TileEntityManager::new();
+        simulation.add_particle(0, 0, MaterialType::Sand, None);
+        let mut clipboard = Clipboard::new();
+        clipboard.copy_region(&simulation, &tile_entities, GridRect::new(0, 0, 2, 1));
+
+        let fill = |simulation: &mut Simulation| {
+            simulation.clear();
+            simulation.add_particle(10, 10, MaterialType::Stone, None);
+            simulation.add_particle(11, 10, MaterialType::Stone, None);
+        };
+
+        fill(&mut simulation);
+        clipboard.paste(&mut simulation, &mut tile_entities, (10, 10), PasteMode::Replace);
+        assert_eq!(material_at(&simulation, 10, 10), Some(MaterialType::Sand));
+        assert_eq!(material_at(&simulation, 11, 10), None);
+
+        fill(&mut simulation);
+        clipboard.paste(&mut simulation, &mut tile_entities, (10, 10), PasteMode::Merge);
+        assert_eq!(material_at(&simulation, 10, 10), Some(MaterialType::Sand));
+        assert_eq!(material_at(&simulation, 11, 10), Some(MaterialType::Stone));
+
+        fill(&mut simulation);
+        assert_eq!(clipboard.paste(&mut simulation, &mut tile_entities, (10, 10), PasteMode::SkipNonEmpty), Some(0));
+        assert_eq!(material_at(&simulation, 10, 10), Some(MaterialType::Stone));
+    }
+}
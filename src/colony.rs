@@ -0,0 +1,234 @@
+use crate::chunk::ChunkManager;
+use crate::ecs::{contact_damage, EntityId, ECS};
+use crate::material_registry::MaterialRegistry;
+use crate::materials::{MaterialKind, MaterialType, ToolTier};
+use rand::Rng;
+
+/// Seconds between an ant's steps
+pub const ANT_STEP_SECONDS: f32 = 0.1;
+/// Hit points a newly spawned ant has
+pub const ANT_HEALTH: f32 = 5.0;
+/// Health an ant loses per second while under water
+pub const ANT_DROWNING_DAMAGE: f32 = 2.0;
+/// Columns either side of its nest an ant drops its diggings in and won't dig from
+pub const NEST_RADIUS: i64 = 3;
+/// Chance per step that a digging ant tunnels diagonally down rather than straight ahead
+const DIG_DOWN_CHANCE: f64 = 0.3;
+/// Where a buried ant looks for air, upwards first
+const ESCAPE_OFFSETS: [(i64, i64); 5] = [(0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)];
+const NEIGHBOR_OFFSETS: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// Step every ant in the ECS across the particle grid. Ants cling to any powder or solid
+/// beside them and fall otherwise, float up through liquids, and walk along their
+/// heading, turning back from walls and from anything that would hurt them. An
+/// empty-handed ant digs powders soft enough to break by hand out of its way, picking
+/// the particle up; a loaded ant climbs back towards its nest and drops the particle
+/// there, so nests grow into mounds over the tunnels. Ants take `contact_damage` from the
+/// cells they touch and drown in water.
+///
+/// Returns the ants whose health ran out this step, having dropped what they carried;
+/// the caller decides what to do with them.
+pub fn ant_system(
+    ecs: &mut ECS,
+    delta_time: f32,
+    materials: &MaterialRegistry,
+    grid: &mut ChunkManager,
+    rng: &mut impl Rng,
+) -> Vec<EntityId> {
+    let ants: Vec<EntityId> = ecs.get_active_entities().iter()
+        .copied()
+        .filter(|&entity_id| ecs.get_ant(entity_id).is_some())
+        .collect();
+
+    let mut killed = Vec::new();
+    for entity_id in ants {
+        let (Some(position), Some(mut ant)) = (ecs.get_position(entity_id), ecs.get_ant(entity_id).cloned()) else {
+            continue;
+        };
+        let (mut x, mut y) = (position.x.floor() as i64, position.y.floor() as i64);
+        let blocks = |grid: &ChunkManager, x: i64, y: i64| {
+            grid.material_at(x, y).is_some_and(|material| {
+                matches!(materials.get(material).kind, MaterialKind::Powder | MaterialKind::Solid | MaterialKind::Static)
+            })
+        };
+        let diggable = |grid: &ChunkManager, x: i64, y: i64| {
+            grid.material_at(x, y).is_some_and(|material| {
+                let properties = materials.get(material);
+                properties.kind == MaterialKind::Powder && ToolTier::Hands.can_break(properties.hardness)
+            })
+        };
+        let in_nest = |x: i64, y: i64| (x - ant.nest.0).abs() <= NEST_RADIUS && y <= ant.nest.1;
+
+        let here = grid.material_at(x, y);
+        let submerged = here.is_some_and(|material| materials.get(material).kind == MaterialKind::Liquid);
+        let mut damage = NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| grid.material_at(x + dx, y + dy))
+            .chain(here)
+            .map(contact_damage)
+            .fold(0.0, f32::max);
+        if submerged {
+            damage += ANT_DROWNING_DAMAGE;
+        }
+        if let Some(health) = ecs.get_health_mut(entity_id) {
+            health.current -= damage * delta_time;
+            if health.current <= 0.0 {
+                if let Some(material) = ant.carrying.take() {
+                    drop_particle(grid, x, y, material, &blocks);
+                }
+                killed.push(entity_id);
+                continue;
+            }
+        }
+
+        ant.step_timer -= delta_time;
+        if ant.step_timer > 0.0 {
+            ecs.add_ant(entity_id, ant);
+            continue;
+        }
+        ant.step_timer += ANT_STEP_SECONDS;
+
+        // Ants won't walk into anything that would hurt them
+        let open = |grid: &ChunkManager, x: i64, y: i64| {
+            !blocks(grid, x, y) && grid.material_at(x, y).is_none_or(|material| contact_damage(material) <= 0.0)
+        };
+        if blocks(grid, x, y) {
+            // Buried: dig out, or squeeze towards the nearest air
+            if ant.carrying.is_none() && diggable(grid, x, y) {
+                ant.carrying = grid.remove_particle(x, y).map(|particle| particle.material_type);
+            } else if let Some(&(dx, dy)) = ESCAPE_OFFSETS.iter().find(|&&(dx, dy)| open(grid, x + dx, y + dy)) {
+                (x, y) = (x + dx, y + dy);
+            }
+        } else if submerged {
+            if open(grid, x, y - 1) {
+                y -= 1;
+            }
+        } else if !NEIGHBOR_OFFSETS.iter().any(|&(dx, dy)| blocks(grid, x + dx, y + dy)) {
+            y += 1;
+        } else if let Some(material) = ant.carrying {
+            if ant.nest.0 != x {
+                ant.heading = (ant.nest.0 - x).signum();
+            }
+            let h = ant.heading;
+            if in_nest(x, y) && open(grid, x + h, y) && grid.material_at(x + h, y).is_none() {
+                grid.add_particle(x + h, y, material, None);
+                ant.carrying = None;
+                ant.heading = -h;
+            } else if let Some(&(dx, dy)) = [(h, -1), (h, 0), (0, -1)].iter().find(|&&(dx, dy)| open(grid, x + dx, y + dy)) {
+                (x, y) = (x + dx, y + dy);
+            } else {
+                // Stuck on the way home: leave the load here and go back to digging
+                drop_particle(grid, x, y, material, &blocks);
+                ant.carrying = None;
+                ant.heading = -h;
+            }
+        } else {
+            let h = ant.heading;
+            let ahead = if rng.gen_bool(DIG_DOWN_CHANCE) { (x + h, y + 1) } else { (x + h, y) };
+            if diggable(grid, ahead.0, ahead.1) && !in_nest(ahead.0, ahead.1) {
+                ant.carrying = grid.remove_particle(ahead.0, ahead.1).map(|particle| particle.material_type);
+                (x, y) = ahead;
+            } else if open(grid, x + h, y) {
+                x += h;
+            } else if open(grid, x + h, y - 1) && open(grid, x, y - 1) {
+                (x, y) = (x + h, y - 1);
+            } else {
+                ant.heading = -h;
+            }
+        }
+
+        if let Some(position) = ecs.get_position_mut(entity_id) {
+            (position.x, position.y) = (x as f64 + 0.5, y as f64 + 0.5);
+        }
+        ecs.add_ant(entity_id, ant);
+    }
+    killed
+}
+
+/// Put a carried particle into the first empty cell around `(x, y)`, the ant's own first.
+/// Nothing is placed if the ant is walled in on every side.
+fn drop_particle(
+    grid: &mut ChunkManager,
+    x: i64,
+    y: i64,
+    material: MaterialType,
+    blocks: &impl Fn(&ChunkManager, i64, i64) -> bool,
+) {
+    let cell = std::iter::once((0, 0)).chain(NEIGHBOR_OFFSETS.iter().rev().copied())
+        .map(|(dx, dy)| (x + dx, y + dy))
+        .find(|&(cx, cy)| grid.material_at(cx, cy).is_none() && !blocks(grid, cx, cy));
+    if let Some((cx, cy)) = cell {
+        grid.add_particle(cx, cy, material, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_v2::AdvancedPhysicsEngine;
+    use crate::materials::MaterialType;
+
+    fn count_in(engine: &AdvancedPhysicsEngine, material: MaterialType, xs: std::ops::Range<i64>, ys: std::ops::Range<i64>) -> usize {
+        ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+            .filter(|&(x, y)| engine.get_particle(x, y).is_some_and(|particle| particle.material_type == material))
+            .count()
+    }
+
+    #[test]
+    fn test_ants_tunnel_into_sand_and_build_a_mound() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.enable_rigid_bodies = false;
+        engine.set_seed(5);
+        for x in 0..60 {
+            engine.add_particle(x, 30, MaterialType::Stone, None);
+        }
+        for y in 10..30 {
+            engine.add_particle(0, y, MaterialType::Stone, None);
+            engine.add_particle(59, y, MaterialType::Stone, None);
+        }
+        for y in 20..30 {
+            for x in 28..50 {
+                engine.add_particle(x, y, MaterialType::Sand, None);
+            }
+        }
+        let ants: Vec<_> = (0..3).map(|_| engine.spawn_ant(20, 29)).collect();
+        for _ in 0..1800 {
+            engine.update_with_delta(1.0 / 60.0);
+        }
+
+        let sand_left = count_in(&engine, MaterialType::Sand, 28..59, 0..30);
+        let mound = count_in(&engine, MaterialType::Sand, 1..28, 0..30);
+        let carried = ants.iter().filter(|&&ant| engine.entities.get_ant(ant).unwrap().carrying.is_some()).count();
+        assert!(mound >= 20, "only {} grains reached the nest", mound);
+        assert_eq!(sand_left + mound + carried, 220);
+    }
+
+    #[test]
+    fn test_ants_drown_and_burn() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.enable_rigid_bodies = false;
+        for x in 0..20 {
+            engine.add_particle(x, 30, MaterialType::Stone, None);
+        }
+        for y in 20..30 {
+            for x in 0..5 {
+                engine.add_particle(x, y, MaterialType::Water, None);
+            }
+        }
+        for (x, y) in [(13, 29), (13, 28), (14, 28), (15, 28), (15, 29)] {
+            engine.add_particle(x, y, MaterialType::Fire, None);
+        }
+        let swimmer = engine.spawn_ant(2, 28);
+        let scorched = engine.spawn_ant(14, 29);
+
+        // Fire kills within a second, water takes longer
+        let mut killed = Vec::new();
+        for _ in 0..60 {
+            killed.extend(engine.update_entities(1.0 / 60.0));
+        }
+        assert_eq!(killed, vec![scorched]);
+        for _ in 0..600 {
+            killed.extend(engine.update_entities(1.0 / 60.0));
+        }
+        assert_eq!(killed, vec![scorched, swimmer]);
+        assert_eq!(engine.entities.entity_count(), 0);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::{get_material_properties, Material, MaterialType};
+use std::collections::HashMap;
+
+/// Seconds of simulated time between compaction passes
+pub const DEFAULT_COMPACTION_INTERVAL: f32 = 1.0;
+/// A particle counts as asleep once it has been still for this many frames
+pub const COMPACTION_SETTLED_FRAMES: u8 = 30;
+/// Side length of the blocks checked for activity before a compaction pass touches them
+pub const COMPACTION_BLOCK_SIZE: usize = 16;
+
+/// A buried powder turns into `product` after carrying at least `min_load` (the summed
+/// density of the unbroken column above it) for `min_seconds` without moving.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionRule {
+    pub material: MaterialType,
+    pub product: MaterialType,
+    pub min_load: f32,
+    pub min_seconds: f32,
+}
+
+impl CompactionRule {
+    pub fn new(material: MaterialType, product: MaterialType, min_load: f32, min_seconds: f32) -> Self {
+        Self {
+            material,
+            product,
+            min_load,
+            min_seconds,
+        }
+    }
+
+    /// The rule in a material's compaction fields, if it compacts at all
+    pub fn from_material(material_type: MaterialType, material: &Material) -> Option<Self> {
+        let product = material.compacts_into?;
+        Some(Self::new(material_type, product, material.compaction_load, material.compaction_seconds))
+    }
+}
+
+/// Compaction rules consulted by `Simulation`. Passes run every `interval` seconds and
+/// only visit blocks where every particle has settled, so active regions cost nothing.
+#[derive(Debug, Clone)]
+pub struct CompactionRules {
+    rules: HashMap<MaterialType, CompactionRule>,
+    pub interval: f32,
+}
+
+impl CompactionRules {
+    /// No rules; compaction is disabled
+    pub fn new() -> Self {
+        Self {
+            rules: HashMap::new(),
+            interval: DEFAULT_COMPACTION_INTERVAL,
+        }
+    }
+
+    /// Rules for the built-in materials, from their compaction table in `materials.rs`
+    pub fn with_defaults() -> Self {
+        let mut rules = Self::new();
+        for material_type in MaterialType::BUILTIN {
+            if let Some(rule) = CompactionRule::from_material(material_type, &get_material_properties(material_type)) {
+                rules.register(rule);
+            }
+        }
+        rules
+    }
+
+    /// Rules for every material in `materials` that compacts, custom ones included
+    pub fn from_materials(materials: &MaterialRegistry) -> Self {
+        let mut rules = Self::new();
+        for material_type in materials.material_types() {
+            if let Some(rule) = CompactionRule::from_material(material_type, materials.get(material_type)) {
+                rules.register(rule);
+            }
+        }
+        rules
+    }
+
+    /// Add a rule, replacing any existing rule for the same material
+    pub fn register(&mut self, rule: CompactionRule) -> Option<CompactionRule> {
+        self.rules.insert(rule.material, rule)
+    }
+
+    pub fn remove(&mut self, material: MaterialType) -> Option<CompactionRule> {
+        self.rules.remove(&material)
+    }
+
+    pub fn get(&self, material: MaterialType) -> Option<&CompactionRule> {
+        self.rules.get(&material)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+}
+
+impl Default for CompactionRules {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+
+    fn fast_rules() -> CompactionRules {
+        let mut rules = CompactionRules::new();
+        rules.interval = 0.25;
+        rules.register(CompactionRule::new(MaterialType::Sand, MaterialType::Sandstone, 8.0, 1.0));
+        rules
+    }
+
+    fn fill(simulation: &mut Simulation, material: MaterialType) {
+        for y in 0..simulation.height {
+            for x in 0..simulation.width {
+                simulation.add_particle(x, y, material, None);
+            }
+        }
+    }
+
+    #[test]
+    fn test_buried_sand_compacts() {
+        let mut simulation = Simulation::new(8, 20);
+        *simulation.compaction_mut() = fast_rules();
+        fill(&mut simulation, MaterialType::Sand);
+
+        for _ in 0..240 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        let material_at = |y| simulation.get_particle(3, y).unwrap().material_type;
+        assert_eq!(material_at(19), MaterialType::Sandstone);
+        assert_eq!(material_at(10), MaterialType::Sandstone);
+        // Too little weight above the top layers
+        assert_eq!(material_at(0), MaterialType::Sand);
+        assert_eq!(material_at(4), MaterialType::Sand);
+        assert_eq!(simulation.get_state().particles.len(), 8 * 20);
+    }
+
+    #[test]
+    fn test_active_blocks_do_not_compact() {
+        let mut simulation = Simulation::new(8, 20);
+        *simulation.compaction_mut() = fast_rules();
+        fill(&mut simulation, MaterialType::Sand);
+
+        // Keep kicking the pile so it never falls asleep
+        for tick in 0..240 {
+            if tick % 20 == 0 {
+                simulation.get_particle_mut(3, 19).unwrap().settled_frames = 0;
+            }
+            simulation.update(1.0 / 60.0);
+        }
+
+        // Only the block holding the disturbed grain stays loose
+        assert_eq!(simulation.get_particle(3, 19).unwrap().material_type, MaterialType::Sand);
+        assert_eq!(simulation.get_particle(3, 16).unwrap().material_type, MaterialType::Sand);
+        assert_eq!(simulation.get_particle(3, 10).unwrap().material_type, MaterialType::Sandstone);
+    }
+
+    #[test]
+    fn test_buried_snow_turns_to_ice() {
+        let defaults = CompactionRules::with_defaults();
+        assert_eq!(defaults.get(MaterialType::Snow).unwrap().product, MaterialType::Ice);
+
+        let mut simulation = Simulation::new(8, 30);
+        let mut rules = CompactionRules::new();
+        rules.interval = 0.25;
+        rules.register(CompactionRule::new(MaterialType::Snow, MaterialType::Ice, 6.0, 1.0));
+        *simulation.compaction_mut() = rules;
+        fill(&mut simulation, MaterialType::Snow);
+
+        for _ in 0..240 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        // Snow is light, so it takes a deeper drift than sand to carry the load
+        let material_at = |y| simulation.get_particle(3, y).unwrap().material_type;
+        assert_eq!(material_at(29), MaterialType::Ice);
+        assert_eq!(material_at(22), MaterialType::Ice);
+        assert_eq!(material_at(10), MaterialType::Snow);
+    }
+
+    #[test]
+    fn test_custom_materials_compact() {
+        let materials = MaterialRegistry::from_toml_str(include_str!("../materials/example_pack.toml")).unwrap();
+        let guano = materials.lookup("Guano").unwrap();
+        let rules = CompactionRules::from_materials(&materials);
+        let rule = rules.get(guano).unwrap();
+        assert_eq!(rule.product, materials.lookup("Phosphorite").unwrap());
+        assert_eq!(rule.min_load, 15.0);
+        // The built-in table is still there
+        assert_eq!(rules.get(MaterialType::Sand).unwrap().product, MaterialType::Sandstone);
+        assert!(rules.get(MaterialType::Water).is_none());
+    }
+
+    #[test]
+    fn test_light_load_keeps_powder() {
+        let mut simulation = Simulation::new(8, 4);
+        *simulation.compaction_mut() = fast_rules();
+        fill(&mut simulation, MaterialType::Sand);
+
+        for _ in 0..240 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        let state = simulation.get_state();
+        assert!(state.particles.values().all(|p| p.material_type == MaterialType::Sand));
+    }
+}
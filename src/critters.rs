@@ -0,0 +1,284 @@
+use crate::chunk::ChunkManager;
+use crate::ecs::{contact_damage, Critter, CritterKind, EntityId, Health, Position, ECS};
+use crate::material_registry::MaterialRegistry;
+use crate::materials::{MaterialKind, MaterialType};
+use crate::simulation::Simulation;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Seconds between a critter's steps
+pub const CRITTER_STEP_SECONDS: f32 = 0.15;
+/// Hit points a newly spawned critter has
+pub const CRITTER_HEALTH: f32 = 3.0;
+/// Health a critter loses per second out of its element: fish out of water, birds in
+/// anything but air
+pub const SMOTHERING_DAMAGE: f32 = 1.0;
+/// Water at least this hot scalds fish
+pub const FISH_LETHAL_TEMP: f32 = 60.0;
+/// Health a fish loses per second in scalding water
+pub const SCALDING_DAMAGE: f32 = 5.0;
+/// How far a stranded fish looks for water to flop back into
+pub const FISH_SENSE_RADIUS: i64 = 4;
+/// How far ahead, in cells, a bird looks for something to steer around
+pub const BIRD_LOOKAHEAD: f32 = 4.0;
+/// Chance per step that a critter turns for no reason
+const WANDER_CHANCE: f64 = 0.1;
+const NEIGHBOR_OFFSETS: [(i64, i64); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+/// Directions a bird flies in; always moving sideways, as birds don't hover
+const FLIGHT_HEADINGS: [(i64, i64); 6] = [(-1, -1), (-1, 0), (-1, 1), (1, -1), (1, 0), (1, 1)];
+
+/// Add a critter with `CRITTER_HEALTH` hit points in the cell at `(x, y)`
+pub fn spawn_critter(ecs: &mut ECS, x: i64, y: i64, kind: CritterKind) -> EntityId {
+    let entity_id = ecs.create_entity();
+    ecs.add_position(entity_id, Position { x: x as f64 + 0.5, y: y as f64 + 0.5, z: 0.0 });
+    ecs.add_health(entity_id, Health { current: CRITTER_HEALTH, max: CRITTER_HEALTH, regeneration_rate: 0.0 });
+    ecs.add_critter(entity_id, Critter::new(kind));
+    entity_id
+}
+
+/// The particle grid critters live in: the engine's chunks, or a `Simulation` for hosts
+/// that have no chunk storage, like the server
+pub trait CritterGrid {
+    fn material_at(&self, x: i64, y: i64) -> Option<MaterialType>;
+    fn temp_at(&self, x: i64, y: i64) -> Option<f32>;
+    /// Cell of the particle of `material` closest to `(x, y)`
+    fn nearest_cell(&self, x: i64, y: i64, material: MaterialType) -> Option<(i64, i64)>;
+    /// How far a ray goes before reaching an occupied cell, if it does within `max_distance`
+    fn hit_distance(&self, from: (f32, f32), direction: (f32, f32), max_distance: f32) -> Option<f32>;
+}
+
+impl CritterGrid for ChunkManager {
+    fn material_at(&self, x: i64, y: i64) -> Option<MaterialType> {
+        ChunkManager::material_at(self, x, y)
+    }
+
+    fn temp_at(&self, x: i64, y: i64) -> Option<f32> {
+        self.get_particle(x, y).map(|particle| particle.temp)
+    }
+
+    fn nearest_cell(&self, x: i64, y: i64, material: MaterialType) -> Option<(i64, i64)> {
+        self.nearest_material(x, y, material).map(|(cell, _)| cell)
+    }
+
+    fn hit_distance(&self, from: (f32, f32), direction: (f32, f32), max_distance: f32) -> Option<f32> {
+        self.raycast(from, direction, max_distance).map(|hit| hit.distance)
+    }
+}
+
+impl CritterGrid for Simulation {
+    fn material_at(&self, x: i64, y: i64) -> Option<MaterialType> {
+        let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+        self.get_particle(x, y).map(|particle| particle.material_type)
+    }
+
+    fn temp_at(&self, x: i64, y: i64) -> Option<f32> {
+        let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+        self.get_particle(x, y).map(|particle| particle.temp)
+    }
+
+    fn nearest_cell(&self, x: i64, y: i64, material: MaterialType) -> Option<(i64, i64)> {
+        let (x, y) = (usize::try_from(x).ok()?, usize::try_from(y).ok()?);
+        self.nearest_material(x, y, material).map(|particle| (particle.x as i64, particle.y as i64))
+    }
+
+    fn hit_distance(&self, from: (f32, f32), direction: (f32, f32), max_distance: f32) -> Option<f32> {
+        self.raycast(from, direction, max_distance).map(|hit| hit.distance)
+    }
+}
+
+fn is_water(material: Option<MaterialType>) -> bool {
+    matches!(material, Some(MaterialType::Water | MaterialType::SaltWater))
+}
+
+/// Step every critter in the ECS across the particle grid. Fish swim between water cells
+/// only, so they stay within the body of water they were put in; once it drains they fall,
+/// flop towards the nearest water within `FISH_SENSE_RADIUS` and suffocate, and water
+/// heated past `FISH_LETHAL_TEMP` scalds them. Birds fly through empty cells, raycasting
+/// `BIRD_LOOKAHEAD` cells along their heading and turning for the clearest way when
+/// something is in it, and smother in liquids or buried in powder. Both take
+/// `contact_damage` from the cells they touch.
+///
+/// Returns the critters whose health ran out this step; the caller decides what to do
+/// with them.
+pub fn critter_system(
+    ecs: &mut ECS,
+    delta_time: f32,
+    materials: &MaterialRegistry,
+    grid: &impl CritterGrid,
+    rng: &mut impl Rng,
+) -> Vec<EntityId> {
+    let critters: Vec<EntityId> = ecs.get_active_entities().iter()
+        .copied()
+        .filter(|&entity_id| ecs.get_critter(entity_id).is_some())
+        .collect();
+
+    let mut killed = Vec::new();
+    for entity_id in critters {
+        let (Some(position), Some(mut critter)) = (ecs.get_position(entity_id), ecs.get_critter(entity_id).cloned()) else {
+            continue;
+        };
+        let (mut x, mut y) = (position.x.floor() as i64, position.y.floor() as i64);
+
+        let here_material = grid.material_at(x, y);
+        let mut damage = NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| grid.material_at(x + dx, y + dy))
+            .chain(here_material)
+            .map(contact_damage)
+            .fold(0.0, f32::max);
+        let in_element = match critter.kind {
+            CritterKind::Fish => is_water(here_material),
+            CritterKind::Bird => here_material.is_none_or(|material| materials.get(material).kind == MaterialKind::Gas),
+        };
+        if !in_element {
+            damage += SMOTHERING_DAMAGE;
+        }
+        if critter.kind == CritterKind::Fish && in_element && grid.temp_at(x, y).is_some_and(|temp| temp >= FISH_LETHAL_TEMP) {
+            damage += SCALDING_DAMAGE;
+        }
+        if let Some(health) = ecs.get_health_mut(entity_id) {
+            health.current -= damage * delta_time;
+            if health.current <= 0.0 {
+                killed.push(entity_id);
+                continue;
+            }
+        }
+
+        critter.step_timer -= delta_time;
+        if critter.step_timer > 0.0 {
+            ecs.add_critter(entity_id, critter);
+            continue;
+        }
+        critter.step_timer += CRITTER_STEP_SECONDS;
+
+        let (hx, hy) = critter.heading;
+        match critter.kind {
+            CritterKind::Fish if in_element => {
+                let water: Vec<(i64, i64)> = NEIGHBOR_OFFSETS.iter()
+                    .copied()
+                    .filter(|&(dx, dy)| is_water(grid.material_at(x + dx, y + dy)))
+                    .collect();
+                if !water.contains(&(hx, hy)) || rng.gen_bool(WANDER_CHANCE) {
+                    critter.heading = water.choose(rng).copied().unwrap_or((-hx, hy));
+                }
+                if water.contains(&critter.heading) {
+                    (x, y) = (x + critter.heading.0, y + critter.heading.1);
+                }
+            }
+            CritterKind::Fish => {
+                if grid.material_at(x, y + 1).is_none() {
+                    y += 1;
+                } else if let Some((wx, wy)) = [MaterialType::Water, MaterialType::SaltWater].into_iter()
+                    .filter_map(|material| grid.nearest_cell(x, y, material))
+                    .filter(|&(wx, wy)| (wx - x).abs().max((wy - y).abs()) <= FISH_SENSE_RADIUS)
+                    .min_by_key(|&(wx, wy)| (wx - x).pow(2) + (wy - y).pow(2))
+                {
+                    // Flop one cell towards it
+                    let (dx, dy) = ((wx - x).signum(), (wy - y).signum());
+                    let target = grid.material_at(x + dx, y + dy);
+                    if target.is_none() || is_water(target) {
+                        (x, y) = (x + dx, y + dy);
+                    }
+                }
+            }
+            CritterKind::Bird => {
+                let from = (x as f32 + 0.5, y as f32 + 0.5);
+                let clearance = |(dx, dy): (i64, i64)| {
+                    grid.hit_distance(from, (dx as f32, dy as f32), BIRD_LOOKAHEAD).unwrap_or(f32::INFINITY)
+                };
+                if clearance((hx, hy)).is_finite() || rng.gen_bool(WANDER_CHANCE) {
+                    let mut headings = FLIGHT_HEADINGS;
+                    headings.shuffle(rng);
+                    critter.heading = headings.into_iter()
+                        .max_by(|&a, &b| clearance(a).total_cmp(&clearance(b)))
+                        .unwrap_or((-hx, hy));
+                }
+                let (dx, dy) = critter.heading;
+                if grid.material_at(x + dx, y + dy).is_none() {
+                    (x, y) = (x + dx, y + dy);
+                }
+            }
+        }
+
+        if let Some(position) = ecs.get_position_mut(entity_id) {
+            (position.x, position.y) = (x as f64 + 0.5, y as f64 + 0.5);
+        }
+        ecs.add_critter(entity_id, critter);
+    }
+    killed
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::CritterKind;
+    use crate::engine_v2::AdvancedPhysicsEngine;
+    use crate::materials::MaterialType;
+
+    fn cell_of(engine: &AdvancedPhysicsEngine, entity_id: crate::ecs::EntityId) -> (i64, i64) {
+        let position = engine.entities.get_position(entity_id).unwrap();
+        (position.x.floor() as i64, position.y.floor() as i64)
+    }
+
+    #[test]
+    fn test_fish_stay_in_their_pond_and_die_when_it_drains() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.set_seed(2);
+        // Two ponds side by side, split by a stone wall
+        for x in 0..20 {
+            engine.add_particle(x, 30, MaterialType::Stone, None);
+        }
+        for y in 24..30 {
+            for x in [0, 9, 10, 19] {
+                engine.add_particle(x, y, MaterialType::Stone, None);
+            }
+            for x in (1..9).chain(11..19) {
+                engine.add_particle(x, y, MaterialType::Water, None);
+            }
+        }
+        let fish = engine.spawn_critter(4, 27, CritterKind::Fish);
+        let stranded = engine.spawn_critter(14, 27, CritterKind::Fish);
+
+        let mut killed = Vec::new();
+        for step in 0..300 {
+            if step == 60 {
+                for y in 24..30 {
+                    for x in 11..19 {
+                        engine.remove_particle(x, y);
+                    }
+                }
+            }
+            killed.extend(engine.update_entities(1.0 / 60.0));
+            if let Some((x, y)) = engine.entities.entity_exists(fish).then(|| cell_of(&engine, fish)) {
+                assert!((1..9).contains(&x) && (24..30).contains(&y), "fish left its pond at {:?}", (x, y));
+            }
+        }
+        assert_eq!(killed, vec![stranded]);
+        assert!(engine.entities.entity_exists(fish));
+    }
+
+    #[test]
+    fn test_birds_steer_around_walls_and_fish_boil() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.set_seed(3);
+        // A closed stone box with a bird inside and a pot of boiling water below it
+        for i in 0..20 {
+            for (x, y) in [(i, 0), (i, 19), (0, i), (19, i)] {
+                engine.add_particle(x, y, MaterialType::Stone, None);
+            }
+        }
+        for x in 1..19 {
+            engine.add_particle(x, 18, MaterialType::Water, Some(90.0));
+        }
+        let bird = engine.spawn_critter(10, 8, CritterKind::Bird);
+        let fish = engine.spawn_critter(10, 18, CritterKind::Fish);
+
+        let mut killed = Vec::new();
+        for _ in 0..600 {
+            killed.extend(engine.update_entities(1.0 / 60.0));
+            if let Some((x, y)) = engine.entities.entity_exists(bird).then(|| cell_of(&engine, bird)) {
+                assert!(engine.get_particle(x, y).is_none(), "bird flew into {:?}", (x, y));
+            }
+        }
+        assert_eq!(killed, vec![fish]);
+        assert!(engine.entities.entity_exists(bird));
+    }
+}
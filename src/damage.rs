@@ -0,0 +1,97 @@
+use crate::explosion;
+use crate::materials::{Material, MaterialType, HEALTH_PER_HARDNESS};
+use crate::particle::Particle;
+use serde::{Deserialize, Serialize};
+
+/// Visible crack stages a damaged solid goes through before it breaks
+pub const CRACK_STAGES: u8 = 3;
+/// Fraction of a solid's color lost per crack stage
+pub const CRACK_DARKENING: f32 = 0.12;
+/// Damage a blast at full strength does to a solid
+pub const BLAST_DAMAGE: f32 = explosion::BLAST_BREAK_POWER * HEALTH_PER_HARDNESS;
+/// Damage acid does to a solid each time it eats at it
+pub const ACID_DAMAGE: f32 = 25.0;
+/// Damage a rigid body impact does per unit of impulse, shared between the solid cells it hit
+pub const IMPACT_DAMAGE: f32 = 0.5;
+
+/// What a hit did to a cell
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DamageOutcome {
+    /// The cell is empty or its material doesn't wear down
+    Immune,
+    /// The cell took the damage and still stands
+    Damaged,
+    /// The cell ran out of health and broke into rubble
+    Broken,
+}
+
+/// Take `amount` points of health from a solid, tracking what it has lost in its payload.
+/// A Broken particle is left as it was; replace it with `rubble_of` its material.
+pub fn apply_damage(particle: &mut Particle, material: &Material, amount: f32) -> DamageOutcome {
+    if particle.material_type == MaterialType::Empty || material.max_health <= 0.0 || amount <= 0.0 {
+        return DamageOutcome::Immune;
+    }
+    let damage = particle.damage(material) + amount / material.max_health;
+    if damage >= 1.0 {
+        return DamageOutcome::Broken;
+    }
+    particle.payload = damage;
+    particle.invalidate_color_cache();
+    DamageOutcome::Damaged
+}
+
+/// Whether a blast of `strength` breaks this particle. Solids with health take
+/// `BLAST_DAMAGE` scaled by the strength and only break once it runs out; anything else
+/// breaks as `explosion::breaks` decides.
+pub fn blast_breaks(particle: &mut Particle, material: &Material, strength: f32) -> bool {
+    match material.max_health > 0.0 {
+        true => apply_damage(particle, material, strength * BLAST_DAMAGE) == DamageOutcome::Broken,
+        false => explosion::breaks(particle.material_type, material, strength),
+    }
+}
+
+/// What a solid breaks into once it runs out of health: the built-ins' blast debris, and
+/// for custom materials whatever they crack into, or Sand
+pub fn rubble_of(material_type: MaterialType, material: &Material) -> MaterialType {
+    match material_type {
+        MaterialType::Custom(_) => material.cracks_into.unwrap_or(MaterialType::Sand),
+        builtin => explosion::debris_of(builtin),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::get_material_properties;
+
+    #[test]
+    fn test_damage_cracks_stone_in_stages_then_breaks_it() {
+        let stone = get_material_properties(MaterialType::Stone);
+        let mut particle = Particle::new(0, 0, MaterialType::Stone, None);
+        let intact = particle.color_with(&stone);
+
+        let mut stages = vec![particle.crack_stage(&stone)];
+        let mut outcome = DamageOutcome::Damaged;
+        while outcome == DamageOutcome::Damaged {
+            outcome = apply_damage(&mut particle, &stone, stone.max_health / 4.0);
+            stages.push(particle.crack_stage(&stone));
+        }
+        assert_eq!(outcome, DamageOutcome::Broken);
+        assert_eq!(stages, vec![0, 1, 2, 3, 3]);
+        assert!(particle.color_with(&stone).iter().zip(intact).all(|(&cracked, intact)| cracked < intact));
+        assert_eq!(rubble_of(MaterialType::Stone, &stone), MaterialType::Sand);
+    }
+
+    #[test]
+    fn test_materials_without_health_are_immune() {
+        let iron = get_material_properties(MaterialType::Iron);
+        let mut particle = Particle::new(0, 0, MaterialType::Iron, None);
+        assert_eq!(apply_damage(&mut particle, &iron, 1000.0), DamageOutcome::Immune);
+        assert_eq!(particle.damage(&iron), 0.0);
+
+        let sand = get_material_properties(MaterialType::Sand);
+        assert_eq!(sand.max_health, 0.0);
+        assert!(get_material_properties(MaterialType::Glass).max_health > 0.0);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::physics::AMBIENT_TEMP;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::TAU;
+
+/// Real seconds in one in-game day unless configured otherwise
+pub const DEFAULT_DAY_LENGTH: f32 = 600.0;
+/// Air temperature at noon
+pub const NOON_TEMP: f32 = 25.0;
+/// Air temperature at midnight
+pub const MIDNIGHT_TEMP: f32 = 5.0;
+/// Ambient light at midnight; noon is fully lit
+pub const MIDNIGHT_LIGHT: f32 = 0.1;
+/// Hour the clock starts at
+const DEFAULT_START_HOUR: f32 = 8.0;
+
+/// In-game time of day, advanced by simulated seconds. Air is warmest and light
+/// brightest at noon, coldest and darkest at midnight.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldClock {
+    /// Hours since midnight, from 0 up to 24
+    hours: f32,
+    /// Real seconds per in-game day
+    day_length: f32,
+    /// Whole days passed since the clock started
+    day: u64,
+}
+
+impl WorldClock {
+    /// A clock at 8 in the morning whose days last `day_length` seconds
+    pub fn new(day_length: f32) -> Self {
+        let mut clock = Self { hours: DEFAULT_START_HOUR, day_length: DEFAULT_DAY_LENGTH, day: 0 };
+        clock.set_day_length(day_length);
+        clock
+    }
+
+    pub fn day_length(&self) -> f32 {
+        self.day_length
+    }
+
+    /// Real seconds per day; lengths that aren't positive and finite are ignored
+    pub fn set_day_length(&mut self, day_length: f32) {
+        if day_length > 0.0 && day_length.is_finite() {
+            self.day_length = day_length;
+        }
+    }
+
+    /// Hours since midnight, from 0 up to 24
+    pub fn time_of_day(&self) -> f32 {
+        self.hours
+    }
+
+    /// Jump to `hours` since midnight, wrapping into the same day
+    pub fn set_time_of_day(&mut self, hours: f32) {
+        if hours.is_finite() {
+            self.hours = hours.rem_euclid(24.0);
+        }
+    }
+
+    pub fn day(&self) -> u64 {
+        self.day
+    }
+
+    /// Move the clock on by `delta_time` real seconds
+    pub fn advance(&mut self, delta_time: f32) {
+        let hours = self.hours + delta_time.max(0.0) * 24.0 / self.day_length;
+        self.day += (hours / 24.0).floor() as u64;
+        self.hours = hours.rem_euclid(24.0);
+    }
+
+    /// How high the sun is, from 0 at midnight to 1 at noon
+    pub fn daylight(&self) -> f32 {
+        (1.0 - (self.hours / 24.0 * TAU).cos()) / 2.0
+    }
+
+    pub fn is_night(&self) -> bool {
+        !(6.0..18.0).contains(&self.hours)
+    }
+
+    /// Temperature of open air at this time of day
+    pub fn ambient_temperature(&self) -> f32 {
+        MIDNIGHT_TEMP + (NOON_TEMP - MIDNIGHT_TEMP) * self.daylight()
+    }
+
+    /// Ambient light level for the lighting subsystem at this time of day
+    pub fn light_level(&self) -> f32 {
+        MIDNIGHT_LIGHT + (1.0 - MIDNIGHT_LIGHT) * self.daylight()
+    }
+}
+
+impl Default for WorldClock {
+    fn default() -> Self {
+        Self::new(DEFAULT_DAY_LENGTH)
+    }
+}
+
+impl Simulation {
+    /// Run a day/night cycle: every update moves `clock` on, sets the open-air
+    /// temperature particles exchange heat with and, while lighting is on, the ambient
+    /// light level
+    pub fn enable_day_cycle(&mut self, clock: WorldClock) {
+        self.clock = Some(clock);
+        self.apply_time_of_day();
+    }
+
+    /// Stop the clock and return the air to its constant default temperature; the
+    /// ambient light stays where the cycle left it
+    pub fn disable_day_cycle(&mut self) {
+        self.clock = None;
+        self.physics.set_ambient_temp(AMBIENT_TEMP);
+    }
+
+    /// The clock; None while there is no day/night cycle
+    pub fn clock(&self) -> Option<&WorldClock> {
+        self.clock.as_ref()
+    }
+
+    /// Hours since midnight; None while there is no day/night cycle
+    pub fn time_of_day(&self) -> Option<f32> {
+        self.clock.as_ref().map(WorldClock::time_of_day)
+    }
+
+    /// Jump the clock to `hours` since midnight, returning false while there is no
+    /// day/night cycle
+    pub fn set_time_of_day(&mut self, hours: f32) -> bool {
+        let Some(clock) = &mut self.clock else {
+            return false;
+        };
+        clock.set_time_of_day(hours);
+        self.apply_time_of_day();
+        true
+    }
+
+    pub(crate) fn step_clock(&mut self, delta_time: f32) {
+        if let Some(clock) = &mut self.clock {
+            clock.advance(delta_time);
+            self.apply_time_of_day();
+        }
+    }
+
+    fn apply_time_of_day(&mut self) {
+        let Some(clock) = &self.clock else {
+            return;
+        };
+        self.physics.set_ambient_temp(clock.ambient_temperature());
+        if let Some(light_map) = &mut self.light_map {
+            light_map.ambient = clock.light_level();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    #[test]
+    fn test_clock_wraps_days() {
+        let mut clock = WorldClock::new(240.0);
+        assert_eq!(clock.time_of_day(), 8.0);
+        clock.advance(40.0);
+        assert!((clock.time_of_day() - 12.0).abs() < 1e-3);
+        assert!((clock.daylight() - 1.0).abs() < 1e-4);
+        assert_eq!(clock.ambient_temperature(), NOON_TEMP);
+        assert!(!clock.is_night());
+
+        clock.advance(120.0);
+        assert!(clock.time_of_day().abs() < 1e-3);
+        assert_eq!(clock.day(), 1);
+        assert!(clock.is_night());
+        assert!((clock.light_level() - MIDNIGHT_LIGHT).abs() < 1e-4);
+
+        clock.set_time_of_day(-3.0);
+        assert_eq!(clock.time_of_day(), 21.0);
+        clock.set_day_length(0.0);
+        assert_eq!(clock.day_length(), 240.0);
+    }
+
+    #[test]
+    fn test_nights_cool_the_air_and_dim_the_light() {
+        let mut simulation = Simulation::new(16, 16);
+        simulation.add_particle(8, 15, MaterialType::Stone, Some(20.0));
+        simulation.enable_lighting(0.5);
+        assert!(!simulation.set_time_of_day(0.0));
+
+        let mut clock = WorldClock::new(60.0);
+        clock.set_time_of_day(0.0);
+        simulation.enable_day_cycle(clock);
+        assert_eq!(simulation.light_map().unwrap().ambient, MIDNIGHT_LIGHT);
+        for _ in 0..60 {
+            simulation.update(1.0 / 60.0);
+        }
+        assert!(simulation.get_particle(8, 15).unwrap().temp < 20.0);
+        assert!(simulation.time_of_day().unwrap() > 0.3);
+
+        assert!(simulation.set_time_of_day(12.0));
+        simulation.update(1.0 / 60.0);
+        assert!(simulation.light_map().unwrap().ambient > 0.99);
+
+        simulation.disable_day_cycle();
+        assert_eq!(simulation.time_of_day(), None);
+    }
+}
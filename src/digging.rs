@@ -0,0 +1,182 @@
+use crate::materials::{MaterialType, ToolTier};
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Seconds of digging per point of hardness at speed 1
+pub const DIG_SECONDS_PER_HARDNESS: f32 = 0.5;
+/// Time every dig takes regardless of hardness, at speed 1
+pub const DIG_BASE_SECONDS: f32 = 0.05;
+/// Particles hotter than this burn bare hands that pick them up
+pub const SAFE_HANDLING_TEMP: f32 = 60.0;
+/// Health lost per degree above `SAFE_HANDLING_TEMP` when collecting by hand
+pub const BURN_DAMAGE_PER_DEGREE: f32 = 0.05;
+
+/// Result of one `Digger::dig` call
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DigOutcome {
+    /// Nothing diggable there: empty, gas, or out of bounds
+    Nothing,
+    /// The current tool can't break the material; `required` is None when no tool can
+    TooHard { material: MaterialType, required: Option<ToolTier> },
+    /// Still working on the cell, with completion in 0..1
+    InProgress(f32),
+    /// The particle was removed and added to the digger's inventory. `damage` is the
+    /// health lost to a hot pickup, for the caller to take off the player's `Health`.
+    Collected { material: MaterialType, damage: f32 },
+}
+
+/// Seconds `tool` needs to break a material of `hardness`, or None if it can't
+pub fn dig_seconds(tool: ToolTier, hardness: f32) -> Option<f32> {
+    tool.can_break(hardness)
+        .then(|| (DIG_BASE_SECONDS + hardness * DIG_SECONDS_PER_HARDNESS) / tool.speed())
+}
+
+/// Health lost collecting a particle at `temp` with `tool`; only bare hands get burned
+pub fn handling_damage(tool: ToolTier, temp: f32) -> f32 {
+    if tool != ToolTier::Hands {
+        return 0.0;
+    }
+    (temp - SAFE_HANDLING_TEMP).max(0.0) * BURN_DAMAGE_PER_DEGREE
+}
+
+/// A player's dig/collect action. Digging a cell takes time set by the material's
+/// hardness and the held tool; progress resets when the target changes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Digger {
+    pub tool: ToolTier,
+    /// Collected particles by material
+    pub inventory: HashMap<MaterialType, u32>,
+    target: Option<(usize, usize, MaterialType)>,
+    progress: f32,
+}
+
+impl Digger {
+    pub fn new(tool: ToolTier) -> Self {
+        Self {
+            tool,
+            ..Self::default()
+        }
+    }
+
+    /// Dig at `(x, y)` for `delta_time` seconds, using the simulation's material registry
+    /// for hardness
+    pub fn dig(&mut self, simulation: &mut Simulation, x: usize, y: usize, delta_time: f32) -> DigOutcome {
+        let Some(particle) = simulation.get_particle(x, y) else {
+            self.reset();
+            return DigOutcome::Nothing;
+        };
+        let (material_type, temp) = (particle.material_type, particle.temp);
+        let material = simulation.materials().get(material_type);
+        if material.is_gas(material_type) {
+            self.reset();
+            return DigOutcome::Nothing;
+        }
+
+        let Some(seconds) = dig_seconds(self.tool, material.hardness) else {
+            self.reset();
+            return DigOutcome::TooHard {
+                material: material_type,
+                required: ToolTier::required_for(material.hardness),
+            };
+        };
+
+        if self.target != Some((x, y, material_type)) {
+            self.target = Some((x, y, material_type));
+            self.progress = 0.0;
+        }
+        self.progress += delta_time;
+        if self.progress < seconds {
+            return DigOutcome::InProgress(self.progress / seconds);
+        }
+
+        self.reset();
+        simulation.remove_particle(x, y);
+        *self.inventory.entry(material_type).or_insert(0) += 1;
+        DigOutcome::Collected { material: material_type, damage: handling_damage(self.tool, temp) }
+    }
+
+    /// Number of collected particles of `material_type`
+    pub fn collected(&self, material_type: MaterialType) -> u32 {
+        self.inventory.get(&material_type).copied().unwrap_or(0)
+    }
+
+    /// Stop digging, dropping any partial progress
+    pub fn reset(&mut self) {
+        self.target = None;
+        self.progress = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material_registry::MaterialRegistry;
+    use std::sync::Arc;
+
+    fn dig_until_done(digger: &mut Digger, simulation: &mut Simulation, x: usize, y: usize) -> (DigOutcome, usize) {
+        for frame in 1..=600 {
+            let outcome = digger.dig(simulation, x, y, 1.0 / 60.0);
+            if !matches!(outcome, DigOutcome::InProgress(_)) {
+                return (outcome, frame);
+            }
+        }
+        panic!("dig never finished");
+    }
+
+    #[test]
+    fn test_tool_tiers() {
+        let mut simulation = Simulation::new(10, 10);
+        simulation.add_particle(1, 9, MaterialType::Sand, None);
+        simulation.add_particle(2, 9, MaterialType::Stone, None);
+        simulation.add_particle(3, 9, MaterialType::Iron, None);
+
+        let mut hands = Digger::new(ToolTier::Hands);
+        assert_eq!(hands.dig(&mut simulation, 2, 9, 1.0), DigOutcome::TooHard {
+            material: MaterialType::Stone,
+            required: Some(ToolTier::Pickaxe),
+        });
+        let (outcome, _) = dig_until_done(&mut hands, &mut simulation, 1, 9);
+        assert_eq!(outcome, DigOutcome::Collected { material: MaterialType::Sand, damage: 0.0 });
+        assert_eq!(hands.collected(MaterialType::Sand), 1);
+        assert!(simulation.get_particle(1, 9).is_none());
+
+        let mut pickaxe = Digger::new(ToolTier::Pickaxe);
+        assert_eq!(dig_until_done(&mut pickaxe, &mut simulation, 2, 9).0, DigOutcome::Collected { material: MaterialType::Stone, damage: 0.0 });
+        assert!(matches!(pickaxe.dig(&mut simulation, 3, 9, 1.0), DigOutcome::TooHard { required: None, .. }));
+        assert_eq!(pickaxe.dig(&mut simulation, 5, 5, 1.0), DigOutcome::Nothing);
+    }
+
+    #[test]
+    fn test_shovel_digs_sand_faster() {
+        let mut simulation = Simulation::new(10, 10);
+        simulation.add_particle(1, 9, MaterialType::Sand, None);
+        simulation.add_particle(2, 9, MaterialType::Sand, None);
+
+        let (_, by_hand) = dig_until_done(&mut Digger::new(ToolTier::Hands), &mut simulation, 1, 9);
+        let (_, by_shovel) = dig_until_done(&mut Digger::new(ToolTier::Shovel), &mut simulation, 2, 9);
+        assert!(by_shovel * 3 <= by_hand, "shovel {} frames, hands {}", by_shovel, by_hand);
+    }
+
+    #[test]
+    fn test_hardness_from_registry() {
+        let registry = MaterialRegistry::from_toml_str("[[material]]\nname = \"Iron\"\nhardness = 1.0\n").unwrap();
+        let mut simulation = Simulation::with_materials(10, 10, Arc::new(registry));
+        simulation.add_particle(4, 9, MaterialType::Iron, None);
+
+        let mut shovel = Digger::new(ToolTier::Shovel);
+        assert_eq!(dig_until_done(&mut shovel, &mut simulation, 4, 9).0, DigOutcome::Collected { material: MaterialType::Iron, damage: 0.0 });
+    }
+
+    #[test]
+    fn test_hot_pickup_burns_bare_hands() {
+        let mut simulation = Simulation::new(10, 10);
+        simulation.add_particle(1, 9, MaterialType::Sand, Some(460.0));
+        simulation.add_particle(2, 9, MaterialType::Sand, Some(460.0));
+
+        let (outcome, _) = dig_until_done(&mut Digger::new(ToolTier::Hands), &mut simulation, 1, 9);
+        assert!(matches!(outcome, DigOutcome::Collected { damage, .. } if damage > 15.0));
+        let (outcome, _) = dig_until_done(&mut Digger::new(ToolTier::Shovel), &mut simulation, 2, 9);
+        assert!(matches!(outcome, DigOutcome::Collected { damage, .. } if damage == 0.0));
+    }
+}
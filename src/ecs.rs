@@ -1,5 +1,8 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::{MaterialKind, MaterialType};
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::ops::RangeInclusive;
 use serde::{Deserialize, Serialize};
 
 pub type EntityId = u32;
@@ -18,6 +21,9 @@ pub struct ECS {
     inventories: Vec<Option<Inventory>>,
     players: Vec<Option<Player>>,
     tile_entities: Vec<Option<TileEntityComponent>>,
+    colliders: Vec<Option<Collider>>,
+    ants: Vec<Option<Ant>>,
+    critters: Vec<Option<Critter>>,
 }
 
 /// Core component types
@@ -42,6 +48,74 @@ pub struct Health {
     pub regeneration_rate: f32,
 }
 
+/// Axis-aligned box, in cells, centred on an entity's `Position`, that collides with
+/// the particle grid in `terrain_physics_system`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collider {
+    pub width: f64,
+    pub height: f64,
+    /// Relative to water, which is 1.0; entities float in denser liquids
+    pub density: f32,
+    /// Whether the entity came to rest on something during the last step
+    #[serde(default)]
+    pub on_ground: bool,
+}
+
+impl Collider {
+    pub fn new(width: f64, height: f64, density: f32) -> Self {
+        Self { width, height, density, on_ground: false }
+    }
+}
+
+/// A critter that walks the particle grid one cell at a time, tunnelling through powders
+/// and carrying what it digs back to its nest; see `colony::ant_system`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ant {
+    /// Cell the ant carries its diggings back to
+    pub nest: (i64, i64),
+    /// Material of the particle the ant is carrying, if any
+    pub carrying: Option<MaterialType>,
+    /// Column direction it is walking in, -1 or 1
+    pub heading: i64,
+    /// Seconds until the ant's next step
+    #[serde(default)]
+    pub step_timer: f32,
+}
+
+impl Ant {
+    pub fn new(nest: (i64, i64)) -> Self {
+        Self { nest, carrying: None, heading: 1, step_timer: 0.0 }
+    }
+}
+
+/// What kind of ambient creature a `Critter` is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CritterKind {
+    /// Swims through water and suffocates out of it
+    Fish,
+    /// Flies through empty air, steering clear of anything in its way
+    Bird,
+}
+
+/// An ambient creature that wanders the particle grid a cell at a time; see
+/// `critters::critter_system`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Critter {
+    pub kind: CritterKind,
+    /// Cell offset it moves by each step, each axis -1, 0 or 1
+    pub heading: (i64, i64),
+    /// Seconds until the critter's next step
+    #[serde(default)]
+    pub step_timer: f32,
+}
+
+impl Critter {
+    pub fn new(kind: CritterKind) -> Self {
+        Self { kind, heading: (1, 0), step_timer: 0.0 }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Inventory {
     pub items: HashMap<String, u32>,
@@ -110,6 +184,15 @@ impl ECS {
             if let Some(slot) = self.tile_entities.get_mut(entity_id as usize) {
                 *slot = None;
             }
+            if let Some(slot) = self.colliders.get_mut(entity_id as usize) {
+                *slot = None;
+            }
+            if let Some(slot) = self.ants.get_mut(entity_id as usize) {
+                *slot = None;
+            }
+            if let Some(slot) = self.critters.get_mut(entity_id as usize) {
+                *slot = None;
+            }
             
             true
         } else {
@@ -148,6 +231,15 @@ impl ECS {
         if self.tile_entities.len() < required_size {
             self.tile_entities.resize(required_size, None);
         }
+        if self.colliders.len() < required_size {
+            self.colliders.resize(required_size, None);
+        }
+        if self.ants.len() < required_size {
+            self.ants.resize(required_size, None);
+        }
+        if self.critters.len() < required_size {
+            self.critters.resize(required_size, None);
+        }
     }
 
     // Component accessors - Position
@@ -256,6 +348,60 @@ impl ECS {
         self.tile_entities.get_mut(entity_id as usize)?.as_mut()
     }
 
+    // Component accessors - Collider
+    pub fn add_collider(&mut self, entity_id: EntityId, collider: Collider) -> bool {
+        if !self.entity_exists(entity_id) {
+            return false;
+        }
+        self.ensure_capacity(entity_id);
+        self.colliders[entity_id as usize] = Some(collider);
+        true
+    }
+
+    pub fn get_collider(&self, entity_id: EntityId) -> Option<&Collider> {
+        self.colliders.get(entity_id as usize)?.as_ref()
+    }
+
+    pub fn get_collider_mut(&mut self, entity_id: EntityId) -> Option<&mut Collider> {
+        self.colliders.get_mut(entity_id as usize)?.as_mut()
+    }
+
+    // Component accessors - Ant
+    pub fn add_ant(&mut self, entity_id: EntityId, ant: Ant) -> bool {
+        if !self.entity_exists(entity_id) {
+            return false;
+        }
+        self.ensure_capacity(entity_id);
+        self.ants[entity_id as usize] = Some(ant);
+        true
+    }
+
+    pub fn get_ant(&self, entity_id: EntityId) -> Option<&Ant> {
+        self.ants.get(entity_id as usize)?.as_ref()
+    }
+
+    pub fn get_ant_mut(&mut self, entity_id: EntityId) -> Option<&mut Ant> {
+        self.ants.get_mut(entity_id as usize)?.as_mut()
+    }
+
+    // Component accessors - Critter
+    pub fn add_critter(&mut self, entity_id: EntityId, critter: Critter) -> bool {
+        if !self.entity_exists(entity_id) {
+            return false;
+        }
+        self.ensure_capacity(entity_id);
+        self.critters[entity_id as usize] = Some(critter);
+        true
+    }
+
+    pub fn get_critter(&self, entity_id: EntityId) -> Option<&Critter> {
+        self.critters.get(entity_id as usize)?.as_ref()
+    }
+
+    pub fn get_critter_mut(&mut self, entity_id: EntityId) -> Option<&mut Critter> {
+        self.critters.get_mut(entity_id as usize)?.as_mut()
+    }
+
     /// System iteration - get entities with position and velocity
     pub fn iter_position_velocity(&self) -> impl Iterator<Item = (EntityId, &Position, &Velocity)> {
         self.active_entities.iter().filter_map(move |&entity_id| {
@@ -299,6 +445,9 @@ impl ECS {
         self.inventories.clear();
         self.players.clear();
         self.tile_entities.clear();
+        self.colliders.clear();
+        self.ants.clear();
+        self.critters.clear();
     }
 
     /// Get entity count
@@ -321,6 +470,154 @@ pub fn physics_system(ecs: &mut ECS, delta_time: f64) {
     }
 }
 
+/// Downward acceleration of entities with a collider, in cells per second squared
+pub const ENTITY_GRAVITY: f64 = 40.0;
+/// Fastest an entity falls or sinks, in cells per second
+pub const ENTITY_TERMINAL_VELOCITY: f64 = 60.0;
+/// Fraction of a fully submerged entity's velocity liquid drag removes per second
+const LIQUID_DRAG: f64 = 2.0;
+const EDGE_EPSILON: f64 = 1e-6;
+
+/// Health lost per second while an entity overlaps a cell of `material`
+pub fn contact_damage(material: MaterialType) -> f32 {
+    match material {
+        MaterialType::Fire => 10.0,
+        MaterialType::Acid => 25.0,
+        MaterialType::Lava => 50.0,
+        _ => 0.0,
+    }
+}
+
+/// Physics system for entities that live in the particle grid. Entities with a `Collider`
+/// fall under gravity, are swept against powders and solids one axis at a time so they
+/// stop at the first blocking cell, float or sink in liquids by comparing their density
+/// with the liquid they displace, and lose health while touching Fire, Acid or Lava.
+/// Entities without a collider move freely as in `physics_system`. `material_at` returns
+/// the material in a cell, None when it is empty.
+///
+/// Returns the entities whose health ran out this step; the caller decides what to do
+/// with them.
+pub fn terrain_physics_system(
+    ecs: &mut ECS,
+    delta_time: f64,
+    materials: &MaterialRegistry,
+    material_at: impl Fn(i64, i64) -> Option<MaterialType>,
+) -> Vec<EntityId> {
+    let blocks = |x: i64, y: i64| {
+        material_at(x, y).is_some_and(|material| {
+            matches!(materials.get(material).kind, MaterialKind::Powder | MaterialKind::Solid | MaterialKind::Static)
+        })
+    };
+
+    let mut killed = Vec::new();
+    for (entity_id, mut position, mut velocity) in ecs.iter_position_velocity_mut() {
+        let Some(collider) = ecs.get_collider(entity_id).cloned() else {
+            position.x += velocity.dx * delta_time;
+            position.y += velocity.dy * delta_time;
+            position.z += velocity.dz * delta_time;
+            ecs.add_position(entity_id, position);
+            continue;
+        };
+
+        // Liquid displaced by the box: submerged fraction and its mean density
+        let (columns, rows) = box_cells(&position, &collider);
+        let cell_count = columns.clone().count() * rows.clone().count();
+        let mut liquid_cells = 0;
+        let mut liquid_density = 0.0;
+        let mut damage: f32 = 0.0;
+        for y in rows.clone() {
+            for x in columns.clone() {
+                let Some(material) = material_at(x, y) else { continue };
+                damage = damage.max(contact_damage(material));
+                let properties = materials.get(material);
+                if properties.kind == MaterialKind::Liquid {
+                    liquid_cells += 1;
+                    liquid_density += properties.density as f64;
+                }
+            }
+        }
+
+        let submerged = liquid_cells as f64 / cell_count.max(1) as f64;
+        let mut acceleration = ENTITY_GRAVITY;
+        if liquid_cells > 0 {
+            let displaced = liquid_density / liquid_cells as f64 * submerged;
+            acceleration *= 1.0 - displaced / (collider.density as f64).max(0.01);
+            let drag = (1.0 - LIQUID_DRAG * submerged * delta_time).max(0.0);
+            velocity.dx *= drag;
+            velocity.dy *= drag;
+        }
+        velocity.dy = (velocity.dy + acceleration * delta_time).clamp(-ENTITY_TERMINAL_VELOCITY, ENTITY_TERMINAL_VELOCITY);
+
+        let half_width = collider.width / 2.0;
+        let half_height = collider.height / 2.0;
+        let (moved, hit) = sweep_axis(
+            position.x - half_width, position.x + half_width, velocity.dx * delta_time, rows,
+            blocks,
+        );
+        position.x += moved;
+        if hit {
+            velocity.dx = 0.0;
+        }
+
+        let (columns, _) = box_cells(&position, &collider);
+        let (moved, hit) = sweep_axis(
+            position.y - half_height, position.y + half_height, velocity.dy * delta_time, columns,
+            |row, column| blocks(column, row),
+        );
+        position.y += moved;
+        let landed = hit && velocity.dy > 0.0;
+        if hit {
+            velocity.dy = 0.0;
+        }
+
+        ecs.add_position(entity_id, position);
+        ecs.add_velocity(entity_id, velocity);
+        if let Some(collider) = ecs.get_collider_mut(entity_id) {
+            collider.on_ground = landed;
+        }
+        if let Some(health) = ecs.get_health_mut(entity_id) {
+            if damage > 0.0 && health.current > 0.0 {
+                health.current = (health.current - damage * delta_time as f32).max(0.0);
+                if health.current <= 0.0 {
+                    killed.push(entity_id);
+                }
+            }
+        }
+    }
+    killed
+}
+
+/// Columns and rows of the cells an entity's box overlaps
+fn box_cells(position: &Position, collider: &Collider) -> (RangeInclusive<i64>, RangeInclusive<i64>) {
+    let span = |centre: f64, size: f64| {
+        let first = (centre - size / 2.0 + EDGE_EPSILON).floor() as i64;
+        let last = (centre + size / 2.0 - EDGE_EPSILON).floor() as i64;
+        first..=last.max(first)
+    };
+    (span(position.x, collider.width), span(position.y, collider.height))
+}
+
+/// Move the span `min..max` along one axis by `delta`, stopping flush against the first
+/// line of cells it enters in which `blocked(line, cross)` holds for some `cross`.
+/// Returns the distance actually moved and whether it was stopped.
+fn sweep_axis(min: f64, max: f64, delta: f64, cross: RangeInclusive<i64>, blocked: impl Fn(i64, i64) -> bool) -> (f64, bool) {
+    let line_blocked = |line: i64| cross.clone().any(|across| blocked(line, across));
+    if delta > 0.0 {
+        let first = (max - EDGE_EPSILON).floor() as i64 + 1;
+        let last = (max + delta - EDGE_EPSILON).floor() as i64;
+        if let Some(line) = (first..=last).find(|&line| line_blocked(line)) {
+            return ((line as f64 - max).max(0.0), true);
+        }
+    } else if delta < 0.0 {
+        let first = (min + EDGE_EPSILON).floor() as i64 - 1;
+        let last = (min + delta + EDGE_EPSILON).floor() as i64;
+        if let Some(line) = (last..=first).rev().find(|&line| line_blocked(line)) {
+            return (((line + 1) as f64 - min).min(0.0), true);
+        }
+    }
+    (delta, false)
+}
+
 /// Health regeneration system
 pub fn health_regen_system(ecs: &mut ECS, delta_time: f64) {
     let active_entities = ecs.get_active_entities().to_vec();
@@ -380,6 +677,61 @@ mod tests {
         assert_eq!(position.y, 5.0);
     }
 
+    fn spawn_box(ecs: &mut ECS, x: f64, y: f64, density: f32) -> EntityId {
+        let entity = ecs.create_entity();
+        ecs.add_position(entity, Position { x, y, z: 0.0 });
+        ecs.add_velocity(entity, Velocity { dx: 0.0, dy: 0.0, dz: 0.0 });
+        ecs.add_collider(entity, Collider::new(2.0, 2.0, density));
+        ecs.add_health(entity, Health { current: 20.0, max: 20.0, regeneration_rate: 0.0 });
+        entity
+    }
+
+    #[test]
+    fn test_entity_lands_on_terrain() {
+        let materials = MaterialRegistry::new();
+        let mut ecs = ECS::new();
+        let entity = spawn_box(&mut ecs, 5.0, 0.0, 1.5);
+        ecs.get_velocity_mut(entity).unwrap().dx = 3.0;
+
+        // Stone floor from row 20 down, and a wall at column 12
+        let ground = |x: i64, y: i64| (y >= 20 || x == 12).then_some(MaterialType::Stone);
+        for _ in 0..240 {
+            terrain_physics_system(&mut ecs, 1.0 / 60.0, &materials, ground);
+        }
+
+        let position = ecs.get_position(entity).unwrap();
+        assert!((position.y - 19.0).abs() < 1e-6, "rests on the floor, not in it: {}", position.y);
+        assert!((position.x - 11.0).abs() < 1e-6, "stopped by the wall: {}", position.x);
+        assert!(ecs.get_collider(entity).unwrap().on_ground);
+    }
+
+    #[test]
+    fn test_entity_buoyancy_and_damage() {
+        let materials = MaterialRegistry::new();
+        let mut ecs = ECS::new();
+        let cork = spawn_box(&mut ecs, 0.0, 0.0, 0.5);
+        let rock = spawn_box(&mut ecs, 10.0, 0.0, 3.0);
+        let victim = spawn_box(&mut ecs, 20.0, 0.0, 1.5);
+
+        // Water below row 10 on the left, lava below row 10 on the right
+        let pool = |x: i64, y: i64| match (x, y) {
+            (_, y) if y >= 40 => Some(MaterialType::Stone),
+            (x, y) if y >= 10 && x >= 18 => Some(MaterialType::Lava),
+            (_, y) if y >= 10 => Some(MaterialType::Water),
+            _ => None,
+        };
+        let mut killed = Vec::new();
+        for _ in 0..600 {
+            killed.extend(terrain_physics_system(&mut ecs, 1.0 / 60.0, &materials, pool));
+        }
+
+        assert!(ecs.get_position(cork).unwrap().y < 12.0, "light entities float");
+        assert!(ecs.get_position(rock).unwrap().y > 35.0, "dense entities sink");
+        assert_eq!(killed, vec![victim]);
+        assert_eq!(ecs.get_health(victim).unwrap().current, 0.0);
+        assert_eq!(ecs.get_health(cork).unwrap().current, 20.0);
+    }
+
     #[test]
     fn test_player_creation() {
         let mut ecs = ECS::new();
@@ -0,0 +1,206 @@
+use crate::materials::MaterialType;
+use crate::simulation::{GridRect, Simulation};
+use crate::undo::UndoManager;
+use std::collections::HashSet;
+
+/// A set of grid cells to move, erase or repaint together
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Selection {
+    cells: HashSet<(usize, usize)>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.cells.contains(&(x, y))
+    }
+
+    pub fn insert(&mut self, x: usize, y: usize) -> bool {
+        self.cells.insert((x, y))
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.cells.iter().copied()
+    }
+
+    /// Smallest rectangle holding every selected cell; None when nothing is selected
+    pub fn bounds(&self) -> Option<GridRect> {
+        let min_x = self.cells.iter().map(|&(x, _)| x).min()?;
+        let min_y = self.cells.iter().map(|&(_, y)| y).min()?;
+        let max_x = self.cells.iter().map(|&(x, _)| x).max()?;
+        let max_y = self.cells.iter().map(|&(_, y)| y).max()?;
+        Some(GridRect::new(min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+    }
+
+    /// The same shape shifted by `(dx, dy)`; None if any cell would leave the
+    /// `width` x `height` grid
+    pub fn offset(&self, dx: i64, dy: i64, width: usize, height: usize) -> Option<Selection> {
+        let cells = self.cells.iter()
+            .map(|&(x, y)| {
+                let (x, y) = (x as i64 + dx, y as i64 + dy);
+                (x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height).then_some((x as usize, y as usize))
+            })
+            .collect::<Option<_>>()?;
+        Some(Selection { cells })
+    }
+}
+
+impl Simulation {
+    /// Every cell of `rect` inside the grid, filled or not
+    pub fn select_rect(&self, rect: GridRect) -> Selection {
+        let mut selection = Selection::new();
+        if let Some(rect) = rect.intersection(&GridRect::new(0, 0, self.width, self.height)) {
+            for y in rect.y..rect.y + rect.height {
+                for x in rect.x..rect.x + rect.width {
+                    selection.insert(x, y);
+                }
+            }
+        }
+        selection
+    }
+
+    /// The particle at `(x, y)` and every particle of the same material joined to it
+    /// through their edges, like a paint bucket; empty if `(x, y)` is empty
+    pub fn select_by_material(&self, x: usize, y: usize) -> Selection {
+        let mut selection = Selection::new();
+        let Some(material) = self.get_particle(x, y).map(|particle| particle.material_type) else {
+            return selection;
+        };
+        let mut frontier = vec![(x, y)];
+        selection.insert(x, y);
+        while let Some((x, y)) = frontier.pop() {
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for (nx, ny) in neighbours {
+                let same = self.get_particle(nx, ny).is_some_and(|particle| particle.material_type == material);
+                if same && selection.insert(nx, ny) {
+                    frontier.push((nx, ny));
+                }
+            }
+        }
+        selection
+    }
+
+    /// Remove every particle in the selection; returns how many there were
+    pub fn erase_selection(&mut self, selection: &Selection) -> usize {
+        selection.cells().filter(|&(x, y)| self.remove_particle(x, y).is_some()).count()
+    }
+
+    /// Turn every particle in the selection into fresh `material`, leaving empty cells
+    /// empty; returns how many changed
+    pub fn replace_selection(&mut self, selection: &Selection, material: MaterialType) -> usize {
+        let filled: Vec<_> = selection.cells().filter(|&(x, y)| self.get_particle(x, y).is_some()).collect();
+        if material == MaterialType::Empty {
+            return filled.into_iter().filter(|&(x, y)| self.remove_particle(x, y).is_some()).count();
+        }
+        filled.into_iter().filter(|&(x, y)| self.add_particle(x, y, material, None)).count()
+    }
+
+    /// Lift every particle in the selection and set them down `(dx, dy)` away, over
+    /// whatever was there. All or nothing: returns the moved selection, or None without
+    /// changing anything if part of it would leave the grid.
+    pub fn move_selection(&mut self, selection: &Selection, dx: i64, dy: i64) -> Option<Selection> {
+        let moved = selection.offset(dx, dy, self.width, self.height)?;
+        let lifted: Vec<_> = selection.cells()
+            .filter_map(|(x, y)| self.remove_particle(x, y))
+            .collect();
+        for particle in lifted {
+            let (x, y) = ((particle.x as i64 + dx) as usize, (particle.y as i64 + dy) as usize);
+            self.set_particle(x, y, particle);
+        }
+        Some(moved)
+    }
+}
+
+impl UndoManager {
+    /// `Simulation::erase_selection` as one undo step
+    pub fn erase_selection(&mut self, simulation: &mut Simulation, selection: &Selection) -> usize {
+        self.edit(simulation, "erase", selection.cells(), |simulation| simulation.erase_selection(selection))
+    }
+
+    /// `Simulation::replace_selection` as one undo step
+    pub fn replace_selection(&mut self, simulation: &mut Simulation, selection: &Selection, material: MaterialType) -> usize {
+        self.edit(simulation, "replace", selection.cells(), |simulation| simulation.replace_selection(selection, material))
+    }
+
+    /// `Simulation::move_selection` as one undo step covering the cells left and the
+    /// cells landed on
+    pub fn move_selection(&mut self, simulation: &mut Simulation, selection: &Selection, dx: i64, dy: i64) -> Option<Selection> {
+        let moved = selection.offset(dx, dy, simulation.width, simulation.height)?;
+        let cells: Vec<_> = selection.cells().chain(moved.cells()).collect();
+        self.edit(simulation, "move", cells, |simulation| simulation.move_selection(selection, dx, dy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material_at(simulation: &Simulation, x: usize, y: usize) -> Option<MaterialType> {
+        simulation.get_particle(x, y).map(|particle| particle.material_type)
+    }
+
+    #[test]
+    fn test_select_by_material_floods_connected_cells() {
+        let mut simulation = Simulation::new(10, 10);
+        for x in 0..4 {
+            simulation.add_particle(x, 5, MaterialType::Stone, None);
+        }
+        simulation.add_particle(3, 4, MaterialType::Stone, None);
+        // Only touching diagonally, so not part of the fill
+        simulation.add_particle(4, 3, MaterialType::Stone, None);
+        simulation.add_particle(4, 5, MaterialType::Wood, None);
+
+        let selection = simulation.select_by_material(0, 5);
+        assert_eq!(selection.len(), 5);
+        assert!(selection.contains(3, 4) && !selection.contains(4, 3) && !selection.contains(4, 5));
+        assert_eq!(selection.bounds(), Some(GridRect::new(0, 4, 4, 2)));
+        assert!(simulation.select_by_material(9, 9).is_empty());
+
+        assert_eq!(simulation.select_rect(GridRect::new(8, 8, 5, 5)).len(), 4);
+        assert_eq!(simulation.replace_selection(&selection, MaterialType::Ice), 5);
+        assert_eq!(material_at(&simulation, 3, 4), Some(MaterialType::Ice));
+        assert_eq!(material_at(&simulation, 4, 3), Some(MaterialType::Stone));
+    }
+
+    #[test]
+    fn test_move_and_erase_undo_as_one_step() {
+        let mut simulation = Simulation::new(10, 10);
+        let mut history = UndoManager::new();
+        simulation.add_particle(1, 1, MaterialType::Stone, Some(250.0));
+        simulation.add_particle(2, 1, MaterialType::Wood, None);
+        simulation.add_particle(3, 1, MaterialType::Sand, None);
+
+        let selection = simulation.select_rect(GridRect::new(1, 1, 2, 1));
+        assert_eq!(history.move_selection(&mut simulation, &selection, 0, 9), None);
+        let moved = history.move_selection(&mut simulation, &selection, 1, 0).unwrap();
+        assert_eq!(material_at(&simulation, 1, 1), None);
+        assert_eq!(material_at(&simulation, 2, 1), Some(MaterialType::Stone));
+        assert_eq!(simulation.get_particle(2, 1).unwrap().temp, 250.0);
+        assert_eq!(material_at(&simulation, 3, 1), Some(MaterialType::Wood));
+
+        assert_eq!(history.erase_selection(&mut simulation, &moved), 2);
+        assert_eq!(simulation.iter_particles().count(), 0);
+
+        assert!(history.undo(&mut simulation));
+        assert!(history.undo(&mut simulation));
+        assert_eq!(material_at(&simulation, 1, 1), Some(MaterialType::Stone));
+        assert_eq!(material_at(&simulation, 2, 1), Some(MaterialType::Wood));
+        assert_eq!(material_at(&simulation, 3, 1), Some(MaterialType::Sand));
+    }
+}
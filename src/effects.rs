@@ -0,0 +1,235 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Effects alive at once; emitting beyond this is ignored
+pub const MAX_EFFECTS: usize = 2048;
+/// Chance per second that a flame throws a spark
+pub const SPARK_CHANCE_PER_SEC: f32 = 1.5;
+/// Chance per second that lava open to the air gives off an ember
+pub const EMBER_CHANCE_PER_SEC: f32 = 0.3;
+/// Chance that a particle breaking the surface of water throws up droplets
+pub const SPLASH_CHANCE: f32 = 0.5;
+/// Pull on sparks and droplets, in cells per second squared
+const EFFECT_GRAVITY: f32 = 60.0;
+/// Embers ride the heat above lava, slowing as they cool
+const EMBER_LIFT: f32 = 10.0;
+/// Fixed so effects look the same from run to run; they never feed back into the simulation
+const EFFECT_SEED: u64 = 0x5EED_EFFE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffectKind {
+    /// Thrown up from flames, falls back in an arc
+    Spark,
+    /// Droplet flung up where something falls into water
+    Splash,
+    /// Glowing speck drifting up from lava
+    Ember,
+}
+
+impl EffectKind {
+    /// Color of a fresh effect; it fades out over its lifetime
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            EffectKind::Spark => [255, 230, 120],
+            EffectKind::Splash => [170, 210, 255],
+            EffectKind::Ember => [255, 110, 30],
+        }
+    }
+
+    /// Shortest and longest lifetime in seconds
+    fn lifetime(self) -> (f32, f32) {
+        match self {
+            EffectKind::Spark => (0.3, 0.8),
+            EffectKind::Splash => (0.3, 0.6),
+            EffectKind::Ember => (1.5, 3.0),
+        }
+    }
+}
+
+/// One visual flourish. Positions are in cells, velocities in cells per second; effects
+/// live outside the grid and never touch particles.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub x: f32,
+    pub y: f32,
+    pub vx: f32,
+    pub vy: f32,
+    /// Seconds left
+    pub life: f32,
+    /// Seconds it started with
+    pub lifetime: f32,
+}
+
+impl Effect {
+    /// Fraction of its life left, from 1 when emitted down to 0
+    pub fn remaining(&self) -> f32 {
+        if self.lifetime > 0.0 {
+            (self.life / self.lifetime).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Cell the effect is in
+    pub fn cell(&self) -> (i32, i32) {
+        (self.x.floor() as i32, self.y.floor() as i32)
+    }
+}
+
+/// Short-lived sparks, splashes and embers drawn over the grid. `Simulation::enable_effects`
+/// makes the simulation emit them from flames, lava and things falling into water.
+#[derive(Debug, Clone)]
+pub struct EffectLayer {
+    effects: Vec<Effect>,
+    rng: ChaCha8Rng,
+}
+
+impl EffectLayer {
+    pub fn new() -> Self {
+        Self {
+            effects: Vec::new(),
+            rng: ChaCha8Rng::seed_from_u64(EFFECT_SEED),
+        }
+    }
+
+    /// An empty layer rolling its own stream of random numbers
+    #[cfg(feature = "parallel")]
+    pub(crate) fn with_seed(seed: u64) -> Self {
+        Self { effects: Vec::new(), rng: ChaCha8Rng::seed_from_u64(seed) }
+    }
+
+    /// Take over the effects `other` emitted, moved down `dy` cells
+    #[cfg(feature = "parallel")]
+    pub(crate) fn absorb(&mut self, other: EffectLayer, dy: f32) {
+        for mut effect in other.effects {
+            effect.y += dy;
+            if !self.emit(effect) {
+                break;
+            }
+        }
+    }
+
+    /// Add an effect; false if the layer is full
+    pub fn emit(&mut self, effect: Effect) -> bool {
+        if self.effects.len() >= MAX_EFFECTS {
+            return false;
+        }
+        self.effects.push(effect);
+        true
+    }
+
+    /// Throw a spark up and to either side from `(x, y)`
+    pub fn spark(&mut self, x: f32, y: f32) -> bool {
+        let velocity = (self.rng.gen_range(-12.0..12.0), self.rng.gen_range(-30.0..-12.0));
+        self.emit_kind(EffectKind::Spark, x, y, velocity)
+    }
+
+    /// Fling up to `droplets` droplets out of the water surface at `(x, y)`
+    pub fn splash(&mut self, x: f32, y: f32, droplets: usize) {
+        for _ in 0..droplets {
+            let velocity = (self.rng.gen_range(-10.0..10.0), self.rng.gen_range(-20.0..-8.0));
+            if !self.emit_kind(EffectKind::Splash, x, y, velocity) {
+                break;
+            }
+        }
+    }
+
+    /// Let an ember drift up from `(x, y)`
+    pub fn ember(&mut self, x: f32, y: f32) -> bool {
+        let velocity = (self.rng.gen_range(-3.0..3.0), self.rng.gen_range(-8.0..-3.0));
+        self.emit_kind(EffectKind::Ember, x, y, velocity)
+    }
+
+    fn emit_kind(&mut self, kind: EffectKind, x: f32, y: f32, (vx, vy): (f32, f32)) -> bool {
+        let (shortest, longest) = kind.lifetime();
+        let lifetime = self.rng.gen_range(shortest..longest);
+        self.emit(Effect { kind, x, y, vx, vy, life: lifetime, lifetime })
+    }
+
+    /// True with probability `chance`, from the layer's own random numbers so effects
+    /// never change how a seeded simulation plays out
+    pub fn roll(&mut self, chance: f32) -> bool {
+        self.rng.gen::<f32>() < chance
+    }
+
+    /// Move every effect and age it by `delta_time` seconds. Effects die when their time
+    /// is up or they fly into a cell for which `blocked` is true.
+    pub fn step(&mut self, delta_time: f32, blocked: impl Fn(i32, i32) -> bool) {
+        for effect in &mut self.effects {
+            effect.vy += match effect.kind {
+                EffectKind::Spark | EffectKind::Splash => EFFECT_GRAVITY * delta_time,
+                EffectKind::Ember => -EMBER_LIFT * effect.remaining() * delta_time,
+            };
+            effect.x += effect.vx * delta_time;
+            effect.y += effect.vy * delta_time;
+            effect.life -= delta_time;
+        }
+        self.effects.retain(|effect| {
+            let (x, y) = effect.cell();
+            effect.life > 0.0 && !blocked(x, y)
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Effect> {
+        self.effects.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+}
+
+impl Default for EffectLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effects_fly_and_expire() {
+        let mut layer = EffectLayer::new();
+        assert!(layer.spark(10.0, 10.0));
+        assert!(layer.ember(20.0, 10.0));
+        layer.step(0.1, |_, _| false);
+        let spark = layer.iter().find(|e| e.kind == EffectKind::Spark).unwrap();
+        let ember = layer.iter().find(|e| e.kind == EffectKind::Ember).unwrap();
+        assert!(spark.y < 10.0 && ember.y < 10.0);
+        assert!(spark.remaining() < 1.0);
+
+        // Sparks arc back down; embers outlive them
+        for _ in 0..10 {
+            layer.step(0.1, |_, _| false);
+        }
+        assert_eq!(layer.iter().map(|e| e.kind).collect::<Vec<_>>(), vec![EffectKind::Ember]);
+        layer.step(5.0, |_, _| false);
+        assert!(layer.is_empty());
+    }
+
+    #[test]
+    fn test_blocked_cells_and_capacity() {
+        let mut layer = EffectLayer::new();
+        layer.splash(5.0, 5.0, 4);
+        assert_eq!(layer.len(), 4);
+        layer.step(0.01, |_, _| true);
+        assert!(layer.is_empty());
+
+        layer.splash(5.0, 5.0, MAX_EFFECTS + 10);
+        assert_eq!(layer.len(), MAX_EFFECTS);
+        assert!(!layer.spark(1.0, 1.0));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::materials::MaterialType;
+use crate::simulation::Simulation;
+use crate::world_generation::BiomeType;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Side of the grid an emitter spawns along
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Trickle of ambient particles along one edge of the grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EdgeEmitter {
+    pub name: String,
+    pub edge: Edge,
+    pub material: MaterialType,
+    /// Particles per second in calm weather
+    pub rate: f32,
+    /// Part of the edge used, as fractions of its length
+    pub span: (f32, f32),
+    pub temp: Option<f32>,
+    /// Impulse given to spawned particles, in cells per tick
+    pub velocity: (f32, f32),
+    /// Biomes the emitter runs in; empty means every biome
+    pub biomes: Vec<BiomeType>,
+    /// Extra rate per unit of weather intensity: at intensity 1 the rate is
+    /// `rate * (1 + weather_response)`
+    pub weather_response: f32,
+    /// Only spawn just inside cells of the edge that hold a solid, like water dripping
+    /// from a cave ceiling, instead of on empty edge cells
+    pub anchored: bool,
+}
+
+impl EdgeEmitter {
+    pub fn new(name: impl Into<String>, edge: Edge, material: MaterialType, rate: f32) -> Self {
+        Self {
+            name: name.into(),
+            edge,
+            material,
+            rate,
+            span: (0.0, 1.0),
+            temp: None,
+            velocity: (0.0, 0.0),
+            biomes: Vec::new(),
+            weather_response: 0.0,
+            anchored: false,
+        }
+    }
+
+    /// Occasional water drips from solid ceiling cells along the top edge
+    pub fn ceiling_drips() -> Self {
+        Self {
+            biomes: vec![BiomeType::Forest, BiomeType::Jungle, BiomeType::Plains],
+            weather_response: 3.0,
+            anchored: true,
+            ..Self::new("ceiling_drips", Edge::Top, MaterialType::Water, 0.5)
+        }
+    }
+
+    /// Sand blown in from the left edge of the map, mostly during storms
+    pub fn sand_storm() -> Self {
+        Self {
+            span: (0.0, 0.6),
+            velocity: (1.5, 0.0),
+            biomes: vec![BiomeType::Desert],
+            weather_response: 20.0,
+            ..Self::new("sand_storm", Edge::Left, MaterialType::Sand, 2.0)
+        }
+    }
+
+    /// Warm ash settling from the sky
+    pub fn ash_fall() -> Self {
+        Self {
+            temp: Some(80.0),
+            biomes: vec![BiomeType::Wasteland],
+            weather_response: 2.0,
+            ..Self::new("ash_fall", Edge::Top, MaterialType::Ash, 6.0)
+        }
+    }
+
+    pub fn runs_in(&self, biome: Option<BiomeType>) -> bool {
+        self.biomes.is_empty() || biome.is_some_and(|biome| self.biomes.contains(&biome))
+    }
+
+    /// Particles per second under `weather` intensity
+    pub fn effective_rate(&self, weather: f32) -> f32 {
+        (self.rate * (1.0 + self.weather_response * weather)).max(0.0)
+    }
+}
+
+/// World-level ambient emitters filtered by the current biome and scaled by the weather,
+/// so a world keeps moving without player input. Uses its own seeded RNG.
+#[derive(Debug, Clone)]
+pub struct EdgeEmitters {
+    emitters: Vec<EdgeEmitter>,
+    biome: Option<BiomeType>,
+    weather: f32,
+    rng: ChaCha8Rng,
+}
+
+impl EdgeEmitters {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            emitters: Vec::new(),
+            biome: None,
+            weather: 0.0,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// The built-in drips, sand storm and ash fall, each running in its own biomes
+    pub fn with_defaults(seed: u64) -> Self {
+        let mut emitters = Self::new(seed);
+        emitters.add(EdgeEmitter::ceiling_drips());
+        emitters.add(EdgeEmitter::sand_storm());
+        emitters.add(EdgeEmitter::ash_fall());
+        emitters
+    }
+
+    /// Add an emitter, replacing any with the same name
+    pub fn add(&mut self, emitter: EdgeEmitter) {
+        self.emitters.retain(|existing| existing.name != emitter.name);
+        self.emitters.push(emitter);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<EdgeEmitter> {
+        let index = self.emitters.iter().position(|emitter| emitter.name == name)?;
+        Some(self.emitters.remove(index))
+    }
+
+    pub fn emitters(&self) -> &[EdgeEmitter] {
+        &self.emitters
+    }
+
+    pub fn biome(&self) -> Option<BiomeType> {
+        self.biome
+    }
+
+    /// Biome the world is in; emitters restricted to other biomes stay idle
+    pub fn set_biome(&mut self, biome: Option<BiomeType>) {
+        self.biome = biome;
+    }
+
+    pub fn weather(&self) -> f32 {
+        self.weather
+    }
+
+    /// Weather intensity from 0 (calm) upwards; 1 is a typical storm
+    pub fn set_weather(&mut self, weather: f32) {
+        self.weather = weather.max(0.0);
+    }
+
+    /// Spawn this tick's particles, returning how many were placed
+    pub fn update(&mut self, simulation: &mut Simulation, delta_time: f32) -> usize {
+        let mut spawned = 0;
+        for emitter in &self.emitters {
+            if !emitter.runs_in(self.biome) {
+                continue;
+            }
+            let mut budget = emitter.effective_rate(self.weather) * delta_time;
+            while budget > 0.0 {
+                // Fractional remainders spawn probabilistically so low rates still trickle
+                if (budget >= 1.0 || self.rng.gen::<f32>() < budget) && Self::emit(&mut self.rng, simulation, emitter) {
+                    spawned += 1;
+                }
+                budget -= 1.0;
+            }
+        }
+        spawned
+    }
+
+    fn emit(rng: &mut ChaCha8Rng, simulation: &mut Simulation, emitter: &EdgeEmitter) -> bool {
+        let (width, height) = (simulation.width, simulation.height);
+        let length = match emitter.edge {
+            Edge::Top | Edge::Bottom => width,
+            Edge::Left | Edge::Right => height,
+        };
+        if width < 2 || height < 2 {
+            return false;
+        }
+        let start = ((emitter.span.0.clamp(0.0, 1.0) * length as f32) as usize).min(length - 1);
+        let end = ((emitter.span.1.clamp(0.0, 1.0) * length as f32) as usize).clamp(start + 1, length);
+        let along = rng.gen_range(start..end);
+        // The edge cell and the one just inside it
+        let (edge_cell, inner_cell) = match emitter.edge {
+            Edge::Top => ((along, 0), (along, 1)),
+            Edge::Bottom => ((along, height - 1), (along, height - 2)),
+            Edge::Left => ((0, along), (1, along)),
+            Edge::Right => ((width - 1, along), (width - 2, along)),
+        };
+
+        let (x, y) = if emitter.anchored {
+            let anchored = simulation.get_particle(edge_cell.0, edge_cell.1).is_some_and(|particle| {
+                let material = simulation.materials().get(particle.material_type);
+                material.is_rigid_solid(particle.material_type) || material.is_stationary(particle.material_type)
+            });
+            if !anchored {
+                return false;
+            }
+            inner_cell
+        } else {
+            edge_cell
+        };
+        if simulation.get_particle(x, y).is_some() || !simulation.add_particle(x, y, emitter.material, emitter.temp) {
+            return false;
+        }
+        if let Some(particle) = simulation.get_particle_mut(x, y) {
+            (particle.vx, particle.vy) = emitter.velocity;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(simulation: &Simulation, material: MaterialType) -> usize {
+        simulation.iter_particles().filter(|particle| particle.material_type == material).count()
+    }
+
+    #[test]
+    fn test_emitters_follow_biome_and_weather() {
+        let mut simulation = Simulation::new(40, 40);
+        let mut emitters = EdgeEmitters::with_defaults(3);
+        emitters.set_biome(Some(BiomeType::Desert));
+
+        let calm: usize = (0..30).map(|_| emitters.update(&mut simulation, 1.0 / 60.0)).sum();
+        simulation.clear();
+        emitters.set_weather(1.0);
+        let stormy: usize = (0..30).map(|_| emitters.update(&mut simulation, 1.0 / 60.0)).sum();
+
+        assert!(stormy > calm * 5, "calm {} stormy {}", calm, stormy);
+        assert_eq!(count(&simulation, MaterialType::Ash), 0);
+        // Storm sand only enters along the left edge, moving inward
+        assert!(simulation.iter_particles().all(|p| p.x == 0 && p.y < 24 && p.vx > 0.0));
+    }
+
+    #[test]
+    fn test_drips_need_a_ceiling() {
+        let mut simulation = Simulation::new(20, 10);
+        for x in 0..10 {
+            simulation.add_particle(x, 0, MaterialType::Stone, None);
+        }
+        let mut emitters = EdgeEmitters::new(1);
+        emitters.add(EdgeEmitter { rate: 60.0, biomes: Vec::new(), ..EdgeEmitter::ceiling_drips() });
+
+        emitters.update(&mut simulation, 1.0);
+        let drops: Vec<_> = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Water).collect();
+        assert!(!drops.is_empty());
+        assert!(drops.iter().all(|p| p.y == 1 && p.x < 10));
+    }
+}
@@ -1,4 +1,8 @@
 use crate::{Simulation, MaterialType, Particle};
+use crate::interpolation::{self, DEFAULT_TICK_RATE};
+use crate::quality::{AdaptiveQuality, QualityLevel};
+use crate::render::{FrameCache, MaterialRenderer};
+use crate::time_control::TimeControl;
 use std::time::Instant;
 
 /// A game engine-style physics server for particle simulation
@@ -7,16 +11,38 @@ pub struct PhysicsEngine {
     last_update: Instant,
     frame_count: u64,
     target_fps: f32,
+    // Fixed physics ticks for `update_fixed` and `advance`, independent of the render rate,
+    // paused or sped up by spectators
+    time: TimeControl,
+    renderer: MaterialRenderer,
+    frame_cache: FrameCache,
+    // Lowers the simulation's quality level while it can't keep up
+    quality: Option<AdaptiveQuality>,
+}
+
+/// A particle placed between cells for rendering, see `PhysicsEngine::get_interpolated_particles`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InterpolatedParticle {
+    pub x: f32,
+    pub y: f32,
+    pub material: MaterialType,
+    pub color: [u8; 3],
 }
 
 impl PhysicsEngine {
     /// Create a new physics engine with specified grid dimensions
     pub fn new(width: usize, height: usize) -> Self {
+        let mut simulation = Simulation::new(width, height);
+        simulation.enable_motion_tracking();
         Self {
-            simulation: Simulation::new(width, height),
+            simulation,
             last_update: Instant::now(),
             frame_count: 0,
             target_fps: 60.0,
+            time: TimeControl::new(DEFAULT_TICK_RATE),
+            renderer: MaterialRenderer::new(),
+            frame_cache: FrameCache::default(),
+            quality: None,
         }
     }
 
@@ -29,14 +55,113 @@ impl PhysicsEngine {
         // Clamp delta time to avoid large jumps
         let delta_time = delta_time.min(1.0 / 30.0); // Max 30 FPS minimum
         
-        self.simulation.update(delta_time);
-        self.frame_count += 1;
+        self.update_with_delta(delta_time);
     }
 
     /// Update with a specific delta time (useful for fixed timestep)
     pub fn update_with_delta(&mut self, delta_time: f32) {
+        let started = Instant::now();
         self.simulation.update(delta_time);
         self.frame_count += 1;
+        if let Some(quality) = &mut self.quality {
+            let level = quality.record(started.elapsed().as_secs_f32(), self.simulation.particle_count());
+            self.simulation.set_quality_level(level);
+        }
+    }
+
+    /// Lower the simulation's quality level, a step at a time, while updates take longer
+    /// than `target_frame_time` seconds or there are more than `particle_budget`
+    /// particles, and raise it again once they are comfortably back under both
+    pub fn enable_adaptive_quality(&mut self, target_frame_time: f32, particle_budget: usize) {
+        self.quality = Some(AdaptiveQuality::new(target_frame_time, particle_budget));
+    }
+
+    /// Stop adapting and go back to full quality
+    pub fn disable_adaptive_quality(&mut self) {
+        self.quality = None;
+        self.simulation.set_quality_level(QualityLevel::Full);
+    }
+
+    pub fn adaptive_quality(&self) -> Option<&AdaptiveQuality> {
+        self.quality.as_ref()
+    }
+
+    /// Run the fixed ticks due for the real time since the last update; call once per
+    /// rendered frame, then draw `get_interpolated_particles`. Returns the ticks run.
+    pub fn update_fixed(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        self.advance(elapsed)
+    }
+
+    /// Run the fixed ticks due after `elapsed` more seconds, each `1 / tick_rate` long;
+    /// none while paused except queued steps, and more or fewer at other speeds
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        let ticks = self.time.advance(elapsed);
+        for _ in 0..ticks {
+            self.update_with_delta(self.time.tick_interval());
+        }
+        ticks
+    }
+
+    /// Pause, single steps and speed for `update_fixed` and `advance`
+    pub fn time_control(&self) -> &TimeControl {
+        &self.time
+    }
+
+    pub fn time_control_mut(&mut self) -> &mut TimeControl {
+        &mut self.time
+    }
+
+    /// Physics ticks per second run by `update_fixed` and `advance`, 30 by default
+    pub fn tick_rate(&self) -> f32 {
+        self.time.timestep().tick_rate()
+    }
+
+    pub fn set_tick_rate(&mut self, tick_rate: f32) {
+        self.time.timestep_mut().set_tick_rate(tick_rate);
+    }
+
+    /// How far between the last tick and the next one rendering is, from 0 to 1
+    pub fn interpolation_alpha(&self) -> f32 {
+        self.time.alpha()
+    }
+
+    /// Every particle, with those that moved in the last tick placed part way from their
+    /// old cell by `interpolation_alpha`. Drawing these each frame looks smooth however far
+    /// the render rate is above the tick rate.
+    pub fn get_interpolated_particles(&self) -> Vec<InterpolatedParticle> {
+        let moved = interpolation::moved_positions(&self.simulation, self.interpolation_alpha());
+        self.simulation.iter_particles()
+            .map(|particle| {
+                let (x, y) = moved.get(&(particle.x, particle.y)).copied()
+                    .unwrap_or((particle.x as f32, particle.y as f32));
+                InterpolatedParticle { x, y, material: particle.material_type, color: particle.color() }
+            })
+            .collect()
+    }
+
+    /// Redraw the chunks changed since the last frame and return the whole frame as
+    /// RGBA8, `cell_size` pixels to a cell
+    pub fn render(&mut self) -> &[u8] {
+        self.renderer.render_cached(&self.simulation, &mut self.frame_cache);
+        self.frame_cache.pixels()
+    }
+
+    /// Draw each cell as a `cell_size` pixel square from the next `render` on
+    pub fn set_cell_size(&mut self, cell_size: usize) {
+        self.frame_cache = FrameCache::new(cell_size);
+    }
+
+    /// (width, height) in pixels of the frames `render` returns
+    pub fn frame_dimensions(&self) -> (usize, usize) {
+        let cell_size = self.frame_cache.cell_size();
+        (self.simulation.width * cell_size, self.simulation.height * cell_size)
+    }
+
+    pub fn renderer_mut(&mut self) -> &mut MaterialRenderer {
+        &mut self.renderer
     }
 
     /// Add a particle at the specified position
@@ -46,27 +171,7 @@ impl PhysicsEngine {
 
     /// Add particles in a brush pattern
     pub fn paint_material(&mut self, x: usize, y: usize, material: MaterialType, brush_size: usize) -> usize {
-        let start_x = x.saturating_sub(brush_size);
-        let end_x = (x + brush_size).min(self.simulation.width.saturating_sub(1));
-        let start_y = y.saturating_sub(brush_size);
-        let end_y = (y + brush_size).min(self.simulation.height.saturating_sub(1));
-        let brush_size_sq = brush_size * brush_size;
-        
-        let mut placed = 0;
-        for px in start_x..=end_x {
-            for py in start_y..=end_y {
-                let dx = px as i32 - x as i32;
-                let dy = py as i32 - y as i32;
-                let dist_sq = (dx * dx + dy * dy) as usize;
-                
-                if dist_sq <= brush_size_sq {
-                    if self.simulation.add_particle(px, py, material, None) {
-                        placed += 1;
-                    }
-                }
-            }
-        }
-        placed
+        self.simulation.paint(x, y, material, brush_size)
     }
 
     /// Get particle information at position
@@ -153,6 +258,11 @@ impl PhysicsEngine {
             frame_count: self.frame_count,
             particle_count: self.particle_count(),
             grid_size: (self.simulation.width, self.simulation.height),
+            chunk_grid: self.simulation.chunk_dimensions(),
+            awake_chunks: self.simulation.awake_chunk_count(),
+            render_time_ms: self.frame_cache.render_time().as_secs_f32() * 1000.0,
+            chunks_rendered: self.frame_cache.chunks_drawn(),
+            quality_level: self.simulation.quality_level(),
         }
     }
 
@@ -168,6 +278,15 @@ pub struct PhysicsStats {
     pub frame_count: u64,
     pub particle_count: usize,
     pub grid_size: (usize, usize),
+    /// Update chunks wide and high
+    pub chunk_grid: (usize, usize),
+    /// Chunks processed by the last update; the rest were asleep
+    pub awake_chunks: usize,
+    /// Time the last `render` took, and the chunks it redrew
+    pub render_time_ms: f32,
+    pub chunks_rendered: usize,
+    /// Fidelity the simulation currently runs at
+    pub quality_level: QualityLevel,
 }
 
 /// Example usage patterns for game integration
@@ -186,9 +305,9 @@ mod tests {
         // Update physics
         engine.update();
         
-        // Sand should have moved down
+        // Sand should have moved down (it can fall up to two cells per tick)
         assert!(engine.get_particle(50, 10).is_none());
-        assert!(engine.get_particle(50, 11).is_some());
+        assert!((11..=12).any(|y| engine.get_particle(50, y).is_some()));
     }
 
     #[test]
@@ -202,4 +321,54 @@ mod tests {
         let stats = engine.stats();
         assert_eq!(stats.particle_count, placed);
     }
+
+    #[test]
+    fn test_render_reports_chunks_redrawn() {
+        let mut engine = PhysicsEngine::new(64, 32);
+        engine.set_cell_size(2);
+        engine.add_particle(5, 5, MaterialType::Stone, None);
+        assert_eq!(engine.render().len(), 128 * 64 * 4);
+        assert_eq!(engine.frame_dimensions(), (128, 64));
+        let (chunks_x, chunks_y) = engine.stats().chunk_grid;
+        assert_eq!(engine.stats().chunks_rendered, chunks_x * chunks_y);
+    }
+
+    #[test]
+    fn test_fixed_ticks_with_interpolation() {
+        let mut engine = PhysicsEngine::new(32, 32);
+        engine.simulation.set_seed(2);
+        assert_eq!(engine.tick_rate(), DEFAULT_TICK_RATE);
+        engine.set_tick_rate(20.0);
+        engine.add_particle(16, 4, MaterialType::Sand, None);
+
+        // Rendering at 144 Hz: several frames pass between ticks of 50 ms
+        let mut ticks = 0;
+        let mut heights = Vec::new();
+        for _ in 0..20 {
+            ticks += engine.advance(1.0 / 144.0);
+            heights.push(engine.get_interpolated_particles()[0].y);
+        }
+        assert_eq!(ticks, 2);
+        assert_eq!(engine.stats().frame_count, 2);
+        // The falling grain glides down between ticks instead of jumping a cell at a time
+        assert!(heights.windows(2).all(|pair| pair[1] >= pair[0]), "{:?}", heights);
+        assert!(heights.iter().any(|y| y.fract() != 0.0), "{:?}", heights);
+    }
+
+    #[test]
+    fn test_adaptive_quality_lowers_the_level_over_budget() {
+        let mut engine = PhysicsEngine::new(64, 64);
+        engine.enable_adaptive_quality(1.0, 100);
+        engine.paint_material(32, 32, MaterialType::Stone, 8);
+        assert_eq!(engine.stats().quality_level, QualityLevel::Full);
+
+        for _ in 0..crate::quality::QUALITY_CHANGE_FRAMES * 2 {
+            engine.update_with_delta(1.0 / 60.0);
+        }
+        assert_eq!(engine.stats().quality_level, QualityLevel::Low);
+        assert_eq!(engine.simulation.quality_level(), QualityLevel::Low);
+
+        engine.disable_adaptive_quality();
+        assert_eq!(engine.stats().quality_level, QualityLevel::Full);
+    }
 }
\ No newline at end of file
@@ -1,14 +1,167 @@
 use crate::{
     chunk::{ChunkManager, ChunkKey},
-    materials::MaterialType,
+    actuators::ActuatorWorld,
+    circuits::SensorWorld,
+    colony::{self, ANT_HEALTH},
+    critters,
+    ecs::{self, Ant, Collider, CritterKind, EntityId, Health, Position, Velocity, ECS},
+    damage::{self, DamageOutcome, IMPACT_DAMAGE},
+    day_cycle::WorldClock,
+    event_bus::{EventBus, SimulationEvent},
+    explosion::{self, Explosion, ExplosionReport, BLAST_HEAT},
+    interactions::{Interaction, InteractionMatrix},
+    materials::{MaterialKind, MaterialType},
     particle::Particle,
-    physics::PhysicsState,
+    physics::{PhysicsState, AMBIENT_TEMP},
+    reactions::{ReactionRegistry, NEIGHBOR_OFFSETS},
     rigidbody::{RigidBodyManager, RigidBodyAnalyzer},
     spatial::{NeighborCache, CollisionDetector},
+    tile_entity::{FluidWorld, ThermalWorld, TileEffectReport, TileEntityEffect, TileEntityManager, EFFECT_SEARCH_RADIUS},
+};
+#[cfg(feature = "files")]
+use crate::{
+    save_load::SaveLoadError,
+    streaming::{StreamingReport, WorldStreamer},
 };
 use ahash::AHashSet;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use smallvec::SmallVec;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Steam condenses back to water once it has cooled below this and existed for a moment
+const STEAM_CONDENSE_TEMP: f32 = 99.0;
+const STEAM_MIN_SECONDS: f32 = 1.0;
+const STEAM_CONDENSE_CHANCE_PER_SEC: f32 = 1.0;
+
+/// Weight of the newest frame in `Profiler::average`
+const PROFILE_SMOOTHING: f64 = 0.1;
+
+/// Stages of a frame timed by `Profiler`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfilePhase {
+    Movement,
+    Temperature,
+    Reactions,
+    RigidBodies,
+    /// Timed by the frontend, after the update it draws
+    Render,
+}
+
+/// Milliseconds spent in each phase of a frame
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub movement_ms: f64,
+    pub temperature_ms: f64,
+    pub reactions_ms: f64,
+    pub rigid_bodies_ms: f64,
+    pub render_ms: f64,
+}
+
+impl PhaseTimings {
+    pub fn get(&self, phase: ProfilePhase) -> f64 {
+        match phase {
+            ProfilePhase::Movement => self.movement_ms,
+            ProfilePhase::Temperature => self.temperature_ms,
+            ProfilePhase::Reactions => self.reactions_ms,
+            ProfilePhase::RigidBodies => self.rigid_bodies_ms,
+            ProfilePhase::Render => self.render_ms,
+        }
+    }
+
+    fn get_mut(&mut self, phase: ProfilePhase) -> &mut f64 {
+        match phase {
+            ProfilePhase::Movement => &mut self.movement_ms,
+            ProfilePhase::Temperature => &mut self.temperature_ms,
+            ProfilePhase::Reactions => &mut self.reactions_ms,
+            ProfilePhase::RigidBodies => &mut self.rigid_bodies_ms,
+            ProfilePhase::Render => &mut self.render_ms,
+        }
+    }
+
+    pub fn total_ms(&self) -> f64 {
+        self.movement_ms + self.temperature_ms + self.reactions_ms + self.rigid_bodies_ms + self.render_ms
+    }
+}
+
+/// Per-phase frame timings. The engine times movement, temperature, reactions and rigid
+/// bodies during `update_with_delta`; frontends add their render time with
+/// `record(ProfilePhase::Render, ..)` after drawing, which lands in the frame just
+/// finished. It reads the clock for every particle, so it is off until `enabled` is set.
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    pub enabled: bool,
+    current: PhaseTimings,
+    last_frame: PhaseTimings,
+    average: PhaseTimings,
+    frames: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            enabled: false,
+            current: PhaseTimings::default(),
+            last_frame: PhaseTimings::default(),
+            average: PhaseTimings::default(),
+            frames: 0,
+        }
+    }
+
+    /// Start timing, or None while disabled
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    /// Charge the time since `clock` to `phase` and restart `clock` for the next phase
+    pub fn lap(&mut self, phase: ProfilePhase, clock: &mut Option<Instant>) {
+        if let Some(started) = clock {
+            let now = Instant::now();
+            self.record(phase, now - *started);
+            *clock = Some(now);
+        }
+    }
+
+    pub fn record(&mut self, phase: ProfilePhase, duration: Duration) {
+        let ms = duration.as_secs_f64() * 1000.0;
+        if phase == ProfilePhase::Render {
+            *self.last_frame.get_mut(phase) += ms;
+            let average = self.average.get_mut(phase);
+            *average += (self.last_frame.get(phase) - *average) * PROFILE_SMOOTHING;
+        } else {
+            *self.current.get_mut(phase) += ms;
+        }
+    }
+
+    /// Close the frame being timed, folding it into the running average
+    pub fn end_frame(&mut self) {
+        self.last_frame = std::mem::take(&mut self.current);
+        let weight = if self.frames == 0 { 1.0 } else { PROFILE_SMOOTHING };
+        for phase in [ProfilePhase::Movement, ProfilePhase::Temperature, ProfilePhase::Reactions, ProfilePhase::RigidBodies] {
+            let average = self.average.get_mut(phase);
+            *average += (self.last_frame.get(phase) - *average) * weight;
+        }
+        self.frames += 1;
+    }
+
+    pub fn last_frame(&self) -> PhaseTimings {
+        self.last_frame
+    }
+
+    /// Exponential moving average over recent frames
+    pub fn average(&self) -> PhaseTimings {
+        self.average
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Next-generation physics engine with chunk-based simulation and rigid body support
 pub struct AdvancedPhysicsEngine {
@@ -17,6 +170,22 @@ pub struct AdvancedPhysicsEngine {
     pub neighbor_cache: NeighborCache,
     pub collision_detector: CollisionDetector,
     pub physics_state: PhysicsState,
+    pub reactions: ReactionRegistry,
+    /// Which materials sink through or trade places with which
+    pub interactions: InteractionMatrix,
+    /// Materials that `add_particle` refuses to place
+    pub disabled_materials: AHashSet<MaterialType>,
+    /// Loads and unloads chunks around the view centre once enabled
+    #[cfg(feature = "files")]
+    pub streamer: Option<WorldStreamer>,
+    /// Creatures and other entities moved through the world by `update_entities`
+    pub entities: ECS,
+    /// Per-phase timings of recent frames, reported in `stats`
+    pub profiler: Profiler,
+    /// Explosions, painting and rigid body impacts, queued for frontends while set
+    pub events: Option<EventBus>,
+    // Time of day driving the air temperature while the cycle runs
+    clock: Option<WorldClock>,
     
     // Performance tracking
     last_update: Instant,
@@ -32,6 +201,8 @@ pub struct AdvancedPhysicsEngine {
     // Active chunk tracking
     active_chunks: AHashSet<ChunkKey>,
     chunks_to_process: Vec<ChunkKey>,
+    // Per-engine randomness so engines in one process don't share state
+    rng: ChaCha8Rng,
 }
 
 impl AdvancedPhysicsEngine {
@@ -44,6 +215,15 @@ impl AdvancedPhysicsEngine {
             neighbor_cache: NeighborCache::new(),
             collision_detector: CollisionDetector::new(),
             physics_state,
+            reactions: ReactionRegistry::with_defaults(),
+            interactions: InteractionMatrix::new(),
+            disabled_materials: AHashSet::new(),
+            #[cfg(feature = "files")]
+            streamer: None,
+            entities: ECS::new(),
+            profiler: Profiler::new(),
+            events: None,
+            clock: None,
             last_update: Instant::now(),
             frame_count: 0,
             target_fps: 60.0,
@@ -53,9 +233,37 @@ impl AdvancedPhysicsEngine {
             rigid_body_threshold: 8,
             active_chunks: AHashSet::new(),
             chunks_to_process: Vec::new(),
+            rng: ChaCha8Rng::from_entropy(),
         }
     }
 
+    /// Run a day/night cycle: every update moves `clock` on and sets the open-air
+    /// temperature to match the time of day
+    pub fn enable_day_cycle(&mut self, clock: WorldClock) {
+        self.physics_state.set_ambient_temp(clock.ambient_temperature());
+        self.clock = Some(clock);
+    }
+
+    /// Stop the clock and return the air to its constant default temperature
+    pub fn disable_day_cycle(&mut self) {
+        self.clock = None;
+        self.physics_state.set_ambient_temp(AMBIENT_TEMP);
+    }
+
+    /// The clock, e.g. for its `light_level`; None while there is no day/night cycle
+    pub fn clock(&self) -> Option<&WorldClock> {
+        self.clock.as_ref()
+    }
+
+    pub fn clock_mut(&mut self) -> Option<&mut WorldClock> {
+        self.clock.as_mut()
+    }
+
+    /// Reseed the engine's random numbers, making updates reproducible
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
     /// Update the physics simulation
     pub fn update(&mut self) {
         let now = Instant::now();
@@ -71,10 +279,23 @@ impl AdvancedPhysicsEngine {
 
     /// Update with a specific delta time
     pub fn update_with_delta(&mut self, delta_time: f32) {
+        if let Some(clock) = &mut self.clock {
+            clock.advance(delta_time);
+            self.physics_state.set_ambient_temp(clock.ambient_temperature());
+        }
+
         // 1. Update rigid body physics
         if self.enable_rigid_bodies {
-            self.rigidbody_manager.step();
-            self.rigidbody_manager.update_rigid_body_positions(&mut self.chunk_manager);
+            let mut clock = self.profiler.start();
+            self.rigidbody_manager.update(&mut self.chunk_manager);
+            if let Some(events) = &mut self.events {
+                for &impact in self.rigidbody_manager.last_impacts() {
+                    events.emit(impact);
+                }
+            }
+            self.damage_impacted_solids();
+            self.rigidbody_manager.split_damaged_bodies(self.rigid_body_threshold);
+            self.profiler.lap(ProfilePhase::RigidBodies, &mut clock);
         }
 
         // 2. Determine active chunks to process
@@ -83,20 +304,27 @@ impl AdvancedPhysicsEngine {
         // 3. Process particles in active chunks
         self.process_particle_physics(delta_time);
 
-        // 4. Handle rigid body formation
+        // 4. Move entities against the updated grid
+        self.update_entities(delta_time);
+
+        // 5. Handle rigid body formation
         if self.enable_rigid_bodies && self.frame_count % 60 == 0 {
+            let mut clock = self.profiler.start();
             self.check_for_new_rigid_bodies();
+            self.profiler.lap(ProfilePhase::RigidBodies, &mut clock);
         }
 
-        // 5. Update spatial structures
+        // 6. Update spatial structures
         if self.enable_spatial_optimization {
             self.update_spatial_structures();
         }
 
-        // 6. Cleanup and maintenance
+        // 7. Cleanup and maintenance
         if self.frame_count % 300 == 0 { // Every 5 seconds at 60fps
             self.maintenance();
         }
+        self.chunk_manager.advance_tick();
+        self.profiler.end_frame();
     }
 
     fn update_active_chunks(&mut self) {
@@ -142,6 +370,18 @@ impl AdvancedPhysicsEngine {
 
     fn process_particle_physics(&mut self, delta_time: f32) {
         let chunks_to_process = self.chunks_to_process.clone();
+
+        // Clear last frame's processed flags so every active particle updates once per frame
+        for chunk_key in &chunks_to_process {
+            if let Some(chunk) = self.chunk_manager.get_chunk_mut(*chunk_key) {
+                for (local_x, local_y) in chunk.active_particles.clone() {
+                    if let Some(mut particle) = chunk.get_particle_mut(local_x, local_y) {
+                        particle.processed = false;
+                    }
+                }
+            }
+        }
+
         for chunk_key in chunks_to_process {
             // Get active particles list without borrowing the chunk mutably
             let active_particles = if let Some(chunk) = self.chunk_manager.get_chunk(chunk_key) {
@@ -158,11 +398,7 @@ impl AdvancedPhysicsEngine {
                 };
                 
                 // Check if particle still exists and needs processing
-                let needs_processing = if let Some(particle) = self.chunk_manager.get_particle(world_x, world_y) {
-                    !particle.processed
-                } else {
-                    false
-                };
+                let needs_processing = self.chunk_manager.processed_at(world_x, world_y) == Some(false);
                 
                 if needs_processing {
                     self.update_single_particle(world_x, world_y, delta_time);
@@ -182,45 +418,77 @@ impl AdvancedPhysicsEngine {
         let neighbor_data: Vec<Option<(MaterialType, f32, bool)>> = {
             let neighbors_iter = if self.enable_spatial_optimization {
                 // Use spatial cache
-                let spatial_neighbors = self.neighbor_cache.get_neighbors(&self.chunk_manager, world_x, world_y);
-                spatial_neighbors.into_iter().map(|opt| opt.map(|p| (p.material_type, p.temp, p.burning))).collect()
+                self.neighbor_cache.get_neighbor_samples(&self.chunk_manager, world_x, world_y).into_vec()
             } else {
                 // Direct chunk lookup
-                let chunk_neighbors = self.chunk_manager.get_neighbors(world_x, world_y);
-                chunk_neighbors.into_iter().map(|opt| opt.map(|p| (p.material_type, p.temp, p.burning))).collect()
+                NEIGHBOR_OFFSETS.iter().map(|&(dx, dy)| self.chunk_manager.sample(world_x + dx as i64, world_y + dy as i64)).collect()
             };
             neighbors_iter
         };
 
-        // Now safely get mutable reference to the particle
-        if let Some(particle) = self.chunk_manager.get_particle_mut(world_x, world_y) {
+        // Read the particle out of chunk storage; it is written back once updated
+        if let Some(mut particle) = self.chunk_manager.get_particle(world_x, world_y) {
             particle.processed = true;
 
             // 1. Handle lifespan and burnout
-            if let Some(new_particle) = self.physics_state.handle_lifespan_and_burnout(particle, delta_time) {
-                *particle = new_particle;
+            if let Some(new_particle) = self.physics_state.handle_lifespan_and_burnout(&mut particle, delta_time) {
+                self.chunk_manager.write_particle(world_x, world_y, &new_particle);
                 return;
             }
 
+            // Pairwise reactions from the reaction table
+            let mut clock = self.profiler.start();
+            let neighbor_materials: SmallVec<[Option<MaterialType>; 8]> =
+                neighbor_data.iter().map(|neighbor| neighbor.map(|(material, _, _)| material)).collect();
+            if let Some(outcome) = self.reactions.try_react(particle.material_type, &neighbor_materials, delta_time, &mut self.rng) {
+                let temp = particle.temp;
+                let mut product = Particle::new(particle.x, particle.y, outcome.product, Some(temp + outcome.heat_delta));
+                product.processed = true;
+
+                if outcome.product == MaterialType::Empty {
+                    self.remove_particle(world_x, world_y);
+                } else {
+                    self.chunk_manager.write_particle(world_x, world_y, &product);
+                }
+                if let Some(neighbor_product) = outcome.neighbor_product {
+                    let (dx, dy) = NEIGHBOR_OFFSETS[outcome.neighbor_index];
+                    let (nx, ny) = (world_x + dx as i64, world_y + dy as i64);
+                    if neighbor_product == MaterialType::Empty {
+                        self.remove_particle(nx, ny);
+                    } else {
+                        let neighbor_temp = self.chunk_manager.temp_at(nx, ny).unwrap_or(temp);
+                        let product = Particle::new(nx as usize, ny as usize, neighbor_product, Some(neighbor_temp + outcome.heat_delta));
+                        self.chunk_manager.set_particle(nx, ny, product);
+                    }
+                }
+                if let Some((material, _)) = outcome.burst {
+                    for index in outcome.burst_targets(&mut self.rng, |index| neighbor_data[index].is_none()) {
+                        let (dx, dy) = NEIGHBOR_OFFSETS[index];
+                        self.add_particle(world_x + dx as i64, world_y + dy as i64, material, Some(temp + outcome.heat_delta));
+                    }
+                }
+                self.profiler.lap(ProfilePhase::Reactions, &mut clock);
+                return;
+            }
+            self.profiler.lap(ProfilePhase::Reactions, &mut clock);
+
             // Store particle data before calling methods
-            let mut particle_copy = particle.clone();
+            let mut particle_copy = particle;
             let old_pos = (world_x, world_y);
 
             // 2. Update temperature using neighbor data
-            Self::update_particle_temperature_static(&mut particle_copy, &neighbor_data, delta_time);
+            Self::update_particle_temperature_static(&mut particle_copy, &neighbor_data, self.physics_state.ambient_temp(), delta_time);
 
             // 3. Handle state changes and effects
             let (state_change_result, new_particles) = 
-                Self::handle_particle_state_changes_static(&mut particle_copy, &neighbor_data, delta_time);
+                Self::handle_particle_state_changes_static(&mut particle_copy, &neighbor_data, delta_time, &mut self.rng);
 
             // 4. Increment time in state
             particle_copy.time_in_state += delta_time;
 
             // Apply changes back to the particle
-            *particle = particle_copy.clone();
-
-            // Drop the mutable borrow before doing chunk operations
-            drop(particle);
+            self.chunk_manager.write_particle(world_x, world_y, &particle_copy);
+            self.profiler.lap(ProfilePhase::Temperature, &mut clock);
             
             // 5. Handle movement
             let (new_x, new_y) = self.calculate_particle_movement(&particle_copy, world_x, world_y);
@@ -232,20 +500,27 @@ impl AdvancedPhysicsEngine {
 
             if let Some(new_particle) = state_change_result {
                 self.chunk_manager.set_particle(world_x, world_y, new_particle);
+                self.profiler.lap(ProfilePhase::Temperature, &mut clock);
                 return;
             }
             
             if (new_x, new_y) != old_pos {
                 // Move particle
                 if let Some(moved_particle) = self.chunk_manager.remove_particle(world_x, world_y) {
-                    self.chunk_manager.set_particle(new_x, new_y, moved_particle);
+                    // Whatever it sank through or traded places with takes the cell it left
+                    let displaced = self.chunk_manager.set_particle(new_x, new_y, moved_particle);
+                    let swapped = displaced.is_some();
+                    if let Some(displaced) = displaced {
+                        self.chunk_manager.set_particle(world_x, world_y, displaced);
+                    }
                     
-                    // Update spatial cache
-                    if self.enable_spatial_optimization {
+                    // Update spatial cache; a swap leaves both cells filled
+                    if self.enable_spatial_optimization && !swapped {
                         self.neighbor_cache.move_particle(world_x, world_y, new_x, new_y);
                     }
                 }
             }
+            self.profiler.lap(ProfilePhase::Movement, &mut clock);
         }
     }
 
@@ -287,7 +562,7 @@ impl AdvancedPhysicsEngine {
         (state_change, new_particles)
     }
 
-    fn update_particle_temperature_static(particle: &mut Particle, neighbor_data: &[Option<(MaterialType, f32, bool)>], delta_time: f32) {
+    fn update_particle_temperature_static(particle: &mut Particle, neighbor_data: &[Option<(MaterialType, f32, bool)>], ambient_temp: f32, delta_time: f32) {
         let mut temp_change = 0.0;
         let mut neighbor_count = 0;
         
@@ -299,6 +574,10 @@ impl AdvancedPhysicsEngine {
                 let temp_diff = neighbor_temp - particle.temp;
                 temp_change += temp_diff * 0.1 * delta_time; // Heat conduction
                 neighbor_count += 1;
+            } else if particle.material_type == MaterialType::Steam {
+                // Rising steam loses heat to the open air around it
+                temp_change += (ambient_temp - particle.temp) * 0.1 * delta_time;
+                neighbor_count += 1;
             }
         }
         
@@ -308,7 +587,7 @@ impl AdvancedPhysicsEngine {
         }
     }
 
-    fn handle_particle_state_changes_static(particle: &mut Particle, _neighbor_data: &[Option<(MaterialType, f32, bool)>], _delta_time: f32) -> (Option<Particle>, Vec<(i32, i32, Particle)>) {
+    fn handle_particle_state_changes_static(particle: &mut Particle, _neighbor_data: &[Option<(MaterialType, f32, bool)>], delta_time: f32, rng: &mut ChaCha8Rng) -> (Option<Particle>, Vec<(i32, i32, Particle)>) {
         // Simplified state changes - can be expanded
         let new_particles = Vec::new();
         
@@ -317,6 +596,13 @@ impl AdvancedPhysicsEngine {
             Some(Particle::new(particle.x, particle.y, MaterialType::Steam, Some(particle.temp)))
         } else if particle.temp < 0.0 && particle.material_type == MaterialType::Water {
             Some(Particle::new(particle.x, particle.y, MaterialType::Ice, Some(particle.temp)))
+        } else if particle.material_type == MaterialType::Steam
+            && particle.temp < STEAM_CONDENSE_TEMP
+            && particle.time_in_state >= STEAM_MIN_SECONDS
+            && rng.gen::<f32>() < STEAM_CONDENSE_CHANCE_PER_SEC * delta_time
+        {
+            // Cooled steam rains back down as water
+            Some(Particle::new(particle.x, particle.y, MaterialType::Water, Some(particle.temp)))
         } else {
             None
         };
@@ -324,12 +610,12 @@ impl AdvancedPhysicsEngine {
         (state_change, new_particles)
     }
 
-    fn calculate_particle_movement(&self, particle: &Particle, world_x: i64, world_y: i64) -> (i64, i64) {
+    fn calculate_particle_movement(&mut self, particle: &Particle, world_x: i64, world_y: i64) -> (i64, i64) {
         // Use the same movement logic as before but without borrowing issues
         self.handle_particle_movement(particle, world_x, world_y)
     }
 
-    fn handle_particle_movement(&self, particle: &Particle, world_x: i64, world_y: i64) -> (i64, i64) {
+    fn handle_particle_movement(&mut self, particle: &Particle, world_x: i64, world_y: i64) -> (i64, i64) {
         // Simplified movement logic - can be expanded
         let props = particle.get_properties();
         
@@ -343,20 +629,20 @@ impl AdvancedPhysicsEngine {
         let vert_dir = if is_gas { -1 } else { 1 };
         let target_y = world_y + vert_dir;
 
-        // Try vertical movement first
-        if self.chunk_manager.get_particle(world_x, target_y).is_none() {
+        // Try vertical movement first, sinking through what the interaction matrix lets it pass
+        if self.passes(particle.material_type, world_x, target_y, true) {
             return (world_x, target_y);
         }
 
         // Try diagonal movement for non-rigid materials
         if !props.is_rigid_solid(particle.material_type) {
-            let directions = if rand::random::<bool>() { [-1, 1] } else { [1, -1] };
+            let directions = if self.rng.gen::<bool>() { [-1, 1] } else { [1, -1] };
             
             for &dx in &directions {
                 let diag_x = world_x + dx;
                 let diag_y = target_y;
                 
-                if self.chunk_manager.get_particle(diag_x, diag_y).is_none() {
+                if self.passes(particle.material_type, diag_x, diag_y, false) {
                     return (diag_x, diag_y);
                 }
             }
@@ -364,19 +650,19 @@ impl AdvancedPhysicsEngine {
 
         // Horizontal movement for liquids and gases
         if props.is_liquid(particle.material_type) || is_gas {
-            let directions = if rand::random::<bool>() { [-1, 1] } else { [1, -1] };
+            let directions = if self.rng.gen::<bool>() { [-1, 1] } else { [1, -1] };
             
             for &dx in &directions {
                 let side_x = world_x + dx;
                 
-                if self.chunk_manager.get_particle(side_x, world_y).is_none() {
+                if self.passes(particle.material_type, side_x, world_y, false) {
                     let move_chance = if props.is_liquid(particle.material_type) {
                         (1.0 - props.viscosity * 0.1).max(0.1)
                     } else {
                         1.0
                     };
                     
-                    if rand::random::<f32>() < move_chance {
+                    if self.rng.gen::<f32>() < move_chance {
                         return (side_x, world_y);
                     }
                 }
@@ -387,14 +673,30 @@ impl AdvancedPhysicsEngine {
         (world_x, world_y)
     }
 
+    /// Whether a particle of `material` may move into `(x, y)`: it is empty, or the
+    /// interaction matrix lets it displace what is there (only `vertical`ly) or swap
+    fn passes(&self, material: MaterialType, x: i64, y: i64, vertical: bool) -> bool {
+        let Some(target) = self.chunk_manager.get_particle(x, y) else {
+            return true;
+        };
+        match self.interactions.get(self.physics_state.materials(), material, target.material_type) {
+            Interaction::Swap => true,
+            Interaction::Displace => vertical,
+            Interaction::Block | Interaction::React => false,
+        }
+    }
+
     fn check_for_new_rigid_bodies(&mut self) {
         let chunks_to_check: Vec<ChunkKey> = self.active_chunks.iter().cloned().collect();
+        // Live bodies sit in the grid too; don't spawn copies of them
+        let body_cells: AHashSet<(i64, i64)> = self.rigidbody_manager.occupied_cells().collect();
         
         for chunk_key in chunks_to_check {
             let candidates = RigidBodyAnalyzer::find_rigid_body_candidates(&self.chunk_manager, chunk_key);
             
             for candidate in candidates {
-                if candidate.len() >= self.rigid_body_threshold {
+                let overlaps_body = candidate.iter().any(|&(x, y, _)| body_cells.contains(&(x as i64, y as i64)));
+                if candidate.len() >= self.rigid_body_threshold && !overlaps_body {
                     // Remove particles from chunk manager (they'll be managed by rigid body)
                     for (x, y, _) in &candidate {
                         self.chunk_manager.remove_particle(*x as i64, *y as i64);
@@ -418,6 +720,7 @@ impl AdvancedPhysicsEngine {
         // Cleanup empty chunks
         self.chunk_manager.cleanup_empty_chunks();
         self.chunk_manager.compact_active_chunks();
+        self.chunk_manager.compress_idle_chunks();
         
         // Spatial structure maintenance
         if self.enable_spatial_optimization {
@@ -430,6 +733,9 @@ impl AdvancedPhysicsEngine {
 
     /// Add a particle at the specified world position
     pub fn add_particle(&mut self, world_x: i64, world_y: i64, material: MaterialType, temp: Option<f32>) -> bool {
+        if self.disabled_materials.contains(&material) {
+            return false;
+        }
         let result = self.chunk_manager.add_particle(world_x, world_y, material, temp);
         
         if result && self.enable_spatial_optimization {
@@ -458,12 +764,21 @@ impl AdvancedPhysicsEngine {
                 }
             }
         }
-        
+
+        if placed > 0 {
+            self.emit_event(SimulationEvent::MaterialPlaced { x: center_x, y: center_y, material, count: placed });
+        }
         placed
     }
 
+    fn emit_event(&mut self, event: SimulationEvent) {
+        if let Some(events) = &mut self.events {
+            events.emit(event);
+        }
+    }
+
     /// Get particle information at world position
-    pub fn get_particle(&self, world_x: i64, world_y: i64) -> Option<&Particle> {
+    pub fn get_particle(&self, world_x: i64, world_y: i64) -> Option<Particle> {
         self.chunk_manager.get_particle(world_x, world_y)
     }
 
@@ -478,10 +793,274 @@ impl AdvancedPhysicsEngine {
         result
     }
 
-    /// Clear all particles and rigid bodies
+    /// Detonate a blast at a world position, shattering solids, flinging loose particles
+    /// and knocking back rigid bodies in range
+    pub fn explode(&mut self, world_x: i64, world_y: i64, radius: f32) -> ExplosionReport {
+        let explosion = Explosion::new(world_x, world_y, radius);
+        self.emit_event(SimulationEvent::ExplosionAt { x: world_x, y: world_y, radius });
+        let materials = Arc::clone(self.physics_state.materials());
+        let cells = explosion.blast(|x, y| {
+            self.chunk_manager.material_at(x, y).map_or(0.0, |material| materials.get(material).density)
+        });
+
+        let mut report = ExplosionReport::default();
+        for cell in cells {
+            // Particles hold unsigned positions, so the blast stops at the top and left edges
+            let (Ok(x), Ok(y)) = (usize::try_from(cell.x), usize::try_from(cell.y)) else {
+                continue;
+            };
+            let Some(mut particle) = self.chunk_manager.get_particle(cell.x, cell.y) else {
+                if let Some(spawned) = explosion::blast_particle(x, y, cell.strength, &mut self.rng) {
+                    self.chunk_manager.set_particle(cell.x, cell.y, spawned);
+                    if self.enable_spatial_optimization {
+                        self.neighbor_cache.add_particle(cell.x, cell.y);
+                    }
+                    report.spawned += 1;
+                }
+                continue;
+            };
+
+            let material_type = particle.material_type;
+            let material = materials.get(material_type);
+            let mut hit = if damage::blast_breaks(&mut particle, material, cell.strength) {
+                report.broken += 1;
+                materials.create_particle(particle.x, particle.y, damage::rubble_of(material_type, material), Some(particle.temp))
+            } else {
+                if !material.is_stationary(material_type) {
+                    report.pushed += 1;
+                }
+                particle
+            };
+            hit.temp += cell.strength * BLAST_HEAT;
+            if !materials.get(hit.material_type).is_stationary(hit.material_type) {
+                let (vx, vy) = cell.impulse();
+                hit.apply_impulse(vx, vy);
+            }
+            self.chunk_manager.set_particle(cell.x, cell.y, hit);
+        }
+
+        if self.enable_rigid_bodies {
+            report.bodies_hit = self.rigidbody_manager.apply_explosion(&explosion);
+        }
+        report
+    }
+
+    /// Take `amount` points of health from the solid at a world position, e.g. from a tool
+    /// or weapon, turning it into rubble once it has none left
+    pub fn damage(&mut self, world_x: i64, world_y: i64, amount: f32) -> DamageOutcome {
+        let Some(mut particle) = self.chunk_manager.get_particle(world_x, world_y) else {
+            return DamageOutcome::Immune;
+        };
+        let materials = Arc::clone(self.physics_state.materials());
+        let material = materials.get(particle.material_type);
+        let outcome = damage::apply_damage(&mut particle, material, amount);
+        match outcome {
+            DamageOutcome::Immune => {}
+            DamageOutcome::Damaged => {
+                self.chunk_manager.set_particle(world_x, world_y, particle);
+            }
+            DamageOutcome::Broken => {
+                let rubble = damage::rubble_of(particle.material_type, material);
+                self.chunk_manager.set_particle(world_x, world_y, materials.create_particle(particle.x, particle.y, rubble, Some(particle.temp)));
+            }
+        }
+        outcome
+    }
+
+    /// Share each rigid body impact of the last step between the solids the body came to
+    /// rest against, so heavy falls crack and eventually break what they land on
+    fn damage_impacted_solids(&mut self) {
+        let materials = Arc::clone(self.physics_state.materials());
+        for (contacts, impulse) in self.rigidbody_manager.impact_contacts() {
+            let solids: Vec<(i64, i64)> = contacts.into_iter()
+                .filter(|&(x, y)| self.chunk_manager.material_at(x, y).is_some_and(|material| materials.get(material).max_health > 0.0))
+                .collect();
+            let amount = impulse * IMPACT_DAMAGE / solids.len().max(1) as f32;
+            for (x, y) in solids {
+                self.damage(x, y, amount);
+            }
+        }
+    }
+
+    /// Run every tile entity for one step, sampling the particles around it, apply the
+    /// effects it produces to the world and move liquid through pipe networks
+    pub fn update_tile_entities(&mut self, tile_entities: &mut TileEntityManager, delta_time: f32) -> TileEffectReport {
+        tile_entities.sample_ambient(self);
+        tile_entities.update_signals(self);
+        let materials = Arc::clone(self.physics_state.materials());
+        let effects = tile_entities.update_all(delta_time, &materials, |(x, y)| {
+            NEIGHBOR_OFFSETS.iter()
+                .filter_map(|&(dx, dy)| {
+                    let (nx, ny) = (x + dx as i64, y + dy as i64);
+                    self.chunk_manager.get_particle(nx, ny).map(|particle| (nx, ny, particle))
+                })
+                .collect()
+        });
+        let mut report = self.apply_tile_entity_effects(&effects, delta_time);
+        let fluids = tile_entities.update_fluids(delta_time, self);
+        report.pumped = fluids.pumped;
+        report.released = fluids.released;
+        report
+    }
+
+    /// Apply tile entity effects the same way as `Simulation::apply_tile_entity_effects`
+    pub fn apply_tile_entity_effects(&mut self, effects: &[TileEntityEffect], delta_time: f32) -> TileEffectReport {
+        let materials = Arc::clone(self.physics_state.materials());
+        let mut report = TileEffectReport::default();
+        for effect in effects {
+            match *effect {
+                TileEntityEffect::HeatGeneration { position, heat_amount, radius } => {
+                    report.heated += TileEntityEffect::apply_heat(self, position, heat_amount, radius, delta_time);
+                }
+                TileEntityEffect::ParticleSpawn { position, material, amount } => {
+                    let mut remaining = amount as usize;
+                    for (dx, dy) in TileEntityEffect::offsets_by_distance(EFFECT_SEARCH_RADIUS) {
+                        if remaining == 0 {
+                            break;
+                        }
+                        let (x, y) = (position.0 + dx, position.1 + dy);
+                        if self.chunk_manager.get_particle(x, y).is_none() && self.add_particle(x, y, material, None) {
+                            remaining -= 1;
+                            report.spawned += 1;
+                        }
+                    }
+                }
+                TileEntityEffect::MaterialConversion { position, from_material, to_material, amount } => {
+                    let mut remaining = amount as usize;
+                    for (dx, dy) in TileEntityEffect::offsets_by_distance(EFFECT_SEARCH_RADIUS) {
+                        if remaining == 0 {
+                            break;
+                        }
+                        let (x, y) = (position.0 + dx, position.1 + dy);
+                        let Some(particle) = self.chunk_manager.get_particle(x, y).filter(|p| p.material_type == from_material) else {
+                            continue;
+                        };
+                        let converted = materials.create_particle(particle.x, particle.y, to_material, Some(particle.temp));
+                        self.chunk_manager.set_particle(x, y, converted);
+                        remaining -= 1;
+                        report.converted += 1;
+                    }
+                }
+                TileEntityEffect::Explosion { position, radius, .. } => {
+                    self.explode(position.0, position.1, radius as f32);
+                    report.explosions += 1;
+                }
+                TileEntityEffect::DoorPanel { position, length, open } => {
+                    for (x, y) in (1..=length as i64).map(|i| (position.0, position.1 + i)) {
+                        let material = self.chunk_manager.material_at(x, y);
+                        if open && material == Some(MaterialType::Door) {
+                            self.remove_particle(x, y);
+                            report.door_cells += 1;
+                        } else if !open && material.is_none() && self.add_particle(x, y, MaterialType::Door, None) {
+                            report.door_cells += 1;
+                        }
+                    }
+                }
+                TileEntityEffect::PistonArm { position, direction, length, extended } => {
+                    TileEntityEffect::apply_piston(self, position, direction, length, extended, &mut report);
+                }
+                TileEntityEffect::GatePanel { position, length, solid } => {
+                    TileEntityEffect::apply_gate(self, position, length, solid, &mut report);
+                }
+                TileEntityEffect::LightGeneration { .. } | TileEntityEffect::FluidFlow { .. } => {}
+            }
+        }
+        report
+    }
+
+    /// Stream chunks around a view centre from now on. Chunks already in memory count as
+    /// loaded and are unloaded once the view moves away from them.
+    #[cfg(feature = "files")]
+    pub fn enable_streaming(&mut self, mut streamer: WorldStreamer) {
+        streamer.adopt_chunks(&self.chunk_manager);
+        self.streamer = Some(streamer);
+    }
+
+    /// Move the view to a world position, loading, generating and unloading chunks to
+    /// match. Does nothing until streaming is enabled.
+    #[cfg(feature = "files")]
+    pub fn set_view_center(&mut self, world_x: i64, world_y: i64) -> Result<StreamingReport, SaveLoadError> {
+        let Some(streamer) = &mut self.streamer else {
+            return Ok(StreamingReport::default());
+        };
+        streamer.set_view_center(world_x, world_y);
+        streamer.stream(&mut self.chunk_manager)
+    }
+
+    /// Radius in chunks of the streamed square; applied on the next `set_view_center`
+    #[cfg(feature = "files")]
+    pub fn set_view_radius(&mut self, radius: i32) {
+        if let Some(streamer) = &mut self.streamer {
+            streamer.set_view_radius(radius);
+        }
+    }
+
+    #[cfg(feature = "files")]
+    pub fn view_radius(&self) -> Option<i32> {
+        self.streamer.as_ref().map(WorldStreamer::view_radius)
+    }
+
+    /// Cells of every rigid body, one list per body, e.g. for `RigidBodyOverlay`
+    pub fn rigid_body_cells(&self) -> Vec<Vec<(i64, i64)>> {
+        self.rigidbody_manager.body_cells()
+    }
+
+    /// Temperature of the particle at a world position, None for empty cells
+    pub fn temperature_at(&self, world_x: i64, world_y: i64) -> Option<f32> {
+        self.chunk_manager.temp_at(world_x, world_y)
+    }
+
+    /// Impulse velocity of the particle at a world position, in cells per tick
+    pub fn velocity_at(&self, world_x: i64, world_y: i64) -> Option<(f32, f32)> {
+        self.chunk_manager.get_particle(world_x, world_y).map(|particle| (particle.vx, particle.vy))
+    }
+
+    /// Spawn an entity centred at a world position with a collision box and `max_health`
+    /// hit points; it starts at rest and moves from the next update
+    pub fn spawn_entity(&mut self, x: f64, y: f64, collider: Collider, max_health: f32) -> EntityId {
+        let entity_id = self.entities.create_entity();
+        self.entities.add_position(entity_id, Position { x, y, z: 0.0 });
+        self.entities.add_velocity(entity_id, Velocity { dx: 0.0, dy: 0.0, dz: 0.0 });
+        self.entities.add_collider(entity_id, collider);
+        self.entities.add_health(entity_id, Health { current: max_health, max: max_health, regeneration_rate: 0.0 });
+        entity_id
+    }
+
+    /// Spawn an ant in the cell at a world position with its nest there; see
+    /// `colony::ant_system` for how it behaves
+    pub fn spawn_ant(&mut self, x: i64, y: i64) -> EntityId {
+        let entity_id = self.entities.create_entity();
+        self.entities.add_position(entity_id, Position { x: x as f64 + 0.5, y: y as f64 + 0.5, z: 0.0 });
+        self.entities.add_health(entity_id, Health { current: ANT_HEALTH, max: ANT_HEALTH, regeneration_rate: 0.0 });
+        self.entities.add_ant(entity_id, Ant::new((x, y)));
+        entity_id
+    }
+
+    /// Spawn a fish or bird in the cell at a world position; see `critters::critter_system`
+    pub fn spawn_critter(&mut self, x: i64, y: i64, kind: CritterKind) -> EntityId {
+        critters::spawn_critter(&mut self.entities, x, y, kind)
+    }
+
+    /// Run entity physics, the ants and the critters against the particle grid and remove
+    /// the entities that died, returning their ids
+    pub fn update_entities(&mut self, delta_time: f32) -> Vec<EntityId> {
+        let materials = Arc::clone(self.physics_state.materials());
+        let mut killed = ecs::terrain_physics_system(&mut self.entities, delta_time as f64, &materials, |x, y| {
+            self.chunk_manager.material_at(x, y)
+        });
+        killed.extend(colony::ant_system(&mut self.entities, delta_time, &materials, &mut self.chunk_manager, &mut self.rng));
+        killed.extend(critters::critter_system(&mut self.entities, delta_time, &materials, &self.chunk_manager, &mut self.rng));
+        for &entity_id in &killed {
+            self.entities.remove_entity(entity_id);
+        }
+        killed
+    }
+
+    /// Clear all particles, rigid bodies and entities
     pub fn clear(&mut self) {
         self.chunk_manager.clear();
         self.rigidbody_manager.clear();
+        self.entities.clear();
         self.neighbor_cache.clear();
         self.collision_detector.clear();
         self.active_chunks.clear();
@@ -500,6 +1079,8 @@ impl AdvancedPhysicsEngine {
             } else {
                 0
             },
+            phase_timings: self.profiler.last_frame(),
+            average_phase_timings: self.profiler.average(),
         }
     }
 
@@ -514,9 +1095,8 @@ impl AdvancedPhysicsEngine {
                 let world_x = min_x + x as i64;
                 let world_y = min_y + y as i64;
                 
-                if let Some(particle) = self.get_particle(world_x, world_y) {
-                    let mut temp_particle = particle.clone();
-                    let color = temp_particle.get_color();
+                if let Some(mut particle) = self.get_particle(world_x, world_y) {
+                    let color = particle.get_color();
                     state[y][x] = Some((particle.material_type, particle.temp, color));
                 }
             }
@@ -553,6 +1133,76 @@ pub struct AdvancedPhysicsStats {
     pub active_chunks: usize,
     pub rigid_body_count: usize,
     pub spatial_cells: usize,
+    /// Where the last frame's time went
+    pub phase_timings: PhaseTimings,
+    /// The same, smoothed over recent frames for display
+    pub average_phase_timings: PhaseTimings,
+}
+
+impl FluidWorld for AdvancedPhysicsEngine {
+    fn liquid_at(&self, (x, y): (i64, i64)) -> Option<MaterialType> {
+        let material_type = self.chunk_manager.material_at(x, y)?;
+        self.physics_state.materials().get(material_type).is_liquid(material_type).then_some(material_type)
+    }
+
+    fn is_cell_empty(&self, (x, y): (i64, i64)) -> bool {
+        self.chunk_manager.get_particle(x, y).is_none()
+    }
+
+    fn take_liquid(&mut self, position: (i64, i64), material: MaterialType) -> bool {
+        self.liquid_at(position) == Some(material) && self.remove_particle(position.0, position.1).is_some()
+    }
+
+    fn put_liquid(&mut self, position: (i64, i64), material: MaterialType) -> bool {
+        self.is_cell_empty(position) && self.add_particle(position.0, position.1, material, None)
+    }
+}
+
+impl SensorWorld for AdvancedPhysicsEngine {
+    fn material_at(&self, (x, y): (i64, i64)) -> Option<MaterialType> {
+        self.chunk_manager.material_at(x, y)
+    }
+}
+
+impl ActuatorWorld for AdvancedPhysicsEngine {
+    fn is_anchored(&self, (x, y): (i64, i64)) -> bool {
+        self.chunk_manager.material_at(x, y)
+            .is_some_and(|material| self.physics_state.materials().get(material).kind == MaterialKind::Static)
+    }
+
+    fn take_particle(&mut self, (x, y): (i64, i64)) -> Option<Particle> {
+        self.remove_particle(x, y)
+    }
+
+    fn put_particle(&mut self, (x, y): (i64, i64), particle: Particle) -> bool {
+        if !self.is_cell_empty((x, y)) {
+            return false;
+        }
+        self.chunk_manager.set_particle(x, y, particle);
+        if self.enable_spatial_optimization {
+            self.neighbor_cache.add_particle(x, y);
+        }
+        true
+    }
+
+    fn place_material(&mut self, (x, y): (i64, i64), material: MaterialType, temp: Option<f32>) -> bool {
+        self.is_cell_empty((x, y)) && self.add_particle(x, y, material, temp)
+    }
+}
+
+impl ThermalWorld for AdvancedPhysicsEngine {
+    fn temperature_at(&self, (x, y): (i64, i64)) -> Option<f32> {
+        AdvancedPhysicsEngine::temperature_at(self, x, y)
+    }
+
+    fn add_heat(&mut self, (x, y): (i64, i64), degrees: f32) -> bool {
+        let Some(mut heated) = self.chunk_manager.get_particle(x, y) else {
+            return false;
+        };
+        heated.temp += degrees;
+        self.chunk_manager.set_particle(x, y, heated);
+        true
+    }
 }
 
 #[cfg(test)]
@@ -578,6 +1228,53 @@ mod tests {
         assert!(engine.get_particle(50, 10).is_some() || engine.get_particle(50, 11).is_some());
     }
 
+    #[test]
+    fn test_profiler_phase_timings() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.paint_material(0, 0, MaterialType::Sand, 6);
+        engine.update_with_delta(1.0 / 60.0);
+        assert_eq!(engine.stats().phase_timings, PhaseTimings::default());
+
+        engine.profiler.enabled = true;
+        engine.update_with_delta(1.0 / 60.0);
+        let timings = engine.stats().phase_timings;
+        assert!(timings.movement_ms > 0.0);
+        assert!(timings.temperature_ms > 0.0);
+        assert_eq!(timings.render_ms, 0.0);
+
+        engine.profiler.record(ProfilePhase::Render, Duration::from_millis(4));
+        let stats = engine.stats();
+        assert_eq!(stats.phase_timings.render_ms, 4.0);
+        assert!(stats.average_phase_timings.render_ms > 0.0);
+        assert!(stats.phase_timings.total_ms() > 4.0);
+
+        engine.profiler.enabled = false;
+        engine.update_with_delta(1.0 / 60.0);
+        assert_eq!(engine.stats().phase_timings, PhaseTimings::default());
+    }
+
+    #[test]
+    fn test_spawned_entities_collide_and_burn() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.enable_rigid_bodies = false;
+        for x in 0..20 {
+            engine.add_particle(x, 30, MaterialType::Stone, None);
+            engine.add_particle(x, 40, MaterialType::Lava, None);
+        }
+        let walker = engine.spawn_entity(5.0, 10.0, Collider::new(1.0, 2.0, 1.5), 10.0);
+        let doomed = engine.spawn_entity(15.0, 38.0, Collider::new(1.0, 2.0, 1.5), 10.0);
+
+        let mut killed = Vec::new();
+        for _ in 0..120 {
+            killed.extend(engine.update_entities(1.0 / 60.0));
+        }
+
+        let position = engine.entities.get_position(walker).unwrap();
+        assert!((position.y - 29.0).abs() < 1e-6);
+        assert_eq!(killed, vec![doomed]);
+        assert!(!engine.entities.entity_exists(doomed));
+    }
+
     #[test]
     fn test_brush_painting() {
         let mut engine = AdvancedPhysicsEngine::new();
@@ -630,4 +1327,163 @@ mod tests {
         // Some particles might have been converted to rigid bodies
         assert!(final_stats.rigid_body_count >= 0);
     }
+
+    #[test]
+    fn test_explosion_knocks_back_rigid_bodies() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        let pixels: Vec<_> = (0..4).flat_map(|x| (0..4).map(move |y| (20 + x, 10 + y, MaterialType::Iron))).collect();
+        let handle = engine.rigidbody_manager.create_rigid_body_from_pixels(pixels, (0, 0)).unwrap();
+        engine.add_particle(12, 12, MaterialType::Stone, None);
+
+        let report = engine.explode(11, 11, 12.0);
+
+        assert_eq!(report.bodies_hit, 1);
+        assert_eq!(report.broken, 1);
+        assert_eq!(engine.get_particle(12, 12).unwrap().material_type, MaterialType::Sand);
+        let body = engine.rigidbody_manager.physics_world.get(handle).unwrap();
+        assert!(body.linvel().x > 0.0);
+    }
+
+    #[test]
+    fn test_explosions_stop_at_the_top_left_edges() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        let report = engine.explode(0, 0, 6.0);
+        assert!(report.spawned > 0);
+        assert!((-6..=6).all(|x| (-6..0).all(|y| engine.get_particle(x, y).is_none() && engine.get_particle(y, x).is_none())));
+        assert_eq!(engine.chunk_manager.total_particles(), report.spawned);
+    }
+
+    #[test]
+    fn test_rigid_body_impacts_crack_the_floor() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        for x in 0..40 {
+            engine.add_particle(x, 40, MaterialType::Stone, None);
+            engine.add_particle(x, 41, MaterialType::Iron, None);
+        }
+        let pixels: Vec<_> = (0..6).flat_map(|x| (0..6).map(move |y| (17 + x, 5 + y, MaterialType::Iron))).collect();
+        engine.rigidbody_manager.create_rigid_body_from_pixels(pixels, (0, 0));
+
+        for _ in 0..120 {
+            engine.update_with_delta(1.0 / 60.0);
+        }
+
+        // The stone under the block is cracked but holds; the rest of the floor is untouched
+        let stone = engine.physics_state.materials().get(MaterialType::Stone).clone();
+        let floor: Vec<Particle> = (0..40).filter_map(|x| engine.get_particle(x, 40)).collect();
+        assert!(floor.iter().all(|p| p.material_type == MaterialType::Stone));
+        assert!(floor[17..23].iter().any(|p| p.crack_stage(&stone) > 0));
+        assert!(floor[..10].iter().all(|p| p.damage(&stone) == 0.0));
+    }
+
+    #[test]
+    fn test_sand_piles_on_rigid_bodies() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        for x in 0..40 {
+            engine.add_particle(x, 40, MaterialType::Iron, None);
+        }
+        let pixels: Vec<_> = (0..8).flat_map(|x| (0..3).map(move |y| (16 + x, 30 + y, MaterialType::Stone))).collect();
+        engine.rigidbody_manager.create_rigid_body_from_pixels(pixels, (0, 0));
+        for y in 10..14 {
+            engine.add_particle(19, y, MaterialType::Sand, None);
+            engine.add_particle(20, y, MaterialType::Sand, None);
+        }
+
+        for _ in 0..120 {
+            engine.update_with_delta(1.0 / 60.0);
+        }
+
+        let stone_top = (30..41).find(|&y| engine.get_particle(19, y).is_some_and(|p| p.material_type == MaterialType::Stone)).unwrap();
+        assert!(stone_top >= 37, "the body landed on the floor");
+        assert_eq!(engine.get_particle(19, stone_top - 1).map(|p| p.material_type), Some(MaterialType::Sand));
+        assert_eq!(engine.stats().total_particles, 40 + 24 + 8);
+    }
+
+    #[test]
+    fn test_sand_sinks_through_water_by_interaction() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        for y in 14..21 {
+            engine.add_particle(4, y, MaterialType::Iron, None);
+            engine.add_particle(6, y, MaterialType::Iron, None);
+        }
+        engine.add_particle(5, 20, MaterialType::Iron, None);
+        for y in 15..20 {
+            engine.add_particle(5, y, MaterialType::Water, None);
+        }
+        engine.add_particle(5, 14, MaterialType::Sand, None);
+        for _ in 0..30 {
+            engine.update_with_delta(1.0 / 60.0);
+        }
+        assert_eq!(engine.get_particle(5, 19).map(|p| p.material_type), Some(MaterialType::Sand));
+        assert_eq!(engine.stats().total_particles, 21);
+    }
+
+    #[test]
+    fn test_tile_entity_effects() {
+        use crate::tile_entity::TileEntity;
+
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.add_particle(-3, 5, MaterialType::Stone, Some(20.0));
+        let mut tile_entities = TileEntityManager::new();
+        tile_entities.add_tile_entity(TileEntity::new_torch((-3, 4)));
+        tile_entities.add_tile_entity(TileEntity::new_spawner((-10, -10), MaterialType::Sand, 1.0));
+
+        let mut report = TileEffectReport::default();
+        for _ in 0..61 {
+            let step = engine.update_tile_entities(&mut tile_entities, 1.0 / 60.0);
+            report.heated += step.heated;
+            report.spawned += step.spawned;
+        }
+        assert!(report.heated > 0);
+        assert!(engine.get_particle(-3, 5).unwrap().temp > 30.0);
+        assert_eq!(report.spawned, 1);
+        assert_eq!(engine.get_particle(-10, -10).map(|p| p.material_type), Some(MaterialType::Sand));
+
+        let explosion = [TileEntityEffect::Explosion { position: (-3, 5), radius: 4, power: 20 }];
+        assert_eq!(engine.apply_tile_entity_effects(&explosion, 1.0 / 60.0).explosions, 1);
+    }
+
+    #[test]
+    fn test_user_registered_reaction() {
+        use crate::reactions::Reaction;
+
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.reactions.register(Reaction::new(
+            MaterialType::Acid, MaterialType::Iron, MaterialType::Empty, Some(MaterialType::Gold), 10.0, 0.0,
+        ));
+
+        engine.add_particle(20, 20, MaterialType::Acid, None);
+        engine.add_particle(21, 20, MaterialType::Iron, None);
+        engine.update_with_delta(1.0 / 60.0);
+
+        assert!(engine.get_particle(20, 20).is_none());
+        assert_eq!(engine.get_particle(21, 20).map(|p| p.material_type), Some(MaterialType::Gold));
+    }
+
+    #[test]
+    fn test_water_steam_cycle() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        for x in 0..5 {
+            engine.add_particle(10 + x * 4, 60, MaterialType::Water, Some(150.0));
+        }
+
+        let count = |engine: &AdvancedPhysicsEngine, material: MaterialType, max_y: i64| {
+            (0..40).flat_map(|x| (-300..=max_y).map(move |y| (x, y)))
+                .filter(|&(x, y)| engine.get_particle(x, y).is_some_and(|p| p.material_type == material))
+                .count()
+        };
+
+        engine.update_with_delta(1.0 / 60.0);
+        assert_eq!(count(&engine, MaterialType::Steam, 60), 5);
+
+        // The steam rises, cools and condenses into water well above where it boiled
+        let mut condensed = false;
+        for _ in 0..600 {
+            engine.update_with_delta(1.0 / 60.0);
+            if count(&engine, MaterialType::Water, 50) > 0 {
+                condensed = true;
+                break;
+            }
+        }
+        assert!(condensed);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,123 @@
+use crate::materials::MaterialType;
+use serde::{Deserialize, Serialize};
+
+/// Events queued between drains; later ones are dropped until the queue is drained
+pub const MAX_QUEUED_EVENTS: usize = 1024;
+
+/// Something audible that happened during an update, in grid coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SimulationEvent {
+    /// A blast of `radius` cells went off
+    ExplosionAt { x: i64, y: i64, radius: f32 },
+    /// Water flashed to steam, boiling or quenching something hot
+    SteamHiss { x: i64, y: i64 },
+    /// A brush stroke put down `count` cells of `material` around `(x, y)`
+    MaterialPlaced { x: i64, y: i64, material: MaterialType, count: usize },
+    /// A rigid body hit something, changing its momentum by `impulse` (mass times cells
+    /// per second)
+    RigidBodyImpact { x: i64, y: i64, impulse: f32 },
+    /// Lightning struck the surface at `(x, y)`
+    LightningStrike { x: i64, y: i64 },
+}
+
+impl SimulationEvent {
+    pub fn position(&self) -> (i64, i64) {
+        match *self {
+            SimulationEvent::ExplosionAt { x, y, .. }
+            | SimulationEvent::SteamHiss { x, y }
+            | SimulationEvent::MaterialPlaced { x, y, .. }
+            | SimulationEvent::RigidBodyImpact { x, y, .. }
+            | SimulationEvent::LightningStrike { x, y } => (x, y),
+        }
+    }
+
+    /// The same event `(dx, dy)` cells away
+    pub fn translated(mut self, dx: i64, dy: i64) -> Self {
+        match &mut self {
+            SimulationEvent::ExplosionAt { x, y, .. }
+            | SimulationEvent::SteamHiss { x, y }
+            | SimulationEvent::MaterialPlaced { x, y, .. }
+            | SimulationEvent::RigidBodyImpact { x, y, .. }
+            | SimulationEvent::LightningStrike { x, y } => {
+                *x += dx;
+                *y += dy;
+            }
+        }
+        self
+    }
+}
+
+/// Queue of `SimulationEvent`s for frontends to drain after each update, e.g. to play
+/// sounds. Nothing is recorded unless a simulation has its bus enabled.
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    events: Vec<SimulationEvent>,
+    dropped: usize,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue an event; false if the queue is full
+    pub fn emit(&mut self, event: SimulationEvent) -> bool {
+        if self.events.len() >= MAX_QUEUED_EVENTS {
+            self.dropped += 1;
+            return false;
+        }
+        self.events.push(event);
+        true
+    }
+
+    /// Take every queued event, oldest first
+    pub fn drain(&mut self) -> Vec<SimulationEvent> {
+        self.dropped = 0;
+        std::mem::take(&mut self.events)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SimulationEvent> {
+        self.events.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Events lost to a full queue since the last drain
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bus_queues_until_drained() {
+        let mut bus = EventBus::new();
+        assert!(bus.emit(SimulationEvent::SteamHiss { x: 1, y: 2 }));
+        assert!(bus.emit(SimulationEvent::ExplosionAt { x: 3, y: 4, radius: 5.0 }));
+        assert_eq!(bus.iter().map(SimulationEvent::position).collect::<Vec<_>>(), vec![(1, 2), (3, 4)]);
+
+        for _ in 0..MAX_QUEUED_EVENTS {
+            bus.emit(SimulationEvent::SteamHiss { x: 0, y: 0 });
+        }
+        assert_eq!(bus.len(), MAX_QUEUED_EVENTS);
+        assert_eq!(bus.dropped(), 2);
+
+        let drained = bus.drain();
+        assert_eq!(drained[1], SimulationEvent::ExplosionAt { x: 3, y: 4, radius: 5.0 });
+        assert!(bus.is_empty());
+        assert_eq!(bus.dropped(), 0);
+
+        let json = serde_json::to_string(&SimulationEvent::RigidBodyImpact { x: 1, y: 2, impulse: 3.0 }).unwrap();
+        assert_eq!(json, r#"{"event":"rigid_body_impact","x":1,"y":2,"impulse":3.0}"#);
+    }
+}
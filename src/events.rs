@@ -0,0 +1,386 @@
+use crate::materials::MaterialType;
+use crate::simulation::Simulation;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Temperature of a meteor's molten core
+const METEOR_CORE_TEMP: f32 = 2500.0;
+/// Fire trail length as a multiple of the meteor radius
+const METEOR_TRAIL_FACTOR: usize = 3;
+/// Peak impulse given to powders near each tick's earthquake epicentre
+const EARTHQUAKE_JOSTLE_STRENGTH: f32 = 1.5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WorldEventKind {
+    Meteor,
+    Flood,
+    Earthquake,
+    /// Event registered through `WorldEventScheduler::register_custom`
+    Custom(String),
+}
+
+/// An event that started this tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldEvent {
+    pub kind: WorldEventKind,
+    /// Impact column for meteors, otherwise None
+    pub x: Option<usize>,
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeteorConfig {
+    /// Average seconds between meteors
+    pub mean_interval: f32,
+    pub radius: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FloodConfig {
+    pub mean_interval: f32,
+    pub duration: f32,
+    /// Water cells spawned along the top edge per second
+    pub rate: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EarthquakeConfig {
+    pub mean_interval: f32,
+    pub duration: f32,
+    /// Fraction of the grid jostled per second
+    pub intensity: f32,
+}
+
+/// Per-world event frequencies. A `None` entry disables that event.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorldEventConfig {
+    pub seed: u64,
+    pub meteor: Option<MeteorConfig>,
+    pub flood: Option<FloodConfig>,
+    pub earthquake: Option<EarthquakeConfig>,
+}
+
+impl WorldEventConfig {
+    /// Every built-in event enabled at a moderate rate
+    pub fn all_enabled(seed: u64) -> Self {
+        Self {
+            seed,
+            meteor: Some(MeteorConfig { mean_interval: 45.0, radius: 3 }),
+            flood: Some(FloodConfig { mean_interval: 120.0, duration: 8.0, rate: 60.0 }),
+            earthquake: Some(EarthquakeConfig { mean_interval: 180.0, duration: 4.0, intensity: 0.05 }),
+        }
+    }
+}
+
+/// State handed to custom event handlers each tick they are active
+pub struct EventContext<'a> {
+    pub rng: &'a mut ChaCha8Rng,
+    pub delta_time: f32,
+    /// Seconds since the event started
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+pub type CustomEventHandler = Box<dyn FnMut(&mut Simulation, &mut EventContext<'_>) + Send>;
+
+struct CustomEvent {
+    name: String,
+    mean_interval: f32,
+    duration: f32,
+    handler: CustomEventHandler,
+}
+
+#[derive(Debug, Clone)]
+enum ActiveKind {
+    Flood { rate: f32 },
+    Earthquake { intensity: f32 },
+    Custom(usize),
+}
+
+#[derive(Debug, Clone)]
+struct ActiveEvent {
+    kind: ActiveKind,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Rolls configured events each tick and applies the active ones to a simulation.
+/// Uses its own seeded RNG so a world's event sequence is reproducible.
+pub struct WorldEventScheduler {
+    config: WorldEventConfig,
+    rng: ChaCha8Rng,
+    active: Vec<ActiveEvent>,
+    custom: Vec<CustomEvent>,
+}
+
+impl WorldEventScheduler {
+    pub fn new(config: WorldEventConfig) -> Self {
+        Self {
+            rng: ChaCha8Rng::seed_from_u64(config.seed),
+            config,
+            active: Vec::new(),
+            custom: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &WorldEventConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: WorldEventConfig) {
+        self.config = config;
+    }
+
+    /// Register a scripted event that fires on average every `mean_interval` seconds and
+    /// calls `handler` every tick for `duration` seconds (zero means a single call)
+    pub fn register_custom(
+        &mut self,
+        name: &str,
+        mean_interval: f32,
+        duration: f32,
+        handler: impl FnMut(&mut Simulation, &mut EventContext<'_>) + Send + 'static,
+    ) {
+        self.custom.push(CustomEvent {
+            name: name.to_string(),
+            mean_interval,
+            duration,
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Number of events currently in progress
+    pub fn active_count(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Roll for new events, then apply all active ones. Returns the events that started.
+    pub fn update(&mut self, simulation: &mut Simulation, delta_time: f32) -> Vec<WorldEvent> {
+        let mut started = Vec::new();
+
+        if let Some(meteor) = &self.config.meteor {
+            if Self::roll(&mut self.rng, meteor.mean_interval, delta_time) {
+                let radius = meteor.radius;
+                started.push(self.spawn_meteor(simulation, radius));
+            }
+        }
+        if let Some(flood) = &self.config.flood {
+            if Self::roll(&mut self.rng, flood.mean_interval, delta_time) {
+                let (rate, duration) = (flood.rate, flood.duration);
+                started.push(self.start(ActiveKind::Flood { rate }, WorldEventKind::Flood, duration));
+            }
+        }
+        if let Some(quake) = &self.config.earthquake {
+            if Self::roll(&mut self.rng, quake.mean_interval, delta_time) {
+                let (intensity, duration) = (quake.intensity, quake.duration);
+                started.push(self.start(ActiveKind::Earthquake { intensity }, WorldEventKind::Earthquake, duration));
+            }
+        }
+        for index in 0..self.custom.len() {
+            if Self::roll(&mut self.rng, self.custom[index].mean_interval, delta_time) {
+                let event = &self.custom[index];
+                let (kind, duration) = (WorldEventKind::Custom(event.name.clone()), event.duration);
+                started.push(self.start(ActiveKind::Custom(index), kind, duration));
+            }
+        }
+
+        self.apply_active(simulation, delta_time);
+        started
+    }
+
+    /// Start an event immediately, ignoring its schedule. Returns None when the event
+    /// is not configured (or no custom event has that name).
+    pub fn trigger(&mut self, kind: WorldEventKind, simulation: &mut Simulation) -> Option<WorldEvent> {
+        match kind {
+            WorldEventKind::Meteor => {
+                let radius = self.config.meteor.as_ref()?.radius;
+                Some(self.spawn_meteor(simulation, radius))
+            }
+            WorldEventKind::Flood => {
+                let flood = self.config.flood.as_ref()?;
+                let (rate, duration) = (flood.rate, flood.duration);
+                Some(self.start(ActiveKind::Flood { rate }, WorldEventKind::Flood, duration))
+            }
+            WorldEventKind::Earthquake => {
+                let quake = self.config.earthquake.as_ref()?;
+                let (intensity, duration) = (quake.intensity, quake.duration);
+                Some(self.start(ActiveKind::Earthquake { intensity }, WorldEventKind::Earthquake, duration))
+            }
+            WorldEventKind::Custom(name) => {
+                let index = self.custom.iter().position(|event| event.name == name)?;
+                let duration = self.custom[index].duration;
+                Some(self.start(ActiveKind::Custom(index), WorldEventKind::Custom(name), duration))
+            }
+        }
+    }
+
+    fn roll(rng: &mut ChaCha8Rng, mean_interval: f32, delta_time: f32) -> bool {
+        mean_interval > 0.0 && rng.gen::<f32>() < delta_time / mean_interval
+    }
+
+    fn start(&mut self, active: ActiveKind, kind: WorldEventKind, duration: f32) -> WorldEvent {
+        self.active.push(ActiveEvent { kind: active, elapsed: 0.0, duration });
+        WorldEvent { kind, x: None, duration }
+    }
+
+    fn apply_active(&mut self, simulation: &mut Simulation, delta_time: f32) {
+        let mut active = std::mem::take(&mut self.active);
+
+        for event in &mut active {
+            match event.kind {
+                ActiveKind::Flood { rate } => self.apply_flood(simulation, rate, delta_time),
+                ActiveKind::Earthquake { intensity } => self.apply_earthquake(simulation, intensity, delta_time),
+                ActiveKind::Custom(index) => {
+                    let mut context = EventContext {
+                        rng: &mut self.rng,
+                        delta_time,
+                        elapsed: event.elapsed,
+                        duration: event.duration,
+                    };
+                    (self.custom[index].handler)(simulation, &mut context);
+                }
+            }
+            event.elapsed += delta_time;
+        }
+
+        active.retain(|event| event.elapsed < event.duration);
+        self.active = active;
+    }
+
+    /// Drop a molten core near the top of the grid with a fire trail above it
+    fn spawn_meteor(&mut self, simulation: &mut Simulation, radius: usize) -> WorldEvent {
+        let width = simulation.width;
+        let x = self.rng.gen_range(radius..width.saturating_sub(radius).max(radius + 1)).min(width.saturating_sub(1));
+        let trail = radius * METEOR_TRAIL_FACTOR;
+        let y = (trail + radius).min(simulation.height.saturating_sub(1));
+
+        let radius_sq = (radius * radius) as i32;
+        for dy in -(radius as i32)..=radius as i32 {
+            for dx in -(radius as i32)..=radius as i32 {
+                let (px, py) = (x as i32 + dx, y as i32 + dy);
+                if dx * dx + dy * dy <= radius_sq && simulation.is_valid(px, py) {
+                    simulation.add_particle(px as usize, py as usize, MaterialType::Lava, Some(METEOR_CORE_TEMP));
+                }
+            }
+        }
+        for ty in y.saturating_sub(radius + trail)..y.saturating_sub(radius) {
+            simulation.add_particle(x, ty, MaterialType::Fire, None);
+        }
+
+        WorldEvent { kind: WorldEventKind::Meteor, x: Some(x), duration: 0.0 }
+    }
+
+    fn apply_flood(&mut self, simulation: &mut Simulation, rate: f32, delta_time: f32) {
+        let mut budget = rate * delta_time;
+        while budget > 0.0 {
+            // Fractional remainders spawn probabilistically so low rates still trickle
+            if budget >= 1.0 || self.rng.gen::<f32>() < budget {
+                let x = self.rng.gen_range(0..simulation.width);
+                if simulation.get_particle(x, 0).is_none() {
+                    simulation.add_particle(x, 0, MaterialType::Water, None);
+                }
+            }
+            budget -= 1.0;
+        }
+    }
+
+    /// Shove random particles sideways into empty neighbours and jostle powders around a
+    /// wandering epicentre, waking settled piles
+    fn apply_earthquake(&mut self, simulation: &mut Simulation, intensity: f32, delta_time: f32) {
+        let (width, height) = (simulation.width, simulation.height);
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let (epicentre_x, epicentre_y) = (self.rng.gen_range(0..width), self.rng.gen_range(0..height));
+        simulation.jostle(epicentre_x, epicentre_y, (width.max(height) / 4).max(4), EARTHQUAKE_JOSTLE_STRENGTH);
+        let attempts = (intensity * (width * height) as f32 * delta_time).ceil() as usize;
+
+        for _ in 0..attempts {
+            let x = self.rng.gen_range(0..width);
+            let y = self.rng.gen_range(0..height);
+            if simulation.get_particle(x, y).is_none_or(|p| p.material_type == MaterialType::Generator) {
+                continue;
+            }
+
+            let dx = if self.rng.gen::<bool>() { 1 } else { -1 };
+            let nx = x as i32 + dx;
+            if simulation.is_valid(nx, y as i32) && simulation.get_particle(nx as usize, y).is_none() {
+                simulation.swap_particles(x, y, nx as usize, y);
+                if let Some(particle) = simulation.get_particle_mut(nx as usize, y) {
+                    particle.settled_frames = 0;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn count(simulation: &Simulation, material: MaterialType) -> usize {
+        (0..simulation.height)
+            .flat_map(|y| (0..simulation.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| simulation.get_particle(x, y).map(|p| p.material_type) == Some(material))
+            .count()
+    }
+
+    #[test]
+    fn test_meteor_spawns_molten_core() {
+        let mut simulation = Simulation::new(64, 64);
+        let mut scheduler = WorldEventScheduler::new(WorldEventConfig::all_enabled(7));
+
+        let event = scheduler.trigger(WorldEventKind::Meteor, &mut simulation).unwrap();
+        assert!(event.x.is_some());
+        assert!(count(&simulation, MaterialType::Lava) > 0);
+        assert!(count(&simulation, MaterialType::Fire) > 0);
+
+        // Disabled events can't be triggered
+        let mut quiet = WorldEventScheduler::new(WorldEventConfig::default());
+        assert!(quiet.trigger(WorldEventKind::Meteor, &mut simulation).is_none());
+    }
+
+    #[test]
+    fn test_flood_runs_for_its_duration() {
+        let mut simulation = Simulation::new(40, 40);
+        let config = WorldEventConfig {
+            flood: Some(FloodConfig { mean_interval: 0.0, duration: 0.5, rate: 120.0 }),
+            ..Default::default()
+        };
+        let mut scheduler = WorldEventScheduler::new(config);
+
+        scheduler.trigger(WorldEventKind::Flood, &mut simulation).unwrap();
+        for _ in 0..60 {
+            scheduler.update(&mut simulation, 1.0 / 60.0);
+            simulation.update(1.0 / 60.0);
+        }
+
+        assert_eq!(scheduler.active_count(), 0);
+        assert!(count(&simulation, MaterialType::Water) > 0);
+    }
+
+    #[test]
+    fn test_custom_event_hook() {
+        let mut simulation = Simulation::new(10, 10);
+        let mut scheduler = WorldEventScheduler::new(WorldEventConfig::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&calls);
+        scheduler.register_custom("rain_of_sand", 0.0, 0.04, move |simulation, _context| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            simulation.add_particle(5, 0, MaterialType::Sand, None);
+        });
+
+        let event = scheduler.trigger(WorldEventKind::Custom("rain_of_sand".to_string()), &mut simulation);
+        assert_eq!(event.map(|e| e.kind), Some(WorldEventKind::Custom("rain_of_sand".to_string())));
+        for _ in 0..10 {
+            scheduler.update(&mut simulation, 1.0 / 60.0);
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(simulation.get_particle(5, 0).is_some());
+    }
+}
@@ -0,0 +1,202 @@
+use crate::materials::{Material, MaterialType};
+use crate::particle::Particle;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Fraction of blast strength lost per unit of density the shock wave passes through
+pub const BLAST_ATTENUATION: f32 = 0.15;
+/// Blast strength times this must exceed a solid's hardness to shatter it
+pub const BLAST_BREAK_POWER: f32 = 3.0;
+/// Impulse, in cells per tick, given to particles at the centre of a blast
+pub const BLAST_MAX_IMPULSE: f32 = 6.0;
+/// Heat added to particles at the centre of a blast
+pub const BLAST_HEAT: f32 = 600.0;
+/// Speed change, in cells per second, of rigid bodies at the centre of a blast
+pub const BLAST_BODY_SPEED: f32 = 60.0;
+const FIRE_LIFESPAN_SEC: f32 = 1.0;
+
+/// A blast centred on a cell. Strength falls off linearly to zero at `radius` and is
+/// further attenuated by the density of everything between the centre and a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Explosion {
+    pub x: i64,
+    pub y: i64,
+    pub radius: f32,
+}
+
+/// One cell reached by a blast
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlastCell {
+    pub x: i64,
+    pub y: i64,
+    /// 0..1, after falloff and attenuation
+    pub strength: f32,
+    /// Unit vector pointing away from the centre; zero at the centre itself
+    pub direction: (f32, f32),
+}
+
+impl BlastCell {
+    /// Radial impulse for a particle in this cell
+    pub fn impulse(&self) -> (f32, f32) {
+        let magnitude = self.strength * BLAST_MAX_IMPULSE;
+        (self.direction.0 * magnitude, self.direction.1 * magnitude)
+    }
+}
+
+/// What a blast did to a world
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExplosionReport {
+    /// Solids shattered into debris
+    pub broken: usize,
+    /// Loose particles flung outwards
+    pub pushed: usize,
+    /// Fire and smoke created in empty cells
+    pub spawned: usize,
+    /// Rigid bodies knocked back or damaged
+    pub bodies_hit: usize,
+}
+
+impl Explosion {
+    pub fn new(x: i64, y: i64, radius: f32) -> Self {
+        Self { x, y, radius }
+    }
+
+    /// Strength at `distance` from the centre through `resistance` summed density
+    pub fn strength(&self, distance: f32, resistance: f32) -> f32 {
+        if self.radius <= 0.0 {
+            return 0.0;
+        }
+        (1.0 - distance / self.radius).max(0.0) * (-resistance * BLAST_ATTENUATION).exp()
+    }
+
+    /// Every cell within the radius with non-zero strength. `density_at` returns the
+    /// density of a cell, or zero for empty and out-of-bounds cells.
+    pub fn blast(&self, mut density_at: impl FnMut(i64, i64) -> f32) -> Vec<BlastCell> {
+        let reach = self.radius.floor() as i64;
+        let mut cells = Vec::new();
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance > self.radius {
+                    continue;
+                }
+
+                // Sum the density of the cells strictly between the centre and this one
+                let steps = dx.abs().max(dy.abs());
+                let mut resistance = 0.0;
+                for step in 1..steps {
+                    let t = step as f32 / steps as f32;
+                    let x = self.x + (dx as f32 * t).round() as i64;
+                    let y = self.y + (dy as f32 * t).round() as i64;
+                    resistance += density_at(x, y).max(0.0);
+                }
+
+                let strength = self.strength(distance, resistance);
+                if strength <= 0.0 {
+                    continue;
+                }
+                let direction = if distance > 0.0 {
+                    (dx as f32 / distance, dy as f32 / distance)
+                } else {
+                    (0.0, 0.0)
+                };
+                cells.push(BlastCell { x: self.x + dx, y: self.y + dy, strength, direction });
+            }
+        }
+        cells
+    }
+}
+
+/// Whether a blast of `strength` shatters this material. Only solids shatter; loose
+/// particles are pushed instead.
+pub fn breaks(material_type: MaterialType, material: &Material, strength: f32) -> bool {
+    material_type != MaterialType::Generator
+        && (material.is_rigid_solid(material_type) || material.is_stationary(material_type))
+        && strength * BLAST_BREAK_POWER > material.hardness
+}
+
+/// What a shattered solid turns into
+pub fn debris_of(material_type: MaterialType) -> MaterialType {
+    match material_type {
+        MaterialType::Stone | MaterialType::Sandstone | MaterialType::Glass => MaterialType::Sand,
+        MaterialType::Wood | MaterialType::Coal => MaterialType::Ash,
+        MaterialType::Ice => MaterialType::Water,
+        other => other,
+    }
+}
+
+/// Fire or smoke thrown into an empty cell, more often and hotter near the centre
+pub fn blast_particle(x: usize, y: usize, strength: f32, rng: &mut impl Rng) -> Option<Particle> {
+    if rng.gen::<f32>() >= strength * 0.95 {
+        return None;
+    }
+    if rng.gen::<f32>() < 0.6 * strength {
+        let mut fire = Particle::new(x, y, MaterialType::Fire, Some(800.0 + strength * 700.0));
+        fire.life = Some(FIRE_LIFESPAN_SEC * strength * 0.5);
+        Some(fire)
+    } else {
+        let mut smoke = Particle::new(x, y, MaterialType::Smoke, Some(400.0 * strength));
+        smoke.life = Some(3.0 * strength);
+        Some(smoke)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::get_material_properties;
+    use crate::simulation::Simulation;
+
+    #[test]
+    fn test_dense_material_attenuates() {
+        let explosion = Explosion::new(0, 0, 8.0);
+        let wall = get_material_properties(MaterialType::Iron).density;
+        // An iron wall two cells thick on the positive x side only
+        let cells = explosion.blast(|x, _| if x == 1 || x == 2 { wall } else { 0.0 });
+        let strength_at = |x, y| cells.iter().find(|cell| (cell.x, cell.y) == (x, y)).unwrap().strength;
+
+        assert!(strength_at(4, 0) < strength_at(-4, 0) * 0.2);
+        assert_eq!(strength_at(4, 0), explosion.strength(4.0, 2.0 * wall));
+        assert!(cells.iter().all(|cell| cell.strength <= 1.0));
+    }
+
+    #[test]
+    fn test_simulation_explosion() {
+        let mut simulation = Simulation::new(40, 40);
+        for y in 18..23 {
+            simulation.add_particle(22, y, MaterialType::Stone, None);
+            simulation.add_particle(18, y, MaterialType::Iron, None);
+        }
+        simulation.add_particle(20, 24, MaterialType::Sand, None);
+
+        let report = simulation.explode(20, 20, 10.0);
+
+        // Stone shatters, metal wall holds
+        assert!(report.broken >= 5);
+        assert_eq!(simulation.get_particle(22, 20).unwrap().material_type, MaterialType::Sand);
+        assert_eq!(simulation.get_particle(18, 20).unwrap().material_type, MaterialType::Iron);
+
+        // Loose particles are thrown away from the centre
+        let sand = simulation.get_particle(20, 24).unwrap();
+        assert!(sand.vy > 0.0 && sand.vx.abs() < 1e-3);
+        let debris = simulation.get_particle(22, 20).unwrap();
+        assert!(debris.vx > 0.0);
+        assert!(report.pushed >= 1);
+    }
+
+    #[test]
+    fn test_gunpowder_detonates() {
+        let mut simulation = Simulation::new(30, 30);
+        for x in 10..20 {
+            simulation.add_particle(x, 29, MaterialType::Stone, None);
+        }
+        simulation.add_particle(15, 28, MaterialType::Gunpowder, Some(400.0));
+
+        simulation.update(1.0 / 60.0);
+
+        let state = simulation.get_state();
+        assert!(state.particles.values().all(|p| p.material_type != MaterialType::Gunpowder));
+        assert!(state.particles.values().any(|p| p.material_type == MaterialType::Sand));
+        assert_eq!(simulation.particle_count(), simulation.iter_particles().count());
+    }
+}
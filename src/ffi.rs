@@ -0,0 +1,228 @@
+use crate::materials::MaterialType;
+use crate::protocol::{material_code, material_from_code};
+use crate::simulation::Simulation;
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+
+/// A grid simulation embedded through the C API in `include/sandengine.h`, which is
+/// generated from this module with `cbindgen --config cbindgen.toml --output include/sandengine.h`.
+///
+/// Ownership: `sandengine_create` hands the caller an engine that it owns until it passes
+/// it to `sandengine_destroy`, exactly once. Every other function borrows the engine for
+/// the length of the call and keeps no pointer to it or to any buffer passed in, so
+/// buffers stay owned by the caller. An engine may move between threads but must not be
+/// used from two at once. Panics never unwind into the host: the call returns
+/// `Panicked` (or a null engine) instead, after which the engine should be destroyed.
+pub struct SandEngine {
+    simulation: Simulation,
+}
+
+/// Result of a call
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandEngineStatus {
+    Ok = 0,
+    NullPointer = 1,
+    /// A material code that names no material, or a position outside the grid
+    InvalidArgument = 2,
+    /// The output buffer holds fewer than width × height cells
+    BufferTooSmall = 3,
+    Panicked = 4,
+}
+
+/// One grid cell as copied out by `sandengine_get_cells`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SandCell {
+    /// Material code as in `sandengine_material_code`; 0 for an empty cell
+    pub material: u16,
+    /// Degrees Celsius; 0 for an empty cell
+    pub temperature: f32,
+}
+
+fn guard(call: impl FnOnce() -> SandEngineStatus) -> SandEngineStatus {
+    panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or(SandEngineStatus::Panicked)
+}
+
+/// A new empty `width` × `height` grid seeded with `seed`, or null if either side is zero
+#[no_mangle]
+pub extern "C" fn sandengine_create(width: usize, height: usize, seed: u64) -> *mut SandEngine {
+    if width == 0 || height == 0 {
+        return std::ptr::null_mut();
+    }
+    panic::catch_unwind(|| {
+        let mut simulation = Simulation::new(width, height);
+        simulation.set_seed(seed);
+        Box::into_raw(Box::new(SandEngine { simulation }))
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Free an engine from `sandengine_create`; null is ignored
+///
+/// # Safety
+/// `engine` must be null or an engine from `sandengine_create` not destroyed before.
+#[no_mangle]
+pub unsafe extern "C" fn sandengine_destroy(engine: *mut SandEngine) {
+    if !engine.is_null() {
+        // Dropping a simulation doesn't panic, and if it did there would be nothing to report to
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(engine))));
+    }
+}
+
+/// Advance the simulation by `delta_time` seconds
+///
+/// # Safety
+/// `engine` must be null or a live engine from `sandengine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn sandengine_step(engine: *mut SandEngine, delta_time: f32) -> SandEngineStatus {
+    let Some(engine) = engine.as_mut() else {
+        return SandEngineStatus::NullPointer;
+    };
+    guard(|| {
+        engine.simulation.update(delta_time);
+        SandEngineStatus::Ok
+    })
+}
+
+/// Fill a disc of `radius` cells around `(x, y)` with `material`; the Eraser's code
+/// clears cells instead
+///
+/// # Safety
+/// `engine` must be null or a live engine from `sandengine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn sandengine_paint(
+    engine: *mut SandEngine,
+    x: usize,
+    y: usize,
+    material: u16,
+    radius: usize,
+) -> SandEngineStatus {
+    let Some(engine) = engine.as_mut() else {
+        return SandEngineStatus::NullPointer;
+    };
+    guard(|| {
+        let simulation = &mut engine.simulation;
+        match material_from_code(material) {
+            Some(material) if x < simulation.width && y < simulation.height && simulation.materials().contains(material) => {
+                simulation.paint(x, y, material, radius);
+                SandEngineStatus::Ok
+            }
+            _ => SandEngineStatus::InvalidArgument,
+        }
+    })
+}
+
+/// Copy every cell, row by row from the top left, into `cells`, which must have room
+/// for `capacity` cells and at least width × height of them
+///
+/// # Safety
+/// `engine` must be null or a live engine from `sandengine_create`, and `cells` null or
+/// valid for writing `capacity` cells.
+#[no_mangle]
+pub unsafe extern "C" fn sandengine_get_cells(
+    engine: *const SandEngine,
+    cells: *mut SandCell,
+    capacity: usize,
+) -> SandEngineStatus {
+    let Some(engine) = engine.as_ref() else {
+        return SandEngineStatus::NullPointer;
+    };
+    if cells.is_null() {
+        return SandEngineStatus::NullPointer;
+    }
+    let simulation = &engine.simulation;
+    let len = simulation.width * simulation.height;
+    if capacity < len {
+        return SandEngineStatus::BufferTooSmall;
+    }
+    let cells = std::slice::from_raw_parts_mut(cells, len);
+    guard(|| {
+        for (index, cell) in cells.iter_mut().enumerate() {
+            *cell = match simulation.get_particle(index % simulation.width, index / simulation.width) {
+                Some(particle) => SandCell { material: material_code(particle.material_type), temperature: particle.temp },
+                None => SandCell { material: material_code(MaterialType::Empty), temperature: 0.0 },
+            };
+        }
+        SandEngineStatus::Ok
+    })
+}
+
+/// Width of the grid in cells; 0 for a null engine
+///
+/// # Safety
+/// `engine` must be null or a live engine from `sandengine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn sandengine_width(engine: *const SandEngine) -> usize {
+    engine.as_ref().map_or(0, |engine| engine.simulation.width)
+}
+
+/// Height of the grid in cells; 0 for a null engine
+///
+/// # Safety
+/// `engine` must be null or a live engine from `sandengine_create`.
+#[no_mangle]
+pub unsafe extern "C" fn sandengine_height(engine: *const SandEngine) -> usize {
+    engine.as_ref().map_or(0, |engine| engine.simulation.height)
+}
+
+/// Code of the material called `name` (e.g. "Sand", ignoring case and spaces) for
+/// `sandengine_paint`, or -1 if there is none
+///
+/// # Safety
+/// `engine` must be null or a live engine from `sandengine_create`, and `name` null or
+/// a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sandengine_material_code(engine: *const SandEngine, name: *const c_char) -> i32 {
+    let (Some(engine), false) = (engine.as_ref(), name.is_null()) else {
+        return -1;
+    };
+    let name = CStr::from_ptr(name).to_string_lossy();
+    engine.simulation.materials().lookup(&name).map_or(-1, |material| i32::from(material_code(material)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_step_and_read_cells() {
+        unsafe {
+            let engine = sandengine_create(16, 16, 5);
+            assert_eq!((sandengine_width(engine), sandengine_height(engine)), (16, 16));
+            let sand = sandengine_material_code(engine, c"sand".as_ptr());
+            assert_eq!(sand, i32::from(material_code(MaterialType::Sand)));
+            assert_eq!(sandengine_material_code(engine, c"unobtainium".as_ptr()), -1);
+
+            assert_eq!(sandengine_paint(engine, 8, 2, sand as u16, 1), SandEngineStatus::Ok);
+            assert_eq!(sandengine_paint(engine, 8, 2, 9999, 1), SandEngineStatus::InvalidArgument);
+            assert_eq!(sandengine_paint(engine, 80, 2, sand as u16, 1), SandEngineStatus::InvalidArgument);
+            for _ in 0..60 {
+                assert_eq!(sandengine_step(engine, 1.0 / 60.0), SandEngineStatus::Ok);
+            }
+
+            let mut cells = vec![SandCell { material: 0, temperature: 0.0 }; 16 * 16];
+            assert_eq!(sandengine_get_cells(engine, cells.as_mut_ptr(), 10), SandEngineStatus::BufferTooSmall);
+            assert_eq!(sandengine_get_cells(engine, cells.as_mut_ptr(), cells.len()), SandEngineStatus::Ok);
+            let grains = cells.iter().filter(|cell| i32::from(cell.material) == sand).count();
+            assert_eq!(grains, 5);
+            // Everything settled onto the bottom rows
+            assert!(cells[..16 * 12].iter().all(|cell| cell.material == 0));
+            sandengine_destroy(engine);
+        }
+    }
+
+    #[test]
+    fn test_null_and_invalid_arguments_are_refused() {
+        unsafe {
+            assert!(sandengine_create(0, 16, 0).is_null());
+            assert_eq!(sandengine_step(std::ptr::null_mut(), 1.0), SandEngineStatus::NullPointer);
+            assert_eq!(sandengine_paint(std::ptr::null_mut(), 0, 0, 1, 1), SandEngineStatus::NullPointer);
+            let engine = sandengine_create(4, 4, 0);
+            assert_eq!(sandengine_get_cells(engine, std::ptr::null_mut(), 16), SandEngineStatus::NullPointer);
+            assert_eq!(sandengine_material_code(engine, std::ptr::null()), -1);
+            sandengine_destroy(engine);
+            sandengine_destroy(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::interactions::Interaction;
+use crate::materials::{Material, MaterialType};
+use crate::particle::Particle;
+use crate::reactions::NEIGHBOR_OFFSETS;
+use crate::simulation::Simulation;
+use rand::Rng;
+
+/// Gases this much lighter than air (density -5) rise at full buoyancy
+const FULL_BUOYANCY_DENSITY: f32 = 5.0;
+/// Extra weight of upward moves at full buoyancy
+const BUOYANCY_LIFT: f32 = 4.0;
+/// How strongly gas is drawn towards cells with fewer gas neighbours
+const PRESSURE_PUSH: f32 = 3.0;
+/// Weight of moving down at full pressure; uncrowded gas never sinks
+const DOWNWARD_EXPANSION: f32 = 0.6;
+/// Diagonal moves count for this fraction of the straight move in the same direction
+const DIAGONAL_FACTOR: f32 = 0.7;
+/// Weight of staying put, so dilute gas drifts rather than jitters
+const STILL_WEIGHT: f32 = 0.4;
+/// Lifespan drain for an isolated cell of gas and for one surrounded by gas; pockets
+/// outlast wisps
+const DILUTE_DISSIPATION: f32 = 1.6;
+const PACKED_DISSIPATION: f32 = 0.4;
+
+impl Simulation {
+    /// Fraction of the 8 cells around `(x, y)` holding gas, 0 to 1. Gas expands from
+    /// high pressure towards low and only pushes downwards once crowded.
+    pub fn gas_pressure(&self, x: usize, y: usize) -> f32 {
+        self.gas_neighbours(x, y, None) as f32 / NEIGHBOR_OFFSETS.len() as f32
+    }
+
+    fn gas_neighbours(&self, x: usize, y: usize, ignore: Option<(usize, usize)>) -> usize {
+        NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| self.offset_cell(x, y, dx, dy))
+            .filter(|&cell| Some(cell) != ignore)
+            .filter(|&(nx, ny)| self.get_particle(nx, ny).is_some_and(|particle| self.is_diffusing_gas(particle.material_type)))
+            .count()
+    }
+
+    /// Gases follow the diffusion model; Fire keeps its own flicker of movement
+    pub(crate) fn is_diffusing_gas(&self, material_type: MaterialType) -> bool {
+        material_type != MaterialType::Fire && self.materials().get(material_type).is_gas(material_type)
+    }
+
+    /// Scale for how fast a gas particle's lifespan runs down: dilute gas fades quickly
+    /// while gas packed into a pocket lingers
+    pub(crate) fn gas_dissipation_scale(&self, particle: &Particle) -> f32 {
+        let pressure = self.gas_pressure(particle.x, particle.y);
+        DILUTE_DISSIPATION + (PACKED_DISSIPATION - DILUTE_DISSIPATION) * pressure
+    }
+
+    /// Where a gas particle moves this tick. Each empty neighbour is weighted by
+    /// buoyancy (lighter gases favour rising) and by how much lower its gas pressure is,
+    /// so gas spreads to fill enclosed volumes, pools under solid ceilings and only
+    /// expands downwards when crowded.
+    pub(crate) fn move_gas(&mut self, particle: &Particle, props: &Material) -> (usize, usize) {
+        let (x, y) = (particle.x, particle.y);
+        let buoyancy = (-props.density / FULL_BUOYANCY_DENSITY).clamp(0.0, 1.0);
+        let pressure = self.gas_pressure(x, y);
+
+        // Blocked neighbours keep a weight of zero
+        let mut candidates = [((x, y), 0.0); NEIGHBOR_OFFSETS.len() + 1];
+        candidates[0].1 = STILL_WEIGHT;
+        let mut total = STILL_WEIGHT;
+        for (slot, &(dx, dy)) in candidates[1..].iter_mut().zip(&NEIGHBOR_OFFSETS) {
+            let Some((nx, ny)) = self.offset_cell(x, y, dx, dy) else {
+                continue;
+            };
+            // Filled cells are only entered where the interaction matrix lets the gas through;
+            // the update loop then moves what was there into the cell the gas left
+            let blocked = self.get_particle(nx, ny).is_some_and(|target| {
+                target.material_type != MaterialType::Empty
+                    && match self.interaction(particle.material_type, target.material_type) {
+                        Interaction::Swap => false,
+                        Interaction::Displace => dy != -1,
+                        Interaction::Block | Interaction::React => true,
+                    }
+            });
+            if blocked {
+                continue;
+            }
+            let mut weight = match dy {
+                -1 => 1.0 + BUOYANCY_LIFT * buoyancy,
+                0 => 1.0,
+                _ => DOWNWARD_EXPANSION * pressure,
+            };
+            if dx != 0 && dy != 0 {
+                weight *= DIAGONAL_FACTOR;
+            }
+            let target_pressure = self.gas_neighbours(nx, ny, Some((x, y))) as f32 / NEIGHBOR_OFFSETS.len() as f32;
+            weight *= 1.0 + PRESSURE_PUSH * (pressure - target_pressure).max(0.0);
+            *slot = ((nx, ny), weight);
+            total += weight;
+        }
+
+        let mut roll = self.rng.gen::<f32>() * total;
+        for &(position, weight) in candidates.iter().filter(|(_, weight)| *weight > 0.0) {
+            if roll < weight {
+                return position;
+            }
+            roll -= weight;
+        }
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gas_cells(simulation: &Simulation) -> Vec<(usize, usize)> {
+        simulation.iter_particles()
+            .filter(|particle| particle.material_type == MaterialType::Steam)
+            .map(|particle| (particle.x, particle.y))
+            .collect()
+    }
+
+    #[test]
+    fn test_gas_pools_under_ceiling() {
+        let mut simulation = Simulation::new(30, 30);
+        simulation.set_seed(4);
+        // Generators are the one built-in solid that never falls
+        for x in 0..30 {
+            simulation.add_particle(x, 5, MaterialType::Generator, None);
+        }
+        for x in 10..20 {
+            simulation.add_particle(x, 25, MaterialType::Steam, None);
+        }
+        for _ in 0..200 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        let gas = gas_cells(&simulation);
+        assert!(!gas.is_empty());
+        assert!(gas.iter().all(|&(_, y)| y > 5));
+        // Buoyant gas gathers in a layer just below the stone instead of drifting
+        let pooled = gas.iter().filter(|&&(_, y)| y <= 9).count();
+        assert!(pooled * 2 > gas.len(), "{} of {} pooled", pooled, gas.len());
+        // and spreads sideways along the ceiling beyond where it was released
+        assert!(gas.iter().any(|&(x, _)| !(10..20).contains(&x)));
+    }
+
+    #[test]
+    fn test_pressure_and_dissipation() {
+        let mut simulation = Simulation::new(10, 10);
+        for y in 3..6 {
+            for x in 3..6 {
+                simulation.add_particle(x, y, MaterialType::Smoke, None);
+            }
+        }
+        simulation.add_particle(8, 8, MaterialType::Smoke, None);
+
+        assert_eq!(simulation.gas_pressure(4, 4), 1.0);
+        assert_eq!(simulation.gas_pressure(8, 8), 0.0);
+        let packed = simulation.get_particle(4, 4).unwrap().clone();
+        let wisp = simulation.get_particle(8, 8).unwrap().clone();
+        assert!(simulation.gas_dissipation_scale(&packed) < simulation.gas_dissipation_scale(&wisp));
+        assert!(!simulation.is_diffusing_gas(MaterialType::Fire));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::materials::MaterialType;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+
+/// Particles a generator emits per second unless configured otherwise
+pub const DEFAULT_GENERATOR_RATE: f32 = 10.0;
+
+/// What one Generator cell emits into the cell below it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeneratorConfig {
+    /// None until configured or until a material is first painted onto the generator
+    pub material: Option<MaterialType>,
+    /// Particles per second
+    pub rate: f32,
+    /// Fraction of the next particle built up so far
+    #[serde(default)]
+    progress: f32,
+}
+
+impl GeneratorConfig {
+    pub fn new(material: MaterialType, rate: f32) -> Self {
+        Self { material: Some(material), rate: rate.max(0.0), progress: 0.0 }
+    }
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        Self { material: None, rate: DEFAULT_GENERATOR_RATE, progress: 0.0 }
+    }
+}
+
+impl Simulation {
+    /// Configuration of the generator at a cell, if one has been configured or painted on
+    pub fn generator(&self, x: usize, y: usize) -> Option<&GeneratorConfig> {
+        self.generators.get(&(x, y))
+    }
+
+    /// Configured generators and their cells, in no particular order
+    pub fn generators(&self) -> impl Iterator<Item = ((usize, usize), &GeneratorConfig)> + '_ {
+        self.generators.iter().map(|(&cell, config)| (cell, config))
+    }
+
+    /// Make the generator at a cell emit `material` at `rate` particles per second; false
+    /// when the cell holds no generator or the material can't be emitted
+    pub fn configure_generator(&mut self, x: usize, y: usize, material: MaterialType, rate: f32) -> bool {
+        if !self.is_generator(x, y) || matches!(material, MaterialType::Empty | MaterialType::Eraser | MaterialType::Generator) {
+            return false;
+        }
+        self.generators.insert((x, y), GeneratorConfig::new(material, rate));
+        true
+    }
+
+    /// Replace every generator configuration, e.g. from a save; entries for cells
+    /// without a generator are dropped
+    pub fn set_generators(&mut self, generators: impl IntoIterator<Item = ((usize, usize), GeneratorConfig)>) {
+        self.generators.clear();
+        for ((x, y), config) in generators {
+            if self.is_generator(x, y) {
+                self.generators.insert((x, y), config);
+            }
+        }
+    }
+
+    /// Paint `material` onto a generator: the first material painted on an unconfigured
+    /// generator becomes what it emits
+    pub(crate) fn paint_generator(&mut self, x: usize, y: usize, material: MaterialType) {
+        if matches!(material, MaterialType::Empty | MaterialType::Eraser | MaterialType::Generator) {
+            return;
+        }
+        let config = self.generators.entry((x, y)).or_default();
+        config.material.get_or_insert(material);
+    }
+
+    /// Emit from every configured generator, dropping configurations whose generator is
+    /// gone. A generator whose cell below is taken waits with at most one particle due.
+    pub(crate) fn step_generators(&mut self, delta_time: f32) {
+        if self.generators.is_empty() {
+            return;
+        }
+        let mut generators = std::mem::take(&mut self.generators);
+        generators.retain(|&(x, y), _| self.is_generator(x, y));
+        for (&(x, y), config) in generators.iter_mut() {
+            let Some(material) = config.material else {
+                continue;
+            };
+            config.progress = (config.progress + config.rate * delta_time).min(1.0);
+            if config.progress < 1.0 {
+                continue;
+            }
+            if let Some((below_x, below_y)) = self.offset_cell(x, y, 0, 1) {
+                if self.get_particle(below_x, below_y).is_none() && self.add_particle(below_x, below_y, material, None) {
+                    config.progress -= 1.0;
+                }
+            }
+        }
+        self.generators = generators;
+    }
+
+    fn is_generator(&self, x: usize, y: usize) -> bool {
+        self.get_particle(x, y).is_some_and(|particle| particle.material_type == MaterialType::Generator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::save_load::SimulationSave;
+
+    #[test]
+    fn test_first_painted_material_is_emitted() {
+        let mut simulation = Simulation::new(8, 16);
+        simulation.add_particle(4, 0, MaterialType::Generator, None);
+        assert!(simulation.generator(4, 0).is_none());
+
+        // Painting doesn't overwrite the generator, but picks what it emits
+        assert!(!simulation.add_particle(4, 0, MaterialType::Water, None));
+        assert!(!simulation.add_particle(4, 0, MaterialType::Sand, None));
+        assert_eq!(simulation.generator(4, 0).and_then(|config| config.material), Some(MaterialType::Water));
+
+        for _ in 0..60 {
+            simulation.update(1.0 / 60.0);
+        }
+        let water = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Water).count();
+        assert!((8..=10).contains(&water), "emitted {}", water);
+        assert_eq!(simulation.get_particle(4, 0).map(|p| p.material_type), Some(MaterialType::Generator));
+
+        // Erasing the generator drops its configuration
+        simulation.add_particle(4, 0, MaterialType::Eraser, None);
+        simulation.update(1.0 / 60.0);
+        assert_eq!(simulation.generators().count(), 0);
+    }
+
+    #[test]
+    fn test_blocked_generator_waits_and_is_saved() {
+        let mut simulation = Simulation::new(8, 8);
+        assert!(!simulation.configure_generator(2, 2, MaterialType::Sand, 30.0));
+        simulation.add_particle(2, 2, MaterialType::Generator, None);
+        simulation.add_particle(2, 3, MaterialType::Generator, None);
+        assert!(simulation.configure_generator(2, 2, MaterialType::Sand, 30.0));
+
+        // Nothing comes out while the cell below is a generator
+        for _ in 0..30 {
+            simulation.update(1.0 / 60.0);
+        }
+        assert_eq!(simulation.iter_particles().count(), 2);
+        assert_eq!(simulation.generator(2, 2).map(|config| config.progress), Some(1.0));
+
+        let restored = SimulationSave::capture(&simulation).restore();
+        assert_eq!(restored.generator(2, 2), simulation.generator(2, 2));
+    }
+}
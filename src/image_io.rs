@@ -0,0 +1,228 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::MaterialType;
+use crate::simulation::Simulation;
+#[cfg(feature = "files")]
+use std::fs::File;
+#[cfg(feature = "files")]
+use std::io::{BufReader, BufWriter};
+use std::io::{Read, Write};
+#[cfg(feature = "files")]
+use std::path::Path;
+use std::sync::Arc;
+
+/// Pixels less opaque than this are read as empty cells
+pub const ALPHA_THRESHOLD: u8 = 128;
+
+/// Colors standing for materials when reading and writing images. Each pixel becomes the
+/// material whose color is closest to it; `Empty` entries leave the cell empty.
+#[derive(Debug, Clone, Default)]
+pub struct ImagePalette {
+    entries: Vec<([u8; 3], MaterialType)>,
+}
+
+impl ImagePalette {
+    /// A palette with no colors; every opaque pixel of an image read with it is empty
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every material of `registry` by its base color, with black as empty space
+    pub fn from_registry(registry: &MaterialRegistry) -> Self {
+        let mut palette = Self::new();
+        for material_type in registry.material_types() {
+            if material_type != MaterialType::Eraser {
+                palette.insert(registry.get(material_type).base_color, material_type);
+            }
+        }
+        palette
+    }
+
+    /// Map `color` to `material_type`, replacing both any material already using the color
+    /// and any color the material already had
+    pub fn insert(&mut self, color: [u8; 3], material_type: MaterialType) -> &mut Self {
+        self.entries.retain(|(existing, material)| *existing != color && *material != material_type);
+        self.entries.push((color, material_type));
+        self
+    }
+
+    pub fn entries(&self) -> &[([u8; 3], MaterialType)] {
+        &self.entries
+    }
+
+    /// The material nearest `color` in RGB space; earlier entries win ties
+    pub fn closest(&self, color: [u8; 3]) -> Option<MaterialType> {
+        self.entries.iter()
+            .min_by_key(|(entry, _)| distance_sq(*entry, color))
+            .map(|(_, material_type)| *material_type)
+    }
+
+    pub fn color_of(&self, material_type: MaterialType) -> Option<[u8; 3]> {
+        self.entries.iter()
+            .find(|(_, material)| *material == material_type)
+            .map(|(color, _)| *color)
+    }
+}
+
+/// Width, height and row-major (color, alpha) pixels of a decoded PNG
+pub(crate) type DecodedPng = (usize, usize, Vec<([u8; 3], u8)>);
+
+/// Decode any 8-bit or smaller PNG
+pub(crate) fn decode_png(reader: impl Read) -> Result<DecodedPng, ImageError> {
+    let mut decoder = png::Decoder::new(reader);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels)?;
+    let pixels = pixels[..info.buffer_size()].chunks_exact(info.color_type.samples())
+        .map(|pixel| match *pixel {
+            [gray] => ([gray; 3], u8::MAX),
+            [gray, alpha] => ([gray; 3], alpha),
+            [r, g, b] => ([r, g, b], u8::MAX),
+            [r, g, b, alpha] => ([r, g, b], alpha),
+            _ => unreachable!("8-bit PNG pixels have one to four samples"),
+        })
+        .collect();
+    Ok((info.width as usize, info.height as usize, pixels))
+}
+
+fn distance_sq(a: [u8; 3], b: [u8; 3]) -> u32 {
+    a.iter().zip(b).map(|(a, b)| (*a as i32 - b as i32).pow(2) as u32).sum()
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    IoError(std::io::Error),
+    DecodingError(png::DecodingError),
+    EncodingError(png::EncodingError),
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(error: std::io::Error) -> Self {
+        ImageError::IoError(error)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(error: png::DecodingError) -> Self {
+        ImageError::DecodingError(error)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(error: png::EncodingError) -> Self {
+        ImageError::EncodingError(error)
+    }
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageError::IoError(e) => write!(f, "IO error: {}", e),
+            ImageError::DecodingError(e) => write!(f, "PNG decoding error: {}", e),
+            ImageError::EncodingError(e) => write!(f, "PNG encoding error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl Simulation {
+    /// Build a simulation the size of a PNG, one cell per pixel, so levels can be drawn
+    /// in an image editor. Transparent pixels are left empty.
+    #[cfg(feature = "files")]
+    pub fn from_image(path: impl AsRef<Path>, palette: &ImagePalette) -> Result<Self, ImageError> {
+        Self::from_image_with_materials(path, palette, Arc::new(MaterialRegistry::new()))
+    }
+
+    /// `from_image` for a palette that uses materials from a custom registry
+    #[cfg(feature = "files")]
+    pub fn from_image_with_materials(
+        path: impl AsRef<Path>,
+        palette: &ImagePalette,
+        materials: Arc<MaterialRegistry>,
+    ) -> Result<Self, ImageError> {
+        Self::read_png(BufReader::new(File::open(path)?), palette, materials)
+    }
+
+    pub fn read_png(reader: impl Read, palette: &ImagePalette, materials: Arc<MaterialRegistry>) -> Result<Self, ImageError> {
+        let (width, height, pixels) = decode_png(reader)?;
+        let mut simulation = Self::with_materials(width, height, materials);
+        for (index, (color, alpha)) in pixels.into_iter().enumerate() {
+            if alpha < ALPHA_THRESHOLD {
+                continue;
+            }
+            if let Some(material_type) = palette.closest(color).filter(|m| *m != MaterialType::Empty) {
+                simulation.add_particle(index % width, index / width, material_type, None);
+            }
+        }
+        Ok(simulation)
+    }
+
+    /// Write the grid as an RGBA PNG, each particle in its palette color (its base color
+    /// for materials the palette lacks) and empty cells transparent, so the image reads
+    /// back into the same grid
+    #[cfg(feature = "files")]
+    pub fn save_image(&self, path: impl AsRef<Path>, palette: &ImagePalette) -> Result<(), ImageError> {
+        self.write_png(BufWriter::new(File::create(path)?), palette)
+    }
+
+    pub fn write_png(&self, writer: impl Write, palette: &ImagePalette) -> Result<(), ImageError> {
+        let mut pixels = vec![0; self.width * self.height * 4];
+        for particle in self.iter_particles() {
+            let color = palette.color_of(particle.material_type)
+                .unwrap_or(self.materials().get(particle.material_type).base_color);
+            let offset = (particle.y * self.width + particle.x) * 4;
+            pixels[offset..offset + 4].copy_from_slice(&[color[0], color[1], color[2], u8::MAX]);
+        }
+
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&pixels)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material_at(simulation: &Simulation, x: usize, y: usize) -> Option<MaterialType> {
+        simulation.get_particle(x, y).map(|particle| particle.material_type)
+    }
+
+    #[test]
+    fn test_closest_color_matching() {
+        let mut palette = ImagePalette::new();
+        palette
+            .insert([0, 0, 0], MaterialType::Empty)
+            .insert([200, 180, 130], MaterialType::Sand)
+            .insert([40, 90, 210], MaterialType::Water);
+        assert_eq!(palette.closest([190, 175, 120]), Some(MaterialType::Sand));
+        assert_eq!(palette.closest([0, 0, 255]), Some(MaterialType::Water));
+        assert_eq!(palette.closest([20, 20, 20]), Some(MaterialType::Empty));
+
+        palette.insert([255, 255, 255], MaterialType::Sand);
+        assert_eq!(palette.color_of(MaterialType::Sand), Some([255, 255, 255]));
+        assert_eq!(palette.entries().len(), 3);
+    }
+
+    #[test]
+    fn test_png_round_trip() {
+        let mut simulation = Simulation::new(6, 4);
+        simulation.add_particle(0, 3, MaterialType::Stone, None);
+        simulation.add_particle(5, 3, MaterialType::Water, None);
+        simulation.add_particle(2, 1, MaterialType::Wood, None);
+        let palette = ImagePalette::from_registry(simulation.materials());
+
+        let mut png = Vec::new();
+        simulation.write_png(&mut png, &palette).unwrap();
+        let loaded = Simulation::read_png(png.as_slice(), &palette, Arc::new(MaterialRegistry::new())).unwrap();
+
+        assert_eq!((loaded.width, loaded.height), (6, 4));
+        assert_eq!(loaded.iter_particles().count(), 3);
+        assert_eq!(material_at(&loaded, 0, 3), Some(MaterialType::Stone));
+        assert_eq!(material_at(&loaded, 5, 3), Some(MaterialType::Water));
+        assert_eq!(material_at(&loaded, 2, 1), Some(MaterialType::Wood));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::MaterialType;
+use crate::render::{ChunkActivity, TextRenderer, BYTES_PER_PIXEL, GLYPH_HEIGHT};
+use crate::simulation::{Simulation, SIM_CHUNK_SIZE};
+
+const PANEL_COLOR: [u8; 3] = [24, 24, 30];
+/// Gap in pixels between the cursor and the inspector panel
+const CURSOR_GAP: usize = 8;
+
+/// Everything the inspector shows about one cell of a simulation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellInfo {
+    pub x: usize,
+    pub y: usize,
+    /// None for an empty cell, as are the particle's fields below
+    pub material: Option<MaterialType>,
+    pub temp: Option<f32>,
+    pub life: Option<f32>,
+    pub burning: Option<bool>,
+    /// Impulse velocity in cells per tick
+    pub velocity: Option<(f32, f32)>,
+    /// Update chunk holding the cell
+    pub chunk: (usize, usize),
+    /// Dirty if a cell of the chunk was written after the tick the inspector was given
+    pub chunk_activity: ChunkActivity,
+}
+
+impl CellInfo {
+    /// Inspect the cell at `(x, y)`, counting its chunk as dirty if it changed after
+    /// `since_tick`; None for cells off the grid
+    pub fn capture(simulation: &Simulation, x: usize, y: usize, since_tick: u64) -> Option<Self> {
+        if x >= simulation.width || y >= simulation.height {
+            return None;
+        }
+        let particle = simulation.get_particle(x, y).filter(|particle| particle.material_type != MaterialType::Empty);
+        let chunk = (x / SIM_CHUNK_SIZE, y / SIM_CHUNK_SIZE);
+        let chunk_activity = if simulation.chunk_changed_since(chunk.0, chunk.1, since_tick) {
+            ChunkActivity::Dirty
+        } else if simulation.is_chunk_awake(chunk.0, chunk.1) {
+            ChunkActivity::Active
+        } else {
+            ChunkActivity::Sleeping
+        };
+        Some(Self {
+            x,
+            y,
+            material: particle.map(|particle| particle.material_type),
+            temp: particle.map(|particle| particle.temp),
+            life: particle.and_then(|particle| particle.life),
+            burning: particle.map(|particle| particle.burning),
+            velocity: particle.map(|particle| (particle.vx, particle.vy)),
+            chunk,
+            chunk_activity,
+        })
+    }
+
+    /// The panel's lines, naming the material from `registry`
+    pub fn lines(&self, registry: &MaterialRegistry) -> Vec<String> {
+        let mut lines = vec![format!("Cell {}, {}", self.x, self.y)];
+        match self.material {
+            Some(material) => lines.push(registry.get(material).name.clone()),
+            None => lines.push("Empty".to_string()),
+        }
+        if let Some(temp) = self.temp {
+            lines.push(format!("Temp {:.1}C", temp));
+        }
+        if let Some(life) = self.life {
+            lines.push(format!("Life {:.1}s", life));
+        }
+        if self.burning == Some(true) {
+            lines.push("Burning".to_string());
+        }
+        if let Some((vx, vy)) = self.velocity.filter(|&(vx, vy)| vx != 0.0 || vy != 0.0) {
+            lines.push(format!("Velocity {:.2}, {:.2}", vx, vy));
+        }
+        lines.push(format!("Chunk {}, {} {:?}", self.chunk.0, self.chunk.1, self.chunk_activity));
+        lines
+    }
+}
+
+/// Draws a `CellInfo` as a floating panel next to the cursor
+#[derive(Debug, Clone)]
+pub struct CellInspector {
+    text: TextRenderer,
+}
+
+impl CellInspector {
+    pub fn new() -> Self {
+        Self { text: TextRenderer::new() }
+    }
+
+    /// Draw the panel below and right of the cursor at frame pixel `cursor`, flipped to
+    /// the other side where it would run off a `width` x `height` frame
+    pub fn draw(&self, frame: &mut [u8], width: usize, height: usize, info: &CellInfo, registry: &MaterialRegistry, cursor: (usize, usize)) {
+        let lines = info.lines(registry);
+        let line_height = GLYPH_HEIGHT + 2;
+        let panel_width = lines.iter().map(|line| TextRenderer::text_width(line)).max().unwrap_or(0) + 5;
+        let panel_height = lines.len() * line_height + 3;
+        let left = match cursor.0 + CURSOR_GAP + panel_width > width {
+            true => cursor.0.saturating_sub(CURSOR_GAP + panel_width),
+            false => cursor.0 + CURSOR_GAP,
+        };
+        let top = match cursor.1 + CURSOR_GAP + panel_height > height {
+            true => cursor.1.saturating_sub(CURSOR_GAP + panel_height),
+            false => cursor.1 + CURSOR_GAP,
+        };
+
+        for py in top..(top + panel_height).min(height) {
+            for px in left..(left + panel_width).min(width) {
+                let offset = (py * width + px) * BYTES_PER_PIXEL;
+                frame[offset..offset + 3].copy_from_slice(&PANEL_COLOR);
+            }
+        }
+        for (index, line) in lines.iter().enumerate() {
+            self.text.draw_text(frame, width, height, left as i32 + 2, (top + 2 + index * line_height) as i32, line);
+        }
+    }
+}
+
+impl Default for CellInspector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::MaterialRenderer;
+
+    #[test]
+    fn test_capture_reports_particle_and_chunk() {
+        let mut simulation = Simulation::new(64, 64);
+        simulation.update(1.0 / 60.0);
+        let tick = simulation.tick();
+        simulation.add_particle(40, 5, MaterialType::Fire, Some(700.0));
+
+        let fire = CellInfo::capture(&simulation, 40, 5, tick).unwrap();
+        assert_eq!(fire.material, Some(MaterialType::Fire));
+        assert!(fire.temp.is_some() && fire.life.is_some());
+        assert_eq!((fire.chunk, fire.chunk_activity), ((2, 0), ChunkActivity::Dirty));
+        assert!(fire.lines(simulation.materials()).contains(&"Fire".to_string()));
+
+        let empty = CellInfo::capture(&simulation, 5, 60, tick).unwrap();
+        assert_eq!((empty.material, empty.temp, empty.chunk), (None, None, (0, 3)));
+        assert_ne!(empty.chunk_activity, ChunkActivity::Dirty);
+        assert!(CellInfo::capture(&simulation, 64, 0, tick).is_none());
+    }
+
+    #[test]
+    fn test_panel_flips_away_from_frame_edges() {
+        let simulation = Simulation::new(32, 32);
+        let info = CellInfo::capture(&simulation, 31, 31, 0).unwrap();
+        let (width, height) = (120, 80);
+        let mut frame = vec![0; MaterialRenderer::frame_len(width, height)];
+        CellInspector::new().draw(&mut frame, width, height, &info, simulation.materials(), (width - 1, height - 1));
+
+        let pixel = |x: usize, y: usize| &frame[(y * width + x) * BYTES_PER_PIXEL..(y * width + x) * BYTES_PER_PIXEL + 3];
+        assert_eq!(pixel(width - 1 - CURSOR_GAP - 1, height - 1 - CURSOR_GAP - 1), PANEL_COLOR);
+        assert_eq!(pixel(width - 1, height - 1), [0, 0, 0]);
+    }
+}
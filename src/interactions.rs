@@ -0,0 +1,222 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Particles at least this much denser than a fluid sink through it
+pub const MIN_SWAP_DENSITY_DIFFERENCE: f32 = 0.1;
+/// Most cells a particle sinks or rises through in one tick
+pub const MAX_SWAP_CELLS: usize = 3;
+
+/// What happens when a moving particle runs into a cell that is already filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interaction {
+    /// The mover sinks into the cell, or rises into it if it is a gas, and pushes what
+    /// was there into the cell it left; only in the direction it falls
+    Displace,
+    /// The two trade places in any direction the mover goes, sideways included
+    Swap,
+    /// The mover stays out
+    Block,
+    /// The mover stays out and leaves the pair to the reaction table
+    React,
+}
+
+impl Interaction {
+    /// Whether a mover falling straight into the cell gets through
+    pub fn passes_vertically(self) -> bool {
+        matches!(self, Interaction::Displace | Interaction::Swap)
+    }
+}
+
+/// For every ordered pair of materials, whether the first gets into a cell holding the
+/// second. Pairs fall back to a rule derived from their material properties unless
+/// overridden with `set`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InteractionMatrix {
+    overrides: HashMap<(MaterialType, MaterialType), Interaction>,
+}
+
+impl InteractionMatrix {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How `mover` interacts with a cell holding `target`
+    pub fn get(&self, materials: &MaterialRegistry, mover: MaterialType, target: MaterialType) -> Interaction {
+        self.overrides.get(&(mover, target)).copied()
+            .unwrap_or_else(|| Self::derived(materials, mover, target))
+    }
+
+    /// Override one ordered pair; `(b, a)` keeps its own rule
+    pub fn set(&mut self, mover: MaterialType, target: MaterialType, interaction: Interaction) {
+        self.overrides.insert((mover, target), interaction);
+    }
+
+    /// Override both orders of a pair, e.g. `Swap` so two powders mix
+    pub fn set_symmetric(&mut self, a: MaterialType, b: MaterialType, interaction: Interaction) {
+        self.set(a, b, interaction);
+        self.set(b, a, interaction);
+    }
+
+    /// Go back to the derived rule for a pair, returning the override it had
+    pub fn reset(&mut self, mover: MaterialType, target: MaterialType) -> Option<Interaction> {
+        self.overrides.remove(&(mover, target))
+    }
+
+    pub fn overrides(&self) -> impl Iterator<Item = (MaterialType, MaterialType, Interaction)> + '_ {
+        self.overrides.iter().map(|(&(mover, target), &interaction)| (mover, target, interaction))
+    }
+
+    /// The rule without overrides: anything that falls displaces a liquid or gas at least
+    /// `MIN_SWAP_DENSITY_DIFFERENCE` lighter than itself, except Fire; everything else
+    /// blocks. Gases spread by diffusion rather than through each other.
+    pub fn derived(materials: &MaterialRegistry, mover: MaterialType, target: MaterialType) -> Interaction {
+        if target == MaterialType::Empty {
+            return Interaction::Displace;
+        }
+        if mover == target || matches!(target, MaterialType::Generator | MaterialType::Fire) {
+            return Interaction::Block;
+        }
+        let (mover_props, target_props) = (materials.get(mover), materials.get(target));
+        let fluid = target_props.is_liquid(target) || target_props.is_gas(target);
+        let falls = mover_props.density >= 0.0;
+        if fluid && falls && mover_props.density - target_props.density >= MIN_SWAP_DENSITY_DIFFERENCE {
+            Interaction::Displace
+        } else {
+            Interaction::Block
+        }
+    }
+}
+
+impl Simulation {
+    /// How a particle of `mover` interacts with a cell holding `target`
+    pub fn interaction(&self, mover: MaterialType, target: MaterialType) -> Interaction {
+        self.interactions().get(self.materials(), mover, target)
+    }
+
+    /// Sink `particle` down (`dir` 1), or float it up (`dir` -1), through the run of up
+    /// to `MAX_SWAP_CELLS` cells it displaces, shifting each one cell back towards where
+    /// it came from; returns where it ends up, or None if the next cell blocks it. Call
+    /// while the particle is lifted out of the grid for its update.
+    pub(crate) fn sink_through(&mut self, particle: &Particle, dir: i32) -> Option<(usize, usize)> {
+        let (x, y) = (particle.x, particle.y);
+        let height = self.height as i32;
+        let row = move |d: usize| {
+            let ny = y as i32 + dir * d as i32;
+            (0..height).contains(&ny).then_some(ny as usize)
+        };
+        let depth = (1..=MAX_SWAP_CELLS)
+            .take_while(|&d| {
+                row(d).and_then(|ny| self.get_particle(x, ny))
+                    .is_some_and(|target| self.interaction(particle.material_type, target.material_type).passes_vertically())
+            })
+            .count();
+        if depth == 0 {
+            return None;
+        }
+        for d in 1..=depth {
+            // The cell behind is empty: the origin at first, then each one vacated
+            self.displace((x, row(d)?), (x, row(d - 1)?));
+        }
+        row(depth).map(|ny| (x, ny))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derived_interactions_follow_density() {
+        let materials = MaterialRegistry::new();
+        let matrix = InteractionMatrix::new();
+        let get = |mover, target| matrix.get(&materials, mover, target);
+        assert_eq!(get(MaterialType::Sand, MaterialType::Water), Interaction::Displace);
+        assert_eq!(get(MaterialType::Water, MaterialType::Sand), Interaction::Block);
+        assert_eq!(get(MaterialType::Water, MaterialType::Oil), Interaction::Displace);
+        assert_eq!(get(MaterialType::Oil, MaterialType::Water), Interaction::Block);
+        assert_eq!(get(MaterialType::Water, MaterialType::Steam), Interaction::Displace);
+        assert_eq!(get(MaterialType::Steam, MaterialType::Smoke), Interaction::Block);
+        assert_eq!(get(MaterialType::Sand, MaterialType::Fire), Interaction::Block);
+        assert_eq!(get(MaterialType::Sand, MaterialType::Stone), Interaction::Block);
+        assert_eq!(get(MaterialType::Water, MaterialType::Water), Interaction::Block);
+
+        let mut matrix = matrix;
+        matrix.set_symmetric(MaterialType::Sand, MaterialType::Ash, Interaction::Swap);
+        matrix.set(MaterialType::Sand, MaterialType::Water, Interaction::Block);
+        assert_eq!(matrix.get(&materials, MaterialType::Ash, MaterialType::Sand), Interaction::Swap);
+        assert_eq!(matrix.get(&materials, MaterialType::Sand, MaterialType::Water), Interaction::Block);
+        assert_eq!(matrix.reset(MaterialType::Sand, MaterialType::Water), Some(Interaction::Block));
+        assert_eq!(matrix.get(&materials, MaterialType::Sand, MaterialType::Water), Interaction::Displace);
+        assert_eq!(matrix.overrides().count(), 2);
+    }
+
+    #[test]
+    fn test_sand_sinks_through_water() {
+        let mut simulation = Simulation::new(1, 12);
+        simulation.set_seed(2);
+        for y in 6..12 {
+            simulation.add_particle(0, y, MaterialType::Water, None);
+        }
+        simulation.add_particle(0, 5, MaterialType::Sand, None);
+        for _ in 0..60 {
+            simulation.update(1.0 / 60.0);
+        }
+        // Soaking on the way down
+        assert_eq!(simulation.get_particle(0, 11).unwrap().material_type, MaterialType::WetSand);
+
+        // Overridden to float
+        simulation.interactions_mut().set(MaterialType::Sand, MaterialType::Water, Interaction::Block);
+        simulation.interactions_mut().set(MaterialType::WetSand, MaterialType::Water, Interaction::Block);
+        simulation.clear();
+        for y in 6..12 {
+            simulation.add_particle(0, y, MaterialType::Water, None);
+        }
+        simulation.add_particle(0, 5, MaterialType::Sand, None);
+        for _ in 0..60 {
+            simulation.update(1.0 / 60.0);
+        }
+        // Only dropping into the gap left by the water it soaked up
+        let grain = simulation.iter_particles()
+            .find(|p| matches!(p.material_type, MaterialType::Sand | MaterialType::WetSand))
+            .unwrap();
+        assert!(simulation.iter_particles().filter(|p| p.material_type == MaterialType::Water).all(|water| water.y > grain.y));
+    }
+
+    #[test]
+    fn test_sinking_moves_each_particle_once_per_tick() {
+        let mut simulation = Simulation::new(1, 12);
+        simulation.set_seed(6);
+        simulation.enable_motion_tracking();
+        for y in 4..12 {
+            simulation.add_particle(0, y, MaterialType::Water, None);
+        }
+        simulation.add_particle(0, 3, MaterialType::Sand, None);
+        simulation.update(1.0 / 60.0);
+
+        // The sand sank through a full run of water, which each moved up a single cell
+        assert_eq!(simulation.get_particle(0, 3 + MAX_SWAP_CELLS).unwrap().material_type, MaterialType::Sand);
+        let moves = simulation.last_moves();
+        assert_eq!(moves.len(), MAX_SWAP_CELLS + 1);
+        assert!(moves.iter().filter(|step| step.to.1 < step.from.1).all(|step| step.from.1 - step.to.1 == 1));
+        assert_eq!(simulation.iter_particles().count(), 9);
+
+        // Oil poured under water floats up through it
+        simulation.clear();
+        for y in 6..12 {
+            simulation.add_particle(0, y, MaterialType::Oil, None);
+        }
+        for y in 0..6 {
+            simulation.add_particle(0, y, MaterialType::Water, None);
+        }
+        for _ in 0..120 {
+            simulation.update(1.0 / 60.0);
+        }
+        assert!((0..6).all(|y| simulation.get_particle(0, y).unwrap().material_type == MaterialType::Oil));
+        assert!((6..12).all(|y| simulation.get_particle(0, y).unwrap().material_type == MaterialType::Water));
+    }
+}
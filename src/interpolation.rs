@@ -0,0 +1,136 @@
+use crate::simulation::Simulation;
+use std::collections::HashMap;
+
+/// Physics ticks per second unless configured otherwise
+pub const DEFAULT_TICK_RATE: f32 = 30.0;
+/// Most ticks `FixedTimestep::advance` runs at once; time beyond that is dropped so a
+/// stall doesn't snowball into ever longer catch-ups
+pub const MAX_CATCH_UP_TICKS: u32 = 8;
+
+/// A particle's move during the last update, recorded while motion tracking is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParticleMove {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+/// Turns real frame times into a whole number of fixed-length physics ticks, carrying the
+/// remainder over; `alpha` is how far rendering is between the last two ticks
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+    tick_rate: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f32) -> Self {
+        let mut timestep = Self { tick_rate: DEFAULT_TICK_RATE, accumulator: 0.0 };
+        timestep.set_tick_rate(tick_rate);
+        timestep
+    }
+
+    pub fn tick_rate(&self) -> f32 {
+        self.tick_rate
+    }
+
+    /// Ticks per second; rates that aren't positive and finite are ignored
+    pub fn set_tick_rate(&mut self, tick_rate: f32) {
+        if tick_rate > 0.0 && tick_rate.is_finite() {
+            self.tick_rate = tick_rate;
+            self.accumulator = self.accumulator.min(self.tick_interval());
+        }
+    }
+
+    /// Seconds of simulated time per tick
+    pub fn tick_interval(&self) -> f32 {
+        1.0 / self.tick_rate
+    }
+
+    /// Add `elapsed` seconds and return how many ticks are now due
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        self.advance_at_most(elapsed, MAX_CATCH_UP_TICKS)
+    }
+
+    /// `advance` with its own limit on the ticks run at once in place of
+    /// `MAX_CATCH_UP_TICKS`
+    pub fn advance_at_most(&mut self, elapsed: f32, max_ticks: u32) -> u32 {
+        let interval = self.tick_interval();
+        self.accumulator += elapsed.max(0.0);
+        let due = (self.accumulator / interval).floor();
+        self.accumulator -= due * interval;
+        if due > max_ticks as f32 {
+            self.accumulator = 0.0;
+            return max_ticks;
+        }
+        due as u32
+    }
+
+    /// Share of the next tick that has already elapsed, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.tick_interval()).clamp(0.0, 1.0)
+    }
+}
+
+impl Default for FixedTimestep {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_RATE)
+    }
+}
+
+/// Where each particle that moved in the last update should be drawn, `alpha` of the way
+/// from the cell it left to the one it is in now, keyed by the cell it is in now. Empty
+/// unless the simulation has motion tracking on.
+pub fn moved_positions(simulation: &Simulation, alpha: f32) -> HashMap<(usize, usize), (f32, f32)> {
+    let alpha = alpha.clamp(0.0, 1.0);
+    simulation.last_moves().iter()
+        .filter(|step| simulation.get_particle(step.to.0, step.to.1).is_some())
+        .map(|step| {
+            let lerp = |from: usize, to: usize| from as f32 + (to as f32 - from as f32) * alpha;
+            (step.to, (lerp(step.from.0, step.to.0), lerp(step.from.1, step.to.1)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    #[test]
+    fn test_fixed_timestep_carries_remainder() {
+        let mut timestep = FixedTimestep::new(30.0);
+        assert_eq!(timestep.advance(0.02), 0);
+        assert!((timestep.alpha() - 0.6).abs() < 1e-4);
+        assert_eq!(timestep.advance(0.05), 2);
+        assert!((timestep.alpha() - 0.1).abs() < 1e-3);
+
+        // A long stall runs a bounded catch-up and starts the next tick afresh
+        assert_eq!(timestep.advance(10.0), MAX_CATCH_UP_TICKS);
+        assert_eq!(timestep.alpha(), 0.0);
+        timestep.set_tick_rate(0.0);
+        assert_eq!(timestep.tick_rate(), 30.0);
+    }
+
+    #[test]
+    fn test_moved_particles_are_placed_part_way() {
+        let mut simulation = Simulation::new(16, 16);
+        simulation.set_seed(1);
+        simulation.add_particle(8, 2, MaterialType::Sand, None);
+        simulation.add_particle(3, 15, MaterialType::Stone, None);
+        simulation.update(1.0 / 30.0);
+        assert!(moved_positions(&simulation, 0.5).is_empty());
+
+        simulation.enable_motion_tracking();
+        simulation.update(1.0 / 30.0);
+        let sand = simulation.iter_particles().find(|p| p.material_type == MaterialType::Sand).unwrap();
+        let (x, y) = (sand.x, sand.y);
+        let step = simulation.last_moves()[0];
+        assert_eq!(step.to, (x, y));
+        assert!(y > step.from.1);
+
+        let halfway = moved_positions(&simulation, 0.5)[&(x, y)];
+        assert_eq!(halfway.1, (step.from.1 + y) as f32 / 2.0);
+        assert_eq!(moved_positions(&simulation, 1.0)[&(x, y)], (x as f32, y as f32));
+        assert_eq!(moved_positions(&simulation, 1.0).len(), 1);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::MaterialType;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// Something a frontend does when a key is pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Action {
+    /// Pick the material in a slot of `KeyBindings::materials`, counting from 1
+    SelectMaterial(usize),
+    BrushLarger,
+    BrushSmaller,
+    CycleTool,
+    Clear,
+    Undo,
+    Redo,
+    TogglePause,
+    StepTick,
+    SlowDown,
+    SpeedUp,
+    QuickSave,
+    QuickLoad,
+    TogglePalette,
+    ToggleInspector,
+    ToggleChunkOverlay,
+    CycleRenderMode,
+    ToggleLighting,
+    ToggleEffects,
+    CycleWeather,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomIn,
+    ZoomOut,
+    BookmarkCamera,
+    NextBookmark,
+    PreviousBookmark,
+    ToggleTour,
+}
+
+/// Names of every action but `SelectMaterial`, which is written `select_material_<slot>`
+const ACTION_NAMES: [(&str, Action); 29] = [
+    ("brush_larger", Action::BrushLarger),
+    ("brush_smaller", Action::BrushSmaller),
+    ("cycle_tool", Action::CycleTool),
+    ("clear", Action::Clear),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("toggle_pause", Action::TogglePause),
+    ("step_tick", Action::StepTick),
+    ("slow_down", Action::SlowDown),
+    ("speed_up", Action::SpeedUp),
+    ("quick_save", Action::QuickSave),
+    ("quick_load", Action::QuickLoad),
+    ("toggle_palette", Action::TogglePalette),
+    ("toggle_inspector", Action::ToggleInspector),
+    ("toggle_chunk_overlay", Action::ToggleChunkOverlay),
+    ("cycle_render_mode", Action::CycleRenderMode),
+    ("toggle_lighting", Action::ToggleLighting),
+    ("toggle_effects", Action::ToggleEffects),
+    ("cycle_weather", Action::CycleWeather),
+    ("pan_left", Action::PanLeft),
+    ("pan_right", Action::PanRight),
+    ("pan_up", Action::PanUp),
+    ("pan_down", Action::PanDown),
+    ("zoom_in", Action::ZoomIn),
+    ("zoom_out", Action::ZoomOut),
+    ("bookmark_camera", Action::BookmarkCamera),
+    ("next_bookmark", Action::NextBookmark),
+    ("previous_bookmark", Action::PreviousBookmark),
+    ("toggle_tour", Action::ToggleTour),
+];
+
+const SELECT_MATERIAL_PREFIX: &str = "select_material_";
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::SelectMaterial(slot) => write!(f, "{}{}", SELECT_MATERIAL_PREFIX, slot),
+            action => {
+                let (name, _) = ACTION_NAMES.iter().find(|(_, named)| named == action).expect("every action is named");
+                f.write_str(name)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        if let Some(slot) = name.strip_prefix(SELECT_MATERIAL_PREFIX) {
+            return match slot.parse() {
+                Ok(slot) if slot > 0 => Ok(Action::SelectMaterial(slot)),
+                _ => Err(format!("'{}' needs a material slot counting from 1", name)),
+            };
+        }
+        ACTION_NAMES.iter()
+            .find(|(named, _)| *named == name)
+            .map(|&(_, action)| action)
+            .ok_or_else(|| format!("unknown action '{}'", name))
+    }
+}
+
+impl TryFrom<String> for Action {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        name.parse()
+    }
+}
+
+impl From<Action> for String {
+    fn from(action: Action) -> Self {
+        action.to_string()
+    }
+}
+
+/// Keys bound by default, by name as `KeyBindings::action` takes them
+const DEFAULT_KEYS: [(&str, Action); 36] = [
+    ("1", Action::SelectMaterial(1)),
+    ("2", Action::SelectMaterial(2)),
+    ("3", Action::SelectMaterial(3)),
+    ("4", Action::SelectMaterial(4)),
+    ("5", Action::SelectMaterial(5)),
+    ("6", Action::SelectMaterial(6)),
+    ("Equals", Action::BrushLarger),
+    ("Plus", Action::BrushLarger),
+    ("Minus", Action::BrushSmaller),
+    ("S", Action::CycleTool),
+    ("C", Action::Clear),
+    ("Ctrl+Z", Action::Undo),
+    ("Ctrl+Y", Action::Redo),
+    ("P", Action::TogglePause),
+    ("Period", Action::StepTick),
+    ("LBracket", Action::SlowDown),
+    ("RBracket", Action::SpeedUp),
+    ("F5", Action::QuickSave),
+    ("F9", Action::QuickLoad),
+    ("Tab", Action::TogglePalette),
+    ("I", Action::ToggleInspector),
+    ("F3", Action::ToggleChunkOverlay),
+    ("F4", Action::CycleRenderMode),
+    ("L", Action::ToggleLighting),
+    ("E", Action::ToggleEffects),
+    ("W", Action::CycleWeather),
+    ("Left", Action::PanLeft),
+    ("Right", Action::PanRight),
+    ("Up", Action::PanUp),
+    ("Down", Action::PanDown),
+    ("Z", Action::ZoomIn),
+    ("X", Action::ZoomOut),
+    ("B", Action::BookmarkCamera),
+    ("PageDown", Action::NextBookmark),
+    ("PageUp", Action::PreviousBookmark),
+    ("T", Action::ToggleTour),
+];
+
+/// Material slots bound to 1-6 by default
+const DEFAULT_MATERIALS: [&str; 6] = ["Sand", "Water", "Stone", "Fire", "Oil", "Eraser"];
+
+/// A keybindings file: every section is optional and only replaces what it names
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyBindingsFile {
+    materials: Option<Vec<String>>,
+    #[serde(default)]
+    keys: BTreeMap<String, Action>,
+}
+
+/// Which key does what in a frontend. Keys are named as winit names them ("P", "1",
+/// "F5", "Left", "PageUp", "LBracket"), ignoring case, with a "Ctrl+" prefix for
+/// chords; a key is bound to one action, an action to any number of keys.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    /// Materials the `SelectMaterial` slots pick, by registry name
+    pub materials: Vec<String>,
+    // Normalized key name to the name as written and its action
+    keys: HashMap<String, (String, Action)>,
+}
+
+impl KeyBindings {
+    /// The default bindings
+    pub fn new() -> Self {
+        let mut bindings = Self {
+            materials: DEFAULT_MATERIALS.iter().map(|name| name.to_string()).collect(),
+            keys: HashMap::new(),
+        };
+        for (key, action) in DEFAULT_KEYS {
+            bindings.bind(key, action);
+        }
+        bindings
+    }
+
+    /// The defaults with the bindings in a TOML file's source applied: its `materials`
+    /// list replaces the default slots, and each action its `[keys]` table binds loses
+    /// its default keys
+    pub fn from_toml_str(source: &str) -> Result<Self, KeyBindingsError> {
+        let file: KeyBindingsFile = toml::from_str(source)?;
+        let mut bindings = Self::new();
+        if let Some(materials) = file.materials {
+            bindings.materials = materials;
+        }
+        for action in file.keys.values() {
+            bindings.unbind_action(*action);
+        }
+        for (key, action) in file.keys {
+            bindings.bind(&key, action);
+        }
+        Ok(bindings)
+    }
+
+    /// The defaults with the bindings in the TOML file at `path` applied
+    #[cfg(feature = "files")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, KeyBindingsError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Bind `key` to `action`, replacing whatever it did before
+    pub fn bind(&mut self, key: &str, action: Action) {
+        self.keys.insert(normalize_key(key), (key.trim().to_string(), action));
+    }
+
+    pub fn unbind_key(&mut self, key: &str) {
+        self.keys.remove(&normalize_key(key));
+    }
+
+    pub fn unbind_action(&mut self, action: Action) {
+        self.keys.retain(|_, (_, bound)| *bound != action);
+    }
+
+    /// The action for a pressed key, trying the Ctrl chord first while Ctrl is held
+    pub fn action(&self, key: &str, ctrl: bool) -> Option<Action> {
+        let chord = ctrl.then(|| self.keys.get(&normalize_key(&format!("Ctrl+{}", key)))).flatten();
+        chord.or_else(|| self.keys.get(&normalize_key(key))).map(|&(_, action)| action)
+    }
+
+    /// Keys bound to `action`, as written, in alphabetical order
+    pub fn keys_for(&self, action: Action) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.keys.values().filter(|(_, bound)| *bound == action).map(|(key, _)| key.as_str()).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    /// Every bound action, in declaration order
+    pub fn actions(&self) -> Vec<Action> {
+        let mut actions: Vec<Action> = self.keys.values().map(|&(_, action)| action).collect();
+        actions.sort_unstable();
+        actions.dedup();
+        actions
+    }
+
+    /// The material in `slot`, counting from 1, if it names one `registry` knows
+    pub fn material(&self, slot: usize, registry: &MaterialRegistry) -> Option<MaterialType> {
+        self.materials.get(slot.checked_sub(1)?).and_then(|name| registry.lookup(name))
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn normalize_key(key: &str) -> String {
+    key.trim().to_ascii_lowercase().replace(' ', "").replace("control+", "ctrl+")
+}
+
+#[derive(Debug)]
+pub enum KeyBindingsError {
+    IoError(std::io::Error),
+    ParseError(toml::de::Error),
+}
+
+impl From<std::io::Error> for KeyBindingsError {
+    fn from(error: std::io::Error) -> Self {
+        KeyBindingsError::IoError(error)
+    }
+}
+
+impl From<toml::de::Error> for KeyBindingsError {
+    fn from(error: toml::de::Error) -> Self {
+        KeyBindingsError::ParseError(error)
+    }
+}
+
+impl std::fmt::Display for KeyBindingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyBindingsError::IoError(e) => write!(f, "IO error: {}", e),
+            KeyBindingsError::ParseError(e) => write!(f, "Keybindings parse error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for KeyBindingsError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_and_chords() {
+        let bindings = KeyBindings::new();
+        let registry = MaterialRegistry::new();
+        assert_eq!(bindings.action("p", false), Some(Action::TogglePause));
+        assert_eq!(bindings.action("Z", false), Some(Action::ZoomIn));
+        assert_eq!(bindings.action("Z", true), Some(Action::Undo));
+        // Ctrl with a key that has no chord does what the key alone does
+        assert_eq!(bindings.action("F5", true), Some(Action::QuickSave));
+        assert_eq!(bindings.action("Q", false), None);
+
+        assert_eq!(bindings.action("6", false), Some(Action::SelectMaterial(6)));
+        assert_eq!(bindings.material(6, &registry), Some(MaterialType::Eraser));
+        assert_eq!(bindings.material(7, &registry), None);
+        assert_eq!(bindings.keys_for(Action::BrushLarger), vec!["Equals", "Plus"]);
+        assert_eq!(bindings.actions()[0], Action::SelectMaterial(1));
+    }
+
+    #[test]
+    fn test_file_replaces_the_bindings_it_names() {
+        let source = r#"
+            materials = ["Lava", "salt water"]
+
+            [keys]
+            Space = "toggle_pause"
+            "Ctrl+S" = "quick_save"
+            Q = "select_material_2"
+        "#;
+        let bindings = KeyBindings::from_toml_str(source).unwrap();
+        let registry = MaterialRegistry::new();
+        assert_eq!(bindings.action("space", false), Some(Action::TogglePause));
+        assert_eq!(bindings.action("P", false), None);
+        assert_eq!(bindings.action("S", true), Some(Action::QuickSave));
+        assert_eq!(bindings.action("S", false), Some(Action::CycleTool));
+        assert_eq!(bindings.keys_for(Action::SelectMaterial(2)), vec!["Q"]);
+        assert_eq!(bindings.material(2, &registry), Some(MaterialType::SaltWater));
+        assert_eq!(bindings.action("Left", false), Some(Action::PanLeft));
+
+        assert!(matches!(KeyBindings::from_toml_str("[keys]\nP = \"jump\""), Err(KeyBindingsError::ParseError(_))));
+        assert!(KeyBindings::from_toml_str("[keys]\nP = \"select_material_0\"").is_err());
+        assert_eq!("select_material_3".parse(), Ok(Action::SelectMaterial(3)));
+        assert_eq!(Action::ZoomOut.to_string(), "zoom_out");
+    }
+}
@@ -1,29 +1,169 @@
 pub mod particle;
 pub mod simulation;
 pub mod materials;
+pub mod material_registry;
 pub mod physics;
+pub mod radiation;
+pub mod radioactivity;
+pub mod quality;
+pub mod budget;
+pub mod gas;
+pub mod liquid;
+pub mod reactions;
+pub mod interactions;
+pub mod bounds;
+pub mod generators;
+pub mod circuits;
+pub mod actuators;
+pub mod recipes;
+pub mod plants;
+pub mod compaction;
 pub mod engine;
 pub mod engine_v2;
 pub mod chunk;
 pub mod rigidbody;
 pub mod spatial;
 pub mod ecs;
+pub mod colony;
+pub mod critters;
 pub mod tile_entity;
 pub mod world_generation;
 pub mod save_load;
+#[cfg(all(feature = "files", feature = "threads"))]
+pub mod autosave;
+#[cfg(feature = "files")]
+pub mod chunk_saver;
 pub mod structures;
+pub mod clipboard;
+pub mod render;
+pub mod textures;
+pub mod palette;
+pub mod inspector;
+pub mod keybindings;
+pub mod world;
+pub mod world_rules;
+pub mod events;
+pub mod emitters;
+pub mod protocol;
+#[cfg(feature = "files")]
+pub mod streaming;
+pub mod digging;
+pub mod explosion;
+pub mod damage;
+pub mod effects;
+pub mod event_bus;
+pub mod interpolation;
+pub mod time_control;
+pub mod lighting;
+pub mod bookmarks;
+pub mod telemetry;
+pub mod measure;
+pub mod oracle;
+pub mod query;
+pub mod undo;
+pub mod editor;
+pub mod challenges;
+pub mod scenario;
+#[cfg(feature = "lua")]
+pub mod scripting;
+pub mod image_io;
+pub mod brush;
+pub mod wind;
+pub mod oxygen;
+pub mod weather;
+pub mod day_cycle;
+pub mod biomes;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+#[cfg(feature = "audio")]
+pub mod audio;
+#[cfg(feature = "export")]
+pub mod timelapse;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+#[allow(clippy::useless_conversion)] // PyO3 0.22's #[pymethods] expansion trips this on every PyResult
+pub mod python;
 
 pub use particle::Particle;
-pub use simulation::Simulation;
-pub use materials::{Material, MaterialType};
+pub use simulation::{GridRect, RngState, Simulation, UpdateState};
+pub use materials::{Material, MaterialKind, MaterialType, ToolTier};
+pub use material_registry::{MaterialDefinition, MaterialIdTable, MaterialPack, MaterialRegistry, MaterialRegistryError};
 pub use physics::PhysicsState;
-pub use engine::{PhysicsEngine, PhysicsStats};
-pub use engine_v2::{AdvancedPhysicsEngine, AdvancedPhysicsStats};
+pub use reactions::{Reaction, ReactionOutcome, ReactionRegistry, StageEffect, StagedReaction};
+pub use interactions::{Interaction, InteractionMatrix};
+pub use bounds::WorldBounds;
+pub use generators::{GeneratorConfig, DEFAULT_GENERATOR_RATE};
+pub use circuits::{LogicGate, SensorCondition, SensorWorld};
+pub use actuators::ActuatorWorld;
+pub use recipes::{Recipe, RecipeDefinition, RecipeFile, RecipeRegistry};
+pub use plants::{PlantFile, PlantRegistry, PlantSpecies, PlantSpeciesDefinition};
+pub use compaction::{CompactionRule, CompactionRules};
+pub use engine::{InterpolatedParticle, PhysicsEngine, PhysicsStats};
+pub use engine_v2::{AdvancedPhysicsEngine, AdvancedPhysicsStats, PhaseTimings, ProfilePhase, Profiler};
 pub use chunk::{Chunk, ChunkManager, ChunkKey, CHUNK_SIZE};
-pub use rigidbody::{RigidBodyManager, RigidBodyData, RigidBodyAnalyzer};
+pub use rigidbody::{RigidBodyManager, RigidBodyData, RigidBodyAnalyzer, BodyJoint, HingeMotor, RigidBodiesSave};
 pub use spatial::{SpatialHashGrid, NeighborCache, CollisionDetector};
-pub use ecs::{ECS, EntityId, Position, Velocity, Health, Player};
-pub use tile_entity::{TileEntity, TileEntityManager, TileEntityType, TileEntityEffect};
+pub use ecs::{ECS, Ant, Collider, Critter, CritterKind, EntityId, Position, Velocity, Health, Player};
+pub use tile_entity::{FluidWorld, ThermalWorld, TileEffectReport, TileEntity, TileEntityManager, TileEntityType, TileEntityEffect};
 pub use world_generation::{WorldGenerator, BiomeType};
-pub use save_load::{SaveLoadManager, WorldSave, WorldMetadata, Difficulty, GameMode};
-pub use structures::{Structure, StructureParticle, StructureTileEntity};
\ No newline at end of file
+pub use explosion::{Explosion, ExplosionReport};
+pub use damage::DamageOutcome;
+pub use effects::{Effect, EffectKind, EffectLayer};
+pub use event_bus::{EventBus, SimulationEvent};
+pub use interpolation::{FixedTimestep, ParticleMove};
+pub use time_control::{TimeControl, TimeControlState};
+pub use digging::{DigOutcome, Digger};
+pub use lighting::{LightMap, LightSource};
+pub use bookmarks::{CameraBookmark, CameraBookmarks, CameraTour};
+pub use telemetry::Telemetry;
+pub use measure::{Cavity, Distance, RegionCount, VolumeTracker};
+pub use oracle::{Assertion, AssertionFailure, Oracle, OracleReport};
+pub use query::{RayCells, RaycastHit};
+pub use undo::UndoManager;
+pub use editor::Selection;
+pub use challenges::{AllowedTool, Challenge, ChallengeAttempt, ChallengeError, ChallengeProgress, ChallengeRecord, ChallengeStatus, SceneFill};
+pub use scenario::{FillDefinition, PaintDefinition, Scenario, ScenarioFile, ScenarioOutcome, ScenarioPaint};
+#[cfg(feature = "lua")]
+pub use scripting::{Script, ScriptError};
+pub use image_io::{ImageError, ImagePalette};
+pub use brush::Brush;
+pub use wind::WindField;
+pub use oxygen::AirField;
+pub use radioactivity::RadiationField;
+pub use quality::{AdaptiveQuality, QualityLevel};
+pub use weather::{WeatherKind, WeatherState};
+pub use day_cycle::WorldClock;
+pub use biomes::BiomeMap;
+#[cfg(feature = "audio")]
+pub use audio::{AudioError, AudioSink};
+#[cfg(feature = "export")]
+pub use timelapse::{ExportError, Timelapse, TimelapseFormat};
+#[cfg(feature = "files")]
+pub use streaming::{StreamingReport, WorldStreamer};
+pub use save_load::{SimulationSave, WorldSave, WorldMetadata, Difficulty, GameMode};
+#[cfg(feature = "files")]
+pub use save_load::SaveLoadManager;
+#[cfg(all(feature = "files", feature = "threads"))]
+pub use autosave::{Autosave, AutosaveConfig, JournalEntry, Recovery};
+#[cfg(feature = "files")]
+pub use chunk_saver::ChunkSnapshot;
+#[cfg(all(feature = "files", feature = "threads"))]
+pub use chunk_saver::{ChunkSaveError, ChunkSaveReport, ChunkSaver};
+pub use structures::{Structure, StructureCritter, StructureLibrary, StructureParticle, StructureTileEntity};
+pub use clipboard::{Blueprint, Clipboard, PasteMode};
+pub use render::{Camera, ChunkActivity, ChunkActivityMap, ChunkOverlay, FrameCache, MaterialRenderer, RenderMode, RigidBodyOverlay, TextRenderer};
+pub use textures::TextureAtlas;
+pub use palette::{MaterialPalette, PaletteCategory, PaletteHit};
+pub use inspector::{CellInfo, CellInspector};
+pub use keybindings::{Action, KeyBindings, KeyBindingsError};
+pub use world::World;
+pub use world_rules::{ReactionMultiplier, WorldRules};
+pub use emitters::{Edge, EdgeEmitter, EdgeEmitters};
+pub use events::{WorldEvent, WorldEventConfig, WorldEventKind, WorldEventScheduler};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmSimulation;
+#[cfg(feature = "ffi")]
+pub use ffi::{SandCell, SandEngine, SandEngineStatus};
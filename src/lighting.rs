@@ -0,0 +1,234 @@
+use crate::tile_entity::{TileEntityData, TileEntityManager};
+use std::collections::VecDeque;
+
+/// Light lost per cell travelled, so a full-strength source reaches about 16 cells
+pub const LIGHT_FALLOFF: f32 = 1.0 / 16.0;
+/// Light level every cell gets regardless of sources
+pub const DEFAULT_AMBIENT_LIGHT: f32 = 0.2;
+/// Changes smaller than this stop propagating
+const LIGHT_EPSILON: f32 = 1e-3;
+
+/// A point light not tied to a material, e.g. a torch tile entity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSource {
+    pub x: usize,
+    pub y: usize,
+    /// Light level at the source, 0..1
+    pub intensity: f32,
+}
+
+impl LightSource {
+    pub fn new(x: usize, y: usize, intensity: f32) -> Self {
+        Self { x, y, intensity }
+    }
+
+    /// One source per lit torch; a torch's light radius sets how bright it is
+    pub fn from_tile_entities(tile_entities: &TileEntityManager) -> Vec<LightSource> {
+        tile_entities.get_tile_entities()
+            .filter(|tile_entity| tile_entity.is_active())
+            .filter_map(|tile_entity| match tile_entity.data {
+                TileEntityData::Torch { fuel_remaining, light_radius, .. } if fuel_remaining > 0.0 => {
+                    let (x, y) = tile_entity.get_position();
+                    let intensity = (light_radius as f32 * LIGHT_FALLOFF).min(1.0);
+                    (x >= 0 && y >= 0).then(|| LightSource::new(x as usize, y as usize, intensity))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Per-cell light levels in 0..1 for a grid, computed from emissive cells and point
+/// sources. Light loses `LIGHT_FALLOFF` per cell and is scaled by the transmission of
+/// every cell it passes through, so translucent materials dim it and opaque ones stop
+/// it; the faces of opaque cells are still lit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightMap {
+    pub width: usize,
+    pub height: usize,
+    /// Floor applied by `get` and `shade`
+    pub ambient: f32,
+    levels: Vec<f32>,
+    // Emission and transmission of every cell and the point lights as of the last
+    // `compute`, so `refresh` can tell whether anything affecting the light changed
+    cells: Vec<(f32, f32)>,
+    sources: Vec<LightSource>,
+}
+
+impl LightMap {
+    pub fn new(width: usize, height: usize, ambient: f32) -> Self {
+        Self {
+            width,
+            height,
+            ambient,
+            levels: vec![0.0; width * height],
+            cells: vec![(0.0, 1.0); width * height],
+            sources: Vec::new(),
+        }
+    }
+
+    /// Light reaching a cell from sources alone, without ambient
+    pub fn level(&self, x: usize, y: usize) -> f32 {
+        if x >= self.width || y >= self.height {
+            return 0.0;
+        }
+        self.levels[y * self.width + x]
+    }
+
+    /// Light at a cell including ambient
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.level(x, y).max(self.ambient).min(1.0)
+    }
+
+    /// Scale a color by the light at its cell
+    pub fn shade(&self, x: usize, y: usize, color: [u8; 3]) -> [u8; 3] {
+        let light = self.get(x, y);
+        color.map(|channel| (channel as f32 * light).round() as u8)
+    }
+
+    /// Recompute every cell. `cell_light` returns the emission and transmission of a
+    /// cell; empty cells should transmit fully.
+    pub fn compute(&mut self, sources: &[LightSource], mut cell_light: impl FnMut(usize, usize) -> (f32, f32)) {
+        self.levels.iter_mut().for_each(|level| *level = 0.0);
+        self.sources = sources.to_vec();
+        let mut queue = VecDeque::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                self.cells[index] = cell_light(x, y);
+                let emission = self.cells[index].0;
+                if emission > self.levels[index] {
+                    self.levels[index] = emission.min(1.0);
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        for source in sources {
+            if source.x < self.width && source.y < self.height {
+                let index = source.y * self.width + source.x;
+                if source.intensity > self.levels[index] {
+                    self.levels[index] = source.intensity.min(1.0);
+                    queue.push_back((source.x, source.y));
+                }
+            }
+        }
+
+        // Relax outwards until no cell can be brightened any further
+        while let Some((x, y)) = queue.pop_front() {
+            let index = y * self.width + x;
+            // Emitters shine from their own cell; anything else passes on what gets through it
+            let (emission, transmission) = self.cells[index];
+            let outgoing = self.levels[index] * if emission > 0.0 { 1.0 } else { transmission.clamp(0.0, 1.0) };
+            let arriving = outgoing - LIGHT_FALLOFF;
+            if arriving <= LIGHT_EPSILON {
+                continue;
+            }
+            for (dx, dy) in [(-1i32, 0i32), (1, 0), (0, -1), (0, 1)] {
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if nx < 0 || ny < 0 || nx as usize >= self.width || ny as usize >= self.height {
+                    continue;
+                }
+                let neighbor = ny as usize * self.width + nx as usize;
+                if arriving > self.levels[neighbor] + LIGHT_EPSILON {
+                    self.levels[neighbor] = arriving;
+                    queue.push_back((nx as usize, ny as usize));
+                }
+            }
+        }
+    }
+
+    /// Recompute only if `sources` differ from the last `compute` or a `changed` cell now
+    /// emits or lets through a different amount of light; returns whether it recomputed
+    pub fn refresh(
+        &mut self,
+        sources: &[LightSource],
+        changed: impl IntoIterator<Item = (usize, usize)>,
+        mut cell_light: impl FnMut(usize, usize) -> (f32, f32),
+    ) -> bool {
+        let stale = sources != self.sources.as_slice()
+            || changed.into_iter().any(|(x, y)| {
+                x < self.width && y < self.height && cell_light(x, y) != self.cells[y * self.width + x]
+            });
+        if stale {
+            self.compute(sources, cell_light);
+        }
+        stale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+    use crate::simulation::Simulation;
+    use crate::tile_entity::TileEntity;
+
+    #[test]
+    fn test_falloff_and_opaque_walls() {
+        let mut light_map = LightMap::new(20, 1, 0.0);
+        // Opaque wall at x = 14
+        light_map.compute(&[LightSource::new(10, 0, 1.0)], |x, _| (0.0, if x == 14 { 0.0 } else { 1.0 }));
+
+        assert_eq!(light_map.level(10, 0), 1.0);
+        assert!((light_map.level(12, 0) - (1.0 - 2.0 * LIGHT_FALLOFF)).abs() < 1e-5);
+        assert_eq!(light_map.level(7, 0), light_map.level(13, 0));
+        // The wall's face is lit but nothing gets past it
+        assert!(light_map.level(14, 0) > 0.0);
+        assert_eq!(light_map.level(15, 0), 0.0);
+    }
+
+    #[test]
+    fn test_simulation_light_through_water() {
+        let mut simulation = Simulation::new(30, 3);
+        // Lava flows, so fix the seed to keep it in its cell for the one update
+        simulation.set_seed(1);
+        simulation.enable_lighting(0.0);
+        for x in 0..30 {
+            simulation.add_particle(x, 2, MaterialType::Stone, None);
+        }
+        simulation.add_particle(15, 1, MaterialType::Lava, None);
+        for x in 17..20 {
+            simulation.add_particle(x, 1, MaterialType::Water, None);
+        }
+
+        simulation.update(1.0 / 60.0);
+
+        let light_map = simulation.light_map().unwrap();
+        assert!(light_map.level(15, 1) > 0.5);
+        // Light passing through the pool is dimmer than light crossing the same gap in air
+        assert!(light_map.level(20, 1) < light_map.level(10, 1));
+        assert!(light_map.level(10, 1) > 0.0);
+    }
+
+    #[test]
+    fn test_refresh_skips_unchanged_light() {
+        let mut light_map = LightMap::new(20, 1, 0.0);
+        let sources = [LightSource::new(10, 0, 1.0)];
+        light_map.compute(&sources, |x, _| (0.0, if x == 14 { 0.0 } else { 1.0 }));
+        let before = light_map.clone();
+
+        // A changed cell that still lets all the light through leaves the map alone
+        assert!(!light_map.refresh(&sources, [(3, 0)], |x, _| (0.0, if x == 14 { 0.0 } else { 1.0 })));
+        assert_eq!(light_map, before);
+        // Knocking the wall out lets the light through
+        assert!(light_map.refresh(&sources, [(14, 0)], |_, _| (0.0, 1.0)));
+        assert!(light_map.level(15, 0) > 0.0);
+        assert!(light_map.refresh(&[], [], |_, _| (0.0, 1.0)));
+        assert_eq!(light_map.level(10, 0), 0.0);
+    }
+
+    #[test]
+    fn test_torch_sources() {
+        let mut tile_entities = TileEntityManager::new();
+        tile_entities.add_tile_entity(TileEntity::new_torch((4, 5)));
+        let sources = LightSource::from_tile_entities(&tile_entities);
+        assert_eq!(sources.len(), 1);
+        assert_eq!((sources[0].x, sources[0].y), (4, 5));
+
+        let mut light_map = LightMap::new(10, 10, DEFAULT_AMBIENT_LIGHT);
+        light_map.compute(&sources, |_, _| (0.0, 1.0));
+        assert!(light_map.get(4, 5) > light_map.get(9, 9));
+        assert_eq!(light_map.shade(0, 0, [0, 0, 0]), [0, 0, 0]);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::interactions::Interaction;
+use crate::materials::{Material, MaterialType};
+use crate::particle::Particle;
+use crate::reactions::NEIGHBOR_OFFSETS;
+use crate::simulation::Simulation;
+use rand::Rng;
+
+/// Cells a liquid of viscosity 1 (Water) can flow sideways in one tick; thicker
+/// liquids flow `MAX_FLOW_CELLS / viscosity`, but always at least one
+const MAX_FLOW_CELLS: f32 = 4.0;
+/// Viscosity at which a liquid holds together as firmly as surface tension allows
+const FULL_TENSION_VISCOSITY: f32 = 10.0;
+
+impl Simulation {
+    /// Cells a liquid may spread sideways in one tick: 4 for Water down to 1 for Lava and Slime
+    pub fn liquid_flow_cells(&self, material_type: MaterialType) -> usize {
+        let viscosity = self.materials().get(material_type).viscosity.max(1.0);
+        (MAX_FLOW_CELLS / viscosity).ceil() as usize
+    }
+
+    /// 0 for runny liquids up to 1 for Slime: the chance a sideways move that would
+    /// pull a particle away from the rest of its blob is refused
+    fn surface_tension(props: &Material) -> f32 {
+        (props.viscosity / FULL_TENSION_VISCOSITY).clamp(0.0, 1.0)
+    }
+
+    /// Neighbours of `(x, y)` holding `material_type`, not counting the cell `ignore`
+    fn cohesion(&self, x: usize, y: usize, material_type: MaterialType, ignore: (usize, usize)) -> usize {
+        NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| self.offset_cell(x, y, dx, dy))
+            .filter(|&cell| cell != ignore)
+            .filter(|&(nx, ny)| self.get_particle(nx, ny).is_some_and(|p| p.material_type == material_type))
+            .count()
+    }
+
+    pub(crate) fn is_open(&self, x: usize, y: usize) -> bool {
+        self.get_particle(x, y).is_none_or(|particle| particle.material_type == MaterialType::Empty)
+    }
+
+    /// Whether a liquid of `material_type` may trade places with what fills `(x, y)` on a
+    /// diagonal or sideways move
+    fn swaps_with(&self, x: usize, y: usize, material_type: MaterialType) -> bool {
+        self.get_particle(x, y).is_some_and(|target| self.interaction(material_type, target.material_type) == Interaction::Swap)
+    }
+
+    /// Surface tension check for a sideways or diagonal move from `(x, y)` to `target`:
+    /// moves that leave a thick liquid with fewer of its own kind around are often refused,
+    /// so Slime and Lava hold together in blobs while Water runs flat
+    fn holds_together(&mut self, particle: &Particle, props: &Material, target: (usize, usize)) -> bool {
+        let tension = Self::surface_tension(props);
+        if tension <= 0.0 {
+            return false;
+        }
+        let origin = (particle.x, particle.y);
+        let here = self.cohesion(origin.0, origin.1, particle.material_type, origin);
+        let there = self.cohesion(target.0, target.1, particle.material_type, origin);
+        there < here && self.rng.gen::<f32>() < tension
+    }
+
+    /// Where a liquid particle moves this tick: straight down, down through lighter
+    /// fluids by swapping with them, diagonally down, or sideways by up to its flow
+    /// distance. Sideways moves are damped by viscosity and resisted by surface tension,
+    /// except at `QualityLevel::Minimal`, where every liquid spreads as fast as Water.
+    pub(crate) fn move_liquid(&mut self, particle: &Particle, props: &Material) -> (usize, usize) {
+        let (x, y) = (particle.x, particle.y);
+        let simplified = self.quality_level().simplifies_liquids();
+
+        let below = self.offset_cell(x, y, 0, 1);
+        if let Some((_, below_y)) = below {
+            if self.is_open(x, below_y) {
+                return (x, below_y);
+            }
+            // Immiscible layering: sink through a run of lighter fluid, shifting it up
+            if let Some(target) = self.sink_through(particle, 1) {
+                return target;
+            }
+        }
+
+        let directions = if self.rng.gen::<bool>() { [-1, 1] } else { [1, -1] };
+        if below.is_some() {
+            for &dx in &directions {
+                let Some(target) = self.offset_cell(x, y, dx, 1) else {
+                    continue;
+                };
+                // A filled cell it swaps with trades places once the update loop sets it down
+                let open = self.is_open(target.0, target.1);
+                if (open || self.swaps_with(target.0, target.1, particle.material_type))
+                    && (simplified || !self.holds_together(particle, props, target))
+                {
+                    return target;
+                }
+            }
+        }
+
+        let move_chance = (1.0 - props.viscosity * 0.1).max(0.1);
+        if !simplified && self.rng.gen::<f32>() >= move_chance {
+            return (x, y);
+        }
+        let flow = match simplified {
+            true => MAX_FLOW_CELLS as usize,
+            false => self.liquid_flow_cells(particle.material_type),
+        };
+        for &dx in &directions {
+            let mut reached = None;
+            for step in 1..=flow as i32 {
+                let Some((nx, ny)) = self.offset_cell(x, y, dx * step, 0) else {
+                    break;
+                };
+                if !self.is_open(nx, ny) {
+                    // Only the cell alongside can be traded into
+                    if step == 1 && self.swaps_with(nx, ny, particle.material_type) {
+                        reached = Some((nx, ny));
+                    }
+                    break;
+                }
+                reached = Some((nx, ny));
+                // Stop at a drop so the liquid falls over the edge next tick
+                if self.offset_cell(nx, ny, 0, 1).is_some_and(|(bx, by)| self.is_open(bx, by)) {
+                    break;
+                }
+            }
+            if let Some(target) = reached {
+                if simplified || !self.holds_together(particle, props, target) {
+                    return target;
+                }
+            }
+        }
+        (x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(simulation: &mut Simulation, ticks: usize) {
+        for _ in 0..ticks {
+            simulation.update(1.0 / 60.0);
+        }
+    }
+
+    fn cells(simulation: &Simulation, material: MaterialType) -> Vec<(usize, usize)> {
+        simulation.iter_particles()
+            .filter(|particle| particle.material_type == material)
+            .map(|particle| (particle.x, particle.y))
+            .collect()
+    }
+
+    #[test]
+    fn test_oil_floats_on_water() {
+        let mut simulation = Simulation::new(12, 20);
+        simulation.set_seed(2);
+        // Oil poured first ends up underneath the water
+        for y in 14..20 {
+            for x in 0..12 {
+                let material = if y < 17 { MaterialType::Water } else { MaterialType::Oil };
+                simulation.add_particle(x, y, material, None);
+            }
+        }
+        run(&mut simulation, 300);
+
+        let water = cells(&simulation, MaterialType::Water);
+        let oil = cells(&simulation, MaterialType::Oil);
+        assert_eq!((water.len(), oil.len()), (36, 36));
+        let mean = |cells: &[(usize, usize)]| cells.iter().map(|&(_, y)| y as f32).sum::<f32>() / cells.len() as f32;
+        assert!(mean(&oil) + 2.0 < mean(&water), "oil {} water {}", mean(&oil), mean(&water));
+    }
+
+    #[test]
+    fn test_viscous_liquids_spread_less() {
+        let spread = |material: MaterialType| {
+            let mut simulation = Simulation::new(60, 20);
+            simulation.set_seed(9);
+            for y in 10..16 {
+                for x in 27..33 {
+                    simulation.add_particle(x, y, material, None);
+                }
+            }
+            run(&mut simulation, 120);
+            let xs: Vec<usize> = cells(&simulation, material).iter().map(|&(x, _)| x).collect();
+            xs.iter().max().unwrap() - xs.iter().min().unwrap()
+        };
+
+        assert!(Simulation::new(1, 1).liquid_flow_cells(MaterialType::Water) > Simulation::new(1, 1).liquid_flow_cells(MaterialType::Oil));
+        let (water, slime) = (spread(MaterialType::Water), spread(MaterialType::Slime));
+        assert!(water > slime * 2, "water {} slime {}", water, slime);
+    }
+}
@@ -0,0 +1,541 @@
+use crate::materials::{get_material_properties, Material, MaterialKind, MaterialType};
+use crate::particle::Particle;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "files")]
+use std::fs;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// One `[[material]]` entry of a material pack.
+///
+/// Every field except `name` is optional: an entry whose name matches an existing material
+/// overrides only the fields it sets, while a new name registers a custom material on top
+/// of the defaults for its `kind`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MaterialDefinition {
+    pub name: String,
+    pub kind: Option<MaterialKind>,
+    pub density: Option<f32>,
+    pub conductivity: Option<f32>,
+    pub flammability: Option<f32>,
+    pub melt_temp: Option<f32>,
+    pub boil_temp: Option<f32>,
+    pub freeze_temp: Option<f32>,
+    pub ignition_temp: Option<f32>,
+    pub color: Option<[u8; 3]>,
+    pub viscosity: Option<f32>,
+    pub life_seconds: Option<f32>,
+    pub corrosive_power: Option<f32>,
+    pub explosive_yield: Option<f32>,
+    pub heat_generation: Option<f32>,
+    /// Resistance to digging; see `ToolTier::max_hardness`
+    pub hardness: Option<f32>,
+    /// Damage taken before breaking into rubble; see `Material::max_health`
+    pub max_health: Option<f32>,
+    /// Light given off and fraction let through; see `LightMap`
+    pub light_emission: Option<f32>,
+    pub light_transmission: Option<f32>,
+    /// Heat radiated to nearby cells while very hot; see `radiation.rs`
+    pub radiant_heat: Option<f32>,
+    /// Radiation given off, how long it takes to decay and what into; see `RadiationField`
+    pub radioactivity: Option<f32>,
+    pub half_life: Option<f32>,
+    pub decays_into: Option<String>,
+    /// Seconds a cell burns for and what it leaves behind; see `Material::fuel_value`
+    pub fuel_value: Option<f32>,
+    pub chars_into: Option<String>,
+    /// What the material compacts into when buried, under how much load and after how
+    /// many seconds; see `CompactionRules`
+    pub compacts_into: Option<String>,
+    pub compaction_load: Option<f32>,
+    pub compaction_seconds: Option<f32>,
+    /// What the material cracks into under rapid temperature swings, and the stress it
+    /// takes; see `Material::cracks_into`
+    pub cracks_into: Option<String>,
+    pub crack_stress: Option<f32>,
+    /// Heat taken in at the melting or boiling point and given off at the freezing or
+    /// condensation point before changing state; see `Material::heating_latent_heat`
+    pub heating_latent_heat: Option<f32>,
+    pub cooling_latent_heat: Option<f32>,
+    /// Names of the materials produced by each phase change
+    pub melts_into: Option<String>,
+    pub boils_into: Option<String>,
+    pub freezes_into: Option<String>,
+}
+
+/// A TOML file of material definitions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaterialPack {
+    #[serde(rename = "material", default)]
+    pub materials: Vec<MaterialDefinition>,
+}
+
+/// The name each stable material id stood for when a world was saved, so the ids read
+/// back right against a registry whose custom materials were registered in another order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaterialIdTable {
+    names: BTreeMap<u16, String>,
+}
+
+impl MaterialIdTable {
+    pub fn name(&self, id: u16) -> Option<&str> {
+        self.names.get(&id).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// Runtime table of material properties, seeded with the built-in materials and
+/// extendable from TOML material packs.
+///
+/// Each `Simulation` holds its own registry, so different worlds in one process can run
+/// different packs.
+#[derive(Debug, Clone)]
+pub struct MaterialRegistry {
+    materials: HashMap<MaterialType, Material>,
+    names: HashMap<String, MaterialType>,
+    next_custom_id: u16,
+    fallback: Material,
+}
+
+impl MaterialRegistry {
+    /// Registry containing only the built-in materials
+    pub fn new() -> Self {
+        let mut registry = Self {
+            materials: HashMap::new(),
+            names: HashMap::new(),
+            next_custom_id: 0,
+            fallback: get_material_properties(MaterialType::Custom(u16::MAX)),
+        };
+
+        for material_type in MaterialType::BUILTIN {
+            let material = get_material_properties(material_type);
+            registry.names.insert(normalize_name(&material.name), material_type);
+            registry.names.insert(normalize_name(&format!("{:?}", material_type)), material_type);
+            registry.materials.insert(material_type, material);
+        }
+
+        registry
+    }
+
+    /// Built-in materials plus everything defined in the given TOML source
+    pub fn from_toml_str(source: &str) -> Result<Self, MaterialRegistryError> {
+        let mut registry = Self::new();
+        registry.load_toml_str(source)?;
+        Ok(registry)
+    }
+
+    /// Built-in materials plus everything defined in the TOML file at `path`
+    #[cfg(feature = "files")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, MaterialRegistryError> {
+        let mut registry = Self::new();
+        registry.load_file(path)?;
+        Ok(registry)
+    }
+
+    /// Apply a TOML material pack file, returning the materials it touched
+    #[cfg(feature = "files")]
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<Vec<MaterialType>, MaterialRegistryError> {
+        let source = fs::read_to_string(path)?;
+        self.load_toml_str(&source)
+    }
+
+    /// Apply a TOML material pack, returning the materials it touched in file order.
+    /// The pack is applied atomically: on error the registry is left unchanged.
+    pub fn load_toml_str(&mut self, source: &str) -> Result<Vec<MaterialType>, MaterialRegistryError> {
+        let pack: MaterialPack = toml::from_str(source)?;
+        self.load_pack(&pack)
+    }
+
+    pub fn load_pack(&mut self, pack: &MaterialPack) -> Result<Vec<MaterialType>, MaterialRegistryError> {
+        let mut staged = self.clone();
+
+        // Register every entry first so phase changes can name materials later in the pack
+        let mut touched = Vec::with_capacity(pack.materials.len());
+        for definition in &pack.materials {
+            touched.push(staged.apply_properties(definition)?);
+        }
+        for (definition, &material_type) in pack.materials.iter().zip(&touched) {
+            staged.apply_transitions(material_type, definition)?;
+        }
+
+        *self = staged;
+        Ok(touched)
+    }
+
+    /// Register or override a single material
+    pub fn register(&mut self, definition: &MaterialDefinition) -> Result<MaterialType, MaterialRegistryError> {
+        let pack = MaterialPack {
+            materials: vec![definition.clone()],
+        };
+        Ok(self.load_pack(&pack)?[0])
+    }
+
+    /// Properties for a material; unknown custom ids resolve to an inert placeholder
+    pub fn get(&self, material_type: MaterialType) -> &Material {
+        self.materials.get(&material_type).unwrap_or(&self.fallback)
+    }
+
+    pub fn contains(&self, material_type: MaterialType) -> bool {
+        self.materials.contains_key(&material_type)
+    }
+
+    /// Look a material up by display or variant name, ignoring case, spaces and underscores
+    pub fn lookup(&self, name: &str) -> Option<MaterialType> {
+        self.names.get(&normalize_name(name)).copied()
+    }
+
+    /// Custom materials in registration order
+    pub fn custom_materials(&self) -> impl Iterator<Item = MaterialType> + '_ {
+        (0..self.next_custom_id)
+            .map(MaterialType::Custom)
+            .filter(|material_type| self.materials.contains_key(material_type))
+    }
+
+    /// Built-in then custom materials
+    pub fn material_types(&self) -> impl Iterator<Item = MaterialType> + '_ {
+        MaterialType::BUILTIN.into_iter().chain(self.custom_materials())
+    }
+
+    /// The registered material with stable id `id` (see `MaterialType::id`)
+    pub fn material_by_id(&self, id: u16) -> Option<MaterialType> {
+        MaterialType::from_id(id).filter(|material_type| self.contains(*material_type))
+    }
+
+    /// Every registered material's stable id with its name, to save alongside the ids
+    pub fn id_table(&self) -> MaterialIdTable {
+        let names = self.material_types()
+            .map(|material_type| (material_type.id(), self.get(material_type).name.clone()))
+            .collect();
+        MaterialIdTable { names }
+    }
+
+    /// The material in this registry that `id` meant when `table` was saved: looked up
+    /// by the name the table gives it, or by the id itself for built-ins, whose ids never
+    /// change, and for ids the table doesn't name. None for a custom material this
+    /// registry doesn't have.
+    pub fn resolve_id(&self, table: &MaterialIdTable, id: u16) -> Option<MaterialType> {
+        match table.name(id) {
+            Some(name) => self.lookup(name).or_else(|| self.material_by_id(id).filter(|material_type| !material_type.is_custom())),
+            None => self.material_by_id(id),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// Create a particle using this registry's properties for its initial state
+    pub fn create_particle(&self, x: usize, y: usize, material_type: MaterialType, temp: Option<f32>) -> Particle {
+        let mut particle = Particle::new(x, y, material_type, temp);
+        particle.life = self.get(material_type).life_seconds;
+        particle
+    }
+
+    fn apply_properties(&mut self, definition: &MaterialDefinition) -> Result<MaterialType, MaterialRegistryError> {
+        if definition.name.trim().is_empty() {
+            return Err(MaterialRegistryError::InvalidDefinition("material name is empty".to_string()));
+        }
+
+        let material_type = match self.lookup(&definition.name) {
+            Some(MaterialType::Empty) | Some(MaterialType::Eraser) => {
+                return Err(MaterialRegistryError::InvalidDefinition(format!(
+                    "'{}' is reserved and cannot be overridden",
+                    definition.name
+                )));
+            }
+            Some(existing) => existing,
+            None => {
+                let kind = definition.kind.ok_or_else(|| {
+                    MaterialRegistryError::InvalidDefinition(format!("new material '{}' needs a kind", definition.name))
+                })?;
+                if self.next_custom_id == u16::MAX {
+                    return Err(MaterialRegistryError::InvalidDefinition("too many custom materials".to_string()));
+                }
+
+                let material_type = MaterialType::Custom(self.next_custom_id);
+                self.next_custom_id += 1;
+                self.names.insert(normalize_name(&definition.name), material_type);
+                self.materials.insert(material_type, default_for_kind(&definition.name, kind));
+                material_type
+            }
+        };
+
+        let material = self.materials.get_mut(&material_type).expect("material registered above");
+        if let Some(kind) = definition.kind {
+            material.kind = kind;
+        }
+        set(&mut material.density, definition.density);
+        set(&mut material.conductivity, definition.conductivity);
+        set(&mut material.flammability, definition.flammability);
+        set(&mut material.base_color, definition.color);
+        set(&mut material.viscosity, definition.viscosity);
+        set(&mut material.corrosive_power, definition.corrosive_power);
+        set(&mut material.heat_generation, definition.heat_generation);
+        set(&mut material.hardness, definition.hardness);
+        set(&mut material.max_health, definition.max_health);
+        set(&mut material.light_emission, definition.light_emission);
+        set(&mut material.light_transmission, definition.light_transmission);
+        set(&mut material.radiant_heat, definition.radiant_heat);
+        set(&mut material.radioactivity, definition.radioactivity);
+        set(&mut material.half_life, definition.half_life);
+        set(&mut material.fuel_value, definition.fuel_value);
+        set(&mut material.compaction_load, definition.compaction_load);
+        set(&mut material.compaction_seconds, definition.compaction_seconds);
+        set(&mut material.crack_stress, definition.crack_stress);
+        set(&mut material.heating_latent_heat, definition.heating_latent_heat);
+        set(&mut material.cooling_latent_heat, definition.cooling_latent_heat);
+        set_some(&mut material.melt_temp, definition.melt_temp);
+        set_some(&mut material.boil_temp, definition.boil_temp);
+        set_some(&mut material.freeze_temp, definition.freeze_temp);
+        set_some(&mut material.ignition_temp, definition.ignition_temp);
+        set_some(&mut material.life_seconds, definition.life_seconds);
+        set_some(&mut material.explosive_yield, definition.explosive_yield);
+
+        Ok(material_type)
+    }
+
+    fn apply_transitions(&mut self, material_type: MaterialType, definition: &MaterialDefinition) -> Result<(), MaterialRegistryError> {
+        let resolve = |name: &Option<String>| -> Result<Option<MaterialType>, MaterialRegistryError> {
+            match name {
+                Some(name) => self
+                    .lookup(name)
+                    .map(Some)
+                    .ok_or_else(|| MaterialRegistryError::UnknownMaterial(name.clone())),
+                None => Ok(None),
+            }
+        };
+        let melts_into = resolve(&definition.melts_into)?;
+        let boils_into = resolve(&definition.boils_into)?;
+        let freezes_into = resolve(&definition.freezes_into)?;
+        let chars_into = resolve(&definition.chars_into)?;
+        let compacts_into = resolve(&definition.compacts_into)?;
+        let cracks_into = resolve(&definition.cracks_into)?;
+        let decays_into = resolve(&definition.decays_into)?;
+
+        let material = self.materials.get_mut(&material_type).expect("material registered before transitions");
+        set_some(&mut material.melts_into, melts_into);
+        set_some(&mut material.boils_into, boils_into);
+        set_some(&mut material.freezes_into, freezes_into);
+        set_some(&mut material.chars_into, chars_into);
+        set_some(&mut material.compacts_into, compacts_into);
+        set_some(&mut material.cracks_into, cracks_into);
+        set_some(&mut material.decays_into, decays_into);
+        Ok(())
+    }
+}
+
+impl Default for MaterialRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn set<T>(field: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *field = value;
+    }
+}
+
+fn set_some<T>(field: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *field = value;
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Starting properties for a new custom material, before its definition is applied
+fn default_for_kind(name: &str, kind: MaterialKind) -> Material {
+    let (density, conductivity, viscosity, hardness, light_transmission) = match kind {
+        MaterialKind::Powder => (1.5, 0.3, 1.0, 0.3, 0.0),
+        MaterialKind::Liquid => (1.0, 0.5, 1.0, 0.0, 0.6),
+        MaterialKind::Gas => (-1.0, 0.1, 1.0, 0.0, 0.8),
+        MaterialKind::Solid => (2.5, 0.3, 1.0, 2.0, 0.0),
+        MaterialKind::Static => (100.0, 0.3, 1.0, 2.0, 0.0),
+    };
+    let mut material = Material::new(
+        density, conductivity, 0.0, None, None, None, [200, 200, 200], name, viscosity, None, 0.0, None, 0.0, None
+    );
+    material.kind = kind;
+    material.hardness = hardness;
+    material.light_transmission = light_transmission;
+    material
+}
+
+#[derive(Debug)]
+pub enum MaterialRegistryError {
+    IoError(std::io::Error),
+    ParseError(toml::de::Error),
+    UnknownMaterial(String),
+    InvalidDefinition(String),
+}
+
+impl From<std::io::Error> for MaterialRegistryError {
+    fn from(error: std::io::Error) -> Self {
+        MaterialRegistryError::IoError(error)
+    }
+}
+
+impl From<toml::de::Error> for MaterialRegistryError {
+    fn from(error: toml::de::Error) -> Self {
+        MaterialRegistryError::ParseError(error)
+    }
+}
+
+impl std::fmt::Display for MaterialRegistryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaterialRegistryError::IoError(e) => write!(f, "IO error: {}", e),
+            MaterialRegistryError::ParseError(e) => write!(f, "Material pack parse error: {}", e),
+            MaterialRegistryError::UnknownMaterial(name) => write!(f, "Unknown material '{}'", name),
+            MaterialRegistryError::InvalidDefinition(msg) => write!(f, "Invalid material definition: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for MaterialRegistryError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = r#"
+        [[material]]
+        name = "Rock Salt"
+        kind = "powder"
+        density = 2.2
+        color = [240, 240, 235]
+        melt_temp = 801.0
+        melts_into = "Brine"
+
+        [[material]]
+        name = "Brine"
+        kind = "liquid"
+        density = 1.2
+
+        [[material]]
+        name = "toxic gas"
+        life_seconds = 2.0
+
+        [[material]]
+        name = "Glass"
+        cracks_into = "Rock Salt"
+        crack_stress = 50.0
+    "#;
+
+    #[test]
+    fn test_builtins_match_static_table() {
+        let registry = MaterialRegistry::new();
+        assert_eq!(registry.len(), MaterialType::BUILTIN.len());
+        assert_eq!(registry.get(MaterialType::Sand).density, get_material_properties(MaterialType::Sand).density);
+        assert_eq!(registry.lookup("ToxicGas"), Some(MaterialType::ToxicGas));
+        assert_eq!(registry.lookup("toxic gas"), Some(MaterialType::ToxicGas));
+    }
+
+    #[test]
+    fn test_load_custom_materials_and_overrides() {
+        let registry = MaterialRegistry::from_toml_str(PACK).unwrap();
+
+        let salt = registry.lookup("rock salt").unwrap();
+        let brine = registry.lookup("Brine").unwrap();
+        assert!(salt.is_custom() && brine.is_custom());
+
+        let salt_props = registry.get(salt);
+        assert_eq!(salt_props.base_color, [240, 240, 235]);
+        assert_eq!(salt_props.melts_into, Some(brine));
+        assert!(salt_props.is_powder(salt));
+        assert!(registry.get(brine).is_liquid(brine));
+
+        // Overrides keep the fields they don't mention
+        let gas = registry.get(MaterialType::ToxicGas);
+        assert_eq!(gas.life_seconds, Some(2.0));
+        assert_eq!(gas.density, -4.0);
+        let glass = registry.get(MaterialType::Glass);
+        assert_eq!((glass.cracks_into, glass.crack_stress), (Some(salt), 50.0));
+        assert_eq!(registry.custom_materials().count(), 2);
+
+        // The pack shipped with the repo must stay loadable
+        MaterialRegistry::from_toml_str(include_str!("../materials/example_pack.toml")).unwrap();
+    }
+
+    #[test]
+    fn test_saved_ids_resolve_after_new_materials_are_added() {
+        let old = MaterialRegistry::from_toml_str(PACK).unwrap();
+        let table: MaterialIdTable = serde_json::from_str(&serde_json::to_string(&old.id_table()).unwrap()).unwrap();
+        let (salt, brine) = (old.lookup("Rock Salt").unwrap(), old.lookup("Brine").unwrap());
+        assert_eq!(table.name(salt.id()), Some("Rock Salt"));
+        // Built-in ids are fixed for good
+        assert_eq!((MaterialType::Sand.id(), MaterialType::Snow.id(), MaterialType::Eraser.id()), (1, 30, 99));
+        assert_eq!(MaterialType::from_id(salt.id()), Some(salt));
+        assert_eq!(MaterialType::from_id(50), None);
+
+        // A newer pack registers another material first, so every custom id moves
+        let mut new = MaterialRegistry::new();
+        new.register(&MaterialDefinition { name: "Clay".to_string(), kind: Some(MaterialKind::Powder), ..Default::default() }).unwrap();
+        new.load_toml_str(PACK).unwrap();
+        assert_ne!(new.lookup("Rock Salt"), Some(salt));
+        assert_eq!(new.resolve_id(&table, salt.id()), new.lookup("Rock Salt"));
+        assert_eq!(new.resolve_id(&table, brine.id()), new.lookup("Brine"));
+        assert_eq!(new.resolve_id(&table, MaterialType::Sand.id()), Some(MaterialType::Sand));
+
+        // Without the pack the custom materials are gone; without a table ids are taken as they are
+        let bare = MaterialRegistry::new();
+        assert_eq!(bare.resolve_id(&table, salt.id()), None);
+        assert_eq!(bare.resolve_id(&table, MaterialType::Lava.id()), Some(MaterialType::Lava));
+        assert_eq!(new.resolve_id(&MaterialIdTable::default(), salt.id()), new.lookup("Clay"));
+    }
+
+    #[test]
+    fn test_invalid_pack_leaves_registry_unchanged() {
+        let mut registry = MaterialRegistry::new();
+
+        let missing_kind = "[[material]]\nname = \"Mystery\"\n";
+        assert!(matches!(registry.load_toml_str(missing_kind), Err(MaterialRegistryError::InvalidDefinition(_))));
+
+        let bad_reference = "[[material]]\nname = \"Foam\"\nkind = \"gas\"\nboils_into = \"Nothing\"\n";
+        assert!(matches!(registry.load_toml_str(bad_reference), Err(MaterialRegistryError::UnknownMaterial(_))));
+
+        assert!(registry.lookup("Foam").is_none());
+        assert_eq!(registry.len(), MaterialType::BUILTIN.len());
+    }
+
+    #[test]
+    fn test_simulation_uses_custom_materials() {
+        use crate::simulation::Simulation;
+        use std::sync::Arc;
+
+        let registry = MaterialRegistry::from_toml_str(PACK).unwrap();
+        let salt = registry.lookup("Rock Salt").unwrap();
+        let brine = registry.lookup("Brine").unwrap();
+        let mut simulation = Simulation::with_materials(10, 10, Arc::new(registry));
+
+        simulation.add_particle(5, 0, salt, None);
+        for _ in 0..20 {
+            simulation.update(1.0 / 60.0);
+        }
+        assert!((0..10).any(|x| simulation.get_particle(x, 9).map(|p| p.material_type) == Some(salt)));
+
+        // Hot enough to melt into the product named in the pack
+        simulation.add_particle(2, 2, salt, Some(1200.0));
+        simulation.update(1.0 / 60.0);
+        assert_eq!(simulation.get_particle(2, 2).map(|p| p.material_type), Some(brine));
+    }
+}
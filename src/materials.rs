@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u16)]
 pub enum MaterialType {
     Empty = 0,
     Sand = 1,
@@ -26,9 +27,206 @@ pub enum MaterialType {
     Ash = 21,
     Gold = 22,
     Iron = 23,
+    Sandstone = 24,
+    MoltenIron = 25,
+    MoltenGold = 26,
+    Seed = 27,
+    Stem = 28,
+    Root = 29,
+    Snow = 30,
+    /// Panel of a door tile entity: never moves, and opens and shuts when the door does
+    Door = 31,
+    /// Arm of a piston tile entity: never moves on its own, and pushes what it extends into
+    Piston = 32,
+    Dirt = 33,
+    /// Sand soaked by water beside it: clumps instead of sliding and dries out when hot
+    WetSand = 34,
+    /// Dirt soaked by water beside it: a thick, slow liquid that dries out when hot
+    Mud = 35,
+    /// Dissolves into water it touches, turning it into salt water
+    Salt = 36,
+    /// Water carrying dissolved salt: sinks below fresh water and leaves salt behind when
+    /// it boils away
+    SaltWater = 37,
+    /// Launches once lit and bursts into sparks the color it was given when lit
+    Firework = 38,
+    /// Short-lived, glowing fleck thrown out by a bursting firework; fades as it burns out
+    Spark = 39,
+    /// Burns in place far hotter than other fuels, melting through metal, and leaves
+    /// molten iron behind
+    Thermite = 40,
+    /// Radioactive metal that irradiates the cells around it and slowly decays into lead
+    Uranium = 41,
+    /// Heavy, soft metal left behind by decayed uranium
+    Lead = 42,
     Eraser = 99,
+    /// Material defined at runtime through a `MaterialRegistry`
+    Custom(u16) = 1000,
 }
 
+impl MaterialType {
+    /// Every built-in material, in discriminant order
+    pub const BUILTIN: [MaterialType; 44] = [
+        MaterialType::Empty, MaterialType::Sand, MaterialType::Water, MaterialType::Stone,
+        MaterialType::Plant, MaterialType::Fire, MaterialType::Lava, MaterialType::Glass,
+        MaterialType::Steam, MaterialType::Oil, MaterialType::Acid, MaterialType::Coal,
+        MaterialType::Gunpowder, MaterialType::Ice, MaterialType::Wood, MaterialType::Smoke,
+        MaterialType::ToxicGas, MaterialType::Slime, MaterialType::Gasoline, MaterialType::Generator,
+        MaterialType::Fuse, MaterialType::Ash, MaterialType::Gold, MaterialType::Iron,
+        MaterialType::Sandstone, MaterialType::MoltenIron, MaterialType::MoltenGold, MaterialType::Seed,
+        MaterialType::Stem, MaterialType::Root, MaterialType::Snow, MaterialType::Door,
+        MaterialType::Piston, MaterialType::Dirt, MaterialType::WetSand, MaterialType::Mud,
+        MaterialType::Salt, MaterialType::SaltWater, MaterialType::Firework, MaterialType::Spark,
+        MaterialType::Thermite, MaterialType::Uranium, MaterialType::Lead, MaterialType::Eraser,
+    ];
+
+    pub fn is_custom(self) -> bool {
+        matches!(self, MaterialType::Custom(_))
+    }
+
+    /// Stable numeric id used by saves and the wire protocol: the discriminant for
+    /// built-ins, which is never reused, so new variants take new numbers rather than
+    /// shifting old ones, and `CUSTOM_MATERIAL_ID` plus the registry id for custom
+    /// materials (custom ids past 64535 don't fit and saturate)
+    pub fn id(self) -> u16 {
+        match self {
+            MaterialType::Custom(id) => CUSTOM_MATERIAL_ID.saturating_add(id),
+            // SAFETY: `MaterialType` is `repr(u16)`, so every variant starts with its u16 discriminant
+            builtin => unsafe { *(&builtin as *const MaterialType as *const u16) },
+        }
+    }
+
+    /// The material with a stable `id`; None for a built-in id this build doesn't have
+    pub fn from_id(id: u16) -> Option<Self> {
+        if id >= CUSTOM_MATERIAL_ID {
+            return Some(MaterialType::Custom(id - CUSTOM_MATERIAL_ID));
+        }
+        MaterialType::BUILTIN.iter().copied().find(|material| material.id() == id)
+    }
+}
+
+/// Stable ids from here up are custom materials
+pub const CUSTOM_MATERIAL_ID: u16 = 1000;
+
+/// Serializes a `MaterialType` as its stable id, for fields marked
+/// `#[serde(with = "material_id")]`
+pub mod material_id {
+    use super::MaterialType;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(material: &MaterialType, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u16(material.id())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MaterialType, D::Error> {
+        let id = u16::deserialize(deserializer)?;
+        MaterialType::from_id(id).ok_or_else(|| D::Error::custom(format!("unknown material id {}", id)))
+    }
+}
+
+/// Broad movement class. Built-in materials keep their hand-tuned behaviour; for custom
+/// materials the kind decides how they fall, flow and pile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MaterialKind {
+    #[default]
+    Powder,
+    Liquid,
+    Gas,
+    /// Falls straight down but never slides diagonally
+    Solid,
+    /// Never moves
+    Static,
+}
+
+impl MaterialKind {
+    fn of_builtin(material_type: MaterialType) -> Self {
+        match material_type {
+            MaterialType::Sand | MaterialType::Ash | MaterialType::Gunpowder |
+            MaterialType::Plant | MaterialType::Fuse | MaterialType::Seed |
+            MaterialType::Snow | MaterialType::Dirt | MaterialType::WetSand |
+            MaterialType::Salt | MaterialType::Firework | MaterialType::Spark |
+            MaterialType::Thermite => MaterialKind::Powder,
+            MaterialType::Water | MaterialType::Oil | MaterialType::Acid |
+            MaterialType::Gasoline | MaterialType::Lava | MaterialType::Slime |
+            MaterialType::MoltenIron | MaterialType::MoltenGold | MaterialType::Mud |
+            MaterialType::SaltWater => MaterialKind::Liquid,
+            MaterialType::Fire | MaterialType::Steam | MaterialType::Smoke |
+            MaterialType::ToxicGas => MaterialKind::Gas,
+            MaterialType::Stone | MaterialType::Glass | MaterialType::Wood |
+            MaterialType::Ice | MaterialType::Coal | MaterialType::Gold |
+            MaterialType::Iron | MaterialType::Sandstone | MaterialType::Uranium |
+            MaterialType::Lead => MaterialKind::Solid,
+            MaterialType::Empty | MaterialType::Generator | MaterialType::Eraser |
+            MaterialType::Stem | MaterialType::Root | MaterialType::Door | MaterialType::Piston |
+            MaterialType::Custom(_) => MaterialKind::Static,
+        }
+    }
+}
+
+/// What a player digs with. Each tier breaks materials up to its `max_hardness`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolTier {
+    #[default]
+    Hands,
+    Shovel,
+    Pickaxe,
+}
+
+impl ToolTier {
+    /// Every tier, weakest first
+    pub const ALL: [ToolTier; 3] = [ToolTier::Hands, ToolTier::Shovel, ToolTier::Pickaxe];
+
+    /// Hardest material this tier can break
+    pub fn max_hardness(self) -> f32 {
+        match self {
+            ToolTier::Hands => 0.5,
+            ToolTier::Shovel => 1.5,
+            ToolTier::Pickaxe => 2.5,
+        }
+    }
+
+    /// Dig speed multiplier; shovels are best on loose ground
+    pub fn speed(self) -> f32 {
+        match self {
+            ToolTier::Hands => 1.0,
+            ToolTier::Shovel => 4.0,
+            ToolTier::Pickaxe => 2.0,
+        }
+    }
+
+    pub fn can_break(self, hardness: f32) -> bool {
+        hardness <= self.max_hardness()
+    }
+
+    /// Weakest tier that can break `hardness`; None when no tool can
+    pub fn required_for(hardness: f32) -> Option<ToolTier> {
+        Self::ALL.into_iter().find(|tier| tier.can_break(hardness))
+    }
+}
+
+/// Fraction of its thermal stress a solid relieves per second
+pub const THERMAL_STRESS_RELIEF: f32 = 10.0;
+/// Stress below this many degrees counts as fully relieved, letting the cell settle
+pub const THERMAL_STRESS_EPSILON: f32 = 0.01;
+/// Stress at which the built-in brittle solids crack; see `Material::crack_stress`
+pub const GLASS_CRACK_STRESS: f32 = 32.0;
+pub const STONE_CRACK_STRESS: f32 = 60.0;
+pub const ICE_CRACK_STRESS: f32 = 0.8;
+/// Temperature burning thermite reaches, well past the melting point of iron
+pub const THERMITE_BURN_TEMP: f32 = 2500.0;
+/// Health the built-in solids get per point of hardness; see `Material::max_health`
+pub const HEALTH_PER_HARDNESS: f32 = 50.0;
+/// Radiation uranium gives off, as the dose a cell beside it takes
+pub const URANIUM_RADIOACTIVITY: f32 = 1.0;
+/// Seconds until half of a pile of uranium has decayed into lead
+pub const URANIUM_HALF_LIFE: f32 = 1800.0;
+/// Latent heat of the built-in water phases, in degrees of a cell's own temperature;
+/// scaled down from the real 80 and 540 so ice and pools still turn over within seconds
+pub const WATER_FUSION_HEAT: f32 = 20.0;
+pub const WATER_VAPORIZATION_HEAT: f32 = 120.0;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Material {
     pub density: f32,
@@ -45,6 +243,73 @@ pub struct Material {
     pub explosive_yield: Option<f32>,
     pub heat_generation: f32,
     pub ignition_temp: Option<f32>,
+    #[serde(default)]
+    pub kind: MaterialKind,
+    /// Resistance to digging, compared against `ToolTier::max_hardness`
+    #[serde(default)]
+    pub hardness: f32,
+    /// Damage a cell takes from blasts, acid and impacts before it breaks into rubble;
+    /// zero for materials that don't wear down. See `damage::apply_damage`
+    #[serde(default)]
+    pub max_health: f32,
+    /// Light given off, 0..1; see `LightMap`
+    #[serde(default)]
+    pub light_emission: f32,
+    /// Fraction of light passing through a cell of this material; 0 is opaque
+    #[serde(default)]
+    pub light_transmission: f32,
+    /// Heat radiated to cells within `RADIATION_RADIUS` while hotter than
+    /// `RADIATION_MIN_TEMP`, per second per degree of difference at distance 1
+    #[serde(default)]
+    pub radiant_heat: f32,
+    /// Seconds a cell burns for once alight with plenty of oxygen. Liquid fuels turn into
+    /// Fire that lasts this long, other fuels burn in place; zero uses the default flame
+    #[serde(default)]
+    pub fuel_value: f32,
+    /// What a cell that burned in place leaves behind; Smoke when unset
+    #[serde(default)]
+    pub chars_into: Option<MaterialType>,
+    /// Temperature a cell burning in place heats itself up to; zero for an ordinary fire
+    #[serde(default)]
+    pub burn_temp: f32,
+    /// Radiation given off, as the dose taken by a cell at distance 1; see `RadiationField`
+    #[serde(default)]
+    pub radioactivity: f32,
+    /// Seconds until half of a radioactive material's cells have decayed into `decays_into`;
+    /// zero never decays
+    #[serde(default)]
+    pub half_life: f32,
+    #[serde(default)]
+    pub decays_into: Option<MaterialType>,
+    /// What a buried cell turns into after carrying `compaction_load` (the summed density
+    /// of the column above it) for `compaction_seconds`; see `CompactionRules`
+    #[serde(default)]
+    pub compacts_into: Option<MaterialType>,
+    #[serde(default)]
+    pub compaction_load: f32,
+    #[serde(default)]
+    pub compaction_seconds: f32,
+    // Phase change products; checked against melt/boil/freeze temps
+    #[serde(default)]
+    pub melts_into: Option<MaterialType>,
+    #[serde(default)]
+    pub boils_into: Option<MaterialType>,
+    #[serde(default)]
+    pub freezes_into: Option<MaterialType>,
+    /// What a cell breaks into once its thermal stress, the temperature swings it has
+    /// gone through less what `THERMAL_STRESS_RELIEF` has eased, passes `crack_stress`
+    #[serde(default)]
+    pub cracks_into: Option<MaterialType>,
+    #[serde(default)]
+    pub crack_stress: f32,
+    /// Degrees of heat a cell soaks up at its melting or boiling point, held there, before
+    /// it changes state; zero changes it as soon as it gets there
+    #[serde(default)]
+    pub heating_latent_heat: f32,
+    /// Degrees of heat a cell gives off at its freezing or condensation point, held there,
+    /// before it changes state
+    #[serde(default)]
+    pub cooling_latent_heat: f32,
 }
 
 impl Material {
@@ -79,32 +344,70 @@ impl Material {
             explosive_yield,
             heat_generation,
             ignition_temp,
+            kind: MaterialKind::default(),
+            hardness: 0.0,
+            max_health: 0.0,
+            light_emission: 0.0,
+            light_transmission: 0.0,
+            radiant_heat: 0.0,
+            fuel_value: 0.0,
+            chars_into: None,
+            burn_temp: 0.0,
+            radioactivity: 0.0,
+            half_life: 0.0,
+            decays_into: None,
+            compacts_into: None,
+            compaction_load: 0.0,
+            compaction_seconds: 0.0,
+            melts_into: None,
+            boils_into: None,
+            freezes_into: None,
+            cracks_into: None,
+            crack_stress: 0.0,
+            heating_latent_heat: 0.0,
+            cooling_latent_heat: 0.0,
         }
     }
 
     pub fn is_liquid(&self, material_type: MaterialType) -> bool {
+        if material_type.is_custom() {
+            return self.kind == MaterialKind::Liquid;
+        }
         matches!(
             material_type,
-            MaterialType::Water | MaterialType::Oil | MaterialType::Acid | MaterialType::Gasoline | MaterialType::Lava
+            MaterialType::Water | MaterialType::Oil | MaterialType::Acid | MaterialType::Gasoline | MaterialType::Lava |
+            MaterialType::MoltenIron | MaterialType::MoltenGold | MaterialType::Mud | MaterialType::SaltWater
         )
     }
 
     pub fn is_powder(&self, material_type: MaterialType) -> bool {
+        if material_type.is_custom() {
+            return self.kind == MaterialKind::Powder;
+        }
         matches!(
             material_type,
-            MaterialType::Sand | MaterialType::Ash | MaterialType::Gunpowder
+            MaterialType::Sand | MaterialType::Ash | MaterialType::Gunpowder | MaterialType::Seed |
+            MaterialType::Snow | MaterialType::Dirt | MaterialType::WetSand | MaterialType::Salt |
+            MaterialType::Firework | MaterialType::Spark | MaterialType::Thermite
         )
     }
 
     pub fn is_rigid_solid(&self, material_type: MaterialType) -> bool {
+        if material_type.is_custom() {
+            return matches!(self.kind, MaterialKind::Solid | MaterialKind::Static);
+        }
         matches!(
             material_type,
             MaterialType::Stone | MaterialType::Glass | MaterialType::Wood | MaterialType::Ice |
-            MaterialType::Gold | MaterialType::Iron | MaterialType::Coal
+            MaterialType::Gold | MaterialType::Iron | MaterialType::Coal |
+            MaterialType::Sandstone | MaterialType::Uranium | MaterialType::Lead
         )
     }
 
     pub fn is_gas(&self, material_type: MaterialType) -> bool {
+        if material_type.is_custom() {
+            return self.kind == MaterialKind::Gas;
+        }
         self.density < 0.0 || matches!(
             material_type,
             MaterialType::Steam | MaterialType::Smoke | MaterialType::ToxicGas
@@ -113,16 +416,189 @@ impl Material {
 
     pub fn is_stationary(&self, material_type: MaterialType) -> bool {
         // Materials that don't fall or move (except when part of rigid bodies)
+        if material_type.is_custom() {
+            return self.kind == MaterialKind::Static;
+        }
         matches!(
             material_type,
             MaterialType::Stone | MaterialType::Wood | MaterialType::Ice | 
             MaterialType::Gold | MaterialType::Iron | MaterialType::Coal |
-            MaterialType::Generator
+            MaterialType::Sandstone | MaterialType::Uranium | MaterialType::Lead | MaterialType::Generator | MaterialType::Stem | MaterialType::Root |
+            MaterialType::Door | MaterialType::Piston
         )
     }
 }
 
 pub fn get_material_properties(material_type: MaterialType) -> Material {
+    let mut material = builtin_material(material_type);
+    material.kind = MaterialKind::of_builtin(material_type);
+    material.hardness = builtin_hardness(material_type);
+    material.max_health = builtin_health(material_type);
+    (material.light_emission, material.light_transmission) = builtin_light(material_type);
+    material.radiant_heat = builtin_radiant_heat(material_type);
+    (material.fuel_value, material.chars_into) = builtin_fuel(material_type);
+    material.burn_temp = builtin_burn_temp(material_type);
+    (material.radioactivity, material.half_life, material.decays_into) = builtin_radioactivity(material_type);
+    (material.compacts_into, material.compaction_load, material.compaction_seconds) = builtin_compaction(material_type);
+    (material.melts_into, material.boils_into, material.freezes_into) = builtin_transitions(material_type);
+    (material.cracks_into, material.crack_stress) = builtin_cracking(material_type);
+    (material.heating_latent_heat, material.cooling_latent_heat) = builtin_latent_heat(material_type);
+    material
+}
+
+fn builtin_hardness(material_type: MaterialType) -> f32 {
+    match material_type {
+        MaterialType::Sand | MaterialType::Gunpowder | MaterialType::Fuse | MaterialType::Dirt |
+        MaterialType::Salt | MaterialType::Firework | MaterialType::Thermite => 0.3,
+        MaterialType::WetSand => 0.4,
+        MaterialType::Ash | MaterialType::Plant | MaterialType::Slime | MaterialType::Seed => 0.2,
+        MaterialType::Snow => 0.1,
+        MaterialType::Stem | MaterialType::Root => 0.5,
+        MaterialType::Sandstone | MaterialType::Wood | MaterialType::Ice => 1.0,
+        MaterialType::Glass => 1.8,
+        MaterialType::Stone | MaterialType::Coal => 2.0,
+        // Metals and machines can't be broken by hand tools
+        MaterialType::Lead => 1.5,
+        MaterialType::Uranium => 2.5,
+        MaterialType::Gold => 3.0,
+        MaterialType::Iron => 4.0,
+        MaterialType::Generator | MaterialType::Door | MaterialType::Piston => 10.0,
+        MaterialType::Custom(_) => 1.0,
+        MaterialType::Empty | MaterialType::Eraser | MaterialType::Water | MaterialType::Oil |
+        MaterialType::Acid | MaterialType::Gasoline | MaterialType::Lava | MaterialType::Fire |
+        MaterialType::Steam | MaterialType::Smoke | MaterialType::ToxicGas |
+        MaterialType::MoltenIron | MaterialType::MoltenGold | MaterialType::Mud | MaterialType::SaltWater |
+        MaterialType::Spark => 0.0,
+    }
+}
+
+/// Health of the built-in solids that wear down, in proportion to their hardness so a
+/// full-strength blast still breaks whatever it used to break outright
+fn builtin_health(material_type: MaterialType) -> f32 {
+    match material_type {
+        MaterialType::Stone | MaterialType::Sandstone | MaterialType::Glass | MaterialType::Wood |
+        MaterialType::Coal | MaterialType::Ice => builtin_hardness(material_type) * HEALTH_PER_HARDNESS,
+        _ => 0.0,
+    }
+}
+
+/// Emission and transmission of the built-in materials
+fn builtin_light(material_type: MaterialType) -> (f32, f32) {
+    match material_type {
+        MaterialType::Fire => (1.0, 0.9),
+        MaterialType::Spark => (0.8, 1.0),
+        MaterialType::Lava => (0.9, 0.0),
+        MaterialType::MoltenIron => (0.8, 0.0),
+        MaterialType::MoltenGold => (0.7, 0.0),
+        MaterialType::Uranium => (0.2, 0.0),
+        MaterialType::Empty | MaterialType::Eraser => (0.0, 1.0),
+        MaterialType::Glass => (0.0, 0.95),
+        MaterialType::Steam | MaterialType::ToxicGas => (0.0, 0.8),
+        MaterialType::Water | MaterialType::SaltWater => (0.0, 0.75),
+        MaterialType::Ice => (0.0, 0.7),
+        MaterialType::Acid | MaterialType::Gasoline => (0.0, 0.6),
+        MaterialType::Smoke => (0.0, 0.4),
+        MaterialType::Oil | MaterialType::Slime => (0.0, 0.3),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// Heat the built-in hot materials radiate; see `radiation.rs`
+fn builtin_radiant_heat(material_type: MaterialType) -> f32 {
+    match material_type {
+        MaterialType::Fire => 0.15,
+        MaterialType::Thermite => 0.3,
+        MaterialType::Lava => 0.1,
+        MaterialType::MoltenIron => 0.08,
+        MaterialType::MoltenGold => 0.05,
+        _ => 0.0,
+    }
+}
+
+/// Burn time and char product of the built-in fuels; wood chars to charcoal, the rest to ash
+fn builtin_fuel(material_type: MaterialType) -> (f32, Option<MaterialType>) {
+    match material_type {
+        MaterialType::Wood => (6.0, Some(MaterialType::Coal)),
+        MaterialType::Coal => (10.0, Some(MaterialType::Ash)),
+        MaterialType::Plant | MaterialType::Stem => (1.0, Some(MaterialType::Ash)),
+        MaterialType::Root => (1.5, Some(MaterialType::Ash)),
+        MaterialType::Seed => (0.5, Some(MaterialType::Ash)),
+        MaterialType::Oil => (2.0, None),
+        MaterialType::Gasoline => (1.0, None),
+        MaterialType::Thermite => (6.0, Some(MaterialType::MoltenIron)),
+        _ => (0.0, None),
+    }
+}
+
+/// Temperature the built-in fuels burn at, where it differs from ordinary fires
+fn builtin_burn_temp(material_type: MaterialType) -> f32 {
+    match material_type {
+        MaterialType::Thermite => THERMITE_BURN_TEMP,
+        _ => 0.0,
+    }
+}
+
+/// Radioactivity, half-life and decay product of the built-in radioactive materials
+fn builtin_radioactivity(material_type: MaterialType) -> (f32, f32, Option<MaterialType>) {
+    match material_type {
+        MaterialType::Uranium => (URANIUM_RADIOACTIVITY, URANIUM_HALF_LIFE, Some(MaterialType::Lead)),
+        _ => (0.0, 0.0, None),
+    }
+}
+
+/// Compaction table for the built-in powders: product, load and seconds buried
+fn builtin_compaction(material_type: MaterialType) -> (Option<MaterialType>, f32, f32) {
+    match material_type {
+        // Roughly fifteen grains of sand, twenty of ash or twenty flakes of snow overhead
+        MaterialType::Sand => (Some(MaterialType::Sandstone), 24.0, 30.0),
+        MaterialType::Ash => (Some(MaterialType::Stone), 18.0, 60.0),
+        MaterialType::Snow => (Some(MaterialType::Ice), 6.0, 20.0),
+        _ => (None, 0.0, 0.0),
+    }
+}
+
+/// Thermal shock table for the built-in brittle solids: debris and stress to crack
+fn builtin_cracking(material_type: MaterialType) -> (Option<MaterialType>, f32) {
+    match material_type {
+        MaterialType::Glass => (Some(MaterialType::Sand), GLASS_CRACK_STRESS),
+        MaterialType::Stone => (Some(MaterialType::Sand), STONE_CRACK_STRESS),
+        MaterialType::Ice => (Some(MaterialType::Snow), ICE_CRACK_STRESS),
+        _ => (None, 0.0),
+    }
+}
+
+/// Latent heat the built-in water phases take in while melting or boiling and give off
+/// while freezing or condensing
+fn builtin_latent_heat(material_type: MaterialType) -> (f32, f32) {
+    match material_type {
+        MaterialType::Ice | MaterialType::Snow => (WATER_FUSION_HEAT, 0.0),
+        MaterialType::Water => (WATER_VAPORIZATION_HEAT, WATER_FUSION_HEAT),
+        MaterialType::SaltWater => (WATER_VAPORIZATION_HEAT, 0.0),
+        MaterialType::Steam => (0.0, WATER_VAPORIZATION_HEAT),
+        _ => (0.0, 0.0),
+    }
+}
+
+fn builtin_transitions(material_type: MaterialType) -> (Option<MaterialType>, Option<MaterialType>, Option<MaterialType>) {
+    match material_type {
+        MaterialType::Sand | MaterialType::Sandstone => (Some(MaterialType::Glass), None, None),
+        MaterialType::Glass => (Some(MaterialType::Lava), None, None),
+        MaterialType::Ice | MaterialType::Snow => (Some(MaterialType::Water), None, None),
+        MaterialType::Stone => (Some(MaterialType::Lava), None, None),
+        MaterialType::Iron => (Some(MaterialType::MoltenIron), None, None),
+        MaterialType::Gold => (Some(MaterialType::MoltenGold), None, None),
+        MaterialType::MoltenIron => (None, None, Some(MaterialType::Iron)),
+        MaterialType::MoltenGold => (None, None, Some(MaterialType::Gold)),
+        MaterialType::Water => (None, Some(MaterialType::Steam), Some(MaterialType::Ice)),
+        MaterialType::SaltWater => (None, Some(MaterialType::Steam), None),
+        MaterialType::Acid | MaterialType::Slime => (None, Some(MaterialType::ToxicGas), None),
+        MaterialType::Lava => (None, None, Some(MaterialType::Stone)),
+        MaterialType::Steam => (None, None, Some(MaterialType::Water)),
+        _ => (None, None, None),
+    }
+}
+
+fn builtin_material(material_type: MaterialType) -> Material {
     match material_type {
         MaterialType::Empty => Material::new(
             0.0, 0.1, 0.0, None, None, None, [0, 0, 0], "Empty", 1.0, None, 0.0, None, 0.0, None
@@ -134,7 +610,7 @@ pub fn get_material_properties(material_type: MaterialType) -> Material {
             1.0, 0.6, 0.0, None, Some(100.0), Some(0.0), [50, 100, 200], "Water", 1.0, None, 0.0, None, 0.0, None
         ),
         MaterialType::Stone => Material::new(
-            2.7, 0.2, 0.0, None, None, None, [100, 100, 100], "Stone", 1.0, None, 0.0, None, 0.0, None
+            2.7, 0.2, 0.0, Some(1800.0), None, None, [100, 100, 100], "Stone", 1.0, None, 0.0, None, 0.0, None
         ),
         MaterialType::Plant => Material::new(
             0.4, 0.1, 0.4, Some(200.0), None, None, [50, 150, 50], "Plant", 1.0, None, 0.0, None, 0.0, Some(150.0)
@@ -196,8 +672,69 @@ pub fn get_material_properties(material_type: MaterialType) -> Material {
         MaterialType::Iron => Material::new(
             7.9, 0.7, 0.0, Some(1538.0), None, None, [139, 139, 139], "Iron", 1.0, None, 0.0, None, 0.0, None
         ),
+        MaterialType::Sandstone => Material::new(
+            2.3, 0.25, 0.0, Some(1600.0), None, None, [176, 150, 102], "Sandstone", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::MoltenIron => Material::new(
+            7.0, 0.6, 0.0, None, None, Some(1500.0), [255, 110, 30], "Molten Iron", 6.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::MoltenGold => Material::new(
+            17.3, 0.7, 0.0, None, None, Some(1040.0), [255, 170, 40], "Molten Gold", 4.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Seed => Material::new(
+            1.1, 0.1, 0.3, None, None, None, [150, 120, 60], "Seed", 1.0, None, 0.0, None, 0.0, Some(200.0)
+        ),
+        MaterialType::Stem => Material::new(
+            0.6, 0.1, 0.4, None, None, None, [70, 140, 40], "Stem", 1.0, None, 0.0, None, 0.0, Some(150.0)
+        ),
+        MaterialType::Root => Material::new(
+            1.0, 0.1, 0.2, None, None, None, [120, 90, 50], "Root", 1.0, None, 0.0, None, 0.0, Some(200.0)
+        ),
+        MaterialType::Snow => Material::new(
+            0.3, 0.01, 0.0, Some(1.0), None, None, [240, 245, 255], "Snow", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Door => Material::new(
+            100.0, 0.2, 0.0, None, None, None, [95, 105, 120], "Door", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Piston => Material::new(
+            100.0, 0.3, 0.0, None, None, None, [150, 120, 70], "Piston", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Dirt => Material::new(
+            1.3, 0.25, 0.0, None, None, None, [110, 80, 50], "Dirt", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::WetSand => Material::new(
+            1.9, 0.5, 0.0, None, None, None, [150, 130, 85], "Wet Sand", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Mud => Material::new(
+            1.7, 0.5, 0.0, None, None, None, [85, 60, 40], "Mud", 12.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Salt => Material::new(
+            2.2, 0.3, 0.0, None, None, None, [235, 235, 228], "Salt", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::SaltWater => Material::new(
+            1.2, 0.6, 0.0, None, Some(100.0), None, [70, 120, 190], "Salt Water", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Firework => Material::new(
+            1.5, 0.1, 1.0, None, None, None, [200, 40, 60], "Firework", 1.0, None, 0.0, None, 0.0, Some(150.0)
+        ),
+        MaterialType::Spark => Material::new(
+            0.3, 0.5, 0.0, None, None, None, [255, 220, 120], "Spark", 1.0, Some(1.2), 0.0, None, 0.0, None
+        ),
+        MaterialType::Thermite => Material::new(
+            7.5, 0.3, 1.0, None, None, None, [140, 90, 80], "Thermite", 1.0, None, 0.0, None, 0.0, Some(500.0)
+        ),
+        MaterialType::Uranium => Material::new(
+            19.1, 0.3, 0.0, None, None, None, [90, 170, 60], "Uranium", 1.0, None, 0.0, None, 0.0, None
+        ),
+        MaterialType::Lead => Material::new(
+            11.3, 0.35, 0.0, None, None, None, [95, 100, 115], "Lead", 1.0, None, 0.0, None, 0.0, None
+        ),
         MaterialType::Eraser => Material::new(
             0.0, 0.0, 0.0, None, None, None, [255, 0, 255], "Eraser", 1.0, None, 0.0, None, 0.0, None
         ),
+        // Unregistered custom ids get an inert placeholder; use MaterialRegistry for real definitions
+        MaterialType::Custom(id) => Material::new(
+            1.0, 0.2, 0.0, None, None, None, [255, 0, 255], &format!("Custom {}", id), 1.0, None, 0.0, None, 0.0, None
+        ),
     }
 }
\ No newline at end of file
@@ -0,0 +1,259 @@
+use crate::materials::MaterialType;
+use crate::simulation::{GridRect, Simulation};
+use crate::telemetry::Telemetry;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Largest cavity `Cavity::find` will flood before deciding it isn't enclosed
+pub const DEFAULT_MAX_CAVITY_CELLS: usize = 20_000;
+
+/// Offset between two cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Distance {
+    pub dx: i64,
+    pub dy: i64,
+}
+
+impl Distance {
+    pub fn between(from: (usize, usize), to: (usize, usize)) -> Self {
+        Self {
+            dx: to.0 as i64 - from.0 as i64,
+            dy: to.1 as i64 - from.1 as i64,
+        }
+    }
+
+    /// Straight-line length in cells
+    pub fn euclidean(&self) -> f64 {
+        ((self.dx * self.dx + self.dy * self.dy) as f64).sqrt()
+    }
+
+    /// Cells walked along the grid axes
+    pub fn manhattan(&self) -> u64 {
+        self.dx.unsigned_abs() + self.dy.unsigned_abs()
+    }
+}
+
+/// Particles by material inside a rectangle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegionCount {
+    pub rect: GridRect,
+    pub total: usize,
+    /// Most common material first
+    pub by_material: Vec<(MaterialType, usize)>,
+}
+
+impl RegionCount {
+    /// Count the particles of `simulation` inside `rect`; parts outside the grid are ignored
+    pub fn of(simulation: &Simulation, rect: GridRect) -> Self {
+        let mut counts: HashMap<MaterialType, usize> = HashMap::new();
+        for particle in simulation.iter_region(rect) {
+            *counts.entry(particle.material_type).or_default() += 1;
+        }
+        let materials = simulation.materials();
+        let mut by_material: Vec<(MaterialType, usize)> = counts.into_iter().collect();
+        by_material.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| materials.get(a.0).name.cmp(&materials.get(b.0).name)));
+        Self {
+            rect,
+            total: by_material.iter().map(|(_, count)| count).sum(),
+            by_material,
+        }
+    }
+
+    pub fn count(&self, material_type: MaterialType) -> usize {
+        self.by_material.iter()
+            .find(|(material, _)| *material == material_type)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Add a `<prefix>.total` sample and one `<prefix>.<material>` sample per material
+    pub fn record(&self, simulation: &Simulation, telemetry: &mut Telemetry, prefix: &str, time: f32) {
+        let tick = simulation.tick();
+        telemetry.record(tick, time, format!("{}.total", prefix), self.total as f64);
+        for (material, count) in &self.by_material {
+            let name = &simulation.materials().get(*material).name;
+            telemetry.record(tick, time, format!("{}.{}", prefix, name), *count as f64);
+        }
+    }
+}
+
+/// The open cells reachable from a seed without passing through solids or going above
+/// the seed's row, so clicking at the rim of an open container measures the container.
+/// Grid edges count as walls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cavity {
+    cells: Vec<(usize, usize)>,
+}
+
+impl Cavity {
+    /// Flood from `(x, y)`; None if the seed is inside a wall or the cavity has more than
+    /// `max_cells` cells, i.e. it isn't enclosed
+    pub fn find(simulation: &Simulation, x: usize, y: usize, max_cells: usize) -> Option<Self> {
+        if x >= simulation.width || y >= simulation.height || is_wall(simulation, x, y) {
+            return None;
+        }
+        let mut seen = HashSet::from([(x, y)]);
+        let mut stack = vec![(x, y)];
+        let mut cells = Vec::new();
+        while let Some((cx, cy)) = stack.pop() {
+            cells.push((cx, cy));
+            if cells.len() > max_cells {
+                return None;
+            }
+            let neighbors = [
+                (cx.wrapping_sub(1), cy),
+                (cx + 1, cy),
+                (cx, cy.wrapping_sub(1)),
+                (cx, cy + 1),
+            ];
+            for (nx, ny) in neighbors {
+                if nx >= simulation.width || ny >= simulation.height || ny < y {
+                    continue;
+                }
+                if !is_wall(simulation, nx, ny) && seen.insert((nx, ny)) {
+                    stack.push((nx, ny));
+                }
+            }
+        }
+        cells.sort_unstable();
+        Some(Self { cells })
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn cells(&self) -> &[(usize, usize)] {
+        &self.cells
+    }
+
+    /// Cells holding liquid or powder; gases don't count towards the fill
+    pub fn filled(&self, simulation: &Simulation) -> usize {
+        let materials = simulation.materials();
+        self.cells.iter()
+            .filter_map(|&(x, y)| simulation.get_particle(x, y))
+            .filter(|particle| {
+                let material = materials.get(particle.material_type);
+                material.is_liquid(particle.material_type) || material.is_powder(particle.material_type)
+            })
+            .count()
+    }
+}
+
+fn is_wall(simulation: &Simulation, x: usize, y: usize) -> bool {
+    simulation.get_particle(x, y).is_some_and(|particle| {
+        let material = simulation.materials().get(particle.material_type);
+        material.is_rigid_solid(particle.material_type) || material.is_stationary(particle.material_type)
+    })
+}
+
+/// Fill of one cavity sampled over time, for flow-rate experiments
+#[derive(Debug, Clone)]
+pub struct VolumeTracker {
+    pub cavity: Cavity,
+    /// (simulated seconds, filled cells), oldest first
+    samples: Vec<(f32, usize)>,
+}
+
+impl VolumeTracker {
+    pub fn new(cavity: Cavity) -> Self {
+        Self { cavity, samples: Vec::new() }
+    }
+
+    /// Record the fill at `time`, returning it
+    pub fn sample(&mut self, simulation: &Simulation, time: f32) -> usize {
+        let filled = self.cavity.filled(simulation);
+        self.samples.push((time, filled));
+        filled
+    }
+
+    pub fn samples(&self) -> &[(f32, usize)] {
+        &self.samples
+    }
+
+    /// Cells per second between the last two samples
+    pub fn flow_rate(&self) -> Option<f32> {
+        let [.., (t0, v0), (t1, v1)] = self.samples.as_slice() else {
+            return None;
+        };
+        rate(*t0, *v0, *t1, *v1)
+    }
+
+    /// Cells per second from the first sample to the last
+    pub fn average_flow_rate(&self) -> Option<f32> {
+        let (&(t0, v0), &(t1, v1)) = (self.samples.first()?, self.samples.last()?);
+        rate(t0, v0, t1, v1)
+    }
+
+    /// Add `<prefix>.filled`, `<prefix>.capacity` and, once known, `<prefix>.flow_rate`
+    /// samples for the latest reading
+    pub fn record(&self, tick: u64, telemetry: &mut Telemetry, prefix: &str) {
+        let Some(&(time, filled)) = self.samples.last() else {
+            return;
+        };
+        telemetry.record(tick, time, format!("{}.filled", prefix), filled as f64);
+        telemetry.record(tick, time, format!("{}.capacity", prefix), self.cavity.capacity() as f64);
+        if let Some(flow_rate) = self.flow_rate() {
+            telemetry.record(tick, time, format!("{}.flow_rate", prefix), flow_rate as f64);
+        }
+    }
+}
+
+fn rate(t0: f32, v0: usize, t1: f32, v1: usize) -> Option<f32> {
+    (t1 > t0).then(|| (v1 as f32 - v0 as f32) / (t1 - t0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stone cup 5 cells wide inside, open at the top, on rows 10..=15
+    fn cup() -> Simulation {
+        let mut simulation = Simulation::new(30, 20);
+        for y in 10..=15 {
+            simulation.add_particle(9, y, MaterialType::Stone, None);
+            simulation.add_particle(15, y, MaterialType::Stone, None);
+        }
+        for x in 9..=15 {
+            simulation.add_particle(x, 16, MaterialType::Stone, None);
+        }
+        simulation
+    }
+
+    #[test]
+    fn test_distance_and_region_count() {
+        let distance = Distance::between((2, 3), (5, 7));
+        assert_eq!(distance.euclidean(), 5.0);
+        assert_eq!(distance.manhattan(), 7);
+
+        let mut simulation = cup();
+        simulation.add_particle(12, 15, MaterialType::Water, None);
+        let count = RegionCount::of(&simulation, GridRect::new(9, 10, 7, 7));
+        assert_eq!(count.by_material[0], (MaterialType::Stone, 19));
+        assert_eq!(count.count(MaterialType::Water), 1);
+        assert_eq!(count.total, 20);
+    }
+
+    #[test]
+    fn test_cavity_volume_over_time() {
+        let mut simulation = cup();
+        let cavity = Cavity::find(&simulation, 12, 10, DEFAULT_MAX_CAVITY_CELLS).unwrap();
+        assert_eq!(cavity.capacity(), 5 * 6);
+        // Outside the cup the flood spills across the whole grid
+        assert!(Cavity::find(&simulation, 2, 10, 100).is_none());
+        assert!(Cavity::find(&simulation, 9, 12, 100).is_none());
+
+        let mut tracker = VolumeTracker::new(cavity);
+        tracker.sample(&simulation, 0.0);
+        for x in 10..15 {
+            simulation.add_particle(x, 15, MaterialType::Water, None);
+            simulation.add_particle(x, 14, MaterialType::Sand, None);
+        }
+        simulation.add_particle(12, 11, MaterialType::Steam, None);
+        assert_eq!(tracker.sample(&simulation, 2.0), 10);
+        assert_eq!(tracker.flow_rate(), Some(5.0));
+
+        let mut telemetry = Telemetry::new();
+        tracker.record(simulation.tick(), &mut telemetry, "cup");
+        assert_eq!(telemetry.series("cup.flow_rate").next().unwrap().value, 5.0);
+    }
+}
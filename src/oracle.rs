@@ -0,0 +1,296 @@
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::simulation::{GridRect, Simulation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Most cells per side a failure snapshot shows; larger regions are cropped
+pub const MAX_SNAPSHOT_SIZE: usize = 48;
+/// Cells of context kept around stray particles in a `NoParticlesOf` snapshot
+const SNAPSHOT_MARGIN: usize = 2;
+
+/// A property a scenario expects of the grid
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Assertion {
+    /// At least `min_count` particles of `material` inside `region`
+    RegionContains { region: GridRect, material: MaterialType, min_count: usize },
+    /// At least `min_count` particles of any of `materials` inside `region`, between them
+    RegionContainsAny { region: GridRect, materials: Vec<MaterialType>, min_count: usize },
+    /// Every particle inside `region` is colder than `max_temp`
+    TemperatureBelow { region: GridRect, max_temp: f32 },
+    /// No particle of `material` anywhere on the grid
+    NoParticlesOf { material: MaterialType },
+}
+
+impl Assertion {
+    /// None if the assertion holds, otherwise a failure report for `simulation`'s current tick
+    pub fn evaluate(&self, simulation: &Simulation) -> Option<AssertionFailure> {
+        let name = |material: MaterialType| simulation.materials().get(material).name.clone();
+        let (message, region) = match self {
+            &Assertion::RegionContains { region, material, min_count } => {
+                let count = simulation.iter_region(region)
+                    .filter(|particle| particle.material_type == material)
+                    .count();
+                if count >= min_count {
+                    return None;
+                }
+                let message = format!(
+                    "expected at least {} {} in {}, found {}",
+                    min_count, name(material), describe(region), count
+                );
+                (message, region)
+            }
+            Assertion::RegionContainsAny { region, materials, min_count } => {
+                let count = simulation.iter_region(*region)
+                    .filter(|particle| materials.contains(&particle.material_type))
+                    .count();
+                if count >= *min_count {
+                    return None;
+                }
+                let names: Vec<String> = materials.iter().map(|&material| name(material)).collect();
+                let message = format!(
+                    "expected at least {} {} in {}, found {}",
+                    min_count, names.join(" or "), describe(*region), count
+                );
+                (message, *region)
+            }
+            &Assertion::TemperatureBelow { region, max_temp } => {
+                let too_hot: Vec<&Particle> = simulation.iter_region(region)
+                    .filter(|particle| self.violated_by(particle))
+                    .collect();
+                let hottest = too_hot.iter().max_by(|a, b| a.temp.total_cmp(&b.temp))?;
+                let message = format!(
+                    "expected {} below {:.1}°C, {} cells are not; hottest is {} at {:.1}°C at ({}, {})",
+                    describe(region), max_temp, too_hot.len(),
+                    name(hottest.material_type), hottest.temp, hottest.x, hottest.y
+                );
+                (message, region)
+            }
+            &Assertion::NoParticlesOf { material } => {
+                let strays: Vec<&Particle> = simulation.iter_particles()
+                    .filter(|particle| self.violated_by(particle))
+                    .collect();
+                let first = strays.first()?;
+                let (x0, y0) = strays.iter().fold((usize::MAX, usize::MAX), |(x, y), p| (x.min(p.x), y.min(p.y)));
+                let (x1, y1) = strays.iter().fold((0, 0), |(x, y), p| (x.max(p.x), y.max(p.y)));
+                let (x0, y0) = (x0.saturating_sub(SNAPSHOT_MARGIN), y0.saturating_sub(SNAPSHOT_MARGIN));
+                let region = GridRect::new(x0, y0, x1 + SNAPSHOT_MARGIN + 1 - x0, y1 + SNAPSHOT_MARGIN + 1 - y0);
+                let message = format!(
+                    "expected no {}, found {} (first at ({}, {}))",
+                    name(material), strays.len(), first.x, first.y
+                );
+                (message, region)
+            }
+        };
+
+        Some(AssertionFailure {
+            tick: simulation.tick(),
+            assertion: self.clone(),
+            message,
+            snapshot: render_snapshot(simulation, region, |particle| self.violated_by(particle)),
+        })
+    }
+
+    /// Whether `particle` on its own breaks the assertion
+    pub fn violated_by(&self, particle: &Particle) -> bool {
+        match *self {
+            Assertion::RegionContains { .. } | Assertion::RegionContainsAny { .. } => false,
+            Assertion::TemperatureBelow { max_temp, .. } => particle.temp >= max_temp,
+            Assertion::NoParticlesOf { material } => particle.material_type == material,
+        }
+    }
+}
+
+fn describe(region: GridRect) -> String {
+    format!("the {}x{} region at ({}, {})", region.width, region.height, region.x, region.y)
+}
+
+/// An assertion that didn't hold, with a text rendering of the cells around it
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssertionFailure {
+    pub tick: u64,
+    pub assertion: Assertion,
+    pub message: String,
+    pub snapshot: String,
+}
+
+impl std::fmt::Display for AssertionFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tick {}: {}\n{}", self.tick, self.message, self.snapshot)
+    }
+}
+
+/// Draw `region` of the grid as text, one character per cell: `.` for empty cells and a
+/// letter per material, listed in a legend underneath. Cells matching `violating` are
+/// drawn in capitals.
+pub fn render_snapshot(simulation: &Simulation, region: GridRect, violating: impl Fn(&Particle) -> bool) -> String {
+    let x0 = region.x.min(simulation.width);
+    let y0 = region.y.min(simulation.height);
+    let x1 = region.x.saturating_add(region.width).min(simulation.width).min(x0 + MAX_SNAPSHOT_SIZE);
+    let y1 = region.y.saturating_add(region.height).min(simulation.height).min(y0 + MAX_SNAPSHOT_SIZE);
+
+    let mut symbols: HashMap<MaterialType, char> = HashMap::new();
+    let mut legend = Vec::new();
+    let mut snapshot = format!("({}, {})..({}, {})\n", x0, y0, x1, y1);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let Some(particle) = simulation.get_particle(x, y) else {
+                snapshot.push('.');
+                continue;
+            };
+            let symbol = *symbols.entry(particle.material_type).or_insert_with(|| {
+                let symbol = if legend.len() < 26 { char::from(b'a' + legend.len() as u8) } else { '?' };
+                legend.push(format!("{} = {}", symbol, simulation.materials().get(particle.material_type).name));
+                symbol
+            });
+            snapshot.push(if violating(particle) { symbol.to_ascii_uppercase() } else { symbol });
+        }
+        snapshot.push('\n');
+    }
+    if !legend.is_empty() {
+        snapshot.push_str(&legend.join(", "));
+        snapshot.push('\n');
+    }
+    snapshot
+}
+
+/// Assertions scheduled at ticks of a scenario. Each is evaluated once, by the first
+/// `check` after the simulation has completed that many updates.
+#[derive(Debug, Clone, Default)]
+pub struct Oracle {
+    /// Sorted by tick, in the order added within a tick
+    scheduled: Vec<(u64, Assertion)>,
+    evaluated: usize,
+    failures: Vec<AssertionFailure>,
+}
+
+impl Oracle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, tick: u64, assertion: Assertion) -> &mut Self {
+        let index = self.scheduled.partition_point(|(scheduled, _)| *scheduled <= tick).max(self.evaluated);
+        self.scheduled.insert(index, (tick, assertion));
+        self
+    }
+
+    pub fn assert_region_contains(&mut self, tick: u64, region: GridRect, material: MaterialType, min_count: usize) -> &mut Self {
+        self.add(tick, Assertion::RegionContains { region, material, min_count })
+    }
+
+    pub fn assert_region_contains_any(&mut self, tick: u64, region: GridRect, materials: &[MaterialType], min_count: usize) -> &mut Self {
+        self.add(tick, Assertion::RegionContainsAny { region, materials: materials.to_vec(), min_count })
+    }
+
+    pub fn assert_temperature_below(&mut self, tick: u64, region: GridRect, max_temp: f32) -> &mut Self {
+        self.add(tick, Assertion::TemperatureBelow { region, max_temp })
+    }
+
+    pub fn assert_no_particles_of(&mut self, tick: u64, material: MaterialType) -> &mut Self {
+        self.add(tick, Assertion::NoParticlesOf { material })
+    }
+
+    /// Evaluate every assertion that has come due, returning the ones that failed now
+    pub fn check(&mut self, simulation: &Simulation) -> &[AssertionFailure] {
+        let already_failed = self.failures.len();
+        while let Some((tick, assertion)) = self.scheduled.get(self.evaluated) {
+            if *tick > simulation.tick() {
+                break;
+            }
+            self.failures.extend(assertion.evaluate(simulation));
+            self.evaluated += 1;
+        }
+        &self.failures[already_failed..]
+    }
+
+    /// True once every scheduled assertion has been evaluated
+    pub fn is_finished(&self) -> bool {
+        self.evaluated == self.scheduled.len()
+    }
+
+    pub fn failures(&self) -> &[AssertionFailure] {
+        &self.failures
+    }
+
+    /// Update `simulation` until every assertion has been evaluated, checking after each
+    /// update
+    pub fn run(&mut self, simulation: &mut Simulation, delta_time: f32) -> Result<(), OracleReport> {
+        self.check(simulation);
+        while !self.is_finished() {
+            simulation.update(delta_time);
+            self.check(simulation);
+        }
+        if self.failures.is_empty() {
+            Ok(())
+        } else {
+            Err(OracleReport { failures: self.failures.clone() })
+        }
+    }
+}
+
+/// Every failure from `Oracle::run`; its `Display` prints them all with their snapshots
+#[derive(Debug, Clone, PartialEq)]
+pub struct OracleReport {
+    pub failures: Vec<AssertionFailure>,
+}
+
+impl std::fmt::Display for OracleReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} assertion(s) failed", self.failures.len())?;
+        for failure in &self.failures {
+            write!(f, "\n{}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for OracleReport {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sand_settles_by_tick() {
+        let mut simulation = Simulation::new(20, 20);
+        for x in 8..12 {
+            simulation.add_particle(x, 0, MaterialType::Sand, None);
+        }
+
+        let floor = GridRect::new(0, 15, 20, 5);
+        let mut oracle = Oracle::new();
+        oracle
+            .assert_region_contains(60, floor, MaterialType::Sand, 4)
+            .assert_no_particles_of(60, MaterialType::Water)
+            .assert_region_contains(0, floor, MaterialType::Sand, 1)
+            .assert_region_contains_any(60, floor, &[MaterialType::Sand, MaterialType::Water], 4)
+            .assert_region_contains_any(60, floor, &[MaterialType::Water, MaterialType::Oil], 1);
+
+        let report = oracle.run(&mut simulation, 1.0 / 60.0).unwrap_err();
+        assert_eq!(simulation.tick(), 60);
+        // The tick 0 check fails as the sand hadn't fallen yet, and there's no liquid at all
+        assert_eq!(report.failures.len(), 2);
+        assert_eq!(report.failures[0].tick, 0);
+        assert!(report.failures[0].message.contains("found 0"), "{}", report);
+        assert!(report.failures[1].message.starts_with("expected at least 1 Water or Oil in"), "{}", report);
+    }
+
+    #[test]
+    fn test_failure_snapshots() {
+        let mut simulation = Simulation::new(10, 10);
+        simulation.add_particle(2, 2, MaterialType::Stone, Some(20.0));
+        simulation.add_particle(3, 2, MaterialType::Stone, Some(500.0));
+        simulation.add_particle(7, 7, MaterialType::Lava, None);
+
+        let failure = Assertion::TemperatureBelow { region: GridRect::new(1, 1, 4, 3), max_temp: 100.0 }
+            .evaluate(&simulation)
+            .unwrap();
+        assert_eq!(failure.snapshot, "(1, 1)..(5, 4)\n....\n.aA.\n....\na = Stone\n");
+        assert!(failure.message.contains("hottest is Stone at 500.0°C at (3, 2)"));
+
+        let failure = Assertion::NoParticlesOf { material: MaterialType::Lava }.evaluate(&simulation).unwrap();
+        assert!(failure.snapshot.contains("..A.."));
+        assert!(failure.to_string().starts_with("tick 0: expected no Lava, found 1"));
+    }
+}
@@ -0,0 +1,233 @@
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::reactions::NEIGHBOR_OFFSETS;
+use crate::simulation::Simulation;
+
+/// Oxygen a flame needs around it; with less it goes out
+pub const FIRE_MIN_OXYGEN: f32 = 0.15;
+/// Oxygen a flame or burning cell draws from the air around it per second
+const BURN_OXYGEN_PER_SEC: f32 = 0.5;
+/// How fast oxygen evens out between neighbouring air cells, per second
+const OXYGEN_DIFFUSION: f32 = 10.0;
+
+/// Oxygen in every cell of a grid, from 0 to 1. It only moves between air cells (empty
+/// or gas), and open air along the grid's edges is always fresh, so fires in the open
+/// keep burning while fires in sealed chambers use up their air and go out.
+#[derive(Debug, Clone)]
+pub struct AirField {
+    width: usize,
+    height: usize,
+    oxygen: Vec<f32>,
+}
+
+impl AirField {
+    /// Fresh air everywhere
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, oxygen: vec![1.0; width * height] }
+    }
+
+    /// Oxygen in cell `(x, y)`; zero outside the grid
+    pub fn level(&self, x: usize, y: usize) -> f32 {
+        if x < self.width && y < self.height {
+            self.oxygen[y * self.width + x]
+        } else {
+            0.0
+        }
+    }
+
+    pub fn set_level(&mut self, x: usize, y: usize, level: f32) {
+        if x < self.width && y < self.height {
+            self.oxygen[y * self.width + x] = level.clamp(0.0, 1.0);
+        }
+    }
+
+    /// The oxygen in rows `top..bottom`; `set_rows` writes it back
+    #[cfg(feature = "parallel")]
+    pub(crate) fn rows(&self, top: usize, bottom: usize) -> AirField {
+        let oxygen = self.oxygen[top * self.width..bottom * self.width].to_vec();
+        AirField { width: self.width, height: bottom - top, oxygen }
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn set_rows(&mut self, top: usize, rows: &AirField) {
+        let start = top * self.width;
+        self.oxygen[start..start + rows.oxygen.len()].copy_from_slice(&rows.oxygen);
+    }
+
+    /// Let oxygen spread between neighbouring air cells for `delta_time` seconds
+    pub fn step(&mut self, delta_time: f32, is_air: impl Fn(usize, usize) -> bool) {
+        // Explicit diffusion is only stable while each cell gives away under a quarter
+        let rate = (OXYGEN_DIFFUSION * delta_time).min(0.24);
+        let previous = self.oxygen.clone();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !is_air(x, y) {
+                    continue;
+                }
+                let index = y * self.width + x;
+                if x == 0 || y == 0 || x + 1 == self.width || y + 1 == self.height {
+                    self.oxygen[index] = 1.0;
+                    continue;
+                }
+                let mut flow = 0.0;
+                for (nx, ny) in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                    if is_air(nx, ny) {
+                        flow += previous[ny * self.width + nx] - previous[index];
+                    }
+                }
+                self.oxygen[index] = (previous[index] + flow * rate).clamp(0.0, 1.0);
+            }
+        }
+    }
+}
+
+impl Simulation {
+    /// Track oxygen so fires use up the air around them; fresh air fills the grid
+    pub fn enable_oxygen(&mut self) {
+        self.air = Some(AirField::new(self.width, self.height));
+    }
+
+    pub fn disable_oxygen(&mut self) {
+        self.air = None;
+    }
+
+    /// Oxygen levels; None while fires burn regardless of air
+    pub fn air(&self) -> Option<&AirField> {
+        self.air.as_ref()
+    }
+
+    /// Best oxygen level among the air cells at and around `(x, y)`; always 1 while
+    /// oxygen is disabled
+    pub fn oxygen_at(&self, x: usize, y: usize) -> f32 {
+        match &self.air {
+            Some(air) if self.is_air(x, y) => self.oxygen_around(x, y).max(air.level(x, y)),
+            _ => self.oxygen_around(x, y),
+        }
+    }
+
+    /// Best oxygen level among the air cells around `(x, y)`, leaving out the cell
+    /// itself, which a particle being updated has been lifted out of
+    pub(crate) fn oxygen_around(&self, x: usize, y: usize) -> f32 {
+        let Some(air) = &self.air else {
+            return 1.0;
+        };
+        NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| self.air_cell(x, y, dx, dy))
+            .map(|(nx, ny)| air.level(nx, ny))
+            .fold(0.0, f32::max)
+    }
+
+    pub(crate) fn step_air(&mut self, delta_time: f32) {
+        if let Some(mut air) = self.air.take() {
+            air.step(delta_time, |x, y| self.is_air(x, y));
+            self.air = Some(air);
+        }
+    }
+
+    /// Draw a flame's or burning cell's share of oxygen from the air around it for
+    /// `delta_time` seconds, returning the level it burned at
+    pub(crate) fn breathe(&mut self, particle: &Particle, delta_time: f32) -> f32 {
+        let oxygen = self.oxygen_around(particle.x, particle.y);
+        if self.air.is_none() {
+            return oxygen;
+        }
+        let cells: Vec<_> = NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| self.air_cell(particle.x, particle.y, dx, dy))
+            .collect();
+        if let Some(air) = &mut self.air {
+            let share = BURN_OXYGEN_PER_SEC * delta_time / cells.len().max(1) as f32;
+            for (x, y) in cells {
+                air.set_level(x, y, air.level(x, y) - share);
+            }
+        }
+        oxygen
+    }
+
+    fn air_cell(&self, x: usize, y: usize, dx: i32, dy: i32) -> Option<(usize, usize)> {
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        (self.is_valid(nx, ny) && self.is_air(nx as usize, ny as usize)).then_some((nx as usize, ny as usize))
+    }
+
+    fn is_air(&self, x: usize, y: usize) -> bool {
+        self.get_particle(x, y).is_none_or(|particle| {
+            particle.material_type == MaterialType::Empty
+                || self.materials().get(particle.material_type).is_gas(particle.material_type)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A shelf of wood hot enough to catch over a pocket of air, between stone walls,
+    /// with a stone lid resting on it when `sealed`
+    fn wood_fire(sealed: bool) -> Simulation {
+        let mut simulation = Simulation::new(24, 24);
+        simulation.set_seed(9);
+        simulation.enable_oxygen();
+        for y in 20..24 {
+            simulation.add_particle(8, y, MaterialType::Stone, None);
+            simulation.add_particle(13, y, MaterialType::Stone, None);
+        }
+        for x in 9..=12 {
+            simulation.add_particle(x, 23, MaterialType::Stone, None);
+            simulation.add_particle(x, 21, MaterialType::Wood, Some(350.0));
+            if sealed {
+                simulation.add_particle(x, 20, MaterialType::Stone, None);
+            }
+        }
+        simulation
+    }
+
+    fn count(simulation: &Simulation, material_type: MaterialType) -> usize {
+        simulation.iter_particles().filter(|p| p.material_type == material_type).count()
+    }
+
+    #[test]
+    fn test_open_fire_burns_wood_to_char() {
+        let mut simulation = wood_fire(false);
+        let mut smoked = false;
+        for _ in 0..600 {
+            simulation.update(1.0 / 60.0);
+            smoked |= count(&simulation, MaterialType::Smoke) > 0;
+        }
+        assert!(smoked);
+        assert_eq!(count(&simulation, MaterialType::Wood), 0);
+        assert!(count(&simulation, MaterialType::Coal) + count(&simulation, MaterialType::Ash) > 0);
+    }
+
+    #[test]
+    fn test_sealed_fire_suffocates() {
+        let mut simulation = wood_fire(true);
+        let mut caught = false;
+        for _ in 0..600 {
+            simulation.update(1.0 / 60.0);
+            caught |= simulation.iter_particles().any(|p| p.burning);
+        }
+        assert!(caught);
+        assert!(simulation.oxygen_at(10, 22) < FIRE_MIN_OXYGEN);
+        assert_eq!(count(&simulation, MaterialType::Fire), 0);
+        // The wood went out before its fuel ran out
+        assert!(count(&simulation, MaterialType::Wood) > 0);
+        assert!(simulation.iter_particles().all(|p| !p.burning));
+    }
+
+    #[test]
+    fn test_air_diffuses_between_open_cells_only() {
+        let mut air = AirField::new(8, 8);
+        air.set_level(3, 3, 0.0);
+        air.set_level(5, 3, 0.0);
+        // Column 4 is a wall
+        for _ in 0..60 {
+            air.step(1.0 / 60.0, |x, _| x != 4);
+        }
+        assert!(air.level(3, 3) > 0.5);
+        let mut sealed = AirField::new(8, 8);
+        sealed.set_level(3, 3, 0.0);
+        for _ in 0..60 {
+            sealed.step(1.0 / 60.0, |x, y| (x, y) == (3, 3));
+        }
+        assert_eq!(sealed.level(3, 3), 0.0);
+    }
+}
@@ -0,0 +1,321 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::{Material, MaterialKind, MaterialType};
+use crate::render::{TextRenderer, BYTES_PER_PIXEL, GLYPH_HEIGHT};
+
+/// Width in pixels of the palette panel
+pub const PALETTE_WIDTH: usize = 100;
+/// Height in pixels of each palette row: the search box, the tabs and every material
+pub const PALETTE_ROW_HEIGHT: usize = GLYPH_HEIGHT + 4;
+/// Longest search the palette keeps
+const MAX_SEARCH_LEN: usize = 16;
+const PANEL_COLOR: [u8; 3] = [24, 24, 30];
+const HIGHLIGHT_COLOR: [u8; 3] = [70, 70, 100];
+const HINT_COLOR: [u8; 3] = [120, 120, 130];
+
+/// Palette tab a material is listed under, by how it moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaletteCategory {
+    Powders,
+    Liquids,
+    Gases,
+    Solids,
+    /// Materials that never move: generators, plant parts, the eraser and the like
+    Special,
+}
+
+impl PaletteCategory {
+    /// Every category, in tab order
+    pub const ALL: [PaletteCategory; 5] = [
+        PaletteCategory::Powders, PaletteCategory::Liquids, PaletteCategory::Gases,
+        PaletteCategory::Solids, PaletteCategory::Special,
+    ];
+
+    pub fn of(material: &Material) -> Self {
+        match material.kind {
+            MaterialKind::Powder => PaletteCategory::Powders,
+            MaterialKind::Liquid => PaletteCategory::Liquids,
+            MaterialKind::Gas => PaletteCategory::Gases,
+            MaterialKind::Solid => PaletteCategory::Solids,
+            MaterialKind::Static => PaletteCategory::Special,
+        }
+    }
+
+    /// Short name drawn on the category's tab
+    pub fn label(self) -> &'static str {
+        match self {
+            PaletteCategory::Powders => "POW",
+            PaletteCategory::Liquids => "LIQ",
+            PaletteCategory::Gases => "GAS",
+            PaletteCategory::Solids => "SOL",
+            PaletteCategory::Special => "SPC",
+        }
+    }
+}
+
+/// What lies under a point of the palette panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteHit {
+    Search,
+    Tab(PaletteCategory),
+    Material(MaterialType),
+}
+
+/// A scrollable panel listing the materials a registry knows, one category tab at a
+/// time or, while a search is typed, every material whose name matches it. Drawn over a
+/// rendered frame like the other overlays, with a tooltip of the properties of the
+/// material under the mouse.
+#[derive(Debug, Clone)]
+pub struct MaterialPalette {
+    /// Top-left corner of the panel in frame pixels
+    pub x: usize,
+    pub y: usize,
+    pub height: usize,
+    pub category: PaletteCategory,
+    search: String,
+    // First listed material shown
+    scroll: usize,
+    hover: Option<(usize, usize)>,
+    text: TextRenderer,
+}
+
+impl MaterialPalette {
+    /// A `PALETTE_WIDTH` wide panel `height` pixels tall with its top-left corner at `(x, y)`
+    pub fn new(x: usize, y: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            height,
+            category: PaletteCategory::Powders,
+            search: String::new(),
+            scroll: 0,
+            hover: None,
+            text: TextRenderer::new(),
+        }
+    }
+
+    pub fn search(&self) -> &str {
+        &self.search
+    }
+
+    /// Type a character into the search; false for characters the font can't draw
+    pub fn push_search(&mut self, c: char) -> bool {
+        if !(c.is_ascii_alphanumeric() || c == ' ') || self.search.len() >= MAX_SEARCH_LEN {
+            return false;
+        }
+        self.search.push(c);
+        self.scroll = 0;
+        true
+    }
+
+    /// Delete the last character of the search
+    pub fn pop_search(&mut self) {
+        self.search.pop();
+        self.scroll = 0;
+    }
+
+    pub fn set_category(&mut self, category: PaletteCategory) {
+        self.category = category;
+        self.search.clear();
+        self.scroll = 0;
+    }
+
+    /// Materials listed, in registry order: those in the current category, or any whose
+    /// name contains the search, ignoring case
+    pub fn entries(&self, registry: &MaterialRegistry) -> Vec<MaterialType> {
+        let search = self.search.trim().to_ascii_lowercase();
+        registry.material_types()
+            .filter(|&material_type| material_type != MaterialType::Empty)
+            .filter(|&material_type| {
+                let material = registry.get(material_type);
+                match search.is_empty() {
+                    true => PaletteCategory::of(material) == self.category,
+                    false => material.name.to_ascii_lowercase().contains(&search),
+                }
+            })
+            .collect()
+    }
+
+    /// How many materials fit below the search box and tabs
+    pub fn visible_rows(&self) -> usize {
+        (self.height / PALETTE_ROW_HEIGHT).saturating_sub(2)
+    }
+
+    /// Scroll the list by `rows`, down for positive, stopping at either end
+    pub fn scroll_by(&mut self, rows: i32, registry: &MaterialRegistry) {
+        let last = self.entries(registry).len().saturating_sub(self.visible_rows());
+        self.scroll = (self.scroll as i64 + rows as i64).clamp(0, last as i64) as usize;
+    }
+
+    /// Whether frame pixel `(x, y)` lies on the panel
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + PALETTE_WIDTH).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+
+    pub fn hit(&self, registry: &MaterialRegistry, x: usize, y: usize) -> Option<PaletteHit> {
+        if !self.contains(x, y) {
+            return None;
+        }
+        match (y - self.y) / PALETTE_ROW_HEIGHT {
+            0 => Some(PaletteHit::Search),
+            1 => {
+                let tab = (x - self.x) * PaletteCategory::ALL.len() / PALETTE_WIDTH;
+                Some(PaletteHit::Tab(PaletteCategory::ALL[tab]))
+            }
+            row => self.entries(registry).get(self.scroll + row - 2).map(|&material_type| PaletteHit::Material(material_type)),
+        }
+    }
+
+    /// Handle a click at frame pixel `(x, y)`: switch to a clicked tab, or return the
+    /// clicked material for the caller to select
+    pub fn click(&mut self, registry: &MaterialRegistry, x: usize, y: usize) -> Option<MaterialType> {
+        match self.hit(registry, x, y)? {
+            PaletteHit::Tab(category) => {
+                self.set_category(category);
+                None
+            }
+            PaletteHit::Material(material_type) => Some(material_type),
+            PaletteHit::Search => None,
+        }
+    }
+
+    /// Where the mouse is in frame pixels, for the tooltip; None once it leaves the frame
+    pub fn set_hover(&mut self, position: Option<(usize, usize)>) {
+        self.hover = position;
+    }
+
+    /// Tooltip lines describing a material's properties
+    pub fn describe(material: &Material) -> Vec<String> {
+        let mut lines = vec![material.name.clone(), format!("{:?}", material.kind), format!("Density {}", material.density)];
+        if let Some(temp) = material.melt_temp {
+            lines.push(format!("Melts at {}C", temp));
+        }
+        if let Some(temp) = material.boil_temp {
+            lines.push(format!("Boils at {}C", temp));
+        }
+        if let Some(temp) = material.freeze_temp {
+            lines.push(format!("Freezes at {}C", temp));
+        }
+        if material.flammability > 0.0 {
+            lines.push(format!("Flammability {}", material.flammability));
+        }
+        lines
+    }
+
+    /// Draw the panel over a `width` x `height` frame, highlighting `selected`, and the
+    /// tooltip of the material under the mouse beside it
+    pub fn draw(&self, frame: &mut [u8], width: usize, height: usize, registry: &MaterialRegistry, selected: MaterialType) {
+        let row_top = |row: usize| self.y + row * PALETTE_ROW_HEIGHT;
+        fill_rect(frame, width, height, (self.x, self.y), (PALETTE_WIDTH, self.height), PANEL_COLOR);
+
+        let mut hint = self.text.clone();
+        if self.search.is_empty() {
+            hint.color = HINT_COLOR;
+            hint.draw_text(frame, width, height, self.x as i32 + 2, row_top(0) as i32 + 2, "Type to search");
+        } else {
+            hint.draw_text(frame, width, height, self.x as i32 + 2, row_top(0) as i32 + 2, &format!("Find: {}", self.search));
+        }
+
+        let tab_width = PALETTE_WIDTH / PaletteCategory::ALL.len();
+        for (index, category) in PaletteCategory::ALL.into_iter().enumerate() {
+            let left = self.x + index * tab_width;
+            if self.search.is_empty() && category == self.category {
+                fill_rect(frame, width, height, (left, row_top(1)), (tab_width, PALETTE_ROW_HEIGHT), HIGHLIGHT_COLOR);
+            }
+            self.text.draw_text(frame, width, height, left as i32 + 2, row_top(1) as i32 + 2, category.label());
+        }
+
+        let entries = self.entries(registry);
+        for (row, &material_type) in entries.iter().skip(self.scroll).take(self.visible_rows()).enumerate() {
+            let top = row_top(row + 2);
+            if material_type == selected {
+                fill_rect(frame, width, height, (self.x, top), (PALETTE_WIDTH, PALETTE_ROW_HEIGHT), HIGHLIGHT_COLOR);
+            }
+            let material = registry.get(material_type);
+            fill_rect(frame, width, height, (self.x + 2, top + 2), (GLYPH_HEIGHT, GLYPH_HEIGHT), material.base_color);
+            self.text.draw_text(frame, width, height, (self.x + GLYPH_HEIGHT + 5) as i32, top as i32 + 2, &material.name);
+        }
+
+        let hovered = self.hover.and_then(|(x, y)| match self.hit(registry, x, y) {
+            Some(PaletteHit::Material(material_type)) => Some((material_type, y)),
+            _ => None,
+        });
+        if let Some((material_type, y)) = hovered {
+            let lines = Self::describe(registry.get(material_type));
+            let line_height = GLYPH_HEIGHT + 2;
+            let box_width = lines.iter().map(|line| TextRenderer::text_width(line)).max().unwrap_or(0) + 5;
+            let left = self.x + PALETTE_WIDTH + 2;
+            fill_rect(frame, width, height, (left, y), (box_width, lines.len() * line_height + 3), PANEL_COLOR);
+            for (index, line) in lines.iter().enumerate() {
+                self.text.draw_text(frame, width, height, left as i32 + 2, (y + 2 + index * line_height) as i32, line);
+            }
+        }
+    }
+}
+
+/// Fill a `size` rectangle from its top-left corner `(x, y)`, clipped to a `width` x
+/// `height` frame
+fn fill_rect(frame: &mut [u8], width: usize, height: usize, (x, y): (usize, usize), size: (usize, usize), color: [u8; 3]) {
+    for py in y..(y + size.1).min(height) {
+        for px in x..(x + size.0).min(width) {
+            let offset = (py * width + px) * BYTES_PER_PIXEL;
+            frame[offset..offset + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material_registry::MaterialDefinition;
+    use crate::render::MaterialRenderer;
+
+    #[test]
+    fn test_palette_lists_categories_and_searches_every_one() {
+        let mut registry = MaterialRegistry::new();
+        let definition = MaterialDefinition { name: "Quicksand".into(), kind: Some(MaterialKind::Liquid), ..Default::default() };
+        let quicksand = registry.register(&definition).unwrap();
+        let mut palette = MaterialPalette::new(0, 0, PALETTE_ROW_HEIGHT * 6);
+
+        let powders = palette.entries(&registry);
+        assert!(powders.contains(&MaterialType::Sand) && powders.contains(&MaterialType::Salt));
+        assert!(!powders.contains(&MaterialType::Water));
+
+        // Clicking the Liquids tab switches to it; custom materials are listed too
+        let tab_x = PALETTE_WIDTH / PaletteCategory::ALL.len() + 1;
+        assert_eq!(palette.click(&registry, tab_x, PALETTE_ROW_HEIGHT + 1), None);
+        assert_eq!(palette.category, PaletteCategory::Liquids);
+        assert!(palette.entries(&registry).contains(&quicksand));
+
+        for c in "SAND".chars() {
+            palette.push_search(c);
+        }
+        let found = palette.entries(&registry);
+        assert_eq!(found, vec![MaterialType::Sand, MaterialType::Sandstone, MaterialType::WetSand, quicksand]);
+        assert_eq!(palette.click(&registry, 10, PALETTE_ROW_HEIGHT * 2 + 1), Some(MaterialType::Sand));
+
+        // Four rows fit, so the list scrolls no further than its last one
+        palette.scroll_by(10, &registry);
+        assert_eq!(palette.click(&registry, 10, PALETTE_ROW_HEIGHT * 2 + 1), Some(MaterialType::Sand));
+        palette.set_category(PaletteCategory::Powders);
+        palette.scroll_by(10, &registry);
+        assert_ne!(palette.click(&registry, 10, PALETTE_ROW_HEIGHT * 2 + 1), Some(MaterialType::Sand));
+    }
+
+    #[test]
+    fn test_palette_draws_selection_and_tooltip() {
+        let registry = MaterialRegistry::new();
+        let (width, height) = (200, 100);
+        let mut palette = MaterialPalette::new(0, 0, height);
+        let sand_row = PALETTE_ROW_HEIGHT * 2;
+        palette.set_hover(Some((PALETTE_WIDTH - 1, sand_row + 1)));
+
+        let mut frame = vec![0; MaterialRenderer::frame_len(width, height)];
+        palette.draw(&mut frame, width, height, &registry, MaterialType::Sand);
+        let pixel = |x: usize, y: usize| &frame[(y * width + x) * BYTES_PER_PIXEL..(y * width + x) * BYTES_PER_PIXEL + 3];
+        assert_eq!(pixel(PALETTE_WIDTH - 1, sand_row), HIGHLIGHT_COLOR);
+        assert_eq!(pixel(3, sand_row + 3), [194, 178, 128]);
+        assert_eq!(pixel(PALETTE_WIDTH + 2, sand_row + 1), PANEL_COLOR);
+        assert_eq!(pixel(width - 1, height - 1), [0, 0, 0]);
+        assert!(MaterialPalette::describe(registry.get(MaterialType::Sand)).contains(&"Melts at 1500C".to_string()));
+    }
+}
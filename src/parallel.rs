@@ -0,0 +1,126 @@
+use crate::bounds::WorldBounds;
+use crate::simulation::{Simulation, SIM_CHUNK_SIZE};
+use rand::Rng;
+use rayon::prelude::*;
+
+/// Rows in each band `update_parallel` splits the grid into
+pub const PARALLEL_BAND_HEIGHT: usize = 4 * SIM_CHUNK_SIZE;
+/// Rows past either edge of its own that a band reads and can move particles into.
+/// Anything reaching further in one tick, such as a blast wider than this, is cut off
+/// at the edge of the band.
+pub const PARALLEL_BAND_MARGIN: usize = 16;
+
+impl Simulation {
+    /// `update` with the grid split into horizontal bands of `PARALLEL_BAND_HEIGHT` rows,
+    /// updated on rayon's thread pool. Each band works on its own copy of its rows plus
+    /// `PARALLEL_BAND_MARGIN` on either side, so bands running at once never share a
+    /// cell: every even band runs first, then every odd one, and each pass is written
+    /// back before the next starts. A seeded simulation replays exactly however many
+    /// threads run it, though it makes different moves from `update`. A wrapped world
+    /// updates serially, since a band can't reach across the top and bottom edges.
+    pub fn update_parallel(&mut self, delta_time: f32) {
+        if self.bounds() == WorldBounds::Wrap {
+            return self.update(delta_time);
+        }
+        self.finish_budgeted_tick();
+        self.step_surroundings(delta_time);
+        let active = self.begin_particle_update(delta_time);
+
+        // Bands hold whole chunk rows, so each keeps its cells in update order
+        let band_count = self.height.div_ceil(PARALLEL_BAND_HEIGHT);
+        let mut bands = vec![Vec::new(); band_count];
+        for &(x, y) in &active {
+            bands[y / PARALLEL_BAND_HEIGHT].push((x, y));
+        }
+        // Drawn in band order up front so the outcome doesn't depend on scheduling
+        let seeds: Vec<u64> = (0..band_count).map(|_| self.rng.gen()).collect();
+
+        for parity in [0, 1] {
+            let jobs: Vec<_> = bands.iter().zip(&seeds).enumerate()
+                .filter(|(band, (cells, _))| band % 2 == parity && !cells.is_empty())
+                .collect();
+            let simulation = &*self;
+            let updated: Vec<_> = jobs.into_par_iter()
+                .map(|(band, (cells, &seed))| {
+                    let top = (band * PARALLEL_BAND_HEIGHT).saturating_sub(PARALLEL_BAND_MARGIN);
+                    let bottom = ((band + 1) * PARALLEL_BAND_HEIGHT + PARALLEL_BAND_MARGIN).min(simulation.height);
+                    let mut window = simulation.band(top, bottom, seed);
+                    let cells: Vec<_> = cells.iter().map(|&(x, y)| (x, y - top)).collect();
+                    window.update_cells(&cells, delta_time);
+                    (top, window)
+                })
+                .collect();
+            for (top, window) in updated {
+                self.merge_band(window, top);
+            }
+        }
+
+        self.finish_particle_update(active);
+        self.step_overlays(delta_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    /// Sand and water poured over a stone floor, tall enough for several bands
+    fn poured(seed: u64) -> Simulation {
+        let mut simulation = Simulation::new(48, 200);
+        simulation.set_seed(seed);
+        for x in 0..48 {
+            simulation.add_particle(x, 199, MaterialType::Stone, None);
+        }
+        for y in 0..120 {
+            simulation.add_particle(10, y, MaterialType::Sand, None);
+            simulation.add_particle(30, y, MaterialType::Water, None);
+        }
+        simulation
+    }
+
+    fn cells(simulation: &Simulation) -> Vec<(usize, usize, MaterialType)> {
+        simulation.iter_particles().map(|p| (p.x, p.y, p.material_type)).collect()
+    }
+
+    #[test]
+    fn test_bands_hand_particles_across_their_edges() {
+        let mut simulation = poured(3);
+        for _ in 0..400 {
+            simulation.update_parallel(1.0 / 60.0);
+        }
+
+        // Sand that landed in the water soaked some of it up
+        let water = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Water).count();
+        assert!(water < 120);
+        assert_eq!(simulation.iter_particles().count(), 48 + 120 + water);
+        assert_eq!(simulation.particle_count(), 48 + 120 + water);
+        // Everything fell through the bands onto the floor; positions stayed in step
+        for y in 0..simulation.height {
+            for x in 0..simulation.width {
+                if let Some(particle) = simulation.get_particle(x, y) {
+                    assert_eq!((particle.x, particle.y), (x, y));
+                }
+            }
+        }
+        assert!(simulation.iter_particles().all(|p| p.y >= PARALLEL_BAND_HEIGHT));
+        let sand = simulation.iter_particles().filter(|p| matches!(p.material_type, MaterialType::Sand | MaterialType::WetSand)).count();
+        assert_eq!(sand, 120);
+    }
+
+    #[test]
+    fn test_same_seed_replays_on_any_thread_count() {
+        let run = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+            pool.install(|| {
+                let mut simulation = poured(9);
+                simulation.enable_motion_tracking();
+                for _ in 0..120 {
+                    simulation.update_parallel(1.0 / 60.0);
+                }
+                (cells(&simulation), simulation.last_moves().len())
+            })
+        };
+        assert_eq!(run(1), run(4));
+    }
+}
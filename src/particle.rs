@@ -1,9 +1,15 @@
+use crate::damage::{CRACK_DARKENING, CRACK_STAGES};
 use crate::materials::{get_material_properties, Material, MaterialType};
-use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 const AMBIENT_TEMP: f32 = 20.0;
 const MAX_TEMP: f32 = 3000.0;
+const DILUTED_ACID_COLOR: [u8; 3] = [60, 150, 170];
+const CONTAMINATED_WATER_COLOR: [u8; 3] = [90, 160, 80];
+/// Solids start to glow at this temperature and are white-hot 1000 degrees above it
+const GLOW_START_TEMP: f32 = 500.0;
+/// Brightness left to a powder soaked by neighbouring water
+const WET_DARKENING: f32 = 0.7;
 
 #[derive(Debug, Clone)]
 struct CachedProperties {
@@ -32,6 +38,35 @@ pub struct Particle {
     // Performance optimizations from reference project
     pub dynamic: bool, // Whether this particle needs frequent updates
     pub settled_frames: u8, // How many frames it's been stationary
+    // Impulse velocity in cells per tick; zero for particles moved only by the cellular rules
+    #[serde(default)]
+    pub vx: f32,
+    #[serde(default)]
+    pub vy: f32,
+    // Seconds spent asleep under a heavy enough column; drives pile compaction
+    #[serde(default)]
+    pub buried_time: f32,
+    // Material-specific state: how diluted an Acid particle is (0 is full strength), how
+    // contaminated a Water particle is or how much salt a Salt Water particle carries, all
+    // 0..=1, the cells a growing stem or root tip has left, the fraction of its health a
+    // solid with `max_health` has lost, and the hue (0..1) of a lit Firework and of the
+    // Sparks it bursts into; zero for everything else
+    #[serde(default)]
+    pub payload: f32,
+    // Thermal stress from recent temperature swings, in degrees; cracks brittle solids
+    #[serde(default)]
+    pub stress: f32,
+    // Latent heat taken in toward melting or boiling (positive) or given off toward
+    // freezing or condensing (negative) so far, in degrees
+    #[serde(default)]
+    pub latent_heat: f32,
+    // Shade picked from where the particle was created; it travels with the particle, so
+    // piles keep their grain instead of shimmering as they move
+    #[serde(default = "neutral_variant")]
+    pub variant: u8,
+    // Whether a powder is touching water; darkens its color
+    #[serde(default)]
+    pub wet: bool,
     #[serde(skip)]
     color_cache: Option<[u8; 3]>,
     #[serde(skip)]
@@ -44,7 +79,9 @@ impl Particle {
         // Static materials that don't need frequent updates
         !matches!(material_type, 
             MaterialType::Empty | MaterialType::Stone | MaterialType::Generator |
-            MaterialType::Glass | MaterialType::Ice | MaterialType::Wood
+            MaterialType::Glass | MaterialType::Ice | MaterialType::Wood |
+            MaterialType::Sandstone | MaterialType::Stem | MaterialType::Root | MaterialType::Door |
+            MaterialType::Piston
         )
     }
 
@@ -63,6 +100,14 @@ impl Particle {
             burning: false,
             dynamic: Self::is_material_dynamic(material_type),
             settled_frames: 0,
+            vx: 0.0,
+            vy: 0.0,
+            buried_time: 0.0,
+            payload: 0.0,
+            stress: 0.0,
+            latent_heat: 0.0,
+            variant: Self::creation_variant(x, y),
+            wet: false,
             color_cache: None,
             properties_cache: None,
         };
@@ -70,6 +115,40 @@ impl Particle {
         particle
     }
 
+    /// Texture variant `new` gives a particle created at `(x, y)`
+    pub(crate) fn creation_variant(x: usize, y: usize) -> u8 {
+        (flicker_noise(x, y, 0.0) * 255.0) as u8
+    }
+
+    /// Every field at its default and none of the material setup `new` does; for storage
+    /// that fills the fields back in itself
+    pub(crate) fn blank(x: usize, y: usize, material_type: MaterialType) -> Self {
+        Self {
+            x,
+            y,
+            material_type,
+            temp: AMBIENT_TEMP,
+            initial_temp: AMBIENT_TEMP,
+            processed: false,
+            life: None,
+            time_in_state: 0.0,
+            moved_this_step: false,
+            burning: false,
+            dynamic: false,
+            settled_frames: 0,
+            vx: 0.0,
+            vy: 0.0,
+            buried_time: 0.0,
+            payload: 0.0,
+            stress: 0.0,
+            latent_heat: 0.0,
+            variant: neutral_variant(),
+            wet: false,
+            color_cache: None,
+            properties_cache: None,
+        }
+    }
+
     pub fn init_properties(&mut self) {
         let props = get_material_properties(self.material_type);
         
@@ -79,7 +158,7 @@ impl Particle {
             MaterialType::Lava => self.temp.max(1800.0),
             MaterialType::Steam => self.temp.max(101.0),
             MaterialType::Generator => self.temp.max(300.0),
-            MaterialType::Ice => self.temp.min(-5.0),
+            MaterialType::Ice | MaterialType::Snow => self.temp.min(-5.0),
             MaterialType::Sand if self.temp > 1500.0 => self.temp.max(1500.0),
             MaterialType::Stone if self.temp > 1000.0 => self.temp.max(1000.0),
             _ => self.temp,
@@ -113,6 +192,60 @@ impl Particle {
         self.properties_cache.as_ref().unwrap()
     }
 
+    #[inline(always)]
+    pub fn has_velocity(&self) -> bool {
+        self.vx != 0.0 || self.vy != 0.0
+    }
+
+    /// Add an impulse and wake the particle so the simulation processes it
+    pub fn apply_impulse(&mut self, vx: f32, vy: f32) {
+        self.vx += vx;
+        self.vy += vy;
+        self.settled_frames = 0;
+    }
+
+    /// Strength of an Acid particle, from 1 for fresh acid down to 0 when fully diluted
+    #[inline(always)]
+    pub fn concentration(&self) -> f32 {
+        (1.0 - self.payload).clamp(0.0, 1.0)
+    }
+
+    pub fn set_concentration(&mut self, concentration: f32) {
+        self.payload = (1.0 - concentration).clamp(0.0, 1.0);
+        self.invalidate_color_cache();
+    }
+
+    /// Water left behind by diluted acid
+    #[inline(always)]
+    pub fn is_contaminated(&self) -> bool {
+        self.material_type == MaterialType::Water && self.payload > 0.0
+    }
+
+    /// Fraction of a Salt Water particle's capacity taken up by dissolved salt, 0..=1
+    #[inline(always)]
+    pub fn salinity(&self) -> f32 {
+        if self.material_type == MaterialType::SaltWater { self.payload.clamp(0.0, 1.0) } else { 0.0 }
+    }
+
+    /// Fraction of its health a solid has lost, 0..=1; zero for materials without health
+    #[inline(always)]
+    pub fn damage(&self, material: &Material) -> f32 {
+        if material.max_health > 0.0 { self.payload.clamp(0.0, 1.0) } else { 0.0 }
+    }
+
+    /// How cracked a damaged solid looks, from 0 for intact up to `CRACK_STAGES`
+    pub fn crack_stage(&self, material: &Material) -> u8 {
+        ((self.damage(material) * (CRACK_STAGES + 1) as f32) as u8).min(CRACK_STAGES)
+    }
+
+    /// Mark a powder as soaked or dried out, refreshing its color when that changes
+    pub fn set_wet(&mut self, wet: bool) {
+        if self.wet != wet {
+            self.wet = wet;
+            self.invalidate_color_cache();
+        }
+    }
+
     pub fn invalidate_color_cache(&mut self) {
         self.color_cache = None;
     }
@@ -126,7 +259,27 @@ impl Particle {
             return cached_color;
         }
 
-        let props = self.get_properties();
+        let color = self.compute_color();
+        self.color_cache = Some(color);
+        color
+    }
+
+    /// Color for read-only contexts (renderers); uses the cache when present but never fills it
+    pub fn color(&self) -> [u8; 3] {
+        self.color_cache.unwrap_or_else(|| self.compute_color())
+    }
+
+    /// Color computed from explicit material properties, e.g. a simulation's registry entry.
+    /// Ignores the cache, which is always filled from the built-in table.
+    pub fn color_with(&self, props: &Material) -> [u8; 3] {
+        self.compute_color_with(props)
+    }
+
+    fn compute_color(&self) -> [u8; 3] {
+        self.compute_color_with(&self.get_properties())
+    }
+
+    fn compute_color_with(&self, props: &Material) -> [u8; 3] {
         let mut r = props.base_color[0] as f32;
         let mut g = props.base_color[1] as f32;
         let mut b = props.base_color[2] as f32;
@@ -134,8 +287,7 @@ impl Particle {
         if self.material_type != MaterialType::Empty {
             match self.material_type {
                 MaterialType::Fire => {
-                    let mut rng = rand::thread_rng();
-                    let flicker = rng.gen_range(0.85..1.15);
+                    let flicker = 0.85 + 0.3 * flicker_noise(self.x, self.y, self.time_in_state);
                     let temp_factor = ((self.temp - 500.0) / 600.0).max(0.0).min(1.0);
                     r = (props.base_color[0] as f32 * flicker + temp_factor * 60.0).min(255.0);
                     g = (props.base_color[1] as f32 * flicker * (1.0 - temp_factor * 0.6)).min(255.0);
@@ -167,7 +319,18 @@ impl Particle {
                         }
                     }
                 }
-                MaterialType::Fuse if self.burning => {
+                MaterialType::Spark => {
+                    // The firework's hue, dimming as the spark burns out
+                    let hue = hue_color(self.payload);
+                    let fade = match (self.life, props.life_seconds) {
+                        (Some(life), Some(max_life)) if max_life > 0.0 => (life / max_life).clamp(0.2, 1.0),
+                        _ => 1.0,
+                    };
+                    r = hue[0] * fade;
+                    g = hue[1] * fade;
+                    b = hue[2] * fade;
+                }
+                MaterialType::Fuse | MaterialType::Firework if self.burning || self.life.is_some() => {
                     r = (r + 100.0).min(255.0);
                     g = (g + 50.0).min(255.0);
                     b = (b - 20.0).max(0.0);
@@ -184,13 +347,40 @@ impl Particle {
                         g = (g + temp_factor * 15.0).max(0.0).min(255.0);
                         b = (b + temp_factor * 10.0 - temp_factor.abs() * 15.0).max(0.0).min(255.0);
                     }
+                    // Hot solids glow dull red, then orange, then yellow-white
+                    let glow = ((self.temp - GLOW_START_TEMP) / 1000.0).clamp(0.0, 1.0);
+                    if glow > 0.0 && !props.is_liquid(self.material_type) && !props.is_gas(self.material_type) {
+                        let hot = [255.0, 60.0 + 180.0 * glow, 20.0 + 160.0 * glow * glow];
+                        let amount = (glow * 2.0).min(0.85);
+                        r += (hot[0] - r) * amount;
+                        g += (hot[1] - g) * amount;
+                        b += (hot[2] - b) * amount;
+                    }
                 }
             }
+
+            // Grain: each particle keeps a slightly lighter or darker shade of its material
+            let grain = 1.0 + texture_amount(self.material_type) * (self.variant as f32 / 127.5 - 1.0);
+            let shade = if self.wet { grain * WET_DARKENING } else { grain };
+            let shade = shade * (1.0 - CRACK_DARKENING * self.crack_stage(props) as f32);
+            r = (r * shade).min(255.0);
+            g = (g * shade).min(255.0);
+            b = (b * shade).min(255.0);
         }
 
-        let color = [r as u8, g as u8, b as u8];
-        self.color_cache = Some(color);
-        color
+        // Weak acid fades toward water and contaminated water toward acid
+        let tint = match self.material_type {
+            MaterialType::Acid => Some((DILUTED_ACID_COLOR, self.payload * 0.6)),
+            MaterialType::Water => Some((CONTAMINATED_WATER_COLOR, self.payload * 0.5)),
+            _ => None,
+        };
+        if let Some((target, amount)) = tint.filter(|(_, amount)| *amount > 0.0) {
+            r += (target[0] as f32 - r) * amount;
+            g += (target[1] as f32 - g) * amount;
+            b += (target[2] as f32 - b) * amount;
+        }
+
+        [r as u8, g as u8, b as u8]
     }
 
     pub fn change_type(&mut self, new_type: MaterialType, new_temp: Option<f32>) {
@@ -206,6 +396,9 @@ impl Particle {
         // Update dynamic flag when material changes
         self.dynamic = Self::is_material_dynamic(new_type);
         self.settled_frames = 0; // Reset settled counter on material change
+        self.payload = 0.0;
+        self.stress = 0.0;
+        self.latent_heat = 0.0;
         
         // Invalidate caches before changing properties
         self.invalidate_color_cache();
@@ -222,14 +415,14 @@ impl Particle {
             let needs_phase_temp_adjust = !matches!(
                 new_type,
                 MaterialType::Fire | MaterialType::Lava | MaterialType::Steam | 
-                MaterialType::Generator | MaterialType::Ice
+                MaterialType::Generator | MaterialType::Ice | MaterialType::Snow
             );
 
             if needs_phase_temp_adjust {
                 self.temp = match new_type {
                     MaterialType::Water => match self.material_type {
-                        MaterialType::Steam => (old_temp - 20.0).max(AMBIENT_TEMP).min(99.0),
-                        MaterialType::Ice => (old_temp + 5.0).max(1.0).min(AMBIENT_TEMP),
+                        MaterialType::Steam => (old_temp - 20.0).clamp(AMBIENT_TEMP, 99.0),
+                        MaterialType::Ice | MaterialType::Snow => (old_temp + 5.0).clamp(1.0, AMBIENT_TEMP),
                         _ => old_temp,
                     },
                     MaterialType::Lava => (old_temp + 50.0).max(1800.0),
@@ -246,4 +439,51 @@ impl Particle {
         self.time_in_state = 0.0;
         self.invalidate_color_cache();
     }
-}
\ No newline at end of file
+}
+
+fn neutral_variant() -> u8 {
+    128
+}
+
+/// How far a material's shades stray from its base color, as a fraction of it
+/// Fully saturated color of `hue`, 0..1 around the color wheel starting at red
+fn hue_color(hue: f32) -> [f32; 3] {
+    let sector = hue.rem_euclid(1.0) * 6.0;
+    let rising = sector.fract() * 255.0;
+    match sector as u8 {
+        0 => [255.0, rising, 0.0],
+        1 => [255.0 - rising, 255.0, 0.0],
+        2 => [0.0, 255.0, rising],
+        3 => [0.0, 255.0 - rising, 255.0],
+        4 => [rising, 0.0, 255.0],
+        _ => [255.0, 0.0, 255.0 - rising],
+    }
+}
+
+fn texture_amount(material_type: MaterialType) -> f32 {
+    match material_type {
+        MaterialType::Sand | MaterialType::Sandstone | MaterialType::Ash | MaterialType::Gunpowder |
+        MaterialType::Seed | MaterialType::Coal | MaterialType::Dirt | MaterialType::WetSand => 0.12,
+        MaterialType::Salt => 0.06,
+        MaterialType::Stone | MaterialType::Wood | MaterialType::Plant | MaterialType::Stem |
+        MaterialType::Root | MaterialType::Custom(_) => 0.08,
+        MaterialType::Snow | MaterialType::Ice | MaterialType::Iron | MaterialType::Gold |
+        MaterialType::Uranium | MaterialType::Lead => 0.05,
+        MaterialType::Water | MaterialType::Oil | MaterialType::Acid | MaterialType::Gasoline |
+        MaterialType::Slime | MaterialType::Glass | MaterialType::Mud | MaterialType::SaltWater => 0.03,
+        _ => 0.0,
+    }
+}
+
+/// Deterministic 0..1 noise for a cell at a moment, about 30 steps per second, so
+/// flames flicker without drawing on a shared random number generator
+fn flicker_noise(x: usize, y: usize, seconds: f32) -> f32 {
+    let frame = (seconds * 30.0) as u32;
+    let mut hash = (x as u32).wrapping_mul(0x9E37_79B1)
+        ^ (y as u32).wrapping_mul(0x85EB_CA77)
+        ^ frame.wrapping_mul(0xC2B2_AE3D);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2C1B_3C6D);
+    hash ^= hash >> 12;
+    (hash & 0xFFFF) as f32 / 65535.0
+}
@@ -1,7 +1,14 @@
+use crate::damage::{self, DamageOutcome, ACID_DAMAGE};
 use crate::particle::Particle;
-use crate::materials::{get_material_properties, MaterialType};
-
-const AMBIENT_TEMP: f32 = 20.0;
+use crate::materials::{MaterialType, THERMAL_STRESS_EPSILON, THERMAL_STRESS_RELIEF};
+use crate::material_registry::MaterialRegistry;
+use crate::oxygen::FIRE_MIN_OXYGEN;
+use crate::reactions::FIREWORK_FUSE_SEC;
+use rand::Rng;
+use std::sync::Arc;
+
+/// Temperature of open air unless a day/night cycle sets it
+pub const AMBIENT_TEMP: f32 = 20.0;
 const COOLING_RATE: f32 = 0.005;
 const FIRE_HEAT_TRANSFER: f32 = 60.0;
 const WATER_COOLING_FACTOR: f32 = 80.0;
@@ -14,18 +21,71 @@ const CONDENSATION_CHANCE_ANYWHERE_PER_SEC: f32 = 0.006;
 const PHASE_CHANGE_TEMP_BUFFER: f32 = 5.0;
 const HIGH_INERTIA_DAMPING: f32 = 0.2;
 const MIN_STATE_SECONDS: f32 = 10.0;
+// Stone quenched out of lava can be hotter than its melting point; give it time to cool
+const QUENCHED_STONE_SECONDS: f32 = 2.0;
 const TARGET_DT_SCALING: f32 = 60.0;
 const ACID_GAS_TEMP_FACTOR: f32 = 0.8;
+// Concentration fresh acid loses per cell it dissolves, so it eats at most five cells deep
+const ACID_CORROSION_COST: f32 = 0.2;
+// Chance per tick at 60 FPS that acid mixes with a neighbouring water cell
+const ACID_DILUTION_RATE: f32 = 0.05;
+// Acid weaker than this is spent and turns into contaminated water
+const ACID_MIN_CONCENTRATION: f32 = 0.1;
+// Chance per second that a burning cell throws a flame into an open neighbour
+const FLAME_CHANCE_PER_SEC: f32 = 4.0;
+// Smoke cells given off per second of fuel burned
+const SMOKE_PER_FUEL_SEC: f32 = 0.6;
+// Heating of a burning cell per second, up to BURNING_MAX_TEMP
+const BURNING_HEAT_PER_SEC: f32 = 300.0;
+const BURNING_MAX_TEMP: f32 = 1000.0;
+// A burnt-out cell's char product keeps this share of its heat
+const CHAR_TEMP_FACTOR: f32 = 0.3;
+/// Chance per second that sand or dirt beside water soaks into wet sand or mud
+const WETTING_CHANCE_PER_SEC: f32 = 2.0;
+/// Wet sand and mud this hot dry out, giving off a puff of steam
+const DRYING_TEMP: f32 = 60.0;
+const DRYING_CHANCE_PER_SEC: f32 = 0.5;
+/// Chance per second that salt beside water dissolves into it
+const DISSOLVING_CHANCE_PER_SEC: f32 = 1.5;
+/// Salinity one grain of salt adds to the water it dissolves into; a cell holds two
+const SALINITY_PER_GRAIN: f32 = 0.5;
+/// Chance per tick at 60 FPS that salt water shares its salt with neighbouring fresh water
+const SALT_DIFFUSION_RATE: f32 = 0.02;
+/// Salt water weaker than this no longer spreads its salt further
+const MIN_DIFFUSING_SALINITY: f32 = 0.1;
 
 #[derive(Debug, Clone)]
 pub struct PhysicsState {
     pub width: usize,
     pub height: usize,
+    materials: Arc<MaterialRegistry>,
+    ambient_temp: f32,
 }
 
 impl PhysicsState {
     pub fn new(width: usize, height: usize) -> Self {
-        Self { width, height }
+        Self::with_materials(width, height, Arc::new(MaterialRegistry::new()))
+    }
+
+    pub fn with_materials(width: usize, height: usize, materials: Arc<MaterialRegistry>) -> Self {
+        Self { width, height, materials, ambient_temp: AMBIENT_TEMP }
+    }
+
+    pub fn materials(&self) -> &Arc<MaterialRegistry> {
+        &self.materials
+    }
+
+    pub fn set_materials(&mut self, materials: Arc<MaterialRegistry>) {
+        self.materials = materials;
+    }
+
+    /// Temperature of the open air particles exchange heat with and cool towards
+    pub fn ambient_temp(&self) -> f32 {
+        self.ambient_temp
+    }
+
+    pub fn set_ambient_temp(&mut self, ambient_temp: f32) {
+        self.ambient_temp = ambient_temp;
     }
 
     pub fn is_valid(&self, x: i32, y: i32) -> bool {
@@ -42,7 +102,7 @@ impl PhysicsState {
             if particle.life.is_none() {
                 particle.life = Some(FUSE_BURN_LIFESPAN_SEC);
             }
-        } else if particle.life.is_some() {
+        } else if particle.life.is_some() && !particle.burning {
             needs_check = true;
         }
 
@@ -60,7 +120,7 @@ impl PhysicsState {
                     let (new_type, new_temp) = match particle.material_type {
                         MaterialType::Fire => (MaterialType::Smoke, (particle.temp * 0.6).min(400.0)),
                         MaterialType::Fuse => (MaterialType::Ash, (particle.temp * 0.5).max(AMBIENT_TEMP)),
-                        MaterialType::Steam | MaterialType::Smoke | MaterialType::ToxicGas => {
+                        MaterialType::Steam | MaterialType::Smoke | MaterialType::ToxicGas | MaterialType::Spark => {
                             (MaterialType::Empty, AMBIENT_TEMP)
                         }
                         _ => return None,
@@ -73,68 +133,69 @@ impl PhysicsState {
         None
     }
 
+    /// Blast radius if `particle` is an explosive that goes off this tick: when it is hot
+    /// enough and touches a flame, or is well past its ignition point
+    pub fn detonation_radius(&self, particle: &Particle, neighbors: &[Option<&Particle>]) -> Option<f32> {
+        let props = self.materials.get(particle.material_type);
+        let radius = props.explosive_yield?;
+        let ignition_temp = props.ignition_temp?;
+        if props.flammability <= 0.0 || particle.temp < ignition_temp {
+            return None;
+        }
+        (particle.temp > ignition_temp || ignition_source_temp(neighbors).is_some()).then_some(radius)
+    }
+
     pub fn handle_state_changes_and_effects(
         &self,
         particle: &mut Particle,
         neighbors: &[Option<&Particle>],
         delta_time: f32,
+        rng: &mut impl Rng,
     ) -> (Option<Particle>, Vec<(usize, usize, Particle)>) {
         let mut new_particles = Vec::new();
-        let props = particle.get_properties();
+        let materials = Arc::clone(&self.materials);
+        let props = materials.get(particle.material_type);
         let dt_scale = delta_time * TARGET_DT_SCALING;
 
         // Ignition check
         if let Some(ignition_temp) = props.ignition_temp {
             if particle.temp >= ignition_temp && props.flammability > 0.0 {
-                let mut external_ignition = false;
-                let mut ignition_source_temp = particle.temp;
-
-                // Check for ignition sources in neighbors
-                for neighbor in neighbors.iter().flatten() {
-                    match neighbor.material_type {
-                        MaterialType::Fire | MaterialType::Lava => {
-                            external_ignition = true;
-                            ignition_source_temp = ignition_source_temp.max(neighbor.temp);
-                            break;
-                        }
-                        MaterialType::Fuse if neighbor.burning => {
-                            external_ignition = true;
-                            ignition_source_temp = ignition_source_temp.max(neighbor.temp);
-                            break;
-                        }
-                        _ => {}
-                    }
-                }
+                let source_temp = ignition_source_temp(neighbors);
+                let external_ignition = source_temp.is_some();
+                let ignition_source_temp = source_temp.map_or(particle.temp, |temp| temp.max(particle.temp));
 
                 match particle.material_type {
                     MaterialType::Plant | MaterialType::Wood | MaterialType::Coal | 
-                    MaterialType::Oil | MaterialType::Gasoline => {
+                    MaterialType::Oil | MaterialType::Gasoline | MaterialType::Seed |
+                    MaterialType::Stem | MaterialType::Root | MaterialType::Thermite |
+                    MaterialType::Custom(_) if !particle.burning => {
                         if external_ignition || particle.temp > ignition_temp + 100.0 {
+                            let fuel = if props.fuel_value > 0.0 { props.fuel_value } else { DEFAULT_FIRE_LIFESPAN_SEC };
+                            // Solid fuels burn where they are; liquid ones go up in flames
+                            if !props.is_liquid(particle.material_type) {
+                                particle.burning = true;
+                                particle.life = Some(fuel);
+                                particle.temp = particle.temp.max(ignition_temp + 50.0);
+                                particle.invalidate_color_cache();
+                                return (None, new_particles);
+                            }
                             let initial_fire_temp = ignition_source_temp.max(800.0);
-                            let initial_fire_life = match particle.material_type {
-                                MaterialType::Wood => 3.0,
-                                MaterialType::Coal => 4.0,
-                                _ => DEFAULT_FIRE_LIFESPAN_SEC,
-                            };
                             let mut new_particle = Particle::new(
                                 particle.x, 
                                 particle.y, 
                                 MaterialType::Fire, 
                                 Some(initial_fire_temp)
                             );
-                            new_particle.life = Some(initial_fire_life);
+                            new_particle.life = Some(fuel);
                             return (Some(new_particle), new_particles);
                         }
                     }
-                    MaterialType::Gunpowder => {
-                        if external_ignition || particle.temp > ignition_temp {
-                            // Handle explosion
-                            let explosion_particles = self.create_explosion(
-                                particle.x, particle.y, props.explosive_yield.unwrap_or(4.0)
-                            );
-                            new_particles.extend(explosion_particles);
-                            return (Some(Particle::new(particle.x, particle.y, MaterialType::Empty, None)), new_particles);
-                        }
+                    // A lit firework's life counts down its staged reactions; see `ReactionRegistry::with_defaults`
+                    MaterialType::Firework if particle.life.is_none() && external_ignition => {
+                        particle.life = Some(FIREWORK_FUSE_SEC);
+                        particle.payload = rng.gen();
+                        particle.temp = particle.temp.max(ignition_temp + 50.0);
+                        particle.invalidate_color_cache();
                     }
                     MaterialType::Fuse if !particle.burning => {
                         if external_ignition {
@@ -150,53 +211,73 @@ impl PhysicsState {
         }
 
         // Melting check
-        if let Some(melt_temp) = props.melt_temp {
-            if particle.temp >= melt_temp + PHASE_CHANGE_TEMP_BUFFER {
-                let new_type = match particle.material_type {
-                    MaterialType::Sand => MaterialType::Glass,
-                    MaterialType::Glass => MaterialType::Lava,
-                    MaterialType::Ice => MaterialType::Water,
-                    _ => return (None, new_particles),
+        let quenching = particle.material_type == MaterialType::Stone && particle.time_in_state < QUENCHED_STONE_SECONDS;
+        if let Some(melt_temp) = props.melt_temp.filter(|_| !quenching) {
+            let threshold = melt_temp + PHASE_CHANGE_TEMP_BUFFER;
+            if particle.temp >= threshold {
+                let Some(new_type) = props.melts_into else {
+                    return (None, new_particles);
                 };
-                return (Some(Particle::new(particle.x, particle.y, new_type, Some(particle.temp))), new_particles);
+                if !take_in_latent_heat(particle, threshold, props.heating_latent_heat, 1.0) {
+                    return (None, new_particles);
+                }
+                return (Some(materials.create_particle(particle.x, particle.y, new_type, Some(particle.temp))), new_particles);
             }
+            give_back_latent_heat(particle, threshold, 1.0);
         }
 
         // Boiling check
         if let Some(boil_temp) = props.boil_temp {
-            if particle.temp >= boil_temp + PHASE_CHANGE_TEMP_BUFFER {
-                let new_type = match particle.material_type {
-                    MaterialType::Water => MaterialType::Steam,
-                    MaterialType::Acid => MaterialType::ToxicGas,
-                    MaterialType::Slime => MaterialType::ToxicGas,
-                    _ => return (None, new_particles),
+            let threshold = boil_temp + PHASE_CHANGE_TEMP_BUFFER;
+            if particle.temp >= threshold {
+                let Some(new_type) = props.boils_into else {
+                    return (None, new_particles);
                 };
-                return (Some(Particle::new(particle.x, particle.y, new_type, Some(particle.temp))), new_particles);
+                if !take_in_latent_heat(particle, threshold, props.heating_latent_heat, 1.0) {
+                    return (None, new_particles);
+                }
+                // Dissolved salt stays behind as a grain, as often as the water carried one
+                if rng.gen::<f32>() < particle.salinity() {
+                    if let Some(i) = open_neighbor(neighbors, rng) {
+                        let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                        new_particles.push((nx, ny, materials.create_particle(nx, ny, new_type, Some(particle.temp))));
+                    }
+                    return (Some(materials.create_particle(particle.x, particle.y, MaterialType::Salt, Some(particle.temp))), new_particles);
+                }
+                return (Some(materials.create_particle(particle.x, particle.y, new_type, Some(particle.temp))), new_particles);
             }
+            give_back_latent_heat(particle, threshold, 1.0);
         }
 
         // Freezing/Condensation check
         if let Some(freeze_temp) = props.freeze_temp {
-            if particle.temp <= freeze_temp - PHASE_CHANGE_TEMP_BUFFER {
+            let threshold = freeze_temp - PHASE_CHANGE_TEMP_BUFFER;
+            if particle.temp <= threshold {
+                let Some(frozen_type) = props.freezes_into else {
+                    return (None, new_particles);
+                };
+                if !take_in_latent_heat(particle, threshold, props.cooling_latent_heat, -1.0) {
+                    return (None, new_particles);
+                }
                 let new_type = match particle.material_type {
-                    MaterialType::Lava => MaterialType::Stone,
-                    MaterialType::Water => MaterialType::Ice,
                     MaterialType::Steam if particle.time_in_state >= MIN_STATE_SECONDS => {
                         let condensation_chance = if particle.y < CONDENSATION_Y_LIMIT {
                             1.0
                         } else {
                             CONDENSATION_CHANCE_ANYWHERE_PER_SEC * delta_time
                         };
-                        if rand::random::<f32>() < condensation_chance {
-                            MaterialType::Water
+                        if rng.gen::<f32>() < condensation_chance {
+                            frozen_type
                         } else {
                             return (None, new_particles);
                         }
                     }
-                    _ => return (None, new_particles),
+                    MaterialType::Steam => return (None, new_particles),
+                    _ => frozen_type,
                 };
-                return (Some(Particle::new(particle.x, particle.y, new_type, Some(particle.temp))), new_particles);
+                return (Some(materials.create_particle(particle.x, particle.y, new_type, Some(particle.temp))), new_particles);
             }
+            give_back_latent_heat(particle, threshold, -1.0);
         }
 
         // Material-specific effects
@@ -206,18 +287,41 @@ impl PhysicsState {
             }
             MaterialType::Acid => {
                 if props.corrosive_power > 0.0 {
-                    // Handle acid corrosion
+                    // Handle acid corrosion and dilution; both use up concentration
                     for (i, neighbor) in neighbors.iter().enumerate() {
                         if let Some(neighbor) = neighbor {
+                            let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                            if neighbor.material_type == MaterialType::Water {
+                                if rng.gen::<f32>() < ACID_DILUTION_RATE * dt_scale {
+                                    // The two cells share the acid evenly
+                                    let shared = particle.concentration() / 2.0;
+                                    particle.set_concentration(shared);
+                                    let mut diluted = Particle::new(nx, ny, MaterialType::Acid, Some(neighbor.temp));
+                                    diluted.set_concentration(shared);
+                                    if shared < ACID_MIN_CONCENTRATION {
+                                        diluted = contaminated_water(nx, ny, neighbor.temp);
+                                    }
+                                    new_particles.push((nx, ny, diluted));
+                                    break;
+                                }
+                                continue;
+                            }
+
                             let immune_materials = [
                                 MaterialType::Empty, MaterialType::Acid, 
                                 MaterialType::Glass, MaterialType::Generator
                             ];
                             if !immune_materials.contains(&neighbor.material_type) {
-                                if rand::random::<f32>() < props.corrosive_power * dt_scale {
-                                    let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
-                                    if neighbor.material_type == MaterialType::Stone && rand::random::<f32>() < 0.3 {
-                                        new_particles.push((nx, ny, Particle::new(nx, ny, MaterialType::Sand, Some(neighbor.temp))));
+                                if rng.gen::<f32>() < props.corrosive_power * particle.concentration() * dt_scale {
+                                    let neighbor_props = materials.get(neighbor.material_type);
+                                    if neighbor_props.max_health > 0.0 {
+                                        // Solids with health are etched a little at a time and crumble once worn through
+                                        let mut etched = Particle::clone(neighbor);
+                                        if damage::apply_damage(&mut etched, neighbor_props, ACID_DAMAGE) == DamageOutcome::Broken {
+                                            let rubble = damage::rubble_of(neighbor.material_type, neighbor_props);
+                                            etched = materials.create_particle(nx, ny, rubble, Some(neighbor.temp));
+                                        }
+                                        new_particles.push((nx, ny, etched));
                                     } else {
                                         new_particles.push((nx, ny, Particle::new(nx, ny, MaterialType::Empty, None)));
                                         // Create toxic gas
@@ -226,14 +330,65 @@ impl PhysicsState {
                                             new_particles.push((nx, ny - 1, Particle::new(nx, ny - 1, MaterialType::ToxicGas, Some(gas_temp))));
                                         }
                                     }
-                                    if rand::random::<f32>() < 0.05 * dt_scale {
-                                        return (Some(Particle::new(particle.x, particle.y, MaterialType::Empty, None)), new_particles);
-                                    }
+                                    particle.set_concentration(particle.concentration() - ACID_CORROSION_COST);
                                     break;
                                 }
                             }
                         }
                     }
+
+                    if particle.concentration() < ACID_MIN_CONCENTRATION {
+                        return (Some(contaminated_water(particle.x, particle.y, particle.temp)), new_particles);
+                    }
+                }
+            }
+            MaterialType::Sand | MaterialType::Dirt => {
+                let water = neighbors.iter()
+                    .position(|neighbor| neighbor.is_some_and(|neighbor| matches!(neighbor.material_type, MaterialType::Water | MaterialType::SaltWater)));
+                if let Some(i) = water.filter(|_| rng.gen::<f32>() < WETTING_CHANCE_PER_SEC * delta_time) {
+                    // The grain soaks up the water it touches, which comes back as steam when it dries
+                    let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                    new_particles.push((nx, ny, Particle::new(nx, ny, MaterialType::Empty, None)));
+                    let soaked = if particle.material_type == MaterialType::Sand { MaterialType::WetSand } else { MaterialType::Mud };
+                    return (Some(materials.create_particle(particle.x, particle.y, soaked, Some(particle.temp))), new_particles);
+                }
+            }
+            MaterialType::WetSand | MaterialType::Mud
+                if particle.temp >= DRYING_TEMP && rng.gen::<f32>() < DRYING_CHANCE_PER_SEC * delta_time => {
+                if let Some(i) = open_neighbor(neighbors, rng) {
+                    let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                    new_particles.push((nx, ny, Particle::new(nx, ny, MaterialType::Steam, Some(particle.temp.max(100.0)))));
+                }
+                let dried = if particle.material_type == MaterialType::WetSand { MaterialType::Sand } else { MaterialType::Dirt };
+                return (Some(materials.create_particle(particle.x, particle.y, dried, Some(particle.temp))), new_particles);
+            }
+            MaterialType::Salt => {
+                let solvent = neighbors.iter().enumerate().find_map(|(i, neighbor)| {
+                    let neighbor = neighbor.as_ref()?;
+                    let dissolves = matches!(neighbor.material_type, MaterialType::Water | MaterialType::SaltWater)
+                        && neighbor.salinity() + SALINITY_PER_GRAIN <= 1.0;
+                    dissolves.then_some((i, *neighbor))
+                });
+                if let Some((i, water)) = solvent {
+                    if rng.gen::<f32>() < DISSOLVING_CHANCE_PER_SEC * delta_time {
+                        let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                        new_particles.push((nx, ny, salt_water(nx, ny, water.temp, water.salinity() + SALINITY_PER_GRAIN)));
+                        return (Some(Particle::new(particle.x, particle.y, MaterialType::Empty, None)), new_particles);
+                    }
+                }
+            }
+            MaterialType::SaltWater if particle.salinity() >= MIN_DIFFUSING_SALINITY => {
+                let fresh = neighbors.iter().enumerate()
+                    .find(|(_, neighbor)| neighbor.is_some_and(|neighbor| neighbor.material_type == MaterialType::Water));
+                if let Some((i, Some(water))) = fresh {
+                    if rng.gen::<f32>() < SALT_DIFFUSION_RATE * dt_scale {
+                        // The two cells share the salt evenly
+                        let shared = particle.salinity() / 2.0;
+                        particle.payload = shared;
+                        particle.invalidate_color_cache();
+                        let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                        new_particles.push((nx, ny, salt_water(nx, ny, water.temp, shared)));
+                    }
                 }
             }
             MaterialType::Plant => {
@@ -243,7 +398,7 @@ impl PhysicsState {
                 
                 for (i, neighbor) in neighbors.iter().enumerate() {
                     if let Some(neighbor) = neighbor {
-                        if neighbor.material_type == MaterialType::Water {
+                        if neighbor.material_type == MaterialType::Water && !neighbor.is_contaminated() {
                             has_adjacent_water = true;
                         }
                     } else {
@@ -253,8 +408,8 @@ impl PhysicsState {
 
                 if has_adjacent_water && !empty_neighbors.is_empty() && 
                    AMBIENT_TEMP < particle.temp && particle.temp < 50.0 {
-                    if rand::random::<f32>() < PLANT_GROWTH_CHANCE_PER_SEC * delta_time {
-                        let neighbor_idx = empty_neighbors[rand::random::<usize>() % empty_neighbors.len()];
+                    if rng.gen::<f32>() < PLANT_GROWTH_CHANCE_PER_SEC * delta_time {
+                        let neighbor_idx = empty_neighbors[rng.gen_range(0..empty_neighbors.len())];
                         let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, neighbor_idx);
                         new_particles.push((nx, ny, Particle::new(nx, ny, MaterialType::Plant, Some(particle.temp))));
                     }
@@ -266,6 +421,65 @@ impl PhysicsState {
         (None, new_particles)
     }
 
+    /// Burn a cell of fuel that is alight in place for `delta_time` seconds. Fuel is used
+    /// up as fast as the `oxygen` level (0..1) allows, flames leap into open neighbours
+    /// and smoke rises in proportion to the fuel burned. A spent cell turns into its char
+    /// product, and one starved of oxygen goes out.
+    pub fn burn(
+        &self,
+        particle: &mut Particle,
+        neighbors: &[Option<&Particle>],
+        oxygen: f32,
+        delta_time: f32,
+        rng: &mut impl Rng,
+    ) -> (Option<Particle>, Vec<(usize, usize, Particle)>) {
+        let mut new_particles = Vec::new();
+        if oxygen < FIRE_MIN_OXYGEN {
+            particle.burning = false;
+            particle.life = None;
+            particle.invalidate_color_cache();
+            return (None, new_particles);
+        }
+
+        let props = self.materials.get(particle.material_type);
+        let burned = delta_time * oxygen.min(1.0);
+        let fuel_left = particle.life.unwrap_or(0.0) - burned;
+        particle.life = Some(fuel_left);
+        // Fuels with a burn temperature, such as thermite, burn at it however much heat
+        // their surroundings draw off; the rest warm up gradually
+        particle.temp = match props.burn_temp > 0.0 {
+            true => particle.temp.max(props.burn_temp),
+            false => (particle.temp + BURNING_HEAT_PER_SEC * delta_time).min(BURNING_MAX_TEMP.max(particle.temp)),
+        };
+        particle.invalidate_color_cache();
+
+        if rng.gen::<f32>() < FLAME_CHANCE_PER_SEC * delta_time {
+            if let Some(i) = open_neighbor(neighbors, rng) {
+                let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                let mut flame = Particle::new(nx, ny, MaterialType::Fire, Some(particle.temp.max(800.0)));
+                flame.life = Some(DEFAULT_FIRE_LIFESPAN_SEC);
+                new_particles.push((nx, ny, flame));
+            }
+        }
+        if rng.gen::<f32>() < SMOKE_PER_FUEL_SEC * burned {
+            if let Some(i) = open_neighbor(neighbors, rng) {
+                let (nx, ny) = self.get_neighbor_coords(particle.x, particle.y, i);
+                new_particles.push((nx, ny, Particle::new(nx, ny, MaterialType::Smoke, Some((particle.temp * 0.6).min(400.0)))));
+            }
+        }
+
+        if fuel_left > 0.0 {
+            return (None, new_particles);
+        }
+        // Ordinary embers cool as they char; what a hotter fire leaves is still molten
+        let char_temp = if props.burn_temp > 0.0 { particle.temp } else { (particle.temp * CHAR_TEMP_FACTOR).max(AMBIENT_TEMP) };
+        let remains = match props.chars_into {
+            Some(product) => self.materials.create_particle(particle.x, particle.y, product, Some(char_temp)),
+            None => Particle::new(particle.x, particle.y, MaterialType::Smoke, Some((particle.temp * 0.6).min(400.0))),
+        };
+        (Some(remains), new_particles)
+    }
+
     fn get_neighbor_coords(&self, x: usize, y: usize, neighbor_index: usize) -> (usize, usize) {
         let offsets = [
             (-1, -1), (0, -1), (1, -1),
@@ -276,43 +490,12 @@ impl PhysicsState {
         ((x as i32 + dx) as usize, (y as i32 + dy) as usize)
     }
 
-    fn create_explosion(&self, cx: usize, cy: usize, radius: f32) -> Vec<(usize, usize, Particle)> {
-        let mut explosion_particles = Vec::new();
-        let radius_sq = radius * radius;
-
-        for dx in -(radius as i32)..=(radius as i32) {
-            for dy in -(radius as i32)..=(radius as i32) {
-                let dist_sq = (dx * dx + dy * dy) as f32;
-                if dist_sq <= radius_sq {
-                    let px = cx as i32 + dx;
-                    let py = cy as i32 + dy;
-                    if self.is_valid(px, py) {
-                        let explosion_strength = (1.0 - (dist_sq.sqrt() / radius)).max(0.0);
-                        if rand::random::<f32>() < explosion_strength * 0.95 {
-                            if rand::random::<f32>() < 0.6 * explosion_strength {
-                                let mut fire_particle = Particle::new(px as usize, py as usize, MaterialType::Fire, Some(800.0 + explosion_strength * 700.0));
-                                fire_particle.life = Some(DEFAULT_FIRE_LIFESPAN_SEC * explosion_strength * 0.5);
-                                explosion_particles.push((px as usize, py as usize, fire_particle));
-                            } else {
-                                let mut smoke_particle = Particle::new(px as usize, py as usize, MaterialType::Smoke, Some(400.0 * explosion_strength));
-                                smoke_particle.life = Some(3.0 * explosion_strength);
-                                explosion_particles.push((px as usize, py as usize, smoke_particle));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        explosion_particles
-    }
-
     pub fn update_temperature(&self, particle: &mut Particle, neighbors: &[Option<&Particle>], delta_time: f32) {
         if particle.material_type == MaterialType::Empty {
             return;
         }
 
-        let props = particle.get_properties();
+        let props = self.materials.get(particle.material_type);
         let mut conductivity = props.conductivity;
         let dt_scale = delta_time * TARGET_DT_SCALING;
 
@@ -330,9 +513,9 @@ impl PhysicsState {
         // Accumulate temperature and conductivity from neighbors
         for neighbor in neighbors.iter() {
             let (neighbor_temp, neighbor_conductivity) = if let Some(neighbor) = neighbor {
-                (neighbor.temp, neighbor.get_properties().conductivity)
+                (neighbor.temp, self.materials.get(neighbor.material_type).conductivity)
             } else {
-                (AMBIENT_TEMP, get_material_properties(MaterialType::Empty).conductivity)
+                (self.ambient_temp, self.materials.get(MaterialType::Empty).conductivity)
             };
 
             neighbor_temp_sum += neighbor_temp * neighbor_conductivity;
@@ -352,7 +535,7 @@ impl PhysicsState {
                 // Apply inertia damping for specific materials
                 if matches!(
                     particle.material_type,
-                    MaterialType::Lava | MaterialType::Stone | MaterialType::Glass | MaterialType::Ice
+                    MaterialType::Lava | MaterialType::Stone | MaterialType::Glass | MaterialType::Ice | MaterialType::Snow
                 ) {
                     delta_temp *= HIGH_INERTIA_DAMPING;
                 }
@@ -364,7 +547,7 @@ impl PhysicsState {
         }
 
         // Apply ambient cooling and heat generation
-        new_temp += (AMBIENT_TEMP - new_temp) * COOLING_RATE * conductivity * dt_scale;
+        new_temp += (self.ambient_temp - new_temp) * COOLING_RATE * conductivity * dt_scale;
         if props.heat_generation > 0.0 {
             new_temp += props.heat_generation * dt_scale;
         }
@@ -376,4 +559,86 @@ impl PhysicsState {
             particle.invalidate_color_cache();
         }
     }
-}
\ No newline at end of file
+
+    /// Add this step's temperature swing to a brittle solid's stress, less what it relieved,
+    /// and return the debris it cracks into once the stress passes its limit
+    pub fn accumulate_thermal_stress(&self, particle: &mut Particle, temp_before: f32, delta_time: f32) -> Option<Particle> {
+        let props = self.materials.get(particle.material_type);
+        let debris = props.cracks_into?;
+        let relieved = particle.stress * (1.0 - THERMAL_STRESS_RELIEF * delta_time).max(0.0);
+        particle.stress = relieved + (particle.temp - temp_before).abs();
+        if particle.stress < THERMAL_STRESS_EPSILON {
+            particle.stress = 0.0;
+        }
+        (particle.stress > props.crack_stress)
+            .then(|| self.materials.create_particle(particle.x, particle.y, debris, Some(particle.temp)))
+    }
+}
+
+/// Temperature of the first neighbour that can set things alight
+fn ignition_source_temp(neighbors: &[Option<&Particle>]) -> Option<f32> {
+    neighbors.iter().flatten().find_map(|neighbor| match neighbor.material_type {
+        MaterialType::Fire | MaterialType::Lava => Some(neighbor.temp),
+        MaterialType::Fuse if neighbor.burning => Some(neighbor.temp),
+        _ => None,
+    })
+}
+
+/// Bank the heat that took a particle past a phase change `threshold`, rising through it
+/// for `direction` 1 and falling for -1, as latent heat, holding the particle at the
+/// threshold. Once `heat` is banked it is ready to change state, and keeps any heat
+/// beyond that as its temperature.
+fn take_in_latent_heat(particle: &mut Particle, threshold: f32, heat: f32, direction: f32) -> bool {
+    let banked = (particle.latent_heat * direction).max(0.0) + (particle.temp - threshold) * direction;
+    let excess = (banked - heat).max(0.0);
+    particle.latent_heat = banked.min(heat) * direction;
+    particle.temp = threshold + excess * direction;
+    particle.invalidate_color_cache();
+    banked >= heat
+}
+
+/// Let latent heat banked toward a phase change flow back out of a particle that has
+/// drifted back from the `threshold`, up to the way back to it
+fn give_back_latent_heat(particle: &mut Particle, threshold: f32, direction: f32) {
+    let banked = particle.latent_heat * direction;
+    if banked <= 0.0 {
+        return;
+    }
+    let returned = banked.min((threshold - particle.temp) * direction);
+    particle.latent_heat -= returned * direction;
+    particle.temp += returned * direction;
+    particle.invalidate_color_cache();
+}
+
+/// Whether sand or dirt touching water is soaking it up, salt touching water is
+/// dissolving, or wet sand or mud is hot enough to dry out, so it has to stay awake until
+/// it does
+pub(crate) fn changes_moisture(particle: &Particle) -> bool {
+    match particle.material_type {
+        MaterialType::Sand | MaterialType::Dirt | MaterialType::Salt => particle.wet,
+        MaterialType::WetSand | MaterialType::Mud => particle.temp >= DRYING_TEMP,
+        _ => false,
+    }
+}
+
+/// Index of a random empty neighbour, preferring the row above, for flames and smoke
+fn open_neighbor(neighbors: &[Option<&Particle>], rng: &mut impl Rng) -> Option<usize> {
+    let open: Vec<usize> = (0..neighbors.len()).filter(|&i| neighbors[i].is_none()).collect();
+    let above: Vec<usize> = open.iter().copied().filter(|&i| i < 3).collect();
+    let choices = if above.is_empty() { open } else { above };
+    (!choices.is_empty()).then(|| choices[rng.gen_range(0..choices.len())])
+}
+
+/// Water carrying `salinity` worth of dissolved salt
+fn salt_water(x: usize, y: usize, temp: f32, salinity: f32) -> Particle {
+    let mut water = Particle::new(x, y, MaterialType::SaltWater, Some(temp));
+    water.payload = salinity.clamp(0.0, 1.0);
+    water
+}
+
+/// What spent acid leaves behind
+fn contaminated_water(x: usize, y: usize, temp: f32) -> Particle {
+    let mut water = Particle::new(x, y, MaterialType::Water, Some(temp));
+    water.payload = 1.0;
+    water
+}
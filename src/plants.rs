@@ -0,0 +1,483 @@
+use crate::material_registry::{MaterialRegistry, MaterialRegistryError};
+use crate::materials::MaterialType;
+use crate::reactions::NEIGHBOR_OFFSETS;
+use crate::simulation::Simulation;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "files")]
+use std::fs;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// Seconds between growth passes; plants grow a few cells a second at most, so checking
+/// every tick would be wasted work
+pub const PLANT_GROWTH_INTERVAL: f32 = 0.25;
+/// How far a root tip looks for water when choosing where to grow
+const ROOT_WATER_SEARCH_RADIUS: i32 = 4;
+/// Where a stem tip may grow, straight up first
+const STEM_GROWTH: [(i32, i32); 3] = [(0, -1), (-1, -1), (1, -1)];
+/// Where a root tip may grow, straight down first
+const ROOT_GROWTH: [(i32, i32); 5] = [(0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)];
+
+/// One `[[species]]` entry of a plant file. Materials are named as in material packs, so a
+/// species can grow from custom seed, stem or root materials.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlantSpeciesDefinition {
+    pub name: String,
+    pub seed: String,
+    #[serde(default = "default_stem")]
+    pub stem: String,
+    #[serde(default = "default_root")]
+    pub root: String,
+    /// Materials the seed sprouts on and roots grow through
+    #[serde(default = "default_soil")]
+    pub soil: Vec<String>,
+    /// Stem cells above the seed
+    pub max_height: u32,
+    /// Root cells below the seed
+    pub root_depth: u32,
+    /// Cells each stem and root tip grows per second
+    pub growth_rate: f32,
+    /// Chance per second that a seed touching soil and water sprouts
+    #[serde(default = "default_sprout_chance")]
+    pub sprout_chance: f32,
+    /// Temperature range in °C the plant grows in
+    #[serde(default = "default_min_temperature")]
+    pub min_temperature: f32,
+    #[serde(default = "default_max_temperature")]
+    pub max_temperature: f32,
+}
+
+fn default_stem() -> String {
+    "Stem".to_string()
+}
+
+fn default_root() -> String {
+    "Root".to_string()
+}
+
+fn default_soil() -> Vec<String> {
+    ["Sand", "Wet Sand", "Dirt"].map(String::from).to_vec()
+}
+
+fn default_sprout_chance() -> f32 {
+    0.5
+}
+
+fn default_min_temperature() -> f32 {
+    5.0
+}
+
+fn default_max_temperature() -> f32 {
+    40.0
+}
+
+/// A TOML file of plant species
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PlantFile {
+    #[serde(rename = "species", default)]
+    pub species: Vec<PlantSpeciesDefinition>,
+}
+
+/// Growth rules for one kind of plant. A sprouted seed becomes the bottom of a stem that
+/// grows upwards towards open space and light, while roots grow down through the soil
+/// towards water. Stem and root tips count the cells they have left in their payload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlantSpecies {
+    pub name: String,
+    pub seed: MaterialType,
+    pub stem: MaterialType,
+    pub root: MaterialType,
+    pub soil: Vec<MaterialType>,
+    pub max_height: u32,
+    pub root_depth: u32,
+    pub growth_rate: f32,
+    pub sprout_chance: f32,
+    pub min_temperature: f32,
+    pub max_temperature: f32,
+}
+
+impl PlantSpecies {
+    pub fn new(name: &str, seed: MaterialType, stem: MaterialType, root: MaterialType) -> Self {
+        Self {
+            name: name.to_string(),
+            seed,
+            stem,
+            root,
+            soil: vec![MaterialType::Sand, MaterialType::WetSand, MaterialType::Dirt],
+            max_height: 12,
+            root_depth: 6,
+            growth_rate: 2.0,
+            sprout_chance: default_sprout_chance(),
+            min_temperature: default_min_temperature(),
+            max_temperature: default_max_temperature(),
+        }
+    }
+
+    pub fn with_size(mut self, max_height: u32, root_depth: u32) -> Self {
+        self.max_height = max_height;
+        self.root_depth = root_depth;
+        self
+    }
+
+    pub fn with_soil(mut self, soil: Vec<MaterialType>) -> Self {
+        self.soil = soil;
+        self
+    }
+
+    pub fn is_soil(&self, material_type: MaterialType) -> bool {
+        self.soil.contains(&material_type)
+    }
+
+    pub fn grows_at(&self, temperature: f32) -> bool {
+        (self.min_temperature..=self.max_temperature).contains(&temperature)
+    }
+
+    fn from_definition(definition: &PlantSpeciesDefinition, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let resolve = |name: &str| {
+            materials.lookup(name).ok_or_else(|| MaterialRegistryError::UnknownMaterial(name.to_string()))
+        };
+        if definition.max_height == 0 {
+            return Err(MaterialRegistryError::InvalidDefinition(format!(
+                "species '{}' needs a non-zero max_height", definition.name
+            )));
+        }
+        if definition.growth_rate.is_nan() || definition.growth_rate <= 0.0 {
+            return Err(MaterialRegistryError::InvalidDefinition(format!(
+                "species '{}' needs a positive growth_rate", definition.name
+            )));
+        }
+
+        Ok(Self {
+            name: definition.name.clone(),
+            seed: resolve(&definition.seed)?,
+            stem: resolve(&definition.stem)?,
+            root: resolve(&definition.root)?,
+            soil: definition.soil.iter().map(|name| resolve(name)).collect::<Result<_, _>>()?,
+            max_height: definition.max_height,
+            root_depth: definition.root_depth,
+            growth_rate: definition.growth_rate,
+            sprout_chance: definition.sprout_chance,
+            min_temperature: definition.min_temperature,
+            max_temperature: definition.max_temperature,
+        })
+    }
+}
+
+/// Plant species consulted by a simulation's growth pass. Species registered later take
+/// precedence, so a loaded file can redefine what the built-in Seed grows into.
+#[derive(Debug, Clone, Default)]
+pub struct PlantRegistry {
+    species: Vec<PlantSpecies>,
+}
+
+impl PlantRegistry {
+    /// No species; nothing grows
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Table with the built-in species
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(PlantSpecies::new("Sapling", MaterialType::Seed, MaterialType::Stem, MaterialType::Root));
+        registry
+    }
+
+    /// Built-in species plus everything defined in `source`, with material names resolved
+    /// against `materials`
+    pub fn from_toml_str(source: &str, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let mut registry = Self::with_defaults();
+        registry.load_toml_str(source, materials)?;
+        Ok(registry)
+    }
+
+    /// Built-in species plus everything defined in the TOML file at `path`
+    #[cfg(feature = "files")]
+    pub fn from_file(path: impl AsRef<Path>, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let mut registry = Self::with_defaults();
+        registry.load_file(path, materials)?;
+        Ok(registry)
+    }
+
+    /// Add the species in a TOML file, returning how many it defined
+    #[cfg(feature = "files")]
+    pub fn load_file(&mut self, path: impl AsRef<Path>, materials: &MaterialRegistry) -> Result<usize, MaterialRegistryError> {
+        let source = fs::read_to_string(path)?;
+        self.load_toml_str(&source, materials)
+    }
+
+    /// Add the species in a TOML plant file, returning how many it defined. The file is
+    /// applied atomically: on error the registry is left unchanged.
+    pub fn load_toml_str(&mut self, source: &str, materials: &MaterialRegistry) -> Result<usize, MaterialRegistryError> {
+        let file: PlantFile = toml::from_str(source)?;
+        let species = file.species.iter()
+            .map(|definition| PlantSpecies::from_definition(definition, materials))
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = species.len();
+        self.species.extend(species);
+        Ok(count)
+    }
+
+    pub fn register(&mut self, species: PlantSpecies) {
+        self.species.push(species);
+    }
+
+    /// Every species in registration order
+    pub fn species(&self) -> &[PlantSpecies] {
+        &self.species
+    }
+
+    /// The species a seed of `material_type` grows into
+    pub fn for_seed(&self, material_type: MaterialType) -> Option<&PlantSpecies> {
+        self.species.iter().rev().find(|species| species.seed == material_type)
+    }
+
+    /// The species whose stems are made of `material_type`
+    pub fn for_stem(&self, material_type: MaterialType) -> Option<&PlantSpecies> {
+        self.species.iter().rev().find(|species| species.stem == material_type)
+    }
+
+    /// The species whose roots are made of `material_type`
+    pub fn for_root(&self, material_type: MaterialType) -> Option<&PlantSpecies> {
+        self.species.iter().rev().find(|species| species.root == material_type)
+    }
+
+    pub fn len(&self) -> usize {
+        self.species.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.species.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Growth {
+    Sprout,
+    Stem,
+    Root,
+}
+
+impl Simulation {
+    /// Sprout seeds and extend stem and root tips by up to one cell each. Runs every
+    /// `PLANT_GROWTH_INTERVAL` seconds with the time since the last pass.
+    pub(crate) fn grow_plants(&mut self, elapsed: f32) {
+        self.grow_plants_where(elapsed, |_, _| true);
+    }
+
+    /// Run a growth pass over `elapsed` seconds for the plant cells `include` accepts
+    pub(crate) fn grow_plants_where(&mut self, elapsed: f32, include: impl Fn(usize, usize) -> bool) {
+        let plants = std::sync::Arc::clone(self.plants());
+        if plants.is_empty() {
+            return;
+        }
+
+        // Collected up front so cells grown this pass wait for the next one
+        let growing: Vec<_> = self.iter_particles()
+            .filter(|particle| include(particle.x, particle.y))
+            .filter_map(|particle| {
+                let material_type = particle.material_type;
+                let (growth, species) = if let Some(species) = plants.for_seed(material_type) {
+                    (Growth::Sprout, species)
+                } else if particle.payload <= 0.0 {
+                    return None;
+                } else if let Some(species) = plants.for_stem(material_type) {
+                    (Growth::Stem, species)
+                } else {
+                    (Growth::Root, plants.for_root(material_type)?)
+                };
+                species.grows_at(particle.temp).then_some((particle.x, particle.y, growth, species))
+            })
+            .collect();
+
+        for (x, y, growth, species) in growing {
+            let rate = match growth {
+                Growth::Sprout => species.sprout_chance,
+                Growth::Stem | Growth::Root => species.growth_rate,
+            };
+            if self.rng.gen::<f32>() >= rate * elapsed {
+                continue;
+            }
+            match growth {
+                Growth::Sprout => self.sprout(x, y, species),
+                Growth::Stem => {
+                    let target = self.stem_target(x, y);
+                    self.grow_tip(x, y, species.stem, target);
+                }
+                Growth::Root => {
+                    let target = self.root_target(x, y, species);
+                    self.grow_tip(x, y, species.root, target);
+                }
+            }
+        }
+    }
+
+    /// A seed resting on soil with water beside it or the soil turns into the bottom of
+    /// a stem with a root beneath it
+    fn sprout(&mut self, x: usize, y: usize, species: &PlantSpecies) {
+        let Some(below) = self.offset_cell(x, y, 0, 1) else {
+            return;
+        };
+        let on_soil = self.get_particle(below.0, below.1).is_some_and(|p| species.is_soil(p.material_type));
+        if !on_soil || !(self.touches_water(x, y) || self.touches_water(below.0, below.1)) {
+            return;
+        }
+
+        let temp = self.get_particle(x, y).map_or(20.0, |p| p.temp);
+        self.place_tip(x, y, species.stem, temp, species.max_height.saturating_sub(1));
+        if species.root_depth > 0 {
+            self.place_tip(below.0, below.1, species.root, temp, species.root_depth - 1);
+        }
+    }
+
+    /// Move the growing tip at `(x, y)` into `target`, which inherits the cells left to grow
+    fn grow_tip(&mut self, x: usize, y: usize, material_type: MaterialType, target: Option<(usize, usize)>) {
+        let Some((nx, ny)) = target else {
+            return;
+        };
+        let Some(tip) = self.get_particle_mut(x, y) else {
+            return;
+        };
+        let (left, temp) = (tip.payload, tip.temp);
+        tip.payload = 0.0;
+        self.place_tip(nx, ny, material_type, temp, left as u32 - 1);
+    }
+
+    fn place_tip(&mut self, x: usize, y: usize, material_type: MaterialType, temp: f32, left: u32) {
+        let mut tip = self.materials().create_particle(x, y, material_type, Some(temp));
+        tip.payload = left as f32;
+        self.set_particle(x, y, tip);
+    }
+
+    /// The open cell above a stem tip to grow into: the brightest while lighting is on,
+    /// otherwise straight up when free and a random upward diagonal when not
+    fn stem_target(&mut self, x: usize, y: usize) -> Option<(usize, usize)> {
+        let open: Vec<_> = STEM_GROWTH.iter()
+            .filter_map(|&(dx, dy)| self.offset_cell(x, y, dx, dy))
+            .filter(|&(nx, ny)| self.is_open(nx, ny))
+            .collect();
+        if let Some(light_map) = self.light_map() {
+            // Ties keep the earlier, straighter move
+            return open.into_iter().reduce(|best, cell| {
+                if light_map.level(cell.0, cell.1) > light_map.level(best.0, best.1) { cell } else { best }
+            });
+        }
+        match open.first() {
+            Some(&(nx, _)) if nx == x => Some(open[0]),
+            Some(_) => Some(open[self.rng.gen_range(0..open.len())]),
+            None => None,
+        }
+    }
+
+    /// The soil cell beside or below a root tip that is nearest to water; straight down
+    /// when no water is in range
+    fn root_target(&self, x: usize, y: usize, species: &PlantSpecies) -> Option<(usize, usize)> {
+        ROOT_GROWTH.iter()
+            .filter_map(|&(dx, dy)| self.offset_cell(x, y, dx, dy))
+            .filter(|&(nx, ny)| self.get_particle(nx, ny).is_some_and(|p| species.is_soil(p.material_type)))
+            .min_by_key(|&(nx, ny)| self.water_distance(nx, ny).unwrap_or(i32::MAX))
+    }
+
+    /// Chebyshev distance to the nearest clean water within `ROOT_WATER_SEARCH_RADIUS`
+    fn water_distance(&self, x: usize, y: usize) -> Option<i32> {
+        (1..=ROOT_WATER_SEARCH_RADIUS).find(|&radius| {
+            (-radius..=radius).any(|dy| (-radius..=radius).any(|dx| {
+                (dx.abs() == radius || dy.abs() == radius)
+                    && self.offset_cell(x, y, dx, dy).is_some_and(|(nx, ny)| self.is_clean_water(nx, ny))
+            }))
+        })
+    }
+
+    fn touches_water(&self, x: usize, y: usize) -> bool {
+        NEIGHBOR_OFFSETS.iter()
+            .filter_map(|&(dx, dy)| self.offset_cell(x, y, dx, dy))
+            .any(|(nx, ny)| self.is_clean_water(nx, ny))
+    }
+
+    fn is_clean_water(&self, x: usize, y: usize) -> bool {
+        self.get_particle(x, y).is_some_and(|p| p.material_type == MaterialType::Water && !p.is_contaminated())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bounds::WorldBounds;
+
+    /// Wet sand bed with a seed resting on it
+    fn seeded_bed() -> Simulation {
+        let mut simulation = Simulation::new(32, 40);
+        simulation.set_seed(4);
+        for y in 24..40 {
+            for x in 0..32 {
+                simulation.add_particle(x, y, MaterialType::WetSand, None);
+            }
+        }
+        simulation.add_particle(16, 23, MaterialType::Seed, None);
+        simulation.add_particle(17, 23, MaterialType::Water, None);
+        // Penned in so the water can't wander off along the surface
+        simulation.add_particle(18, 23, MaterialType::Stone, None);
+        // Water deep in the sand off to one side for the roots to find, capped so the
+        // sand above doesn't sink through it
+        simulation.remove_particle(22, 33);
+        simulation.add_particle(22, 33, MaterialType::Stone, None);
+        for y in 34..36 {
+            simulation.remove_particle(22, y);
+            simulation.add_particle(22, y, MaterialType::Water, None);
+        }
+        simulation
+    }
+
+    #[test]
+    fn test_seed_sprouts_into_stem_and_roots() {
+        let mut simulation = seeded_bed();
+        for _ in 0..1200 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        let count = |material| simulation.iter_particles().filter(|p| p.material_type == material).count();
+        assert_eq!(count(MaterialType::Seed), 0);
+        assert_eq!(count(MaterialType::Stem), 12);
+        assert_eq!(count(MaterialType::Root), 6);
+        // Stems grow up from the seed and roots down into the sand, bending towards the water
+        assert!(simulation.iter_particles().filter(|p| p.material_type == MaterialType::Stem).all(|p| p.y <= 23));
+        let roots: Vec<_> = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Root).collect();
+        assert!(roots.iter().all(|p| p.y >= 24));
+        assert!(roots.iter().any(|p| p.x > 16));
+    }
+
+    #[test]
+    fn test_dry_seed_stays_dormant() {
+        let mut simulation = seeded_bed();
+        simulation.remove_particle(17, 23);
+        for _ in 0..600 {
+            simulation.update(1.0 / 60.0);
+        }
+        assert!(simulation.iter_particles().any(|p| p.material_type == MaterialType::Seed));
+        assert!(simulation.iter_particles().all(|p| p.material_type != MaterialType::Stem));
+
+        // Across the edge of a wrapped world, water on the far side is right beside it
+        let mut wrapped = Simulation::new(8, 8);
+        wrapped.add_particle(7, 3, MaterialType::Water, None);
+        assert!(!wrapped.touches_water(0, 3));
+        wrapped.set_bounds(WorldBounds::Wrap);
+        assert!(wrapped.touches_water(0, 3));
+    }
+
+    #[test]
+    #[cfg(feature = "files")]
+    fn test_load_plant_file() {
+        let materials = MaterialRegistry::new();
+        let plants = PlantRegistry::from_file("materials/example_plants.toml", &materials).unwrap();
+        assert!(plants.len() > PlantRegistry::with_defaults().len());
+        // The file's species takes over the built-in seed
+        assert_ne!(plants.for_seed(MaterialType::Seed).unwrap().name, "Sapling");
+
+        let unknown = "[[species]]\nname = \"Fern\"\nseed = \"Spore\"\nmax_height = 3\nroot_depth = 1\ngrowth_rate = 1.0\n";
+        let mut plants = PlantRegistry::new();
+        assert!(matches!(plants.load_toml_str(unknown, &materials), Err(MaterialRegistryError::UnknownMaterial(_))));
+        assert!(plants.is_empty());
+    }
+}
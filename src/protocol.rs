@@ -0,0 +1,526 @@
+use crate::materials::MaterialType;
+use crate::simulation::{GridRect, Simulation};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Wire protocol version spoken by this build
+pub const PROTOCOL_VERSION: u32 = 1;
+/// Oldest client protocol version the server still accepts in a handshake
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Version assumed for clients that connect without a handshake
+pub const LEGACY_PROTOCOL_VERSION: u32 = 0;
+/// Payloads smaller than this many bytes are sent uncompressed
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 4 * 1024;
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 3;
+/// First byte of a binary frame carrying a `GridFrame` rather than compressed JSON
+pub const GRID_FRAME_TAG: u8 = 3;
+const GRID_FRAME_FULL: u8 = 0;
+const GRID_FRAME_DELTA: u8 = 1;
+
+/// Optional protocol features a peer may support
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    BinaryDeltas,
+    EntityReplication,
+    WebRtc,
+    /// `effects` messages with the sparks, splashes and embers to draw over the grid
+    Effects,
+}
+
+/// Per-message compression codecs a peer can decode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompressionCodec {
+    Zstd,
+    Gzip,
+}
+
+impl CompressionCodec {
+    /// First byte of a binary frame carrying a payload compressed with this codec
+    pub fn frame_tag(self) -> u8 {
+        match self {
+            CompressionCodec::Zstd => 1,
+            CompressionCodec::Gzip => 2,
+        }
+    }
+
+    pub fn from_frame_tag(tag: u8) -> Option<Self> {
+        match tag {
+            1 => Some(CompressionCodec::Zstd),
+            2 => Some(CompressionCodec::Gzip),
+            _ => None,
+        }
+    }
+
+    pub fn compress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd => zstd::encode_all(data, ZSTD_LEVEL),
+            #[cfg(not(feature = "zstd"))]
+            CompressionCodec::Zstd => Err(zstd_unavailable()),
+            CompressionCodec::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    pub fn decompress(self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd => zstd::decode_all(data),
+            #[cfg(not(feature = "zstd"))]
+            CompressionCodec::Zstd => Err(zstd_unavailable()),
+            CompressionCodec::Gzip => {
+                let mut decoded = Vec::new();
+                GzDecoder::new(data).read_to_end(&mut decoded)?;
+                Ok(decoded)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd_unavailable() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::Unsupported, "built without zstd support")
+}
+
+/// A JSON message ready for the socket: sent as a text frame, or as a binary frame of
+/// one codec tag byte followed by the compressed JSON
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Payload {
+    /// Compress `json` with `codec` when it is at least `threshold` bytes and compression
+    /// actually shrinks it; otherwise keep it as text
+    pub fn encode(json: String, codec: Option<CompressionCodec>, threshold: usize) -> Self {
+        let Some(codec) = codec.filter(|_| json.len() >= threshold) else {
+            return Payload::Text(json);
+        };
+        match codec.compress(json.as_bytes()) {
+            Ok(compressed) if compressed.len() + 1 < json.len() => {
+                let mut frame = Vec::with_capacity(compressed.len() + 1);
+                frame.push(codec.frame_tag());
+                frame.extend_from_slice(&compressed);
+                Payload::Binary(frame)
+            }
+            _ => Payload::Text(json),
+        }
+    }
+
+    /// Recover the JSON text of a payload
+    pub fn decode(&self) -> std::io::Result<String> {
+        let bytes = match self {
+            Payload::Text(text) => return Ok(text.clone()),
+            Payload::Binary(frame) => {
+                let (&tag, body) = frame.split_first().ok_or_else(|| invalid_data("empty frame"))?;
+                let codec = CompressionCodec::from_frame_tag(tag).ok_or_else(|| invalid_data("unknown codec tag"))?;
+                codec.decompress(body)?
+            }
+        };
+        String::from_utf8(bytes).map_err(|_| invalid_data("payload is not UTF-8"))
+    }
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Two-byte material id used by grid frames, `MaterialType::id`
+pub fn material_code(material: MaterialType) -> u16 {
+    material.id()
+}
+
+pub fn material_from_code(code: u16) -> Option<MaterialType> {
+    MaterialType::from_id(code)
+}
+
+/// One changed cell of a binary delta
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellUpdate {
+    pub x: usize,
+    pub y: usize,
+    pub material: MaterialType,
+    pub temp: f32,
+    pub color: [u8; 3],
+}
+
+/// Grid state in the binary protocol negotiated with `Capability::BinaryDeltas`, about an
+/// order of magnitude smaller than the JSON messages with their `"x,y"` keys. Frames start
+/// with `GRID_FRAME_TAG` and a kind byte; all numbers are little-endian.
+///
+/// - Full: `width: u16, height: u16`, then per row `runs: u16` and that many
+///   `(material: u16, length: u16)` runs covering the row
+/// - Delta: `added: u32, removed: u32, keyframes: u16`, then the added cells as
+///   `(x: u16, y: u16, material: u16, temp: f32, r, g, b)`, removed cells as `(x: u16, y: u16)`
+///   and keyframed chunks as `(x, y, width, height: u16)`
+#[derive(Debug, Clone, PartialEq)]
+pub enum GridFrame {
+    /// Material of every cell in row-major order, `Empty` where there is nothing
+    Full { width: usize, height: usize, cells: Vec<MaterialType> },
+    /// Changes since the previous frame; keyframed chunks are cleared before `added` applies
+    Delta { added: Vec<CellUpdate>, removed: Vec<(usize, usize)>, keyframes: Vec<GridRect> },
+}
+
+impl GridFrame {
+    pub fn full(simulation: &Simulation) -> Self {
+        let mut cells = vec![MaterialType::Empty; simulation.width * simulation.height];
+        for particle in simulation.iter_particles() {
+            cells[particle.y * simulation.width + particle.x] = particle.material_type;
+        }
+        GridFrame::Full { width: simulation.width, height: simulation.height, cells }
+    }
+
+    /// Serialize the frame; fails for grids or counts too large for the wire format
+    pub fn encode(&self) -> std::io::Result<Vec<u8>> {
+        let mut frame = vec![GRID_FRAME_TAG];
+        match self {
+            GridFrame::Full { width, height, cells } => {
+                if cells.len() != width * height {
+                    return Err(invalid_input("cell count does not match the grid size"));
+                }
+                frame.push(GRID_FRAME_FULL);
+                put_u16(&mut frame, *width)?;
+                put_u16(&mut frame, *height)?;
+                for row in cells.chunks(*width.max(&1)) {
+                    let mut runs: Vec<(MaterialType, usize)> = Vec::new();
+                    for &material in row {
+                        match runs.last_mut() {
+                            Some((run_material, length)) if *run_material == material => *length += 1,
+                            _ => runs.push((material, 1)),
+                        }
+                    }
+                    put_u16(&mut frame, runs.len())?;
+                    for (material, length) in runs {
+                        frame.extend_from_slice(&material_code(material).to_le_bytes());
+                        put_u16(&mut frame, length)?;
+                    }
+                }
+            }
+            GridFrame::Delta { added, removed, keyframes } => {
+                frame.push(GRID_FRAME_DELTA);
+                for count in [added.len(), removed.len()] {
+                    let count = u32::try_from(count).map_err(|_| invalid_input("too many cells"))?;
+                    frame.extend_from_slice(&count.to_le_bytes());
+                }
+                put_u16(&mut frame, keyframes.len())?;
+                for cell in added {
+                    put_u16(&mut frame, cell.x)?;
+                    put_u16(&mut frame, cell.y)?;
+                    frame.extend_from_slice(&material_code(cell.material).to_le_bytes());
+                    frame.extend_from_slice(&cell.temp.to_le_bytes());
+                    frame.extend_from_slice(&cell.color);
+                }
+                for &(x, y) in removed {
+                    put_u16(&mut frame, x)?;
+                    put_u16(&mut frame, y)?;
+                }
+                for rect in keyframes {
+                    for value in [rect.x, rect.y, rect.width, rect.height] {
+                        put_u16(&mut frame, value)?;
+                    }
+                }
+            }
+        }
+        Ok(frame)
+    }
+
+    pub fn decode(frame: &[u8]) -> std::io::Result<Self> {
+        let mut reader = FrameReader { bytes: frame };
+        if reader.u8()? != GRID_FRAME_TAG {
+            return Err(invalid_data("not a grid frame"));
+        }
+        let decoded = match reader.u8()? {
+            GRID_FRAME_FULL => {
+                let (width, height) = (reader.u16()?, reader.u16()?);
+                let mut cells = Vec::with_capacity(width * height);
+                for row in 0..height {
+                    for _ in 0..reader.u16()? {
+                        let material = reader.material()?;
+                        let length = reader.u16()?;
+                        cells.extend(std::iter::repeat_n(material, length));
+                    }
+                    if cells.len() != (row + 1) * width {
+                        return Err(invalid_data("runs do not cover the row"));
+                    }
+                }
+                GridFrame::Full { width, height, cells }
+            }
+            GRID_FRAME_DELTA => {
+                let (added, removed, keyframes) = (reader.u32()?, reader.u32()?, reader.u16()?);
+                let added = (0..added)
+                    .map(|_| Ok(CellUpdate {
+                        x: reader.u16()?,
+                        y: reader.u16()?,
+                        material: reader.material()?,
+                        temp: f32::from_le_bytes(reader.take()?),
+                        color: reader.take()?,
+                    }))
+                    .collect::<std::io::Result<_>>()?;
+                let removed = (0..removed)
+                    .map(|_| Ok((reader.u16()?, reader.u16()?)))
+                    .collect::<std::io::Result<_>>()?;
+                let keyframes = (0..keyframes)
+                    .map(|_| Ok(GridRect::new(reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?)))
+                    .collect::<std::io::Result<_>>()?;
+                GridFrame::Delta { added, removed, keyframes }
+            }
+            _ => return Err(invalid_data("unknown grid frame kind")),
+        };
+        if !reader.bytes.is_empty() {
+            return Err(invalid_data("trailing bytes after grid frame"));
+        }
+        Ok(decoded)
+    }
+}
+
+fn put_u16(frame: &mut Vec<u8>, value: usize) -> std::io::Result<()> {
+    let value = u16::try_from(value).map_err(|_| invalid_input("value does not fit in 16 bits"))?;
+    frame.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn invalid_input(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+}
+
+struct FrameReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl FrameReader<'_> {
+    fn take<const N: usize>(&mut self) -> std::io::Result<[u8; N]> {
+        let (head, rest) = self.bytes.split_first_chunk::<N>().ok_or_else(|| invalid_data("truncated grid frame"))?;
+        self.bytes = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> std::io::Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> std::io::Result<usize> {
+        Ok(u16::from_le_bytes(self.take()?) as usize)
+    }
+
+    fn u32(&mut self) -> std::io::Result<usize> {
+        Ok(u32::from_le_bytes(self.take()?) as usize)
+    }
+
+    fn material(&mut self) -> std::io::Result<MaterialType> {
+        let code = u16::from_le_bytes(self.take()?);
+        material_from_code(code).ok_or_else(|| invalid_data("unknown material code"))
+    }
+}
+
+/// Handshake sent by a client right after connecting. Lists are in preference order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol_version: u32,
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    #[serde(default)]
+    pub codecs: Vec<CompressionCodec>,
+    /// Display name shown to other clients
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// What the server side of a connection is able to speak
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ServerFeatures {
+    pub capabilities: Vec<Capability>,
+    pub codecs: Vec<CompressionCodec>,
+}
+
+/// Agreed protocol for one connection; the server encodes everything it sends to the
+/// client according to this.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub protocol_version: u32,
+    pub capabilities: Vec<Capability>,
+    pub codec: Option<CompressionCodec>,
+}
+
+impl Session {
+    /// Session for a client that never sent a `Hello`: plain JSON, legacy messages only
+    pub fn legacy() -> Self {
+        Self {
+            protocol_version: LEGACY_PROTOCOL_VERSION,
+            capabilities: Vec::new(),
+            codec: None,
+        }
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.protocol_version == LEGACY_PROTOCOL_VERSION
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::legacy()
+    }
+}
+
+/// Pick the highest common version, the shared capabilities and the client's most
+/// preferred codec the server also supports
+pub fn negotiate(hello: &Hello, server: &ServerFeatures) -> Result<Session, HandshakeError> {
+    if hello.protocol_version < MIN_PROTOCOL_VERSION {
+        return Err(HandshakeError::UnsupportedVersion {
+            client: hello.protocol_version,
+            min: MIN_PROTOCOL_VERSION,
+            max: PROTOCOL_VERSION,
+        });
+    }
+
+    let mut capabilities = Vec::new();
+    for capability in &hello.capabilities {
+        if server.capabilities.contains(capability) && !capabilities.contains(capability) {
+            capabilities.push(*capability);
+        }
+    }
+
+    Ok(Session {
+        protocol_version: hello.protocol_version.min(PROTOCOL_VERSION),
+        capabilities,
+        codec: hello.codecs.iter().copied().find(|codec| server.codecs.contains(codec)),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandshakeError {
+    UnsupportedVersion { client: u32, min: u32, max: u32 },
+}
+
+impl std::fmt::Display for HandshakeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandshakeError::UnsupportedVersion { client, min, max } => write!(
+                f,
+                "Protocol version {} is not supported (server accepts {} to {})",
+                client, min, max
+            ),
+        }
+    }
+}
+
+impl std::error::Error for HandshakeError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_intersects_features() {
+        let hello = Hello {
+            protocol_version: PROTOCOL_VERSION + 3,
+            capabilities: vec![Capability::WebRtc, Capability::BinaryDeltas],
+            codecs: vec![CompressionCodec::Gzip, CompressionCodec::Zstd],
+            name: None,
+        };
+        let server = ServerFeatures {
+            capabilities: vec![Capability::BinaryDeltas, Capability::EntityReplication],
+            codecs: vec![CompressionCodec::Zstd],
+        };
+
+        let session = negotiate(&hello, &server).unwrap();
+        assert_eq!(session.protocol_version, PROTOCOL_VERSION);
+        assert_eq!(session.capabilities, vec![Capability::BinaryDeltas]);
+        assert_eq!(session.codec, Some(CompressionCodec::Zstd));
+        assert!(!session.supports(Capability::WebRtc));
+    }
+
+    #[test]
+    fn test_reject_old_clients() {
+        let hello = Hello {
+            protocol_version: LEGACY_PROTOCOL_VERSION,
+            capabilities: Vec::new(),
+            codecs: Vec::new(),
+            name: None,
+        };
+        let error = negotiate(&hello, &ServerFeatures::default()).unwrap_err();
+        assert_eq!(
+            error,
+            HandshakeError::UnsupportedVersion { client: 0, min: MIN_PROTOCOL_VERSION, max: PROTOCOL_VERSION }
+        );
+    }
+
+    #[test]
+    fn test_payload_compression_threshold() {
+        let small = r#"{"type":"delta_update"}"#.to_string();
+        assert_eq!(Payload::encode(small.clone(), Some(CompressionCodec::Zstd), 64), Payload::Text(small));
+
+        let large = format!(r#"{{"particles":"{}"}}"#, "10,20 Sand ".repeat(200));
+        for codec in [#[cfg(feature = "zstd")] CompressionCodec::Zstd, CompressionCodec::Gzip] {
+            let payload = Payload::encode(large.clone(), Some(codec), 64);
+            let Payload::Binary(frame) = &payload else {
+                panic!("expected a compressed frame");
+            };
+            assert_eq!(frame[0], codec.frame_tag());
+            assert!(frame.len() < large.len() / 4);
+            assert_eq!(payload.decode().unwrap(), large);
+        }
+        assert!(matches!(Payload::encode(large, None, 64), Payload::Text(_)));
+    }
+
+    #[test]
+    fn test_full_grid_frame_round_trip() {
+        let mut simulation = Simulation::new(64, 48);
+        for x in 0..64 {
+            simulation.add_particle(x, 47, MaterialType::Stone, None);
+        }
+        simulation.add_particle(10, 46, MaterialType::Water, None);
+        simulation.add_particle(11, 46, MaterialType::Eraser, None);
+
+        let frame = GridFrame::full(&simulation);
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes[0], GRID_FRAME_TAG);
+        // Empty rows are a single run, so the whole grid costs a few hundred bytes
+        assert!(bytes.len() < 400, "{} bytes", bytes.len());
+        assert_eq!(GridFrame::decode(&bytes).unwrap(), frame);
+
+        assert_eq!(material_code(MaterialType::Eraser), 99);
+        assert_eq!(material_from_code(material_code(MaterialType::Custom(7))), Some(MaterialType::Custom(7)));
+        assert_eq!(material_from_code(50), None);
+    }
+
+    #[test]
+    fn test_delta_grid_frame_round_trip() {
+        let added: Vec<CellUpdate> = (0..100)
+            .map(|i| CellUpdate { x: i, y: 120, material: MaterialType::Sand, temp: 20.5, color: [194, 178, 128] })
+            .collect();
+        let frame = GridFrame::Delta {
+            added,
+            removed: vec![(3, 4), (199, 149)],
+            keyframes: vec![GridRect::new(16, 32, 16, 16)],
+        };
+        let bytes = frame.encode().unwrap();
+        assert_eq!(bytes.len(), 2 + 10 + 100 * 13 + 2 * 4 + 8);
+        assert_eq!(GridFrame::decode(&bytes).unwrap(), frame);
+
+        assert!(GridFrame::decode(&bytes[..bytes.len() - 1]).is_err());
+        let too_wide = GridFrame::Full { width: 70_000, height: 1, cells: vec![MaterialType::Empty; 70_000] };
+        assert!(too_wide.encode().is_err());
+    }
+
+    #[test]
+    fn test_hello_wire_format() {
+        let hello: Hello = serde_json::from_str(r#"{"protocol_version": 1, "capabilities": ["binary_deltas"]}"#).unwrap();
+        assert_eq!(hello.capabilities, vec![Capability::BinaryDeltas]);
+        assert!(hello.codecs.is_empty());
+        assert!(Session::default().is_legacy());
+    }
+}
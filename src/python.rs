@@ -0,0 +1,259 @@
+use crate::material_registry::MaterialRegistry;
+use crate::materials::MaterialType;
+use crate::protocol::{material_code, material_from_code};
+use crate::simulation::Simulation;
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray2};
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+
+/// A material, e.g. `MaterialType.SAND`. `code` is the number used in `Simulation.materials()`.
+#[pyclass(name = "MaterialType", module = "sand_engine", frozen, eq, hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PyMaterialType(MaterialType);
+
+#[pymethods]
+impl PyMaterialType {
+    /// A built-in material by display or variant name, ignoring case, spaces and underscores
+    #[staticmethod]
+    fn from_name(name: &str) -> PyResult<Self> {
+        MaterialRegistry::new()
+            .lookup(name)
+            .map(Self)
+            .ok_or_else(|| PyValueError::new_err(format!("no material called '{}'", name)))
+    }
+
+    #[staticmethod]
+    fn from_code(code: u16) -> PyResult<Self> {
+        material_from_code(code).map(Self).ok_or_else(|| PyValueError::new_err(format!("no material with code {}", code)))
+    }
+
+    #[getter]
+    fn code(&self) -> u16 {
+        material_code(self.0)
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        match self.0 {
+            MaterialType::Custom(id) => format!("Custom({})", id),
+            builtin => MaterialRegistry::new().get(builtin).name.clone(),
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        match self.0 {
+            MaterialType::Custom(id) => format!("MaterialType.from_code({})", material_code(MaterialType::Custom(id))),
+            _ => format!("MaterialType.{}", constant_name(&self.name())),
+        }
+    }
+}
+
+/// A material given from Python either as a `MaterialType` or by name
+#[derive(FromPyObject)]
+enum MaterialArg {
+    Material(PyMaterialType),
+    Name(String),
+}
+
+/// A grid simulation for scripting scenarios. `materials()` and `temperatures()` copy
+/// the grid out as `(height, width)` numpy arrays, indexed `[y, x]` with y pointing down.
+#[pyclass(name = "Simulation", module = "sand_engine")]
+pub struct PySimulation {
+    simulation: Simulation,
+}
+
+impl PySimulation {
+    fn material(&self, material: MaterialArg) -> PyResult<MaterialType> {
+        match material {
+            MaterialArg::Material(material) => Ok(material.0),
+            MaterialArg::Name(name) => self.simulation.materials().lookup(&name)
+                .ok_or_else(|| PyValueError::new_err(format!("no material called '{}'", name))),
+        }
+    }
+
+    fn check_cell(&self, x: usize, y: usize) -> PyResult<()> {
+        if x < self.simulation.width && y < self.simulation.height {
+            Ok(())
+        } else {
+            Err(PyIndexError::new_err(format!("({}, {}) is outside the grid", x, y)))
+        }
+    }
+
+    /// One value per cell in row-major order, shaped `(height, width)`
+    fn grid<T>(&self, value: impl Fn(Option<&crate::particle::Particle>) -> T) -> Array2<T> {
+        let (width, height) = (self.simulation.width, self.simulation.height);
+        let values = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| value(self.simulation.get_particle(x, y)))
+            .collect();
+        Array2::from_shape_vec((height, width), values).expect("one value per cell")
+    }
+}
+
+#[pymethods]
+impl PySimulation {
+    /// An empty grid; with a seed, the same edits always play out the same way
+    #[new]
+    #[pyo3(signature = (width, height, seed = None))]
+    fn new(width: usize, height: usize, seed: Option<u64>) -> PyResult<Self> {
+        if width == 0 || height == 0 {
+            return Err(PyValueError::new_err("the grid needs at least one cell"));
+        }
+        let mut simulation = Simulation::new(width, height);
+        if let Some(seed) = seed {
+            simulation.set_seed(seed);
+        }
+        Ok(Self { simulation })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.simulation.width
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.simulation.height
+    }
+
+    /// Number of completed steps
+    #[getter]
+    fn tick(&self) -> u64 {
+        self.simulation.tick()
+    }
+
+    /// Advance `steps` times by `delta_time` seconds each, releasing the GIL meanwhile
+    #[pyo3(signature = (delta_time = 1.0 / 60.0, steps = 1))]
+    fn step(&mut self, py: Python<'_>, delta_time: f32, steps: usize) {
+        let simulation = &mut self.simulation;
+        py.allow_threads(|| {
+            for _ in 0..steps {
+                simulation.update(delta_time);
+            }
+        });
+    }
+
+    /// Fill a disc around `(x, y)`, returning how many cells were written
+    #[pyo3(signature = (x, y, material, radius = 1))]
+    fn paint(&mut self, x: usize, y: usize, material: MaterialArg, radius: usize) -> PyResult<usize> {
+        let material = self.material(material)?;
+        self.check_cell(x, y)?;
+        Ok(self.simulation.paint(x, y, material, radius))
+    }
+
+    /// Place one particle, at `temperature` degrees if given; False if the cell refused it
+    #[pyo3(signature = (x, y, material, temperature = None))]
+    fn add_particle(&mut self, x: usize, y: usize, material: MaterialArg, temperature: Option<f32>) -> PyResult<bool> {
+        let material = self.material(material)?;
+        self.check_cell(x, y)?;
+        Ok(self.simulation.add_particle(x, y, material, temperature))
+    }
+
+    fn clear(&mut self) {
+        self.simulation.clear();
+    }
+
+    /// Material in cell `(x, y)`; None if it is empty
+    fn material_at(&self, x: usize, y: usize) -> PyResult<Option<PyMaterialType>> {
+        self.check_cell(x, y)?;
+        Ok(self.simulation.get_particle(x, y).map(|particle| PyMaterialType(particle.material_type)))
+    }
+
+    /// Number of cells holding `material`
+    fn count(&self, material: MaterialArg) -> PyResult<usize> {
+        let material = self.material(material)?;
+        Ok(self.simulation.iter_particles().filter(|particle| particle.material_type == material).count())
+    }
+
+    /// Material codes (see `MaterialType.code`) as a uint16 array; 0 is empty
+    fn materials<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<u16>> {
+        self.grid(|particle| particle.map_or(0, |particle| material_code(particle.material_type)))
+            .into_pyarray_bound(py)
+    }
+
+    /// Temperatures in degrees Celsius as a float32 array; NaN where cells are empty
+    fn temperatures<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        self.grid(|particle| particle.map_or(f32::NAN, |particle| particle.temp))
+            .into_pyarray_bound(py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Simulation({}x{}, tick {})", self.simulation.width, self.simulation.height, self.simulation.tick())
+    }
+}
+
+/// "Toxic Gas" as the class attribute `TOXIC_GAS`
+fn constant_name(name: &str) -> String {
+    name.to_uppercase().replace([' ', '-'], "_")
+}
+
+/// The `sand_engine` Python module
+#[pymodule]
+pub fn sand_engine(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PySimulation>()?;
+    module.add_class::<PyMaterialType>()?;
+    let py = module.py();
+    let material_type = py.get_type_bound::<PyMaterialType>();
+    let registry = MaterialRegistry::new();
+    for material in MaterialType::BUILTIN {
+        let name = constant_name(&registry.get(material).name);
+        material_type.setattr(name.as_str(), PyMaterialType(material).into_py(py))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    /// Run `script` with the module imported as `sand_engine`
+    fn run(script: &str) {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "sand_engine").unwrap();
+            sand_engine(&module).unwrap();
+            let globals = PyDict::new_bound(py);
+            globals.set_item("sand_engine", module).unwrap();
+            py.run_bound(script, Some(&globals), None).unwrap();
+        });
+    }
+
+    #[test]
+    fn test_script_paints_and_steps() {
+        run(r#"
+from_name = sand_engine.MaterialType.from_name
+sim = sand_engine.Simulation(32, 32, seed=4)
+assert sim.paint(16, 4, sand_engine.MaterialType.SAND, radius=2) == 13
+assert sim.add_particle(2, 2, "water", temperature=40.0)
+sim.step(steps=120)
+assert sim.tick == 120
+assert sim.count("Sand") == 13
+assert sim.material_at(16, 31) == from_name("sand")
+assert sand_engine.MaterialType.TOXIC_GAS.name == "Toxic Gas"
+assert sand_engine.MaterialType.from_code(sand_engine.MaterialType.SAND.code) == sand_engine.MaterialType.SAND
+try:
+    sim.paint(99, 0, "Sand")
+    raise AssertionError("painted outside the grid")
+except IndexError:
+    pass
+"#);
+    }
+
+    #[test]
+    #[ignore = "needs numpy installed for the Python running the tests"]
+    fn test_grid_arrays() {
+        run(r#"
+import numpy as np
+sim = sand_engine.Simulation(8, 4, seed=1)
+sim.add_particle(3, 1, sand_engine.MaterialType.STONE, temperature=100.0)
+materials = sim.materials()
+assert materials.shape == (4, 8) and materials.dtype == np.uint16
+assert materials[1, 3] == sand_engine.MaterialType.STONE.code
+assert (materials != 0).sum() == 1
+temperatures = sim.temperatures()
+assert temperatures[1, 3] == 100.0 and np.isnan(temperatures[0, 0])
+"#);
+    }
+}
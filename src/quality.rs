@@ -0,0 +1,263 @@
+use crate::simulation::{Simulation, SIM_CHUNK_SIZE};
+use serde::{Deserialize, Serialize};
+
+/// Chunks from the quality focus, on either axis, that still update every tick at
+/// `QualityLevel::Low` and below
+pub const QUALITY_NEAR_CHUNKS: usize = 4;
+/// Consecutive frames of pressure, or of relief, before `AdaptiveQuality` changes level
+pub const QUALITY_CHANGE_FRAMES: u32 = 30;
+/// Default fraction either side of the target frame time and under the particle budget
+/// that `AdaptiveQuality` waits for before acting, so it doesn't flip between levels
+pub const QUALITY_HYSTERESIS: f32 = 0.2;
+/// Weight of the newest frame in the running average frame time
+const FRAME_TIME_SMOOTHING: f32 = 0.1;
+
+/// How much fidelity the simulation gives up to keep up. Each level keeps the
+/// savings of the ones above it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityLevel {
+    /// Everything every tick
+    #[default]
+    Full,
+    /// Temperature updates every other tick, twice as far each time
+    Reduced,
+    /// Chunks more than `QUALITY_NEAR_CHUNKS` from the focus update every other tick
+    Low,
+    /// Liquids spread as fast as they can, ignoring viscosity and surface tension
+    Minimal,
+}
+
+impl QualityLevel {
+    /// Every level, best first
+    pub const ALL: [QualityLevel; 4] = [QualityLevel::Full, QualityLevel::Reduced, QualityLevel::Low, QualityLevel::Minimal];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QualityLevel::Full => "full",
+            QualityLevel::Reduced => "reduced",
+            QualityLevel::Low => "low",
+            QualityLevel::Minimal => "minimal",
+        }
+    }
+
+    pub fn skips_temperature(self) -> bool {
+        self >= QualityLevel::Reduced
+    }
+
+    pub fn halves_distant_chunks(self) -> bool {
+        self >= QualityLevel::Low
+    }
+
+    pub fn simplifies_liquids(self) -> bool {
+        self >= QualityLevel::Minimal
+    }
+
+    /// The next level down, if any
+    pub fn lower(self) -> Option<QualityLevel> {
+        Self::ALL.get(self as usize + 1).copied()
+    }
+
+    /// The next level up, if any
+    pub fn higher(self) -> Option<QualityLevel> {
+        (self as usize).checked_sub(1).map(|index| Self::ALL[index])
+    }
+}
+
+/// Picks a `QualityLevel` from how long frames take and how many particles there are.
+/// It drops a level after `QUALITY_CHANGE_FRAMES` frames in a row over the particle
+/// budget or slower than the target frame time, and climbs back one after as many frames
+/// comfortably under both, by `hysteresis`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdaptiveQuality {
+    /// Seconds a frame should take at most
+    pub target_frame_time: f32,
+    pub particle_budget: usize,
+    pub hysteresis: f32,
+    level: QualityLevel,
+    average_frame_time: f32,
+    // Frames in a row under pressure when positive, under relief when negative
+    streak: i32,
+}
+
+impl AdaptiveQuality {
+    pub fn new(target_frame_time: f32, particle_budget: usize) -> Self {
+        Self {
+            target_frame_time,
+            particle_budget,
+            hysteresis: QUALITY_HYSTERESIS,
+            level: QualityLevel::Full,
+            average_frame_time: 0.0,
+            streak: 0,
+        }
+    }
+
+    pub fn level(&self) -> QualityLevel {
+        self.level
+    }
+
+    /// Running average of the frame times recorded, in seconds
+    pub fn average_frame_time(&self) -> f32 {
+        self.average_frame_time
+    }
+
+    /// Take a frame's time and the particle count after it; returns the level to run at
+    pub fn record(&mut self, frame_time: f32, particle_count: usize) -> QualityLevel {
+        self.average_frame_time += (frame_time - self.average_frame_time) * FRAME_TIME_SMOOTHING;
+        let pressure = particle_count > self.particle_budget
+            || self.average_frame_time > self.target_frame_time * (1.0 + self.hysteresis);
+        let relief = (particle_count as f32) < self.particle_budget as f32 * (1.0 - self.hysteresis)
+            && self.average_frame_time < self.target_frame_time * (1.0 - self.hysteresis);
+        self.streak = match (pressure, relief) {
+            (true, _) => self.streak.max(0) + 1,
+            (false, true) => self.streak.min(0) - 1,
+            (false, false) => 0,
+        };
+
+        let change = if self.streak >= QUALITY_CHANGE_FRAMES as i32 {
+            self.level.lower()
+        } else if self.streak <= -(QUALITY_CHANGE_FRAMES as i32) {
+            self.level.higher()
+        } else {
+            None
+        };
+        if let Some(level) = change {
+            self.level = level;
+            self.streak = 0;
+        }
+        self.level
+    }
+}
+
+impl Simulation {
+    pub fn quality_level(&self) -> QualityLevel {
+        self.quality
+    }
+
+    /// Run later updates at `level`; see `AdaptiveQuality` to pick it automatically
+    pub fn set_quality_level(&mut self, level: QualityLevel) {
+        self.quality = level;
+    }
+
+    /// Cell the "distant" chunks of `QualityLevel::Low` are measured from; the middle of
+    /// the grid unless set
+    pub fn quality_focus(&self) -> (i64, i64) {
+        self.quality_focus.unwrap_or((self.width as i64 / 2, self.height as i64 / 2))
+    }
+
+    pub fn set_quality_focus(&mut self, x: usize, y: usize) {
+        self.quality_focus = Some((x as i64, y as i64));
+    }
+
+    /// Whether `(x, y)` lies in a chunk that only updates every other tick at this level
+    pub(crate) fn is_distant(&self, x: usize, y: usize) -> bool {
+        if !self.quality.halves_distant_chunks() {
+            return false;
+        }
+        let (focus_x, focus_y) = self.quality_focus();
+        let chunk_distance = |cell: usize, focus: i64| (cell as i64).div_euclid(SIM_CHUNK_SIZE as i64).abs_diff(focus.div_euclid(SIM_CHUNK_SIZE as i64));
+        chunk_distance(x, focus_x).max(chunk_distance(y, focus_y)) > QUALITY_NEAR_CHUNKS as u64
+    }
+
+    /// On the ticks distant chunks sit out, take their cells out of `active` and queue
+    /// them for the next tick instead
+    pub(crate) fn defer_distant_cells(&mut self, active: &mut Vec<(usize, usize)>) {
+        if !self.quality.halves_distant_chunks() || self.tick().is_multiple_of(2) {
+            return;
+        }
+        let mut deferred = Vec::new();
+        active.retain(|&(x, y)| {
+            let distant = self.is_distant(x, y);
+            if distant {
+                deferred.push((x, y));
+            }
+            !distant
+        });
+        for (x, y) in deferred {
+            self.wake_cell(x, y);
+        }
+    }
+
+    /// Time step for the cell at `(x, y)`: doubled in distant chunks, which update every
+    /// other tick
+    pub(crate) fn cell_delta(&self, x: usize, y: usize, delta_time: f32) -> f32 {
+        match self.is_distant(x, y) {
+            true => delta_time * 2.0,
+            false => delta_time,
+        }
+    }
+
+    /// Whether the cell at `(x, y)` skips its temperature update this tick
+    pub(crate) fn skips_temperature_at(&self, x: usize, y: usize) -> bool {
+        self.quality.skips_temperature() && !self.tick().is_multiple_of(2) && !self.is_distant(x, y)
+    }
+
+    /// Time step for the temperature of the cell at `(x, y)`: nothing on the ticks it
+    /// skips, and double the cell's step on the ticks after, to make up for them
+    pub(crate) fn thermal_delta(&self, x: usize, y: usize, delta_time: f32) -> f32 {
+        if !self.quality.skips_temperature() || self.is_distant(x, y) {
+            delta_time
+        } else if self.skips_temperature_at(x, y) {
+            0.0
+        } else {
+            delta_time * 2.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    #[test]
+    fn test_adaptive_quality_degrades_under_load_and_recovers_with_hysteresis() {
+        let mut quality = AdaptiveQuality::new(1.0 / 60.0, 1000);
+        let mut run = |frames: u32, frame_time: f32, particles: usize| {
+            (0..frames).map(|_| quality.record(frame_time, particles)).last().unwrap()
+        };
+
+        assert_eq!(run(100, 0.005, 500), QualityLevel::Full);
+        // Over budget: one level down per QUALITY_CHANGE_FRAMES, down to the last
+        assert_eq!(run(QUALITY_CHANGE_FRAMES, 0.005, 5000), QualityLevel::Reduced);
+        assert_eq!(run(QUALITY_CHANGE_FRAMES * 10, 0.005, 5000), QualityLevel::Minimal);
+        // Just under the budget isn't enough to climb back
+        assert_eq!(run(200, 0.005, 950), QualityLevel::Minimal);
+        assert_eq!(run(QUALITY_CHANGE_FRAMES, 0.005, 500), QualityLevel::Low);
+        // Slow frames push it down even with few particles
+        assert_eq!(run(200, 0.1, 500), QualityLevel::Minimal);
+    }
+
+    #[test]
+    fn test_distant_chunks_and_temperature_update_every_other_tick() {
+        let mut simulation = Simulation::new(256, 32);
+        simulation.set_seed(1);
+        simulation.set_quality_focus(8, 8);
+        simulation.set_quality_level(QualityLevel::Low);
+        for x in [8, 240] {
+            simulation.add_particle(x, 0, MaterialType::Sand, None);
+        }
+        simulation.add_particle(20, 31, MaterialType::Stone, Some(500.0));
+
+        let mut moves = [0, 0];
+        let mut temps = Vec::new();
+        for _ in 0..6 {
+            let before: Vec<usize> = [8, 240].iter()
+                .map(|&x| simulation.iter_particles().find(|p| p.material_type == MaterialType::Sand && p.x.abs_diff(x) < 4).unwrap().y)
+                .collect();
+            simulation.update(1.0 / 60.0);
+            for (index, &x) in [8, 240].iter().enumerate() {
+                let after = simulation.iter_particles().find(|p| p.material_type == MaterialType::Sand && p.x.abs_diff(x) < 4).unwrap().y;
+                if after != before[index] {
+                    moves[index] += 1;
+                }
+            }
+            temps.push(simulation.get_particle(20, 31).unwrap().temp);
+        }
+        // The grain near the focus falls every tick, the distant one every other tick
+        assert_eq!(moves, [6, 3]);
+        // The stone cools on even ticks only
+        assert!(temps[1] == temps[0] && temps[3] == temps[2], "{:?}", temps);
+        assert!(temps[2] < temps[1], "{:?}", temps);
+    }
+}
@@ -0,0 +1,305 @@
+use crate::chunk::{ChunkManager, CHUNK_SIZE};
+use crate::engine_v2::AdvancedPhysicsEngine;
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::simulation::{GridRect, Simulation};
+use std::borrow::Cow;
+
+/// The first occupied cell along a ray
+#[derive(Debug, Clone)]
+pub struct RaycastHit<'a> {
+    pub x: i64,
+    pub y: i64,
+    /// How far along the ray, in cells, it entered the cell; 0 if it started there
+    pub distance: f32,
+    /// Borrowed from a `Simulation` grid; chunk storage builds the particle on read
+    pub particle: Cow<'a, Particle>,
+}
+
+/// The cells a ray passes through, in order, each with the distance at which the ray
+/// enters it (Amanatides & Woo). Cell `(x, y)` covers `x..x + 1` by `y..y + 1`, so a ray
+/// from a cell's centre starts at `(x + 0.5, y + 0.5)`. A zero or non-finite direction
+/// visits only the starting cell.
+#[derive(Debug, Clone)]
+pub struct RayCells {
+    cell: (i64, i64),
+    step: (i64, i64),
+    /// Distance at which the ray crosses the next vertical and horizontal cell boundary
+    next_crossing: (f32, f32),
+    /// Distance between successive crossings on each axis
+    crossing_interval: (f32, f32),
+    distance: f32,
+    max_distance: f32,
+    moving: bool,
+}
+
+impl RayCells {
+    pub fn new(from: (f32, f32), direction: (f32, f32), max_distance: f32) -> Self {
+        let length = direction.0.hypot(direction.1);
+        let moving = length > 0.0 && length.is_finite();
+        let (dx, dy) = if moving { (direction.0 / length, direction.1 / length) } else { (0.0, 0.0) };
+        let cell = (from.0.floor() as i64, from.1.floor() as i64);
+        let axis = |d: f32, from: f32, cell: i64| -> (i64, f32, f32) {
+            if d > 0.0 {
+                (1, (cell as f32 + 1.0 - from) / d, 1.0 / d)
+            } else if d < 0.0 {
+                (-1, (from - cell as f32) / -d, -1.0 / d)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, next_x, interval_x) = axis(dx, from.0, cell.0);
+        let (step_y, next_y, interval_y) = axis(dy, from.1, cell.1);
+        Self {
+            cell,
+            step: (step_x, step_y),
+            next_crossing: (next_x, next_y),
+            crossing_interval: (interval_x, interval_y),
+            distance: 0.0,
+            max_distance,
+            moving,
+        }
+    }
+}
+
+impl Iterator for RayCells {
+    type Item = ((i64, i64), f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.distance > self.max_distance || self.max_distance.is_nan() {
+            return None;
+        }
+        let visited = (self.cell, self.distance);
+        if !self.moving {
+            self.distance = f32::INFINITY;
+        } else if self.next_crossing.0 < self.next_crossing.1 {
+            self.cell.0 += self.step.0;
+            self.distance = self.next_crossing.0;
+            self.next_crossing.0 += self.crossing_interval.0;
+        } else {
+            self.cell.1 += self.step.1;
+            self.distance = self.next_crossing.1;
+            self.next_crossing.1 += self.crossing_interval.1;
+        }
+        Some(visited)
+    }
+}
+
+/// Farthest a ray from `from` can travel and still be inside the box `min..max`
+fn reach_into(from: (f32, f32), min: (i64, i64), max: (i64, i64)) -> f32 {
+    let far_x = (from.0 - min.0 as f32).abs().max((max.0 as f32 - from.0).abs());
+    let far_y = (from.1 - min.1 as f32).abs().max((max.1 as f32 - from.1).abs());
+    far_x.hypot(far_y)
+}
+
+impl Simulation {
+    /// The first occupied cell a ray from `from` along `direction` reaches within
+    /// `max_distance` cells, counting the cell it starts in
+    pub fn raycast(&self, from: (f32, f32), direction: (f32, f32), max_distance: f32) -> Option<RaycastHit<'_>> {
+        let reach = reach_into(from, (0, 0), (self.width as i64, self.height as i64));
+        RayCells::new(from, direction, max_distance.min(reach)).find_map(|((x, y), distance)| {
+            if x < 0 || y < 0 {
+                return None;
+            }
+            self.get_particle(x as usize, y as usize).map(|particle| RaycastHit { x, y, distance, particle: Cow::Borrowed(particle) })
+        })
+    }
+
+    /// Particles inside `rect` for which `predicate` holds, in row-major order
+    pub fn box_query<'a>(&'a self, rect: GridRect, predicate: impl Fn(&Particle) -> bool + 'a) -> impl Iterator<Item = &'a Particle> + 'a {
+        self.iter_region(rect).filter(move |particle| predicate(particle))
+    }
+
+    /// The particle of `material` closest to `(x, y)` in straight-line distance, searching
+    /// outwards ring by ring; ties go to the first found
+    pub fn nearest_material(&self, x: usize, y: usize, material: MaterialType) -> Option<&Particle> {
+        let (cx, cy) = (x as i64, y as i64);
+        let mut best: Option<(i64, &Particle)> = None;
+        let rings = self.width.max(self.height) as i64;
+        for radius in 0..=rings {
+            // Every cell of this ring is at least `radius` away
+            if best.is_some_and(|(distance, _)| radius * radius > distance) {
+                break;
+            }
+            for (x, y) in ring(cx, cy, radius) {
+                if x < 0 || y < 0 {
+                    continue;
+                }
+                let Some(particle) = self.get_particle(x as usize, y as usize) else { continue };
+                let distance = (x - cx).pow(2) + (y - cy).pow(2);
+                if particle.material_type == material && best.is_none_or(|(best, _)| distance < best) {
+                    best = Some((distance, particle));
+                }
+            }
+        }
+        best.map(|(_, particle)| particle)
+    }
+}
+
+/// The cells at Chebyshev distance `radius` from `(cx, cy)`
+fn ring(cx: i64, cy: i64, radius: i64) -> impl Iterator<Item = (i64, i64)> {
+    let side = (-radius..=radius).flat_map(move |offset| {
+        let edges = [(cx + offset, cy - radius), (cx + offset, cy + radius)];
+        edges.into_iter().take(if radius == 0 { 1 } else { 2 })
+    });
+    let middle = (1 - radius..radius).flat_map(move |offset| [(cx - radius, cy + offset), (cx + radius, cy + offset)]);
+    side.chain(middle)
+}
+
+impl ChunkManager {
+    /// The first occupied cell a ray from `from` along `direction` reaches within
+    /// `max_distance` cells, counting the cell it starts in; unloaded chunks are empty
+    pub fn raycast(&self, from: (f32, f32), direction: (f32, f32), max_distance: f32) -> Option<RaycastHit<'_>> {
+        let (min, max) = self.loaded_bounds()?;
+        RayCells::new(from, direction, max_distance.min(reach_into(from, min, max)))
+            .find_map(|((x, y), distance)| self.get_particle(x, y).map(|particle| RaycastHit { x, y, distance, particle: Cow::Owned(particle) }))
+    }
+
+    /// Particles with world positions in `min..max` (max exclusive) for which `predicate`
+    /// holds, visiting only the chunks that overlap the region
+    pub fn box_query<'a>(
+        &'a self,
+        min: (i64, i64),
+        max: (i64, i64),
+        predicate: impl Fn(&Particle) -> bool + 'a,
+    ) -> impl Iterator<Item = ((i64, i64), Particle)> + 'a {
+        self.iter_region(min, max).filter(move |(_, particle)| predicate(particle))
+    }
+
+    /// The particle of `material` closest to `(x, y)` among the loaded chunks, searching
+    /// outwards a ring of chunks at a time; ties go to the first found
+    pub fn nearest_material(&self, x: i64, y: i64, material: MaterialType) -> Option<((i64, i64), Particle)> {
+        let (min, max) = self.loaded_bounds()?;
+        let center = Self::world_to_chunk_pos(x, y);
+        let (min_key, max_key) = (Self::world_to_chunk_pos(min.0, min.1), Self::world_to_chunk_pos(max.0 - 1, max.1 - 1));
+        let rings = [center.0 - min_key.0, max_key.0 - center.0, center.1 - min_key.1, max_key.1 - center.1]
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+            .max(0) as i64;
+        let mut best: Option<(i64, (i64, i64))> = None;
+        for radius in 0..=rings {
+            // Chunks in this ring are at least `radius - 1` whole chunks away
+            let nearest_possible = (radius - 1).max(0) * CHUNK_SIZE as i64;
+            if best.is_some_and(|(distance, _)| nearest_possible * nearest_possible > distance) {
+                break;
+            }
+            for (cx, cy) in ring(i64::from(center.0), i64::from(center.1), radius) {
+                let Some(chunk) = self.get_chunk((cx as i32, cy as i32)) else { continue };
+                for local_y in 0..CHUNK_SIZE {
+                    for local_x in 0..CHUNK_SIZE {
+                        if chunk.material_at(local_x, local_y) != Some(material) {
+                            continue;
+                        }
+                        let position = chunk.world_pos(local_x, local_y);
+                        let distance = (position.0 - x).pow(2) + (position.1 - y).pow(2);
+                        if best.is_none_or(|(best, _)| distance < best) {
+                            best = Some((distance, position));
+                        }
+                    }
+                }
+            }
+        }
+        best.and_then(|(_, position)| Some((position, self.get_particle(position.0, position.1)?)))
+    }
+
+    /// World-space box `min..max` covering every loaded chunk, if any are loaded
+    fn loaded_bounds(&self) -> Option<((i64, i64), (i64, i64))> {
+        let mut keys = self.chunks_iter().map(|(key, _)| *key);
+        let first = keys.next()?;
+        let (min_key, max_key) = keys.fold((first, first), |(min, max), (cx, cy)| {
+            ((min.0.min(cx), min.1.min(cy)), (max.0.max(cx), max.1.max(cy)))
+        });
+        let size = CHUNK_SIZE as i64;
+        Some((
+            (min_key.0 as i64 * size, min_key.1 as i64 * size),
+            ((max_key.0 as i64 + 1) * size, (max_key.1 as i64 + 1) * size),
+        ))
+    }
+}
+
+impl AdvancedPhysicsEngine {
+    /// See `ChunkManager::raycast`
+    pub fn raycast(&self, from: (f32, f32), direction: (f32, f32), max_distance: f32) -> Option<RaycastHit<'_>> {
+        self.chunk_manager.raycast(from, direction, max_distance)
+    }
+
+    /// See `ChunkManager::box_query`
+    pub fn box_query<'a>(
+        &'a self,
+        min: (i64, i64),
+        max: (i64, i64),
+        predicate: impl Fn(&Particle) -> bool + 'a,
+    ) -> impl Iterator<Item = ((i64, i64), Particle)> + 'a {
+        self.chunk_manager.box_query(min, max, predicate)
+    }
+
+    /// See `ChunkManager::nearest_material`. The chunks are searched directly rather than
+    /// through `neighbor_cache`, which holds at most 64 particles per 32-cell bucket and is
+    /// resynced only every 10 frames, so it would miss cells in dense or changing areas.
+    pub fn nearest_material(&self, x: i64, y: i64, material: MaterialType) -> Option<((i64, i64), Particle)> {
+        self.chunk_manager.nearest_material(x, y, material)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ray_cells_walk_the_grid() {
+        let cells: Vec<(i64, i64)> = RayCells::new((0.5, 0.5), (1.0, 0.0), 3.0).map(|(cell, _)| cell).collect();
+        assert_eq!(cells, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+
+        let cells: Vec<((i64, i64), f32)> = RayCells::new((0.5, 0.25), (-1.0, 1.0), 1.5).collect();
+        assert_eq!(cells.iter().map(|(cell, _)| *cell).collect::<Vec<_>>(), vec![(0, 0), (-1, 0), (-1, 1)]);
+        assert!((cells[1].1 - 0.5 * std::f32::consts::SQRT_2).abs() < 1e-5);
+
+        assert_eq!(RayCells::new((2.5, 2.5), (0.0, 0.0), 10.0).count(), 1);
+    }
+
+    #[test]
+    fn test_simulation_queries() {
+        let mut simulation = Simulation::new(32, 32);
+        simulation.add_particle(10, 4, MaterialType::Stone, None);
+        simulation.add_particle(20, 4, MaterialType::Stone, None);
+        simulation.add_particle(3, 30, MaterialType::Water, None);
+
+        let hit = simulation.raycast((0.5, 4.5), (1.0, 0.0), 100.0).unwrap();
+        assert_eq!((hit.x, hit.y, hit.distance), (10, 4, 9.5));
+        assert!(simulation.raycast((0.5, 4.5), (1.0, 0.0), 9.0).is_none());
+        assert!(simulation.raycast((0.5, 4.5), (-1.0, 0.0), f32::INFINITY).is_none());
+        // Rays from outside the grid find it too
+        assert_eq!(simulation.raycast((-5.5, 4.5), (1.0, 0.0), f32::INFINITY).unwrap().x, 10);
+
+        let stone: Vec<usize> = simulation.box_query(GridRect::new(0, 0, 32, 8), |p| p.material_type == MaterialType::Stone)
+            .map(|p| p.x)
+            .collect();
+        assert_eq!(stone, vec![10, 20]);
+
+        assert_eq!(simulation.nearest_material(18, 10, MaterialType::Stone).unwrap().x, 20);
+        assert_eq!(simulation.nearest_material(31, 0, MaterialType::Water).unwrap().y, 30);
+        assert!(simulation.nearest_material(0, 0, MaterialType::Lava).is_none());
+    }
+
+    #[test]
+    fn test_chunk_queries_span_chunks() {
+        let mut engine = AdvancedPhysicsEngine::new();
+        let far = CHUNK_SIZE as i64 * 3 + 5;
+        engine.add_particle(far, -2, MaterialType::Stone, None);
+        engine.add_particle(-40, -2, MaterialType::Stone, None);
+        engine.add_particle(0, 0, MaterialType::Sand, None);
+
+        let hit = engine.raycast((1.5, -1.5), (1.0, 0.0), f32::INFINITY).unwrap();
+        assert_eq!((hit.x, hit.y), (far, -2));
+        assert!(engine.raycast((1.5, -1.5), (0.0, -1.0), f32::INFINITY).is_none());
+
+        let stone = engine.box_query((-100, -10), (far + 1, 10), |p| p.material_type == MaterialType::Stone).count();
+        assert_eq!(stone, 2);
+
+        let (position, _) = engine.nearest_material(0, 0, MaterialType::Stone).unwrap();
+        assert_eq!(position, (-40, -2));
+        assert_eq!(engine.nearest_material(far, 0, MaterialType::Stone).unwrap().0, (far, -2));
+        assert!(engine.nearest_material(0, 0, MaterialType::Lava).is_none());
+    }
+}
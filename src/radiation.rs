@@ -0,0 +1,116 @@
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::simulation::Simulation;
+
+/// Cells further than this from a radiating particle receive no heat
+pub const RADIATION_RADIUS: i32 = 3;
+/// Materials only radiate while at least this hot
+pub const RADIATION_MIN_TEMP: f32 = 500.0;
+/// Fraction of the heat handed out that the emitter loses itself
+const EMITTER_LOSS: f32 = 0.05;
+/// Heating below this is dropped so distant radiators don't keep chunks awake
+const MIN_HEATING: f32 = 0.01;
+
+impl Simulation {
+    /// Whether `particle` radiates heat this tick: its material has `radiant_heat` and it
+    /// is above `RADIATION_MIN_TEMP`
+    pub fn radiates(&self, particle: &Particle) -> bool {
+        particle.temp >= RADIATION_MIN_TEMP && self.materials().get(particle.material_type).radiant_heat > 0.0
+    }
+
+    /// Heat every cooler particle within `RADIATION_RADIUS` of a radiating `particle`,
+    /// falling off with the square of the distance, so Lava and Fire warm and melt
+    /// things they don't touch. The emitter gives up a small share of what it hands out.
+    /// Heated particles are woken so settled solids run their melting checks.
+    pub(crate) fn radiate_heat(&mut self, particle: &mut Particle, delta_time: f32) {
+        if !self.radiates(particle) {
+            return;
+        }
+        let rate = self.materials().get(particle.material_type).radiant_heat;
+        let mut given = 0.0;
+        for dy in -RADIATION_RADIUS..=RADIATION_RADIUS {
+            for dx in -RADIATION_RADIUS..=RADIATION_RADIUS {
+                let distance_sq = dx * dx + dy * dy;
+                let (nx, ny) = (particle.x as i32 + dx, particle.y as i32 + dy);
+                if distance_sq == 0 || distance_sq > RADIATION_RADIUS * RADIATION_RADIUS || !self.is_valid(nx, ny) {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                let Some(target) = self.get_particle_mut(nx, ny) else {
+                    continue;
+                };
+                if target.material_type == MaterialType::Empty || target.temp >= particle.temp {
+                    continue;
+                }
+                let heating = rate * (particle.temp - target.temp) * delta_time / distance_sq as f32;
+                if heating < MIN_HEATING {
+                    continue;
+                }
+                target.temp = (target.temp + heating).min(particle.temp);
+                target.settled_frames = 0;
+                target.invalidate_color_cache();
+                given += heating;
+                self.wake_cell(nx, ny);
+                self.mark_changed(nx, ny);
+            }
+        }
+        particle.temp -= given * EMITTER_LOSS;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material_at(simulation: &Simulation, x: usize, y: usize) -> Option<MaterialType> {
+        simulation.get_particle(x, y).map(|particle| particle.material_type)
+    }
+
+    #[test]
+    fn test_lava_heats_cells_it_does_not_touch() {
+        let mut simulation = Simulation::new(20, 20);
+        // Generators never move, so they hold the lava in place
+        for x in 0..20 {
+            simulation.add_particle(x, 19, MaterialType::Generator, Some(20.0));
+        }
+        simulation.add_particle(9, 18, MaterialType::Generator, Some(20.0));
+        simulation.add_particle(11, 18, MaterialType::Generator, Some(20.0));
+        simulation.add_particle(10, 18, MaterialType::Lava, None);
+        simulation.add_particle(13, 18, MaterialType::Stone, Some(20.0));
+        simulation.add_particle(17, 18, MaterialType::Stone, Some(20.0));
+        for _ in 0..60 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        // Both stones sit on warm generators; only the one in range is radiated
+        let near = simulation.get_particle(13, 18).unwrap().temp;
+        let far = simulation.get_particle(17, 18).unwrap().temp;
+        assert!(near > far + 10.0, "near {} far {}", near, far);
+        assert!(!simulation.radiates(simulation.get_particle(13, 18).unwrap()));
+
+        // Radiated cells are redrawn as they warm
+        let tick = simulation.tick();
+        let mut lava = simulation.materials().create_particle(10, 18, MaterialType::Lava, Some(1500.0));
+        simulation.radiate_heat(&mut lava, 1.0 / 60.0);
+        assert!(simulation.iter_changed_since(tick).any(|(x, y, _)| (x, y) == (13, 18)));
+    }
+
+    #[test]
+    fn test_metals_melt_and_resolidify() {
+        let mut simulation = Simulation::new(10, 10);
+        simulation.add_particle(5, 9, MaterialType::Iron, Some(1600.0));
+        simulation.update(1.0 / 60.0);
+        assert_eq!(material_at(&simulation, 5, 9), Some(MaterialType::MoltenIron));
+
+        // Cooling back below its freezing point turns it into Iron again
+        simulation.get_particle_mut(5, 9).unwrap().temp = 1400.0;
+        simulation.update(1.0 / 60.0);
+        assert_eq!(material_at(&simulation, 5, 9), Some(MaterialType::Iron));
+
+        simulation.add_particle(2, 9, MaterialType::Gold, Some(1300.0));
+        simulation.update(1.0 / 60.0);
+        assert_eq!(material_at(&simulation, 2, 9), Some(MaterialType::MoltenGold));
+        let props = simulation.materials().get(MaterialType::MoltenGold);
+        assert!(props.is_liquid(MaterialType::MoltenGold));
+    }
+}
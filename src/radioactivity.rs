@@ -0,0 +1,246 @@
+use crate::materials::MaterialType;
+use crate::simulation::Simulation;
+use rand::Rng;
+
+/// Seconds between radioactivity passes
+pub const RADIOACTIVITY_PASS_INTERVAL: f32 = 0.25;
+/// Cells further than this from a radioactive particle take none of its radiation
+pub const RADIOACTIVE_RADIUS: i32 = 4;
+/// Degrees per second a cell of water warms by per unit of dose
+pub const WATER_HEATING_PER_DOSE: f32 = 100.0;
+/// Chance per second per unit of dose that a plant cell mutates
+pub const MUTATION_CHANCE_PER_DOSE: f32 = 0.2;
+/// What irradiated plants mutate into
+pub const MUTANT_MATERIAL: MaterialType = MaterialType::Slime;
+/// Doses below this are dropped so stray radiation at the edge of the radius does nothing
+const MIN_DOSE: f32 = 0.01;
+
+/// Radiation dose in every cell of a grid, rebuilt from the radioactive particles on each
+/// radioactivity pass. A particle gives each cell within `RADIOACTIVE_RADIUS` its
+/// `radioactivity`, falling off with the square of the distance.
+#[derive(Debug, Clone)]
+pub struct RadiationField {
+    width: usize,
+    height: usize,
+    dose: Vec<f32>,
+}
+
+impl RadiationField {
+    /// No radiation anywhere
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, dose: vec![0.0; width * height] }
+    }
+
+    /// Dose in cell `(x, y)`; zero outside the grid
+    pub fn dose(&self, x: usize, y: usize) -> f32 {
+        if x < self.width && y < self.height {
+            self.dose[y * self.width + x]
+        } else {
+            0.0
+        }
+    }
+
+    /// Strongest dose anywhere on the grid
+    pub fn peak(&self) -> f32 {
+        self.dose.iter().copied().fold(0.0, f32::max)
+    }
+
+    pub fn clear(&mut self) {
+        self.dose.fill(0.0);
+    }
+
+    /// Spread the radiation of an emitter at `(x, y)` over the cells around it
+    pub fn emit(&mut self, x: usize, y: usize, radioactivity: f32) {
+        for dy in -RADIOACTIVE_RADIUS..=RADIOACTIVE_RADIUS {
+            for dx in -RADIOACTIVE_RADIUS..=RADIOACTIVE_RADIUS {
+                let distance_sq = (dx * dx + dy * dy).max(1);
+                let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                if distance_sq > RADIOACTIVE_RADIUS * RADIOACTIVE_RADIUS
+                    || nx < 0 || ny < 0 || nx as usize >= self.width || ny as usize >= self.height
+                {
+                    continue;
+                }
+                let dose = radioactivity / distance_sq as f32;
+                if dose >= MIN_DOSE {
+                    self.dose[ny as usize * self.width + nx as usize] += dose;
+                }
+            }
+        }
+    }
+}
+
+impl Simulation {
+    /// Let radioactive materials irradiate the world: water they reach heats up, plants
+    /// mutate into `MUTANT_MATERIAL`, and the emitters themselves decay over their
+    /// half-life. Runs every `RADIOACTIVITY_PASS_INTERVAL` seconds, sleeping chunks included.
+    pub fn enable_radioactivity(&mut self) {
+        self.radiation = Some(RadiationField::new(self.width, self.height));
+    }
+
+    pub fn disable_radioactivity(&mut self) {
+        self.radiation = None;
+    }
+
+    /// Radiation as of the last pass; None while radioactivity is off
+    pub fn radiation(&self) -> Option<&RadiationField> {
+        self.radiation.as_ref()
+    }
+
+    /// Dose at `(x, y)` as of the last pass; zero while radioactivity is off
+    pub fn radiation_at(&self, x: usize, y: usize) -> f32 {
+        self.radiation.as_ref().map_or(0.0, |radiation| radiation.dose(x, y))
+    }
+
+    /// Runs every `RADIOACTIVITY_PASS_INTERVAL` seconds with the time since the last pass
+    pub(crate) fn apply_radioactivity(&mut self, elapsed: f32) {
+        let Some(mut radiation) = self.radiation.take() else {
+            return;
+        };
+
+        // Collected up front so cells that decay this pass still radiated for it
+        let emitters: Vec<_> = self.iter_particles()
+            .filter_map(|particle| {
+                let material = self.materials().get(particle.material_type);
+                (material.radioactivity > 0.0).then_some((particle.x, particle.y, material.radioactivity))
+            })
+            .collect();
+        radiation.clear();
+        for &(x, y, radioactivity) in &emitters {
+            radiation.emit(x, y, radioactivity);
+        }
+
+        for (x, y, _) in emitters {
+            let Some(particle) = self.get_particle(x, y) else {
+                continue;
+            };
+            let (temp, material) = (particle.temp, self.materials().get(particle.material_type));
+            let (Some(product), half_life) = (material.decays_into, material.half_life) else {
+                continue;
+            };
+            if half_life > 0.0 && self.rng.gen::<f32>() < 1.0 - 0.5f32.powf(elapsed / half_life) {
+                self.remove_particle(x, y);
+                self.add_particle(x, y, product, Some(temp));
+            }
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let dose = radiation.dose(x, y);
+                if dose <= 0.0 {
+                    continue;
+                }
+                let Some(material_type) = self.get_particle(x, y).map(|particle| particle.material_type) else {
+                    continue;
+                };
+                if matches!(material_type, MaterialType::Water | MaterialType::SaltWater) {
+                    if let Some(water) = self.get_particle_mut(x, y) {
+                        water.temp += dose * WATER_HEATING_PER_DOSE * elapsed;
+                        water.settled_frames = 0;
+                        water.invalidate_color_cache();
+                    }
+                    self.wake_cell(x, y);
+                    self.mark_changed(x, y);
+                } else if self.is_plant(material_type) && self.rng.gen::<f32>() < dose * MUTATION_CHANCE_PER_DOSE * elapsed {
+                    let temp = self.get_particle(x, y).map(|particle| particle.temp);
+                    self.remove_particle(x, y);
+                    self.add_particle(x, y, MUTANT_MATERIAL, temp);
+                }
+            }
+        }
+
+        self.radiation = Some(radiation);
+    }
+
+    /// Living plant matter: the built-in plants and the seeds, stems and roots of every
+    /// registered species
+    fn is_plant(&self, material_type: MaterialType) -> bool {
+        matches!(material_type, MaterialType::Plant | MaterialType::Seed | MaterialType::Stem | MaterialType::Root)
+            || self.plants().for_seed(material_type).is_some()
+            || self.plants().for_stem(material_type).is_some()
+            || self.plants().for_root(material_type).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::{URANIUM_HALF_LIFE, URANIUM_RADIOACTIVITY};
+
+    fn count(simulation: &Simulation, material_type: MaterialType) -> usize {
+        simulation.iter_particles().filter(|p| p.material_type == material_type).count()
+    }
+
+    #[test]
+    fn test_uranium_heats_water_and_mutates_plants() {
+        let mut simulation = Simulation::new(32, 16);
+        simulation.set_seed(3);
+        simulation.enable_radioactivity();
+        // Two tanks of water, one with uranium at the bottom, and a stem beside them
+        for x in 0..32 {
+            simulation.add_particle(x, 15, MaterialType::Stone, None);
+        }
+        for left in [2, 20] {
+            for y in 10..15 {
+                simulation.add_particle(left, y, MaterialType::Stone, None);
+                simulation.add_particle(left + 7, y, MaterialType::Stone, None);
+            }
+            for y in 11..15 {
+                for x in left + 1..left + 7 {
+                    simulation.add_particle(x, y, MaterialType::Water, Some(20.0));
+                }
+            }
+        }
+        for (x, y) in [(7, 14), (8, 14), (7, 13), (8, 13)] {
+            simulation.remove_particle(x, y);
+            simulation.add_particle(x, y, MaterialType::Uranium, None);
+        }
+        for y in 11..15 {
+            simulation.add_particle(10, y, MaterialType::Stem, None);
+        }
+        for _ in 0..600 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        let radiation = simulation.radiation().unwrap();
+        assert!(radiation.dose(6, 14) > URANIUM_RADIOACTIVITY);
+        assert_eq!(radiation.dose(23, 14), 0.0);
+        let mean_temp = |left: usize| {
+            let water: Vec<f32> = simulation.iter_particles()
+                .filter(|p| p.material_type == MaterialType::Water && p.x > left && p.x < left + 7)
+                .map(|p| p.temp)
+                .collect();
+            water.iter().sum::<f32>() / water.len() as f32
+        };
+        assert!(mean_temp(2) > mean_temp(20) + 20.0, "{} {}", mean_temp(2), mean_temp(20));
+        assert!(count(&simulation, MUTANT_MATERIAL) > 0);
+
+        // Warmed water is redrawn
+        let tick = simulation.tick();
+        simulation.apply_radioactivity(RADIOACTIVITY_PASS_INTERVAL);
+        assert!(simulation.iter_changed_since(tick).any(|(x, y, _)| (x, y) == (6, 14)));
+    }
+
+    #[test]
+    fn test_uranium_decays_into_lead() {
+        let mut simulation = Simulation::new(16, 16);
+        simulation.set_seed(4);
+        simulation.enable_radioactivity();
+        for y in 8..16 {
+            for x in 0..16 {
+                simulation.add_particle(x, y, MaterialType::Uranium, None);
+            }
+        }
+        // One half-life in passes of a whole minute each
+        for _ in 0..(URANIUM_HALF_LIFE / 60.0) as usize {
+            simulation.apply_radioactivity(60.0);
+        }
+        let (uranium, lead) = (count(&simulation, MaterialType::Uranium), count(&simulation, MaterialType::Lead));
+        assert_eq!(uranium + lead, 128);
+        assert!((40..=88).contains(&lead), "{} of 128 decayed", lead);
+
+        simulation.disable_radioactivity();
+        simulation.apply_radioactivity(URANIUM_HALF_LIFE * 10.0);
+        assert_eq!(count(&simulation, MaterialType::Lead), lead);
+        assert_eq!(simulation.radiation_at(0, 8), 0.0);
+    }
+}
@@ -0,0 +1,392 @@
+use crate::materials::MaterialType;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashMap;
+
+const TARGET_DT_SCALING: f32 = 60.0;
+/// Seconds a lit firework's life counts down from before it bursts
+pub const FIREWORK_FUSE_SEC: f32 = 1.5;
+/// Upward impulse, in cells per tick, of each burst of a firework's thrust
+pub const FIREWORK_THRUST: f32 = 1.0;
+
+/// Offsets of the eight neighbours in the row-major order used by every engine's
+/// `get_neighbors`; `ReactionOutcome::neighbor_index` indexes into this table.
+pub const NEIGHBOR_OFFSETS: [(i32, i32); 8] = [
+    (-1, -1), (0, -1), (1, -1),
+    (-1,  0),          (1,  0),
+    (-1,  1), (0,  1), (1,  1),
+];
+
+/// A pairwise rule: when `reactant` touches `neighbor`, the reactant may turn into
+/// `product` and the neighbour into `neighbor_product` (None leaves it untouched).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reaction {
+    pub reactant: MaterialType,
+    pub neighbor: MaterialType,
+    pub product: MaterialType,
+    pub neighbor_product: Option<MaterialType>,
+    /// Chance per 1/60 s tick while the two are in contact
+    pub probability: f32,
+    /// Added to both products' temperature (negative values absorb heat)
+    pub heat_delta: f32,
+    /// Extra particles sprayed into empty cells around the reactant, e.g. a steam burst
+    pub burst: Option<(MaterialType, usize)>,
+}
+
+impl Reaction {
+    pub fn new(
+        reactant: MaterialType,
+        neighbor: MaterialType,
+        product: MaterialType,
+        neighbor_product: Option<MaterialType>,
+        probability: f32,
+        heat_delta: f32,
+    ) -> Self {
+        Self {
+            reactant,
+            neighbor,
+            product,
+            neighbor_product,
+            probability,
+            heat_delta,
+            burst: None,
+        }
+    }
+
+    /// Spray up to `count` particles of `material` into empty neighbouring cells
+    pub fn with_burst(mut self, material: MaterialType, count: usize) -> Self {
+        self.burst = Some((material, count));
+        self
+    }
+
+    /// The same rule seen from the neighbour's side
+    pub fn mirrored(&self) -> Self {
+        Self {
+            reactant: self.neighbor,
+            neighbor: self.reactant,
+            product: self.neighbor_product.unwrap_or(self.neighbor),
+            neighbor_product: Some(self.product),
+            probability: self.probability,
+            heat_delta: self.heat_delta,
+            burst: self.burst,
+        }
+    }
+}
+
+/// What a staged reaction does to the particle it fires on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StageEffect {
+    /// Add to the particle's impulse velocity, in cells per tick
+    Launch { vx: f32, vy: f32 },
+    /// Throw `count` particles of `material` outwards at `speed` cells per tick, each
+    /// carrying the particle's payload, and turn the particle into `product`
+    Burst { material: MaterialType, count: usize, speed: f32, product: MaterialType },
+    /// Turn the particle into `product`
+    Become(MaterialType),
+}
+
+/// A delayed follow-up for a particle whose `life` is counting down: fires once, on the
+/// tick a particle of `material` gets down to `at_life` seconds left
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StagedReaction {
+    pub material: MaterialType,
+    pub at_life: f32,
+    pub effect: StageEffect,
+}
+
+impl StagedReaction {
+    pub fn new(material: MaterialType, at_life: f32, effect: StageEffect) -> Self {
+        Self { material, at_life, effect }
+    }
+
+    /// Whether the stage is due on a tick that took the particle's life from `before` to `after`
+    pub fn is_due(&self, before: f32, after: f32) -> bool {
+        before > self.at_life && after <= self.at_life
+    }
+}
+
+/// What a particle should turn into after a successful reaction roll
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReactionOutcome {
+    pub product: MaterialType,
+    /// Index into `NEIGHBOR_OFFSETS` of the neighbour that reacted
+    pub neighbor_index: usize,
+    pub neighbor_product: Option<MaterialType>,
+    pub heat_delta: f32,
+    pub burst: Option<(MaterialType, usize)>,
+}
+
+impl ReactionOutcome {
+    /// Indices into `NEIGHBOR_OFFSETS` that should receive burst particles, chosen at
+    /// random among the cells `is_empty` accepts
+    pub fn burst_targets(&self, rng: &mut impl Rng, mut is_empty: impl FnMut(usize) -> bool) -> Vec<usize> {
+        let Some((_, count)) = self.burst else {
+            return Vec::new();
+        };
+        let mut targets: Vec<usize> = (0..NEIGHBOR_OFFSETS.len())
+            .filter(|&index| index != self.neighbor_index && is_empty(index))
+            .collect();
+        targets.shuffle(rng);
+        targets.truncate(count);
+        targets
+    }
+}
+
+/// Table of pairwise reactions consulted by `Simulation` and `AdvancedPhysicsEngine`
+/// for every particle they update.
+#[derive(Debug, Clone, Default)]
+pub struct ReactionRegistry {
+    reactions: HashMap<(MaterialType, MaterialType), Vec<Reaction>>,
+    stages: HashMap<MaterialType, Vec<StagedReaction>>,
+}
+
+impl ReactionRegistry {
+    /// Empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Table with the built-in chemistry
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        // Lava quenched by water hardens while the water flashes to steam
+        registry.register_symmetric(Reaction::new(
+            MaterialType::Lava, MaterialType::Water, MaterialType::Stone, Some(MaterialType::Steam), 0.5, -200.0,
+        ).with_burst(MaterialType::Steam, 2));
+        // A lit firework sputters, then climbs on a pulse of thrust every tenth of a second
+        // until it goes off in sparks
+        for pulse in 1..=13 {
+            registry.register_stage(StagedReaction::new(
+                MaterialType::Firework, 1.5 - pulse as f32 * 0.1, StageEffect::Launch { vx: 0.0, vy: -FIREWORK_THRUST },
+            ));
+        }
+        registry.register_stage(StagedReaction::new(MaterialType::Firework, 0.0, StageEffect::Burst {
+            material: MaterialType::Spark, count: 16, speed: 2.5, product: MaterialType::Smoke,
+        }));
+        registry
+    }
+
+    pub fn register(&mut self, reaction: Reaction) {
+        self.reactions
+            .entry((reaction.reactant, reaction.neighbor))
+            .or_default()
+            .push(reaction);
+    }
+
+    /// Register a rule and its mirror so it fires whichever side updates first
+    pub fn register_symmetric(&mut self, reaction: Reaction) {
+        if reaction.reactant != reaction.neighbor {
+            self.register(reaction.mirrored());
+        }
+        self.register(reaction);
+    }
+
+    /// Remove every rule for the ordered pair
+    pub fn remove(&mut self, reactant: MaterialType, neighbor: MaterialType) -> Vec<Reaction> {
+        self.reactions.remove(&(reactant, neighbor)).unwrap_or_default()
+    }
+
+    /// Multiply the probability of every rule for the ordered pair
+    pub fn scale(&mut self, reactant: MaterialType, neighbor: MaterialType, factor: f32) {
+        for reaction in self.reactions.get_mut(&(reactant, neighbor)).into_iter().flatten() {
+            reaction.probability *= factor;
+        }
+    }
+
+    /// Multiply the probability of every rule
+    pub fn scale_all(&mut self, factor: f32) {
+        for reaction in self.reactions.values_mut().flatten() {
+            reaction.probability *= factor;
+        }
+    }
+
+    /// Add a staged reaction, keeping each material's stages ordered from most life left
+    pub fn register_stage(&mut self, stage: StagedReaction) {
+        let stages = self.stages.entry(stage.material).or_default();
+        stages.push(stage);
+        stages.sort_by(|a, b| b.at_life.total_cmp(&a.at_life));
+    }
+
+    /// Remove every staged reaction of a material
+    pub fn remove_stages(&mut self, material: MaterialType) -> Vec<StagedReaction> {
+        self.stages.remove(&material).unwrap_or_default()
+    }
+
+    pub fn stages_for(&self, material: MaterialType) -> &[StagedReaction] {
+        self.stages.get(&material).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Stages of `material` due on a tick that took a particle's life from `before` to
+    /// `after`, in the order they fall due; none unless its life was already counting down
+    pub fn due_stages(&self, material: MaterialType, before: Option<f32>, after: Option<f32>) -> impl Iterator<Item = &StagedReaction> {
+        let span = before.zip(after);
+        self.stages_for(material).iter()
+            .filter(move |stage| span.is_some_and(|(before, after)| stage.is_due(before, after)))
+    }
+
+    pub fn reactions_for(&self, reactant: MaterialType, neighbor: MaterialType) -> &[Reaction] {
+        self.reactions
+            .get(&(reactant, neighbor))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn len(&self) -> usize {
+        self.reactions.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reactions.is_empty()
+    }
+
+    /// Roll the reactions between `material` and its neighbours (in `NEIGHBOR_OFFSETS`
+    /// order) and return the first that fires
+    pub fn try_react(
+        &self,
+        material: MaterialType,
+        neighbors: &[Option<MaterialType>],
+        delta_time: f32,
+        rng: &mut impl Rng,
+    ) -> Option<ReactionOutcome> {
+        if self.reactions.is_empty() {
+            return None;
+        }
+
+        let dt_scale = delta_time * TARGET_DT_SCALING;
+        for (neighbor_index, neighbor) in neighbors.iter().enumerate() {
+            let Some(neighbor) = neighbor else {
+                continue;
+            };
+            for reaction in self.reactions_for(material, *neighbor) {
+                if rng.gen::<f32>() < reaction.probability * dt_scale {
+                    return Some(ReactionOutcome {
+                        product: reaction.product,
+                        neighbor_index,
+                        neighbor_product: reaction.neighbor_product,
+                        heat_delta: reaction.heat_delta,
+                        burst: reaction.burst,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_symmetric_registration() {
+        let registry = ReactionRegistry::with_defaults();
+
+        let forward = registry.reactions_for(MaterialType::Lava, MaterialType::Water);
+        let backward = registry.reactions_for(MaterialType::Water, MaterialType::Lava);
+        assert_eq!(forward.len(), 1);
+        assert_eq!(backward[0].product, MaterialType::Steam);
+        assert_eq!(backward[0].neighbor_product, Some(MaterialType::Stone));
+        assert!(registry.reactions_for(MaterialType::Sand, MaterialType::Water).is_empty());
+    }
+
+    #[test]
+    fn test_try_react_reports_neighbor() {
+        let mut registry = ReactionRegistry::new();
+        registry.register(Reaction::new(MaterialType::Acid, MaterialType::Iron, MaterialType::Empty, Some(MaterialType::Sand), 1.0, 0.0));
+
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0);
+        let mut neighbors = [None; 8];
+        assert!(registry.try_react(MaterialType::Acid, &neighbors, 1.0 / 60.0, &mut rng).is_none());
+
+        neighbors[6] = Some(MaterialType::Iron);
+        let outcome = registry.try_react(MaterialType::Acid, &neighbors, 1.0 / 60.0, &mut rng).unwrap();
+        assert_eq!(outcome.neighbor_index, 6);
+        assert_eq!(NEIGHBOR_OFFSETS[outcome.neighbor_index], (0, 1));
+        assert_eq!(outcome.neighbor_product, Some(MaterialType::Sand));
+    }
+
+    #[test]
+    fn test_stages_fall_due_as_life_runs_down() {
+        let registry = ReactionRegistry::with_defaults();
+        let stages = registry.stages_for(MaterialType::Firework);
+        assert!(stages.windows(2).all(|pair| pair[0].at_life > pair[1].at_life));
+
+        let due = |before, after| registry.due_stages(MaterialType::Firework, before, after).map(|stage| stage.effect).collect::<Vec<_>>();
+        assert!(due(None, Some(FIREWORK_FUSE_SEC)).is_empty());
+        assert!(due(Some(1.38), Some(1.32)).is_empty());
+        assert_eq!(due(Some(1.42), Some(1.38)), [StageEffect::Launch { vx: 0.0, vy: -FIREWORK_THRUST }]);
+        assert!(matches!(due(Some(0.01), Some(-0.006))[..], [StageEffect::Burst { material: MaterialType::Spark, .. }]));
+        // A long tick fires everything it skipped past, in order
+        assert_eq!(due(Some(1.5), Some(-1.0)).len(), 14);
+    }
+
+    #[test]
+    fn test_firework_launches_and_bursts_into_colored_sparks() {
+        use crate::simulation::Simulation;
+
+        let mut simulation = Simulation::new(32, 120);
+        simulation.set_seed(4);
+        simulation.add_particle(16, 119, MaterialType::Firework, Some(200.0));
+        simulation.add_particle(15, 119, MaterialType::Fire, Some(900.0));
+
+        let mut highest_spark = None;
+        for _ in 0..150 {
+            simulation.update(1.0 / 60.0);
+            let sparks: Vec<_> = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Spark).collect();
+            if let Some(spark) = sparks.first() {
+                assert!(sparks.iter().all(|other| other.payload == spark.payload));
+                highest_spark = highest_spark.max(Some(120 - sparks.iter().map(|p| p.y).min().unwrap()));
+            }
+        }
+
+        assert!(highest_spark.is_some_and(|height| (40..100).contains(&height)), "{:?}", highest_spark);
+        assert!(simulation.iter_particles().all(|p| p.material_type != MaterialType::Firework));
+        // Sparks burn out
+        for _ in 0..120 {
+            simulation.update(1.0 / 60.0);
+        }
+        assert!(simulation.iter_particles().all(|p| p.material_type != MaterialType::Spark));
+    }
+
+    #[test]
+    fn test_simulation_consults_registry() {
+        use crate::simulation::Simulation;
+
+        let mut simulation = Simulation::new(10, 10);
+        simulation.reactions_mut().register(Reaction::new(
+            MaterialType::Stone, MaterialType::Iron, MaterialType::Gold, Some(MaterialType::Empty), 1.0, 100.0,
+        ));
+        simulation.add_particle(4, 9, MaterialType::Stone, None);
+        simulation.add_particle(5, 9, MaterialType::Iron, None);
+
+        simulation.update(1.0 / 60.0);
+
+        let gold = simulation.get_particle(4, 9).unwrap();
+        assert_eq!(gold.material_type, MaterialType::Gold);
+        assert!(gold.temp > 100.0);
+        assert!(simulation.get_particle(5, 9).is_none());
+    }
+
+    #[test]
+    fn test_lava_quench_bursts_steam() {
+        use crate::simulation::Simulation;
+
+        let mut simulation = Simulation::new(10, 10);
+        let reactions = simulation.reactions_mut();
+        reactions.remove(MaterialType::Lava, MaterialType::Water);
+        reactions.remove(MaterialType::Water, MaterialType::Lava);
+        reactions.register_symmetric(Reaction::new(
+            MaterialType::Lava, MaterialType::Water, MaterialType::Stone, Some(MaterialType::Steam), 1.0, -200.0,
+        ).with_burst(MaterialType::Steam, 3));
+        simulation.add_particle(4, 9, MaterialType::Lava, None);
+        simulation.add_particle(5, 9, MaterialType::Water, None);
+
+        simulation.update(1.0 / 60.0);
+
+        let state = simulation.get_state();
+        let steam = state.particles.values().filter(|p| p.material_type == MaterialType::Steam).count();
+        assert_eq!(steam, 4);
+        assert_eq!(state.particles.values().filter(|p| p.material_type == MaterialType::Stone).count(), 1);
+    }
+}
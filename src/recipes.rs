@@ -0,0 +1,261 @@
+use crate::material_registry::{MaterialRegistry, MaterialRegistryError};
+use crate::materials::MaterialType;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "files")]
+use std::fs;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// One `[[recipe]]` entry of a recipe file. Materials are named as in material packs, so
+/// custom materials from the registry the file is resolved against can be used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RecipeDefinition {
+    pub input: String,
+    #[serde(default = "default_amount")]
+    pub input_amount: u32,
+    /// Fuel the furnace must be burning; any fuel, or none, when absent
+    pub fuel: Option<String>,
+    pub output: String,
+    #[serde(default = "default_amount")]
+    pub output_amount: u32,
+    /// Seconds at temperature per batch
+    pub smelt_time: f32,
+    /// Furnace temperature the batch needs, in °C
+    #[serde(default)]
+    pub min_temperature: f32,
+}
+
+fn default_amount() -> u32 {
+    1
+}
+
+/// A TOML file of recipe definitions
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecipeFile {
+    #[serde(rename = "recipe", default)]
+    pub recipes: Vec<RecipeDefinition>,
+}
+
+/// Turns `input_amount` of `input` into `output_amount` of `output` after `smelt_time`
+/// seconds in a furnace at `min_temperature` or hotter
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Recipe {
+    pub input: MaterialType,
+    pub input_amount: u32,
+    pub fuel: Option<MaterialType>,
+    pub output: MaterialType,
+    pub output_amount: u32,
+    pub smelt_time: f32,
+    pub min_temperature: f32,
+}
+
+impl Recipe {
+    pub fn new(input: MaterialType, output: MaterialType, smelt_time: f32, min_temperature: f32) -> Self {
+        Self {
+            input,
+            input_amount: 1,
+            fuel: None,
+            output,
+            output_amount: 1,
+            smelt_time,
+            min_temperature,
+        }
+    }
+
+    pub fn with_amounts(mut self, input_amount: u32, output_amount: u32) -> Self {
+        self.input_amount = input_amount;
+        self.output_amount = output_amount;
+        self
+    }
+
+    /// Only run while the furnace burns `fuel`
+    pub fn with_fuel(mut self, fuel: MaterialType) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Whether the ingredients and fuel are right, ignoring temperature
+    pub fn can_use(&self, input: MaterialType, input_amount: u32, fuel: Option<MaterialType>) -> bool {
+        self.input == input
+            && input_amount >= self.input_amount
+            && self.fuel.is_none_or(|required| fuel == Some(required))
+    }
+
+    /// Whether a furnace holding these ingredients at `temperature` can run the recipe
+    pub fn can_smelt(&self, input: MaterialType, input_amount: u32, fuel: Option<MaterialType>, temperature: f32) -> bool {
+        self.can_use(input, input_amount, fuel) && temperature >= self.min_temperature
+    }
+
+    fn from_definition(definition: &RecipeDefinition, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let resolve = |name: &str| {
+            materials.lookup(name).ok_or_else(|| MaterialRegistryError::UnknownMaterial(name.to_string()))
+        };
+        if definition.input_amount == 0 || definition.output_amount == 0 {
+            return Err(MaterialRegistryError::InvalidDefinition(format!(
+                "recipe for '{}' needs non-zero amounts", definition.input
+            )));
+        }
+        if definition.smelt_time.is_nan() || definition.smelt_time <= 0.0 {
+            return Err(MaterialRegistryError::InvalidDefinition(format!(
+                "recipe for '{}' needs a positive smelt_time", definition.input
+            )));
+        }
+
+        Ok(Self {
+            input: resolve(&definition.input)?,
+            input_amount: definition.input_amount,
+            fuel: definition.fuel.as_deref().map(resolve).transpose()?,
+            output: resolve(&definition.output)?,
+            output_amount: definition.output_amount,
+            smelt_time: definition.smelt_time,
+            min_temperature: definition.min_temperature,
+        })
+    }
+}
+
+/// Smelting recipes consulted by furnace tile entities. Recipes registered later take
+/// precedence, so a loaded file can override the defaults for an input.
+#[derive(Debug, Clone, Default)]
+pub struct RecipeRegistry {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeRegistry {
+    /// Empty table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Table with the built-in recipes
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Recipe::new(MaterialType::Sand, MaterialType::Glass, 10.0, 500.0));
+        registry.register(Recipe::new(MaterialType::Wood, MaterialType::Coal, 5.0, 300.0).with_amounts(2, 1));
+        registry.register(Recipe::new(MaterialType::Sandstone, MaterialType::Glass, 15.0, 700.0).with_amounts(1, 2));
+        registry
+    }
+
+    /// Built-in recipes plus everything defined in `source`, with material names resolved
+    /// against `materials`
+    pub fn from_toml_str(source: &str, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let mut registry = Self::with_defaults();
+        registry.load_toml_str(source, materials)?;
+        Ok(registry)
+    }
+
+    /// Built-in recipes plus everything defined in the TOML file at `path`
+    #[cfg(feature = "files")]
+    pub fn from_file(path: impl AsRef<Path>, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let mut registry = Self::with_defaults();
+        registry.load_file(path, materials)?;
+        Ok(registry)
+    }
+
+    /// Add the recipes in a TOML file, returning how many it defined
+    #[cfg(feature = "files")]
+    pub fn load_file(&mut self, path: impl AsRef<Path>, materials: &MaterialRegistry) -> Result<usize, MaterialRegistryError> {
+        let source = fs::read_to_string(path)?;
+        self.load_toml_str(&source, materials)
+    }
+
+    /// Add the recipes in a TOML recipe file, returning how many it defined. The file is
+    /// applied atomically: on error the registry is left unchanged.
+    pub fn load_toml_str(&mut self, source: &str, materials: &MaterialRegistry) -> Result<usize, MaterialRegistryError> {
+        let file: RecipeFile = toml::from_str(source)?;
+        let recipes = file.recipes.iter()
+            .map(|definition| Recipe::from_definition(definition, materials))
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = recipes.len();
+        self.recipes.extend(recipes);
+        Ok(count)
+    }
+
+    pub fn register(&mut self, recipe: Recipe) {
+        self.recipes.push(recipe);
+    }
+
+    /// Remove every recipe for `input`
+    pub fn remove(&mut self, input: MaterialType) -> Vec<Recipe> {
+        let (removed, kept) = self.recipes.drain(..).partition(|recipe| recipe.input == input);
+        self.recipes = kept;
+        removed
+    }
+
+    /// Every recipe in registration order, for listing in a UI
+    pub fn recipes(&self) -> &[Recipe] {
+        &self.recipes
+    }
+
+    /// Recipes that take `input`, highest precedence first
+    pub fn recipes_for(&self, input: MaterialType) -> impl Iterator<Item = &Recipe> + '_ {
+        self.recipes.iter().rev().filter(move |recipe| recipe.input == input)
+    }
+
+    /// Recipes a furnace holding these ingredients could run once hot enough, highest
+    /// precedence first
+    pub fn available(&self, input: MaterialType, input_amount: u32, fuel: Option<MaterialType>) -> impl Iterator<Item = &Recipe> + '_ {
+        self.recipes_for(input).filter(move |recipe| recipe.can_use(input, input_amount, fuel))
+    }
+
+    /// The recipe a furnace holding these ingredients at `temperature` runs, if any
+    pub fn find(&self, input: MaterialType, input_amount: u32, fuel: Option<MaterialType>, temperature: f32) -> Option<&Recipe> {
+        self.recipes_for(input).find(|recipe| recipe.can_smelt(input, input_amount, fuel, temperature))
+    }
+
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_recipes() {
+        let recipes = RecipeRegistry::with_defaults();
+        let glass = recipes.find(MaterialType::Sand, 1, Some(MaterialType::Coal), 600.0).unwrap();
+        assert_eq!(glass.output, MaterialType::Glass);
+        assert!(recipes.find(MaterialType::Sand, 1, Some(MaterialType::Coal), 400.0).is_none());
+        // Charcoal needs two logs
+        assert!(recipes.find(MaterialType::Wood, 1, None, 400.0).is_none());
+        assert_eq!(recipes.available(MaterialType::Wood, 2, None).count(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "files")]
+    fn test_load_recipe_file() {
+        let materials = MaterialRegistry::from_toml_str(include_str!("../materials/example_pack.toml")).unwrap();
+        let recipes = RecipeRegistry::from_file("materials/example_recipes.toml", &materials).unwrap();
+        assert!(recipes.len() > RecipeRegistry::with_defaults().len());
+
+        // The file's recipe overrides the default one for Sand
+        let salt = materials.lookup("Rock Salt").unwrap();
+        let recipe = recipes.find(MaterialType::Sand, 1, Some(MaterialType::Coal), 1000.0).unwrap();
+        assert_eq!(recipe.fuel, Some(MaterialType::Coal));
+        assert!(recipes.find(MaterialType::Sand, 1, Some(MaterialType::Wood), 1000.0).unwrap().fuel.is_none());
+        assert!(recipes.recipes_for(salt).next().is_some());
+    }
+
+    #[test]
+    fn test_invalid_recipes_rejected() {
+        let materials = MaterialRegistry::new();
+        let mut recipes = RecipeRegistry::new();
+        let unknown = "[[recipe]]\ninput = \"Unobtainium\"\noutput = \"Gold\"\nsmelt_time = 1.0\n";
+        assert!(matches!(
+            recipes.load_toml_str(unknown, &materials),
+            Err(MaterialRegistryError::UnknownMaterial(_))
+        ));
+        let instant = "[[recipe]]\ninput = \"Sand\"\noutput = \"Glass\"\nsmelt_time = 0.0\n";
+        assert!(matches!(
+            recipes.load_toml_str(instant, &materials),
+            Err(MaterialRegistryError::InvalidDefinition(_))
+        ));
+        assert!(recipes.is_empty());
+    }
+}
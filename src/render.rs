@@ -0,0 +1,1176 @@
+use crate::brush::line_cells;
+use crate::effects::EffectLayer;
+use crate::interpolation;
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::simulation::{GridRect, Simulation, SIM_CHUNK_SIZE};
+use crate::textures::TextureAtlas;
+use crate::tile_entity::TileEntityManager;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Bytes per pixel in exported frames (RGBA8)
+pub const BYTES_PER_PIXEL: usize = 4;
+/// Size in pixels of one character of the built-in bitmap font
+pub const GLYPH_WIDTH: usize = 3;
+pub const GLYPH_HEIGHT: usize = 5;
+/// Horizontal distance between consecutive characters
+const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+
+/// Zoom limits accepted by `Camera::set_zoom`
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 8.0;
+/// Cells between the velocity arrows drawn in `RenderMode::Velocity`
+pub const VELOCITY_ARROW_SPACING: usize = 8;
+/// Heatmap colors at increasing temperatures; colors in between are interpolated
+const HEATMAP_STOPS: [(f32, [u8; 3]); 6] = [
+    (-40.0, [60, 0, 160]),
+    (0.0, [0, 90, 255]),
+    (20.0, [0, 150, 90]),
+    (100.0, [240, 220, 0]),
+    (600.0, [255, 60, 0]),
+    (1500.0, [255, 255, 255]),
+];
+/// Color `RenderMode::Radiation` tints cells towards as their dose rises
+const RADIATION_COLOR: [u8; 3] = [140, 255, 40];
+/// Dose at which `RenderMode::Radiation` shows a cell fully tinted
+pub const RADIATION_OVERLAY_DOSE: f32 = 1.0;
+
+/// View offset into the grid, in cells, a zoom factor, plus a decaying screen-shake
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub x: i32,
+    pub y: i32,
+    /// Frame pixels per cell; 2.0 shows each cell as a 2x2 block
+    pub zoom: f32,
+    shake_intensity: f32,
+    shake_duration: f32,
+    shake_remaining: f32,
+    shake_offset: (i32, i32),
+    shake_rng: ChaCha8Rng,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            zoom: 1.0,
+            shake_intensity: 0.0,
+            shake_duration: 0.0,
+            shake_remaining: 0.0,
+            shake_offset: (0, 0),
+            shake_rng: ChaCha8Rng::seed_from_u64(0),
+        }
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    /// Grid cell shown at a frame pixel
+    pub fn screen_to_cell(&self, screen_x: usize, screen_y: usize) -> (i32, i32) {
+        let (offset_x, offset_y) = self.offset();
+        let zoom = self.zoom.max(MIN_ZOOM);
+        (
+            offset_x + (screen_x as f32 / zoom).floor() as i32,
+            offset_y + (screen_y as f32 / zoom).floor() as i32,
+        )
+    }
+
+    /// Frame pixel at the top-left corner of a grid cell
+    pub fn cell_to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        let (offset_x, offset_y) = self.offset();
+        let zoom = self.zoom.max(MIN_ZOOM);
+        (((x - offset_x) as f32 * zoom).round() as i32, ((y - offset_y) as f32 * zoom).round() as i32)
+    }
+
+    /// Frame pixel at a point given in cells, e.g. a particle drawn part way between two
+    pub fn position_to_screen(&self, x: f32, y: f32) -> (i32, i32) {
+        let (offset_x, offset_y) = self.offset();
+        let zoom = self.zoom.max(MIN_ZOOM);
+        (((x - offset_x as f32) * zoom).round() as i32, ((y - offset_y as f32) * zoom).round() as i32)
+    }
+
+    /// Start shaking by up to `intensity` cells for `duration` seconds. A stronger shake
+    /// replaces a weaker one; a weaker one never cuts a stronger one short.
+    pub fn shake(&mut self, intensity: f32, duration: f32) {
+        if intensity >= self.current_shake() {
+            self.shake_intensity = intensity;
+            self.shake_duration = duration.max(f32::EPSILON);
+            self.shake_remaining = duration;
+        }
+    }
+
+    pub fn is_shaking(&self) -> bool {
+        self.shake_remaining > 0.0
+    }
+
+    /// Shake magnitude right now, fading linearly to zero
+    pub fn current_shake(&self) -> f32 {
+        if self.shake_remaining <= 0.0 {
+            return 0.0;
+        }
+        self.shake_intensity * (self.shake_remaining / self.shake_duration)
+    }
+
+    /// Advance the shake and pick a new jitter offset
+    pub fn update(&mut self, delta_time: f32) {
+        self.shake_remaining = (self.shake_remaining - delta_time).max(0.0);
+
+        let magnitude = self.current_shake();
+        self.shake_offset = if magnitude > 0.0 {
+            let mut jitter = || ((self.shake_rng.gen::<f32>() * 2.0 - 1.0) * magnitude).round() as i32;
+            (jitter(), jitter())
+        } else {
+            (0, 0)
+        };
+    }
+
+    /// Grid cell drawn at the frame's top-left corner
+    pub fn offset(&self) -> (i32, i32) {
+        (self.x + self.shake_offset.0, self.y + self.shake_offset.1)
+    }
+}
+
+/// What rendered frames show. The debug modes color cells by simulation state instead
+/// of material; chunk and rigid body modes draw materials and leave their overlays to
+/// the caller, which has the data for them (see `World::render`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderMode {
+    #[default]
+    Normal,
+    /// Particles colored by temperature, from purple (cold) through green (ambient) to white
+    Temperature,
+    /// Settled particles dimmed, moving ones highlighted, and arrows for impulse velocities
+    Velocity,
+    /// Particles in dim gray, tinted green by the radiation dose of their cell; see
+    /// `Simulation::enable_radioactivity`
+    Radiation,
+    /// Chunk boundaries tinted by dirty and sleeping state
+    Chunks,
+    /// Outlines around rigid bodies
+    RigidBodies,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 6] = [
+        RenderMode::Normal, RenderMode::Temperature, RenderMode::Velocity, RenderMode::Radiation, RenderMode::Chunks,
+        RenderMode::RigidBodies,
+    ];
+
+    /// The mode after this one, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&mode| mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RenderMode::Normal => "normal",
+            RenderMode::Temperature => "temperature",
+            RenderMode::Velocity => "velocity",
+            RenderMode::Radiation => "radiation",
+            RenderMode::Chunks => "chunks",
+            RenderMode::RigidBodies => "rigid bodies",
+        }
+    }
+}
+
+/// Heatmap color of a temperature in °C
+pub fn temperature_color(temp: f32) -> [u8; 3] {
+    let (first, last) = (HEATMAP_STOPS[0], HEATMAP_STOPS[HEATMAP_STOPS.len() - 1]);
+    if temp <= first.0 {
+        return first.1;
+    }
+    for pair in HEATMAP_STOPS.windows(2) {
+        let ((low, low_color), (high, high_color)) = (pair[0], pair[1]);
+        if temp <= high {
+            let t = (temp - low) / (high - low);
+            return [0, 1, 2].map(|channel| {
+                (low_color[channel] as f32 + (high_color[channel] as f32 - low_color[channel] as f32) * t) as u8
+            });
+        }
+    }
+    last.1
+}
+
+/// Color of a cell in `RenderMode::Radiation`: `base` dimmed to gray and tinted towards
+/// `RADIATION_COLOR` by `dose`
+pub fn radiation_color(dose: f32, base: [u8; 3]) -> [u8; 3] {
+    let gray = (base[0] as f32 * 0.3 + base[1] as f32 * 0.59 + base[2] as f32 * 0.11) * 0.4;
+    let tint = (dose / RADIATION_OVERLAY_DOSE).clamp(0.0, 1.0);
+    [0, 1, 2].map(|channel| (gray + (RADIATION_COLOR[channel] as f32 - gray) * tint) as u8)
+}
+
+/// Rasterizes a Simulation grid into an RGBA8 frame buffer, one pixel per cell, shaded
+/// by the simulation's light map when lighting is enabled.
+/// Has no windowing or GPU dependencies so it can back any frontend or run headless.
+#[derive(Debug, Clone)]
+pub struct MaterialRenderer {
+    pub background: [u8; 3],
+    pub mode: RenderMode,
+    /// Tiles that materials assigned one are drawn from instead of their flat color, in
+    /// every mode that colors cells by material
+    pub textures: Option<Arc<TextureAtlas>>,
+}
+
+impl MaterialRenderer {
+    pub fn new() -> Self {
+        Self {
+            background: [0, 0, 0],
+            mode: RenderMode::Normal,
+            textures: None,
+        }
+    }
+
+    /// Size in bytes of a frame buffer for a grid of the given dimensions
+    pub fn frame_len(width: usize, height: usize) -> usize {
+        width * height * BYTES_PER_PIXEL
+    }
+
+    /// Render the simulation into `frame`, which is laid out row-major as RGBA8.
+    /// Extra bytes beyond the grid area are left untouched.
+    pub fn render(&self, simulation: &Simulation, frame: &mut [u8]) {
+        self.render_with_camera(simulation, frame, &Camera::default());
+    }
+
+    /// Render a grid-sized view shifted by the camera offset and scaled by its zoom;
+    /// cells outside the grid are drawn as background.
+    pub fn render_with_camera(&self, simulation: &Simulation, frame: &mut [u8], camera: &Camera) {
+        let (width, height) = (simulation.width, simulation.height);
+
+        for (index, pixel) in frame
+            .chunks_exact_mut(BYTES_PER_PIXEL)
+            .take(width * height)
+            .enumerate()
+        {
+            let (x, y) = camera.screen_to_cell(index % width, index / width);
+            let particle = if simulation.is_valid(x, y) {
+                simulation.get_particle(x as usize, y as usize)
+            } else {
+                None
+            };
+
+            let color = self.cell_color(simulation, particle, x, y);
+            pixel[0] = color[0];
+            pixel[1] = color[1];
+            pixel[2] = color[2];
+            pixel[3] = 255;
+        }
+
+        if self.mode == RenderMode::Velocity {
+            Self::draw_velocity_arrows(simulation, frame, camera);
+        }
+    }
+
+    /// Like `render_with_camera`, but particles that moved in the last update (with
+    /// `Simulation::enable_motion_tracking`) are drawn `alpha` of the way from the cell they
+    /// left to the one they are in, so frames between fixed ticks show smooth motion
+    pub fn render_interpolated(&self, simulation: &Simulation, frame: &mut [u8], camera: &Camera, alpha: f32) {
+        self.render_with_camera(simulation, frame, camera);
+        if alpha >= 1.0 {
+            return;
+        }
+        let moved = interpolation::moved_positions(simulation, alpha);
+        // Clear where the movers ended up before drawing them on their way there
+        for &(x, y) in moved.keys() {
+            let color = self.cell_color(simulation, None, x as i32, y as i32);
+            fill_cell(frame, simulation.width, simulation.height, camera.cell_to_screen(x as i32, y as i32), camera, color);
+        }
+        for (&(x, y), &(px, py)) in &moved {
+            let color = self.cell_color(simulation, simulation.get_particle(x, y), x as i32, y as i32);
+            fill_cell(frame, simulation.width, simulation.height, camera.position_to_screen(px, py), camera, color);
+        }
+    }
+
+    /// Bring `cache` up to date with the simulation, redrawing only the chunks written since
+    /// it was last drawn, one band of chunk rows per rayon task with the `parallel`
+    /// feature. Chunks written during the tick the cache was last drawn at are drawn again,
+    /// as the simulation can't tell those writes apart. Everything is redrawn the first
+    /// time, after the mode or background changes and, since their colors shift without
+    /// cells being written, while lighting is on or in temperature, velocity and radiation
+    /// modes.
+    pub fn render_cached(&self, simulation: &Simulation, cache: &mut FrameCache) {
+        let started = Instant::now();
+        let (width, height) = (simulation.width, simulation.height);
+        let (chunks_x, chunks_y) = simulation.chunk_dimensions();
+        if (cache.width, cache.height) != (width, height) {
+            cache.width = width;
+            cache.height = height;
+            cache.pixels = vec![0; MaterialRenderer::frame_len(width * cache.cell_size, height * cache.cell_size)];
+            cache.drawn_at = None;
+        }
+        let redraw_all = matches!(self.mode, RenderMode::Temperature | RenderMode::Velocity | RenderMode::Radiation)
+            || simulation.light_map().is_some()
+            || cache.drawn_with != Some(self.drawn_with())
+            || cache.drawn_at.is_none_or(|tick| tick > simulation.tick());
+        let dirty: Vec<bool> = (0..chunks_x * chunks_y)
+            .map(|chunk| redraw_all || cache.drawn_at.is_some_and(|tick| simulation.chunk_changed_since(chunk % chunks_x, chunk / chunks_x, tick)))
+            .collect();
+
+        let cell_size = cache.cell_size;
+        let row_len = width * cell_size * BYTES_PER_PIXEL;
+        let draw_band = |cy: usize, band: &mut [u8]| {
+            for cx in (0..chunks_x).filter(|&cx| dirty[cy * chunks_x + cx]) {
+                for y in cy * SIM_CHUNK_SIZE..((cy + 1) * SIM_CHUNK_SIZE).min(height) {
+                    for x in cx * SIM_CHUNK_SIZE..((cx + 1) * SIM_CHUNK_SIZE).min(width) {
+                        let color = self.cell_color(simulation, simulation.get_particle(x, y), x as i32, y as i32);
+                        let pixel = [color[0], color[1], color[2], 255];
+                        for py in (y - cy * SIM_CHUNK_SIZE) * cell_size..(y + 1 - cy * SIM_CHUNK_SIZE) * cell_size {
+                            let start = py * row_len + x * cell_size * BYTES_PER_PIXEL;
+                            for out in band[start..start + cell_size * BYTES_PER_PIXEL].chunks_exact_mut(BYTES_PER_PIXEL) {
+                                out.copy_from_slice(&pixel);
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        let band_len = SIM_CHUNK_SIZE * cell_size * row_len;
+        if band_len > 0 {
+            #[cfg(feature = "parallel")]
+            cache.pixels.par_chunks_mut(band_len).enumerate().for_each(|(cy, band)| draw_band(cy, band));
+            #[cfg(not(feature = "parallel"))]
+            cache.pixels.chunks_mut(band_len).enumerate().for_each(|(cy, band)| draw_band(cy, band));
+        }
+
+        cache.drawn_at = Some(simulation.tick());
+        cache.drawn_with = Some(self.drawn_with());
+        cache.chunks_drawn = dirty.iter().filter(|&&dirty| dirty).count();
+        cache.render_time = started.elapsed();
+    }
+
+    /// `render_cached`, then copy the grid-sized view through `camera` into `frame`, like
+    /// `render_with_camera` but without redrawing unchanged chunks
+    pub fn render_cached_with_camera(&self, simulation: &Simulation, cache: &mut FrameCache, frame: &mut [u8], camera: &Camera) {
+        self.render_cached(simulation, cache);
+        let (width, height) = (simulation.width, simulation.height);
+        let background = [self.background[0], self.background[1], self.background[2], 255];
+        let source_row = width * cache.cell_size;
+        for (index, pixel) in frame.chunks_exact_mut(BYTES_PER_PIXEL).take(width * height).enumerate() {
+            let (x, y) = camera.screen_to_cell(index % width, index / width);
+            if simulation.is_valid(x, y) {
+                let offset = (y as usize * cache.cell_size * source_row + x as usize * cache.cell_size) * BYTES_PER_PIXEL;
+                pixel.copy_from_slice(&cache.pixels[offset..offset + BYTES_PER_PIXEL]);
+            } else {
+                pixel.copy_from_slice(&background);
+            }
+        }
+        if self.mode == RenderMode::Velocity {
+            Self::draw_velocity_arrows(simulation, frame, camera);
+        }
+    }
+
+    /// Color of a cell holding `particle` (or nothing) in the current mode, lit by the
+    /// simulation's light map where that applies
+    fn cell_color(&self, simulation: &Simulation, particle: Option<&Particle>, x: i32, y: i32) -> [u8; 3] {
+        let particle = particle.filter(|particle| particle.material_type != MaterialType::Empty);
+        let color = match (self.mode, particle) {
+            (RenderMode::Radiation, particle) => {
+                let dose = if simulation.is_valid(x, y) { simulation.radiation_at(x as usize, y as usize) } else { 0.0 };
+                let base = particle.map_or(self.background, |particle| particle.color_with(simulation.materials().get(particle.material_type)));
+                radiation_color(dose, base)
+            }
+            (_, None) => self.background,
+            (RenderMode::Temperature, Some(particle)) => temperature_color(particle.temp),
+            (RenderMode::Velocity, Some(particle)) => Self::motion_color(particle),
+            (_, Some(particle)) if particle.material_type.is_custom() => {
+                particle.color_with(simulation.materials().get(particle.material_type))
+            }
+            (_, Some(particle)) => particle.color(),
+        };
+        let color = match (self.textures.as_ref(), particle) {
+            (Some(textures), Some(particle)) if !matches!(self.mode, RenderMode::Temperature | RenderMode::Velocity | RenderMode::Radiation) => {
+                textured_color(textures, simulation, particle, x, y, color)
+            }
+            _ => color,
+        };
+        let shaded = !matches!(self.mode, RenderMode::Temperature | RenderMode::Velocity | RenderMode::Radiation);
+        match simulation.light_map().filter(|_| shaded && simulation.is_valid(x, y)) {
+            Some(light_map) => light_map.shade(x as usize, y as usize, color),
+            None => color,
+        }
+    }
+
+    /// Settings a cached frame depends on; the atlas is told apart by its address, so
+    /// swapping in another one redraws everything
+    fn drawn_with(&self) -> (RenderMode, [u8; 3], usize) {
+        let textures = self.textures.as_ref().map_or(0, |textures| Arc::as_ptr(textures) as usize);
+        (self.mode, self.background, textures)
+    }
+
+    /// Bright for particles that moved this step or carry an impulse, fading towards dark
+    /// gray the longer a particle has been settled
+    fn motion_color(particle: &Particle) -> [u8; 3] {
+        if particle.has_velocity() {
+            return [255, 80, 220];
+        }
+        if particle.moved_this_step {
+            return [255, 255, 255];
+        }
+        let settled = particle.settled_frames as f32 / u8::MAX as f32;
+        let level = (200.0 - 170.0 * settled.sqrt()) as u8;
+        [level, level, level]
+    }
+
+    /// An arrow from the centre of each `VELOCITY_ARROW_SPACING` block along the mean
+    /// impulse velocity of the particles in it, one frame pixel per cell per tick
+    fn draw_velocity_arrows(simulation: &Simulation, frame: &mut [u8], camera: &Camera) {
+        let (width, height) = (simulation.width, simulation.height);
+        for block_y in (0..height).step_by(VELOCITY_ARROW_SPACING) {
+            for block_x in (0..width).step_by(VELOCITY_ARROW_SPACING) {
+                let block = GridRect::new(block_x, block_y, VELOCITY_ARROW_SPACING, VELOCITY_ARROW_SPACING);
+                let (mut vx, mut vy, mut moving) = (0.0, 0.0, 0);
+                for particle in simulation.iter_region(block).filter(|particle| particle.has_velocity()) {
+                    vx += particle.vx;
+                    vy += particle.vy;
+                    moving += 1;
+                }
+                if moving == 0 {
+                    continue;
+                }
+
+                let centre = (block_x + VELOCITY_ARROW_SPACING / 2, block_y + VELOCITY_ARROW_SPACING / 2);
+                let (start_x, start_y) = camera.cell_to_screen(centre.0 as i32, centre.1 as i32);
+                let scale = camera.zoom * VELOCITY_ARROW_SPACING as f32 / 2.0;
+                let end_x = start_x + (vx / moving as f32 * scale).round() as i32;
+                let end_y = start_y + (vy / moving as f32 * scale).round() as i32;
+                let clamp = |value: i32, limit: usize| value.clamp(0, limit as i32 - 1) as usize;
+                let from = (clamp(start_x, width), clamp(start_y, height));
+                let to = (clamp(end_x, width), clamp(end_y, height));
+                for (index, (x, y)) in line_cells(from, to).into_iter().enumerate() {
+                    // The tail starts yellow so the arrow's direction reads at a glance
+                    let color = if index == 0 { [255, 255, 0] } else { [255, 0, 0] };
+                    let offset = (y * width + x) * BYTES_PER_PIXEL;
+                    frame[offset..offset + 3].copy_from_slice(&color);
+                }
+            }
+        }
+    }
+}
+
+/// The texel of `particle`'s material at its cell, shifted as far from it as `color` is
+/// from the material's base color so heat, wetness and burning still show; `color` as
+/// is for materials without a tile
+fn textured_color(textures: &TextureAtlas, simulation: &Simulation, particle: &Particle, x: i32, y: i32, color: [u8; 3]) -> [u8; 3] {
+    let Some(texel) = textures.sample(particle.material_type, x, y) else {
+        return color;
+    };
+    let base = simulation.materials().get(particle.material_type).base_color;
+    std::array::from_fn(|channel| (texel[channel] as i32 + color[channel] as i32 - base[channel] as i32).clamp(0, 255) as u8)
+}
+
+/// Paint the pixels one cell covers at `camera`'s zoom, from its top-left corner at
+/// `(screen_x, screen_y)`, clipped to a `width` x `height` frame
+fn fill_cell(frame: &mut [u8], width: usize, height: usize, (screen_x, screen_y): (i32, i32), camera: &Camera, color: [u8; 3]) {
+    let size = (camera.zoom.ceil() as i32).max(1);
+    for py in screen_y.max(0)..(screen_y + size).min(height as i32) {
+        for px in screen_x.max(0)..(screen_x + size).min(width as i32) {
+            let offset = (py as usize * width + px as usize) * BYTES_PER_PIXEL;
+            frame[offset..offset + 3].copy_from_slice(&color);
+        }
+    }
+}
+
+impl Default for MaterialRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A whole simulation grid drawn `cell_size` frame pixels to a cell and kept between
+/// frames, so `MaterialRenderer::render_cached` only redraws the chunks that changed
+#[derive(Debug, Clone)]
+pub struct FrameCache {
+    cell_size: usize,
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    // Simulation tick and renderer settings the pixels were drawn at
+    drawn_at: Option<u64>,
+    drawn_with: Option<(RenderMode, [u8; 3], usize)>,
+    chunks_drawn: usize,
+    render_time: Duration,
+}
+
+impl FrameCache {
+    /// An empty cache drawing each cell as a `cell_size` pixel square
+    pub fn new(cell_size: usize) -> Self {
+        Self {
+            cell_size: cell_size.max(1),
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            drawn_at: None,
+            drawn_with: None,
+            chunks_drawn: 0,
+            render_time: Duration::ZERO,
+        }
+    }
+
+    pub fn cell_size(&self) -> usize {
+        self.cell_size
+    }
+
+    /// Frame pixels wide and high
+    pub fn frame_dimensions(&self) -> (usize, usize) {
+        (self.width * self.cell_size, self.height * self.cell_size)
+    }
+
+    /// The frame as last drawn, row-major RGBA8
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Chunks the last `render_cached` redrew
+    pub fn chunks_drawn(&self) -> usize {
+        self.chunks_drawn
+    }
+
+    /// Time the last `render_cached` took
+    pub fn render_time(&self) -> Duration {
+        self.render_time
+    }
+
+    /// Have the next `render_cached` redraw everything
+    pub fn invalidate(&mut self) {
+        self.drawn_at = None;
+    }
+}
+
+impl Default for FrameCache {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// What the chunk activity overlay shows a chunk as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkActivity {
+    /// Skipped by updates until something wakes it
+    Sleeping,
+    /// Updated every tick
+    Active,
+    /// Had cells written since the reference tick
+    Dirty,
+    /// Still being generated or streamed in
+    Generating,
+    /// Holds pixels of a rigid body
+    RigidBody,
+}
+
+impl ChunkActivity {
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            ChunkActivity::Sleeping => [40, 60, 140],
+            ChunkActivity::Active => [60, 200, 80],
+            ChunkActivity::Dirty => [255, 170, 0],
+            ChunkActivity::Generating => [210, 70, 210],
+            ChunkActivity::RigidBody => [0, 200, 230],
+        }
+    }
+}
+
+/// Activity and particle count of every chunk of a simulation's update grid.
+/// Generating and rigid body chunks aren't known to the simulation; callers mark them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkActivityMap {
+    width: usize,
+    height: usize,
+    chunks_x: usize,
+    chunks_y: usize,
+    states: Vec<ChunkActivity>,
+    counts: Vec<usize>,
+}
+
+impl ChunkActivityMap {
+    /// Classify each chunk as dirty if any of its cells were written after `since_tick`,
+    /// active if it is awake for the next update, and sleeping otherwise
+    pub fn capture(simulation: &Simulation, since_tick: u64) -> Self {
+        let (chunks_x, chunks_y) = simulation.chunk_dimensions();
+        let mut states = Vec::with_capacity(chunks_x * chunks_y);
+        for cy in 0..chunks_y {
+            for cx in 0..chunks_x {
+                states.push(if simulation.chunk_changed_since(cx, cy, since_tick) {
+                    ChunkActivity::Dirty
+                } else if simulation.is_chunk_awake(cx, cy) {
+                    ChunkActivity::Active
+                } else {
+                    ChunkActivity::Sleeping
+                });
+            }
+        }
+        let mut counts = vec![0; chunks_x * chunks_y];
+        for particle in simulation.iter_particles() {
+            counts[(particle.y / SIM_CHUNK_SIZE) * chunks_x + particle.x / SIM_CHUNK_SIZE] += 1;
+        }
+        Self { width: simulation.width, height: simulation.height, chunks_x, chunks_y, states, counts }
+    }
+
+    /// (chunks wide, chunks high)
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.chunks_x, self.chunks_y)
+    }
+
+    pub fn get(&self, cx: usize, cy: usize) -> Option<ChunkActivity> {
+        (cx < self.chunks_x && cy < self.chunks_y).then(|| self.states[cy * self.chunks_x + cx])
+    }
+
+    pub fn particle_count(&self, cx: usize, cy: usize) -> usize {
+        if cx < self.chunks_x && cy < self.chunks_y { self.counts[cy * self.chunks_x + cx] } else { 0 }
+    }
+
+    /// Number of chunks in `activity`
+    pub fn count(&self, activity: ChunkActivity) -> usize {
+        self.states.iter().filter(|state| **state == activity).count()
+    }
+
+    pub fn mark(&mut self, cx: usize, cy: usize, activity: ChunkActivity) {
+        if cx < self.chunks_x && cy < self.chunks_y {
+            self.states[cy * self.chunks_x + cx] = activity;
+        }
+    }
+
+    /// Mark every chunk containing one of `cells`, e.g. the pixels of rigid bodies
+    pub fn mark_cells(&mut self, cells: impl IntoIterator<Item = (i64, i64)>, activity: ChunkActivity) {
+        for (x, y) in cells {
+            if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+                self.mark(x as usize / SIM_CHUNK_SIZE, y as usize / SIM_CHUNK_SIZE, activity);
+            }
+        }
+    }
+}
+
+/// Debug overlay for diagnosing chunk activation: tints each chunk by its activity,
+/// outlines the chunk grid and labels chunks with their particle counts
+#[derive(Debug, Clone)]
+pub struct ChunkOverlay {
+    /// How strongly chunks are tinted, from 0 to 1
+    pub opacity: f32,
+    pub show_counts: bool,
+    text: TextRenderer,
+}
+
+impl ChunkOverlay {
+    pub fn new() -> Self {
+        Self {
+            opacity: 0.35,
+            show_counts: true,
+            text: TextRenderer::new(),
+        }
+    }
+
+    /// Draw over a `width` x `height` frame already rendered through `camera`
+    pub fn draw(&self, frame: &mut [u8], width: usize, height: usize, map: &ChunkActivityMap, camera: &Camera) {
+        let opacity = self.opacity.clamp(0.0, 1.0);
+        for (index, pixel) in frame.chunks_exact_mut(BYTES_PER_PIXEL).take(width * height).enumerate() {
+            let (x, y) = camera.screen_to_cell(index % width, index / width);
+            if x < 0 || y < 0 || x as usize >= map.width || y as usize >= map.height {
+                continue;
+            }
+            let (x, y) = (x as usize, y as usize);
+            let Some(activity) = map.get(x / SIM_CHUNK_SIZE, y / SIM_CHUNK_SIZE) else {
+                continue;
+            };
+            let color = activity.color();
+            // The top and left cells of each chunk outline the chunk grid
+            let border = x.is_multiple_of(SIM_CHUNK_SIZE) || y.is_multiple_of(SIM_CHUNK_SIZE);
+            let alpha = if border { 1.0 } else { opacity };
+            for channel in 0..3 {
+                pixel[channel] = (pixel[channel] as f32 * (1.0 - alpha) + color[channel] as f32 * alpha) as u8;
+            }
+        }
+
+        if !self.show_counts {
+            return;
+        }
+        let chunk_pixels = (SIM_CHUNK_SIZE as f32 * camera.zoom) as usize;
+        for cy in 0..map.chunks_y {
+            for cx in 0..map.chunks_x {
+                let count = map.particle_count(cx, cy);
+                let label = count.to_string();
+                if count == 0 || TextRenderer::text_width(&label) + 3 > chunk_pixels {
+                    continue;
+                }
+                let (screen_x, screen_y) = camera.cell_to_screen((cx * SIM_CHUNK_SIZE) as i32, (cy * SIM_CHUNK_SIZE) as i32);
+                self.text.draw_text(frame, width, height, screen_x + 2, screen_y + 2, &label);
+            }
+        }
+    }
+}
+
+impl Default for ChunkOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Debug overlay outlining groups of cells, one color per group, e.g. the rigid bodies
+/// from `AdvancedPhysicsEngine::rigid_body_cells` or `Simulation::rigid_regions`
+#[derive(Debug, Clone)]
+pub struct RigidBodyOverlay {
+    pub colors: Vec<[u8; 3]>,
+}
+
+impl RigidBodyOverlay {
+    pub fn new() -> Self {
+        Self {
+            colors: vec![[0, 200, 230], [255, 120, 0], [140, 255, 60], [255, 60, 160]],
+        }
+    }
+
+    /// Draw over a `width` x `height` frame already rendered through `camera`: every cell
+    /// of a body with a 4-neighbour outside the body is painted in the body's color
+    pub fn draw(&self, frame: &mut [u8], width: usize, height: usize, bodies: &[Vec<(i64, i64)>], camera: &Camera) {
+        if self.colors.is_empty() {
+            return;
+        }
+        for (index, body) in bodies.iter().enumerate() {
+            let color = self.colors[index % self.colors.len()];
+            let cells: HashSet<(i64, i64)> = body.iter().copied().collect();
+            let edges = body.iter().filter(|&&(x, y)| {
+                [(1, 0), (-1, 0), (0, 1), (0, -1)].iter().any(|(dx, dy)| !cells.contains(&(x + dx, y + dy)))
+            });
+            for &(x, y) in edges {
+                let (screen_x, screen_y) = camera.cell_to_screen(x as i32, y as i32);
+                let size = (camera.zoom.ceil() as i32).max(1);
+                for py in screen_y.max(0)..(screen_y + size).min(height as i32) {
+                    for px in screen_x.max(0)..(screen_x + size).min(width as i32) {
+                        let offset = (py as usize * width + px as usize) * BYTES_PER_PIXEL;
+                        frame[offset..offset + 3].copy_from_slice(&color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for RigidBodyOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draws the sparks, splashes and embers of an `EffectLayer` over a rendered frame,
+/// each fading out as it ages
+#[derive(Debug, Clone)]
+pub struct EffectOverlay {
+    /// Opacity of a fresh effect, from 0 to 1
+    pub opacity: f32,
+}
+
+impl EffectOverlay {
+    pub fn new() -> Self {
+        Self { opacity: 0.9 }
+    }
+
+    /// Draw over a `width` x `height` frame already rendered through `camera`
+    pub fn draw(&self, frame: &mut [u8], width: usize, height: usize, effects: &EffectLayer, camera: &Camera) {
+        let size = (camera.zoom.ceil() as i32).max(1);
+        for effect in effects.iter() {
+            let (x, y) = effect.cell();
+            let (screen_x, screen_y) = camera.cell_to_screen(x, y);
+            let color = effect.kind.color();
+            let alpha = self.opacity.clamp(0.0, 1.0) * effect.remaining();
+            for py in screen_y.max(0)..(screen_y + size).min(height as i32) {
+                for px in screen_x.max(0)..(screen_x + size).min(width as i32) {
+                    let offset = (py as usize * width + px as usize) * BYTES_PER_PIXEL;
+                    for channel in 0..3 {
+                        let pixel = &mut frame[offset + channel];
+                        *pixel = (*pixel as f32 * (1.0 - alpha) + color[channel] as f32 * alpha) as u8;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for EffectOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draws short strings into RGBA8 frames with a built-in 3x5 bitmap font, e.g. sign
+/// labels. Letters are drawn upper case; characters without a glyph show as '?'.
+#[derive(Debug, Clone)]
+pub struct TextRenderer {
+    pub color: [u8; 3],
+    /// Drawn one pixel down and right of every glyph pixel so text stays readable
+    pub shadow: Option<[u8; 3]>,
+}
+
+impl TextRenderer {
+    pub fn new() -> Self {
+        Self {
+            color: [255, 255, 255],
+            shadow: Some([0, 0, 0]),
+        }
+    }
+
+    /// Width in pixels of `text` when drawn
+    pub fn text_width(text: &str) -> usize {
+        (text.chars().count() * GLYPH_ADVANCE).saturating_sub(1)
+    }
+
+    /// Draw `text` with its top-left corner at `(x, y)` in a `width` x `height` frame,
+    /// clipping anything outside it
+    pub fn draw_text(&self, frame: &mut [u8], width: usize, height: usize, x: i32, y: i32, text: &str) {
+        if let Some(shadow) = self.shadow {
+            draw_glyphs(frame, width, height, x + 1, y + 1, text, shadow);
+        }
+        draw_glyphs(frame, width, height, x, y, text, self.color);
+    }
+
+    /// Draw each sign's text centred just above the sign, as seen through the camera
+    pub fn draw_signs(&self, frame: &mut [u8], width: usize, height: usize, tile_entities: &TileEntityManager, camera: &Camera) {
+        for ((x, y), text) in tile_entities.signs() {
+            let (screen_x, screen_y) = camera.cell_to_screen(x as i32, y as i32);
+            let centre = screen_x + (camera.zoom / 2.0) as i32;
+            let left = centre - Self::text_width(text) as i32 / 2;
+            let top = screen_y - GLYPH_HEIGHT as i32 - 1;
+            self.draw_text(frame, width, height, left, top, text);
+        }
+    }
+}
+
+impl Default for TextRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn draw_glyphs(frame: &mut [u8], width: usize, height: usize, x: i32, y: i32, text: &str, color: [u8; 3]) {
+    for (index, c) in text.chars().enumerate() {
+        let left = x + (index * GLYPH_ADVANCE) as i32;
+        for (row, bits) in glyph(c).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - column)) == 0 {
+                    continue;
+                }
+                let (px, py) = (left + column as i32, y + row as i32);
+                if px < 0 || py < 0 || px as usize >= width || py as usize >= height {
+                    continue;
+                }
+                let offset = (py as usize * width + px as usize) * BYTES_PER_PIXEL;
+                if let Some(pixel) = frame.get_mut(offset..offset + BYTES_PER_PIXEL) {
+                    pixel.copy_from_slice(&[color[0], color[1], color[2], 255]);
+                }
+            }
+        }
+    }
+}
+
+/// Rows of a character, top first, with the leftmost pixel in the highest of three bits
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [6, 1, 2, 4, 7],
+        '3' => [6, 1, 2, 1, 6],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 6, 1, 6],
+        '6' => [3, 4, 6, 5, 2],
+        '7' => [7, 1, 2, 2, 2],
+        '8' => [2, 5, 2, 5, 2],
+        '9' => [2, 5, 3, 1, 6],
+        ' ' => [0, 0, 0, 0, 0],
+        '.' => [0, 0, 0, 0, 2],
+        ',' => [0, 0, 0, 2, 4],
+        '!' => [2, 2, 2, 0, 2],
+        ':' => [0, 2, 0, 2, 0],
+        '-' => [0, 0, 7, 0, 0],
+        '+' => [0, 2, 7, 2, 0],
+        '=' => [0, 7, 0, 7, 0],
+        '/' => [1, 1, 2, 4, 4],
+        '\'' => [2, 2, 0, 0, 0],
+        '"' => [5, 5, 0, 0, 0],
+        '(' => [1, 2, 2, 2, 1],
+        ')' => [4, 2, 2, 2, 4],
+        '<' => [1, 2, 4, 2, 1],
+        '>' => [4, 2, 1, 2, 4],
+        '_' => [0, 0, 0, 0, 7],
+        '#' => [5, 7, 5, 7, 5],
+        '%' => [5, 1, 2, 4, 5],
+        _ => [6, 1, 2, 0, 2],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::radioactivity::RADIOACTIVITY_PASS_INTERVAL;
+    use crate::tile_entity::TileEntity;
+
+    fn lit_pixels(frame: &[u8]) -> Vec<(usize, usize)> {
+        frame.chunks(BYTES_PER_PIXEL)
+            .enumerate()
+            .filter(|(_, pixel)| pixel[0] == 255)
+            .map(|(index, _)| (index % 20, index / 20))
+            .collect()
+    }
+
+    #[test]
+    fn test_draw_text_clips_to_frame() {
+        let renderer = TextRenderer { shadow: None, ..TextRenderer::new() };
+        let mut frame = vec![0; MaterialRenderer::frame_len(20, 10)];
+        renderer.draw_text(&mut frame, 20, 10, 18, -2, "T");
+
+        // The top bar is above the frame, so only the stem's visible rows are drawn
+        assert_eq!(lit_pixels(&frame), vec![(19, 0), (19, 1), (19, 2)]);
+        assert_eq!(TextRenderer::text_width("HI"), 7);
+    }
+
+    #[test]
+    fn test_temperature_render_mode() {
+        let mut simulation = Simulation::new(4, 1);
+        simulation.add_particle(0, 0, MaterialType::Stone, Some(20.0));
+        simulation.add_particle(1, 0, MaterialType::Stone, Some(1200.0));
+        let mut renderer = MaterialRenderer::new();
+        let mut frame = vec![0; MaterialRenderer::frame_len(4, 1)];
+
+        renderer.mode = RenderMode::Temperature;
+        renderer.render(&simulation, &mut frame);
+        assert_eq!(frame[0..3], temperature_color(20.0));
+        assert_eq!(frame[4..7], temperature_color(1200.0));
+        assert_ne!(frame[0..3], frame[4..7]);
+        assert_eq!(frame[8..11], [0, 0, 0], "empty cells stay background");
+        assert_eq!(temperature_color(-500.0), HEATMAP_STOPS[0].1);
+        assert_eq!(RenderMode::RigidBodies.next(), RenderMode::Normal);
+    }
+
+    #[test]
+    fn test_radiation_render_mode() {
+        let mut simulation = Simulation::new(12, 1);
+        simulation.enable_radioactivity();
+        simulation.add_particle(0, 0, MaterialType::Uranium, None);
+        simulation.add_particle(10, 0, MaterialType::Stone, None);
+        simulation.update(RADIOACTIVITY_PASS_INTERVAL);
+        let mut renderer = MaterialRenderer::new();
+        let mut frame = vec![0; MaterialRenderer::frame_len(12, 1)];
+
+        renderer.mode = RenderMode::Radiation;
+        renderer.render(&simulation, &mut frame);
+        let pixel = |x: usize| [frame[x * 4], frame[x * 4 + 1], frame[x * 4 + 2]];
+        assert_eq!(pixel(1), RADIATION_COLOR, "empty cells in range show their dose");
+        assert_eq!(pixel(10), radiation_color(0.0, simulation.get_particle(10, 0).unwrap().color()));
+        assert!(pixel(10)[0] == pixel(10)[1] && pixel(10)[1] == pixel(10)[2]);
+        assert_eq!(RenderMode::Velocity.next(), RenderMode::Radiation);
+    }
+
+    #[test]
+    fn test_cached_render_redraws_only_changed_chunks() {
+        let mut simulation = Simulation::new(SIM_CHUNK_SIZE * 3, SIM_CHUNK_SIZE * 2);
+        simulation.add_particle(3, 3, MaterialType::Generator, None);
+        simulation.update(1.0 / 60.0);
+        let renderer = MaterialRenderer::new();
+        let mut cache = FrameCache::default();
+        let mut frame = vec![0; MaterialRenderer::frame_len(simulation.width, simulation.height)];
+
+        renderer.render_cached(&simulation, &mut cache);
+        assert_eq!(cache.chunks_drawn(), 6);
+        renderer.render(&simulation, &mut frame);
+        assert_eq!(cache.pixels(), &frame[..]);
+
+        renderer.render_cached(&simulation, &mut cache);
+        assert_eq!(cache.chunks_drawn(), 0);
+        let x = SIM_CHUNK_SIZE * 2 + 1;
+        simulation.add_particle(x, SIM_CHUNK_SIZE + 1, MaterialType::Stone, None);
+        simulation.remove_particle(3, 3);
+        renderer.render_cached(&simulation, &mut cache);
+        assert_eq!(cache.chunks_drawn(), 2);
+        renderer.render(&simulation, &mut frame);
+        assert_eq!(cache.pixels(), &frame[..]);
+    }
+
+    #[test]
+    fn test_cached_render_keeps_up_with_heat_fire_and_smoke() {
+        let mut simulation = Simulation::new(SIM_CHUNK_SIZE * 4, SIM_CHUNK_SIZE);
+        simulation.set_seed(7);
+        for x in 0..SIM_CHUNK_SIZE * 4 {
+            simulation.add_particle(x, SIM_CHUNK_SIZE - 1, MaterialType::Stone, None);
+        }
+        for x in 4..8 {
+            simulation.add_particle(x, SIM_CHUNK_SIZE - 2, MaterialType::Lava, None);
+        }
+        simulation.add_particle(20, SIM_CHUNK_SIZE - 2, MaterialType::Wood, Some(600.0));
+        // Smoke shut in a stone pocket fades where it is
+        for (x, y) in [(39, 4), (40, 3), (41, 4), (40, 5)] {
+            simulation.add_particle(x, y, MaterialType::Stone, None);
+        }
+        simulation.add_particle(40, 4, MaterialType::Smoke, None);
+        // Warm stone cooling a little each tick
+        simulation.add_particle(56, 4, MaterialType::Stone, Some(80.0));
+
+        for mode in [RenderMode::Normal, RenderMode::Temperature] {
+            let mut renderer = MaterialRenderer::new();
+            renderer.mode = mode;
+            let mut cache = FrameCache::default();
+            let mut frame = vec![0; MaterialRenderer::frame_len(simulation.width, simulation.height)];
+            for tick in 0..120 {
+                simulation.update(1.0 / 60.0);
+                renderer.render_cached(&simulation, &mut cache);
+                renderer.render(&simulation, &mut frame);
+                assert!(cache.pixels() == &frame[..], "{:?} render went stale at tick {}", mode, tick);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cached_render_scales_cells() {
+        let mut simulation = Simulation::new(4, 2);
+        simulation.add_particle(1, 1, MaterialType::Stone, None);
+        let mut cache = FrameCache::new(3);
+        MaterialRenderer::new().render_cached(&simulation, &mut cache);
+        assert_eq!(cache.frame_dimensions(), (12, 6));
+        assert_eq!(cache.pixels().len(), MaterialRenderer::frame_len(12, 6));
+
+        let stone = simulation.get_particle(1, 1).unwrap().color();
+        let pixel = |x: usize, y: usize| &cache.pixels()[(y * 12 + x) * BYTES_PER_PIXEL..][..3];
+        assert!((3..6).all(|y| (3..6).all(|x| pixel(x, y) == stone)));
+        assert_eq!(pixel(2, 3), [0, 0, 0]);
+        assert_eq!(pixel(3, 2), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_interpolated_render_draws_movers_part_way() {
+        let mut simulation = Simulation::new(4, 16);
+        simulation.set_seed(5);
+        simulation.enable_motion_tracking();
+        simulation.add_particle(1, 0, MaterialType::Sand, None);
+        simulation.update(1.0 / 30.0);
+        simulation.update(1.0 / 30.0);
+        let step = simulation.last_moves()[0];
+        assert!(step.to.1 > step.from.1);
+
+        let renderer = MaterialRenderer::new();
+        let camera = Camera::new();
+        let drawn = |alpha: f32| {
+            let mut frame = vec![0; MaterialRenderer::frame_len(4, 16)];
+            renderer.render_interpolated(&simulation, &mut frame, &camera, alpha);
+            frame.chunks(BYTES_PER_PIXEL)
+                .enumerate()
+                .filter(|(_, pixel)| pixel[..3] != [0, 0, 0])
+                .map(|(index, _)| (index % 4, index / 4))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(drawn(0.0), vec![step.from]);
+        assert_eq!(drawn(1.0), vec![step.to]);
+    }
+
+    #[test]
+    fn test_rigid_body_overlay_outlines() {
+        let mut simulation = Simulation::new(8, 8);
+        for y in 2..5 {
+            for x in 2..5 {
+                simulation.add_particle(x, y, MaterialType::Stone, None);
+            }
+        }
+        simulation.add_particle(7, 7, MaterialType::Sand, None);
+        let regions = simulation.rigid_regions();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].len(), 9);
+
+        let overlay = RigidBodyOverlay { colors: vec![[255, 0, 0]] };
+        let mut frame = vec![0; MaterialRenderer::frame_len(8, 8)];
+        overlay.draw(&mut frame, 8, 8, &regions, &Camera::default());
+        let outlined: Vec<(usize, usize)> = frame.chunks(BYTES_PER_PIXEL)
+            .enumerate()
+            .filter(|(_, pixel)| pixel[0..3] == [255, 0, 0])
+            .map(|(index, _)| (index % 8, index / 8))
+            .collect();
+        // The ring of eight around the centre, which has all four neighbours inside
+        assert_eq!(outlined.len(), 8);
+        assert!(!outlined.contains(&(3, 3)));
+    }
+
+    #[test]
+    fn test_chunk_activity_map() {
+        let mut simulation = Simulation::new(48, 32);
+        for _ in 0..200 {
+            simulation.update(1.0 / 60.0);
+        }
+        let settled = simulation.tick();
+        simulation.add_particle(20, 5, MaterialType::Sand, None);
+        simulation.add_particle(21, 5, MaterialType::Sand, None);
+
+        let mut map = ChunkActivityMap::capture(&simulation, settled);
+        assert_eq!(map.dimensions(), (3, 2));
+        assert_eq!(map.get(1, 0), Some(ChunkActivity::Dirty));
+        assert_eq!(map.particle_count(1, 0), 2);
+        assert_eq!(map.get(2, 1), Some(ChunkActivity::Sleeping));
+
+        map.mark_cells([(40, 20), (-3, 4)], ChunkActivity::RigidBody);
+        assert_eq!(map.get(2, 1), Some(ChunkActivity::RigidBody));
+        assert_eq!(map.count(ChunkActivity::Sleeping), 4);
+    }
+
+    #[test]
+    fn test_chunk_overlay_tints_and_outlines() {
+        let simulation = Simulation::new(32, 16);
+        let map = ChunkActivityMap::capture(&simulation, simulation.tick());
+        let overlay = ChunkOverlay { opacity: 0.5, ..ChunkOverlay::new() };
+        let mut frame = vec![0; MaterialRenderer::frame_len(32, 16)];
+        overlay.draw(&mut frame, 32, 16, &map, &Camera::new());
+
+        let pixel = |x: usize, y: usize| &frame[(y * 32 + x) * BYTES_PER_PIXEL..][..3];
+        let color = ChunkActivity::Sleeping.color();
+        assert_eq!(pixel(16, 5), color);
+        assert_eq!(pixel(5, 5), [color[0] / 2, color[1] / 2, color[2] / 2]);
+    }
+
+    #[test]
+    fn test_signs_drawn_above_position() {
+        let mut tile_entities = TileEntityManager::new();
+        tile_entities.add_tile_entity(TileEntity::new_sign((10, 8), "i"));
+        let renderer = TextRenderer { shadow: None, ..TextRenderer::new() };
+        let mut frame = vec![0; MaterialRenderer::frame_len(20, 10)];
+
+        renderer.draw_signs(&mut frame, 20, 10, &tile_entities, &Camera::new());
+
+        let pixels = lit_pixels(&frame);
+        assert!(!pixels.is_empty());
+        assert!(pixels.iter().all(|&(x, y)| (9..12).contains(&x) && (2..7).contains(&y)));
+    }
+}
@@ -1,10 +1,31 @@
 use crate::materials::{MaterialType, get_material_properties};
 use crate::chunk::{ChunkManager, ChunkKey, CHUNK_SIZE};
+use crate::event_bus::SimulationEvent;
+use crate::explosion::{Explosion, BLAST_BODY_SPEED, BLAST_BREAK_POWER};
+use crate::save_load::SaveLoadError;
 use nalgebra::{Point2, Vector2, UnitComplex};
 use rapier2d::prelude::*;
-use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
 const PHYSICS_SCALE: f32 = 0.1; // Scale factor for physics world (pixels to meters)
+/// Downward gravity for rigid bodies; stronger than real gravity for falling sand
+pub const DEFAULT_GRAVITY: f32 = 9.81 * 10.0;
+/// Cells around a body, beyond how far it moves in a step, sampled into terrain colliders
+const TERRAIN_MARGIN: i64 = 3;
+/// Speed in cells per second above which a body plows through powder instead of resting on it
+pub const PLOW_SPEED: f32 = 30.0;
+/// How far sideways (or up) a body shoves a loose particle in its way
+const DISPLACE_REACH: i64 = 4;
+/// Share of a fully submerged body's velocity liquid damps away per second
+const LIQUID_DRAG: f32 = 3.0;
+/// Steps a body must stay still before it is left in the grid as ordinary particles
+const SETTLE_STEPS: u32 = 30;
+/// How hard a hinge motor pulls its bodies towards the target speed
+const MOTOR_DAMPING: f32 = 10.0;
+/// Sudden change in a body's speed, in cells per second, that counts as hitting something;
+/// well above the pull of gravity in one step, which resting bodies feel as a steady push
+const IMPACT_MIN_SPEED_CHANGE: f32 = 40.0;
 
 #[derive(Debug, Clone)]
 pub struct RigidBodyData {
@@ -14,6 +35,14 @@ pub struct RigidBodyData {
     pub handle: Option<RigidBodyHandle>,
     pub collider_handle: Option<ColliderHandle>,
     pub chunk_origin: ChunkKey,
+    /// Cells the body currently occupies in the grid
+    pub stamped: Vec<(i64, i64, MaterialType)>,
+    /// Index into `pixels` of each `stamped` cell
+    pub stamped_pixels: Vec<usize>,
+    /// Consecutive steps the body has been still
+    pub rest_steps: u32,
+    /// Pixels lost since connectivity was last checked, where they were in `pixels`' coordinates
+    pub broken_pixels: Vec<(i32, i32)>,
 }
 
 impl RigidBodyData {
@@ -43,8 +72,52 @@ impl RigidBodyData {
             handle: None,
             collider_handle: None,
             chunk_origin,
+            stamped: Vec::new(),
+            stamped_pixels: Vec::new(),
+            rest_steps: 0,
+            broken_pixels: Vec::new(),
         }
     }
+
+    /// Drop the pixels at `indices`, remembering where they were for `split_damaged_bodies`.
+    /// Their cells are left in the grid as they are.
+    pub fn remove_pixels(&mut self, indices: &HashSet<usize>) {
+        if indices.is_empty() {
+            return;
+        }
+        let mut remap = vec![None; self.pixels.len()];
+        let mut kept = Vec::with_capacity(self.pixels.len());
+        for (index, &pixel) in self.pixels.iter().enumerate() {
+            if indices.contains(&index) {
+                self.broken_pixels.push((pixel.0, pixel.1));
+            } else {
+                remap[index] = Some(kept.len());
+                kept.push(pixel);
+            }
+        }
+        self.pixels = kept;
+        let (stamped, stamped_pixels) = self.stamped.iter().zip(&self.stamped_pixels)
+            .filter_map(|(&cell, &pixel)| remap[pixel].map(|pixel| (cell, pixel)))
+            .unzip();
+        self.stamped = stamped;
+        self.stamped_pixels = stamped_pixels;
+    }
+
+    /// The cell each pixel covers, with its index, when the body's physics body is at
+    /// `position` turned by `rotation`
+    fn cells_at(&self, position: &Vector2<f32>, rotation: &UnitComplex<f32>) -> Vec<(usize, (i64, i64, MaterialType))> {
+        let center = position / PHYSICS_SCALE;
+        let center_of_mass = Vector2::new(self.center_of_mass.0, self.center_of_mass.1);
+        let mut seen = HashSet::new();
+        self.pixels.iter().enumerate()
+            .map(|(index, &(x, y, material))| {
+                let cell = center + rotation * (Vector2::new(x as f32, y as f32) - center_of_mass);
+                (index, (cell.x.round() as i64, cell.y.round() as i64, material))
+            })
+            // Turned bodies can round two pixels into one cell
+            .filter(|&(_, (x, y, _))| seen.insert((x, y)))
+            .collect()
+    }
 }
 
 pub struct RigidBodyManager {
@@ -62,6 +135,64 @@ pub struct RigidBodyManager {
     pub event_handler: (),
     pub rigid_bodies: HashMap<RigidBodyHandle, RigidBodyData>,
     pub gravity: Vector2<f32>,
+    /// Fixed colliders sampled from the grid around bodies for the current step
+    terrain_colliders: Vec<ColliderHandle>,
+    /// Fixed body at the origin that world pins attach to
+    world_anchor: Option<RigidBodyHandle>,
+    /// `RigidBodyImpact`s from the most recent step
+    impacts: Vec<SimulationEvent>,
+    /// Bodies behind `impacts`, with the impulse of each
+    impacting: Vec<(RigidBodyHandle, f32)>,
+}
+
+/// Drives a hinge at `speed` radians per second (positive turns clockwise on screen, where
+/// y points down), applying at most `max_torque`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HingeMotor {
+    pub speed: f32,
+    pub max_torque: f32,
+}
+
+/// A pin between a body and another body or the world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BodyJoint {
+    pub handle: ImpulseJointHandle,
+    pub body: RigidBodyHandle,
+    /// None when `body` is pinned to the world
+    pub other: Option<RigidBodyHandle>,
+    /// Where the pin sits now, in world cells
+    pub anchor: (f32, f32),
+    pub motor: Option<HingeMotor>,
+}
+
+/// A body as saved: its pixels in the coordinates it was made with, and where its centre
+/// of mass is and how it is moving, in cells and cells per second
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RigidBodySave {
+    pub pixels: Vec<(i32, i32, MaterialType)>,
+    pub position: (f32, f32),
+    pub angle: f32,
+    pub velocity: (f32, f32),
+    pub spin: f32,
+}
+
+/// A pin as saved, between bodies given by their index in `RigidBodiesSave::bodies`.
+/// Anchors are in cells in each body's own frame; `other_anchor` is in world cells when
+/// the pin holds `body` to the world.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JointSave {
+    pub body: usize,
+    pub other: Option<usize>,
+    pub anchor: (f32, f32),
+    pub other_anchor: (f32, f32),
+    pub motor: Option<HingeMotor>,
+}
+
+/// Every rigid body and the pins between them, so constructs survive a save and load
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RigidBodiesSave {
+    pub bodies: Vec<RigidBodySave>,
+    pub joints: Vec<JointSave>,
 }
 
 impl RigidBodyManager {
@@ -83,11 +214,384 @@ impl RigidBodyManager {
             physics_hooks: (),
             event_handler: (),
             rigid_bodies: HashMap::new(),
-            gravity: Vector2::new(0.0, 9.81 * 10.0), // Stronger gravity for falling sand
+            gravity: Vector2::new(0.0, DEFAULT_GRAVITY),
+            terrain_colliders: Vec::new(),
+            world_anchor: None,
+            impacts: Vec::new(),
+            impacting: Vec::new(),
+        }
+    }
+
+    /// Advance bodies one step coupled both ways with the grid: the particles around each
+    /// body become colliders it lands on, liquid it sits in pushes it up, and the bodies
+    /// are then written back into the grid, shoving loose particles out of their way
+    pub fn update(&mut self, chunk_manager: &mut ChunkManager) {
+        self.sync_grid_damage(chunk_manager);
+        self.sync_terrain_colliders(chunk_manager);
+        self.apply_buoyancy(chunk_manager);
+        self.step();
+        self.update_rigid_body_positions(chunk_manager);
+    }
+
+    /// Drop the pixels whose cells no longer hold the body's material, e.g. eaten by acid
+    /// or burnt away, leaving `split_damaged_bodies` to sort out what remains
+    pub fn sync_grid_damage(&mut self, chunk_manager: &ChunkManager) {
+        for body_data in self.rigid_bodies.values_mut() {
+            let lost: HashSet<usize> = body_data.stamped.iter().zip(&body_data.stamped_pixels)
+                .filter(|(&(x, y, material), _)| {
+                    chunk_manager.material_at(x, y) != Some(material)
+                })
+                .map(|(_, &pixel)| pixel)
+                .collect();
+            body_data.remove_pixels(&lost);
+        }
+    }
+
+    /// Rebuild every body that lost pixels from what is still connected of it. Pieces of
+    /// at least `min_pixels` become bodies of their own, moving as that part of the old
+    /// body was; smaller ones dissolve into the grid as ordinary particles. Returns how
+    /// many bodies the pieces became.
+    pub fn split_damaged_bodies(&mut self, min_pixels: usize) -> usize {
+        let damaged: Vec<RigidBodyHandle> = self.rigid_bodies.iter()
+            .filter(|(_, body_data)| !body_data.broken_pixels.is_empty())
+            .map(|(handle, _)| *handle)
+            .collect();
+        let mut created = 0;
+
+        for handle in damaged {
+            let (Some(body_data), Some(rigid_body)) = (self.rigid_bodies.get(&handle), self.physics_world.get(handle)) else {
+                continue;
+            };
+            let body_data = body_data.clone();
+            let (position, rotation) = (*rigid_body.translation(), *rigid_body.rotation());
+            let (linvel, angvel) = (*rigid_body.linvel(), rigid_body.angvel());
+            let pieces = RigidBodyAnalyzer::components_after_removal(&body_data.pixels, &body_data.broken_pixels);
+            let pins: Vec<BodyJoint> = self.joints().into_iter()
+                .filter(|joint| joint.body == handle || joint.other == Some(handle))
+                .collect();
+            self.remove_rigid_body(handle);
+
+            let old_center = Vector2::new(body_data.center_of_mass.0, body_data.center_of_mass.1);
+            for piece in pieces.into_iter().filter(|piece| piece.len() >= min_pixels) {
+                let mut piece_data = RigidBodyData::new(
+                    piece.iter().map(|&index| body_data.pixels[index]).collect(),
+                    body_data.chunk_origin,
+                );
+                // The piece already sits in the grid where the old body left it
+                let new_index: HashMap<usize, usize> = piece.iter().enumerate().map(|(new, &old)| (old, new)).collect();
+                for (&cell, pixel) in body_data.stamped.iter().zip(&body_data.stamped_pixels) {
+                    if let Some(&index) = new_index.get(pixel) {
+                        piece_data.stamped.push(cell);
+                        piece_data.stamped_pixels.push(index);
+                    }
+                }
+
+                let center = Vector2::new(piece_data.center_of_mass.0, piece_data.center_of_mass.1);
+                let offset = rotation * (center - old_center) * PHYSICS_SCALE;
+                let piece_position = Isometry::new(position + offset, rotation.angle());
+                let piece_velocity = linvel + Vector2::new(-angvel * offset.y, angvel * offset.x);
+                let cells: HashSet<(i64, i64)> = piece_data.stamped.iter().map(|&(x, y, _)| (x, y)).collect();
+                let piece_handle = self.insert_body(piece_data, piece_position, piece_velocity, angvel);
+                created += 1;
+
+                // Pins move to whichever piece still holds the pixel they went through
+                for pin in &pins {
+                    let cell = (pin.anchor.0.round() as i64, pin.anchor.1.round() as i64);
+                    if !cells.contains(&cell) {
+                        continue;
+                    }
+                    let other = if pin.body == handle { pin.other } else { Some(pin.body) };
+                    let joint = match other {
+                        Some(other) => self.pin_bodies(piece_handle, other, pin.anchor),
+                        None => self.pin_to_world(piece_handle, pin.anchor),
+                    };
+                    if let (Some(joint), Some(motor)) = (joint, pin.motor) {
+                        self.set_hinge_motor(joint, Some(motor));
+                    }
+                }
+            }
+        }
+        created
+    }
+
+    /// Pin two bodies together at `anchor`, in world cells, so they turn about it without
+    /// colliding with each other. None unless both are live, distinct bodies.
+    pub fn pin_bodies(&mut self, body: RigidBodyHandle, other: RigidBodyHandle, anchor: (f32, f32)) -> Option<ImpulseJointHandle> {
+        if body == other || !self.rigid_bodies.contains_key(&body) || !self.rigid_bodies.contains_key(&other) {
+            return None;
+        }
+        let point = Point2::new(anchor.0, anchor.1) * PHYSICS_SCALE;
+        let local = |handle: RigidBodyHandle| self.physics_world[handle].position().inverse_transform_point(&point);
+        Some(self.insert_pin(body, other, local(body), local(other), None))
+    }
+
+    /// Pin a body to a fixed point of the world at `anchor`, in world cells, like the
+    /// pivot of a see-saw or the hinge of a door. None unless `body` is live.
+    pub fn pin_to_world(&mut self, body: RigidBodyHandle, anchor: (f32, f32)) -> Option<ImpulseJointHandle> {
+        let rigid_body = self.physics_world.get(body).filter(|_| self.rigid_bodies.contains_key(&body))?;
+        let point = Point2::new(anchor.0, anchor.1) * PHYSICS_SCALE;
+        let local = rigid_body.position().inverse_transform_point(&point);
+        let world = self.world_anchor();
+        Some(self.insert_pin(body, world, local, point, None))
+    }
+
+    /// Turn a pin into a motorized hinge, or back into a free one with None. False if
+    /// there is no such pin.
+    pub fn set_hinge_motor(&mut self, joint: ImpulseJointHandle, motor: Option<HingeMotor>) -> bool {
+        let Some(joint) = self.impulse_joint_set.get_mut(joint) else {
+            return false;
+        };
+        match motor {
+            Some(motor) => joint.data
+                .set_motor_velocity(JointAxis::AngX, motor.speed, MOTOR_DAMPING)
+                .set_motor_max_force(JointAxis::AngX, motor.max_torque),
+            None => joint.data
+                .set_motor_velocity(JointAxis::AngX, 0.0, 0.0)
+                .set_motor_max_force(JointAxis::AngX, 0.0),
+        };
+        if let Some(body) = self.physics_world.get_mut(joint.body1) {
+            body.wake_up(true);
         }
+        true
+    }
+
+    pub fn remove_joint(&mut self, joint: ImpulseJointHandle) -> bool {
+        self.impulse_joint_set.remove(joint, true).is_some()
     }
 
+    /// Every pin, in no particular order
+    pub fn joints(&self) -> Vec<BodyJoint> {
+        self.impulse_joint_set.iter()
+            .filter_map(|(handle, joint)| {
+                let body = self.physics_world.get(joint.body1)?;
+                let anchor = body.position() * joint.data.local_anchor1() / PHYSICS_SCALE;
+                Some(BodyJoint {
+                    handle,
+                    body: joint.body1,
+                    other: Some(joint.body2).filter(|&other| Some(other) != self.world_anchor),
+                    anchor: (anchor.x, anchor.y),
+                    motor: hinge_motor(&joint.data),
+                })
+            })
+            .collect()
+    }
+
+    fn insert_pin(
+        &mut self,
+        body: RigidBodyHandle,
+        other: RigidBodyHandle,
+        anchor: Point2<f32>,
+        other_anchor: Point2<f32>,
+        motor: Option<HingeMotor>,
+    ) -> ImpulseJointHandle {
+        let joint = RevoluteJointBuilder::new()
+            .local_anchor1(anchor)
+            .local_anchor2(other_anchor)
+            .contacts_enabled(false);
+        let handle = self.impulse_joint_set.insert(body, other, joint, true);
+        if motor.is_some() {
+            self.set_hinge_motor(handle, motor);
+        }
+        handle
+    }
+
+    fn world_anchor(&mut self) -> RigidBodyHandle {
+        match self.world_anchor {
+            Some(handle) => handle,
+            None => {
+                let handle = self.physics_world.insert(RigidBodyBuilder::fixed().build());
+                self.world_anchor = Some(handle);
+                handle
+            }
+        }
+    }
+
+    /// Bodies, with where they are and how they move, and the pins between them
+    pub fn capture(&self) -> RigidBodiesSave {
+        let mut handles: Vec<RigidBodyHandle> = self.rigid_bodies.keys().copied().collect();
+        handles.sort_by_key(|handle| handle.into_raw_parts());
+        let index: HashMap<RigidBodyHandle, usize> = handles.iter().enumerate().map(|(i, &handle)| (handle, i)).collect();
+        let bodies = handles.iter()
+            .filter_map(|handle| {
+                let rigid_body = self.physics_world.get(*handle)?;
+                let position = rigid_body.translation() / PHYSICS_SCALE;
+                let velocity = rigid_body.linvel() / PHYSICS_SCALE;
+                Some(RigidBodySave {
+                    pixels: self.rigid_bodies[handle].pixels.clone(),
+                    position: (position.x, position.y),
+                    angle: rigid_body.rotation().angle(),
+                    velocity: (velocity.x, velocity.y),
+                    spin: rigid_body.angvel(),
+                })
+            })
+            .collect();
+        let joints = self.impulse_joint_set.iter()
+            .filter_map(|(_, joint)| {
+                let anchor = joint.data.local_anchor1() / PHYSICS_SCALE;
+                let other_anchor = joint.data.local_anchor2() / PHYSICS_SCALE;
+                let other = match index.get(&joint.body2) {
+                    Some(&other) => Some(other),
+                    None if Some(joint.body2) == self.world_anchor => None,
+                    None => return None,
+                };
+                Some(JointSave {
+                    body: *index.get(&joint.body1)?,
+                    other,
+                    anchor: (anchor.x, anchor.y),
+                    other_anchor: (other_anchor.x, other_anchor.y),
+                    motor: hinge_motor(&joint.data),
+                })
+            })
+            .collect();
+        RigidBodiesSave { bodies, joints }
+    }
+
+    /// Replace every body and pin with saved ones. Bodies take back the cells of
+    /// `chunk_manager` that still hold their pixels, as restored with the chunks.
+    pub fn restore(&mut self, save: &RigidBodiesSave, chunk_manager: &ChunkManager) -> Result<(), SaveLoadError> {
+        let body_count = save.bodies.len();
+        if let Some(joint) = save.joints.iter().find(|joint| joint.body >= body_count || joint.other.is_some_and(|other| other >= body_count)) {
+            return Err(SaveLoadError::CorruptedData(format!("joint between bodies {} and {:?} of {}", joint.body, joint.other, body_count)));
+        }
+        if save.bodies.iter().any(|body| body.pixels.is_empty()) {
+            return Err(SaveLoadError::CorruptedData("rigid body without pixels".to_string()));
+        }
+
+        self.clear();
+        let mut handles = Vec::with_capacity(body_count);
+        for body in &save.bodies {
+            let mut body_data = RigidBodyData::new(body.pixels.clone(), ChunkManager::world_to_chunk_pos(body.position.0 as i64, body.position.1 as i64));
+            let translation = Vector2::new(body.position.0, body.position.1) * PHYSICS_SCALE;
+            let rotation = UnitComplex::new(body.angle);
+            for (index, (x, y, material)) in body_data.cells_at(&translation, &rotation) {
+                if chunk_manager.material_at(x, y) == Some(material) {
+                    body_data.stamped.push((x, y, material));
+                    body_data.stamped_pixels.push(index);
+                }
+            }
+            let velocity = Vector2::new(body.velocity.0, body.velocity.1) * PHYSICS_SCALE;
+            handles.push(self.insert_body(body_data, Isometry::new(translation, body.angle), velocity, body.spin));
+        }
+        for joint in &save.joints {
+            let other = match joint.other {
+                Some(other) => handles[other],
+                None => self.world_anchor(),
+            };
+            let anchor = Point2::new(joint.anchor.0, joint.anchor.1) * PHYSICS_SCALE;
+            let other_anchor = Point2::new(joint.other_anchor.0, joint.other_anchor.1) * PHYSICS_SCALE;
+            self.insert_pin(handles[joint.body], other, anchor, other_anchor, joint.motor);
+        }
+        Ok(())
+    }
+
+    /// Replace the terrain colliders with fixed boxes for the particles around each body.
+    /// Liquids, gases and bodies' own cells are left out, and so is powder near bodies
+    /// moving faster than `PLOW_SPEED`, which plow through it instead.
+    pub fn sync_terrain_colliders(&mut self, chunk_manager: &ChunkManager) {
+        for handle in self.terrain_colliders.drain(..) {
+            self.collider_set.remove(handle, &mut self.island_manager, &mut self.physics_world, false);
+        }
+
+        let body_cells: HashSet<(i64, i64)> = self.occupied_cells().collect();
+        let mut solid = HashSet::new();
+        for (handle, body_data) in &self.rigid_bodies {
+            let Some(rigid_body) = self.physics_world.get(*handle) else {
+                continue;
+            };
+            let cells = body_data.cells_at(rigid_body.translation(), rigid_body.rotation());
+            let Some((min, max)) = bounds(cells.iter().map(|&(_, (x, y, _))| (x, y))) else {
+                continue;
+            };
+            let speed = rigid_body.linvel().magnitude() / PHYSICS_SCALE;
+            let margin = TERRAIN_MARGIN + (speed * self.integration_parameters.dt).ceil() as i64;
+            let plowing = speed > PLOW_SPEED;
+            let min = (min.0 - margin, min.1 - margin);
+            let max = (max.0 + margin, max.1 + margin);
+            for (position, particle) in chunk_manager.iter_region(min, max) {
+                let props = get_material_properties(particle.material_type);
+                let loose = props.is_liquid(particle.material_type) || props.is_gas(particle.material_type)
+                    || (plowing && props.is_powder(particle.material_type));
+                if !loose && !body_cells.contains(&position) {
+                    solid.insert(position);
+                }
+            }
+        }
+
+        // One box per horizontal run keeps the collider count down
+        let mut cells: Vec<(i64, i64)> = solid.into_iter().collect();
+        cells.sort_unstable_by_key(|&(x, y)| (y, x));
+        let mut runs: Vec<(i64, i64, i64)> = Vec::new();
+        for (x, y) in cells {
+            match runs.last_mut() {
+                Some((start, row, length)) if *row == y && *start + *length == x => *length += 1,
+                _ => runs.push((x, y, 1)),
+            }
+        }
+        for (start, y, length) in runs {
+            let center_x = start as f32 + (length - 1) as f32 * 0.5;
+            let collider = ColliderBuilder::cuboid(length as f32 * 0.5 * PHYSICS_SCALE, 0.5 * PHYSICS_SCALE)
+                .translation(Vector2::new(center_x * PHYSICS_SCALE, y as f32 * PHYSICS_SCALE))
+                .build();
+            self.terrain_colliders.push(self.collider_set.insert(collider));
+        }
+    }
+
+    /// Push bodies up with the weight of the liquid they displace and damp their motion
+    /// through it. A row of a body counts as submerged when liquid sits beside either end.
+    pub fn apply_buoyancy(&mut self, chunk_manager: &ChunkManager) {
+        let dt = self.integration_parameters.dt;
+        for (handle, body_data) in &self.rigid_bodies {
+            if body_data.stamped.is_empty() {
+                continue;
+            }
+            let Some(rigid_body) = self.physics_world.get_mut(*handle) else {
+                continue;
+            };
+
+            let mut rows: BTreeMap<i64, (i64, i64, usize)> = BTreeMap::new();
+            for &(x, y, _) in &body_data.stamped {
+                let row = rows.entry(y).or_insert((x, x, 0));
+                row.0 = row.0.min(x);
+                row.1 = row.1.max(x);
+                row.2 += 1;
+            }
+            let (mut submerged, mut liquid_density, mut centroid) = (0usize, 0.0, Vector2::zeros());
+            for (&y, &(min_x, max_x, count)) in &rows {
+                let liquid = [min_x - 1, max_x + 1].into_iter()
+                    .filter_map(|x| chunk_manager.get_particle(x, y))
+                    .map(|particle| particle.material_type)
+                    .find(|&material| get_material_properties(material).is_liquid(material));
+                if let Some(material) = liquid {
+                    submerged += count;
+                    liquid_density += get_material_properties(material).density * count as f32;
+                    centroid += Vector2::new((min_x + max_x) as f32 * 0.5, y as f32) * count as f32;
+                }
+            }
+            if submerged == 0 {
+                continue;
+            }
+
+            let fraction = submerged as f32 / body_data.stamped.len() as f32;
+            let liquid_density = liquid_density / submerged as f32;
+            let body_density = body_data.mass / body_data.pixels.len() as f32;
+            let mass = rigid_body.mass();
+            // Archimedes: the displaced liquid weighs (liquid / body density) times the submerged share
+            let lift = -self.gravity * mass * fraction * liquid_density / body_density;
+            let drag = -rigid_body.linvel() * mass * LIQUID_DRAG * fraction;
+            let point = centroid / submerged as f32 * PHYSICS_SCALE;
+            rigid_body.apply_impulse_at_point((lift + drag) * dt, Point2::from(point), true);
+            let angular_drag = -rigid_body.angvel() * LIQUID_DRAG * fraction * dt;
+            rigid_body.set_angvel(rigid_body.angvel() + angular_drag, true);
+        }
+    }
+
+    /// Advance the physics world one step, noting bodies that hit something in
+    /// `last_impacts`
     pub fn step(&mut self) {
+        let dt = self.integration_parameters.dt;
+        let before: Vec<(RigidBodyHandle, Vector2<f32>)> = self.rigid_bodies.keys()
+            .filter_map(|&handle| Some((handle, *self.physics_world.get(handle)?.linvel())))
+            .collect();
         self.physics_pipeline.step(
             &self.gravity,
             &self.integration_parameters,
@@ -103,6 +607,47 @@ impl RigidBodyManager {
             &self.physics_hooks,
             &self.event_handler,
         );
+
+        // Whatever gravity doesn't explain of a body's change in velocity came from a collision
+        self.impacts.clear();
+        self.impacting.clear();
+        for (handle, velocity) in before {
+            let Some(rigid_body) = self.physics_world.get(handle).filter(|body| !body.is_sleeping()) else {
+                continue;
+            };
+            let change = (rigid_body.linvel() - velocity - self.gravity * dt).magnitude() / PHYSICS_SCALE;
+            if change >= IMPACT_MIN_SPEED_CHANGE {
+                let position = rigid_body.translation() / PHYSICS_SCALE;
+                let impulse = rigid_body.mass() * change;
+                self.impacts.push(SimulationEvent::RigidBodyImpact {
+                    x: position.x.round() as i64,
+                    y: position.y.round() as i64,
+                    impulse,
+                });
+                self.impacting.push((handle, impulse));
+            }
+        }
+    }
+
+    /// Bodies that hit terrain or each other during the most recent step
+    pub fn last_impacts(&self) -> &[SimulationEvent] {
+        &self.impacts
+    }
+
+    /// For each body in `last_impacts`, the cells bordering it in the grid and the impulse
+    /// of its impact; call after `update` so the cells are those of the body's new pose
+    pub fn impact_contacts(&self) -> Vec<(Vec<(i64, i64)>, f32)> {
+        self.impacting.iter()
+            .filter_map(|(handle, impulse)| {
+                let body_data = self.rigid_bodies.get(handle)?;
+                let own: HashSet<(i64, i64)> = body_data.stamped.iter().map(|&(x, y, _)| (x, y)).collect();
+                let contacts: HashSet<(i64, i64)> = own.iter()
+                    .flat_map(|&(x, y)| [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)])
+                    .filter(|cell| !own.contains(cell))
+                    .collect();
+                Some((contacts.into_iter().collect(), *impulse))
+            })
+            .collect()
     }
 
     pub fn create_rigid_body_from_pixels(
@@ -114,14 +659,29 @@ impl RigidBodyManager {
             return None;
         }
 
-        let mut body_data = RigidBodyData::new(pixels, chunk_origin);
+        let body_data = RigidBodyData::new(pixels, chunk_origin);
         
         // Create rigid body at center of mass
+        let position = Isometry::translation(
+            body_data.center_of_mass.0 * PHYSICS_SCALE,
+            body_data.center_of_mass.1 * PHYSICS_SCALE,
+        );
+        Some(self.insert_body(body_data, position, Vector2::zeros(), 0.0))
+    }
+
+    /// Add a dynamic body for `body_data` at `position`, already moving
+    fn insert_body(
+        &mut self,
+        mut body_data: RigidBodyData,
+        position: Isometry<f32>,
+        linvel: Vector2<f32>,
+        angvel: f32,
+    ) -> RigidBodyHandle {
         let rigid_body = RigidBodyBuilder::dynamic()
-            .translation(Vector2::new(
-                body_data.center_of_mass.0 * PHYSICS_SCALE,
-                body_data.center_of_mass.1 * PHYSICS_SCALE,
-            ))
+            .position(position)
+            .linvel(linvel)
+            .angvel(angvel)
+            .ccd_enabled(true)
             .build();
         
         let handle = self.physics_world.insert(rigid_body);
@@ -133,7 +693,7 @@ impl RigidBodyManager {
         }
         
         self.rigid_bodies.insert(handle, body_data);
-        Some(handle)
+        handle
     }
 
     fn create_collider_from_pixels(
@@ -149,34 +709,34 @@ impl RigidBodyManager {
             relative_pixels.push((rel_x, rel_y));
         }
 
-        // Create convex hull from pixels
-        if let Some(convex_hull) = self.create_convex_hull(&relative_pixels) {
-            let collider = ColliderBuilder::convex_hull(&convex_hull)
-                .unwrap_or_else(|| {
-                    // Fallback to bounding box if convex hull fails
-                    let (min_x, max_x) = relative_pixels.iter()
-                        .map(|(x, _)| *x)
-                        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| (min.min(x), max.max(x)));
-                    let (min_y, max_y) = relative_pixels.iter()
-                        .map(|(_, y)| *y)
-                        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), y| (min.min(y), max.max(y)));
-                    
-                    ColliderBuilder::cuboid(
-                        (max_x - min_x) * 0.5 * PHYSICS_SCALE,
-                        (max_y - min_y) * 0.5 * PHYSICS_SCALE,
-                    )
-                })
-                .density(body_data.mass / body_data.pixels.len() as f32)
-                .build();
+        // Create convex hull from pixels. Hulls run through pixel centres, so round them out
+        // by half a cell to cover whole pixels. Rounding adds no mass, so lines and single
+        // pixels, whose hulls have no area, get their bounding box instead.
+        let collider = self.create_convex_hull(&relative_pixels)
+            .filter(|convex_hull| polygon_area(convex_hull) > 0.5 * PHYSICS_SCALE * PHYSICS_SCALE)
+            .and_then(|convex_hull| ColliderBuilder::round_convex_hull(&convex_hull, 0.5 * PHYSICS_SCALE))
+            .unwrap_or_else(|| {
+                let (min_x, max_x) = relative_pixels.iter()
+                    .map(|(x, _)| *x)
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), x| (min.min(x), max.max(x)));
+                let (min_y, max_y) = relative_pixels.iter()
+                    .map(|(_, y)| *y)
+                    .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), y| (min.min(y), max.max(y)));
+                
+                ColliderBuilder::cuboid(
+                    (max_x - min_x + 1.0) * 0.5 * PHYSICS_SCALE,
+                    (max_y - min_y + 1.0) * 0.5 * PHYSICS_SCALE,
+                )
+                .translation(Vector2::new(min_x + max_x, min_y + max_y) * 0.5 * PHYSICS_SCALE)
+            })
+            .density(body_data.mass / body_data.pixels.len() as f32)
+            .build();
 
-            Some(self.collider_set.insert_with_parent(
-                collider,
-                rigid_body_handle,
-                &mut self.physics_world,
-            ))
-        } else {
-            None
-        }
+        Some(self.collider_set.insert_with_parent(
+            collider,
+            rigid_body_handle,
+            &mut self.physics_world,
+        ))
     }
 
     fn create_convex_hull(&self, pixels: &[(f32, f32)]) -> Option<Vec<Point2<f32>>> {
@@ -230,71 +790,93 @@ impl RigidBodyManager {
         }
     }
 
+    /// Move each body's cells in the grid to where its physics body is now. Powder and
+    /// liquid in the way is shoved sideways, away from the body's middle; cells held by
+    /// other solids are left alone. Bodies still for `SETTLE_STEPS` steps are dropped and
+    /// left behind as ordinary particles.
     pub fn update_rigid_body_positions(&mut self, chunk_manager: &mut ChunkManager) {
         let mut bodies_to_remove = Vec::new();
-        
-        for (handle, body_data) in &self.rigid_bodies {
-            if let Some(rigid_body) = self.physics_world.get(*handle) {
-                let position = rigid_body.translation();
-                let rotation = rigid_body.rotation();
-                
-                // Check if body has moved significantly
-                let world_pos = (position.x / PHYSICS_SCALE, position.y / PHYSICS_SCALE);
-                let distance_moved = ((world_pos.0 - body_data.center_of_mass.0).powi(2) + 
-                                     (world_pos.1 - body_data.center_of_mass.1).powi(2)).sqrt();
-                
-                if distance_moved > 0.5 || rotation.angle().abs() > 0.1 {
-                    // Body has moved significantly, update particle positions
-                    self.update_particle_positions_from_rigid_body(chunk_manager, body_data, position, rotation);
-                    
-                    // If body is moving very slowly, consider making it static
-                    if rigid_body.linvel().magnitude() < 0.1 && rigid_body.angvel().abs() < 0.1 {
-                        bodies_to_remove.push(*handle);
-                    }
+
+        for (handle, body_data) in self.rigid_bodies.iter_mut() {
+            let Some(rigid_body) = self.physics_world.get(*handle) else {
+                continue;
+            };
+            let position = rigid_body.translation();
+            let cells = body_data.cells_at(position, rigid_body.rotation());
+            let moved = cells.len() != body_data.stamped.len()
+                || cells.iter().zip(&body_data.stamped).any(|((_, new), old)| new != old);
+            if moved {
+                unstamp(chunk_manager, &body_data.stamped);
+                let written = stamp(chunk_manager, &cells, &body_data.stamped, position.x / PHYSICS_SCALE);
+                (body_data.stamped_pixels, body_data.stamped) = written.into_iter().unzip();
+            }
+
+            // Pinned bodies stay bodies, so see-saws and doors keep working after coming to rest
+            let pinned = self.impulse_joint_set.attached_joints(*handle).next().is_some();
+            if !pinned && rigid_body.linvel().magnitude() < 0.1 && rigid_body.angvel().abs() < 0.1 {
+                body_data.rest_steps += 1;
+                if body_data.rest_steps >= SETTLE_STEPS {
+                    bodies_to_remove.push(*handle);
                 }
+            } else {
+                body_data.rest_steps = 0;
             }
         }
-        
-        // Remove slow/stopped bodies to improve performance
+
+        // Remove stopped bodies to improve performance; their cells stay in the grid
         for handle in bodies_to_remove {
             self.remove_rigid_body(handle);
         }
     }
 
-    fn update_particle_positions_from_rigid_body(
-        &self,
-        chunk_manager: &mut ChunkManager,
-        body_data: &RigidBodyData,
-        position: &Vector2<f32>,
-        rotation: &UnitComplex<f32>,
-    ) {
-        // Clear old particle positions
-        for (old_x, old_y, _) in &body_data.pixels {
-            chunk_manager.remove_particle(*old_x as i64, *old_y as i64);
+    /// Knock bodies away from a blast and chip off the pixels it is strong enough to
+    /// break, removing bodies with nothing left; `split_damaged_bodies` breaks up the
+    /// rest. Returns how many bodies were hit.
+    pub fn apply_explosion(&mut self, explosion: &Explosion) -> usize {
+        let center = Vector2::new(explosion.x as f32, explosion.y as f32);
+        let mut emptied = Vec::new();
+        let mut hit = 0;
+
+        for (handle, body_data) in self.rigid_bodies.iter_mut() {
+            let Some(rigid_body) = self.physics_world.get_mut(*handle) else {
+                continue;
+            };
+            let position = rigid_body.translation() / PHYSICS_SCALE;
+            let rotation = *rigid_body.rotation();
+            let offset = position - center;
+            let strength = explosion.strength(offset.magnitude(), 0.0);
+            if strength <= 0.0 {
+                continue;
+            }
+            hit += 1;
+
+            let center_of_mass = Vector2::new(body_data.center_of_mass.0, body_data.center_of_mass.1);
+            let broken: HashSet<usize> = body_data.pixels.iter().enumerate()
+                .filter(|(_, (x, y, material))| {
+                    let pixel = position + rotation * (Vector2::new(*x as f32, *y as f32) - center_of_mass);
+                    let pixel_strength = explosion.strength((pixel - center).magnitude(), 0.0);
+                    pixel_strength * BLAST_BREAK_POWER > get_material_properties(*material).hardness
+                })
+                .map(|(index, _)| index)
+                .collect();
+            body_data.remove_pixels(&broken);
+            if body_data.pixels.is_empty() {
+                emptied.push(*handle);
+                continue;
+            }
+
+            let direction = if offset.magnitude() > 0.0 { offset.normalize() } else { Vector2::new(0.0, -1.0) };
+            let impulse = direction * strength * BLAST_BODY_SPEED * PHYSICS_SCALE * rigid_body.mass();
+            rigid_body.apply_impulse(impulse, true);
         }
-        
-        // Set new particle positions
-        for (rel_x, rel_y, material) in &body_data.pixels {
-            let relative_pos = Vector2::new(
-                *rel_x as f32 - body_data.center_of_mass.0,
-                *rel_y as f32 - body_data.center_of_mass.1,
-            );
-            
-            let rotated_pos = rotation * relative_pos;
-            let new_world_pos = Vector2::new(
-                position.x / PHYSICS_SCALE + rotated_pos.x,
-                position.y / PHYSICS_SCALE + rotated_pos.y,
-            );
-            
-            chunk_manager.add_particle(
-                new_world_pos.x as i64,
-                new_world_pos.y as i64,
-                *material,
-                None,
-            );
+
+        for handle in emptied {
+            self.remove_rigid_body(handle);
         }
+        hit
     }
 
+    /// Drop a body from the physics world. Its cells stay in the grid as ordinary particles.
     pub fn remove_rigid_body(&mut self, handle: RigidBodyHandle) {
         if let Some(body_data) = self.rigid_bodies.remove(&handle) {
             // Remove collider
@@ -321,6 +903,9 @@ impl RigidBodyManager {
 
     pub fn clear(&mut self) {
         self.rigid_bodies.clear();
+        self.terrain_colliders.clear();
+        self.world_anchor = None;
+        self.impacts.clear();
         // Create new instances to effectively "clear" them
         self.physics_world = RigidBodySet::new();
         self.collider_set = ColliderSet::new();
@@ -331,6 +916,20 @@ impl RigidBodyManager {
         self.narrow_phase = NarrowPhase::new();
     }
 
+    /// World coordinates of every cell rigid bodies occupy in the grid, e.g. for
+    /// `ChunkActivityMap::mark_cells`
+    pub fn occupied_cells(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.rigid_bodies.values()
+            .flat_map(|body| body.stamped.iter().map(|&(x, y, _)| (x, y)))
+    }
+
+    /// World coordinates of the cells each rigid body occupies in the grid, one list per body
+    pub fn body_cells(&self) -> Vec<Vec<(i64, i64)>> {
+        self.rigid_bodies.values()
+            .map(|body| body.stamped.iter().map(|&(x, y, _)| (x, y)).collect())
+            .collect()
+    }
+
     pub fn rigid_body_count(&self) -> usize {
         self.rigid_bodies.len()
     }
@@ -342,6 +941,105 @@ impl Default for RigidBodyManager {
     }
 }
 
+/// Area enclosed by a polygon's vertices, in either winding
+fn polygon_area(vertices: &[Point2<f32>]) -> f32 {
+    let twice_area: f32 = vertices.iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum();
+    twice_area.abs() * 0.5
+}
+
+/// The motor driving a hinge, None if it turns freely
+fn hinge_motor(joint: &GenericJoint) -> Option<HingeMotor> {
+    joint.motor(JointAxis::AngX)
+        .filter(|motor| motor.max_force > 0.0)
+        .map(|motor| HingeMotor { speed: motor.target_vel, max_torque: motor.max_force })
+}
+
+/// Smallest and largest corner of `cells`, None if there are none
+fn bounds(cells: impl Iterator<Item = (i64, i64)>) -> Option<((i64, i64), (i64, i64))> {
+    cells.fold(None, |bounds, (x, y)| match bounds {
+        None => Some(((x, y), (x, y))),
+        Some((min, max)) => Some(((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))),
+    })
+}
+
+/// Take a body's cells back out of the grid, skipping any that have since changed material
+fn unstamp(chunk_manager: &mut ChunkManager, cells: &[(i64, i64, MaterialType)]) {
+    for &(x, y, material) in cells {
+        if chunk_manager.material_at(x, y) == Some(material) {
+            chunk_manager.remove_particle(x, y);
+        }
+    }
+}
+
+/// Write a body's cells, tagged with their pixel indices, into the grid, moving loose
+/// particles out of the way, into the cells the body just left (`previous`) when nothing
+/// is free nearby. Returns the cells written; those blocked by solids, or by particles
+/// with nowhere to go, are skipped.
+fn stamp(
+    chunk_manager: &mut ChunkManager,
+    cells: &[(usize, (i64, i64, MaterialType))],
+    previous: &[(i64, i64, MaterialType)],
+    center_x: f32,
+) -> Vec<(usize, (i64, i64, MaterialType))> {
+    let body: HashSet<(i64, i64)> = cells.iter().map(|&(_, (x, y, _))| (x, y)).collect();
+    let vacated: Vec<(i64, i64)> = previous.iter()
+        .map(|&(x, y, _)| (x, y))
+        .filter(|cell| !body.contains(cell))
+        .collect();
+    let mut stamped = Vec::with_capacity(cells.len());
+    for &(index, (x, y, material)) in cells {
+        if let Some(existing) = chunk_manager.material_at(x, y) {
+            let props = get_material_properties(existing);
+            if props.is_stationary(existing) || props.is_rigid_solid(existing) {
+                continue;
+            }
+            let away = if (x as f32) < center_x { -1 } else { 1 };
+            let target = free_cell_near(chunk_manager, &body, x, y, away).or_else(|| {
+                vacated.iter()
+                    .filter(|&&(vx, vy)| chunk_manager.get_particle(vx, vy).is_none())
+                    .min_by_key(|&&(vx, vy)| (vx - x).pow(2) + (vy - y).pow(2))
+                    .copied()
+            });
+            let Some((to_x, to_y)) = target else {
+                continue;
+            };
+            if let Some(mut particle) = chunk_manager.remove_particle(x, y) {
+                particle.x = to_x as usize;
+                particle.y = to_y as usize;
+                chunk_manager.set_particle(to_x, to_y, particle);
+            }
+        }
+        if chunk_manager.add_particle(x, y, material, None) {
+            stamped.push((index, (x, y, material)));
+        }
+    }
+    stamped
+}
+
+/// The nearest empty cell beside `(x, y)`, trying the `away` side first, then the other
+/// side, then straight up. The search never passes through the body or a solid.
+fn free_cell_near(chunk_manager: &ChunkManager, body: &HashSet<(i64, i64)>, x: i64, y: i64, away: i64) -> Option<(i64, i64)> {
+    for (dx, dy) in [(away, 0), (-away, 0), (0, -1)] {
+        for reach in 1..=DISPLACE_REACH {
+            let cell = (x + dx * reach, y + dy * reach);
+            match chunk_manager.get_particle(cell.0, cell.1) {
+                _ if body.contains(&cell) => break,
+                None => return Some(cell),
+                Some(particle) => {
+                    let props = get_material_properties(particle.material_type);
+                    if props.is_stationary(particle.material_type) || props.is_rigid_solid(particle.material_type) {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
 /// Analyzes chunks to find connected solid regions that should become rigid bodies
 pub struct RigidBodyAnalyzer;
 
@@ -382,6 +1080,69 @@ impl RigidBodyAnalyzer {
         candidates
     }
 
+    /// Connected groups of `pixels`, as indices into it, once the pixels that were at
+    /// `removed` have broken off a connected body. The search starts from the pixels
+    /// around the break and stops as soon as it has joined them all up, so chipping a
+    /// corner off a large body doesn't walk all of it.
+    pub fn components_after_removal(pixels: &[(i32, i32, MaterialType)], removed: &[(i32, i32)]) -> Vec<Vec<usize>> {
+        if pixels.is_empty() {
+            return Vec::new();
+        }
+        let index: HashMap<(i32, i32), usize> = pixels.iter().enumerate().map(|(i, &(x, y, _))| ((x, y), i)).collect();
+        let neighbours = |(x, y): (i32, i32)| {
+            let index = &index;
+            (-1..=1).flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+                .filter_map(move |cell| index.get(&cell).copied())
+        };
+
+        let mut seeds: HashSet<usize> = removed.iter().flat_map(|&cell| neighbours(cell)).collect();
+        let mut visited = vec![false; pixels.len()];
+        if let Some(&start) = seeds.iter().next() {
+            let mut queue = VecDeque::from([start]);
+            visited[start] = true;
+            while let Some(current) = queue.pop_front() {
+                seeds.remove(&current);
+                if seeds.is_empty() {
+                    break;
+                }
+                let (x, y, _) = pixels[current];
+                for next in neighbours((x, y)) {
+                    if !visited[next] {
+                        visited[next] = true;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        if seeds.is_empty() {
+            // Everything around the break is still joined, so the rest of the body is too
+            return vec![(0..pixels.len()).collect()];
+        }
+
+        let mut visited = vec![false; pixels.len()];
+        let mut components = Vec::new();
+        for start in 0..pixels.len() {
+            if visited[start] {
+                continue;
+            }
+            visited[start] = true;
+            let mut component = vec![start];
+            let mut next_to_visit = 0;
+            while let Some(&current) = component.get(next_to_visit) {
+                next_to_visit += 1;
+                let (x, y, _) = pixels[current];
+                for next in neighbours((x, y)) {
+                    if !visited[next] {
+                        visited[next] = true;
+                        component.push(next);
+                    }
+                }
+            }
+            components.push(component);
+        }
+        components
+    }
+
     fn is_solid_material(material: MaterialType) -> bool {
         matches!(
             material,
@@ -498,4 +1259,281 @@ mod tests {
         // Body should still exist
         assert_eq!(manager.rigid_body_count(), 1);
     }
-}
\ No newline at end of file
+
+    fn fill(chunk_manager: &mut ChunkManager, xs: std::ops::Range<i64>, ys: std::ops::Range<i64>, material: MaterialType) {
+        for x in xs {
+            for y in ys.clone() {
+                chunk_manager.add_particle(x, y, material, None);
+            }
+        }
+    }
+
+    fn block(xs: std::ops::Range<i32>, ys: std::ops::Range<i32>, material: MaterialType) -> Vec<(i32, i32, MaterialType)> {
+        xs.flat_map(|x| ys.clone().map(move |y| (x, y, material))).collect()
+    }
+
+    fn cells_of(chunk_manager: &ChunkManager, material: MaterialType) -> Vec<(i64, i64)> {
+        chunk_manager.iter_region((-100, -100), (100, 100))
+            .filter(|(_, particle)| particle.material_type == material)
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    #[test]
+    fn test_body_lands_on_the_grid() {
+        let mut chunk_manager = ChunkManager::new();
+        fill(&mut chunk_manager, 0..30, 40..41, MaterialType::Iron);
+        let mut manager = RigidBodyManager::new();
+        manager.create_rigid_body_from_pixels(block(10..16, 20..23, MaterialType::Stone), (0, 0));
+
+        for _ in 0..120 {
+            manager.update(&mut chunk_manager);
+        }
+
+        let stone = cells_of(&chunk_manager, MaterialType::Stone);
+        assert_eq!(stone.len(), 18);
+        assert_eq!(stone.iter().map(|&(_, y)| y).max(), Some(39));
+        assert_eq!(cells_of(&chunk_manager, MaterialType::Iron).len(), 30);
+    }
+
+    #[test]
+    fn test_landing_reports_an_impact() {
+        let mut chunk_manager = ChunkManager::new();
+        fill(&mut chunk_manager, 0..30, 40..41, MaterialType::Iron);
+        let mut manager = RigidBodyManager::new();
+        manager.create_rigid_body_from_pixels(block(10..16, 20..23, MaterialType::Stone), (0, 0));
+
+        let mut impacts = Vec::new();
+        for step in 0..120 {
+            manager.update(&mut chunk_manager);
+            impacts.extend(manager.last_impacts().iter().map(|impact| (step, *impact)));
+        }
+
+        // Nothing while falling or once it has come to rest on the iron
+        let &(step, first) = impacts.first().expect("no impact reported");
+        assert!(step > 5, "{:?}", impacts);
+        assert!(impacts.len() <= 2, "{:?}", impacts);
+        let SimulationEvent::RigidBodyImpact { x, y, impulse } = first else {
+            panic!("{:?}", first);
+        };
+        assert!((10..16).contains(&x) && (33..40).contains(&y), "{:?}", first);
+        assert!(impulse > 0.0);
+    }
+
+    #[test]
+    fn test_fast_body_plows_through_powder() {
+        let mut chunk_manager = ChunkManager::new();
+        fill(&mut chunk_manager, 0..40, 40..41, MaterialType::Iron);
+        fill(&mut chunk_manager, 5..35, 34..40, MaterialType::Sand);
+        let mut manager = RigidBodyManager::new();
+        manager.create_rigid_body_from_pixels(block(18..22, 0..4, MaterialType::Stone), (0, 0));
+
+        for _ in 0..120 {
+            manager.update(&mut chunk_manager);
+        }
+
+        assert_eq!(cells_of(&chunk_manager, MaterialType::Sand).len(), 180);
+        let stone = cells_of(&chunk_manager, MaterialType::Stone);
+        assert_eq!(stone.len(), 16);
+        assert!(stone.iter().any(|&(_, y)| y > 34), "{:?}", stone);
+    }
+
+    #[test]
+    fn test_liquid_floats_light_bodies() {
+        let mut chunk_manager = ChunkManager::new();
+        fill(&mut chunk_manager, 0..42, 40..41, MaterialType::Iron);
+        fill(&mut chunk_manager, 0..1, 5..40, MaterialType::Iron);
+        fill(&mut chunk_manager, 41..42, 5..40, MaterialType::Iron);
+        fill(&mut chunk_manager, 1..41, 20..40, MaterialType::Water);
+        let mut manager = RigidBodyManager::new();
+        manager.create_rigid_body_from_pixels(block(24..32, 8..11, MaterialType::Wood), (0, 0));
+        manager.create_rigid_body_from_pixels(block(6..10, 8..12, MaterialType::Stone), (0, 0));
+
+        for _ in 0..300 {
+            manager.update(&mut chunk_manager);
+        }
+
+        let wood = cells_of(&chunk_manager, MaterialType::Wood);
+        assert_eq!(wood.len(), 24);
+        let top = wood.iter().map(|&(_, y)| y).min().unwrap();
+        assert!((14..20).contains(&top), "wood top at {}", top);
+        let stone = cells_of(&chunk_manager, MaterialType::Stone);
+        assert_eq!(stone.iter().map(|&(_, y)| y).max(), Some(39));
+        assert_eq!(cells_of(&chunk_manager, MaterialType::Water).len(), 800);
+    }
+
+    #[test]
+    fn test_connectivity_after_removal() {
+        let bar = block(0..10, 0..1, MaterialType::Glass);
+        let chipped: Vec<_> = bar[1..].to_vec();
+        assert_eq!(RigidBodyAnalyzer::components_after_removal(&chipped, &[(0, 0)]).len(), 1);
+
+        let halves: Vec<_> = bar.iter().copied().filter(|&(x, _, _)| x != 4).collect();
+        let mut components = RigidBodyAnalyzer::components_after_removal(&halves, &[(4, 0)]);
+        components.sort_by_key(|component| component.len());
+        let lengths: Vec<usize> = components.iter().map(Vec::len).collect();
+        assert_eq!(lengths, vec![4, 5]);
+    }
+
+    #[test]
+    fn test_damaged_body_splits_and_dissolves() {
+        let mut chunk_manager = ChunkManager::new();
+        fill(&mut chunk_manager, 0..40, 40..41, MaterialType::Iron);
+        let mut manager = RigidBodyManager::new();
+        // Two 5x3 slabs resting on the floor, joined by a single bridge pixel, with a 2-pixel stub off the right one
+        let mut pixels = block(5..10, 37..40, MaterialType::Wood);
+        pixels.push((10, 38, MaterialType::Wood));
+        pixels.extend(block(11..16, 37..40, MaterialType::Wood));
+        pixels.extend([(16, 38, MaterialType::Wood), (17, 38, MaterialType::Wood)]);
+        manager.create_rigid_body_from_pixels(pixels, (0, 0));
+        manager.update(&mut chunk_manager);
+        assert_eq!(manager.split_damaged_bodies(8), 0);
+
+        // Acid eats the bridge and the stub's root
+        chunk_manager.add_particle(10, 38, MaterialType::Acid, None);
+        chunk_manager.remove_particle(16, 38);
+        manager.update(&mut chunk_manager);
+        assert_eq!(manager.split_damaged_bodies(8), 2);
+
+        assert_eq!(manager.rigid_body_count(), 2);
+        let mut sizes: Vec<usize> = manager.rigid_bodies.values().map(|body| body.pixels.len()).collect();
+        sizes.sort();
+        assert_eq!(sizes, vec![15, 15]);
+        // The pieces carry on from where they were, and the stub is left in the grid
+        let stub = chunk_manager.material_at(17, 38);
+        assert_eq!(stub, Some(MaterialType::Wood));
+        assert!(!manager.occupied_cells().any(|cell| cell == (17, 38)));
+        for _ in 0..120 {
+            manager.update(&mut chunk_manager);
+        }
+        assert_eq!(cells_of(&chunk_manager, MaterialType::Wood).len(), 31);
+    }
+
+    #[test]
+    fn test_door_swings_down_from_its_hinge() {
+        let mut chunk_manager = ChunkManager::new();
+        let mut manager = RigidBodyManager::new();
+        let door = manager.create_rigid_body_from_pixels(block(10..30, 10..12, MaterialType::Wood), (0, 0)).unwrap();
+        let hinge = manager.pin_to_world(door, (10.0, 10.5)).unwrap();
+        let extra = manager.pin_to_world(door, (0.0, 0.0)).unwrap();
+        assert!(manager.remove_joint(extra));
+
+        let mut lowest = f32::MIN;
+        for _ in 0..300 {
+            manager.update(&mut chunk_manager);
+            // It swings about the hinge instead of falling away or settling into the grid
+            let center = manager.physics_world[door].translation() / PHYSICS_SCALE;
+            let reach = (center - Vector2::new(10.0, 10.5)).magnitude();
+            assert!((reach - 9.5).abs() < 0.5, "door centre {} cells from the hinge", reach);
+            lowest = lowest.max(center.y);
+        }
+        assert!(lowest > 19.0);
+        assert_eq!(manager.rigid_body_count(), 1);
+        let joints = manager.joints();
+        assert_eq!(joints.len(), 1);
+        assert_eq!((joints[0].handle, joints[0].body, joints[0].other), (hinge, door, None));
+        assert!((joints[0].anchor.0 - 10.0).abs() < 0.5 && (joints[0].anchor.1 - 10.5).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_motorized_hinge() {
+        let mut chunk_manager = ChunkManager::new();
+        let mut manager = RigidBodyManager::new();
+        let wheel = manager.create_rigid_body_from_pixels(block(0..7, 0..7, MaterialType::Stone), (0, 0)).unwrap();
+        let axle = manager.pin_to_world(wheel, (3.0, 3.0)).unwrap();
+        let motor = HingeMotor { speed: 2.0, max_torque: 1.0e4 };
+        assert!(manager.set_hinge_motor(axle, Some(motor)));
+
+        for _ in 0..120 {
+            manager.update(&mut chunk_manager);
+        }
+        let spin = manager.physics_world[wheel].angvel();
+        assert!((spin - 2.0).abs() < 0.1, "wheel turning at {}", spin);
+        assert_eq!(manager.joints()[0].motor, Some(motor));
+
+        assert!(manager.set_hinge_motor(axle, None));
+        assert_eq!(manager.joints()[0].motor, None);
+    }
+
+    #[test]
+    fn test_pinned_bodies_hang_together() {
+        let mut chunk_manager = ChunkManager::new();
+        let mut manager = RigidBodyManager::new();
+        let upper = manager.create_rigid_body_from_pixels(block(0..10, 0..2, MaterialType::Wood), (0, 0)).unwrap();
+        let lower = manager.create_rigid_body_from_pixels(block(10..20, 0..2, MaterialType::Stone), (0, 0)).unwrap();
+        manager.pin_to_world(upper, (0.0, 0.5)).unwrap();
+        let link = manager.pin_bodies(upper, lower, (9.5, 0.5)).unwrap();
+        assert!(manager.pin_bodies(lower, lower, (0.0, 0.0)).is_none());
+
+        for _ in 0..240 {
+            manager.update(&mut chunk_manager);
+        }
+
+        assert_eq!(manager.rigid_body_count(), 2);
+        let joint = manager.impulse_joint_set.get(link).unwrap();
+        let upper_end = manager.physics_world[upper].position() * joint.data.local_anchor1();
+        let lower_end = manager.physics_world[lower].position() * joint.data.local_anchor2();
+        assert!((upper_end - lower_end).magnitude() / PHYSICS_SCALE < 0.5);
+        assert!(lower_end.y / PHYSICS_SCALE > 5.0, "the chain hangs down");
+    }
+
+    #[test]
+    fn test_constructs_survive_save_and_load() {
+        let mut chunk_manager = ChunkManager::new();
+        fill(&mut chunk_manager, 0..40, 40..41, MaterialType::Iron);
+        let mut manager = RigidBodyManager::new();
+        let plank = manager.create_rigid_body_from_pixels(block(5..35, 20..21, MaterialType::Wood), (0, 0)).unwrap();
+        manager.pin_to_world(plank, (20.0, 20.0)).unwrap();
+        let weight = manager.create_rigid_body_from_pixels(block(6..10, 16..20, MaterialType::Stone), (0, 0)).unwrap();
+        let wheel = manager.create_rigid_body_from_pixels(block(30..35, 2..7, MaterialType::Stone), (0, 0)).unwrap();
+        let axle = manager.pin_to_world(wheel, (32.0, 4.0)).unwrap();
+        manager.set_hinge_motor(axle, Some(HingeMotor { speed: -1.0, max_torque: 1.0e4 }));
+        manager.pin_bodies(weight, plank, (8.0, 19.5));
+        for _ in 0..30 {
+            manager.update(&mut chunk_manager);
+        }
+
+        let save = manager.capture();
+        assert_eq!((save.bodies.len(), save.joints.len()), (3, 3));
+        let bytes = bincode::serialize(&save).unwrap();
+        let loaded: RigidBodiesSave = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(loaded, save);
+
+        let mut restored = RigidBodyManager::new();
+        restored.restore(&loaded, &chunk_manager).unwrap();
+        let recaptured = restored.capture();
+        assert_eq!(recaptured.joints, save.joints);
+        for (body, saved) in recaptured.bodies.iter().zip(&save.bodies) {
+            assert_eq!(body.pixels, saved.pixels);
+            let close = |a: f32, b: f32| (a - b).abs() < 1.0e-3;
+            assert!(close(body.position.0, saved.position.0) && close(body.position.1, saved.position.1));
+            assert!(close(body.angle, saved.angle) && close(body.spin, saved.spin));
+        }
+        let stamped: usize = restored.rigid_bodies.values().map(|body| body.stamped.len()).sum();
+        assert_eq!(stamped, manager.occupied_cells().count());
+
+        // Both carry on the same way
+        let mut copy = ChunkManager::new();
+        for (position, particle) in chunk_manager.iter_region((-100, -100), (100, 100)) {
+            copy.set_particle(position.0, position.1, particle);
+        }
+        for _ in 0..30 {
+            manager.update(&mut chunk_manager);
+            restored.update(&mut copy);
+        }
+        let angles = |manager: &RigidBodyManager| {
+            let mut angles: Vec<f32> = manager.physics_world.iter()
+                .filter(|(handle, _)| manager.rigid_bodies.contains_key(handle))
+                .map(|(_, body)| body.rotation().angle())
+                .collect();
+            angles.sort_by(f32::total_cmp);
+            angles
+        };
+        for (a, b) in angles(&manager).iter().zip(angles(&restored)) {
+            assert!((a - b).abs() < 0.05, "{} vs {}", a, b);
+        }
+
+        let broken = RigidBodiesSave { joints: vec![JointSave { body: 9, other: None, anchor: (0.0, 0.0), other_anchor: (0.0, 0.0), motor: None }], ..save };
+        assert!(matches!(restored.restore(&broken, &chunk_manager), Err(SaveLoadError::CorruptedData(_))));
+    }
+}
@@ -1,16 +1,46 @@
-use crate::chunk::{ChunkManager, ChunkKey};
+use crate::bookmarks::CameraBookmarks;
+#[cfg(feature = "files")]
+use crate::chunk::{Chunk, ChunkManager};
+use crate::chunk::ChunkKey;
+#[cfg(feature = "files")]
+use crate::chunk_saver::chunked_simulation_path;
+#[cfg(feature = "files")]
 use crate::ecs::ECS;
-use crate::materials::MaterialType;
+#[cfg(feature = "files")]
+use crate::engine_v2::AdvancedPhysicsEngine;
+use crate::materials::{material_id, MaterialType};
+use crate::material_registry::{MaterialIdTable, MaterialRegistry};
 use crate::particle::Particle;
+#[cfg(feature = "files")]
+use crate::rigidbody::RigidBodyManager;
+use crate::rigidbody::RigidBodiesSave;
+use crate::generators::GeneratorConfig;
+use crate::simulation::{RngState, Simulation, UpdateState};
+#[cfg(feature = "files")]
+use crate::structures::StructureLibrary;
+#[cfg(feature = "files")]
+use crate::clipboard::Blueprint;
 use crate::tile_entity::{TileEntity, TileEntityManager};
-use crate::world_generation::{BiomeType, WorldGenerator};
+use crate::world_generation::BiomeType;
+#[cfg(feature = "files")]
+use crate::world_generation::WorldGenerator;
+use crate::day_cycle::WorldClock;
+use crate::weather::WeatherState;
+use crate::world_rules::WorldRules;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+#[cfg(feature = "files")]
 use std::fs::{self, File};
+#[cfg(feature = "files")]
 use std::io::{BufReader, BufWriter, Read, Write};
+#[cfg(feature = "files")]
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "files")]
 use flate2::read::GzDecoder;
+#[cfg(feature = "files")]
 use flate2::write::GzEncoder;
+#[cfg(feature = "files")]
 use flate2::Compression;
 
 /// World save/load system
@@ -21,6 +51,33 @@ pub struct WorldSave {
     pub entities: ECSSnapshot,
     pub tile_entities: Vec<TileEntity>,
     pub world_generator_seed: u64,
+    /// Empty for worlds saved without `save_rigid_bodies`
+    #[serde(default)]
+    pub rigid_bodies: RigidBodiesSave,
+    /// None for worlds saved without a clock
+    #[serde(default)]
+    pub clock: Option<WorldClock>,
+    /// What each material id in `chunks` named when saved; empty for worlds saved before
+    /// the table was kept, whose ids are read as they are
+    #[serde(default)]
+    pub material_ids: MaterialIdTable,
+}
+
+/// A grid `Simulation` saved with its tick, random number and update state, so a loaded copy
+/// carries on exactly where the original left off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationSave {
+    pub width: usize,
+    pub height: usize,
+    pub tick: u64,
+    /// Occupied cells, with their temperatures, lifetimes and velocities
+    pub particles: Vec<Particle>,
+    pub rng: RngState,
+    pub update: UpdateState,
+    pub tile_entities: Vec<TileEntity>,
+    /// What each Generator cell emits
+    #[serde(default)]
+    pub generators: Vec<((usize, usize), GeneratorConfig)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +93,13 @@ pub struct WorldMetadata {
     pub difficulty: Difficulty,
     pub game_mode: GameMode,
     pub seed: u64,
+    // Physics overrides that travel with the save; older saves load with the defaults
+    #[serde(default)]
+    pub rules: WorldRules,
+    #[serde(default)]
+    pub camera_bookmarks: CameraBookmarks,
+    #[serde(default)]
+    pub weather: WeatherState,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,6 +115,8 @@ pub struct ChunkSave {
 pub struct ParticleSave {
     pub local_x: usize,
     pub local_y: usize,
+    /// Stored as the material's stable id, resolved through `WorldSave::material_ids`
+    #[serde(with = "material_id")]
     pub material_type: MaterialType,
     pub temp: f32,
     pub life: Option<f32>,
@@ -99,11 +165,13 @@ pub enum GameMode {
 }
 
 /// Save/Load manager
+#[cfg(feature = "files")]
 pub struct SaveLoadManager {
-    save_directory: PathBuf,
-    compression_level: Compression,
+    pub(crate) save_directory: PathBuf,
+    pub(crate) compression_level: Compression,
 }
 
+#[cfg(feature = "files")]
 impl SaveLoadManager {
     pub fn new(save_directory: impl AsRef<Path>) -> std::io::Result<Self> {
         let save_dir = save_directory.as_ref().to_path_buf();
@@ -115,15 +183,19 @@ impl SaveLoadManager {
         })
     }
 
-    /// Save a complete world
+    /// Save a complete world, with the names of the materials its chunks use by id from
+    /// `materials`
+    #[allow(clippy::too_many_arguments)]
     pub fn save_world(
         &self,
         world_name: &str,
         chunk_manager: &ChunkManager,
+        materials: &MaterialRegistry,
         ecs: &ECS,
         tile_entity_manager: &TileEntityManager,
         world_generator: &WorldGenerator,
         metadata: WorldMetadata,
+        clock: Option<&WorldClock>,
     ) -> Result<(), SaveLoadError> {
         let world_dir = self.save_directory.join(world_name);
         fs::create_dir_all(&world_dir)?;
@@ -131,8 +203,9 @@ impl SaveLoadManager {
         // Save metadata
         self.save_metadata(&world_dir, &metadata)?;
 
-        // Save chunks
+        // Save chunks and what their material ids stand for
         self.save_chunks(&world_dir, chunk_manager)?;
+        self.save_material_ids(&world_dir, &materials.id_table())?;
 
         // Save ECS data
         self.save_ecs(&world_dir, ecs)?;
@@ -143,6 +216,9 @@ impl SaveLoadManager {
         // Save world generator data
         self.save_world_generator_data(&world_dir, world_generator)?;
 
+        // Save the time of day, if the world keeps one
+        self.save_clock(&world_dir, clock)?;
+
         Ok(())
     }
 
@@ -172,18 +248,31 @@ impl SaveLoadManager {
         // Load world generator seed
         let world_generator_seed = self.load_world_generator_data(&world_dir)?;
 
+        // Load rigid bodies and their joints
+        let rigid_bodies = self.load_rigid_bodies(&world_dir)?;
+
+        // Load the time of day
+        let clock = self.load_clock(&world_dir)?;
+
+        let material_ids = self.load_material_ids(&world_dir)?;
+
         Ok(WorldSave {
             metadata,
             chunks,
             entities,
             tile_entities,
             world_generator_seed,
+            rigid_bodies,
+            clock,
+            material_ids,
         })
     }
 
-    /// Apply loaded world data to game systems
+    /// Apply loaded world data to game systems. Saved material ids are resolved against
+    /// `materials`; particles of custom materials it lacks are dropped.
     pub fn apply_world_save(
         world_save: &WorldSave,
+        materials: &MaterialRegistry,
         chunk_manager: &mut ChunkManager,
         ecs: &mut ECS,
         tile_entity_manager: &mut TileEntityManager,
@@ -194,8 +283,9 @@ impl SaveLoadManager {
         tile_entity_manager.clear();
 
         // Apply chunks
+        let resolve = |material_type: MaterialType| materials.resolve_id(&world_save.material_ids, material_type.id());
         for chunk_save in &world_save.chunks {
-            Self::apply_chunk_save(chunk_save, chunk_manager)?;
+            Self::apply_particles(chunk_save, chunk_manager, &world_save.metadata.rules, resolve);
         }
 
         // Apply ECS data
@@ -209,6 +299,68 @@ impl SaveLoadManager {
         Ok(())
     }
 
+    /// Apply loaded world data to an engine, including the save's physics rules and its
+    /// rigid bodies and joints and its clock
+    pub fn apply_world_save_to_engine(
+        world_save: &WorldSave,
+        engine: &mut AdvancedPhysicsEngine,
+        ecs: &mut ECS,
+        tile_entity_manager: &mut TileEntityManager,
+    ) -> Result<(), SaveLoadError> {
+        let rules = &world_save.metadata.rules;
+        rules.apply_to_engine(engine)
+            .map_err(|e| SaveLoadError::CorruptedData(format!("world rules: {}", e)))?;
+
+        let materials = Arc::clone(engine.physics_state.materials());
+        Self::apply_world_save(world_save, &materials, &mut engine.chunk_manager, ecs, tile_entity_manager)?;
+        match &world_save.clock {
+            Some(clock) => engine.enable_day_cycle(clock.clone()),
+            None => engine.disable_day_cycle(),
+        }
+        engine.rigidbody_manager.restore(&world_save.rigid_bodies, &engine.chunk_manager)
+    }
+
+    /// Save an engine's rigid bodies and the joints between them alongside a world written
+    /// by `save_world`
+    pub fn save_rigid_bodies(&self, world_name: &str, rigidbody_manager: &RigidBodyManager) -> Result<(), SaveLoadError> {
+        let world_dir = self.save_directory.join(world_name);
+        fs::create_dir_all(&world_dir)?;
+        let encoder = GzEncoder::new(File::create(world_dir.join("rigid_bodies.dat"))?, self.compression_level);
+        let mut writer = BufWriter::new(encoder);
+        bincode::serialize_into(&mut writer, &rigidbody_manager.capture())?;
+        writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+        Ok(())
+    }
+
+    fn load_rigid_bodies(&self, world_dir: &Path) -> Result<RigidBodiesSave, SaveLoadError> {
+        let path = world_dir.join("rigid_bodies.dat");
+        if !path.exists() {
+            return Ok(RigidBodiesSave::default());
+        }
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    /// Write the time of day, or remove one an earlier save left behind when the world
+    /// no longer keeps it
+    fn save_clock(&self, world_dir: &Path, clock: Option<&WorldClock>) -> Result<(), SaveLoadError> {
+        let path = world_dir.join("clock.json");
+        match clock {
+            Some(clock) => serde_json::to_writer(BufWriter::new(File::create(path)?), clock)?,
+            None if path.exists() => fs::remove_file(path)?,
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn load_clock(&self, world_dir: &Path) -> Result<Option<WorldClock>, SaveLoadError> {
+        let path = world_dir.join("clock.json");
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_reader(BufReader::new(File::open(path)?))?))
+    }
+
     fn save_metadata(&self, world_dir: &Path, metadata: &WorldMetadata) -> Result<(), SaveLoadError> {
         let metadata_path = world_dir.join("metadata.json");
         let file = File::create(metadata_path)?;
@@ -225,25 +377,65 @@ impl SaveLoadManager {
         Ok(metadata)
     }
 
+    fn save_material_ids(&self, world_dir: &Path, material_ids: &MaterialIdTable) -> Result<(), SaveLoadError> {
+        let writer = BufWriter::new(File::create(world_dir.join("materials.json"))?);
+        serde_json::to_writer_pretty(writer, material_ids)?;
+        Ok(())
+    }
+
+    fn load_material_ids(&self, world_dir: &Path) -> Result<MaterialIdTable, SaveLoadError> {
+        let path = world_dir.join("materials.json");
+        if !path.exists() {
+            return Ok(MaterialIdTable::default());
+        }
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
     fn save_chunks(&self, world_dir: &Path, chunk_manager: &ChunkManager) -> Result<(), SaveLoadError> {
         let chunks_dir = world_dir.join("chunks");
         fs::create_dir_all(&chunks_dir)?;
 
         for (chunk_key, chunk) in chunk_manager.chunks_iter() {
-            let chunk_save = ChunkSave::from_chunk(*chunk_key, chunk);
-            let chunk_filename = format!("chunk_{}_{}.dat", chunk_key.0, chunk_key.1);
-            let chunk_path = chunks_dir.join(chunk_filename);
-            
-            // Save with compression
-            let file = File::create(chunk_path)?;
-            let encoder = GzEncoder::new(file, self.compression_level);
-            let writer = BufWriter::new(encoder);
-            bincode::serialize_into(writer, &chunk_save)?;
+            self.write_chunk(&chunks_dir, *chunk_key, chunk)?;
         }
 
         Ok(())
     }
 
+    fn chunk_path(chunks_dir: &Path, chunk_key: ChunkKey) -> PathBuf {
+        chunks_dir.join(format!("chunk_{}_{}.dat", chunk_key.0, chunk_key.1))
+    }
+
+    fn write_chunk(&self, chunks_dir: &Path, chunk_key: ChunkKey, chunk: &Chunk) -> Result<(), SaveLoadError> {
+        let chunk_save = ChunkSave::from_chunk(chunk_key, chunk);
+
+        // Save with compression
+        let file = File::create(Self::chunk_path(chunks_dir, chunk_key))?;
+        let encoder = GzEncoder::new(file, self.compression_level);
+        let mut writer = BufWriter::new(encoder);
+        writer.write_all(&CHUNK_FILE_MAGIC)?;
+        bincode::serialize_into(writer, &chunk_save)?;
+        Ok(())
+    }
+
+    /// Save one chunk of a world, e.g. when streaming it out of memory
+    pub fn save_chunk(&self, world_name: &str, chunk_key: ChunkKey, chunk: &Chunk) -> Result<(), SaveLoadError> {
+        let chunks_dir = self.save_directory.join(world_name).join("chunks");
+        fs::create_dir_all(&chunks_dir)?;
+        self.write_chunk(&chunks_dir, chunk_key, chunk)
+    }
+
+    /// Load one chunk of a world; None if it was never saved
+    pub fn load_chunk(&self, world_name: &str, chunk_key: ChunkKey) -> Result<Option<ChunkSave>, SaveLoadError> {
+        let chunk_path = Self::chunk_path(&self.save_directory.join(world_name).join("chunks"), chunk_key);
+        if !chunk_path.exists() {
+            return Ok(None);
+        }
+
+        let file = File::open(chunk_path)?;
+        Ok(Some(read_chunk_save(BufReader::new(GzDecoder::new(file)))?))
+    }
+
     fn load_chunks(&self, world_dir: &Path) -> Result<Vec<ChunkSave>, SaveLoadError> {
         let chunks_dir = world_dir.join("chunks");
         let mut chunks = Vec::new();
@@ -256,9 +448,7 @@ impl SaveLoadManager {
                 if path.extension().and_then(|s| s.to_str()) == Some("dat") {
                     let file = File::open(path)?;
                     let decoder = GzDecoder::new(file);
-                    let reader = BufReader::new(decoder);
-                    let chunk_save: ChunkSave = bincode::deserialize_from(reader)?;
-                    chunks.push(chunk_save);
+                    chunks.push(read_chunk_save(BufReader::new(decoder))?);
                 }
             }
         }
@@ -347,19 +537,33 @@ impl SaveLoadManager {
         }
     }
 
-    fn apply_chunk_save(chunk_save: &ChunkSave, chunk_manager: &mut ChunkManager) -> Result<(), SaveLoadError> {
+    /// Replace a chunk's particles with a saved copy, taking its material ids as they are
+    pub fn apply_chunk_save(chunk_save: &ChunkSave, chunk_manager: &mut ChunkManager, rules: &WorldRules) -> Result<(), SaveLoadError> {
+        Self::apply_particles(chunk_save, chunk_manager, rules, Some);
+        Ok(())
+    }
+
+    fn apply_particles(
+        chunk_save: &ChunkSave,
+        chunk_manager: &mut ChunkManager,
+        rules: &WorldRules,
+        resolve: impl Fn(MaterialType) -> Option<MaterialType>,
+    ) {
         // Get chunk key
         let chunk_key = chunk_save.chunk_key;
         
         // Clear existing particles by key
         chunk_manager.clear_chunk(chunk_key);
         
-        // Apply saved particles
+        // Apply saved particles, dropping any the world's rules have disabled
         for particle_save in &chunk_save.particles {
+            let Some(material_type) = resolve(particle_save.material_type).filter(|&material| rules.allows(material)) else {
+                continue;
+            };
             let particle = Particle::new(
                 particle_save.local_x,
                 particle_save.local_y,
-                particle_save.material_type,
+                material_type,
                 Some(particle_save.temp),
             );
             
@@ -369,8 +573,6 @@ impl SaveLoadManager {
             
             chunk_manager.set_particle(world_x, world_y, particle);
         }
-
-        Ok(())
     }
 
     fn apply_ecs_snapshot(snapshot: &ECSSnapshot, ecs: &mut ECS) -> Result<(), SaveLoadError> {
@@ -444,6 +646,73 @@ impl SaveLoadManager {
         }
     }
 
+    fn simulation_path(&self, name: &str) -> PathBuf {
+        self.save_directory.join(format!("{}.sim", name))
+    }
+
+    /// Save a grid simulation as `<name>.sim` in the save directory
+    pub fn save_simulation(&self, name: &str, save: &SimulationSave) -> Result<(), SaveLoadError> {
+        save.write_to_file(self.simulation_path(name), self.compression_level)
+    }
+
+    /// Load `<name>.sim`, or the `<name>.chunks` a `ChunkSaver` wrote if that is newer
+    pub fn load_simulation(&self, name: &str) -> Result<SimulationSave, SaveLoadError> {
+        let path = self.simulation_path(name);
+        let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        let chunked = chunked_simulation_path(&self.save_directory, name);
+        if modified(&chunked) > modified(&path) {
+            return self.load_chunked_simulation(name);
+        }
+        if !path.exists() {
+            return Err(SaveLoadError::WorldNotFound(name.to_string()));
+        }
+        SimulationSave::read_from_file(path)
+    }
+
+    fn structures_path(&self, name: &str) -> PathBuf {
+        self.save_directory.join(format!("{}.structures", name))
+    }
+
+    /// Save a library of user structures as `<name>.structures`, gzip-compressed bincode
+    /// like chunks
+    pub fn save_structures(&self, name: &str, library: &StructureLibrary) -> Result<(), SaveLoadError> {
+        let encoder = GzEncoder::new(File::create(self.structures_path(name))?, self.compression_level);
+        let mut writer = BufWriter::new(encoder);
+        bincode::serialize_into(&mut writer, library)?;
+        writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+        Ok(())
+    }
+
+    /// Load a library of user structures; an empty one if it was never saved
+    pub fn load_structures(&self, name: &str) -> Result<StructureLibrary, SaveLoadError> {
+        let path = self.structures_path(name);
+        if !path.exists() {
+            return Ok(StructureLibrary::default());
+        }
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    fn blueprint_path(&self, name: &str) -> PathBuf {
+        self.save_directory.join(format!("{}.blueprint", name))
+    }
+
+    /// Save a blueprint as `<name>.blueprint`, plain JSON so it can be shared and
+    /// pasted into any world
+    pub fn save_blueprint(&self, name: &str, blueprint: &Blueprint) -> Result<(), SaveLoadError> {
+        let writer = BufWriter::new(File::create(self.blueprint_path(name))?);
+        serde_json::to_writer(writer, blueprint)?;
+        Ok(())
+    }
+
+    pub fn load_blueprint(&self, name: &str) -> Result<Blueprint, SaveLoadError> {
+        let path = self.blueprint_path(name);
+        if !path.exists() {
+            return Err(SaveLoadError::WorldNotFound(name.to_string()));
+        }
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
     /// Get world metadata without loading the entire world
     pub fn get_world_metadata(&self, world_name: &str) -> Result<WorldMetadata, SaveLoadError> {
         let world_dir = self.save_directory.join(world_name);
@@ -456,7 +725,65 @@ impl SaveLoadManager {
     }
 }
 
+/// Starts chunk files that store materials by id. Older files start straight away with
+/// the chunk key, whose first four bytes never spell this out for a real world.
+#[cfg(feature = "files")]
+const CHUNK_FILE_MAGIC: [u8; 4] = *b"SEC2";
+
+/// A `ChunkSave` as written before materials were stored by id, with each material as
+/// its position among `MaterialType`'s variants in this build
+#[cfg(feature = "files")]
+#[derive(Deserialize)]
+struct LegacyChunkSave {
+    chunk_key: ChunkKey,
+    particles: Vec<LegacyParticleSave>,
+    biome_data: HashMap<(usize, usize), BiomeType>,
+    last_updated: String,
+    generation_stage: u8,
+}
+
+#[cfg(feature = "files")]
+#[derive(Deserialize)]
+struct LegacyParticleSave {
+    local_x: usize,
+    local_y: usize,
+    material_type: MaterialType,
+    temp: f32,
+    life: Option<f32>,
+    burning: bool,
+    time_in_state: f32,
+}
+
+/// Read a chunk file in either format
+#[cfg(feature = "files")]
+fn read_chunk_save(mut reader: impl Read) -> Result<ChunkSave, SaveLoadError> {
+    let mut head = [0; 4];
+    reader.read_exact(&mut head)?;
+    if head == CHUNK_FILE_MAGIC {
+        return Ok(bincode::deserialize_from(reader)?);
+    }
+    let legacy: LegacyChunkSave = bincode::deserialize_from(head.as_slice().chain(reader))?;
+    Ok(ChunkSave {
+        chunk_key: legacy.chunk_key,
+        particles: legacy.particles.into_iter()
+            .map(|particle| ParticleSave {
+                local_x: particle.local_x,
+                local_y: particle.local_y,
+                material_type: particle.material_type,
+                temp: particle.temp,
+                life: particle.life,
+                burning: particle.burning,
+                time_in_state: particle.time_in_state,
+            })
+            .collect(),
+        biome_data: legacy.biome_data,
+        last_updated: legacy.last_updated,
+        generation_stage: legacy.generation_stage,
+    })
+}
+
 impl ChunkSave {
+    #[cfg(feature = "files")]
     fn from_chunk(chunk_key: ChunkKey, chunk: &crate::chunk::Chunk) -> Self {
         let mut particles = Vec::new();
         let mut biome_data = HashMap::new();
@@ -490,7 +817,77 @@ impl ChunkSave {
     }
 }
 
+impl SimulationSave {
+    pub fn capture(simulation: &Simulation) -> Self {
+        Self {
+            width: simulation.width,
+            height: simulation.height,
+            tick: simulation.tick(),
+            particles: simulation.iter_particles().cloned().collect(),
+            rng: simulation.rng_state(),
+            update: simulation.update_state(),
+            tile_entities: Vec::new(),
+            generators: simulation.generators().map(|(cell, config)| (cell, *config)).collect(),
+        }
+    }
+
+    pub fn with_tile_entities(mut self, tile_entity_manager: &TileEntityManager) -> Self {
+        self.tile_entities = tile_entity_manager.get_tile_entities().cloned().collect();
+        self
+    }
+
+    /// A new simulation with the default materials holding the saved grid
+    pub fn restore(&self) -> Simulation {
+        self.restore_with_materials(Arc::new(MaterialRegistry::new()))
+    }
+
+    pub fn restore_with_materials(&self, materials: Arc<MaterialRegistry>) -> Simulation {
+        let mut simulation = Simulation::with_materials(self.width, self.height, materials);
+        self.apply_to(&mut simulation).expect("a simulation built to the save's size");
+        simulation
+    }
+
+    /// Replace the contents, tick and random state of an existing simulation of the same
+    /// size, keeping its materials and settings. Particles of disabled materials are dropped.
+    pub fn apply_to(&self, simulation: &mut Simulation) -> Result<(), SaveLoadError> {
+        if (simulation.width, simulation.height) != (self.width, self.height) {
+            return Err(SaveLoadError::CorruptedData(format!(
+                "saved grid is {}x{}, simulation is {}x{}",
+                self.width, self.height, simulation.width, simulation.height
+            )));
+        }
+        simulation.clear();
+        simulation.resume_at_tick(self.tick);
+        for particle in &self.particles {
+            if particle.x < self.width && particle.y < self.height && simulation.is_material_enabled(particle.material_type) {
+                simulation.set_particle(particle.x, particle.y, particle.clone());
+            }
+        }
+        simulation.set_generators(self.generators.iter().copied());
+        simulation.set_rng_state(&self.rng);
+        simulation.set_update_state(&self.update);
+        Ok(())
+    }
+
+    /// Write in the gzip-compressed bincode format used for chunks
+    #[cfg(feature = "files")]
+    pub fn write_to_file(&self, path: impl AsRef<Path>, compression: Compression) -> Result<(), SaveLoadError> {
+        let encoder = GzEncoder::new(File::create(path)?, compression);
+        let mut writer = BufWriter::new(encoder);
+        bincode::serialize_into(&mut writer, self)?;
+        writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+        Ok(())
+    }
+
+    #[cfg(feature = "files")]
+    pub fn read_from_file(path: impl AsRef<Path>) -> Result<Self, SaveLoadError> {
+        let reader = BufReader::new(GzDecoder::new(File::open(path)?));
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
 impl ECSSnapshot {
+    #[cfg(feature = "files")]
     fn from_ecs(ecs: &ECS) -> Self {
         let mut entities = Vec::new();
         
@@ -566,6 +963,8 @@ impl std::error::Error for SaveLoadError {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bookmarks::CameraBookmark;
+    use crate::weather::WeatherKind;
     // use tempfile::TempDir; // TODO: Add tempfile dependency for testing
 
     #[test]
@@ -576,9 +975,29 @@ mod tests {
         // assert!(manager.save_directory.exists());
     }
 
+    #[cfg(feature = "files")]
+    fn metadata(world_name: &str) -> WorldMetadata {
+        WorldMetadata {
+            world_name: world_name.to_string(),
+            version: "1.0.0".to_string(),
+            created_at: String::new(),
+            last_played: String::new(),
+            player_count: 0,
+            total_playtime: 0.0,
+            world_size: (0, 0),
+            spawn_point: (0.0, 0.0),
+            difficulty: Difficulty::Normal,
+            game_mode: GameMode::Creative,
+            seed: 1,
+            rules: WorldRules::default(),
+            camera_bookmarks: CameraBookmarks::default(),
+            weather: WeatherState::default(),
+        }
+    }
+
     #[test]
     fn test_world_metadata_serialization() {
-        let metadata = WorldMetadata {
+        let mut metadata = WorldMetadata {
             world_name: "TestWorld".to_string(),
             version: "1.0.0".to_string(),
             created_at: "2024-01-01T00:00:00Z".to_string(),
@@ -590,13 +1009,33 @@ mod tests {
             difficulty: Difficulty::Normal,
             game_mode: GameMode::Survival,
             seed: 12345,
+            rules: WorldRules {
+                gravity: 0.5,
+                ..WorldRules::default()
+            },
+            camera_bookmarks: CameraBookmarks::default(),
+            weather: WeatherState::new(WeatherKind::Snow, 0.7),
         };
+        metadata.camera_bookmarks.set(CameraBookmark::new("spawn", 0.0, -20.0, 2.0));
 
         let json = serde_json::to_string(&metadata).unwrap();
         let deserialized: WorldMetadata = serde_json::from_str(&json).unwrap();
         
         assert_eq!(metadata.world_name, deserialized.world_name);
         assert_eq!(metadata.seed, deserialized.seed);
+        assert_eq!(metadata.rules, deserialized.rules);
+        assert_eq!(metadata.camera_bookmarks, deserialized.camera_bookmarks);
+        assert_eq!(deserialized.weather, WeatherState::new(WeatherKind::Snow, 0.7));
+
+        // Saves written before rules existed still load
+        let mut legacy: serde_json::Value = serde_json::from_str(&json).unwrap();
+        legacy.as_object_mut().unwrap().remove("rules");
+        legacy.as_object_mut().unwrap().remove("camera_bookmarks");
+        legacy.as_object_mut().unwrap().remove("weather");
+        let legacy: WorldMetadata = serde_json::from_value(legacy).unwrap();
+        assert!(legacy.weather.is_clear());
+        assert!(legacy.rules.is_default());
+        assert!(legacy.camera_bookmarks.is_empty());
     }
 
     #[test]
@@ -609,4 +1048,146 @@ mod tests {
         // let worlds = manager.list_worlds().unwrap();
         // assert!(worlds.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    #[cfg(feature = "files")]
+    fn test_simulation_save_resumes_exactly() {
+        let mut simulation = Simulation::new(32, 32);
+        simulation.set_seed(11);
+        simulation.paint(10, 5, MaterialType::Sand, 3);
+        simulation.paint(20, 5, MaterialType::Water, 3);
+        simulation.add_particle(16, 30, MaterialType::Lava, None);
+        for _ in 0..20 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        let dir = std::env::temp_dir().join(format!("sand_engine_sim_save_{}", std::process::id()));
+        let manager = SaveLoadManager::new(&dir).unwrap();
+        manager.save_simulation("quick", &SimulationSave::capture(&simulation)).unwrap();
+        let mut loaded = manager.load_simulation("quick").unwrap().restore();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.tick(), 20);
+
+        for _ in 0..40 {
+            simulation.update(1.0 / 60.0);
+            loaded.update(1.0 / 60.0);
+        }
+        let cells = |simulation: &Simulation| -> Vec<_> {
+            simulation.iter_particles().map(|p| (p.x, p.y, p.material_type, p.temp.to_bits())).collect()
+        };
+        assert_eq!(cells(&loaded), cells(&simulation));
+        assert!(matches!(manager.load_simulation("missing"), Err(SaveLoadError::WorldNotFound(_))));
+    }
+
+    #[test]
+    #[cfg(feature = "files")]
+    fn test_world_materials_resolve_by_name_and_old_chunks_load() {
+        let pack = "[[material]]\nname = \"Rock Salt\"\nkind = \"powder\"\n";
+        let saved_with = MaterialRegistry::from_toml_str(pack).unwrap();
+        let salt = saved_with.lookup("Rock Salt").unwrap();
+        let mut chunk_manager = ChunkManager::new();
+        chunk_manager.set_particle(1, 1, Particle::new(1, 1, salt, None));
+        chunk_manager.add_particle(2, 1, MaterialType::Sand, None);
+
+        let dir = std::env::temp_dir().join(format!("sand_engine_material_ids_{}", std::process::id()));
+        let manager = SaveLoadManager::new(&dir).unwrap();
+        let (ecs, tile_entities) = (ECS::new(), TileEntityManager::new());
+        manager.save_world("salt", &chunk_manager, &saved_with, &ecs, &tile_entities, &WorldGenerator::new(1), metadata("salt"), None).unwrap();
+
+        // A chunk written the old way, with the material as its variant index
+        let particles = vec![(5usize, 6usize, MaterialType::Water, 20.0f32, None::<f32>, false, 0.0f32)];
+        let legacy = ((3, 0), particles, HashMap::<(usize, usize), BiomeType>::new(), String::new(), 100u8);
+        let file = File::create(dir.join("salt").join("chunks").join("chunk_3_0.dat")).unwrap();
+        bincode::serialize_into(GzEncoder::new(file, Compression::default()), &legacy).unwrap();
+
+        let world = manager.load_world("salt").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(world.material_ids.name(salt.id()), Some("Rock Salt"));
+
+        // A newer pack registers another material first, so Rock Salt's custom id has moved
+        let mut loaded_with = MaterialRegistry::new();
+        loaded_with.load_toml_str("[[material]]\nname = \"Clay\"\nkind = \"powder\"\n").unwrap();
+        loaded_with.load_toml_str(pack).unwrap();
+        let (mut chunk_manager, mut ecs, mut tile_entities) = (ChunkManager::new(), ECS::new(), TileEntityManager::new());
+        SaveLoadManager::apply_world_save(&world, &loaded_with, &mut chunk_manager, &mut ecs, &mut tile_entities).unwrap();
+        assert_ne!(loaded_with.lookup("Rock Salt"), Some(salt));
+        assert_eq!(chunk_manager.material_at(1, 1), loaded_with.lookup("Rock Salt"));
+        assert_eq!(chunk_manager.material_at(2, 1), Some(MaterialType::Sand));
+        assert_eq!(chunk_manager.material_at(3 * 64 + 5, 6), Some(MaterialType::Water));
+
+        // Custom materials the registry lacks are dropped rather than read as something else
+        SaveLoadManager::apply_world_save(&world, &MaterialRegistry::new(), &mut chunk_manager, &mut ecs, &mut tile_entities).unwrap();
+        assert_eq!(chunk_manager.material_at(1, 1), None);
+        assert_eq!(chunk_manager.total_particles(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "files")]
+    fn test_clock_round_trip() {
+        let dir = std::env::temp_dir().join(format!("sand_engine_clock_{}", std::process::id()));
+        let manager = SaveLoadManager::new(&dir).unwrap();
+        let (chunk_manager, ecs, tile_entities) = (ChunkManager::new(), ECS::new(), TileEntityManager::new());
+        let save = |clock: Option<&WorldClock>| {
+            manager.save_world("night", &chunk_manager, &MaterialRegistry::new(), &ecs, &tile_entities, &WorldGenerator::new(1), metadata("night"), clock).unwrap();
+            manager.load_world("night").unwrap().clock
+        };
+        let mut clock = WorldClock::new(120.0);
+        clock.advance(100.0);
+        let loaded = save(Some(&clock));
+        // Saving again without a clock doesn't leave the old one to be loaded
+        let missing = save(None);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.as_ref(), Some(&clock));
+        assert_eq!(missing, None);
+
+        // The engine's air follows the loaded time of day
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.enable_day_cycle(loaded.unwrap());
+        engine.update_with_delta(1.0 / 60.0);
+        assert!(engine.clock().unwrap().is_night());
+        assert!(engine.physics_state.ambient_temp() < 11.0);
+        engine.disable_day_cycle();
+        assert_eq!(engine.physics_state.ambient_temp(), crate::physics::AMBIENT_TEMP);
+    }
+
+    #[test]
+    #[cfg(feature = "files")]
+    fn test_structure_library_round_trip() {
+        let mut simulation = Simulation::new(16, 16);
+        simulation.paint(8, 8, MaterialType::Stone, 2);
+        let mut structure = crate::structures::Structure::from_region(&simulation, crate::GridRect::new(4, 4, 8, 8));
+        structure.name = "Boulder".to_string();
+        let mut library = StructureLibrary::default();
+        library.insert(structure);
+
+        let dir = std::env::temp_dir().join(format!("sand_engine_structures_{}", std::process::id()));
+        let manager = SaveLoadManager::new(&dir).unwrap();
+        assert!(manager.load_structures("user").unwrap().structures.is_empty());
+        manager.save_structures("user", &library).unwrap();
+        let loaded = manager.load_structures("user").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let boulder = loaded.get("Boulder").unwrap();
+        assert_eq!(boulder.particles.len(), 13);
+        assert_eq!((boulder.width, boulder.height), (8, 8));
+    }
+
+    #[test]
+    #[cfg(feature = "files")]
+    fn test_blueprint_file_round_trip() {
+        let mut simulation = Simulation::new(16, 16);
+        simulation.add_particle(3, 3, MaterialType::Lava, None);
+        let mut tile_entities = TileEntityManager::new();
+        tile_entities.add_tile_entity(TileEntity::new_sign((4, 4), "hot"));
+        let blueprint = Blueprint::capture(&simulation, &tile_entities, crate::GridRect::new(2, 2, 4, 4));
+
+        let dir = std::env::temp_dir().join(format!("sand_engine_blueprints_{}", std::process::id()));
+        let manager = SaveLoadManager::new(&dir).unwrap();
+        assert!(matches!(manager.load_blueprint("lava"), Err(SaveLoadError::WorldNotFound(_))));
+        manager.save_blueprint("lava", &blueprint).unwrap();
+        let loaded = manager.load_blueprint("lava").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((loaded.width, loaded.height), (4, 4));
+        assert_eq!(loaded.particles[0].material_type, MaterialType::Lava);
+        assert_eq!(loaded.tile_entities[0].position, (2, 2));
+    }
+}
@@ -0,0 +1,268 @@
+use crate::challenges::SceneFill;
+use crate::material_registry::{MaterialRegistry, MaterialRegistryError};
+use crate::materials::MaterialType;
+use crate::measure::RegionCount;
+use crate::oracle::{Assertion, AssertionFailure};
+use crate::simulation::{GridRect, Simulation};
+use crate::telemetry::Telemetry;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+#[cfg(feature = "files")]
+use std::fs;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// One `[[fill]]` rectangle of a scenario file's starting grid
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FillDefinition {
+    pub rect: GridRect,
+    pub material: String,
+    pub temp: Option<f32>,
+}
+
+/// One `[[paint]]` event of a scenario file: a disc painted just before step `frame`
+/// (counting from 0). "Eraser" clears cells.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaintDefinition {
+    pub frame: u64,
+    pub x: usize,
+    pub y: usize,
+    pub material: String,
+    #[serde(default = "default_radius")]
+    pub radius: usize,
+}
+
+fn default_radius() -> usize {
+    1
+}
+
+fn default_delta_time() -> f32 {
+    1.0 / 60.0
+}
+
+fn default_metrics_interval() -> u64 {
+    1
+}
+
+/// A TOML scenario file. Materials are named as in material packs; `[[expect]]` entries
+/// are oracle assertions checked after the last frame.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioFile {
+    pub width: usize,
+    pub height: usize,
+    /// Steps to run
+    pub frames: u64,
+    #[serde(default = "default_delta_time")]
+    pub delta_time: f32,
+    pub seed: Option<u64>,
+    /// Record metrics after every this many steps
+    #[serde(default = "default_metrics_interval")]
+    pub metrics_interval: u64,
+    #[serde(rename = "fill", default)]
+    pub fills: Vec<FillDefinition>,
+    #[serde(rename = "paint", default)]
+    pub paints: Vec<PaintDefinition>,
+    #[serde(rename = "expect", default)]
+    pub expectations: Vec<Assertion>,
+}
+
+/// A scripted paint event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScenarioPaint {
+    pub frame: u64,
+    pub x: usize,
+    pub y: usize,
+    pub material: MaterialType,
+    pub radius: usize,
+}
+
+/// A reproducible run: a starting grid, paint events at fixed frames and a number of
+/// steps, with assertions the final grid must satisfy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scenario {
+    pub width: usize,
+    pub height: usize,
+    pub frames: u64,
+    pub delta_time: f32,
+    pub seed: Option<u64>,
+    pub metrics_interval: u64,
+    pub fills: Vec<SceneFill>,
+    /// Ordered by frame
+    pub paints: Vec<ScenarioPaint>,
+    pub expectations: Vec<Assertion>,
+}
+
+/// What a scenario left behind
+#[derive(Debug)]
+pub struct ScenarioOutcome {
+    pub simulation: Simulation,
+    /// `particles.total` and `particles.<material>` counts and `temperature.min`/`mean`/`max`
+    /// of the occupied cells, every `metrics_interval` steps
+    pub telemetry: Telemetry,
+    pub failures: Vec<AssertionFailure>,
+}
+
+impl ScenarioOutcome {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Scenario {
+    /// Parse a TOML scenario, resolving material names against `materials`
+    pub fn from_toml_str(source: &str, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let file: ScenarioFile = toml::from_str(source)?;
+        Self::from_definition(&file, materials)
+    }
+
+    #[cfg(feature = "files")]
+    pub fn from_file(path: impl AsRef<Path>, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let source = fs::read_to_string(path)?;
+        Self::from_toml_str(&source, materials)
+    }
+
+    pub fn from_definition(file: &ScenarioFile, materials: &MaterialRegistry) -> Result<Self, MaterialRegistryError> {
+        let resolve = |name: &str| {
+            materials.lookup(name).ok_or_else(|| MaterialRegistryError::UnknownMaterial(name.to_string()))
+        };
+        let invalid = |message: &str| Err(MaterialRegistryError::InvalidDefinition(format!("scenario {}", message)));
+        if file.width == 0 || file.height == 0 {
+            return invalid("needs a grid of at least one cell");
+        }
+        if !(file.delta_time > 0.0 && file.delta_time.is_finite()) {
+            return invalid("needs a positive delta_time");
+        }
+        if file.metrics_interval == 0 {
+            return invalid("needs a metrics_interval of at least 1");
+        }
+
+        let fills = file.fills.iter()
+            .map(|fill| Ok(SceneFill { rect: fill.rect, material: resolve(&fill.material)?, temp: fill.temp }))
+            .collect::<Result<Vec<_>, MaterialRegistryError>>()?;
+        let mut paints = Vec::with_capacity(file.paints.len());
+        for paint in &file.paints {
+            if paint.x >= file.width || paint.y >= file.height {
+                return invalid(&format!("paints at ({}, {}), outside the grid", paint.x, paint.y));
+            }
+            paints.push(ScenarioPaint {
+                frame: paint.frame,
+                x: paint.x,
+                y: paint.y,
+                material: resolve(&paint.material)?,
+                radius: paint.radius,
+            });
+        }
+        // Stable, so events for the same frame keep the file's order
+        paints.sort_by_key(|paint| paint.frame);
+
+        Ok(Self {
+            width: file.width,
+            height: file.height,
+            frames: file.frames,
+            delta_time: file.delta_time,
+            seed: file.seed,
+            metrics_interval: file.metrics_interval,
+            fills,
+            paints,
+            expectations: file.expectations.clone(),
+        })
+    }
+
+    /// A fresh simulation holding the starting grid
+    pub fn build(&self, materials: Arc<MaterialRegistry>) -> Simulation {
+        let mut simulation = Simulation::with_materials(self.width, self.height, materials);
+        if let Some(seed) = self.seed {
+            simulation.set_seed(seed);
+        }
+        for fill in &self.fills {
+            fill.apply_to(&mut simulation);
+        }
+        simulation
+    }
+
+    /// Build the starting grid and run every frame, recording metrics along the way
+    pub fn run(&self, materials: Arc<MaterialRegistry>) -> ScenarioOutcome {
+        let mut simulation = self.build(materials);
+        let mut telemetry = Telemetry::new();
+        let mut paints = self.paints.iter().peekable();
+        for frame in 0..self.frames {
+            while let Some(paint) = paints.next_if(|paint| paint.frame == frame) {
+                simulation.paint(paint.x, paint.y, paint.material, paint.radius);
+            }
+            simulation.update(self.delta_time);
+            if (frame + 1) % self.metrics_interval == 0 {
+                record_metrics(&simulation, &mut telemetry, (frame + 1) as f32 * self.delta_time);
+            }
+        }
+        let failures = self.expectations.iter()
+            .filter_map(|assertion| assertion.evaluate(&simulation))
+            .collect();
+        ScenarioOutcome { simulation, telemetry, failures }
+    }
+}
+
+fn record_metrics(simulation: &Simulation, telemetry: &mut Telemetry, time: f32) {
+    let grid = GridRect::new(0, 0, simulation.width, simulation.height);
+    RegionCount::of(simulation, grid).record(simulation, telemetry, "particles", time);
+
+    let (mut count, mut sum, mut min, mut max) = (0usize, 0.0f64, f32::INFINITY, f32::NEG_INFINITY);
+    for particle in simulation.iter_particles() {
+        count += 1;
+        sum += f64::from(particle.temp);
+        min = min.min(particle.temp);
+        max = max.max(particle.temp);
+    }
+    if count > 0 {
+        let tick = simulation.tick();
+        telemetry.record(tick, time, "temperature.min", f64::from(min));
+        telemetry.record(tick, time, "temperature.mean", sum / count as f64);
+        telemetry.record(tick, time, "temperature.max", f64::from(max));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_example_scenario_runs_and_meets_expectations() {
+        let materials = Arc::new(MaterialRegistry::new());
+        let scenario = Scenario::from_toml_str(include_str!("../scenarios/sand_and_water.toml"), &materials).unwrap();
+        let outcome = scenario.run(materials);
+
+        assert!(outcome.passed(), "{}", outcome.failures.iter().map(|f| f.to_string()).collect::<Vec<_>>().join("\n"));
+        assert_eq!(outcome.simulation.tick(), scenario.frames);
+        let totals = outcome.telemetry.series("particles.total").count() as u64;
+        assert_eq!(totals, scenario.frames / scenario.metrics_interval);
+        assert!(outcome.telemetry.series("temperature.mean").next().is_some());
+    }
+
+    #[test]
+    fn test_paints_apply_at_their_frame() {
+        let source = "width = 16\nheight = 16\nframes = 3\nseed = 1\n\
+            [[paint]]\nframe = 2\nx = 8\ny = 2\nmaterial = \"stone\"\nradius = 0\n";
+        let materials = Arc::new(MaterialRegistry::new());
+        let scenario = Scenario::from_toml_str(source, &materials).unwrap();
+        let outcome = scenario.run(materials);
+        let counts: Vec<f64> = outcome.telemetry.series("particles.total").map(|sample| sample.value).collect();
+        assert_eq!(counts, vec![0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_invalid_scenarios_rejected() {
+        let materials = MaterialRegistry::new();
+        let unknown = "width = 8\nheight = 8\nframes = 1\n[[fill]]\nrect = { x = 0, y = 0, width = 2, height = 2 }\nmaterial = \"Unobtainium\"\n";
+        assert!(matches!(
+            Scenario::from_toml_str(unknown, &materials),
+            Err(MaterialRegistryError::UnknownMaterial(_))
+        ));
+        let outside = "width = 8\nheight = 8\nframes = 1\n[[paint]]\nframe = 0\nx = 8\ny = 0\nmaterial = \"Sand\"\n";
+        assert!(matches!(
+            Scenario::from_toml_str(outside, &materials),
+            Err(MaterialRegistryError::InvalidDefinition(_))
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::simulation::{GridRect, Simulation};
+use crate::structures::{Structure, StructureLibrary};
+use mlua::{Function, Lua, LuaOptions, StdLib, Table, Value};
+use std::cell::RefCell;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// Registry key of the functions passed to `on_frame`
+const FRAME_CALLBACKS: &str = "sand_engine.frame_callbacks";
+
+#[derive(Debug)]
+pub enum ScriptError {
+    IoError(std::io::Error),
+    LuaError(mlua::Error),
+}
+
+impl From<std::io::Error> for ScriptError {
+    fn from(error: std::io::Error) -> Self {
+        ScriptError::IoError(error)
+    }
+}
+
+impl From<mlua::Error> for ScriptError {
+    fn from(error: mlua::Error) -> Self {
+        ScriptError::LuaError(error)
+    }
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScriptError::IoError(e) => write!(f, "IO error: {}", e),
+            ScriptError::LuaError(e) => write!(f, "Lua error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// A Lua 5.4 script driving a simulation, for tutorials, puzzles and stress tests that
+/// change without recompiling. The script's top level runs once when it is loaded and
+/// can register functions with `on_frame(function(frame, delta_time) ... end)`, which
+/// `step` calls before every update.
+///
+/// Both see the simulation as the global `sim`:
+/// - `sim.paint(x, y, material, radius)` fills a disc, returning the cells painted
+/// - `sim.place_structure(name, x, y)` places a built-in or added structure by its top-left corner
+/// - `sim.set_wind(vx, vy [, x, y, width, height])` runs a fan over a region, the whole
+///   grid by default; `0, 0` stops it
+/// - `sim.material_at(x, y)` and `sim.temperature_at(x, y)`, nil for empty cells
+/// - `sim.count(material [, x, y, width, height])`
+/// - `sim.width()`, `sim.height()` and `sim.tick()`
+///
+/// Materials are named as in material packs. Scripts only get Lua's table, string and
+/// math libraries, so they can't reach files or the OS.
+pub struct Script {
+    lua: Lua,
+    structures: StructureLibrary,
+    frame: u64,
+}
+
+impl Script {
+    /// Run `source`'s top level against `simulation`
+    pub fn load(source: &str, simulation: &mut Simulation) -> Result<Self, ScriptError> {
+        Self::load_with_structures(source, simulation, StructureLibrary::default())
+    }
+
+    /// Like `load`, with `structures` placeable by name alongside the built-in ones
+    pub fn load_with_structures(source: &str, simulation: &mut Simulation, structures: StructureLibrary) -> Result<Self, ScriptError> {
+        let lua = Lua::new_with(StdLib::TABLE | StdLib::STRING | StdLib::MATH, LuaOptions::default())?;
+        lua.set_named_registry_value(FRAME_CALLBACKS, lua.create_table()?)?;
+        let on_frame = lua.create_function(|lua, callback: Function| {
+            lua.named_registry_value::<Table>(FRAME_CALLBACKS)?.push(callback)
+        })?;
+        lua.globals().set("on_frame", on_frame)?;
+
+        let script = Self { lua, structures, frame: 0 };
+        script.with_simulation(simulation, |lua| lua.load(source).set_name("script").exec())?;
+        Ok(script)
+    }
+
+    #[cfg(feature = "files")]
+    pub fn from_file(path: impl AsRef<Path>, simulation: &mut Simulation) -> Result<Self, ScriptError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::load(&source, simulation)
+    }
+
+    /// Call the `on_frame` functions, then advance `simulation` by `delta_time` seconds
+    pub fn step(&mut self, simulation: &mut Simulation, delta_time: f32) -> Result<(), ScriptError> {
+        let frame = self.frame;
+        self.with_simulation(simulation, |lua| {
+            for callback in lua.named_registry_value::<Table>(FRAME_CALLBACKS)?.sequence_values::<Function>() {
+                callback?.call::<_, ()>((frame, delta_time))?;
+            }
+            Ok(())
+        })?;
+        simulation.update(delta_time);
+        self.frame += 1;
+        Ok(())
+    }
+
+    /// Steps taken so far
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Expose `simulation` as the global `sim` while `run` executes
+    fn with_simulation<R>(&self, simulation: &mut Simulation, run: impl FnOnce(&Lua) -> mlua::Result<R>) -> mlua::Result<R> {
+        let simulation = RefCell::new(simulation);
+        let simulation = &simulation;
+        let structures = &self.structures;
+        let lua = &self.lua;
+        lua.scope(|scope| {
+            let sim = lua.create_table()?;
+            sim.set("width", scope.create_function(|_, ()| Ok(simulation.borrow().width))?)?;
+            sim.set("height", scope.create_function(|_, ()| Ok(simulation.borrow().height))?)?;
+            sim.set("tick", scope.create_function(|_, ()| Ok(simulation.borrow().tick()))?)?;
+            sim.set("paint", scope.create_function(|_, (x, y, material, radius): (usize, usize, String, Option<usize>)| {
+                let mut simulation = simulation.borrow_mut();
+                let material = lookup(&simulation, &material)?;
+                Ok(simulation.paint(x, y, material, radius.unwrap_or(1)))
+            })?)?;
+            sim.set("place_structure", scope.create_function(|_, (name, x, y): (String, usize, usize)| {
+                let structure = Structure::get_by_name(&name)
+                    .or_else(|| structures.get(&name).cloned())
+                    .ok_or_else(|| mlua::Error::RuntimeError(format!("unknown structure '{}'", name)))?;
+                Ok(structure.place(&mut simulation.borrow_mut(), x, y))
+            })?)?;
+            sim.set("set_wind", scope.create_function(|_, (vx, vy, region): (f32, f32, mlua::Variadic<usize>)| {
+                let mut simulation = simulation.borrow_mut();
+                let region = rect_or_grid(&simulation, &region)?;
+                simulation.set_wind(region, (vx, vy));
+                Ok(())
+            })?)?;
+            sim.set("material_at", scope.create_function(|_, (x, y): (usize, usize)| {
+                let simulation = simulation.borrow();
+                Ok(simulation.get_particle(x, y).map(|particle| simulation.materials().get(particle.material_type).name.clone()))
+            })?)?;
+            sim.set("temperature_at", scope.create_function(|_, (x, y): (usize, usize)| {
+                Ok(simulation.borrow().get_particle(x, y).map(|particle| particle.temp))
+            })?)?;
+            sim.set("count", scope.create_function(|_, (material, region): (String, mlua::Variadic<usize>)| {
+                let simulation = simulation.borrow();
+                let material = lookup(&simulation, &material)?;
+                let region = rect_or_grid(&simulation, &region)?;
+                Ok(simulation.iter_region(region).filter(|particle| particle.material_type == material).count())
+            })?)?;
+
+            lua.globals().set("sim", sim)?;
+            let result = run(lua);
+            // The functions die with the scope; don't leave a table of dead ones behind
+            lua.globals().set("sim", Value::Nil)?;
+            result
+        })
+    }
+}
+
+fn lookup(simulation: &Simulation, name: &str) -> mlua::Result<crate::materials::MaterialType> {
+    simulation.materials().lookup(name)
+        .ok_or_else(|| mlua::Error::RuntimeError(format!("unknown material '{}'", name)))
+}
+
+/// `x, y, width, height` from optional trailing arguments, or the whole grid without them
+fn rect_or_grid(simulation: &Simulation, region: &[usize]) -> mlua::Result<GridRect> {
+    match *region {
+        [] => Ok(GridRect::new(0, 0, simulation.width, simulation.height)),
+        [x, y, width, height] => Ok(GridRect::new(x, y, width, height)),
+        _ => Err(mlua::Error::RuntimeError("a region needs x, y, width and height".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    #[test]
+    fn test_script_sets_up_and_drives_a_scene() {
+        let mut simulation = Simulation::new(48, 48);
+        simulation.set_seed(3);
+        let mut script = Script::load(r#"
+            assert(sim.width() == 48)
+            sim.place_structure("House", 4, 30)
+            poured = 0
+            on_frame(function(frame, dt)
+                if frame % 10 == 0 and frame < 50 then
+                    poured = poured + sim.paint(30, 4, "Sand", 1)
+                end
+            end)
+        "#, &mut simulation).unwrap();
+        let house = simulation.iter_particles().count();
+        assert_eq!(house, Structure::house().particles.len());
+
+        for _ in 0..120 {
+            script.step(&mut simulation, 1.0 / 60.0).unwrap();
+        }
+        assert_eq!(script.frame(), 120);
+        assert_eq!(simulation.tick(), 120);
+        let sand = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Sand).count();
+        assert!(sand > 0);
+        let poured: usize = script.lua.globals().get("poured").unwrap();
+        assert_eq!(poured, sand);
+    }
+
+    #[test]
+    fn test_queries_and_wind() {
+        let mut simulation = Simulation::new(32, 32);
+        simulation.add_particle(5, 6, MaterialType::Stone, Some(80.0));
+        Script::load(r#"
+            assert(sim.material_at(5, 6) == "Stone")
+            assert(sim.temperature_at(5, 6) == 80)
+            assert(sim.material_at(0, 0) == nil)
+            assert(sim.count("stone") == 1)
+            assert(sim.count("Stone", 0, 0, 4, 4) == 0)
+            sim.set_wind(2, 0, 0, 0, 16, 16)
+        "#, &mut simulation).unwrap();
+        assert_eq!(simulation.wind().fans().len(), 1);
+    }
+
+    #[test]
+    fn test_script_errors_are_reported() {
+        let mut simulation = Simulation::new(8, 8);
+        assert!(matches!(Script::load("sim.paint(1, 1, 'Unobtainium')", &mut simulation), Err(ScriptError::LuaError(_))));
+        assert!(Script::load("io.open('x')", &mut simulation).is_err());
+
+        let mut script = Script::load("on_frame(function() sim.place_structure('Nowhere', 0, 0) end)", &mut simulation).unwrap();
+        let error = script.step(&mut simulation, 1.0 / 60.0).unwrap_err();
+        assert!(error.to_string().contains("unknown structure 'Nowhere'"));
+    }
+}
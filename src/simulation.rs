@@ -1,9 +1,59 @@
+use crate::bounds::WorldBounds;
+use crate::brush::Brush;
+use crate::effects::{EffectLayer, EMBER_CHANCE_PER_SEC, SPARK_CHANCE_PER_SEC, SPLASH_CHANCE};
+use crate::event_bus::{EventBus, SimulationEvent};
+use crate::compaction::{CompactionRules, COMPACTION_BLOCK_SIZE, COMPACTION_SETTLED_FRAMES};
+use crate::generators::GeneratorConfig;
+use crate::damage::{self, DamageOutcome};
+use crate::explosion::{self, Explosion, ExplosionReport, BLAST_HEAT};
+use crate::interpolation::ParticleMove;
+use crate::lighting::{LightMap, LightSource};
 use crate::particle::Particle;
-use crate::materials::MaterialType;
-use crate::physics::PhysicsState;
+use crate::materials::{MaterialKind, MaterialType};
+use crate::material_registry::MaterialRegistry;
+use crate::oxygen::{AirField, FIRE_MIN_OXYGEN};
+use crate::radioactivity::{RadiationField, RADIOACTIVITY_PASS_INTERVAL};
+use crate::quality::QualityLevel;
+use crate::budget::BudgetedTick;
+use crate::physics::{self, PhysicsState};
+use crate::plants::{PlantRegistry, PLANT_GROWTH_INTERVAL};
+use crate::reactions::{ReactionRegistry, StageEffect, StagedReaction, NEIGHBOR_OFFSETS};
+use crate::interactions::{Interaction, InteractionMatrix};
+use crate::day_cycle::WorldClock;
+use crate::biomes::{BiomeMap, BIOME_PASS_INTERVAL};
+use crate::weather::WeatherState;
+use crate::wind::WindField;
+#[cfg(feature = "export")]
+use crate::timelapse::Timelapse;
+use crate::actuators::ActuatorWorld;
+use crate::circuits::SensorWorld;
+use crate::tile_entity::{FluidWorld, ThermalWorld, TileEffectReport, TileEntityEffect, TileEntityManager, EFFECT_SEARCH_RADIUS};
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Position in a simulation's random number stream, saved so a loaded world carries on
+/// exactly as the original would have
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngState {
+    pub seed: [u8; 32],
+    pub stream: u64,
+    pub word_pos: u128,
+}
+
+/// Update bookkeeping outside the grid (column order, compaction clock and which chunks
+/// are awake), saved with the RNG so a restored simulation makes the same moves
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdateState {
+    column_order: Vec<usize>,
+    compaction_timer: f32,
+    awake: Vec<GridRect>,
+    #[serde(default)]
+    active: Vec<(usize, usize)>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SimulationState {
@@ -12,14 +62,52 @@ pub struct SimulationState {
     pub particles: HashMap<(usize, usize), Particle>,
 }
 
-// Pre-computed direction lookup tables for performance
-const NEIGHBOR_OFFSETS: [(i32, i32); 8] = [
-    (-1, -1), (0, -1), (1, -1),
-    (-1,  0),          (1,  0),
-    (-1,  1), (0,  1), (1,  1),
-];
+// Impulse velocities decay each tick and are dropped below the threshold
+const VELOCITY_DAMPING: f32 = 0.8;
+/// Distance from a bursting particle at which `StageEffect::Burst` places what it throws
+const STAGE_BURST_RADIUS: f32 = 2.0;
+const MIN_VELOCITY: f32 = 0.1;
+const MAX_VELOCITY: f32 = 8.0;
+// Chance a grain of wet sand slides off a pile instead of clumping where it lands
+const WET_SAND_SLIDE_CHANCE: f32 = 0.15;
 
-#[derive(Debug)]
+/// Side length of the chunks the grid is updated and put to sleep in
+pub const SIM_CHUNK_SIZE: usize = 16;
+/// A particle stops keeping its chunk awake once it has been still for this many frames
+const SLEEP_SETTLED_FRAMES: u8 = 30;
+/// Temperature change per tick below which a particle counts as thermally settled
+const SLEEP_TEMP_EPSILON: f32 = 0.5;
+/// Temperature gap to a neighbour that wakes a settled brittle solid to take the heat
+const THERMAL_SHOCK_WAKE_DIFFERENCE: f32 = 30.0;
+
+/// A rectangle of grid cells; `x + width` and `y + height` are exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl GridRect {
+    pub fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x - self.x < self.width && y - self.y < self.height
+    }
+
+    /// The cells both rectangles cover, if any
+    pub fn intersection(&self, other: &GridRect) -> Option<GridRect> {
+        let (x, y) = (self.x.max(other.x), self.y.max(other.y));
+        let right = self.x.saturating_add(self.width).min(other.x.saturating_add(other.width));
+        let bottom = self.y.saturating_add(self.height).min(other.y.saturating_add(other.height));
+        (right > x && bottom > y).then(|| GridRect::new(x, y, right - x, bottom - y))
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct DirtyRect {
     pub min_x: usize,
     pub min_y: usize,
@@ -62,32 +150,396 @@ pub struct Simulation {
     pub height: usize,
     // Optimized flat array for better cache performance
     grid: Vec<Option<Particle>>,
-    // Track dirty rectangles for efficient updates
-    dirty_rect: DirtyRect,
+    // Per-chunk bounds of the cells queued for the next update; chunks with an empty
+    // rect are asleep
+    dirty_chunks: Vec<DirtyRect>,
+    chunks_x: usize,
+    chunks_y: usize,
+    // Chunks and particles updated by the last call to `update`
+    awake_chunks: usize,
+    updated_particles: usize,
+    // Completed updates; cells and chunks are stamped with `tick + 1` when written
+    tick: u64,
+    changed_at: Vec<u64>,
+    chunk_changed_at: Vec<u64>,
     col_order: Vec<usize>,
-    physics: PhysicsState,
+    pub(crate) physics: PhysicsState,
+    materials: Arc<MaterialRegistry>,
+    reactions: Arc<ReactionRegistry>,
+    interactions: Arc<InteractionMatrix>,
+    compaction: CompactionRules,
+    // Simulated time since the last compaction pass
+    compaction_timer: f32,
+    plants: Arc<PlantRegistry>,
+    // Simulated time since the last plant growth pass
+    plant_timer: f32,
+    // Fraction of ticks on which particles obey gravity
+    gravity: f32,
+    pub(crate) bounds: WorldBounds,
+    // Particles lost past the edges of a void world
+    pub(crate) voided: u64,
+    // What each Generator cell emits, by cell
+    pub(crate) generators: HashMap<(usize, usize), GeneratorConfig>,
+    disabled_materials: HashSet<MaterialType>,
     particle_count: usize,
-    // Active particles that need processing (performance optimization)
+    // Cells to update next tick, each queued once; the update loop visits only these,
+    // so settled particles cost nothing until a neighbour changes
     active_particles: Vec<(usize, usize)>,
+    queued: Vec<bool>,
+    // Brought up to date after every update while lighting is enabled
+    pub(crate) light_map: Option<LightMap>,
+    light_sources: Vec<LightSource>,
+    // Tick the light map was last brought up to date at; None forces a full recompute
+    lit_at_tick: Option<u64>,
+    // Sparks, splashes and embers while effects are enabled
+    pub(crate) effects: Option<EffectLayer>,
+    // Explosions, hissing steam and painting, queued for frontends while enabled
+    events: Option<EventBus>,
+    // Particles that moved in the last update while motion tracking is on, for interpolation
+    moves: Option<Vec<ParticleMove>>,
+    pub(crate) wind: WindField,
+    // Oxygen per cell while fires can suffocate
+    pub(crate) air: Option<AirField>,
+    // Radiation dose per cell while radioactive materials irradiate the world
+    pub(crate) radiation: Option<RadiationField>,
+    // Simulated time since the last radioactivity pass
+    radioactivity_timer: f32,
+    // Fidelity given up to keep up, and the cell distant chunks are measured from
+    pub(crate) quality: QualityLevel,
+    pub(crate) quality_focus: Option<(i64, i64)>,
+    // Tick `update_budgeted` has left part way through
+    pub(crate) budgeted: Option<BudgetedTick>,
+    // Rain, snow or storms falling from the top rows while weather is on
+    pub(crate) weather: Option<WeatherState>,
+    // Time of day driving the air temperature and ambient light while the cycle runs
+    pub(crate) clock: Option<WorldClock>,
+    // Biome per chunk while biomes shape the world
+    pub(crate) biomes: Option<BiomeMap>,
+    // Simulated time since the last biome pass
+    biome_timer: f32,
+    // Frames rendered every few ticks while a time-lapse is recording
+    #[cfg(feature = "export")]
+    pub(crate) timelapse: Option<Timelapse>,
+    // Every random choice the simulation makes comes from here, so separate
+    // simulations never share state and a seeded one replays exactly
+    pub(crate) rng: ChaCha8Rng,
 }
 
 impl Simulation {
     pub fn new(width: usize, height: usize) -> Self {
+        Self::with_materials(width, height, Arc::new(MaterialRegistry::new()))
+    }
+
+    /// Create a simulation that reads material properties from the given registry
+    pub fn with_materials(width: usize, height: usize, materials: Arc<MaterialRegistry>) -> Self {
         // Use flat array for better cache performance and memory layout
         let grid = vec![None; width * height];
         let col_order: Vec<usize> = (0..width).collect();
-        let physics = PhysicsState::new(width, height);
+        let physics = PhysicsState::with_materials(width, height, Arc::clone(&materials));
+        let compaction = CompactionRules::from_materials(&materials);
+        let chunks_x = width.div_ceil(SIM_CHUNK_SIZE);
+        let chunks_y = height.div_ceil(SIM_CHUNK_SIZE);
 
         Self {
             width,
             height,
             grid,
-            dirty_rect: DirtyRect::new(),
+            dirty_chunks: vec![DirtyRect::new(); chunks_x * chunks_y],
+            chunks_x,
+            chunks_y,
+            awake_chunks: 0,
+            updated_particles: 0,
+            tick: 0,
+            changed_at: vec![0; width * height],
+            chunk_changed_at: vec![0; chunks_x * chunks_y],
             col_order,
             physics,
+            materials,
+            reactions: Arc::new(ReactionRegistry::with_defaults()),
+            interactions: Arc::new(InteractionMatrix::new()),
+            compaction,
+            compaction_timer: 0.0,
+            plants: Arc::new(PlantRegistry::with_defaults()),
+            plant_timer: 0.0,
+            gravity: 1.0,
+            bounds: WorldBounds::default(),
+            voided: 0,
+            generators: HashMap::new(),
+            disabled_materials: HashSet::new(),
             particle_count: 0,
             active_particles: Vec::new(),
+            queued: vec![false; width * height],
+            light_map: None,
+            effects: None,
+            events: None,
+            moves: None,
+            light_sources: Vec::new(),
+            lit_at_tick: None,
+            wind: WindField::new(width, height),
+            air: None,
+            radiation: None,
+            radioactivity_timer: 0.0,
+            quality: QualityLevel::Full,
+            quality_focus: None,
+            budgeted: None,
+            weather: None,
+            clock: None,
+            biomes: None,
+            biome_timer: 0.0,
+            #[cfg(feature = "export")]
+            timelapse: None,
+            rng: ChaCha8Rng::from_entropy(),
+        }
+    }
+
+    /// Reseed the random numbers behind movement, reactions and effects; two simulations
+    /// with the same seed and edits evolve identically
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    pub fn rng_state(&self) -> RngState {
+        RngState {
+            seed: self.rng.get_seed(),
+            stream: self.rng.get_stream(),
+            word_pos: self.rng.get_word_pos(),
+        }
+    }
+
+    pub fn set_rng_state(&mut self, state: &RngState) {
+        self.rng = ChaCha8Rng::from_seed(state.seed);
+        self.rng.set_stream(state.stream);
+        self.rng.set_word_pos(state.word_pos);
+    }
+
+    pub fn update_state(&self) -> UpdateState {
+        UpdateState {
+            column_order: self.col_order.clone(),
+            compaction_timer: self.compaction_timer,
+            awake: self.dirty_chunks.iter()
+                .filter(|rect| rect.is_valid())
+                .map(|rect| GridRect::new(rect.min_x, rect.min_y, rect.max_x + 1 - rect.min_x, rect.max_y + 1 - rect.min_y))
+                .collect(),
+            active: self.active_particles.clone(),
+        }
+    }
+
+    /// Restore bookkeeping from `update_state`, replacing which cells are awake; state
+    /// saved from a grid of another width keeps the current column order, and state
+    /// saved without a cell list wakes every cell of its awake rectangles
+    pub fn set_update_state(&mut self, state: &UpdateState) {
+        if state.column_order.len() == self.width {
+            self.col_order.clone_from(&state.column_order);
+        }
+        self.compaction_timer = state.compaction_timer;
+        self.clear_active();
+        for &(x, y) in &state.active {
+            if x < self.width && y < self.height {
+                self.queue_cell(x, y);
+            }
+        }
+        if !state.active.is_empty() {
+            return;
+        }
+        for rect in &state.awake {
+            if rect.width == 0 || rect.height == 0 || rect.x + rect.width > self.width || rect.y + rect.height > self.height {
+                continue;
+            }
+            for y in rect.y..rect.y + rect.height {
+                for x in rect.x..rect.x + rect.width {
+                    self.queue_cell(x, y);
+                }
+            }
+        }
+    }
+
+    /// Move the tick counter, e.g. back to a save's; every cell counts as changed at the
+    /// new tick, so consumers of `iter_changed_since` must rewind alongside it
+    pub(crate) fn resume_at_tick(&mut self, tick: u64) {
+        self.tick = tick;
+        self.changed_at.fill(tick + 1);
+        self.chunk_changed_at.fill(tick + 1);
+        self.lit_at_tick = None;
+    }
+
+    pub fn materials(&self) -> &Arc<MaterialRegistry> {
+        &self.materials
+    }
+
+    /// Swap the material registry; existing particles keep their types
+    pub fn set_materials(&mut self, materials: Arc<MaterialRegistry>) {
+        self.physics.set_materials(Arc::clone(&materials));
+        self.materials = materials;
+        // The new registry may light or shade every cell differently
+        self.lit_at_tick = None;
+    }
+
+    pub fn reactions(&self) -> &Arc<ReactionRegistry> {
+        &self.reactions
+    }
+
+    /// Mutable access to the reaction table, cloning it first if it is shared
+    pub fn reactions_mut(&mut self) -> &mut ReactionRegistry {
+        Arc::make_mut(&mut self.reactions)
+    }
+
+    pub fn set_reactions(&mut self, reactions: Arc<ReactionRegistry>) {
+        self.reactions = reactions;
+    }
+
+    pub fn interactions(&self) -> &Arc<InteractionMatrix> {
+        &self.interactions
+    }
+
+    /// Mutable access to the interaction matrix, cloning it first if it is shared
+    pub fn interactions_mut(&mut self) -> &mut InteractionMatrix {
+        Arc::make_mut(&mut self.interactions)
+    }
+
+    pub fn set_interactions(&mut self, interactions: Arc<InteractionMatrix>) {
+        self.interactions = interactions;
+    }
+
+    pub fn plants(&self) -> &Arc<PlantRegistry> {
+        &self.plants
+    }
+
+    /// Mutable access to the plant species, cloning them first if they are shared
+    pub fn plants_mut(&mut self) -> &mut PlantRegistry {
+        Arc::make_mut(&mut self.plants)
+    }
+
+    pub fn set_plants(&mut self, plants: Arc<PlantRegistry>) {
+        self.plants = plants;
+    }
+
+    pub fn gravity(&self) -> f32 {
+        self.gravity
+    }
+
+    /// Scale gravity; below 1 particles only fall, flow or rise on that fraction of
+    /// ticks, while values above 1 behave like 1
+    pub fn set_gravity(&mut self, gravity: f32) {
+        self.gravity = gravity.max(0.0);
+    }
+
+    pub fn is_material_enabled(&self, material_type: MaterialType) -> bool {
+        !self.disabled_materials.contains(&material_type)
+    }
+
+    /// Replace the set of materials that `add_particle` refuses to place
+    pub fn set_disabled_materials(&mut self, materials: impl IntoIterator<Item = MaterialType>) {
+        self.disabled_materials = materials.into_iter().collect();
+    }
+
+    /// Start keeping a light map up to date after every update, with `ambient` as the
+    /// darkest level
+    pub fn enable_lighting(&mut self, ambient: f32) {
+        self.light_map = Some(LightMap::new(self.width, self.height, ambient));
+        self.lit_at_tick = None;
+        self.update_lighting();
+    }
+
+    pub fn disable_lighting(&mut self) {
+        self.light_map = None;
+    }
+
+    /// Light levels as of the last update; None while lighting is disabled
+    pub fn light_map(&self) -> Option<&LightMap> {
+        self.light_map.as_ref()
+    }
+
+    /// Start emitting sparks from flames, embers from lava and splashes where things
+    /// fall into water; they are purely visual and never change the grid
+    pub fn enable_effects(&mut self) {
+        self.effects.get_or_insert_with(EffectLayer::new);
+    }
+
+    pub fn disable_effects(&mut self) {
+        self.effects = None;
+    }
+
+    /// Live effects; None while effects are disabled
+    pub fn effects(&self) -> Option<&EffectLayer> {
+        self.effects.as_ref()
+    }
+
+    /// Start queueing `SimulationEvent`s for `drain_events`, e.g. to drive audio
+    pub fn enable_event_bus(&mut self) {
+        self.events.get_or_insert_with(EventBus::new);
+    }
+
+    pub fn disable_event_bus(&mut self) {
+        self.events = None;
+    }
+
+    /// Events queued since the last drain; None while the bus is disabled
+    pub fn event_bus(&self) -> Option<&EventBus> {
+        self.events.as_ref()
+    }
+
+    /// Take the events queued since the last call, oldest first
+    pub fn drain_events(&mut self) -> Vec<SimulationEvent> {
+        self.events.as_mut().map_or_else(Vec::new, EventBus::drain)
+    }
+
+    pub(crate) fn emit_event(&mut self, event: SimulationEvent) {
+        if let Some(events) = &mut self.events {
+            events.emit(event);
+        }
+    }
+
+    /// Record where particles move each update, so renderers can draw them part way
+    /// between ticks (see `interpolation::moved_positions`)
+    pub fn enable_motion_tracking(&mut self) {
+        self.moves.get_or_insert_with(Vec::new);
+    }
+
+    pub fn disable_motion_tracking(&mut self) {
+        self.moves = None;
+    }
+
+    /// Particles that moved in the last update; empty while motion tracking is off
+    pub fn last_moves(&self) -> &[ParticleMove] {
+        self.moves.as_deref().unwrap_or_default()
+    }
+
+    /// Point lights on top of emissive materials, e.g. `LightSource::from_tile_entities`
+    pub fn set_light_sources(&mut self, sources: Vec<LightSource>) {
+        self.light_sources = sources;
+    }
+
+    /// Recompute the light map only if a light source changed or a cell changed since the
+    /// last update now emits or blocks a different amount of light
+    fn update_lighting(&mut self) {
+        let Some(mut light_map) = self.light_map.take() else {
+            return;
+        };
+        let cell_light = |x: usize, y: usize| match &self.grid[y * self.width + x] {
+            Some(particle) => {
+                let material = self.materials.get(particle.material_type);
+                (material.light_emission, material.light_transmission)
+            }
+            None => (0.0, 1.0),
+        };
+        match self.lit_at_tick {
+            Some(tick) => {
+                let changed = self.iter_changed_since(tick).map(|(x, y, _)| (x, y));
+                light_map.refresh(&self.light_sources, changed, cell_light);
+            }
+            None => light_map.compute(&self.light_sources, cell_light),
         }
+        self.light_map = Some(light_map);
+        self.lit_at_tick = Some(self.tick);
+    }
+
+    pub fn compaction(&self) -> &CompactionRules {
+        &self.compaction
+    }
+
+    /// Mutable access to the compaction rules; clear them to disable compaction
+    pub fn compaction_mut(&mut self) -> &mut CompactionRules {
+        &mut self.compaction
     }
     
     // Helper for flat array indexing - inline for performance
@@ -96,11 +548,179 @@ impl Simulation {
         y * self.width + x
     }
 
+    #[inline(always)]
+    fn chunk_index(&self, x: usize, y: usize) -> usize {
+        (y / SIM_CHUNK_SIZE) * self.chunks_x + x / SIM_CHUNK_SIZE
+    }
+
+    /// Mark a changed cell and its neighbours for the next update. Neighbours across a
+    /// chunk border wake the adjacent chunk.
+    pub(crate) fn wake_cell(&mut self, x: usize, y: usize) {
+        let (max_x, max_y) = ((x + 1).min(self.width - 1), (y + 1).min(self.height - 1));
+        for ny in y.saturating_sub(1)..=max_y {
+            for nx in x.saturating_sub(1)..=max_x {
+                self.queue_cell(nx, ny);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn queue_cell(&mut self, x: usize, y: usize) {
+        let index = self.get_index(x, y);
+        if !self.queued[index] {
+            self.queued[index] = true;
+            self.active_particles.push((x, y));
+            let chunk = self.chunk_index(x, y);
+            self.dirty_chunks[chunk].expand(x, y);
+        }
+    }
+
+    /// Put every cell to sleep
+    fn clear_active(&mut self) {
+        for (x, y) in self.active_particles.drain(..) {
+            self.queued[y * self.width + x] = false;
+        }
+        self.dirty_chunks.iter_mut().for_each(DirtyRect::clear);
+    }
+
+    /// Record that a cell's contents changed, for `iter_changed_since`
+    #[inline(always)]
+    pub(crate) fn mark_changed(&mut self, x: usize, y: usize) {
+        let (index, chunk) = (self.get_index(x, y), self.chunk_index(x, y));
+        self.changed_at[index] = self.tick + 1;
+        self.chunk_changed_at[chunk] = self.tick + 1;
+    }
+
+    /// Color a particle is drawn in, from the registry for custom materials
+    fn appearance(&self, particle: &Particle) -> [u8; 3] {
+        match particle.material_type.is_custom() {
+            true => particle.color_with(self.materials.get(particle.material_type)),
+            false => particle.color(),
+        }
+    }
+
+    /// Number of completed updates
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Every particle in row-major order
+    pub fn iter_particles(&self) -> impl Iterator<Item = &Particle> + '_ {
+        self.grid.iter().flatten()
+    }
+
+    /// Particles inside `rect`, clipped to the grid, in row-major order
+    pub fn iter_region(&self, rect: GridRect) -> impl Iterator<Item = &Particle> + '_ {
+        let (x0, x1) = (rect.x.min(self.width), rect.x.saturating_add(rect.width).min(self.width));
+        let (y0, y1) = (rect.y.min(self.height), rect.y.saturating_add(rect.height).min(self.height));
+        (y0..y1).flat_map(move |y| self.grid[y * self.width + x0..y * self.width + x1].iter().flatten())
+    }
+
+    /// 4-connected groups of rigid solid cells (see `Material::is_rigid_solid`), e.g. for
+    /// `RigidBodyOverlay`. The grid has no rigid body physics of its own; these are the
+    /// solid structures it moves cell by cell.
+    pub fn rigid_regions(&self) -> Vec<Vec<(i64, i64)>> {
+        let is_rigid = |index: usize| {
+            self.grid[index].as_ref().is_some_and(|particle| {
+                self.materials.get(particle.material_type).is_rigid_solid(particle.material_type)
+            })
+        };
+        let mut visited = vec![false; self.grid.len()];
+        let mut regions = Vec::new();
+        for start in 0..self.grid.len() {
+            if visited[start] || !is_rigid(start) {
+                continue;
+            }
+            visited[start] = true;
+            let mut region = Vec::new();
+            let mut stack = vec![start];
+            while let Some(index) = stack.pop() {
+                let (x, y) = (index % self.width, index / self.width);
+                region.push((x as i64, y as i64));
+                let neighbors = [
+                    (x > 0).then(|| index - 1),
+                    (x + 1 < self.width).then_some(index + 1),
+                    (y > 0).then(|| index - self.width),
+                    (y + 1 < self.height).then_some(index + self.width),
+                ];
+                for neighbor in neighbors.into_iter().flatten() {
+                    if !visited[neighbor] && is_rigid(neighbor) {
+                        visited[neighbor] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            regions.push(region);
+        }
+        regions
+    }
+
+    /// Cells whose contents were placed, removed, moved or changed material (or, during
+    /// an update, temperature) at or after `tick`, e.g. since a snapshot taken when
+    /// `tick()` returned `tick`. Emptied cells are reported with `None`. Chunks without
+    /// changes are skipped without visiting their cells.
+    pub fn iter_changed_since(&self, tick: u64) -> impl Iterator<Item = (usize, usize, Option<&Particle>)> + '_ {
+        (0..self.chunks_x * self.chunks_y)
+            .filter(move |&chunk| self.chunk_changed_at[chunk] > tick)
+            .flat_map(move |chunk| {
+                let (x0, y0) = ((chunk % self.chunks_x) * SIM_CHUNK_SIZE, (chunk / self.chunks_x) * SIM_CHUNK_SIZE);
+                let (x1, y1) = ((x0 + SIM_CHUNK_SIZE).min(self.width), (y0 + SIM_CHUNK_SIZE).min(self.height));
+                (y0..y1).flat_map(move |y| (x0..x1).map(move |x| (x, y)))
+            })
+            .filter(move |&(x, y)| self.changed_at[y * self.width + x] > tick)
+            .map(move |(x, y)| (x, y, self.get_particle(x, y)))
+    }
+
+    /// Whether the chunk at chunk coordinates (cx, cy) will be updated next tick
+    pub fn is_chunk_awake(&self, cx: usize, cy: usize) -> bool {
+        cx < self.chunks_x && cy < self.chunks_y && self.dirty_chunks[cy * self.chunks_x + cx].is_valid()
+    }
+
+    /// Whether any cell of the chunk at (cx, cy) was written after `tick`
+    pub fn chunk_changed_since(&self, cx: usize, cy: usize, tick: u64) -> bool {
+        cx < self.chunks_x && cy < self.chunks_y && self.chunk_changed_at[cy * self.chunks_x + cx] > tick
+    }
+
+    /// (chunks wide, chunks high) of the update grid
+    pub fn chunk_dimensions(&self) -> (usize, usize) {
+        (self.chunks_x, self.chunks_y)
+    }
+
+    /// Number of chunks the last `update` actually processed
+    pub fn awake_chunk_count(&self) -> usize {
+        self.awake_chunks
+    }
+
+    /// Number of particles the last `update` ran; settled ones nothing disturbed are
+    /// skipped without being visited
+    pub fn updated_particle_count(&self) -> usize {
+        self.updated_particles
+    }
+
+    /// Number of particles on the grid
+    pub fn particle_count(&self) -> usize {
+        self.particle_count
+    }
+
+    /// Number of cells queued for the next `update`
+    pub fn active_cell_count(&self) -> usize {
+        self.active_particles.len()
+    }
+
     pub fn clear(&mut self) {
         self.grid.fill(None);
-        self.dirty_rect.clear();
+        self.clear_active();
+        self.changed_at.fill(self.tick + 1);
+        self.chunk_changed_at.fill(self.tick + 1);
         self.particle_count = 0;
-        self.active_particles.clear();
+        self.voided = 0;
+        self.generators.clear();
+        if let Some(effects) = &mut self.effects {
+            effects.clear();
+        }
+        if let Some(moves) = &mut self.moves {
+            moves.clear();
+        }
     }
 
     pub fn is_valid(&self, x: i32, y: i32) -> bool {
@@ -135,20 +755,15 @@ impl Simulation {
             
             let index = self.get_index(x, y);
             let was_empty = self.grid[index].is_none();
-            let is_dynamic = new_particle.dynamic;
             let old_particle = self.grid[index].replace(new_particle);
             
             if was_empty {
                 self.particle_count += 1;
             }
             
-            // Mark dirty region
-            self.dirty_rect.expand(x, y);
-            
-            // Track active particles if they're dynamic
-            if is_dynamic {
-                self.active_particles.push((x, y));
-            }
+            // Wake this cell and anything resting against it
+            self.wake_cell(x, y);
+            self.mark_changed(x, y);
             
             old_particle
         } else {
@@ -161,7 +776,8 @@ impl Simulation {
             let index = self.get_index(x, y);
             if let Some(particle) = self.grid[index].take() {
                 self.particle_count = self.particle_count.saturating_sub(1);
-                self.dirty_rect.expand(x, y);
+                self.wake_cell(x, y);
+                self.mark_changed(x, y);
                 Some(particle)
             } else {
                 None
@@ -193,101 +809,395 @@ impl Simulation {
                 self.grid[index1] = Some(p2);
             }
             
-            self.dirty_rect.expand(x1, y1);
-            self.dirty_rect.expand(x2, y2);
+            self.wake_cell(x1, y1);
+            self.wake_cell(x2, y2);
+            self.mark_changed(x1, y1);
+            self.mark_changed(x2, y2);
         }
     }
 
     pub fn update(&mut self, delta_time: f32) {
-        // Early exit if no dirty region
-        if !self.dirty_rect.is_valid() {
-            return;
+        self.finish_budgeted_tick();
+        self.step_surroundings(delta_time);
+        let active = self.begin_particle_update(delta_time);
+        self.update_cells(&active, delta_time);
+        self.finish_particle_update(active);
+        self.step_overlays(delta_time);
+    }
+
+    /// Clock, wind, air and weather, which run before the particles each update
+    pub(crate) fn step_surroundings(&mut self, delta_time: f32) {
+        self.step_clock(delta_time);
+        self.wind.step(delta_time);
+        self.step_air(delta_time);
+        self.step_weather(delta_time);
+        self.step_generators(delta_time);
+    }
+
+    /// Effects and lighting, which follow the particles each update
+    pub(crate) fn step_overlays(&mut self, delta_time: f32) {
+        if let Some(mut effects) = self.effects.take() {
+            // Effects fly through flames and gases but not into anything denser
+            effects.step(delta_time, |x, y| {
+                !self.is_valid(x, y) || self.get_particle(x as usize, y as usize).is_some_and(|particle| {
+                    particle.material_type != MaterialType::Fire && !self.is_diffusing_gas(particle.material_type)
+                })
+            });
+            self.effects = Some(effects);
         }
+        self.update_lighting();
+        #[cfg(feature = "export")]
+        self.capture_frame();
+    }
 
-        // Reset processed and moved flags only in dirty region
-        for y in self.dirty_rect.min_y..=self.dirty_rect.max_y.min(self.height - 1) {
-            for x in self.dirty_rect.min_x..=self.dirty_rect.max_x.min(self.width - 1) {
-                let index = self.get_index(x, y);
-                if let Some(particle) = &mut self.grid[index] {
-                    particle.processed = false;
-                    particle.moved_this_step = false;
-                }
+    /// Run the slow passes that are due and take the cells queued for this tick, in the
+    /// order to update them
+    pub(crate) fn begin_particle_update(&mut self, delta_time: f32) -> Vec<(usize, usize)> {
+        // Compaction runs on its own slow clock so settled piles cost nothing per tick
+        self.compaction_timer += delta_time;
+        if self.compaction_timer >= self.compaction.interval {
+            let elapsed = std::mem::take(&mut self.compaction_timer);
+            self.compact_settled_piles(elapsed);
+        }
+        // Plants grow on their own clock too, including in sleeping chunks
+        self.plant_timer += delta_time;
+        if self.plant_timer >= PLANT_GROWTH_INTERVAL {
+            let elapsed = std::mem::take(&mut self.plant_timer);
+            self.grow_plants(elapsed);
+        }
+        if self.biomes.is_some() {
+            self.biome_timer += delta_time;
+            if self.biome_timer >= BIOME_PASS_INTERVAL {
+                let elapsed = std::mem::take(&mut self.biome_timer);
+                self.apply_biomes(elapsed);
+            }
+        }
+        if self.radiation.is_some() {
+            self.radioactivity_timer += delta_time;
+            if self.radioactivity_timer >= RADIOACTIVITY_PASS_INTERVAL {
+                let elapsed = std::mem::take(&mut self.radioactivity_timer);
+                self.apply_radioactivity(elapsed);
             }
         }
 
-        // Shuffle column processing order
-        let mut rng = rand::thread_rng();
-        self.col_order.shuffle(&mut rng);
-        let col_order = self.col_order.clone();
+        if let Some(moves) = &mut self.moves {
+            moves.clear();
+        }
 
-        // Process particles bottom-up, only in dirty region
-        let mut new_dirty_rect = DirtyRect::new();
-        
-        // Process with chunked approach for better performance
-        const CHUNK_SIZE: usize = 16;
-        let dirty_width = self.dirty_rect.max_x.min(self.width - 1) - self.dirty_rect.min_x + 1;
-        let dirty_height = self.dirty_rect.max_y.min(self.height - 1) - self.dirty_rect.min_y + 1;
-        
-        // Process in chunks to improve cache locality
-        for chunk_y in (0..((dirty_height + CHUNK_SIZE - 1) / CHUNK_SIZE)).rev() {
-            for chunk_x in 0..((dirty_width + CHUNK_SIZE - 1) / CHUNK_SIZE) {
-                let start_x = self.dirty_rect.min_x + chunk_x * CHUNK_SIZE;
-                let end_x = (start_x + CHUNK_SIZE).min(self.dirty_rect.max_x.min(self.width - 1) + 1);
-                let start_y = self.dirty_rect.min_y + chunk_y * CHUNK_SIZE;
-                let end_y = (start_y + CHUNK_SIZE).min(self.dirty_rect.max_y.min(self.height - 1) + 1);
-                
-                for y in (start_y..end_y).rev() {
-                    for &x in &col_order {
-                        if x >= start_x && x < end_x {
-                            let index = self.get_index(x, y);
-                            if let Some(particle) = self.grid[index].take() {
-                                if !particle.processed && particle.material_type != MaterialType::Empty {
-                                    // Skip processing for static particles that are settled
-                                    if !particle.dynamic && particle.settled_frames > 30 {
-                                        self.grid[index] = Some(particle);
-                                        continue;
-                                    }
-                                    
-                                    let updated_particle = self.update_particle(particle, delta_time);
-                                    if let Some(updated) = updated_particle {
-                                        if updated.material_type != MaterialType::Empty {
-                                            let new_x = updated.x;
-                                            let new_y = updated.y;
-                                            let new_index = self.get_index(new_x, new_y);
-                                            self.grid[new_index] = Some(updated);
-                                            
-                                            // Track new dirty region
-                                            new_dirty_rect.expand(new_x, new_y);
-                                            if new_x != x || new_y != y {
-                                                new_dirty_rect.expand(x, y); // Mark old position as dirty too
-                                            }
-                                        }
-                                    }
-                                } else {
-                                    self.grid[index] = Some(particle);
-                                }
-                            }
+        // Cells woken this tick are queued for the next one
+        let mut active = std::mem::take(&mut self.active_particles);
+        for &(x, y) in &active {
+            self.queued[y * self.width + x] = false;
+        }
+        let dirty_chunks = std::mem::replace(&mut self.dirty_chunks, vec![DirtyRect::new(); self.chunks_x * self.chunks_y]);
+        self.awake_chunks = dirty_chunks.iter().filter(|rect| rect.is_valid()).count();
+        self.updated_particles = 0;
+        if active.is_empty() {
+            return active;
+        }
+
+        // Reset processed and moved flags only on queued cells
+        for &(x, y) in &active {
+            let index = self.get_index(x, y);
+            if let Some(particle) = &mut self.grid[index] {
+                particle.processed = false;
+                particle.moved_this_step = false;
+            }
+        }
+
+        // Process chunks bottom-up so falling material lands before it is revisited,
+        // each one row by row from the bottom in a shuffled column order
+        self.col_order.shuffle(&mut self.rng);
+        let mut column_rank = vec![0; self.width];
+        for (rank, &x) in self.col_order.iter().enumerate() {
+            column_rank[x] = rank;
+        }
+        active.sort_unstable_by_key(|&(x, y)| {
+            (std::cmp::Reverse(y / SIM_CHUNK_SIZE), x / SIM_CHUNK_SIZE, std::cmp::Reverse(y), column_rank[x])
+        });
+        self.defer_distant_cells(&mut active);
+        active
+    }
+
+    /// Update the particles in `cells`, in order, waking whatever they disturb
+    pub(crate) fn update_cells(&mut self, cells: &[(usize, usize)], delta_time: f32) {
+        for &(x, y) in cells {
+            let index = self.get_index(x, y);
+            if let Some(particle) = self.grid[index].take() {
+                if !particle.processed && particle.material_type != MaterialType::Empty {
+                    // Skip processing for static particles that are settled
+                    if !particle.dynamic && particle.settled_frames > 30 && particle.stress == 0.0 {
+                        self.grid[index] = Some(particle);
+                        continue;
+                    }
+
+                    self.updated_particles += 1;
+                    let (material_before, temp_before) = (particle.material_type, particle.temp);
+                    let color_before = self.appearance(&particle);
+                    // A cell that skipped its temperature stays queued to catch up next tick
+                    let skipped_temperature = self.skips_temperature_at(x, y);
+                    let updated_particle = self.update_particle(particle, self.cell_delta(x, y, delta_time));
+                    let Some(updated) = updated_particle.filter(|updated| updated.material_type != MaterialType::Empty) else {
+                        // Expired, dissolved, fell out of the world or blew up
+                        self.particle_count = self.particle_count.saturating_sub(1);
+                        self.wake_cell(x, y);
+                        self.mark_changed(x, y);
+                        continue;
+                    };
+                    let (new_x, new_y) = (updated.x, updated.y);
+                    let changed = updated.material_type != material_before
+                        || (updated.temp - temp_before).abs() > SLEEP_TEMP_EPSILON;
+                    let active = changed || skipped_temperature || self.keeps_chunk_awake(&updated);
+                    let material = updated.material_type;
+                    let new_index = self.get_index(new_x, new_y);
+                    // A particle it sank into or swapped with takes the cell it left
+                    if (new_x, new_y) != (x, y) && self.grid[new_index].is_some() {
+                        self.displace((new_x, new_y), (x, y));
+                    }
+                    self.grid[new_index] = Some(updated);
+
+                    if new_y > y && self.effects.is_some() {
+                        self.splash_onto_water(new_x, new_y, material);
+                    }
+                    if new_x != x || new_y != y {
+                        if let Some(moves) = &mut self.moves {
+                            moves.push(ParticleMove { from: (x, y), to: (new_x, new_y) });
+                        }
+                        // The vacated cell may let neighbours move too
+                        self.wake_cell(new_x, new_y);
+                        self.wake_cell(x, y);
+                        self.mark_changed(new_x, new_y);
+                        self.mark_changed(x, y);
+                    } else {
+                        if active {
+                            self.wake_cell(x, y);
+                        }
+                        // Drifting temperature, flicker and fading change how a cell looks
+                        // without changing what is in it
+                        let recolored = self.get_particle(x, y).is_some_and(|updated| self.appearance(updated) != color_before);
+                        if changed || recolored {
+                            self.mark_changed(x, y);
                         }
                     }
+                } else {
+                    self.grid[index] = Some(particle);
                 }
             }
         }
+    }
+
+    /// Count the tick, handing back the buffer `begin_particle_update` took so the next
+    /// one queues into it without reallocating
+    pub(crate) fn finish_particle_update(&mut self, mut active: Vec<(usize, usize)>) {
+        active.clear();
+        active.append(&mut self.active_particles);
+        self.active_particles = active;
+        self.tick += 1;
+    }
+
+    /// A simulation over rows `top..bottom` of this one, with its rules, subsystems and a
+    /// copy of the cells, for `update_parallel` to update one band in; `merge_band`
+    /// writes it back. `top` must be a multiple of `WIND_CELL_SIZE`.
+    #[cfg(feature = "parallel")]
+    pub(crate) fn band(&self, top: usize, bottom: usize, seed: u64) -> Simulation {
+        let (width, height) = (self.width, bottom - top);
+        let grid: Vec<_> = self.grid[top * width..bottom * width].iter()
+            .map(|cell| cell.clone().map(|mut particle| {
+                particle.y -= top;
+                particle
+            }))
+            .collect();
+        let particle_count = grid.iter().flatten().count();
+        let chunks = self.chunks_x * height.div_ceil(SIM_CHUNK_SIZE);
+        let mut physics = PhysicsState::with_materials(width, height, Arc::clone(&self.materials));
+        physics.set_ambient_temp(self.physics.ambient_temp());
+        let (focus_x, focus_y) = self.quality_focus();
+        Simulation {
+            width,
+            height,
+            grid,
+            dirty_chunks: vec![DirtyRect::new(); chunks],
+            chunks_x: self.chunks_x,
+            chunks_y: height.div_ceil(SIM_CHUNK_SIZE),
+            awake_chunks: 0,
+            updated_particles: 0,
+            tick: self.tick,
+            changed_at: vec![0; width * height],
+            chunk_changed_at: vec![0; chunks],
+            col_order: Vec::new(),
+            physics,
+            materials: Arc::clone(&self.materials),
+            reactions: Arc::clone(&self.reactions),
+            interactions: Arc::clone(&self.interactions),
+            compaction: self.compaction.clone(),
+            compaction_timer: 0.0,
+            plants: Arc::clone(&self.plants),
+            plant_timer: 0.0,
+            gravity: self.gravity,
+            bounds: self.bounds,
+            voided: 0,
+            generators: HashMap::new(),
+            disabled_materials: self.disabled_materials.clone(),
+            particle_count,
+            active_particles: Vec::new(),
+            queued: vec![false; width * height],
+            light_map: None,
+            light_sources: Vec::new(),
+            lit_at_tick: None,
+            effects: self.effects.as_ref().map(|_| EffectLayer::with_seed(seed)),
+            events: self.events.as_ref().map(|_| EventBus::new()),
+            moves: self.moves.as_ref().map(|_| Vec::new()),
+            wind: self.wind.rows(top, bottom),
+            air: self.air.as_ref().map(|air| air.rows(top, bottom)),
+            radiation: None,
+            radioactivity_timer: 0.0,
+            quality: self.quality,
+            quality_focus: Some((focus_x, focus_y - top as i64)),
+            budgeted: None,
+            weather: None,
+            clock: None,
+            biomes: None,
+            biome_timer: 0.0,
+            #[cfg(feature = "export")]
+            timelapse: None,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Write back a band taken with `band(top, ..)`: its cells, what it changed and woke,
+    /// and what it emitted, all moved down to `top`
+    #[cfg(feature = "parallel")]
+    pub(crate) fn merge_band(&mut self, band: Simulation, top: usize) {
+        let start = top * self.width;
+        let before = self.grid[start..start + band.grid.len()].iter().flatten().count();
+        self.particle_count = self.particle_count - before + band.particle_count;
+        for (cell, particle) in self.grid[start..].iter_mut().zip(band.grid) {
+            *cell = particle.map(|mut particle| {
+                particle.y += top;
+                particle
+            });
+        }
+        for (index, &changed_at) in band.changed_at.iter().enumerate() {
+            if changed_at > self.tick {
+                self.mark_changed(index % self.width, top + index / self.width);
+            }
+        }
+        for &(x, y) in &band.active_particles {
+            // Neighbours past the band's edge were out of its reach
+            let edge = (y == 0 && top > 0) || (y + 1 == band.height && top + band.height < self.height);
+            if edge {
+                self.wake_cell(x, top + y);
+            } else {
+                self.queue_cell(x, top + y);
+            }
+        }
+        self.updated_particles += band.updated_particles;
+        self.voided += band.voided;
+
+        if let (Some(moves), Some(band_moves)) = (&mut self.moves, band.moves) {
+            moves.extend(band_moves.into_iter().map(|step| ParticleMove {
+                from: (step.from.0, step.from.1 + top),
+                to: (step.to.0, step.to.1 + top),
+            }));
+        }
+        if let (Some(events), Some(mut band_events)) = (&mut self.events, band.events) {
+            for event in band_events.drain() {
+                events.emit(event.translated(0, top as i64));
+            }
+        }
+        if let (Some(effects), Some(band_effects)) = (&mut self.effects, band.effects) {
+            effects.absorb(band_effects, top as f32);
+        }
+        self.wind.set_rows(top, &band.wind);
+        if let (Some(air), Some(band_air)) = (&mut self.air, &band.air) {
+            air.set_rows(top, band_air);
+        }
+    }
+
+    /// Move the particle at `from` into the empty cell `to` on behalf of the particle
+    /// taking its place. It counts as processed, so it isn't updated a second time this
+    /// tick after being pushed into a cell the loop has yet to reach.
+    pub(crate) fn displace(&mut self, from: (usize, usize), to: (usize, usize)) {
+        let from_index = self.get_index(from.0, from.1);
+        let Some(mut particle) = self.grid[from_index].take() else {
+            return;
+        };
+        particle.x = to.0;
+        particle.y = to.1;
+        particle.processed = true;
+        particle.moved_this_step = true;
+        particle.settled_frames = 0;
+        particle.invalidate_color_cache();
+        let to_index = self.get_index(to.0, to.1);
+        self.grid[to_index] = Some(particle);
+        if let Some(moves) = &mut self.moves {
+            moves.push(ParticleMove { from, to });
+        }
+        self.wake_cell(from.0, from.1);
+        self.wake_cell(to.0, to.1);
+        self.mark_changed(from.0, from.1);
+        self.mark_changed(to.0, to.1);
+    }
 
-        // Update dirty rectangle for next frame
-        self.dirty_rect = new_dirty_rect;
+    /// Whether a particle that stayed put still needs updating next tick
+    fn keeps_chunk_awake(&self, particle: &Particle) -> bool {
+        if particle.settled_frames < SLEEP_SETTLED_FRAMES
+            || particle.has_velocity()
+            || particle.life.is_some()
+            || particle.burning
+            || physics::changes_moisture(particle)
+        {
+            return true;
+        }
+
+        // Hanging over an empty cell, e.g. held up only by skipped gravity rolls
+        let props = self.materials.get(particle.material_type);
+        let below = self.offset_cell(particle.x, particle.y, 0, if props.density < 0.0 { -1 } else { 1 });
+        let falls = props.is_powder(particle.material_type) || props.is_liquid(particle.material_type) || props.density < 0.0;
+        falls && below.is_some_and(|(x, y)| self.get_particle(x, y).is_none())
     }
 
     fn update_particle(&mut self, mut particle: Particle, delta_time: f32) -> Option<Particle> {
         particle.processed = true;
         let (x, y) = (particle.x, particle.y);
+        if particle.material_type == MaterialType::Fire {
+            self.wind.add_updraft(x, y, delta_time);
+        }
+        if self.effects.is_some() {
+            self.emit_effects(&particle, delta_time);
+        }
+        // Flames and burning fuel use up the air around them; starved flames die out
+        let oxygen = if particle.material_type == MaterialType::Fire || particle.burning {
+            self.breathe(&particle, delta_time)
+        } else {
+            1.0
+        };
+        if particle.material_type == MaterialType::Fire && oxygen < FIRE_MIN_OXYGEN {
+            particle.life = Some(0.0);
+        }
 
-        // 1. Handle lifespan and burnout
-        if let Some(new_particle) = self.physics.handle_lifespan_and_burnout(&mut particle, delta_time) {
+        // 1. Handle lifespan and burnout; gas fades faster the thinner it is
+        let life_delta = if self.is_diffusing_gas(particle.material_type) {
+            delta_time * self.gas_dissipation_scale(&particle)
+        } else {
+            delta_time
+        };
+        let life_before = particle.life;
+        let expired = self.physics.handle_lifespan_and_burnout(&mut particle, life_delta);
+        if let Some(staged) = self.apply_stages(&mut particle, life_before) {
+            return Some(staged);
+        }
+        if let Some(new_particle) = expired {
             return Some(new_particle);
         }
 
+        // Pairwise reactions from the reaction table
+        if let Some(reacted) = self.apply_reactions(&particle, delta_time) {
+            return Some(reacted);
+        }
+
         // Dynamic flag optimization: skip expensive physics for static particles
-        let skip_physics = !particle.dynamic && particle.settled_frames > 10;
+        let skip_physics = !particle.dynamic && particle.settled_frames > 10 && !particle.burning && particle.stress == 0.0;
 
         let (state_change_result, new_particles) = if skip_physics {
             // Just increment time for static particles
@@ -295,38 +1205,84 @@ impl Simulation {
             particle.settled_frames = particle.settled_frames.saturating_add(1);
             (None, Vec::new())
         } else {
-            // 2. Get neighbors for temperature and state change calculations
-            let neighbors = self.get_neighbors(x, y);
+            self.wake_brittle_neighbors(&particle);
+            let temp_before = particle.temp;
 
-            // 3. Update temperature
-            self.physics.update_temperature(&mut particle, &neighbors, delta_time);
+            // Very hot materials heat cells within a radius before conducting to neighbours
+            self.radiate_heat(&mut particle, delta_time);
 
-            // 4. Handle state changes and effects
-            self.physics.handle_state_changes_and_effects(&mut particle, &neighbors, delta_time)
-        };
-        
-        // Place new particles from effects
+            // 2. Get neighbors for temperature and state change calculations
+            let neighbors = neighbors_in(&self.grid, self.width, self.height, x, y);
+            // Powders touching water look damp
+            if self.materials.get(particle.material_type).is_powder(particle.material_type) {
+                particle.set_wet(neighbors.iter().flatten().any(|n| matches!(n.material_type, MaterialType::Water | MaterialType::SaltWater)));
+            }
+
+            // 3. Update temperature, across the edge of a wrapped world but not into the void
+            let edge_neighbors = self.edge_thermal_neighbors(x, y);
+            let thermal_delta = self.thermal_delta(x, y, delta_time);
+            if thermal_delta > 0.0 {
+                self.physics.update_temperature(&mut particle, edge_neighbors.as_deref().unwrap_or(&neighbors), thermal_delta);
+            }
+            if let Some(debris) = self.physics.accumulate_thermal_stress(&mut particle, temp_before, delta_time) {
+                return Some(debris);
+            }
+
+            // Explosives that ignite leave nothing behind but the blast
+            if let Some(radius) = self.physics.detonation_radius(&particle, &neighbors) {
+                self.explode(x, y, radius);
+                return None;
+            }
+
+            // 4. Burn fuel that is alight, otherwise handle state changes and effects
+            if particle.burning && particle.material_type != MaterialType::Fuse {
+                self.physics.burn(&mut particle, &neighbors, oxygen, delta_time, &mut self.rng)
+            } else {
+                let result = self.physics.handle_state_changes_and_effects(&mut particle, &neighbors, delta_time, &mut self.rng);
+                // Fuel can't catch where there is no air to burn
+                if particle.burning && particle.material_type != MaterialType::Fuse && self.oxygen_around(x, y) < FIRE_MIN_OXYGEN {
+                    particle.burning = false;
+                    particle.life = None;
+                    particle.invalidate_color_cache();
+                }
+                result
+            }
+        };
+        
+        // Place new particles from effects
         for (nx, ny, new_particle) in new_particles {
-            if nx < self.width && ny < self.height {
-                let index = self.get_index(nx, ny);
-                self.grid[index] = Some(new_particle);
+            if new_particle.material_type == MaterialType::Empty {
+                self.remove_particle(nx, ny);
+            } else {
+                self.set_particle(nx, ny, new_particle);
             }
         }
 
         if let Some(new_particle) = state_change_result {
+            if new_particle.material_type == MaterialType::Steam && particle.material_type != MaterialType::Steam {
+                self.emit_event(SimulationEvent::SteamHiss { x: x as i64, y: y as i64 });
+            }
             return Some(new_particle);
         }
 
         // 5. Increment time in state
         particle.time_in_state += delta_time;
 
-        // 6. Handle movement
-        let (new_x, new_y) = self.handle_movement(&mut particle);
+        // 6. Handle movement; an impulse overrides the cellular rules while it lasts
+        if self.falls_out(&particle) {
+            self.void_particle(x, y);
+            return None;
+        }
+        let (new_x, new_y) = match self.handle_velocity(&mut particle, delta_time) {
+            Some(position) => position,
+            None => self.handle_movement(&mut particle),
+        };
         particle.x = new_x;
         particle.y = new_y;
         if new_x != x || new_y != y {
             particle.moved_this_step = true;
             particle.settled_frames = 0; // Reset settled counter when moving
+            particle.buried_time = 0.0;
         } else {
             particle.settled_frames = particle.settled_frames.saturating_add(1);
             // If particle becomes static, it might be removed from active tracking
@@ -338,29 +1294,495 @@ impl Simulation {
         Some(particle)
     }
 
-    fn get_neighbors(&self, x: usize, y: usize) -> Vec<Option<&Particle>> {
-        let mut neighbors = Vec::with_capacity(8);
+    /// Sparks above flames and embers above lava open to the air
+    fn emit_effects(&mut self, particle: &Particle, delta_time: f32) {
+        let chance = match particle.material_type {
+            MaterialType::Fire => SPARK_CHANCE_PER_SEC,
+            MaterialType::Lava => EMBER_CHANCE_PER_SEC,
+            _ => return,
+        } * delta_time;
+        let (x, y) = (particle.x, particle.y);
+        if y == 0 || self.get_particle(x, y - 1).is_some() {
+            return;
+        }
+        let Some(effects) = &mut self.effects else {
+            return;
+        };
+        if effects.roll(chance) {
+            let (ex, ey) = (x as f32 + 0.5, y as f32 - 0.5);
+            if particle.material_type == MaterialType::Fire {
+                effects.spark(ex, ey);
+            } else {
+                effects.ember(ex, ey);
+            }
+        }
+    }
 
+    /// Throw up droplets when a particle falling to `(x, y)` lands on water
+    fn splash_onto_water(&mut self, x: usize, y: usize, material: MaterialType) {
+        let onto_water = self.get_particle(x, y + 1).is_some_and(|p| p.material_type == MaterialType::Water);
+        if !onto_water || y == 0 || self.materials.get(material).is_liquid(material) {
+            return;
+        }
+        let Some(effects) = &mut self.effects else {
+            return;
+        };
+        if effects.roll(SPLASH_CHANCE) {
+            effects.splash(x as f32 + 0.5, y as f32 - 0.5, 3);
+        }
+    }
+
+    /// Settled solids skip temperature updates, so wake brittle neighbours much hotter or
+    /// colder than `particle` for long enough to feel the swing and build up stress
+    fn wake_brittle_neighbors(&mut self, particle: &Particle) {
+        let materials = Arc::clone(&self.materials);
         for &(dx, dy) in &NEIGHBOR_OFFSETS {
-            let nx = x as i32 + dx;
-            let ny = y as i32 + dy;
+            let (nx, ny) = (particle.x as i32 + dx, particle.y as i32 + dy);
+            if !self.is_valid(nx, ny) {
+                continue;
+            }
+            if let Some(neighbor) = self.get_particle_mut(nx as usize, ny as usize) {
+                if neighbor.settled_frames > 0
+                    && (neighbor.temp - particle.temp).abs() > THERMAL_SHOCK_WAKE_DIFFERENCE
+                    && materials.get(neighbor.material_type).cracks_into.is_some()
+                {
+                    neighbor.settled_frames = 0;
+                    self.wake_cell(nx as usize, ny as usize);
+                }
+            }
+        }
+    }
+
+    /// Convert long-buried powders into their compressed variants. Column loads are
+    /// recomputed from scratch each pass, but only blocks where every particle is asleep
+    /// accumulate burial time; anything in an active block starts over.
+    fn compact_settled_piles(&mut self, elapsed: f32) {
+        if self.compaction.is_empty() {
+            return;
+        }
+
+        let blocks_x = self.width.div_ceil(COMPACTION_BLOCK_SIZE);
+        let blocks_y = self.height.div_ceil(COMPACTION_BLOCK_SIZE);
+        let mut asleep = vec![true; blocks_x * blocks_y];
+        for (index, cell) in self.grid.iter().enumerate() {
+            if let Some(particle) = cell {
+                if particle.settled_frames < COMPACTION_SETTLED_FRAMES || particle.has_velocity() {
+                    let (x, y) = (index % self.width, index / self.width);
+                    asleep[(y / COMPACTION_BLOCK_SIZE) * blocks_x + x / COMPACTION_BLOCK_SIZE] = false;
+                }
+            }
+        }
+        if !asleep.contains(&true) {
+            return;
+        }
+
+        let materials = Arc::clone(&self.materials);
+        for x in 0..self.width {
+            // Summed density of the unbroken column above the current cell
+            let mut load = 0.0;
+            for y in 0..self.height {
+                let index = self.get_index(x, y);
+                let Some(particle) = &mut self.grid[index] else {
+                    load = 0.0;
+                    continue;
+                };
+                let density = materials.get(particle.material_type).density;
+                let block_asleep = asleep[(y / COMPACTION_BLOCK_SIZE) * blocks_x + x / COMPACTION_BLOCK_SIZE];
+
+                if let Some(rule) = self.compaction.get(particle.material_type) {
+                    if block_asleep && load >= rule.min_load {
+                        particle.buried_time += elapsed;
+                        if particle.buried_time >= rule.min_seconds {
+                            let product = materials.create_particle(x, y, rule.product, Some(particle.temp));
+                            self.grid[index] = Some(product);
+                            self.wake_cell(x, y);
+                            self.mark_changed(x, y);
+                        }
+                    } else {
+                        particle.buried_time = 0.0;
+                    }
+                }
+
+                // Gases don't press down; a gap means the column above is supported elsewhere
+                load = if density > 0.0 { load + density } else { 0.0 };
+            }
+        }
+    }
+
+    /// Roll the reaction table against the particle's neighbours. On success the neighbour
+    /// is replaced in place and the reactant's product is returned.
+    fn apply_reactions(&mut self, particle: &Particle, delta_time: f32) -> Option<Particle> {
+        let (x, y) = (particle.x, particle.y);
+        let mut neighbors = [None; 8];
+        for (neighbor, &(dx, dy)) in neighbors.iter_mut().zip(&NEIGHBOR_OFFSETS) {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
             if self.is_valid(nx, ny) {
-                neighbors.push(self.get_particle(nx as usize, ny as usize));
+                *neighbor = self.get_particle(nx as usize, ny as usize).map(|p| p.material_type);
+            }
+        }
+
+        let outcome = self.reactions.try_react(particle.material_type, &neighbors, delta_time, &mut self.rng)?;
+        let products = [Some(outcome.product), outcome.neighbor_product, outcome.burst.map(|(material, _)| material)];
+        if products.contains(&Some(MaterialType::Steam)) {
+            self.emit_event(SimulationEvent::SteamHiss { x: x as i64, y: y as i64 });
+        }
+
+        if let Some(neighbor_product) = outcome.neighbor_product {
+            let (dx, dy) = NEIGHBOR_OFFSETS[outcome.neighbor_index];
+            let (nx, ny) = ((x as i32 + dx) as usize, (y as i32 + dy) as usize);
+            if neighbor_product == MaterialType::Empty {
+                self.remove_particle(nx, ny);
             } else {
-                neighbors.push(None);
+                let neighbor_temp = self.get_particle(nx, ny).map_or(particle.temp, |p| p.temp);
+                let product = self.materials.create_particle(nx, ny, neighbor_product, Some(neighbor_temp + outcome.heat_delta));
+                self.set_particle(nx, ny, product);
+            }
+        }
+
+        if let Some((material, _)) = outcome.burst {
+            let (width, height) = (self.width as i32, self.height as i32);
+            let targets = outcome.burst_targets(&mut self.rng, |index| {
+                let (nx, ny) = (x as i32 + NEIGHBOR_OFFSETS[index].0, y as i32 + NEIGHBOR_OFFSETS[index].1);
+                neighbors[index].is_none() && (0..width).contains(&nx) && (0..height).contains(&ny)
+            });
+            for index in targets {
+                let (dx, dy) = NEIGHBOR_OFFSETS[index];
+                let (nx, ny) = ((x as i32 + dx) as usize, (y as i32 + dy) as usize);
+                let burst = self.materials.create_particle(nx, ny, material, Some(particle.temp + outcome.heat_delta));
+                self.set_particle(nx, ny, burst);
             }
         }
 
-        neighbors
+        Some(self.materials.create_particle(x, y, outcome.product, Some(particle.temp + outcome.heat_delta)))
+    }
+
+    /// Fire the staged reactions the particle's life ran down to this tick, in order. Returns
+    /// what the particle turned into when a stage replaced it.
+    fn apply_stages(&mut self, particle: &mut Particle, life_before: Option<f32>) -> Option<Particle> {
+        let due: Vec<StagedReaction> = self.reactions.due_stages(particle.material_type, life_before, particle.life).copied().collect();
+        let (x, y) = (particle.x, particle.y);
+        for stage in due {
+            let product = match stage.effect {
+                StageEffect::Launch { vx, vy } => {
+                    particle.apply_impulse(vx, vy);
+                    continue;
+                }
+                StageEffect::Become(product) => product,
+                StageEffect::Burst { material, count, speed, product } => {
+                    self.burst_outwards(particle, material, count, speed);
+                    product
+                }
+            };
+            return Some(self.materials.create_particle(x, y, product, Some(particle.temp)));
+        }
+        None
+    }
+
+    /// Throw `count` particles of `material` outwards from `particle` in evenly spread
+    /// directions, into the empty cells `STAGE_BURST_RADIUS` away
+    fn burst_outwards(&mut self, particle: &Particle, material: MaterialType, count: usize, speed: f32) {
+        let spin = self.rng.gen::<f32>() * std::f32::consts::TAU;
+        for i in 0..count {
+            let angle = spin + std::f32::consts::TAU * i as f32 / count as f32;
+            let (dx, dy) = (angle.cos(), angle.sin());
+            let target = (particle.x as i64 + (dx * STAGE_BURST_RADIUS).round() as i64, particle.y as i64 + (dy * STAGE_BURST_RADIUS).round() as i64);
+            let Some((nx, ny)) = self.bounded_cell(target.0, target.1).filter(|&(nx, ny)| self.get_particle(nx, ny).is_none()) else {
+                continue;
+            };
+            let mut thrown = self.materials.create_particle(nx, ny, material, Some(particle.temp));
+            thrown.payload = particle.payload;
+            thrown.apply_impulse(dx * speed, dy * speed);
+            self.set_particle(nx, ny, thrown);
+        }
+    }
+
+    /// Move along the particle's impulse velocity until blocked. Returns None when the
+    /// particle has no velocity or could not move, leaving it to the normal movement rules.
+    fn handle_velocity(&mut self, particle: &mut Particle, delta_time: f32) -> Option<(usize, usize)> {
+        if !particle.has_velocity() {
+            return None;
+        }
+
+        let dt_scale = delta_time * 60.0;
+        let dx = (particle.vx * dt_scale).clamp(-MAX_VELOCITY, MAX_VELOCITY);
+        let dy = (particle.vy * dt_scale).clamp(-MAX_VELOCITY, MAX_VELOCITY);
+        let steps = dx.abs().max(dy.abs()).round().max(1.0) as usize;
+
+        let (start_x, start_y) = (particle.x, particle.y);
+        let (mut x, mut y) = (start_x, start_y);
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            let nx = (start_x as f32 + dx * t).round() as i64;
+            let ny = (start_y as f32 + dy * t).round() as i64;
+            let cell = self.bounded_cell(nx, ny);
+            if cell.is_none() && self.bounds == WorldBounds::Void {
+                // Keeps going, so it falls out next tick
+                break;
+            }
+            let Some((nx, ny)) = cell.filter(|&(nx, ny)| self.get_particle(nx, ny).is_none()) else {
+                // Lose the blocked component of the impulse
+                if nx != x as i64 {
+                    particle.vx = 0.0;
+                }
+                if ny != y as i64 {
+                    particle.vy = 0.0;
+                }
+                break;
+            };
+            (x, y) = (nx, ny);
+        }
+
+        particle.vx *= VELOCITY_DAMPING;
+        particle.vy *= VELOCITY_DAMPING;
+        if particle.vx.abs() < MIN_VELOCITY && particle.vy.abs() < MIN_VELOCITY {
+            particle.vx = 0.0;
+            particle.vy = 0.0;
+        }
+
+        ((x, y) != (start_x, start_y)).then_some((x, y))
+    }
+
+    /// Detonate a blast at `(x, y)`: solids it is strong enough to break shatter into
+    /// debris, loose particles are flung outwards, everything in range is heated and
+    /// empty cells near the centre fill with fire and smoke.
+    pub fn explode(&mut self, x: usize, y: usize, radius: f32) -> ExplosionReport {
+        let explosion = Explosion::new(x as i64, y as i64, radius);
+        self.wind.gust(x, y, radius);
+        self.emit_event(SimulationEvent::ExplosionAt { x: x as i64, y: y as i64, radius });
+        let materials = Arc::clone(&self.materials);
+        let cells = explosion.blast(|px, py| {
+            self.bounded_cell(px, py)
+                .and_then(|(px, py)| self.get_particle(px, py))
+                .map_or(0.0, |p| materials.get(p.material_type).density)
+        });
+
+        let mut report = ExplosionReport::default();
+        for cell in cells {
+            let Some((px, py)) = self.bounded_cell(cell.x, cell.y) else {
+                continue;
+            };
+            let Some(particle) = self.get_particle(px, py) else {
+                if let Some(spawned) = explosion::blast_particle(px, py, cell.strength, &mut self.rng) {
+                    self.set_particle(px, py, spawned);
+                    report.spawned += 1;
+                }
+                continue;
+            };
+
+            let material_type = particle.material_type;
+            let material = materials.get(material_type);
+            let mut struck = particle.clone();
+            struck.temp += cell.strength * BLAST_HEAT;
+            let mut hit = if damage::blast_breaks(&mut struck, material, cell.strength) {
+                report.broken += 1;
+                materials.create_particle(px, py, damage::rubble_of(material_type, material), Some(struck.temp))
+            } else if material.is_stationary(material_type) {
+                self.set_particle(px, py, struck);
+                continue;
+            } else {
+                report.pushed += 1;
+                struck
+            };
+            let (vx, vy) = cell.impulse();
+            hit.apply_impulse(vx, vy);
+            self.set_particle(px, py, hit);
+        }
+        report
+    }
+
+    /// Take `amount` points of health from the solid at `(x, y)`, e.g. from a tool or
+    /// weapon, turning it into rubble once it has none left
+    pub fn damage(&mut self, x: usize, y: usize, amount: f32) -> DamageOutcome {
+        let Some(mut particle) = self.get_particle(x, y).cloned() else {
+            return DamageOutcome::Immune;
+        };
+        let materials = Arc::clone(&self.materials);
+        let material = materials.get(particle.material_type);
+        let outcome = damage::apply_damage(&mut particle, material, amount);
+        match outcome {
+            DamageOutcome::Immune => {}
+            DamageOutcome::Damaged => {
+                self.set_particle(x, y, particle);
+            }
+            DamageOutcome::Broken => {
+                let rubble = damage::rubble_of(particle.material_type, material);
+                self.set_particle(x, y, materials.create_particle(x, y, rubble, Some(particle.temp)));
+            }
+        }
+        outcome
+    }
+
+    /// Run every tile entity for one step, sampling the particles around it, apply the
+    /// effects it produces to the grid and move liquid through pipe networks
+    pub fn update_tile_entities(&mut self, tile_entities: &mut TileEntityManager, delta_time: f32) -> TileEffectReport {
+        tile_entities.sample_ambient(self);
+        tile_entities.update_signals(self);
+        let materials = Arc::clone(&self.materials);
+        let effects = tile_entities.update_all(delta_time, &materials, |(x, y)| {
+            NEIGHBOR_OFFSETS.iter()
+                .filter_map(|&(dx, dy)| {
+                    let (nx, ny) = (x + dx as i64, y + dy as i64);
+                    let (cx, cy) = self.grid_cell(nx, ny)?;
+                    self.get_particle(cx, cy).map(|particle| (nx, ny, particle.clone()))
+                })
+                .collect()
+        });
+        let mut report = self.apply_tile_entity_effects(&effects, delta_time);
+        let fluids = tile_entities.update_fluids(delta_time, self);
+        report.pumped = fluids.pumped;
+        report.released = fluids.released;
+        report
+    }
+
+    /// Apply tile entity effects: heat sources warm the particles around them, spawns
+    /// fill the nearest empty cells, conversions transmute the nearest matching cells and
+    /// explosions detonate. Light comes from `LightSource::from_tile_entities` instead,
+    /// and effects outside the grid are dropped.
+    pub fn apply_tile_entity_effects(&mut self, effects: &[TileEntityEffect], delta_time: f32) -> TileEffectReport {
+        let mut report = TileEffectReport::default();
+        for effect in effects {
+            match *effect {
+                TileEntityEffect::HeatGeneration { position, heat_amount, radius } => {
+                    report.heated += TileEntityEffect::apply_heat(self, position, heat_amount, radius, delta_time);
+                }
+                TileEntityEffect::ParticleSpawn { position, material, amount } => {
+                    let mut remaining = amount as usize;
+                    for (dx, dy) in TileEntityEffect::offsets_by_distance(EFFECT_SEARCH_RADIUS) {
+                        if remaining == 0 {
+                            break;
+                        }
+                        let Some((x, y)) = self.grid_cell(position.0 + dx, position.1 + dy) else {
+                            continue;
+                        };
+                        if self.get_particle(x, y).is_none() && self.add_particle(x, y, material, None) {
+                            remaining -= 1;
+                            report.spawned += 1;
+                        }
+                    }
+                }
+                TileEntityEffect::MaterialConversion { position, from_material, to_material, amount } => {
+                    let mut remaining = amount as usize;
+                    for (dx, dy) in TileEntityEffect::offsets_by_distance(EFFECT_SEARCH_RADIUS) {
+                        if remaining == 0 {
+                            break;
+                        }
+                        let Some((x, y)) = self.grid_cell(position.0 + dx, position.1 + dy) else {
+                            continue;
+                        };
+                        let Some(particle) = self.get_particle(x, y).filter(|p| p.material_type == from_material) else {
+                            continue;
+                        };
+                        let converted = self.materials.create_particle(x, y, to_material, Some(particle.temp));
+                        self.set_particle(x, y, converted);
+                        remaining -= 1;
+                        report.converted += 1;
+                    }
+                }
+                TileEntityEffect::Explosion { position, radius, .. } => {
+                    if let Some((x, y)) = self.grid_cell(position.0, position.1) {
+                        self.explode(x, y, radius as f32);
+                        report.explosions += 1;
+                    }
+                }
+                TileEntityEffect::DoorPanel { position, length, open } => {
+                    for i in 1..=length as i64 {
+                        let Some((x, y)) = self.grid_cell(position.0, position.1 + i) else {
+                            continue;
+                        };
+                        let material = self.get_particle(x, y).map(|particle| particle.material_type);
+                        if open && material == Some(MaterialType::Door) {
+                            self.remove_particle(x, y);
+                            report.door_cells += 1;
+                        } else if !open && material.is_none() && self.add_particle(x, y, MaterialType::Door, None) {
+                            report.door_cells += 1;
+                        }
+                    }
+                }
+                TileEntityEffect::PistonArm { position, direction, length, extended } => {
+                    TileEntityEffect::apply_piston(self, position, direction, length, extended, &mut report);
+                }
+                TileEntityEffect::GatePanel { position, length, solid } => {
+                    TileEntityEffect::apply_gate(self, position, length, solid, &mut report);
+                }
+                TileEntityEffect::LightGeneration { .. } | TileEntityEffect::FluidFlow { .. } => {}
+            }
+        }
+        report
+    }
+
+    /// Grid coordinates of a world position, if it is inside the grid
+    fn grid_cell(&self, x: i64, y: i64) -> Option<(usize, usize)> {
+        (x >= 0 && y >= 0 && (x as u64) < self.width as u64 && (y as u64) < self.height as u64)
+            .then_some((x as usize, y as usize))
+    }
+
+    /// Give settled powders in a circle small random velocities so piles slump, returning
+    /// how many particles were disturbed. `strength` is the peak impulse in cells per tick.
+    pub fn jostle(&mut self, x: usize, y: usize, radius: usize, strength: f32) -> usize {
+        let start_x = x.saturating_sub(radius);
+        let end_x = (x + radius).min(self.width.saturating_sub(1));
+        let start_y = y.saturating_sub(radius);
+        let end_y = (y + radius).min(self.height.saturating_sub(1));
+        let radius_sq = radius * radius;
+
+        let mut jostled = 0;
+        for py in start_y..=end_y {
+            for px in start_x..=end_x {
+                let dx = px as i32 - x as i32;
+                let dy = py as i32 - y as i32;
+                if (dx * dx + dy * dy) as usize > radius_sq {
+                    continue;
+                }
+
+                let index = self.get_index(px, py);
+                let Some(particle) = self.grid[index].as_mut() else {
+                    continue;
+                };
+                if !self.materials.get(particle.material_type).is_powder(particle.material_type) {
+                    continue;
+                }
+
+                let vx = (self.rng.gen::<f32>() * 2.0 - 1.0) * strength;
+                let vy = -self.rng.gen::<f32>() * strength * 0.5;
+                particle.apply_impulse(vx, vy);
+                self.wake_cell(px, py);
+                jostled += 1;
+            }
+        }
+        jostled
+    }
+
+    /// Generators, rooted plants, door panels, piston arms and static custom materials
+    /// never move
+    pub(crate) fn is_immovable(&self, particle: &Particle) -> bool {
+        matches!(
+            particle.material_type,
+            MaterialType::Generator | MaterialType::Stem | MaterialType::Root | MaterialType::Door | MaterialType::Piston
+        )
+            || (particle.material_type.is_custom() && self.materials.get(particle.material_type).kind == MaterialKind::Static)
     }
 
     #[inline(always)]
     fn handle_movement(&mut self, particle: &mut Particle) -> (usize, usize) {
         let (x, y) = (particle.x, particle.y);
-        let props = particle.get_properties();
+        let materials = Arc::clone(&self.materials);
+        let props = materials.get(particle.material_type);
         
-        if particle.material_type == MaterialType::Generator {
-            return (x, y); // Generators are immovable
+        if self.is_immovable(particle) {
+            return (x, y);
+        }
+        let custom_kind = particle.material_type.is_custom().then_some(props.kind);
+        if self.gravity < 1.0 && self.rng.gen::<f32>() >= self.gravity {
+            return (x, y);
+        }
+        if let Some(position) = self.wind_drift(particle, props) {
+            return position;
+        }
+        if self.is_diffusing_gas(particle.material_type) {
+            return self.move_gas(particle, props);
+        }
+        if props.is_liquid(particle.material_type) {
+            return self.move_liquid(particle, props);
         }
 
         let density = props.density;
@@ -369,14 +1791,11 @@ impl Simulation {
         let is_powder = props.is_powder(particle.material_type);
 
         let vert_dir = if is_gas { -1 } else { 1 };
-        let ny = y as i32 + vert_dir;
 
         // Check boundaries
-        if !self.is_valid(x as i32, ny) {
+        let Some((_, target_y)) = self.offset_cell(x, y, 0, vert_dir) else {
             return (x, y);
-        }
-
-        let target_y = ny as usize;
+        };
 
         // Try vertical movement first - check if target cell is empty
         if self.get_particle(x, target_y).is_none() {
@@ -400,40 +1819,24 @@ impl Simulation {
             
             // Target cell is truly empty (None), move there
             return (x, target_y);
-        } else if let Some(target_particle) = self.get_particle(x, target_y) {
+        } else if self.get_particle(x, target_y).is_some_and(|target| target.material_type == MaterialType::Empty) {
             // If target contains an Empty particle, move there
-            if target_particle.material_type == MaterialType::Empty {
-                return (x, target_y);
-            }
-
-            // Check for density-based swapping
-            let target_props = target_particle.get_properties();
-            let target_density = target_props.density;
-            let should_swap = if is_gas {
-                target_density > density
-            } else {
-                density > target_density
-            };
-
-            if should_swap && target_particle.material_type != MaterialType::Generator {
-                // Need to handle swapping differently - for now just don't move
-                // TODO: Implement proper swapping in the main update loop
-                return (x, y);
-            }
+            return (x, target_y);
+        }
+        // Sink, or rise for gases, through what the interaction matrix lets it pass
+        if let Some(target) = self.sink_through(particle, vert_dir) {
+            return target;
         }
 
-        // Try diagonal movement for non-rigid materials
-        if !matches!(particle.material_type, MaterialType::Stone | MaterialType::Glass | MaterialType::Wood | MaterialType::Ice) {
-            let directions = if rand::random::<bool>() { [-1, 1] } else { [1, -1] };
+        // Try diagonal movement for non-rigid materials; wet sand mostly clumps instead
+        let is_rigid = matches!(particle.material_type, MaterialType::Stone | MaterialType::Sandstone | MaterialType::Glass | MaterialType::Wood | MaterialType::Ice)
+            || custom_kind == Some(MaterialKind::Solid);
+        let slides = particle.material_type != MaterialType::WetSand || self.rng.gen::<f32>() < WET_SAND_SLIDE_CHANCE;
+        if !is_rigid && slides {
+            let directions = if self.rng.gen::<bool>() { [-1, 1] } else { [1, -1] };
             
             for &dx in &directions {
-                let diag_x = x as i32 + dx;
-                let diag_y = ny;
-                
-                if self.is_valid(diag_x, diag_y) {
-                    let diag_x = diag_x as usize;
-                    let diag_y = diag_y as usize;
-                    
+                if let Some((diag_x, diag_y)) = self.offset_cell(x, y, dx, vert_dir) {
                     if self.get_particle(diag_x, diag_y).is_none() {
                         // Empty diagonal spot
                         return (diag_x, diag_y);
@@ -441,6 +1844,10 @@ impl Simulation {
                         if diag_target.material_type == MaterialType::Empty {
                             return (diag_x, diag_y);
                         }
+                        // Trades places once the update loop sets this particle down
+                        if self.interaction(particle.material_type, diag_target.material_type) == Interaction::Swap {
+                            return (diag_x, diag_y);
+                        }
                     }
                 }
             }
@@ -448,14 +1855,10 @@ impl Simulation {
 
         // Horizontal movement for liquids and gases
         if is_liquid || is_gas {
-            let directions = if rand::random::<bool>() { [-1, 1] } else { [1, -1] };
+            let directions = if self.rng.gen::<bool>() { [-1, 1] } else { [1, -1] };
             
             for &dx in &directions {
-                let side_x = x as i32 + dx;
-                
-                if self.is_valid(side_x, y as i32) {
-                    let side_x = side_x as usize;
-                    
+                if let Some((side_x, _)) = self.offset_cell(x, y, dx, 0) {
                     if self.get_particle(side_x, y).is_none() {
                         // Empty side spot
                         let move_chance = if is_liquid {
@@ -464,10 +1867,13 @@ impl Simulation {
                             1.0
                         };
                         
-                        if rand::random::<f32>() < move_chance {
+                        if self.rng.gen::<f32>() < move_chance {
                             return (side_x, y);
                         }
                     } else if let Some(side_target) = self.get_particle(side_x, y) {
+                        if self.interaction(particle.material_type, side_target.material_type) == Interaction::Swap {
+                            return (side_x, y);
+                        }
                         if side_target.material_type == MaterialType::Empty {
                             let move_chance = if is_liquid {
                                 (1.0 - props.viscosity * 0.1).max(0.1)
@@ -475,7 +1881,7 @@ impl Simulation {
                                 1.0
                             };
                             
-                            if rand::random::<f32>() < move_chance {
+                            if self.rng.gen::<f32>() < move_chance {
                                 return (side_x, y);
                             }
                         }
@@ -485,19 +1891,14 @@ impl Simulation {
         }
 
         // Powder piling for falling powders
-        if is_powder && vert_dir == 1 {
-            if y + 1 < self.height {
-                if let Some(below) = self.get_particle(x, y + 1) {
+        if is_powder && vert_dir == 1 && slides {
+            if let Some((_, below_y)) = self.offset_cell(x, y, 0, 1) {
+                if let Some(below) = self.get_particle(x, below_y) {
                     if below.material_type != MaterialType::Empty && below.material_type != MaterialType::Generator {
-                        let directions = if rand::random::<bool>() { [-1, 1] } else { [1, -1] };
+                        let directions = if self.rng.gen::<bool>() { [-1, 1] } else { [1, -1] };
                         
                         for &dx in &directions {
-                            let pile_x = x as i32 + dx;
-                            let pile_y = y + 1;
-                            
-                            if self.is_valid(pile_x, pile_y as i32) {
-                                let pile_x = pile_x as usize;
-                                
+                            if let Some((pile_x, pile_y)) = self.offset_cell(x, y, dx, 1) {
                                 if self.get_particle(pile_x, pile_y).is_none() {
                                     // Empty pile spot
                                     return (pile_x, pile_y);
@@ -518,22 +1919,33 @@ impl Simulation {
     }
 
     pub fn add_particle(&mut self, x: usize, y: usize, material_type: MaterialType, temp: Option<f32>) -> bool {
+        if !self.is_material_enabled(material_type) {
+            return false;
+        }
         if x < self.width && y < self.height {
             // Check if we can place here - only protect generators from non-eraser materials
             if let Some(existing) = self.get_particle(x, y) {
                 if existing.material_type == MaterialType::Generator && material_type != MaterialType::Eraser {
-                    return false; // Can't overwrite generators unless erasing
+                    // Can't overwrite generators unless erasing, but the first material
+                    // painted on one is what it emits
+                    self.paint_generator(x, y, material_type);
+                    return false;
                 }
             }
+            if material_type == MaterialType::Generator {
+                self.generators.remove(&(x, y));
+            }
             
             if material_type == MaterialType::Eraser {
                 self.remove_particle(x, y);
             } else {
                 let initial_temp = match material_type {
                     MaterialType::Lava => Some(2500.0),
+                    MaterialType::MoltenIron => Some(1700.0),
+                    MaterialType::MoltenGold => Some(1250.0),
                     _ => temp,
                 };
-                let particle = Particle::new(x, y, material_type, initial_temp);
+                let particle = self.materials.create_particle(x, y, material_type, initial_temp);
                 self.set_particle(x, y, particle);
             }
             true
@@ -542,6 +1954,12 @@ impl Simulation {
         }
     }
 
+    /// Paint a filled circle of material centred on (x, y), returning how many cells were written;
+    /// see `apply_brush` for other shapes
+    pub fn paint(&mut self, x: usize, y: usize, material_type: MaterialType, radius: usize) -> usize {
+        self.apply_brush(&Brush::circle(radius), (x, y), material_type)
+    }
+
     pub fn get_state(&self) -> SimulationState {
         let mut particles = HashMap::new();
         
@@ -575,4 +1993,612 @@ impl Simulation {
             None
         }
     }
-}
\ No newline at end of file
+}
+/// The eight neighbours of `(x, y)` in `NEIGHBOR_OFFSETS` order. Takes the grid rather
+/// than the simulation so the other fields stay free to borrow mutably.
+fn neighbors_in(grid: &[Option<Particle>], width: usize, height: usize, x: usize, y: usize) -> Vec<Option<&Particle>> {
+    NEIGHBOR_OFFSETS.iter()
+        .map(|&(dx, dy)| {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            let in_bounds = nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height;
+            in_bounds.then(|| grid[ny as usize * width + nx as usize].as_ref()).flatten()
+        })
+        .collect()
+}
+
+impl FluidWorld for Simulation {
+    fn liquid_at(&self, (x, y): (i64, i64)) -> Option<MaterialType> {
+        let (x, y) = self.grid_cell(x, y)?;
+        let material_type = self.get_particle(x, y)?.material_type;
+        self.materials.get(material_type).is_liquid(material_type).then_some(material_type)
+    }
+
+    fn is_cell_empty(&self, (x, y): (i64, i64)) -> bool {
+        self.grid_cell(x, y).is_some_and(|(x, y)| self.get_particle(x, y).is_none())
+    }
+
+    fn take_liquid(&mut self, position: (i64, i64), material: MaterialType) -> bool {
+        if self.liquid_at(position) != Some(material) {
+            return false;
+        }
+        self.remove_particle(position.0 as usize, position.1 as usize).is_some()
+    }
+
+    fn put_liquid(&mut self, position: (i64, i64), material: MaterialType) -> bool {
+        self.is_cell_empty(position) && self.add_particle(position.0 as usize, position.1 as usize, material, None)
+    }
+}
+
+impl SensorWorld for Simulation {
+    fn material_at(&self, (x, y): (i64, i64)) -> Option<MaterialType> {
+        let (x, y) = self.grid_cell(x, y)?;
+        self.get_particle(x, y).map(|particle| particle.material_type)
+    }
+}
+
+impl ActuatorWorld for Simulation {
+    fn is_anchored(&self, (x, y): (i64, i64)) -> bool {
+        self.grid_cell(x, y).is_none_or(|(x, y)| self.get_particle(x, y).is_some_and(|particle| self.is_immovable(particle)))
+    }
+
+    fn take_particle(&mut self, (x, y): (i64, i64)) -> Option<Particle> {
+        let (x, y) = self.grid_cell(x, y)?;
+        self.remove_particle(x, y)
+    }
+
+    fn put_particle(&mut self, (x, y): (i64, i64), particle: Particle) -> bool {
+        let Some((x, y)) = self.grid_cell(x, y).filter(|&(x, y)| self.get_particle(x, y).is_none()) else {
+            return false;
+        };
+        self.set_particle(x, y, particle);
+        true
+    }
+
+    fn place_material(&mut self, (x, y): (i64, i64), material: MaterialType, temp: Option<f32>) -> bool {
+        self.is_cell_empty((x, y)) && self.add_particle(x as usize, y as usize, material, temp)
+    }
+}
+
+impl ThermalWorld for Simulation {
+    fn temperature_at(&self, (x, y): (i64, i64)) -> Option<f32> {
+        let (x, y) = self.grid_cell(x, y)?;
+        self.get_particle(x, y).map(|particle| particle.temp)
+    }
+
+    fn add_heat(&mut self, (x, y): (i64, i64), degrees: f32) -> bool {
+        let Some((x, y)) = self.grid_cell(x, y) else {
+            return false;
+        };
+        let Some(particle) = self.get_particle(x, y) else {
+            return false;
+        };
+        let mut heated = particle.clone();
+        heated.temp += degrees;
+        self.set_particle(x, y, heated);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effects::EffectKind;
+    use crate::materials::{GLASS_CRACK_STRESS, THERMITE_BURN_TEMP, WATER_FUSION_HEAT, WATER_VAPORIZATION_HEAT};
+
+    fn step(simulation: &mut Simulation, ticks: usize) {
+        for _ in 0..ticks {
+            simulation.update(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn test_settled_chunks_fall_asleep() {
+        let mut simulation = Simulation::new(64, 32);
+        for x in 0..8 {
+            simulation.add_particle(x, 31, MaterialType::Stone, None);
+            simulation.add_particle(x, 30, MaterialType::Sand, None);
+        }
+        assert!(simulation.is_chunk_awake(0, 1));
+        assert!(!simulation.is_chunk_awake(3, 0));
+
+        step(&mut simulation, 60);
+        assert_eq!(simulation.awake_chunk_count(), 0);
+        assert!(!simulation.is_chunk_awake(0, 1));
+        // A sleeping world costs nothing to update
+        step(&mut simulation, 1);
+        assert_eq!(simulation.awake_chunk_count(), 0);
+    }
+
+    #[test]
+    fn test_border_changes_wake_adjacent_chunk() {
+        let mut simulation = Simulation::new(32, 32);
+        // Sand resting on a stone bed whose top sits just below the horizontal chunk border
+        for y in 16..32 {
+            for x in 0..32 {
+                simulation.add_particle(x, y, MaterialType::Stone, None);
+            }
+        }
+        simulation.add_particle(5, 15, MaterialType::Sand, None);
+        step(&mut simulation, 60);
+        assert!(!simulation.is_chunk_awake(0, 0) && !simulation.is_chunk_awake(0, 1));
+
+        // Digging out the stone in the lower chunk wakes the sand in the upper one
+        simulation.remove_particle(5, 16);
+        assert!(simulation.is_chunk_awake(0, 0));
+        step(&mut simulation, 10);
+        assert!(simulation.get_particle(5, 15).is_none());
+        assert_eq!(simulation.get_particle(5, 16).unwrap().material_type, MaterialType::Sand);
+    }
+
+    #[test]
+    fn test_static_scene_updates_only_what_moves() {
+        let mut simulation = Simulation::new(128, 128);
+        simulation.set_seed(8);
+        for y in 64..128 {
+            for x in 0..128 {
+                let material = if y < 96 { MaterialType::Sand } else { MaterialType::Stone };
+                simulation.add_particle(x, y, material, None);
+            }
+        }
+        step(&mut simulation, 60);
+        assert_eq!(simulation.active_cell_count(), 0);
+        step(&mut simulation, 1);
+        assert_eq!(simulation.updated_particle_count(), 0);
+
+        // One grain dropped on the 8192 settled particles only wakes what it lands on
+        simulation.add_particle(40, 10, MaterialType::Sand, None);
+        for _ in 0..120 {
+            simulation.update(1.0 / 60.0);
+            assert!(simulation.updated_particle_count() <= 12, "{}", simulation.updated_particle_count());
+        }
+        assert_eq!(simulation.get_particle(40, 63).unwrap().material_type, MaterialType::Sand);
+        step(&mut simulation, 60);
+        assert_eq!(simulation.updated_particle_count(), 0);
+    }
+
+    #[test]
+    fn test_particle_iterators() {
+        let mut simulation = Simulation::new(40, 20);
+        for x in 0..40 {
+            simulation.add_particle(x, 19, MaterialType::Stone, None);
+        }
+        simulation.add_particle(30, 18, MaterialType::Wood, None);
+        assert_eq!(simulation.iter_particles().count(), 41);
+
+        let region: Vec<_> = simulation.iter_region(GridRect::new(28, 17, 100, 100)).map(|p| (p.x, p.y)).collect();
+        assert_eq!(region.len(), 13);
+        assert!(region.contains(&(30, 18)));
+
+        step(&mut simulation, 60);
+        let since = simulation.tick();
+        assert_eq!(simulation.iter_changed_since(since).count(), 0);
+
+        simulation.remove_particle(30, 18);
+        simulation.add_particle(2, 18, MaterialType::Wood, None);
+        let mut changed: Vec<_> = simulation.iter_changed_since(since).map(|(x, y, p)| (x, y, p.is_some())).collect();
+        changed.sort();
+        assert_eq!(changed, vec![(2, 18, true), (30, 18, false)]);
+    }
+
+    #[test]
+    fn test_expired_gas_leaves_the_count_and_the_changed_cells() {
+        let mut simulation = Simulation::new(16, 16);
+        simulation.set_seed(3);
+        for x in 3..13 {
+            simulation.add_particle(x, 15, MaterialType::Smoke, None);
+        }
+        assert_eq!(simulation.particle_count(), 10);
+
+        for _ in 0..1200 {
+            let since = simulation.tick();
+            let before: Vec<_> = simulation.iter_particles().map(|p| (p.x, p.y)).collect();
+            simulation.update(1.0 / 60.0);
+            // Every cell a particle left, including by expiring, is reported as changed
+            let changed: Vec<_> = simulation.iter_changed_since(since).map(|(x, y, _)| (x, y)).collect();
+            for (x, y) in before {
+                assert!(simulation.get_particle(x, y).is_some() || changed.contains(&(x, y)), "({}, {}) emptied unseen", x, y);
+            }
+            assert_eq!(simulation.particle_count(), simulation.iter_particles().count());
+            if simulation.particle_count() == 0 {
+                return;
+            }
+        }
+        panic!("smoke never cleared");
+    }
+
+    #[test]
+    fn test_grid_rect_intersection() {
+        let view = GridRect::new(10, 10, 20, 5);
+        assert_eq!(view.intersection(&GridRect::new(25, 0, 16, 16)), Some(GridRect::new(25, 10, 5, 5)));
+        assert_eq!(view.intersection(&GridRect::new(30, 10, 4, 4)), None);
+        assert!(view.contains(29, 14));
+        assert!(!view.contains(30, 14));
+    }
+
+    #[test]
+    fn test_seeded_simulations_evolve_identically() {
+        let build = || {
+            let mut simulation = Simulation::new(24, 24);
+            simulation.set_seed(7);
+            simulation.paint(8, 4, MaterialType::Sand, 3);
+            simulation.paint(16, 4, MaterialType::Water, 3);
+            simulation.add_particle(12, 20, MaterialType::Fire, None);
+            simulation
+        };
+        let (mut a, mut b) = (build(), build());
+        step(&mut a, 60);
+        step(&mut b, 60);
+
+        let cells = |simulation: &Simulation| -> Vec<_> {
+            simulation.iter_particles().map(|p| (p.x, p.y, p.material_type)).collect()
+        };
+        assert_eq!(cells(&a), cells(&b));
+    }
+
+    #[test]
+    fn test_acid_corrosion_uses_up_concentration() {
+        let mut simulation = Simulation::new(32, 40);
+        simulation.set_seed(3);
+        for y in 20..40 {
+            for x in 0..32 {
+                simulation.add_particle(x, y, MaterialType::Stone, None);
+            }
+        }
+        simulation.add_particle(16, 19, MaterialType::Acid, None);
+        step(&mut simulation, 1200);
+
+        // One cell of fresh acid eats a shallow pit and is spent
+        assert!(simulation.iter_particles().all(|p| p.material_type != MaterialType::Acid));
+        assert!(simulation.iter_particles().any(|p| p.is_contaminated()));
+        assert!((25..40).all(|y| (0..32).all(|x| {
+            simulation.get_particle(x, y).is_some_and(|p| p.material_type == MaterialType::Stone)
+        })));
+    }
+
+    #[test]
+    fn test_blasts_and_tools_crack_stone_before_breaking_it() {
+        let mut simulation = Simulation::new(40, 40);
+        let stone = simulation.materials().get(MaterialType::Stone).clone();
+        simulation.add_particle(26, 20, MaterialType::Stone, None);
+
+        // A weak blast only cracks it; a strong one finishes it off
+        simulation.explode(20, 20, 10.0);
+        let cracked = simulation.get_particle(26, 20).unwrap();
+        assert_eq!((cracked.material_type, cracked.crack_stage(&stone)), (MaterialType::Stone, 2));
+        assert_eq!(simulation.explode(25, 20, 10.0).broken, 1);
+        assert_eq!(simulation.get_particle(26, 20).unwrap().material_type, MaterialType::Sand);
+
+        simulation.add_particle(5, 35, MaterialType::Stone, None);
+        let hits: Vec<DamageOutcome> = (0..4).map(|_| simulation.damage(5, 35, 30.0)).collect();
+        assert_eq!(hits, [DamageOutcome::Damaged, DamageOutcome::Damaged, DamageOutcome::Damaged, DamageOutcome::Broken]);
+        assert_eq!(simulation.get_particle(5, 35).unwrap().material_type, MaterialType::Sand);
+        assert_eq!(simulation.damage(5, 35, 30.0), DamageOutcome::Immune);
+    }
+
+    #[test]
+    fn test_thermite_melts_through_an_iron_plate() {
+        let mut simulation = Simulation::new(20, 40);
+        simulation.set_seed(6);
+        for y in 34..40 {
+            for x in 0..20 {
+                simulation.add_particle(x, y, MaterialType::Iron, None);
+            }
+        }
+        for y in 30..34 {
+            for x in 8..12 {
+                simulation.add_particle(x, y, MaterialType::Thermite, None);
+            }
+        }
+        // Thermite well past its ignition point lights itself, and the rest from it
+        simulation.add_particle(10, 29, MaterialType::Thermite, Some(700.0));
+
+        let mut hottest = 0.0f32;
+        let mut melted = false;
+        for _ in 0..600 {
+            simulation.update(1.0 / 60.0);
+            hottest = simulation.iter_particles().fold(hottest, |hottest, p| hottest.max(p.temp));
+            melted |= (8..12).any(|x| simulation.get_particle(x, 34).is_some_and(|p| p.material_type == MaterialType::MoltenIron));
+        }
+        assert!(hottest >= THERMITE_BURN_TEMP);
+        assert!(melted);
+        assert!(simulation.iter_particles().all(|p| p.material_type != MaterialType::Thermite));
+    }
+
+    #[test]
+    fn test_water_dilutes_acid() {
+        let mut simulation = Simulation::new(32, 32);
+        simulation.set_seed(5);
+        for x in 0..32 {
+            simulation.add_particle(x, 31, MaterialType::Glass, None);
+            for y in 24..31 {
+                simulation.add_particle(x, y, MaterialType::Water, None);
+            }
+        }
+        simulation.add_particle(16, 20, MaterialType::Acid, None);
+        step(&mut simulation, 30);
+        let strength: f32 = simulation.iter_particles()
+            .filter(|p| p.material_type == MaterialType::Acid)
+            .map(|p| p.concentration())
+            .sum();
+        assert!(strength <= 1.0);
+
+        step(&mut simulation, 1200);
+        assert!(simulation.iter_particles().all(|p| p.material_type != MaterialType::Acid));
+        assert!(simulation.iter_particles().filter(|p| p.is_contaminated()).count() >= 2);
+    }
+
+    /// A block of `material` at `temp` on a stone floor, flanked by `fluid` if given
+    fn brittle_block(material: MaterialType, temp: f32, fluid: Option<(MaterialType, f32)>) -> Simulation {
+        let mut simulation = Simulation::new(32, 32);
+        simulation.set_seed(7);
+        for x in 0..32 {
+            simulation.add_particle(x, 31, MaterialType::Stone, None);
+        }
+        for y in 26..31 {
+            for x in 0..32 {
+                if (12..20).contains(&x) {
+                    simulation.add_particle(x, y, material, Some(temp));
+                } else if let Some((fluid, fluid_temp)) = fluid {
+                    simulation.add_particle(x, y, fluid, Some(fluid_temp));
+                }
+            }
+        }
+        simulation
+    }
+
+    fn count(simulation: &Simulation, material: MaterialType) -> usize {
+        simulation.iter_particles().filter(|p| p.material_type == material).count()
+    }
+
+    #[test]
+    fn test_quenched_glass_cracks() {
+        let mut quenched = brittle_block(MaterialType::Glass, 800.0, Some((MaterialType::Water, 5.0)));
+        step(&mut quenched, 120);
+        // Sand debris landing in the water soaks it up
+        let debris = count(&quenched, MaterialType::Sand) + count(&quenched, MaterialType::WetSand);
+        assert!(debris > 0);
+        assert_eq!(count(&quenched, MaterialType::Glass) + debris, 40);
+
+        // The same glass cooling in air sheds heat too slowly to crack
+        let mut cooled = brittle_block(MaterialType::Glass, 800.0, None);
+        step(&mut cooled, 120);
+        assert_eq!(count(&cooled, MaterialType::Glass), 40);
+        assert!(cooled.iter_particles().any(|p| p.stress > 0.0));
+        assert!(cooled.iter_particles().all(|p| p.stress < GLASS_CRACK_STRESS));
+    }
+
+    #[test]
+    fn test_hot_water_shatters_deep_frozen_ice() {
+        let mut shocked = brittle_block(MaterialType::Ice, -40.0, Some((MaterialType::Water, 90.0)));
+        step(&mut shocked, 120);
+        assert!(count(&shocked, MaterialType::Snow) > 0);
+
+        let mut mild = brittle_block(MaterialType::Ice, -10.0, Some((MaterialType::Water, 20.0)));
+        step(&mut mild, 120);
+        assert_eq!(count(&mild, MaterialType::Snow), 0);
+    }
+
+    #[test]
+    fn test_grain_color_travels_with_particle() {
+        let mut simulation = Simulation::new(16, 16);
+        for x in 0..16 {
+            simulation.add_particle(x, 0, MaterialType::Sand, None);
+        }
+        let colors: Vec<[u8; 3]> = (0..16).map(|x| simulation.get_particle(x, 0).unwrap().color()).collect();
+        assert!(colors.iter().any(|&color| color != colors[0]));
+
+        step(&mut simulation, 60);
+        for (x, color) in colors.into_iter().enumerate() {
+            assert_eq!(simulation.get_particle(x, 15).unwrap().color(), color);
+        }
+    }
+
+    #[test]
+    fn test_wet_sand_darkens_and_hot_iron_glows() {
+        let mut simulation = Simulation::new(16, 16);
+        simulation.set_seed(1);
+        simulation.add_particle(4, 15, MaterialType::Sand, None);
+        simulation.add_particle(10, 15, MaterialType::Sand, None);
+        for x in 11..16 {
+            simulation.add_particle(x, 15, MaterialType::Water, None);
+        }
+        let dry = simulation.get_particle(10, 15).unwrap().color();
+        step(&mut simulation, 1);
+        assert!(!simulation.get_particle(4, 15).unwrap().wet);
+        let wet = simulation.get_particle(10, 15).unwrap();
+        assert!(wet.wet);
+        assert!(wet.color().iter().zip(dry).all(|(&wet, dry)| wet < dry));
+
+        let cold = Particle::new(0, 0, MaterialType::Iron, None).color();
+        let hot = Particle::new(0, 0, MaterialType::Iron, Some(1000.0)).color();
+        assert!(hot[0] > cold[0] && hot[0] > hot[1] && hot[1] > hot[2]);
+    }
+
+    #[test]
+    fn test_sand_and_dirt_soak_up_water_and_dry_out() {
+        let mut simulation = Simulation::new(16, 8);
+        simulation.set_seed(6);
+        for x in 0..16 {
+            simulation.add_particle(x, 7, MaterialType::Stone, None);
+        }
+        for (x, material) in [(1, MaterialType::Stone), (2, MaterialType::Sand), (3, MaterialType::Water), (4, MaterialType::Stone),
+                              (5, MaterialType::Dirt), (6, MaterialType::Water), (7, MaterialType::Stone)] {
+            simulation.add_particle(x, 6, material, None);
+        }
+        simulation.add_particle(12, 6, MaterialType::Sand, None);
+        step(&mut simulation, 120);
+        let count = |simulation: &Simulation, material| simulation.iter_particles().filter(|p| p.material_type == material).count();
+        assert_eq!(count(&simulation, MaterialType::WetSand), 1);
+        assert_eq!(count(&simulation, MaterialType::Mud), 1);
+        // Each grain soaked up the water beside it
+        assert_eq!(count(&simulation, MaterialType::Water), 0);
+        assert_eq!(simulation.particle_count(), 16 + 6);
+        assert_eq!(simulation.get_particle(12, 6).map(|p| p.material_type), Some(MaterialType::Sand));
+
+        // Heat dries them back out, giving off steam
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for (wet, dry) in [(MaterialType::WetSand, MaterialType::Sand), (MaterialType::Mud, MaterialType::Dirt)] {
+            let mut particle = Particle::new(5, 5, wet, Some(120.0));
+            let (dried, steam) = (0..600)
+                .map(|_| simulation.physics.handle_state_changes_and_effects(&mut particle, &[None; 8], 1.0 / 60.0, &mut rng))
+                .find(|(changed, _)| changed.is_some())
+                .unwrap();
+            assert_eq!(dried.map(|p| p.material_type), Some(dry));
+            assert_eq!(steam[0].2.material_type, MaterialType::Steam);
+        }
+        let mut lukewarm = Particle::new(5, 5, MaterialType::Mud, Some(40.0));
+        assert!((0..600).all(|_| simulation.physics.handle_state_changes_and_effects(&mut lukewarm, &[None; 8], 1.0 / 60.0, &mut rng).0.is_none()));
+    }
+
+    #[test]
+    fn test_ice_and_water_hold_their_temperature_while_changing_state() {
+        let simulation = Simulation::new(8, 8);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut change = |particle: &mut Particle| {
+            simulation.physics.handle_state_changes_and_effects(particle, &[None; 8], 1.0 / 60.0, &mut rng).0
+        };
+
+        // Ice melts past 6 degrees, but first banks the fusion heat while held there
+        let mut ice = Particle::new(4, 4, MaterialType::Ice, None);
+        ice.temp = 14.0;
+        assert!(change(&mut ice).is_none());
+        assert_eq!((ice.temp, ice.latent_heat), (6.0, 8.0));
+        // Cooling off draws the banked heat back out before the ice gets colder
+        ice.temp = 2.0;
+        assert!(change(&mut ice).is_none());
+        assert_eq!((ice.temp, ice.latent_heat), (6.0, 4.0));
+        ice.temp = 6.0 + WATER_FUSION_HEAT;
+        let water = change(&mut ice).unwrap();
+        assert_eq!((water.material_type, water.temp), (MaterialType::Water, 10.0));
+
+        // Freezing water gives off its fusion heat the same way
+        let mut water = Particle::new(4, 4, MaterialType::Water, Some(-10.0));
+        assert!(change(&mut water).is_none());
+        assert_eq!((water.temp, water.latent_heat), (-5.0, -5.0));
+    }
+
+    #[test]
+    fn test_salt_dissolves_and_boiling_leaves_it_behind() {
+        let mut simulation = Simulation::new(8, 12);
+        simulation.set_seed(4);
+        for x in 0..8 {
+            for y in 6..12 {
+                simulation.add_particle(x, y, MaterialType::Water, None);
+            }
+        }
+        for x in 2..6 {
+            simulation.add_particle(x, 5, MaterialType::Salt, None);
+        }
+        step(&mut simulation, 600);
+        let salt = |simulation: &Simulation| simulation.iter_particles().map(|p| p.salinity()).sum::<f32>();
+        assert!(simulation.iter_particles().all(|p| p.material_type != MaterialType::Salt));
+        assert!(simulation.iter_particles().filter(|p| p.material_type == MaterialType::SaltWater).count() > 2);
+        assert!((salt(&simulation) - 2.0).abs() < 1e-4, "salinity {}", salt(&simulation));
+
+        // Fully salted water boils off into steam and a grain of salt
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut brine = Particle::new(5, 5, MaterialType::SaltWater, Some(120.0));
+        brine.payload = 1.0;
+        brine.latent_heat = WATER_VAPORIZATION_HEAT;
+        let (residue, steam) = simulation.physics.handle_state_changes_and_effects(&mut brine, &[None; 8], 1.0 / 60.0, &mut rng);
+        assert_eq!(residue.map(|p| p.material_type), Some(MaterialType::Salt));
+        assert_eq!(steam[0].2.material_type, MaterialType::Steam);
+    }
+
+    #[test]
+    fn test_wet_sand_piles_steeper_than_dry_sand() {
+        let pile_width = |material| {
+            let mut simulation = Simulation::new(64, 40);
+            simulation.set_seed(1);
+            for x in 0..64 {
+                simulation.add_particle(x, 39, MaterialType::Stone, None);
+            }
+            for tick in 0..600 {
+                if tick < 300 {
+                    simulation.add_particle(32, 0, material, None);
+                }
+                simulation.update(1.0 / 60.0);
+            }
+            let xs: Vec<usize> = simulation.iter_particles().filter(|p| p.material_type == material).map(|p| p.x).collect();
+            xs.iter().max().unwrap() - xs.iter().min().unwrap()
+        };
+        assert!(pile_width(MaterialType::WetSand) < pile_width(MaterialType::Sand));
+    }
+
+    #[test]
+    fn test_effects_from_fire_lava_and_splashes() {
+        let mut simulation = Simulation::new(48, 32);
+        simulation.set_seed(2);
+        simulation.enable_effects();
+        for x in 0..48 {
+            simulation.add_particle(x, 31, MaterialType::Stone, None);
+        }
+        for x in 0..16 {
+            simulation.add_particle(x, 30, MaterialType::Lava, None);
+        }
+        for x in 32..48 {
+            for y in 26..31 {
+                simulation.add_particle(x, y, MaterialType::Water, None);
+            }
+        }
+        let mut seen = HashSet::new();
+        for frame in 0..240 {
+            if frame % 4 == 0 {
+                simulation.add_particle(20, 20, MaterialType::Fire, None);
+                simulation.add_particle(40, 2, MaterialType::Sand, None);
+            }
+            step(&mut simulation, 1);
+            let effects = simulation.effects().unwrap();
+            seen.extend(effects.iter().map(|effect| effect.kind));
+            assert!(effects.iter().all(|effect| simulation.is_valid(effect.cell().0, effect.cell().1)));
+        }
+        assert_eq!(seen, HashSet::from([EffectKind::Spark, EffectKind::Ember, EffectKind::Splash]));
+
+        simulation.clear();
+        assert!(simulation.effects().unwrap().is_empty());
+        simulation.disable_effects();
+        assert!(simulation.effects().is_none());
+    }
+
+    #[test]
+    fn test_effects_leave_seeded_runs_unchanged() {
+        let run = |effects: bool| {
+            let mut simulation = Simulation::new(32, 32);
+            simulation.set_seed(9);
+            if effects {
+                simulation.enable_effects();
+            }
+            for x in 4..28 {
+                simulation.add_particle(x, 20, MaterialType::Fire, None);
+                simulation.add_particle(x, 5, MaterialType::Sand, None);
+            }
+            step(&mut simulation, 90);
+            simulation.iter_particles().map(|p| (p.x, p.y, p.material_type)).collect::<Vec<_>>()
+        };
+        assert_eq!(run(true), run(false));
+    }
+
+    #[test]
+    fn test_event_bus_reports_explosions_steam_and_painting() {
+        let mut simulation = Simulation::new(32, 32);
+        simulation.set_seed(4);
+        simulation.paint(5, 5, MaterialType::Sand, 1);
+        assert!(simulation.drain_events().is_empty());
+
+        simulation.enable_event_bus();
+        let painted = simulation.paint(16, 28, MaterialType::Lava, 2);
+        simulation.explode(4, 4, 3.0);
+        let events = simulation.drain_events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[0], SimulationEvent::MaterialPlaced { material: MaterialType::Lava, count, .. } if count == painted));
+        assert_eq!(events[1], SimulationEvent::ExplosionAt { x: 4, y: 4, radius: 3.0 });
+
+        for x in 10..22 {
+            simulation.add_particle(x, 24, MaterialType::Water, None);
+        }
+        step(&mut simulation, 30);
+        let events = simulation.drain_events();
+        assert!(events.iter().any(|event| matches!(event, SimulationEvent::SteamHiss { .. })), "{:?}", events);
+        assert!(simulation.event_bus().unwrap().is_empty());
+    }
+}
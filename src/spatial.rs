@@ -1,6 +1,7 @@
 use crate::chunk::{ChunkManager, CHUNK_SIZE};
 use crate::particle::Particle;
 use crate::materials::MaterialType;
+use crate::reactions::NEIGHBOR_OFFSETS;
 use ahash::AHashMap;
 use nalgebra::Point2;
 use smallvec::SmallVec;
@@ -228,7 +229,7 @@ impl NeighborCache {
     }
 
     /// Get optimized neighbor list for a particle
-    pub fn get_neighbors<'a>(&self, chunk_manager: &'a ChunkManager, x: i64, y: i64) -> SmallVec<[Option<&'a Particle>; 8]> {
+    pub fn get_neighbors(&self, chunk_manager: &ChunkManager, x: i64, y: i64) -> SmallVec<[Option<Particle>; 8]> {
         let mut neighbors = SmallVec::new();
         
         // Standard 8-directional neighbors
@@ -247,6 +248,11 @@ impl NeighborCache {
         neighbors
     }
 
+    /// `ChunkManager::sample` for each of the 8 neighbours, in `get_neighbors` order
+    pub fn get_neighbor_samples(&self, chunk_manager: &ChunkManager, x: i64, y: i64) -> SmallVec<[Option<(MaterialType, f32, bool)>; 8]> {
+        NEIGHBOR_OFFSETS.iter().map(|&(dx, dy)| chunk_manager.sample(x + dx as i64, y + dy as i64)).collect()
+    }
+
     /// Get particles within a radius (useful for advanced interactions)
     pub fn get_particles_in_radius(&self, x: i64, y: i64, radius: f32) -> SmallVec<[(i64, i64); 16]> {
         self.spatial_grid.get_nearby_particles(x, y, radius)
@@ -309,7 +315,7 @@ impl CollisionDetector {
                             if other_x != x || other_y != y {
                                 if let Some(other_particle) = chunk_manager.get_particle(other_x, other_y) {
                                     // Check for collision based on material properties
-                                    if self.should_collide(particle, other_particle) {
+                                    if self.should_collide(&particle, &other_particle) {
                                         collisions.push((x, y, other_x, other_y));
                                     }
                                 }
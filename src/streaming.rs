@@ -0,0 +1,210 @@
+use crate::chunk::{ChunkKey, ChunkManager};
+use crate::save_load::{SaveLoadError, SaveLoadManager};
+use crate::tile_entity::TileEntityManager;
+use crate::world_generation::WorldGenerator;
+use crate::world_rules::WorldRules;
+use ahash::AHashSet;
+
+/// Chunks kept loaded in every direction around the view centre
+pub const DEFAULT_VIEW_RADIUS: i32 = 2;
+/// Extra chunks a loaded chunk may drift past the radius before it is unloaded, so
+/// chunks at the edge don't thrash as the view moves back and forth
+pub const UNLOAD_MARGIN: i32 = 1;
+
+/// Keeps the chunks around a view centre in memory: chunks entering the view are loaded
+/// from disk, or generated the first time, and chunks far outside it are saved and
+/// dropped, so the world can grow without bound.
+///
+/// Generated features that spill into a chunk which is not loaded yet are discarded.
+/// Tile entities placed by the generator stay in `tile_entities` and are not streamed.
+pub struct WorldStreamer {
+    generator: WorldGenerator,
+    save_manager: SaveLoadManager,
+    world_name: String,
+    /// Applied to chunks read back from disk
+    pub rules: WorldRules,
+    pub tile_entities: TileEntityManager,
+    view_center: ChunkKey,
+    view_radius: i32,
+    loaded: AHashSet<ChunkKey>,
+}
+
+/// What one streaming pass did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamingReport {
+    pub generated: usize,
+    pub loaded: usize,
+    pub unloaded: usize,
+}
+
+impl WorldStreamer {
+    pub fn new(generator: WorldGenerator, save_manager: SaveLoadManager, world_name: impl Into<String>) -> Self {
+        Self {
+            generator,
+            save_manager,
+            world_name: world_name.into(),
+            rules: WorldRules::default(),
+            tile_entities: TileEntityManager::new(),
+            view_center: (0, 0),
+            view_radius: DEFAULT_VIEW_RADIUS,
+            loaded: AHashSet::new(),
+        }
+    }
+
+    pub fn view_center(&self) -> ChunkKey {
+        self.view_center
+    }
+
+    pub fn view_radius(&self) -> i32 {
+        self.view_radius
+    }
+
+    pub fn set_view_radius(&mut self, radius: i32) {
+        self.view_radius = radius.max(0);
+    }
+
+    /// Centre the view on a world position; takes effect on the next `stream`
+    pub fn set_view_center(&mut self, world_x: i64, world_y: i64) {
+        self.view_center = ChunkManager::world_to_chunk_pos(world_x, world_y);
+    }
+
+    pub fn is_loaded(&self, chunk_key: ChunkKey) -> bool {
+        self.loaded.contains(&chunk_key)
+    }
+
+    pub fn loaded_count(&self) -> usize {
+        self.loaded.len()
+    }
+
+    /// Treat chunks already in memory (e.g. from a loaded save) as streamed in
+    pub fn adopt_chunks(&mut self, chunks: &ChunkManager) {
+        self.loaded.extend(chunks.chunks_iter().map(|(key, _)| *key));
+    }
+
+    fn distance(&self, chunk_key: ChunkKey) -> i32 {
+        (chunk_key.0 - self.view_center.0).abs().max((chunk_key.1 - self.view_center.1).abs())
+    }
+
+    /// Unload chunks far from the view, then load or generate everything inside it
+    pub fn stream(&mut self, chunks: &mut ChunkManager) -> Result<StreamingReport, SaveLoadError> {
+        let mut report = StreamingReport::default();
+
+        let far: Vec<ChunkKey> = self.loaded.iter()
+            .copied()
+            .filter(|&key| self.distance(key) > self.view_radius + UNLOAD_MARGIN)
+            .collect();
+        for key in far {
+            if let Some(chunk) = chunks.remove_chunk(key) {
+                self.save_manager.save_chunk(&self.world_name, key, &chunk)?;
+            }
+            self.loaded.remove(&key);
+            report.unloaded += 1;
+        }
+
+        let (center_x, center_y) = self.view_center;
+        for chunk_y in center_y - self.view_radius..=center_y + self.view_radius {
+            for chunk_x in center_x - self.view_radius..=center_x + self.view_radius {
+                let key = (chunk_x, chunk_y);
+                if self.loaded.contains(&key) {
+                    continue;
+                }
+
+                if let Some(chunk_save) = self.save_manager.load_chunk(&self.world_name, key)? {
+                    SaveLoadManager::apply_chunk_save(&chunk_save, chunks, &self.rules)?;
+                    chunks.compress_chunk(key);
+                    report.loaded += 1;
+                } else {
+                    self.generate(key, chunks);
+                    report.generated += 1;
+                }
+                self.loaded.insert(key);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Save every loaded chunk without unloading it, e.g. before shutting down
+    pub fn save_all(&self, chunks: &ChunkManager) -> Result<(), SaveLoadError> {
+        for &key in &self.loaded {
+            if let Some(chunk) = chunks.get_chunk(key) {
+                self.save_manager.save_chunk(&self.world_name, key, chunk)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn generate(&mut self, key: ChunkKey, chunks: &mut ChunkManager) {
+        let existing: AHashSet<ChunkKey> = chunks.chunks_iter().map(|(key, _)| *key).collect();
+        self.generator.generate_chunk(key, chunks, &mut self.tile_entities);
+
+        let spilled: Vec<ChunkKey> = chunks.chunks_iter()
+            .map(|(key, _)| *key)
+            .filter(|spilled| *spilled != key && !existing.contains(spilled))
+            .collect();
+        for spilled in spilled {
+            chunks.remove_chunk(spilled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::CHUNK_SIZE;
+    use crate::engine_v2::AdvancedPhysicsEngine;
+    use crate::materials::MaterialType;
+
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("sand_engine_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_chunks_stream_out_and_back() {
+        let dir = scratch_dir("streaming");
+        let streamer = WorldStreamer::new(WorldGenerator::new(7), SaveLoadManager::new(&dir).unwrap(), "infinite");
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.enable_streaming(streamer);
+        engine.set_view_radius(0);
+
+        engine.set_view_center(10, 10).unwrap();
+        assert!(engine.chunk_manager.get_chunk((0, 0)).is_some());
+        engine.chunk_manager.remove_particle(5, 5);
+        assert!(engine.add_particle(5, 5, MaterialType::Gold, Some(321.0)));
+
+        // Far away the chunk is written out and dropped
+        let far = CHUNK_SIZE as i64 * 5;
+        engine.set_view_center(far, 10).unwrap();
+        assert!(engine.chunk_manager.get_chunk((0, 0)).is_none());
+        assert!(engine.chunk_manager.get_chunk((5, 0)).is_some());
+
+        // Coming back restores the edit instead of regenerating
+        let report = engine.set_view_center(10, 10).unwrap();
+        assert_eq!(report.loaded, 1);
+        let gold = engine.chunk_manager.get_particle(5, 5).unwrap();
+        assert_eq!(gold.material_type, MaterialType::Gold);
+        assert_eq!(gold.temp, 321.0);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_unload_margin() {
+        let dir = scratch_dir("margin");
+        let mut streamer = WorldStreamer::new(WorldGenerator::new(3), SaveLoadManager::new(&dir).unwrap(), "margin");
+        streamer.set_view_radius(0);
+        let mut chunks = ChunkManager::new();
+
+        streamer.stream(&mut chunks).unwrap();
+        // One chunk over is still within the margin
+        streamer.set_view_center(CHUNK_SIZE as i64, 0);
+        let report = streamer.stream(&mut chunks).unwrap();
+        assert_eq!(report.unloaded, 0);
+        assert!(streamer.is_loaded((0, 0)) && streamer.is_loaded((1, 0)));
+
+        streamer.set_view_center(CHUNK_SIZE as i64 * 2, 0);
+        assert_eq!(streamer.stream(&mut chunks).unwrap().unloaded, 1);
+        assert!(!streamer.is_loaded((0, 0)));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
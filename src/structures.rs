@@ -1,19 +1,26 @@
 use crate::materials::MaterialType;
 use crate::particle::Particle;
 use crate::chunk::ChunkManager;
+use crate::critters;
+use crate::ecs::{CritterKind, EntityId, ECS};
+use crate::simulation::{GridRect, Simulation};
 use crate::tile_entity::{TileEntity, TileEntityManager};
+use serde::{Deserialize, Serialize};
 
 /// Predefined structures that can be spawned in the world
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Structure {
     pub name: String,
     pub particles: Vec<StructureParticle>,
     pub tile_entities: Vec<StructureTileEntity>,
+    /// Creatures that come with the structure, added by `spawn_critters`
+    #[serde(default)]
+    pub critters: Vec<StructureCritter>,
     pub width: usize,
     pub height: usize,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StructureParticle {
     pub x: usize,
     pub y: usize,
@@ -21,13 +28,20 @@ pub struct StructureParticle {
     pub temp: Option<f32>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StructureTileEntity {
     pub x: i64,
     pub y: i64,
     pub entity_type: crate::tile_entity::TileEntityType,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructureCritter {
+    pub x: i64,
+    pub y: i64,
+    pub kind: CritterKind,
+}
+
 impl Structure {
     /// Create a simple house structure
     pub fn house() -> Self {
@@ -103,6 +117,7 @@ impl Structure {
                     entity_type: crate::tile_entity::TileEntityType::Torch,
                 },
             ],
+            critters: vec![],
             width: 12,
             height: 9,
         }
@@ -156,6 +171,7 @@ impl Structure {
                     entity_type: crate::tile_entity::TileEntityType::Torch,
                 },
             ],
+            critters: vec![],
             width: 20,
             height: 7,
         }
@@ -237,6 +253,7 @@ impl Structure {
                     entity_type: crate::tile_entity::TileEntityType::Torch,
                 },
             ],
+            critters: vec![],
             width: 8,
             height: 16,
         }
@@ -317,6 +334,7 @@ impl Structure {
                     entity_type: crate::tile_entity::TileEntityType::Torch,
                 },
             ],
+            critters: vec![],
             width: 10,
             height: 15,
         }
@@ -341,6 +359,7 @@ impl Structure {
             name: "Rigid Box".to_string(),
             particles,
             tile_entities: vec![],
+            critters: vec![],
             width: 4,
             height: 4,
         }
@@ -375,11 +394,39 @@ impl Structure {
             name: "Rigid Platform".to_string(),
             particles,
             tile_entities: vec![],
+            critters: vec![],
             width: 8,
             height: 5,
         }
     }
     
+    /// Create a glass tank of water with fish in it
+    pub fn aquarium() -> Self {
+        let mut particles = Vec::new();
+        for y in 0..8 {
+            for x in 0..12 {
+                let material = match x == 0 || x == 11 || y == 7 {
+                    true => MaterialType::Glass,
+                    // Leave the top row open as air
+                    false if y == 0 => continue,
+                    false => MaterialType::Water,
+                };
+                particles.push(StructureParticle { x, y, material, temp: Some(20.0) });
+            }
+        }
+
+        Self {
+            name: "Aquarium".to_string(),
+            particles,
+            tile_entities: vec![],
+            critters: [(3, 3), (6, 5), (8, 2)].into_iter()
+                .map(|(x, y)| StructureCritter { x, y, kind: CritterKind::Fish })
+                .collect(),
+            width: 12,
+            height: 8,
+        }
+    }
+    
     /// Spawn this structure in the world
     pub fn spawn(&self, center_x: i64, center_y: i64, chunk_manager: &mut ChunkManager, tile_entity_manager: &mut TileEntityManager) {
         let offset_x = center_x - (self.width as i64 / 2);
@@ -415,6 +462,9 @@ impl Structure {
                 crate::tile_entity::TileEntityType::Torch => {
                     TileEntity::new_torch((world_x, world_y))
                 },
+                crate::tile_entity::TileEntityType::Sign => {
+                    TileEntity::new_sign((world_x, world_y), "")
+                },
                 _ => TileEntity::new_chest((world_x, world_y), 50), // Default fallback
             };
             
@@ -422,6 +472,27 @@ impl Structure {
         }
     }
     
+    /// Add the structure's critters to `ecs`, centred as in `spawn`; returns their ids
+    pub fn spawn_critters(&self, center_x: i64, center_y: i64, ecs: &mut ECS) -> Vec<EntityId> {
+        let offset_x = center_x - (self.width as i64 / 2);
+        let offset_y = center_y - (self.height as i64 / 2);
+        self.critters.iter()
+            .map(|critter| critters::spawn_critter(ecs, offset_x + critter.x, offset_y + critter.y, critter.kind))
+            .collect()
+    }
+
+    /// Add the structure's particles to a grid with its top-left corner at `(x, y)`,
+    /// replacing what was there; returns how many landed inside the grid
+    pub fn place(&self, simulation: &mut Simulation, x: usize, y: usize) -> usize {
+        self.particles.iter()
+            .filter(|particle_data| {
+                let (particle_x, particle_y) = (x + particle_data.x, y + particle_data.y);
+                particle_x < simulation.width && particle_y < simulation.height
+                    && simulation.add_particle(particle_x, particle_y, particle_data.material, particle_data.temp)
+            })
+            .count()
+    }
+
     /// Get all available structures
     pub fn get_all_structures() -> Vec<Structure> {
         vec![
@@ -431,9 +502,59 @@ impl Structure {
             Structure::windmill(),
             Structure::rigid_box(),
             Structure::rigid_platform(),
+            Structure::aquarium(),
         ]
     }
     
+    /// Capture the particles inside `rect` as a new structure named "Custom", with
+    /// positions relative to the rectangle's top-left corner. The rectangle is clipped
+    /// to the grid.
+    pub fn from_region(simulation: &Simulation, rect: GridRect) -> Self {
+        let rect = rect.intersection(&GridRect::new(0, 0, simulation.width, simulation.height))
+            .unwrap_or(GridRect::new(0, 0, 0, 0));
+        let mut particles = Vec::new();
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                if let Some(particle) = simulation.get_particle(x, y) {
+                    particles.push(StructureParticle {
+                        x: x - rect.x,
+                        y: y - rect.y,
+                        material: particle.material_type,
+                        temp: Some(particle.temp),
+                    });
+                }
+            }
+        }
+
+        Self {
+            name: "Custom".to_string(),
+            particles,
+            tile_entities: vec![],
+            critters: vec![],
+            width: rect.width,
+            height: rect.height,
+        }
+    }
+
+    /// Add the tile entities inside `rect`, the same rectangle the structure was
+    /// captured from, e.g. `Structure::from_region(&sim, rect).with_tile_entities(&tiles, rect)`
+    pub fn with_tile_entities(mut self, tile_entity_manager: &TileEntityManager, rect: GridRect) -> Self {
+        let (left, top) = (rect.x as i64, rect.y as i64);
+        let (right, bottom) = (left + self.width as i64, top + self.height as i64);
+        self.tile_entities = tile_entity_manager.get_tile_entities()
+            .filter(|tile_entity| {
+                let (x, y) = tile_entity.position;
+                x >= left && x < right && y >= top && y < bottom
+            })
+            .map(|tile_entity| StructureTileEntity {
+                x: tile_entity.position.0 - left,
+                y: tile_entity.position.1 - top,
+                entity_type: tile_entity.tile_type.clone(),
+            })
+            .collect();
+        self
+    }
+
     /// Get a structure by name
     pub fn get_by_name(name: &str) -> Option<Structure> {
         match name {
@@ -443,11 +564,37 @@ impl Structure {
             "Windmill" => Some(Structure::windmill()),
             "Rigid Box" => Some(Structure::rigid_box()),
             "Rigid Platform" => Some(Structure::rigid_platform()),
+            "Aquarium" => Some(Structure::aquarium()),
             _ => None,
         }
     }
 }
 
+/// Structures authored by players, saved with `SaveLoadManager::save_structures`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StructureLibrary {
+    pub structures: Vec<Structure>,
+}
+
+impl StructureLibrary {
+    /// Add a structure, replacing any with the same name
+    pub fn insert(&mut self, structure: Structure) {
+        match self.structures.iter_mut().find(|existing| existing.name == structure.name) {
+            Some(existing) => *existing = structure,
+            None => self.structures.push(structure),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Structure> {
+        self.structures.iter().find(|structure| structure.name == name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Structure> {
+        let index = self.structures.iter().position(|structure| structure.name == name)?;
+        Some(self.structures.remove(index))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -492,4 +639,53 @@ mod tests {
         let invalid = Structure::get_by_name("Invalid");
         assert!(invalid.is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_aquarium_spawns_fish_in_its_water() {
+        let mut chunk_manager = ChunkManager::new();
+        let mut tile_entity_manager = TileEntityManager::new();
+        let mut ecs = ECS::new();
+
+        let aquarium = Structure::get_by_name("Aquarium").unwrap();
+        aquarium.spawn(50, 50, &mut chunk_manager, &mut tile_entity_manager);
+        let fish = aquarium.spawn_critters(50, 50, &mut ecs);
+        assert_eq!(fish.len(), 3);
+        for entity_id in fish {
+            assert_eq!(ecs.get_critter(entity_id).unwrap().kind, CritterKind::Fish);
+            let position = ecs.get_position(entity_id).unwrap();
+            assert_eq!(chunk_manager.material_at(position.x.floor() as i64, position.y.floor() as i64), Some(MaterialType::Water));
+        }
+    }
+
+    #[test]
+    fn test_from_region() {
+        let mut simulation = Simulation::new(20, 20);
+        simulation.add_particle(5, 5, MaterialType::Stone, Some(300.0));
+        simulation.add_particle(7, 6, MaterialType::Wood, None);
+        simulation.add_particle(12, 12, MaterialType::Stone, None);
+        let mut tile_entities = TileEntityManager::new();
+        tile_entities.add_tile_entity(TileEntity::new_torch((6, 6)));
+        tile_entities.add_tile_entity(TileEntity::new_chest((15, 15), 10));
+
+        let rect = GridRect::new(4, 4, 5, 5);
+        let structure = Structure::from_region(&simulation, rect).with_tile_entities(&tile_entities, rect);
+        assert_eq!((structure.width, structure.height), (5, 5));
+        assert_eq!(structure.particles.len(), 2);
+        assert_eq!((structure.particles[0].x, structure.particles[0].y), (1, 1));
+        assert_eq!(structure.particles[0].temp, Some(300.0));
+        assert_eq!(structure.tile_entities.len(), 1);
+        assert_eq!((structure.tile_entities[0].x, structure.tile_entities[0].y), (2, 2));
+
+        // Clipped to the grid
+        let edge = Structure::from_region(&simulation, GridRect::new(10, 10, 50, 50));
+        assert_eq!((edge.width, edge.height, edge.particles.len()), (10, 10, 1));
+
+        let mut library = StructureLibrary::default();
+        library.insert(structure.clone());
+        library.insert(Structure { particles: vec![], ..structure });
+        assert_eq!(library.structures.len(), 1);
+        assert!(library.get("Custom").unwrap().particles.is_empty());
+        assert!(library.remove("Custom").is_some());
+        assert!(library.get("Custom").is_none());
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::io::Write;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// Samples a `Telemetry` keeps by default before dropping the oldest
+pub const DEFAULT_TELEMETRY_CAPACITY: usize = 100_000;
+
+/// One named measurement at a point in simulated time
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetrySample {
+    pub tick: u64,
+    /// Simulated seconds
+    pub time: f32,
+    pub name: String,
+    pub value: f64,
+}
+
+/// Time series of measurements, exported as CSV with one `tick,time,name,value` row per
+/// sample so any spreadsheet can pivot it by name. Only the latest `capacity` samples are
+/// kept, so a long-running recorder doesn't grow without bound.
+#[derive(Debug, Clone)]
+pub struct Telemetry {
+    samples: VecDeque<TelemetrySample>,
+    capacity: usize,
+}
+
+impl Default for Telemetry {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_TELEMETRY_CAPACITY)
+    }
+}
+
+impl Telemetry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the latest `capacity` samples, at least one
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { samples: VecDeque::new(), capacity: capacity.max(1) }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Add a sample, dropping the oldest once full
+    pub fn record(&mut self, tick: u64, time: f32, name: impl Into<String>, value: f64) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(TelemetrySample { tick, time, name: name.into(), value });
+    }
+
+    /// Samples kept, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = &TelemetrySample> + '_ {
+        self.samples.iter()
+    }
+
+    /// Samples recorded under `name`, oldest first
+    pub fn series<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a TelemetrySample> + 'a {
+        self.samples.iter().filter(move |sample| sample.name == name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn write_csv(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "tick,time,name,value")?;
+        for sample in &self.samples {
+            writeln!(writer, "{},{},{},{}", sample.tick, sample.time, csv_field(&sample.name), sample.value)?;
+        }
+        Ok(())
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = Vec::new();
+        self.write_csv(&mut csv).expect("writing to a Vec cannot fail");
+        String::from_utf8(csv).expect("CSV is built from UTF-8 strings")
+    }
+
+    #[cfg(feature = "files")]
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        self.write_csv(std::io::BufWriter::new(std::fs::File::create(path)?))
+    }
+}
+
+/// Quote a field if it would otherwise break the row
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_export() {
+        let mut telemetry = Telemetry::new();
+        telemetry.record(1, 0.5, "volume.filled", 12.0);
+        telemetry.record(2, 1.0, "count,\"odd\"", 3.0);
+
+        assert_eq!(
+            telemetry.to_csv(),
+            "tick,time,name,value\n1,0.5,volume.filled,12\n2,1,\"count,\"\"odd\"\"\",3\n"
+        );
+        assert_eq!(telemetry.series("volume.filled").count(), 1);
+    }
+
+    #[test]
+    fn test_oldest_samples_are_dropped_when_full() {
+        let mut telemetry = Telemetry::with_capacity(3);
+        for tick in 0..5 {
+            telemetry.record(tick, tick as f32, "count", tick as f64);
+        }
+        assert_eq!(telemetry.len(), 3);
+        assert_eq!(telemetry.samples().map(|sample| sample.tick).collect::<Vec<_>>(), [2, 3, 4]);
+        assert_eq!(Telemetry::new().capacity(), DEFAULT_TELEMETRY_CAPACITY);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::image_io::{decode_png, ImageError};
+use crate::materials::MaterialType;
+use std::collections::HashMap;
+#[cfg(feature = "files")]
+use std::fs::File;
+#[cfg(feature = "files")]
+use std::io::BufReader;
+use std::io::Read;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+/// Square textures that materials repeat across the grid, e.g. speckled stone or grained
+/// wood. Set one as `MaterialRenderer::textures` and textured materials are drawn from
+/// their tile, sampled at the cell's world position, instead of a flat color.
+#[derive(Debug, Clone)]
+pub struct TextureAtlas {
+    tile_size: usize,
+    // Row-major colors of each tile
+    tiles: Vec<Vec<[u8; 3]>>,
+    assigned: HashMap<MaterialType, usize>,
+}
+
+impl TextureAtlas {
+    /// An atlas with no tiles, each `tile_size` cells square once added
+    pub fn new(tile_size: usize) -> Self {
+        Self { tile_size: tile_size.max(1), tiles: Vec::new(), assigned: HashMap::new() }
+    }
+
+    /// Load an atlas from a PNG file, as `read_png`
+    #[cfg(feature = "files")]
+    pub fn from_image(path: impl AsRef<Path>, tile_size: usize) -> Result<Self, ImageError> {
+        Self::read_png(BufReader::new(File::open(path)?), tile_size)
+    }
+
+    /// Cut a PNG into `tile_size` square tiles, numbered left to right and top to bottom;
+    /// a strip too narrow or short for a whole tile at the right or bottom is ignored
+    pub fn read_png(reader: impl Read, tile_size: usize) -> Result<Self, ImageError> {
+        let (width, height, pixels) = decode_png(reader)?;
+        let mut atlas = Self::new(tile_size);
+        let size = atlas.tile_size;
+        for tile_y in 0..height / size {
+            for tile_x in 0..width / size {
+                let tile = (0..size * size)
+                    .map(|index| pixels[(tile_y * size + index / size) * width + tile_x * size + index % size].0)
+                    .collect();
+                atlas.tiles.push(tile);
+            }
+        }
+        Ok(atlas)
+    }
+
+    /// Add a tile of `tile_size * tile_size` row-major colors, returning its number, or
+    /// None if it is the wrong size
+    pub fn add_tile(&mut self, colors: Vec<[u8; 3]>) -> Option<usize> {
+        if colors.len() != self.tile_size * self.tile_size {
+            return None;
+        }
+        self.tiles.push(colors);
+        Some(self.tiles.len() - 1)
+    }
+
+    /// Draw `material_type` from tile number `tile`; false if the atlas has no such tile
+    pub fn assign(&mut self, material_type: MaterialType, tile: usize) -> bool {
+        if tile >= self.tiles.len() {
+            return false;
+        }
+        self.assigned.insert(material_type, tile);
+        true
+    }
+
+    /// Go back to drawing `material_type` in its flat color
+    pub fn unassign(&mut self, material_type: MaterialType) {
+        self.assigned.remove(&material_type);
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// The texture color of `material_type` at world cell `(x, y)`, repeating every
+    /// `tile_size` cells; None for materials without a tile
+    pub fn sample(&self, material_type: MaterialType, x: i32, y: i32) -> Option<[u8; 3]> {
+        let tile = &self.tiles[*self.assigned.get(&material_type)?];
+        let size = self.tile_size as i32;
+        Some(tile[(y.rem_euclid(size) * size + x.rem_euclid(size)) as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{MaterialRenderer, BYTES_PER_PIXEL};
+    use crate::simulation::Simulation;
+    use std::sync::Arc;
+
+    /// A 4x2 RGB image holding two 2x2 tiles: a red and blue checker, then a green gradient
+    fn atlas_png() -> Vec<u8> {
+        let rows = [
+            [[255, 0, 0], [0, 0, 255], [0, 60, 0], [0, 120, 0]],
+            [[0, 0, 255], [255, 0, 0], [0, 180, 0], [0, 240, 0]],
+        ];
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 4, 2);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&rows.concat().concat()).unwrap();
+        png
+    }
+
+    #[test]
+    fn test_atlas_cuts_tiles_and_repeats_them() {
+        let mut atlas = TextureAtlas::read_png(atlas_png().as_slice(), 2).unwrap();
+        assert_eq!(atlas.tile_count(), 2);
+        assert!(!atlas.assign(MaterialType::Stone, 2));
+        assert!(atlas.assign(MaterialType::Stone, 1));
+
+        assert_eq!(atlas.sample(MaterialType::Stone, 0, 0), Some([0, 60, 0]));
+        assert_eq!(atlas.sample(MaterialType::Stone, 5, 7), Some([0, 240, 0]));
+        assert_eq!(atlas.sample(MaterialType::Stone, -1, 0), Some([0, 120, 0]));
+        assert_eq!(atlas.sample(MaterialType::Wood, 0, 0), None);
+        assert_eq!(atlas.add_tile(vec![[0, 0, 0]; 3]), None);
+    }
+
+    #[test]
+    fn test_renderer_draws_textured_materials_from_their_tile() {
+        let mut simulation = Simulation::new(4, 1);
+        for x in 0..3 {
+            simulation.add_particle(x, 0, MaterialType::Generator, None);
+        }
+        let mut atlas = TextureAtlas::read_png(atlas_png().as_slice(), 2).unwrap();
+        atlas.assign(MaterialType::Generator, 0);
+        let mut renderer = MaterialRenderer::new();
+        renderer.textures = Some(Arc::new(atlas));
+
+        let mut frame = vec![0; MaterialRenderer::frame_len(4, 1)];
+        renderer.render(&simulation, &mut frame);
+        let pixel = |x: usize| &frame[x * BYTES_PER_PIXEL..x * BYTES_PER_PIXEL + 3];
+        assert_eq!(pixel(0), [255, 0, 0]);
+        assert_eq!(pixel(1), [0, 0, 255]);
+        assert_eq!(pixel(2), [255, 0, 0]);
+        assert_eq!(pixel(3), [0, 0, 0], "empty cells stay background");
+    }
+}
@@ -1,8 +1,52 @@
+use crate::circuits::{LogicGate, SensorCondition};
+use crate::material_registry::MaterialRegistry;
 use crate::materials::MaterialType;
 use crate::particle::Particle;
+use crate::recipes::RecipeRegistry;
 use ahash::AHashMap;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, OnceLock};
+
+/// Temperature tile entities start at and cool back towards
+pub const TILE_AMBIENT_TEMP: f32 = 20.0;
+/// Fraction of the gap to the surrounding temperature closed per second
+const TILE_HEAT_EXCHANGE_RATE: f32 = 0.5;
+/// Wooden containers catch fire above this, the same as loose Wood
+pub const WOODEN_IGNITION_TEMP: f32 = 200.0;
+/// Seconds a burning chest lasts before it collapses and spills its contents
+pub const CHEST_BURN_SECONDS: f32 = 3.0;
+const BURNING_TEMP: f32 = 600.0;
+/// Longest text a sign holds, in characters; longer text is cut off
+pub const MAX_SIGN_TEXT_LEN: usize = 64;
+/// How far from an effect's position spawned particles and material conversions reach
+pub const EFFECT_SEARCH_RADIUS: u32 = 3;
+/// Units of liquid, one particle each, a pipe segment holds
+pub const PIPE_CAPACITY: u32 = 4;
+const CARDINAL_OFFSETS: [(i64, i64); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+/// Materials a furnace accepts as fuel
+pub const FURNACE_FUELS: [MaterialType; 4] = [MaterialType::Coal, MaterialType::Wood, MaterialType::Oil, MaterialType::Gasoline];
+/// Seconds one unit of fuel keeps a furnace burning
+pub const FUEL_BURN_SECONDS: f32 = 4.0;
+/// Most units a furnace's fuel, input and output slots each hold
+pub const FURNACE_SLOT_CAPACITY: u32 = 64;
+const FURNACE_MAX_TEMP: f32 = 1000.0;
+const FURNACE_HEATING_RATE: f32 = 100.0;
+const FURNACE_COOLING_RATE: f32 = 20.0;
+/// Cells around an entity whose temperature makes up its ambient temperature
+pub const AMBIENT_SAMPLE_RADIUS: u32 = 3;
+/// Degrees per second a reactor core heats up by per unit of its fuel rod's radioactivity
+pub const REACTOR_HEATING_RATE: f32 = 50.0;
+/// Fraction of a reactor's excess heat lost to the air per second, coolant or not
+const REACTOR_PASSIVE_LOSS: f32 = 0.02;
+/// Fraction of a reactor's excess heat each touching coolant cell carries off per second
+const REACTOR_COOLANT_TRANSFER: f32 = 0.1;
+/// Share of a reactor's excess heat it gives off to the cells around it per second
+const REACTOR_HEAT_EMISSION: f32 = 0.5;
+/// A reactor core this hot melts down and explodes
+pub const REACTOR_MELTDOWN_TEMP: f32 = 2000.0;
 
 /// Tile entity system for complex objects that need more than just material data
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +56,30 @@ pub struct TileEntity {
     pub data: TileEntityData,
     pub active: bool,
     pub update_timer: f32,
+    /// Follows the temperature of the particles around the entity
+    #[serde(default = "ambient_temp")]
+    pub temperature: f32,
+    /// Temperature of the air around the entity, which it settles to when nothing touches
+    /// it; `TileEntityManager::sample_ambient` lowers it near ice and raises it near heat
+    #[serde(default = "ambient_temp")]
+    pub ambient_temperature: f32,
+    /// Seconds left before a burning entity is destroyed
+    #[serde(default)]
+    pub burn_time: Option<f32>,
+    /// Sensors and gates whose signals this entity reads; see `TileEntityManager::update_signals`
+    #[serde(default)]
+    pub inputs: Vec<(i64, i64)>,
+    /// Output of a sensor or gate, or for anything else whether one of its inputs is on
+    #[serde(default)]
+    pub signal: bool,
+}
+
+fn ambient_temp() -> f32 {
+    TILE_AMBIENT_TEMP
+}
+
+fn sign_text(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control()).take(MAX_SIGN_TEXT_LEN).collect()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +92,12 @@ pub enum TileEntityType {
     Torch,
     Spawner,
     Reactor,
+    Sign,
+    Sensor,
+    Logic,
+    Door,
+    Piston,
+    Gate,
     Custom(String),
 }
 
@@ -86,6 +160,38 @@ pub enum TileEntityData {
         power_output: f32,
         waste_products: HashMap<MaterialType, u32>,
     },
+    Sign {
+        text: String,
+    },
+    /// Watches the cell `target` away from it
+    Sensor {
+        target: (i64, i64),
+        condition: SensorCondition,
+    },
+    Logic {
+        gate: LogicGate,
+        /// Inputs of past ticks a delay gate has yet to pass on
+        history: VecDeque<bool>,
+    },
+    /// A panel of `length` Door cells hanging below the entity, drawn back while open
+    Door {
+        length: u32,
+        open: bool,
+    },
+    /// An arm of up to `length` Piston cells reaching out in `direction` while powered
+    Piston {
+        direction: (i64, i64),
+        length: u32,
+        extended: bool,
+    },
+    /// A panel of `length` Door cells below the entity that turns solid or passable each
+    /// time its signal comes on
+    Gate {
+        length: u32,
+        solid: bool,
+        /// The signal as of the last tick, to catch it coming on
+        powered: bool,
+    },
     Custom {
         properties: HashMap<String, String>,
     },
@@ -102,6 +208,11 @@ impl TileEntity {
             },
             active: true,
             update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
         }
     }
 
@@ -121,6 +232,11 @@ impl TileEntity {
             },
             active: true,
             update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
         }
     }
 
@@ -137,6 +253,11 @@ impl TileEntity {
             },
             active: true,
             update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
         }
     }
 
@@ -152,6 +273,57 @@ impl TileEntity {
             },
             active: true,
             update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// An empty pipe segment; it joins the pipes and pumps next to it, and anything in
+    /// `connections`, into one network
+    pub fn new_pipe(position: (i64, i64)) -> Self {
+        Self {
+            tile_type: TileEntityType::Pipe,
+            position,
+            data: TileEntityData::Pipe {
+                fluid_type: None,
+                fluid_amount: 0,
+                flow_rate: 8.0,
+                pressure: 0.0,
+                connections: Vec::new(),
+            },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// A pump drawing `flow_rate` particles per second of any liquid from around it into
+    /// the pipes it touches
+    pub fn new_pump(position: (i64, i64), flow_rate: f32) -> Self {
+        Self {
+            tile_type: TileEntityType::Pump,
+            position,
+            data: TileEntityData::Pump {
+                input_fluid: None,
+                output_fluid: None,
+                flow_rate,
+                power_consumption: 1.0,
+                suction_range: 2,
+            },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
         }
     }
 
@@ -168,72 +340,422 @@ impl TileEntity {
             },
             active: true,
             update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// A water-cooled reactor with a uranium fuel rod lasting `fuel` seconds. It has to
+    /// touch its coolant to shed heat; a dry core climbs to `REACTOR_MELTDOWN_TEMP` and explodes.
+    pub fn new_reactor(position: (i64, i64), fuel: f32) -> Self {
+        Self {
+            tile_type: TileEntityType::Reactor,
+            position,
+            data: TileEntityData::Reactor {
+                fuel_rods: vec![(MaterialType::Uranium, fuel)],
+                moderator: None,
+                coolant: Some(MaterialType::Water),
+                temperature: TILE_AMBIENT_TEMP,
+                pressure: 0.0,
+                power_output: 0.0,
+                waste_products: HashMap::new(),
+            },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// A sign showing `text`, cut to `MAX_SIGN_TEXT_LEN` characters
+    pub fn new_sign(position: (i64, i64), text: &str) -> Self {
+        Self {
+            tile_type: TileEntityType::Sign,
+            position,
+            data: TileEntityData::Sign {
+                text: sign_text(text),
+            },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
         }
     }
 
-    /// Update the tile entity logic
+    /// A sensor whose signal is on while the cell `target` away from it meets `condition`,
+    /// e.g. `SensorCondition::Liquid` a few cells up a tank for a fluid level
+    pub fn new_sensor(position: (i64, i64), target: (i64, i64), condition: SensorCondition) -> Self {
+        Self {
+            tile_type: TileEntityType::Sensor,
+            position,
+            data: TileEntityData::Sensor { target, condition },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// A logic gate reading the signals of the entities at `inputs`
+    pub fn new_logic_gate(position: (i64, i64), gate: LogicGate, inputs: Vec<(i64, i64)>) -> Self {
+        Self {
+            tile_type: TileEntityType::Logic,
+            position,
+            data: TileEntityData::Logic { gate, history: VecDeque::new() },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs,
+            signal: false,
+        }
+    }
+
+    /// A shut door whose panel fills the `length` cells below it; it opens while one of
+    /// its inputs is on
+    pub fn new_door(position: (i64, i64), length: u32) -> Self {
+        Self {
+            tile_type: TileEntityType::Door,
+            position,
+            data: TileEntityData::Door { length, open: false },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// A retracted piston pushing out in `direction`, one of the four unit offsets, by
+    /// `length` cells while powered
+    pub fn new_piston(position: (i64, i64), direction: (i64, i64), length: u32) -> Self {
+        Self {
+            tile_type: TileEntityType::Piston,
+            position,
+            data: TileEntityData::Piston { direction: (direction.0.signum(), direction.1.signum()), length, extended: false },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// A solid gate whose panel fills the `length` cells below it
+    pub fn new_gate(position: (i64, i64), length: u32) -> Self {
+        Self {
+            tile_type: TileEntityType::Gate,
+            position,
+            data: TileEntityData::Gate { length, solid: true, powered: false },
+            active: true,
+            update_timer: 0.0,
+            temperature: TILE_AMBIENT_TEMP,
+            ambient_temperature: TILE_AMBIENT_TEMP,
+            burn_time: None,
+            inputs: Vec::new(),
+            signal: false,
+        }
+    }
+
+    /// Read the signal of the sensor or gate at `source` from the next tick on. Spawners
+    /// with inputs only run while one is on.
+    pub fn connect_input(&mut self, source: (i64, i64)) {
+        if !self.inputs.contains(&source) {
+            self.inputs.push(source);
+        }
+    }
+
+    /// Whether an actuator is driven: unwired spawners always are
+    pub fn is_powered(&self) -> bool {
+        self.signal || (self.inputs.is_empty() && matches!(self.tile_type, TileEntityType::Spawner))
+    }
+
+    /// Text of a sign; None for every other entity
+    pub fn sign_text(&self) -> Option<&str> {
+        match &self.data {
+            TileEntityData::Sign { text } => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Replace a sign's text, returning false if this isn't a sign
+    pub fn set_sign_text(&mut self, new_text: &str) -> bool {
+        match &mut self.data {
+            TileEntityData::Sign { text } => {
+                *text = sign_text(new_text);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Update the entity with the built-in furnace recipes
     pub fn update(&mut self, delta_time: f32, surrounding_particles: &[(i64, i64, &Particle)]) -> Vec<TileEntityEffect> {
+        static DEFAULT_RECIPES: OnceLock<RecipeRegistry> = OnceLock::new();
+        self.update_with_recipes(delta_time, surrounding_particles, DEFAULT_RECIPES.get_or_init(RecipeRegistry::with_defaults))
+    }
+
+    /// Update the entity, with furnaces smelting by `recipes` and the built-in materials
+    pub fn update_with_recipes(&mut self, delta_time: f32, surrounding_particles: &[(i64, i64, &Particle)], recipes: &RecipeRegistry) -> Vec<TileEntityEffect> {
+        static DEFAULT_MATERIALS: OnceLock<MaterialRegistry> = OnceLock::new();
+        self.update_with_registries(delta_time, surrounding_particles, recipes, DEFAULT_MATERIALS.get_or_init(MaterialRegistry::new))
+    }
+
+    /// Update the entity, with furnaces smelting by `recipes` and reactors burning fuel rods
+    /// as `materials` describes them
+    pub fn update_with_registries(
+        &mut self,
+        delta_time: f32,
+        surrounding_particles: &[(i64, i64, &Particle)],
+        recipes: &RecipeRegistry,
+        materials: &MaterialRegistry,
+    ) -> Vec<TileEntityEffect> {
         self.update_timer += delta_time;
         
         if !self.active {
             return Vec::new();
         }
 
-        match &self.data {
-            TileEntityData::Furnace { temperature, fuel_amount, smelting_progress, .. } => {
-                Self::update_furnace_static(delta_time, *temperature, *fuel_amount, *smelting_progress)
-            },
+        let mut effects = self.sample_heat(delta_time, surrounding_particles);
+        if !self.active {
+            return effects;
+        }
+
+        if matches!(self.tile_type, TileEntityType::Furnace) {
+            effects.extend(self.update_furnace(delta_time, recipes));
+            return effects;
+        }
+        if matches!(self.tile_type, TileEntityType::Reactor) {
+            effects.extend(self.update_reactor(delta_time, surrounding_particles, materials));
+            return effects;
+        }
+
+        let position = self.position;
+        let powered = self.is_powered();
+        effects.extend(match &mut self.data {
             TileEntityData::Generator { fuel_amount, heat_generation, .. } => {
-                Self::update_generator_static(delta_time, *fuel_amount, *heat_generation, surrounding_particles)
+                Self::update_generator_static(position, delta_time, *fuel_amount, *heat_generation, surrounding_particles)
             },
             TileEntityData::Torch { fuel_remaining, heat_output, light_radius, .. } => {
-                Self::update_torch_static(delta_time, *fuel_remaining, *heat_output, *light_radius)
+                Self::update_torch_static(position, delta_time, *fuel_remaining, *heat_output, *light_radius)
+            },
+            TileEntityData::Spawner { spawn_material, spawn_rate, spawn_amount, spawn_radius, .. } if powered => {
+                Self::update_spawner_static(position, &mut self.update_timer, *spawn_material, *spawn_rate, *spawn_amount, *spawn_radius)
             },
-            TileEntityData::Spawner { spawn_material, spawn_rate, spawn_amount, spawn_radius, .. } => {
-                Self::update_spawner_static(delta_time, *spawn_material, *spawn_rate, *spawn_amount, *spawn_radius)
+            TileEntityData::Door { length, open } => {
+                *open = powered;
+                vec![TileEntityEffect::DoorPanel { position, length: *length, open: *open }]
             },
-            TileEntityData::Reactor { temperature, pressure, power_output, .. } => {
-                Self::update_reactor_static(delta_time, *temperature, *pressure, *power_output)
+            TileEntityData::Piston { direction, length, extended } => {
+                *extended = powered;
+                vec![TileEntityEffect::PistonArm { position, direction: *direction, length: *length, extended: *extended }]
+            },
+            TileEntityData::Gate { length, solid, powered: was_powered } => {
+                if powered && !*was_powered {
+                    *solid = !*solid;
+                }
+                *was_powered = powered;
+                vec![TileEntityEffect::GatePanel { position, length: *length, solid: *solid }]
             },
             _ => Vec::new(),
+        });
+        effects
+    }
+
+    /// Wooden entities that can catch fire
+    pub fn is_flammable(&self) -> bool {
+        matches!(self.tile_type, TileEntityType::Chest)
+    }
+
+    pub fn is_burning(&self) -> bool {
+        self.burn_time.is_some()
+    }
+
+    /// Move the entity's temperature towards the particles around it, ignite wooden
+    /// containers that touch fire or lava or get too hot, and burn them down. A chest
+    /// that burns out is deactivated and spills its contents as particles.
+    pub fn sample_heat(&mut self, delta_time: f32, surrounding_particles: &[(i64, i64, &Particle)]) -> Vec<TileEntityEffect> {
+        let target = if surrounding_particles.is_empty() {
+            self.ambient_temperature
+        } else {
+            surrounding_particles.iter().map(|(_, _, p)| p.temp).sum::<f32>() / surrounding_particles.len() as f32
+        };
+        self.temperature += (target - self.temperature) * (TILE_HEAT_EXCHANGE_RATE * delta_time).min(1.0);
+
+        let touches_flame = surrounding_particles.iter()
+            .any(|(_, _, p)| matches!(p.material_type, MaterialType::Fire | MaterialType::Lava));
+        if self.is_flammable() && !self.is_burning() && (touches_flame || self.temperature >= WOODEN_IGNITION_TEMP) {
+            self.burn_time = Some(CHEST_BURN_SECONDS);
+        }
+
+        let Some(burn_time) = self.burn_time.as_mut() else {
+            return Vec::new();
+        };
+        *burn_time -= delta_time;
+        self.temperature = self.temperature.max(BURNING_TEMP);
+        let (x, y) = self.position;
+        let mut effects = vec![TileEntityEffect::ParticleSpawn {
+            position: (x, y - 1),
+            material: MaterialType::Fire,
+            amount: 1,
+        }];
+        if *burn_time <= 0.0 {
+            self.active = false;
+            effects.extend(self.spill_contents());
+            effects.push(TileEntityEffect::ParticleSpawn { position: (x, y), material: MaterialType::Ash, amount: 1 });
         }
+        effects
     }
 
-    fn update_furnace(&mut self, delta_time: f32, temperature: &mut f32, fuel_amount: &mut u32, smelting_progress: &mut f32) -> Vec<TileEntityEffect> {
-        let mut effects = Vec::new();
+    /// Empty a chest's inventory into particle spawns at its position
+    fn spill_contents(&mut self) -> Vec<TileEntityEffect> {
+        let TileEntityData::Chest { inventory, .. } = &mut self.data else {
+            return Vec::new();
+        };
+        let position = self.position;
+        inventory.drain()
+            .map(|(material, amount)| TileEntityEffect::ParticleSpawn { position, material, amount })
+            .collect()
+    }
 
-        // Consume fuel to maintain temperature
-        if *fuel_amount > 0 && *temperature < 1000.0 {
-            *fuel_amount = fuel_amount.saturating_sub(1);
-            *temperature += 100.0 * delta_time;
+    /// Burn fuel to heat the furnace, and smelt its input by the first recipe in `recipes`
+    /// that the input, fuel and temperature allow. A finished batch waits in the output
+    /// slot; smelting stalls while the slot holds something else or is full.
+    fn update_furnace(&mut self, delta_time: f32, recipes: &RecipeRegistry) -> Vec<TileEntityEffect> {
+        let (position, ambient) = (self.position, self.ambient_temperature);
+        let TileEntityData::Furnace {
+            fuel, fuel_amount, input_material, input_amount, output_material, output_amount, temperature, smelting_progress,
+        } = &mut self.data else {
+            return Vec::new();
+        };
+
+        let mut effects = Vec::new();
+        if *fuel_amount > 0 {
+            *temperature = (*temperature + FURNACE_HEATING_RATE * delta_time).min(FURNACE_MAX_TEMP);
             effects.push(TileEntityEffect::HeatGeneration {
-                position: self.position,
+                position,
                 heat_amount: 50.0,
                 radius: 3,
             });
-        } else {
-            // Cool down when no fuel
-            *temperature -= 20.0 * delta_time;
-            *temperature = temperature.max(20.0);
-        }
-
-        // Smelting logic
-        if *temperature > 500.0 {
-            *smelting_progress += delta_time * 0.1;
-            if *smelting_progress >= 1.0 {
-                *smelting_progress = 0.0;
-                effects.push(TileEntityEffect::MaterialConversion {
-                    position: self.position,
-                    from_material: MaterialType::Sand, // Example conversion
-                    to_material: MaterialType::Glass,
-                    amount: 1,
-                });
+            while self.update_timer >= FUEL_BURN_SECONDS && *fuel_amount > 0 {
+                self.update_timer -= FUEL_BURN_SECONDS;
+                *fuel_amount -= 1;
+            }
+            if *fuel_amount == 0 {
+                *fuel = None;
             }
+        } else {
+            self.update_timer = 0.0;
+            *temperature = (*temperature - FURNACE_COOLING_RATE * delta_time).max(ambient);
         }
 
+        let recipe = input_material
+            .and_then(|input| recipes.find(input, *input_amount, *fuel, *temperature))
+            .filter(|recipe| {
+                output_material.is_none_or(|output| output == recipe.output)
+                    && *output_amount + recipe.output_amount <= FURNACE_SLOT_CAPACITY
+            });
+        let Some(recipe) = recipe else {
+            *smelting_progress = 0.0;
+            return effects;
+        };
+
+        *smelting_progress += delta_time / recipe.smelt_time;
+        if *smelting_progress >= 1.0 {
+            *smelting_progress = 0.0;
+            *input_amount -= recipe.input_amount;
+            if *input_amount == 0 {
+                *input_material = None;
+            }
+            *output_material = Some(recipe.output);
+            *output_amount += recipe.output_amount;
+        }
         effects
     }
 
+    /// Add fuel to a furnace, returning how much it took. A furnace burns one fuel at a
+    /// time and only accepts `FURNACE_FUELS`.
+    pub fn add_fuel(&mut self, material: MaterialType, amount: u32) -> u32 {
+        match &mut self.data {
+            TileEntityData::Furnace { fuel, fuel_amount, .. } if FURNACE_FUELS.contains(&material) => {
+                Self::fill_slot(fuel, fuel_amount, material, amount)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Load material to smelt into a furnace, returning how much it took
+    pub fn add_furnace_input(&mut self, material: MaterialType, amount: u32) -> u32 {
+        match &mut self.data {
+            TileEntityData::Furnace { input_material, input_amount, .. } => {
+                Self::fill_slot(input_material, input_amount, material, amount)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Empty a furnace's output slot
+    pub fn take_furnace_output(&mut self) -> Option<(MaterialType, u32)> {
+        match &mut self.data {
+            TileEntityData::Furnace { output_material, output_amount, .. } => {
+                let output = output_material.take().map(|material| (material, *output_amount));
+                *output_amount = 0;
+                output
+            }
+            _ => None,
+        }
+    }
+
+    /// Load a reactor with a rod of `material` lasting `fuel` seconds, burned after the rods
+    /// already in it. Only materials radioactive in `materials` make fuel rods; returns whether
+    /// it was taken.
+    pub fn add_fuel_rod(&mut self, material: MaterialType, fuel: f32, materials: &MaterialRegistry) -> bool {
+        match &mut self.data {
+            TileEntityData::Reactor { fuel_rods, .. } if fuel > 0.0 && materials.get(material).radioactivity > 0.0 => {
+                fuel_rods.push((material, fuel));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Empty a reactor of its spent rods, which have decayed into what their material decays into
+    pub fn take_reactor_waste(&mut self) -> Vec<(MaterialType, u32)> {
+        match &mut self.data {
+            TileEntityData::Reactor { waste_products, .. } => waste_products.drain().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn fill_slot(slot: &mut Option<MaterialType>, slot_amount: &mut u32, material: MaterialType, amount: u32) -> u32 {
+        if slot.is_some_and(|held| held != material) {
+            return 0;
+        }
+        let added = FURNACE_SLOT_CAPACITY.saturating_sub(*slot_amount).min(amount);
+        if added > 0 {
+            *slot = Some(material);
+            *slot_amount += added;
+        }
+        added
+    }
+
     fn update_generator(&mut self, delta_time: f32, fuel_amount: &mut u32, heat_generation: f32, _surrounding_particles: &[(i64, i64, &Particle)]) -> Vec<TileEntityEffect> {
         let mut effects = Vec::new();
 
@@ -254,7 +776,7 @@ impl TileEntity {
         effects
     }
 
-    fn update_torch(&mut self, delta_time: f32, fuel_remaining: &mut f32, heat_output: f32, light_radius: u32) -> Vec<TileEntityEffect> {
+    fn update_torch(&mut self, delta_time: f32, fuel_remaining: &mut f32, heat_output: f32, light_radius: u32, rng: &mut impl Rng) -> Vec<TileEntityEffect> {
         let mut effects = Vec::new();
 
         if *fuel_remaining > 0.0 {
@@ -271,9 +793,9 @@ impl TileEntity {
             });
             
             // Occasional spark particles
-            if rand::random::<f32>() < 0.1 {
+            if rng.gen::<f32>() < 0.1 {
                 effects.push(TileEntityEffect::ParticleSpawn {
-                    position: (self.position.0 + rand::random::<i64>() % 3 - 1, self.position.1 - 1),
+                    position: (self.position.0 + rng.gen_range(-1..=1), self.position.1 - 1),
                     material: MaterialType::Fire,
                     amount: 1,
                 });
@@ -285,15 +807,15 @@ impl TileEntity {
         effects
     }
 
-    fn update_spawner(&mut self, delta_time: f32, spawn_material: MaterialType, spawn_rate: f32, spawn_amount: u32, spawn_radius: u32) -> Vec<TileEntityEffect> {
+    fn update_spawner(&mut self, delta_time: f32, spawn_material: MaterialType, spawn_rate: f32, spawn_amount: u32, spawn_radius: u32, rng: &mut impl Rng) -> Vec<TileEntityEffect> {
         let mut effects = Vec::new();
 
         if self.update_timer >= 1.0 / spawn_rate {
             self.update_timer = 0.0;
             
             for _ in 0..spawn_amount {
-                let offset_x = (rand::random::<i64>() % (spawn_radius as i64 * 2 + 1)) - spawn_radius as i64;
-                let offset_y = (rand::random::<i64>() % (spawn_radius as i64 * 2 + 1)) - spawn_radius as i64;
+                let radius = spawn_radius as i64;
+                let (offset_x, offset_y) = (rng.gen_range(-radius..=radius), rng.gen_range(-radius..=radius));
                 
                 effects.push(TileEntityEffect::ParticleSpawn {
                     position: (self.position.0 + offset_x, self.position.1 + offset_y),
@@ -306,25 +828,54 @@ impl TileEntity {
         effects
     }
 
-    fn update_reactor(&mut self, _delta_time: f32, temperature: &mut f32, pressure: &mut f32, power_output: &mut f32) -> Vec<TileEntityEffect> {
-        let mut effects = Vec::new();
+    /// Burn the first fuel rod to heat the core, in proportion to the rod's radioactivity,
+    /// shed heat to the air and to every touching cell of coolant, and give the rest off to
+    /// the cells around. Spent rods go to the waste as their decay product. Without coolant
+    /// the core climbs past `REACTOR_MELTDOWN_TEMP` and explodes.
+    fn update_reactor(&mut self, delta_time: f32, surrounding_particles: &[(i64, i64, &Particle)], materials: &MaterialRegistry) -> Vec<TileEntityEffect> {
+        let (position, ambient) = (self.position, self.ambient_temperature);
+        let TileEntityData::Reactor { fuel_rods, coolant, temperature, pressure, power_output, waste_products, .. } = &mut self.data else {
+            return Vec::new();
+        };
 
-        // Simplified reactor physics
-        *temperature += 10.0; // Heat generation from nuclear reactions
+        if let Some((material, fuel)) = fuel_rods.first_mut() {
+            let rod = materials.get(*material);
+            // Reactors built before rods were radioactive hold a Generator rod, which
+            // keeps heating at the flat rate it always did and leaves no waste
+            let legacy = *material == MaterialType::Generator;
+            let radioactivity = if legacy { 1.0 } else { rod.radioactivity };
+            let burned = delta_time.min(*fuel).max(0.0);
+            *fuel -= burned;
+            *temperature += REACTOR_HEATING_RATE * radioactivity * burned;
+            if *fuel <= 0.0 {
+                if !legacy {
+                    *waste_products.entry(rod.decays_into.unwrap_or(*material)).or_insert(0) += 1;
+                }
+                fuel_rods.remove(0);
+            }
+        }
+        let coolant_cells = surrounding_particles.iter()
+            .filter(|(_, _, p)| Some(p.material_type) == *coolant)
+            .count();
+        let loss_rate = REACTOR_PASSIVE_LOSS + coolant_cells as f32 * REACTOR_COOLANT_TRANSFER;
+        let excess = *temperature - ambient;
+        *temperature -= excess * (loss_rate * delta_time).min(1.0);
         *pressure = *temperature / 100.0;
         *power_output = *temperature * 0.1;
 
-        // Safety systems
-        if *temperature > 2000.0 {
-            effects.push(TileEntityEffect::Explosion {
-                position: self.position,
-                radius: 10,
-                power: (*temperature / 100.0) as u32,
-            });
+        if *temperature > REACTOR_MELTDOWN_TEMP {
+            let power = (*temperature / 100.0) as u32;
             self.active = false;
+            return vec![TileEntityEffect::Explosion { position, radius: 10, power }];
         }
-
-        effects
+        if excess <= 0.0 {
+            return Vec::new();
+        }
+        vec![TileEntityEffect::HeatGeneration {
+            position,
+            heat_amount: excess * REACTOR_HEAT_EMISSION,
+            radius: 3,
+        }]
     }
 
     /// Add items to a chest
@@ -373,26 +924,12 @@ impl TileEntity {
     }
 
     // Static helper methods to avoid borrowing issues
-    fn update_furnace_static(_delta_time: f32, temperature: f32, fuel_amount: u32, smelting_progress: f32) -> Vec<TileEntityEffect> {
+    fn update_generator_static(position: (i64, i64), _delta_time: f32, fuel_amount: u32, heat_generation: f32, _surrounding_particles: &[(i64, i64, &crate::particle::Particle)]) -> Vec<TileEntityEffect> {
         let mut effects = Vec::new();
         
         if fuel_amount > 0 {
             effects.push(TileEntityEffect::HeatGeneration {
-                position: (0, 0), // Position will be set by caller
-                heat_amount: temperature,
-                radius: 3,
-            });
-        }
-        
-        effects
-    }
-
-    fn update_generator_static(_delta_time: f32, fuel_amount: u32, heat_generation: f32, _surrounding_particles: &[(i64, i64, &crate::particle::Particle)]) -> Vec<TileEntityEffect> {
-        let mut effects = Vec::new();
-        
-        if fuel_amount > 0 {
-            effects.push(TileEntityEffect::HeatGeneration {
-                position: (0, 0), // Position will be set by caller
+                position,
                 heat_amount: heat_generation,
                 radius: 5,
             });
@@ -401,18 +938,18 @@ impl TileEntity {
         effects
     }
 
-    fn update_torch_static(_delta_time: f32, fuel_remaining: f32, heat_output: f32, light_radius: u32) -> Vec<TileEntityEffect> {
+    fn update_torch_static(position: (i64, i64), _delta_time: f32, fuel_remaining: f32, heat_output: f32, light_radius: u32) -> Vec<TileEntityEffect> {
         let mut effects = Vec::new();
         
         if fuel_remaining > 0.0 {
             effects.push(TileEntityEffect::HeatGeneration {
-                position: (0, 0), // Position will be set by caller
+                position,
                 heat_amount: heat_output,
                 radius: 2,
             });
             
             effects.push(TileEntityEffect::LightGeneration {
-                position: (0, 0), // Position will be set by caller
+                position,
                 intensity: fuel_remaining / 100.0,
                 radius: light_radius,
             });
@@ -421,13 +958,15 @@ impl TileEntity {
         effects
     }
 
-    fn update_spawner_static(_delta_time: f32, spawn_material: MaterialType, spawn_rate: f32, spawn_amount: u32, spawn_radius: u32) -> Vec<TileEntityEffect> {
+    /// Spawn `spawn_amount` particles every `1 / spawn_rate` seconds of `update_timer`;
+    /// they land in the empty cells nearest the spawner
+    fn update_spawner_static(position: (i64, i64), update_timer: &mut f32, spawn_material: MaterialType, spawn_rate: f32, spawn_amount: u32, _spawn_radius: u32) -> Vec<TileEntityEffect> {
         let mut effects = Vec::new();
         
-        // Simplified spawning logic
-        if spawn_rate > 0.0 {
+        if spawn_rate > 0.0 && *update_timer >= 1.0 / spawn_rate {
+            *update_timer = 0.0;
             effects.push(TileEntityEffect::ParticleSpawn {
-                position: (0, 0), // Position will be set by caller
+                position,
                 material: spawn_material,
                 amount: spawn_amount,
             });
@@ -435,23 +974,12 @@ impl TileEntity {
         
         effects
     }
-
-    fn update_reactor_static(_delta_time: f32, temperature: f32, pressure: f32, power_output: f32) -> Vec<TileEntityEffect> {
-        let mut effects = Vec::new();
-        
-        if temperature > 1000.0 && pressure > 50.0 {
-            effects.push(TileEntityEffect::HeatGeneration {
-                position: (0, 0), // Position will be set by caller
-                heat_amount: power_output,
-                radius: 8,
-            });
-        }
-        
-        effects
-    }
 }
 
-/// Effects that tile entities can produce
+/// Effects that tile entities can produce, applied to a world by
+/// `Simulation::apply_tile_entity_effects` or `AdvancedPhysicsEngine::apply_tile_entity_effects`.
+/// `heat_amount` is degrees per second at the centre; spawns and conversions pick the
+/// cells within `EFFECT_SEARCH_RADIUS` nearest the position.
 #[derive(Debug, Clone)]
 pub enum TileEntityEffect {
     ParticleSpawn {
@@ -486,13 +1014,149 @@ pub enum TileEntityEffect {
         material: MaterialType,
         amount: u32,
     },
+    /// A door's panel of `length` cells below `position`: a shut door fills the empty
+    /// ones with Door, an open one clears them
+    DoorPanel {
+        position: (i64, i64),
+        length: u32,
+        open: bool,
+    },
+    /// A piston's arm: extending pushes what is in its way along, crushing it into
+    /// debris when it can't move; retracting draws the arm back
+    PistonArm {
+        position: (i64, i64),
+        direction: (i64, i64),
+        length: u32,
+        extended: bool,
+    },
+    /// A gate's panel of `length` cells below `position`: turning solid crushes anything
+    /// in it into debris
+    GatePanel {
+        position: (i64, i64),
+        length: u32,
+        solid: bool,
+    },
+}
+
+impl TileEntityEffect {
+    /// Offsets within `radius` of an effect's position, nearest first
+    pub fn offsets_by_distance(radius: u32) -> Vec<(i64, i64)> {
+        let reach = radius as i64;
+        let mut offsets: Vec<(i64, i64)> = (-reach..=reach)
+            .flat_map(|dy| (-reach..=reach).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| dx * dx + dy * dy <= reach * reach)
+            .collect();
+        offsets.sort_by_key(|&(dx, dy)| dx * dx + dy * dy);
+        offsets
+    }
+
+    /// Degrees per second a heat source adds at `offset` from its position, falling off
+    /// linearly to nothing just past `radius`
+    pub fn heat_at(heat_amount: f32, radius: u32, offset: (i64, i64)) -> f32 {
+        let distance = ((offset.0 * offset.0 + offset.1 * offset.1) as f32).sqrt();
+        heat_amount * (1.0 - distance / (radius as f32 + 1.0)).max(0.0)
+    }
+
+    /// Warm every particle within `radius` of `position` by `heat_at` for `delta_time`
+    /// seconds, or chill it when `heat_amount` is negative, returning how many changed
+    pub fn apply_heat(world: &mut impl ThermalWorld, position: (i64, i64), heat_amount: f32, radius: u32, delta_time: f32) -> usize {
+        TileEntityEffect::offsets_by_distance(radius).into_iter()
+            .filter(|&offset| {
+                let degrees = TileEntityEffect::heat_at(heat_amount, radius, offset) * delta_time;
+                world.add_heat((position.0 + offset.0, position.1 + offset.1), degrees)
+            })
+            .count()
+    }
+}
+
+/// What applying tile entity effects did to a world
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileEffectReport {
+    /// Particles warmed by heat sources
+    pub heated: usize,
+    pub spawned: usize,
+    /// Cells transmuted by material conversions
+    pub converted: usize,
+    pub explosions: usize,
+    /// Liquid particles pumps drew into pipes
+    pub pumped: usize,
+    /// Liquid particles let out of pipe outlets
+    pub released: usize,
+    /// Door panel cells shut or drawn back
+    pub door_cells: usize,
+    /// Piston arm and gate panel cells put out or drawn back
+    pub actuated: usize,
+    /// Particles moved along by extending pistons
+    pub pushed: usize,
+    /// Particles crushed into debris by pistons and gates
+    pub crushed: usize,
+}
+
+/// The cells pipe networks exchange liquid with
+pub trait FluidWorld {
+    /// The liquid in a cell, if it holds one
+    fn liquid_at(&self, position: (i64, i64)) -> Option<MaterialType>;
+    /// Whether a particle could be placed in a cell
+    fn is_cell_empty(&self, position: (i64, i64)) -> bool;
+    /// Remove the particle in a cell if it is `material`
+    fn take_liquid(&mut self, position: (i64, i64), material: MaterialType) -> bool;
+    fn put_liquid(&mut self, position: (i64, i64), material: MaterialType) -> bool;
+}
+
+/// The temperature layer tile entities heat, cool and sample
+pub trait ThermalWorld {
+    /// Temperature of the particle in a cell, None for empty cells
+    fn temperature_at(&self, position: (i64, i64)) -> Option<f32>;
+    /// Change the temperature of the particle in a cell by `degrees`; false for empty cells
+    fn add_heat(&mut self, position: (i64, i64), degrees: f32) -> bool;
+
+    /// Mean temperature of the cells within `radius` of `position`, empty cells counting
+    /// as `TILE_AMBIENT_TEMP` air; it drops around ice and rises around fire
+    fn ambient_temperature(&self, position: (i64, i64), radius: u32) -> f32 {
+        let offsets = TileEntityEffect::offsets_by_distance(radius);
+        let total: f32 = offsets.iter()
+            .map(|&(dx, dy)| self.temperature_at((position.0 + dx, position.1 + dy)).unwrap_or(TILE_AMBIENT_TEMP))
+            .sum();
+        total / offsets.len() as f32
+    }
+}
+
+type FluidLinks = HashMap<(i64, i64), Vec<(i64, i64)>>;
+
+/// Pipes and pumps connected to each other
+struct FluidNetwork {
+    nodes: Vec<(i64, i64)>,
+    links: FluidLinks,
+    /// Steps from each node to the nearest pump; empty when the network has no pump
+    pump_distance: HashMap<(i64, i64), usize>,
+}
+
+impl FluidNetwork {
+    /// Whether `to` is further from the pumps than `from`, i.e. where pumped liquid heads
+    fn is_downstream(&self, from: (i64, i64), to: (i64, i64)) -> bool {
+        match (self.pump_distance.get(&from), self.pump_distance.get(&to)) {
+            (Some(from), Some(to)) => to > from,
+            _ => false,
+        }
+    }
 }
 
 /// Manager for all tile entities in the world
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct TileEntityManager {
-    entities: AHashMap<(i64, i64), TileEntity>,
+    pub(crate) entities: AHashMap<(i64, i64), TileEntity>,
     update_order: Vec<(i64, i64)>,
+    recipes: Arc<RecipeRegistry>,
+}
+
+impl Default for TileEntityManager {
+    fn default() -> Self {
+        Self {
+            entities: AHashMap::new(),
+            update_order: Vec::new(),
+            recipes: Arc::new(RecipeRegistry::with_defaults()),
+        }
+    }
 }
 
 impl TileEntityManager {
@@ -500,6 +1164,15 @@ impl TileEntityManager {
         Self::default()
     }
 
+    /// Recipes the managed furnaces smelt by
+    pub fn recipes(&self) -> &Arc<RecipeRegistry> {
+        &self.recipes
+    }
+
+    pub fn set_recipes(&mut self, recipes: Arc<RecipeRegistry>) {
+        self.recipes = recipes;
+    }
+
     pub fn add_tile_entity(&mut self, tile_entity: TileEntity) {
         let position = tile_entity.position;
         self.entities.insert(position, tile_entity);
@@ -519,8 +1192,13 @@ impl TileEntityManager {
         self.entities.get_mut(&position)
     }
 
-    /// Update all tile entities and return their effects
-    pub fn update_all(&mut self, delta_time: f32, get_surrounding_particles: impl Fn((i64, i64)) -> Vec<(i64, i64, Particle)>) -> Vec<TileEntityEffect> {
+    /// Update all tile entities, with fuel rods as `materials` describes them, and return their effects
+    pub fn update_all(
+        &mut self,
+        delta_time: f32,
+        materials: &MaterialRegistry,
+        get_surrounding_particles: impl Fn((i64, i64)) -> Vec<(i64, i64, Particle)>,
+    ) -> Vec<TileEntityEffect> {
         let mut all_effects = Vec::new();
         
         for &position in &self.update_order.clone() {
@@ -530,7 +1208,7 @@ impl TileEntityManager {
                     .map(|(x, y, p)| (*x, *y, p))
                     .collect();
                 
-                let effects = tile_entity.update(delta_time, &surrounding_refs);
+                let effects = tile_entity.update_with_registries(delta_time, &surrounding_refs, &self.recipes, materials);
                 all_effects.extend(effects);
                 
                 // Remove inactive tile entities
@@ -543,10 +1221,281 @@ impl TileEntityManager {
         all_effects
     }
 
+    /// Set every entity's ambient temperature from the cells within
+    /// `AMBIENT_SAMPLE_RADIUS` of it, so idle entities cool below `TILE_AMBIENT_TEMP`
+    /// beside ice and furnaces and reactors lose heat more slowly beside fire. Run this
+    /// before `update_all`.
+    pub fn sample_ambient(&mut self, world: &impl ThermalWorld) {
+        for (&position, tile_entity) in self.entities.iter_mut() {
+            tile_entity.ambient_temperature = world.ambient_temperature(position, AMBIENT_SAMPLE_RADIUS);
+        }
+    }
+
+    /// Move liquid through pipe networks: pumps draw particles from within their suction
+    /// range into the nearest pipe with room, pipes even out their contents with their
+    /// neighbours at up to `flow_rate` units per second, and outlets (pipe ends away from
+    /// any pump) let particles out into an empty cell beyond them. A network carries one
+    /// liquid at a time. Pumps and outlets work off their `update_timer`, so run this
+    /// after `update_all`.
+    pub fn update_fluids(&mut self, delta_time: f32, world: &mut impl FluidWorld) -> TileEffectReport {
+        let mut report = TileEffectReport::default();
+        let links = self.fluid_links();
+        for network in self.fluid_networks(&links) {
+            self.pump_into(&network, world, &mut report);
+            self.balance_pipes(&network, delta_time);
+            self.release_from_outlets(&network, world, &mut report);
+        }
+        report
+    }
+
+    fn is_fluid_node(&self, position: (i64, i64)) -> bool {
+        self.entities.get(&position).is_some_and(|entity| {
+            entity.active && matches!(entity.data, TileEntityData::Pipe { .. } | TileEntityData::Pump { .. })
+        })
+    }
+
+    fn is_pump(&self, position: (i64, i64)) -> bool {
+        self.entities.get(&position).is_some_and(|entity| matches!(entity.data, TileEntityData::Pump { .. }))
+    }
+
+    /// The pipes and pumps joined to each one: those beside it and its explicit
+    /// connections, in either direction
+    fn fluid_links(&self) -> FluidLinks {
+        let mut links: FluidLinks = self.entities.keys()
+            .filter(|&&position| self.is_fluid_node(position))
+            .map(|&position| {
+                let beside = CARDINAL_OFFSETS.iter()
+                    .map(|&(dx, dy)| (position.0 + dx, position.1 + dy))
+                    .filter(|&neighbour| self.is_fluid_node(neighbour))
+                    .collect();
+                (position, beside)
+            })
+            .collect();
+        for entity in self.entities.values().filter(|entity| self.is_fluid_node(entity.position)) {
+            if let TileEntityData::Pipe { connections, .. } = &entity.data {
+                for &connection in connections.iter().filter(|&&c| c != entity.position && self.is_fluid_node(c)) {
+                    links.get_mut(&entity.position).unwrap().push(connection);
+                    links.get_mut(&connection).unwrap().push(entity.position);
+                }
+            }
+        }
+        for neighbours in links.values_mut() {
+            neighbours.sort_unstable();
+            neighbours.dedup();
+        }
+        links
+    }
+
+    /// Connected groups of pipes and pumps, each listed breadth first, in update order
+    fn fluid_networks(&self, links: &FluidLinks) -> Vec<FluidNetwork> {
+        let mut seen = HashSet::new();
+        let mut networks = Vec::new();
+        for &start in &self.update_order {
+            if !links.contains_key(&start) || !seen.insert(start) {
+                continue;
+            }
+            let mut nodes = Vec::new();
+            let mut queue = VecDeque::from([start]);
+            while let Some(position) = queue.pop_front() {
+                nodes.push(position);
+                for &neighbour in &links[&position] {
+                    if seen.insert(neighbour) {
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+            // Steps from the nearest pump, breadth first from all of them
+            let mut pump_distance: HashMap<(i64, i64), usize> = nodes.iter()
+                .filter(|&&node| self.is_pump(node))
+                .map(|&node| (node, 0))
+                .collect();
+            let mut queue: VecDeque<(i64, i64)> = nodes.iter().copied().filter(|node| pump_distance.contains_key(node)).collect();
+            while let Some(position) = queue.pop_front() {
+                let distance = pump_distance[&position] + 1;
+                for &neighbour in &links[&position] {
+                    if let Entry::Vacant(entry) = pump_distance.entry(neighbour) {
+                        entry.insert(distance);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+            networks.push(FluidNetwork {
+                links: nodes.iter().map(|node| (*node, links[node].clone())).collect(),
+                nodes,
+                pump_distance,
+            });
+        }
+        networks
+    }
+
+    /// The liquid a network carries, if any of its pipes hold some
+    fn network_fluid(&self, network: &FluidNetwork) -> Option<MaterialType> {
+        network.nodes.iter().find_map(|position| match self.entities.get(position)?.data {
+            TileEntityData::Pipe { fluid_type, fluid_amount, .. } if fluid_amount > 0 => fluid_type,
+            _ => None,
+        })
+    }
+
+    fn pump_into(&mut self, network: &FluidNetwork, world: &mut impl FluidWorld, report: &mut TileEffectReport) {
+        let mut claimed = HashSet::new();
+        for &pump_position in &network.nodes {
+            let Some(TileEntity { data: TileEntityData::Pump { input_fluid, flow_rate, suction_range, .. }, update_timer, .. }) =
+                self.entities.get(&pump_position).cloned()
+            else {
+                continue;
+            };
+            if flow_rate <= 0.0 {
+                continue;
+            }
+            let interval = 1.0 / flow_rate;
+            let mut timer = update_timer;
+            while timer >= interval {
+                let wanted = input_fluid.or(self.network_fluid(network));
+                let Some(pipe) = self.nearest_pipe_with_room(network, pump_position) else {
+                    break;
+                };
+                let source = TileEntityEffect::offsets_by_distance(suction_range)
+                    .into_iter()
+                    .map(|(dx, dy)| (pump_position.0 + dx, pump_position.1 + dy))
+                    .filter(|cell| !claimed.contains(cell))
+                    .find_map(|cell| {
+                        let liquid = world.liquid_at(cell)?;
+                        wanted.is_none_or(|wanted| wanted == liquid).then_some((cell, liquid))
+                    });
+                let Some((cell, liquid)) = source else {
+                    break;
+                };
+                claimed.insert(cell);
+                if !world.take_liquid(cell, liquid) {
+                    continue;
+                }
+                if let Some(TileEntityData::Pipe { fluid_type, fluid_amount, .. }) = self.entities.get_mut(&pipe).map(|e| &mut e.data) {
+                    *fluid_type = Some(liquid);
+                    *fluid_amount += 1;
+                }
+                timer -= interval;
+                report.pumped += 1;
+            }
+            // Pumps with nothing to do don't bank time
+            if let Some(pump) = self.entities.get_mut(&pump_position) {
+                pump.update_timer = timer.min(interval);
+            }
+        }
+    }
+
+    /// Breadth-first from a pump, the first pipe of its network that is not full
+    fn nearest_pipe_with_room(&self, network: &FluidNetwork, pump_position: (i64, i64)) -> Option<(i64, i64)> {
+        let mut seen = HashSet::from([pump_position]);
+        let mut queue = VecDeque::from([pump_position]);
+        while let Some(position) = queue.pop_front() {
+            if let Some(TileEntityData::Pipe { fluid_amount, .. }) = self.entities.get(&position).map(|e| &e.data) {
+                if *fluid_amount < PIPE_CAPACITY {
+                    return Some(position);
+                }
+            }
+            for &neighbour in &network.links[&position] {
+                if seen.insert(neighbour) {
+                    queue.push_back(neighbour);
+                }
+            }
+        }
+        None
+    }
+
+    fn pipe_amount(&self, position: (i64, i64)) -> Option<(u32, f32)> {
+        match self.entities.get(&position)?.data {
+            TileEntityData::Pipe { fluid_amount, flow_rate, .. } => Some((fluid_amount, flow_rate)),
+            _ => None,
+        }
+    }
+
+    /// Move liquid from fuller pipes to emptier neighbours, then record each pipe's
+    /// pressure as how full it is. Pumps push: a pipe hands a single unit on to an
+    /// emptier pipe further from the pumps, where back towards them it takes a
+    /// difference of two.
+    fn balance_pipes(&mut self, network: &FluidNetwork, delta_time: f32) {
+        let fluid = self.network_fluid(network);
+        for &position in &network.nodes {
+            for &neighbour in &network.links[&position] {
+                let (Some((amount, flow_rate)), Some((neighbour_amount, _))) = (self.pipe_amount(position), self.pipe_amount(neighbour)) else {
+                    continue;
+                };
+                let max_flow = (flow_rate * delta_time).ceil().max(1.0) as u32;
+                let push = network.is_downstream(position, neighbour) as u32;
+                let moved = ((amount.saturating_sub(neighbour_amount) + push) / 2).min(max_flow);
+                if moved == 0 {
+                    continue;
+                }
+                if let TileEntityData::Pipe { fluid_amount, .. } = &mut self.entities.get_mut(&position).unwrap().data {
+                    *fluid_amount -= moved;
+                }
+                if let TileEntityData::Pipe { fluid_amount, .. } = &mut self.entities.get_mut(&neighbour).unwrap().data {
+                    *fluid_amount += moved;
+                }
+            }
+        }
+        for position in &network.nodes {
+            if let Some(TileEntityData::Pipe { fluid_type, fluid_amount, pressure, .. }) = self.entities.get_mut(position).map(|e| &mut e.data) {
+                *fluid_type = if *fluid_amount > 0 { fluid } else { None };
+                *pressure = *fluid_amount as f32 / PIPE_CAPACITY as f32;
+            }
+        }
+    }
+
+    fn release_from_outlets(&mut self, network: &FluidNetwork, world: &mut impl FluidWorld, report: &mut TileEffectReport) {
+        for &position in &network.nodes {
+            let neighbours = &network.links[&position];
+            if neighbours.len() > 1 || neighbours.iter().any(|&neighbour| self.is_pump(neighbour)) {
+                continue;
+            }
+            let Some(TileEntity { data: TileEntityData::Pipe { fluid_type: Some(fluid), fluid_amount, flow_rate, .. }, update_timer, .. }) =
+                self.entities.get(&position).cloned()
+            else {
+                continue;
+            };
+            if fluid_amount == 0 || flow_rate <= 0.0 {
+                continue;
+            }
+            let interval = 1.0 / flow_rate;
+            if update_timer < interval {
+                continue;
+            }
+            // Straight on out of the end if possible, otherwise any free side
+            let mut exits: Vec<(i64, i64)> = neighbours.first()
+                .map(|&(nx, ny)| (position.0 + (position.0 - nx).signum(), position.1 + (position.1 - ny).signum()))
+                .into_iter()
+                .collect();
+            exits.extend(CARDINAL_OFFSETS.iter().map(|&(dx, dy)| (position.0 + dx, position.1 + dy)));
+            let released = exits.into_iter()
+                .any(|cell| !self.entities.contains_key(&cell) && world.is_cell_empty(cell) && world.put_liquid(cell, fluid));
+            let pipe = self.entities.get_mut(&position).unwrap();
+            pipe.update_timer = (update_timer - interval).min(interval);
+            if released {
+                if let TileEntityData::Pipe { fluid_type, fluid_amount, pressure, .. } = &mut pipe.data {
+                    *fluid_amount -= 1;
+                    *pressure = *fluid_amount as f32 / PIPE_CAPACITY as f32;
+                    if *fluid_amount == 0 {
+                        *fluid_type = None;
+                    }
+                }
+                report.released += 1;
+            }
+        }
+    }
+
+    pub fn get_tile_entities(&self) -> impl ExactSizeIterator<Item = &TileEntity> + '_ {
+        self.entities.values()
+    }
+
     pub fn get_all_positions(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
         self.entities.keys().copied()
     }
 
+    /// Position and text of every sign
+    pub fn signs(&self) -> impl Iterator<Item = ((i64, i64), &str)> + '_ {
+        self.entities.values().filter_map(|entity| entity.sign_text().map(|text| (entity.position, text)))
+    }
+
     pub fn count(&self) -> usize {
         self.entities.len()
     }
@@ -560,6 +1509,7 @@ impl TileEntityManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::recipes::Recipe;
 
     #[test]
     fn test_tile_entity_creation() {
@@ -621,4 +1571,304 @@ mod tests {
         // Should generate heat
         assert!(effects.iter().any(|effect| matches!(effect, TileEntityEffect::HeatGeneration { .. })));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_furnace_smelts_by_recipe() {
+        let mut recipes = RecipeRegistry::new();
+        recipes.register(Recipe::new(MaterialType::Iron, MaterialType::Gold, 2.0, 300.0).with_amounts(2, 1));
+
+        let mut furnace = TileEntity::new_furnace((0, 0));
+        assert_eq!(furnace.add_fuel(MaterialType::Sand, 5), 0);
+        assert_eq!(furnace.add_fuel(MaterialType::Coal, 5), 5);
+        assert_eq!(furnace.add_furnace_input(MaterialType::Iron, 4), 4);
+        assert_eq!(furnace.add_furnace_input(MaterialType::Sand, 1), 0);
+
+        // Three seconds to heat past 300°C, then two per batch
+        for _ in 0..450 {
+            furnace.update_with_recipes(1.0 / 60.0, &[], &recipes);
+        }
+        assert_eq!(furnace.take_furnace_output(), Some((MaterialType::Gold, 2)));
+        assert_eq!(furnace.take_furnace_output(), None);
+        if let TileEntityData::Furnace { input_material, fuel_amount, .. } = &furnace.data {
+            assert_eq!(*input_material, None);
+            assert_eq!(*fuel_amount, 4);
+        }
+
+        // The built-in recipes don't know what to do with Iron
+        furnace.add_furnace_input(MaterialType::Iron, 2);
+        for _ in 0..(4 * 60) {
+            furnace.update(1.0 / 60.0, &[]);
+        }
+        assert_eq!(furnace.take_furnace_output(), None);
+    }
+
+    #[test]
+    fn test_chest_burns_near_lava() {
+        let mut chest = TileEntity::new_chest((10, 10), 100);
+        chest.add_to_inventory(MaterialType::Gold, 7);
+
+        let lava = Particle::new(11, 10, MaterialType::Lava, Some(1200.0));
+        let mut spilled = Vec::new();
+        for _ in 0..240 {
+            spilled = chest.update(1.0 / 60.0, &[(11, 10, &lava)]);
+            if !chest.is_active() {
+                break;
+            }
+        }
+
+        assert!(!chest.is_active());
+        assert!(spilled.iter().any(|effect| matches!(
+            effect,
+            TileEntityEffect::ParticleSpawn { material: MaterialType::Gold, amount: 7, .. }
+        )));
+        assert_eq!(chest.remove_from_inventory(MaterialType::Gold, 7), 0);
+    }
+
+    #[test]
+    fn test_tile_heat_sampling() {
+        let mut furnace = TileEntity::new_furnace((0, 0));
+        let hot = Particle::new(1, 0, MaterialType::Stone, Some(500.0));
+        for _ in 0..600 {
+            furnace.update(1.0 / 60.0, &[(1, 0, &hot)]);
+        }
+        assert!(furnace.temperature > 450.0);
+        // Only wooden containers burn
+        assert!(furnace.is_active() && !furnace.is_burning());
+
+        let mut chest = TileEntity::new_chest((0, 0), 10);
+        chest.temperature = WOODEN_IGNITION_TEMP + 10.0;
+        chest.update(1.0 / 60.0, &[]);
+        assert!(chest.is_burning());
+    }
+
+    #[test]
+    fn test_sign_text() {
+        let mut manager = TileEntityManager::new();
+        let long = "x".repeat(MAX_SIGN_TEXT_LEN + 10);
+        manager.add_tile_entity(TileEntity::new_sign((3, 4), &long));
+        manager.add_tile_entity(TileEntity::new_chest((0, 0), 10));
+
+        let signs: Vec<_> = manager.signs().collect();
+        assert_eq!(signs.len(), 1);
+        assert_eq!(signs[0].1.len(), MAX_SIGN_TEXT_LEN);
+
+        let sign = manager.get_tile_entity_mut((3, 4)).unwrap();
+        assert!(sign.set_sign_text("Pull lever\nthen run"));
+        assert_eq!(sign.sign_text(), Some("Pull leverthen run"));
+        assert!(!manager.get_tile_entity_mut((0, 0)).unwrap().set_sign_text("nope"));
+
+        // Signs survive the save format
+        let saved = bincode::serialize(manager.get_tile_entity((3, 4)).unwrap()).unwrap();
+        let loaded: TileEntity = bincode::deserialize(&saved).unwrap();
+        assert_eq!(loaded.sign_text(), Some("Pull leverthen run"));
+    }
+
+    #[test]
+    fn test_effects_carry_positions() {
+        let mut torch = TileEntity::new_torch((7, 3));
+        let effects = torch.update(1.0 / 60.0, &[]);
+        assert!(effects.iter().all(|effect| matches!(
+            effect,
+            TileEntityEffect::HeatGeneration { position: (7, 3), .. } | TileEntityEffect::LightGeneration { position: (7, 3), .. }
+        )));
+
+        // Spawners wait out their rate between bursts
+        let mut spawner = TileEntity::new_spawner((2, 2), MaterialType::Water, 2.0);
+        assert!(spawner.update(0.25, &[]).is_empty());
+        let effects = spawner.update(0.25, &[]);
+        assert!(matches!(effects[..], [TileEntityEffect::ParticleSpawn { position: (2, 2), material: MaterialType::Water, amount: 1 }]));
+        assert!(spawner.update(0.25, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_effects_applied_to_simulation() {
+        use crate::simulation::Simulation;
+
+        let mut simulation = Simulation::new(20, 20);
+        simulation.add_particle(10, 10, MaterialType::Stone, Some(20.0));
+        simulation.add_particle(10, 14, MaterialType::Stone, Some(20.0));
+        for x in 3..6 {
+            simulation.add_particle(x, 15, MaterialType::Sand, None);
+        }
+        let effects = [
+            TileEntityEffect::HeatGeneration { position: (10, 11), heat_amount: 100.0, radius: 2 },
+            TileEntityEffect::ParticleSpawn { position: (15, 5), material: MaterialType::Gold, amount: 7 },
+            TileEntityEffect::MaterialConversion { position: (4, 15), from_material: MaterialType::Sand, to_material: MaterialType::Glass, amount: 2 },
+            TileEntityEffect::ParticleSpawn { position: (-40, 5), material: MaterialType::Gold, amount: 1 },
+        ];
+        let report = simulation.apply_tile_entity_effects(&effects, 1.0);
+
+        assert_eq!(report, TileEffectReport { heated: 1, spawned: 7, converted: 2, ..TileEffectReport::default() });
+        // One cell from the centre of a radius 2 source: two thirds of the heat
+        assert!((simulation.get_particle(10, 10).unwrap().temp - (20.0 + 100.0 * 2.0 / 3.0)).abs() < 1e-3);
+        assert_eq!(simulation.get_particle(10, 14).unwrap().temp, 20.0);
+        assert_eq!(simulation.get_particle(15, 5).unwrap().material_type, MaterialType::Gold);
+        assert_eq!(simulation.get_particle(4, 15).unwrap().material_type, MaterialType::Glass);
+        let glass = (3..6).filter(|&x| simulation.get_particle(x, 15).unwrap().material_type == MaterialType::Glass).count();
+        assert_eq!(glass, 2);
+
+        // A burning chest spills its gold into the grid
+        let mut tile_entities = TileEntityManager::new();
+        let mut chest = TileEntity::new_chest((15, 15), 10);
+        chest.add_to_inventory(MaterialType::Gold, 3);
+        tile_entities.add_tile_entity(chest);
+        simulation.add_particle(16, 15, MaterialType::Lava, None);
+        let mut spawned = 0;
+        for _ in 0..240 {
+            spawned += simulation.update_tile_entities(&mut tile_entities, 1.0 / 60.0).spawned;
+        }
+        assert_eq!(tile_entities.count(), 0);
+        assert!(spawned >= 3);
+    }
+
+    #[test]
+    fn test_reactor_overheats_without_coolant() {
+        let core_temperature = |reactor: &TileEntity| match reactor.data {
+            TileEntityData::Reactor { temperature, .. } => temperature,
+            _ => unreachable!(),
+        };
+        let water = Particle::new(1, 0, MaterialType::Water, Some(20.0));
+        let mut cooled = TileEntity::new_reactor((0, 0), 1000.0);
+        let mut dry = TileEntity::new_reactor((0, 0), 1000.0);
+        let mut meltdown = Vec::new();
+        for _ in 0..400 {
+            let effects = cooled.update(0.5, &[(1, 0, &water), (-1, 0, &water)]);
+            assert!(effects.iter().all(|effect| matches!(effect, TileEntityEffect::HeatGeneration { .. })));
+            if dry.is_active() {
+                meltdown = dry.update(0.5, &[]);
+            }
+        }
+
+        assert!(cooled.is_active());
+        assert!(core_temperature(&cooled) > 100.0 && core_temperature(&cooled) < 300.0);
+        assert!(!dry.is_active());
+        assert!(matches!(meltdown[..], [TileEntityEffect::Explosion { position: (0, 0), .. }]));
+    }
+
+    #[test]
+    fn test_reactor_burns_radioactive_rods_into_their_waste() {
+        let materials = MaterialRegistry::from_toml_str(
+            "[[material]]\nname = \"Thorium\"\nkind = \"solid\"\nradioactivity = 2.0\ndecays_into = \"Lead\"\n",
+        ).unwrap();
+        let thorium = materials.lookup("Thorium").unwrap();
+        let recipes = RecipeRegistry::with_defaults();
+        let mut reactor = TileEntity::new_reactor((0, 0), 2.0);
+        assert!(reactor.add_fuel_rod(thorium, 3.0, &materials));
+        assert!(!reactor.add_fuel_rod(MaterialType::Coal, 3.0, &materials));
+        assert!(!TileEntity::new_furnace((0, 0)).add_fuel_rod(MaterialType::Uranium, 3.0, &materials));
+        assert!(!reactor.add_fuel_rod(thorium, 3.0, &MaterialRegistry::new()));
+
+        let water = Particle::new(1, 0, MaterialType::Water, Some(20.0));
+        for _ in 0..4 {
+            reactor.update_with_registries(1.0, &[(1, 0, &water)], &recipes, &materials);
+        }
+        assert_eq!(reactor.take_reactor_waste(), vec![(MaterialType::Lead, 1)]);
+        reactor.update_with_registries(1.0, &[(1, 0, &water)], &recipes, &materials);
+        assert_eq!(reactor.take_reactor_waste(), vec![(MaterialType::Lead, 1)]);
+        assert!(matches!(&reactor.data, TileEntityData::Reactor { fuel_rods, .. } if fuel_rods.is_empty()));
+
+        // A reactor built with a Generator rod before rods were radioactive still heats up
+        let mut legacy = TileEntity::new_reactor((0, 0), 2.0);
+        if let TileEntityData::Reactor { fuel_rods, .. } = &mut legacy.data {
+            fuel_rods[0].0 = MaterialType::Generator;
+        }
+        let mut fresh = TileEntity::new_reactor((0, 0), 2.0);
+        for _ in 0..3 {
+            legacy.update(1.0, &[]);
+            fresh.update(1.0, &[]);
+        }
+        let core = |reactor: &TileEntity| match reactor.data {
+            TileEntityData::Reactor { temperature, .. } => temperature,
+            _ => unreachable!(),
+        };
+        assert!(core(&legacy) > TILE_AMBIENT_TEMP + 50.0);
+        assert_eq!(core(&legacy), core(&fresh));
+        assert!(legacy.take_reactor_waste().is_empty());
+    }
+
+    #[test]
+    fn test_tile_entities_heat_and_sample_the_grid() {
+        use crate::simulation::Simulation;
+
+        let mut simulation = Simulation::new(20, 20);
+        simulation.add_particle(5, 6, MaterialType::Stone, Some(20.0));
+        for x in 12..19 {
+            simulation.add_particle(x, 12, MaterialType::Ice, Some(-10.0));
+        }
+        let mut tile_entities = TileEntityManager::new();
+        tile_entities.add_tile_entity(TileEntity::new_torch((5, 5)));
+        tile_entities.add_tile_entity(TileEntity::new_chest((15, 10), 10));
+        tile_entities.add_tile_entity(TileEntity::new_chest((2, 15), 10));
+        for _ in 0..600 {
+            simulation.update_tile_entities(&mut tile_entities, 1.0 / 60.0);
+        }
+
+        // The torch warms the stone under it; the chest near the ice settles below the
+        // chest out in the open
+        assert!(simulation.temperature_at((5, 6)).unwrap() > 100.0);
+        let near_ice = tile_entities.get_tile_entity((15, 10)).unwrap();
+        let open_air = tile_entities.get_tile_entity((2, 15)).unwrap();
+        assert!(near_ice.ambient_temperature < 15.0);
+        assert_eq!(open_air.ambient_temperature, TILE_AMBIENT_TEMP);
+        assert!(near_ice.temperature < open_air.temperature - 3.0);
+    }
+
+    #[test]
+    fn test_pumps_carry_water_through_pipes() {
+        use crate::simulation::Simulation;
+
+        let mut simulation = Simulation::new(30, 20);
+        simulation.set_seed(3);
+        // A basin of water in the bottom left corner
+        for y in 14..20 {
+            simulation.add_particle(6, y, MaterialType::Stone, None);
+        }
+        for x in 0..6 {
+            for y in 17..20 {
+                simulation.add_particle(x, y, MaterialType::Water, None);
+            }
+        }
+        let mut tile_entities = TileEntityManager::new();
+        let mut pump = TileEntity::new_pump((3, 16), 20.0);
+        if let TileEntityData::Pump { suction_range, .. } = &mut pump.data {
+            *suction_range = 4;
+        }
+        tile_entities.add_tile_entity(pump);
+        for y in 5..16 {
+            tile_entities.add_tile_entity(TileEntity::new_pipe((3, y)));
+        }
+        // Over the gap at x = 11 on an explicit connection
+        for x in 4..11 {
+            tile_entities.add_tile_entity(TileEntity::new_pipe((x, 5)));
+        }
+        let mut bridged = TileEntity::new_pipe((12, 5));
+        if let TileEntityData::Pipe { connections, .. } = &mut bridged.data {
+            connections.push((10, 5));
+        }
+        tile_entities.add_tile_entity(bridged);
+        tile_entities.add_tile_entity(TileEntity::new_pipe((13, 5)));
+
+        let mut report = TileEffectReport::default();
+        for _ in 0..600 {
+            let step = simulation.update_tile_entities(&mut tile_entities, 1.0 / 60.0);
+            report.pumped += step.pumped;
+            report.released += step.released;
+            simulation.update(1.0 / 60.0);
+        }
+
+        assert!(report.pumped >= 15, "pumped {}", report.pumped);
+        assert!(report.released > 0, "released {}", report.released);
+        let held: u32 = tile_entities.get_tile_entities()
+            .map(|entity| match entity.data {
+                TileEntityData::Pipe { fluid_amount, .. } => fluid_amount,
+                _ => 0,
+            })
+            .sum();
+        assert_eq!(report.pumped, report.released + held as usize);
+        let water = |simulation: &Simulation, xs: std::ops::Range<usize>| {
+            simulation.iter_particles().filter(|p| p.material_type == MaterialType::Water && xs.contains(&p.x)).count()
+        };
+        assert_eq!(water(&simulation, 0..6) + report.pumped, 18);
+        assert!(water(&simulation, 7..30) > 0);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::interpolation::{FixedTimestep, DEFAULT_TICK_RATE, MAX_CATCH_UP_TICKS};
+use serde::{Deserialize, Serialize};
+
+/// Slowest speed multiplier `TimeControl::set_speed` accepts
+pub const MIN_SPEED: f32 = 0.25;
+/// Fastest speed multiplier `TimeControl::set_speed` accepts
+pub const MAX_SPEED: f32 = 8.0;
+
+/// Spectator controls over how fast simulated time passes: pause, single steps and a
+/// speed multiplier. Wall-clock time goes in, scaled by the speed, and comes out as a
+/// whole number of fixed-length ticks, so how often a frontend draws or broadcasts has
+/// no bearing on how many ticks run.
+#[derive(Debug, Clone)]
+pub struct TimeControl {
+    timestep: FixedTimestep,
+    paused: bool,
+    speed: f32,
+    pending_steps: u32,
+}
+
+/// What a `TimeControl` is set to, e.g. to show spectators
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeControlState {
+    pub paused: bool,
+    pub speed: f32,
+}
+
+impl TimeControl {
+    pub fn new(tick_rate: f32) -> Self {
+        Self { timestep: FixedTimestep::new(tick_rate), paused: false, speed: 1.0, pending_steps: 0 }
+    }
+
+    pub fn timestep(&self) -> &FixedTimestep {
+        &self.timestep
+    }
+
+    pub fn timestep_mut(&mut self) -> &mut FixedTimestep {
+        &mut self.timestep
+    }
+
+    /// Seconds of simulated time each tick covers, whatever the speed
+    pub fn tick_interval(&self) -> f32 {
+        self.timestep.tick_interval()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Carry on at the current speed, dropping any steps not yet run
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.pending_steps = 0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        if self.paused {
+            self.resume();
+        } else {
+            self.pause();
+        }
+    }
+
+    /// Pause, then run `ticks` more ticks over the next calls to `advance`
+    pub fn step(&mut self, ticks: u32) {
+        self.pause();
+        self.pending_steps = self.pending_steps.saturating_add(ticks);
+    }
+
+    /// Ticks queued by `step` that haven't run yet
+    pub fn pending_steps(&self) -> u32 {
+        self.pending_steps
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Simulated seconds per real second, clamped to `MIN_SPEED..=MAX_SPEED`; speeds
+    /// that aren't finite are ignored
+    pub fn set_speed(&mut self, speed: f32) {
+        if speed.is_finite() {
+            self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        }
+    }
+
+    pub fn state(&self) -> TimeControlState {
+        TimeControlState { paused: self.paused, speed: self.speed }
+    }
+
+    /// Most ticks one `advance` runs: `MAX_CATCH_UP_TICKS` scaled up by the speed
+    pub fn max_ticks(&self) -> u32 {
+        (MAX_CATCH_UP_TICKS as f32 * self.speed.max(1.0)).ceil() as u32
+    }
+
+    /// Add `elapsed` seconds of wall-clock time and return how many ticks are now due.
+    /// While paused the clock stands still and only queued steps run.
+    pub fn advance(&mut self, elapsed: f32) -> u32 {
+        let max_ticks = self.max_ticks();
+        if self.paused {
+            let steps = self.pending_steps.min(max_ticks);
+            self.pending_steps -= steps;
+            return steps;
+        }
+        self.timestep.advance_at_most(elapsed * self.speed, max_ticks)
+    }
+
+    /// How far between the last tick and the next one rendering is, from 0 to 1
+    pub fn alpha(&self) -> f32 {
+        self.timestep.alpha()
+    }
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_RATE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_scales_ticks_per_second() {
+        let mut time = TimeControl::new(60.0);
+        let run = |time: &mut TimeControl| (0..60).map(|_| time.advance(1.0 / 60.0)).sum::<u32>();
+        assert_eq!(run(&mut time), 60);
+
+        time.set_speed(0.25);
+        assert_eq!(run(&mut time), 15);
+        time.set_speed(100.0);
+        assert_eq!(time.speed(), MAX_SPEED);
+        assert_eq!(run(&mut time), 480);
+        time.set_speed(f32::NAN);
+        assert_eq!(time.speed(), MAX_SPEED);
+
+        // A stall still only catches up so far
+        assert_eq!(time.advance(10.0), time.max_ticks());
+    }
+
+    #[test]
+    fn test_paused_time_only_runs_steps() {
+        let mut time = TimeControl::new(30.0);
+        time.pause();
+        assert_eq!(time.advance(1.0), 0);
+
+        time.step(3);
+        assert!(time.is_paused());
+        assert_eq!(time.advance(0.0), 3);
+        assert_eq!(time.advance(1.0), 0);
+
+        // Long runs of steps are spread over several frames
+        time.step(20);
+        assert_eq!(time.advance(0.0), MAX_CATCH_UP_TICKS);
+        assert_eq!(time.pending_steps(), 20 - MAX_CATCH_UP_TICKS);
+
+        time.resume();
+        assert_eq!(time.pending_steps(), 0);
+        assert_eq!(time.advance(0.11), 3);
+    }
+}
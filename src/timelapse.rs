@@ -0,0 +1,256 @@
+use crate::render::MaterialRenderer;
+use crate::simulation::Simulation;
+
+/// Frames a time-lapse holds before it thins them out: every other frame is dropped and
+/// the capture interval doubles, so a long recording stays the same length
+pub const MAX_TIMELAPSE_FRAMES: usize = 480;
+/// Frames per second of the encoded animation unless set otherwise
+pub const DEFAULT_PLAYBACK_FPS: u16 = 20;
+
+/// Animation formats a time-lapse encodes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelapseFormat {
+    /// Animated GIF, each frame quantized to 256 colors
+    Gif,
+    /// Animated PNG, lossless
+    Apng,
+}
+
+impl TimelapseFormat {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            TimelapseFormat::Gif => "image/gif",
+            TimelapseFormat::Apng => "image/apng",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            TimelapseFormat::Gif => "gif",
+            TimelapseFormat::Apng => "png",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    NotCapturing,
+    NoFrames,
+    /// GIF frames are at most 65535 pixels on a side
+    TooLarge { width: usize, height: usize },
+    GifError(gif::EncodingError),
+    PngError(png::EncodingError),
+}
+
+impl From<gif::EncodingError> for ExportError {
+    fn from(error: gif::EncodingError) -> Self {
+        ExportError::GifError(error)
+    }
+}
+
+impl From<png::EncodingError> for ExportError {
+    fn from(error: png::EncodingError) -> Self {
+        ExportError::PngError(error)
+    }
+}
+
+impl std::fmt::Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::NotCapturing => write!(f, "No time-lapse is being captured"),
+            ExportError::NoFrames => write!(f, "The time-lapse has no frames yet"),
+            ExportError::TooLarge { width, height } => write!(f, "{}x{} is too large for a GIF", width, height),
+            ExportError::GifError(e) => write!(f, "GIF encoding error: {}", e),
+            ExportError::PngError(e) => write!(f, "PNG encoding error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+/// RGBA frames of the grid rendered every few ticks, encoded into an animation on demand
+#[derive(Debug, Clone)]
+pub struct Timelapse {
+    width: usize,
+    height: usize,
+    every_ticks: u32,
+    ticks_until_frame: u32,
+    playback_fps: u16,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Timelapse {
+    /// Capture a `width` x `height` grid every `every_ticks` ticks, at least one
+    pub fn new(width: usize, height: usize, every_ticks: u32) -> Self {
+        Self {
+            width,
+            height,
+            every_ticks: every_ticks.max(1),
+            ticks_until_frame: 0,
+            playback_fps: DEFAULT_PLAYBACK_FPS,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Ticks between frames; doubles each time the recording is thinned out
+    pub fn every_ticks(&self) -> u32 {
+        self.every_ticks
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn playback_fps(&self) -> u16 {
+        self.playback_fps
+    }
+
+    /// Frames per second of the encoded animation, at least one
+    pub fn set_playback_fps(&mut self, fps: u16) {
+        self.playback_fps = fps.max(1);
+    }
+
+    /// Count one tick, rendering the simulation if a frame is due
+    pub fn tick(&mut self, simulation: &Simulation) {
+        if self.ticks_until_frame > 0 {
+            self.ticks_until_frame -= 1;
+            return;
+        }
+        self.ticks_until_frame = self.every_ticks - 1;
+        let mut frame = vec![0; MaterialRenderer::frame_len(self.width, self.height)];
+        MaterialRenderer::new().render(simulation, &mut frame);
+        self.frames.push(frame);
+        if self.frames.len() >= MAX_TIMELAPSE_FRAMES {
+            let mut index = 0;
+            self.frames.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            self.every_ticks = self.every_ticks.saturating_mul(2);
+            self.ticks_until_frame = self.every_ticks - 1;
+        }
+    }
+
+    /// Encode every frame so far as a looping animation
+    pub fn encode(&self, format: TimelapseFormat) -> Result<Vec<u8>, ExportError> {
+        if self.frames.is_empty() {
+            return Err(ExportError::NoFrames);
+        }
+        match format {
+            TimelapseFormat::Gif => self.encode_gif(),
+            TimelapseFormat::Apng => self.encode_apng(),
+        }
+    }
+
+    fn encode_gif(&self) -> Result<Vec<u8>, ExportError> {
+        let too_large = || ExportError::TooLarge { width: self.width, height: self.height };
+        let width = u16::try_from(self.width).map_err(|_| too_large())?;
+        let height = u16::try_from(self.height).map_err(|_| too_large())?;
+        // Delays are in hundredths of a second
+        let delay = (100 / self.playback_fps).max(1);
+
+        let mut encoder = gif::Encoder::new(Vec::new(), width, height, &[])?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        for frame in &self.frames {
+            let mut pixels = frame.clone();
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+        Ok(encoder.into_inner().map_err(gif::EncodingError::from)?)
+    }
+
+    fn encode_apng(&self) -> Result<Vec<u8>, ExportError> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+        encoder.set_frame_delay(1, self.playback_fps)?;
+        let mut writer = encoder.write_header()?;
+        for frame in &self.frames {
+            writer.write_image_data(frame)?;
+        }
+        writer.finish()?;
+        Ok(bytes)
+    }
+}
+
+impl Simulation {
+    /// Start recording a time-lapse of the grid, a frame every `every_ticks` updates,
+    /// replacing any recording in progress
+    pub fn start_capture(&mut self, every_ticks: u32) {
+        self.timelapse = Some(Timelapse::new(self.width, self.height, every_ticks));
+    }
+
+    pub fn capture(&self) -> Option<&Timelapse> {
+        self.timelapse.as_ref()
+    }
+
+    pub fn capture_mut(&mut self) -> Option<&mut Timelapse> {
+        self.timelapse.as_mut()
+    }
+
+    /// Stop recording and hand back the frames, e.g. to encode off the update thread
+    pub fn stop_capture(&mut self) -> Option<Timelapse> {
+        self.timelapse.take()
+    }
+
+    /// Stop recording and encode the time-lapse
+    pub fn finish_capture(&mut self, format: TimelapseFormat) -> Result<Vec<u8>, ExportError> {
+        self.stop_capture().ok_or(ExportError::NotCapturing)?.encode(format)
+    }
+
+    /// Render a frame into the recording if one is due; called once per update
+    pub(crate) fn capture_frame(&mut self) {
+        if let Some(mut timelapse) = self.timelapse.take() {
+            timelapse.tick(self);
+            self.timelapse = Some(timelapse);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    #[test]
+    fn test_capture_encodes_gif_and_apng() {
+        let mut simulation = Simulation::new(16, 12);
+        simulation.set_seed(4);
+        assert!(matches!(simulation.finish_capture(TimelapseFormat::Gif), Err(ExportError::NotCapturing)));
+        simulation.add_particle(8, 0, MaterialType::Sand, None);
+        simulation.start_capture(3);
+        for _ in 0..9 {
+            simulation.update(1.0 / 60.0);
+        }
+        let timelapse = simulation.stop_capture().unwrap();
+        assert_eq!(timelapse.frame_count(), 3);
+
+        let gif = timelapse.encode(TimelapseFormat::Gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        let mut decoder = gif::DecodeOptions::new().read_info(gif.as_slice()).unwrap();
+        let mut frames = 0;
+        while decoder.read_next_frame().unwrap().is_some() {
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+
+        let apng = timelapse.encode(TimelapseFormat::Apng).unwrap();
+        let reader = png::Decoder::new(apng.as_slice()).read_info().unwrap();
+        assert_eq!(reader.info().animation_control().map(|control| control.num_frames), Some(3));
+    }
+
+    #[test]
+    fn test_long_recordings_thin_out() {
+        let mut simulation = Simulation::new(4, 4);
+        simulation.start_capture(1);
+        for _ in 0..MAX_TIMELAPSE_FRAMES {
+            simulation.update(1.0 / 60.0);
+        }
+        let timelapse = simulation.capture().unwrap();
+        assert_eq!(timelapse.frame_count(), MAX_TIMELAPSE_FRAMES / 2);
+        assert_eq!(timelapse.every_ticks(), 2);
+    }
+}
@@ -0,0 +1,344 @@
+use crate::brush::Brush;
+use crate::materials::MaterialType;
+use crate::particle::Particle;
+use crate::simulation::{GridRect, Simulation};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Bytes of cell history kept before the oldest edits are forgotten
+pub const DEFAULT_UNDO_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+type CellRecord = (usize, usize, Option<Particle>);
+
+/// The cells one edit touched and what they held before it. Applying an edit swaps those
+/// contents back into the grid, which turns it into the edit that reverses the swap.
+#[derive(Debug, Clone, Default)]
+pub struct Edit {
+    label: String,
+    cells: Vec<CellRecord>,
+    recorded: HashSet<(usize, usize)>,
+}
+
+impl Edit {
+    fn new(label: String) -> Self {
+        Self { label, ..Self::default() }
+    }
+
+    /// What made the edit, e.g. "paint" or "structure"
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Number of cells it changes
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    fn memory(&self) -> usize {
+        self.cells.len() * std::mem::size_of::<CellRecord>()
+    }
+
+    fn record(&mut self, simulation: &Simulation, x: usize, y: usize) {
+        if x < simulation.width && y < simulation.height && self.recorded.insert((x, y)) {
+            self.cells.push((x, y, simulation.get_particle(x, y).cloned()));
+        }
+    }
+
+    /// Forget cells the edit didn't end up changing
+    fn prune(&mut self, simulation: &Simulation) {
+        self.cells.retain(|(x, y, before)| {
+            let now = simulation.get_particle(*x, *y).map(|particle| particle.material_type);
+            now != before.as_ref().map(|particle| particle.material_type)
+        });
+        self.recorded.clear();
+    }
+
+    fn apply(mut self, simulation: &mut Simulation) -> Edit {
+        for (x, y, contents) in &mut self.cells {
+            let current = match contents.take() {
+                Some(particle) => simulation.set_particle(*x, *y, particle),
+                None => simulation.remove_particle(*x, *y),
+            };
+            *contents = current;
+        }
+        self
+    }
+}
+
+/// Undo and redo stacks of grid edits. Changes are recorded by noting each cell before
+/// it is modified; everything noted between `begin_edit` and `end_edit` is undone together.
+/// Once the history outgrows its memory limit the oldest edits are dropped, though the
+/// latest one is always kept.
+#[derive(Debug, Clone)]
+pub struct UndoManager {
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    open: Option<Edit>,
+    /// Strokes of editors painting at the same time, e.g. a server's clients, by editor id
+    strokes: HashMap<u64, Edit>,
+    memory_limit: usize,
+}
+
+impl Default for UndoManager {
+    fn default() -> Self {
+        Self::with_memory_limit(DEFAULT_UNDO_MEMORY_LIMIT)
+    }
+}
+
+impl UndoManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_memory_limit(memory_limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            open: None,
+            strokes: HashMap::new(),
+            memory_limit,
+        }
+    }
+
+    pub fn memory_limit(&self) -> usize {
+        self.memory_limit
+    }
+
+    pub fn set_memory_limit(&mut self, memory_limit: usize) {
+        self.memory_limit = memory_limit;
+        self.enforce_memory_limit();
+    }
+
+    /// Approximate bytes held by the undo and redo stacks
+    pub fn memory_used(&self) -> usize {
+        self.undo.iter().chain(&self.redo).map(Edit::memory).sum()
+    }
+
+    /// Start a new edit, finishing any that is still open
+    pub fn begin_edit(&mut self, simulation: &Simulation, label: impl Into<String>) {
+        self.end_edit(simulation);
+        self.open = Some(Edit::new(label.into()));
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// Note the contents of `(x, y)` before the open edit changes it; later notes of the
+    /// same cell are ignored. Does nothing when no edit is open.
+    pub fn record_cell(&mut self, simulation: &Simulation, x: usize, y: usize) {
+        if let Some(edit) = &mut self.open {
+            edit.record(simulation, x, y);
+        }
+    }
+
+    pub fn record_region(&mut self, simulation: &Simulation, rect: GridRect) {
+        for y in rect.y..rect.y.saturating_add(rect.height).min(simulation.height) {
+            for x in rect.x..rect.x.saturating_add(rect.width).min(simulation.width) {
+                self.record_cell(simulation, x, y);
+            }
+        }
+    }
+
+    /// Finish the open edit and push it onto the undo stack, clearing the redo stack.
+    /// Edits that changed nothing are discarded.
+    pub fn end_edit(&mut self, simulation: &Simulation) {
+        if let Some(edit) = self.open.take() {
+            self.push(edit, simulation);
+        }
+    }
+
+    fn push(&mut self, mut edit: Edit, simulation: &Simulation) {
+        edit.prune(simulation);
+        if edit.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push_back(edit);
+        self.enforce_memory_limit();
+    }
+
+    /// Run `change` as one edit after noting `cells`, the cells it may modify
+    pub fn edit<R>(
+        &mut self,
+        simulation: &mut Simulation,
+        label: impl Into<String>,
+        cells: impl IntoIterator<Item = (usize, usize)>,
+        change: impl FnOnce(&mut Simulation) -> R,
+    ) -> R {
+        self.begin_edit(simulation, label);
+        for (x, y) in cells {
+            self.record_cell(simulation, x, y);
+        }
+        let result = change(simulation);
+        self.end_edit(simulation);
+        result
+    }
+
+    /// `Simulation::paint` as part of a stroke: joins the open edit, starting a "paint"
+    /// edit if there is none, so a stroke is finished by calling `end_edit`
+    pub fn paint(&mut self, simulation: &mut Simulation, x: usize, y: usize, material: MaterialType, radius: usize) -> usize {
+        self.apply_brush(simulation, &Brush::circle(radius), (x, y), material)
+    }
+
+    /// `Simulation::apply_brush` as part of a stroke, like `paint`
+    pub fn apply_brush(&mut self, simulation: &mut Simulation, brush: &Brush, pos: (usize, usize), material: MaterialType) -> usize {
+        if self.open.is_none() {
+            self.open = Some(Edit::new("paint".to_string()));
+        }
+        let cells = simulation.brush_cells(brush, pos);
+        for &(x, y) in &cells {
+            self.record_cell(simulation, x, y);
+        }
+        simulation.paint_cells(&cells, material)
+    }
+
+    /// `apply_brush` for one of several editors painting at once: each editor's stroke
+    /// stays open until its own `end_stroke`, whatever the others do meanwhile
+    pub fn apply_brush_as(&mut self, editor: u64, simulation: &mut Simulation, brush: &Brush, pos: (usize, usize), material: MaterialType) -> usize {
+        let stroke = self.strokes.entry(editor).or_insert_with(|| Edit::new("paint".to_string()));
+        let cells = simulation.brush_cells(brush, pos);
+        for &(x, y) in &cells {
+            stroke.record(simulation, x, y);
+        }
+        simulation.paint_cells(&cells, material)
+    }
+
+    /// Finish `editor`'s stroke as one undo step
+    pub fn end_stroke(&mut self, editor: u64, simulation: &Simulation) {
+        if let Some(stroke) = self.strokes.remove(&editor) {
+            self.push(stroke, simulation);
+        }
+    }
+
+    /// Revert the latest edit; returns false when there is nothing to undo
+    pub fn undo(&mut self, simulation: &mut Simulation) -> bool {
+        self.end_edit(simulation);
+        let Some(edit) = self.undo.pop_back() else {
+            return false;
+        };
+        self.redo.push(edit.apply(simulation));
+        true
+    }
+
+    /// Reapply the latest undone edit; returns false when there is nothing to redo
+    pub fn redo(&mut self, simulation: &mut Simulation) -> bool {
+        self.end_edit(simulation);
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push_back(edit.apply(simulation));
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.open.as_ref().is_some_and(|edit| !edit.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget all history, e.g. after loading a different world
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = None;
+        self.strokes.clear();
+    }
+
+    fn enforce_memory_limit(&mut self) {
+        while self.memory_used() > self.memory_limit && self.undo.len() + self.redo.len() > 1 {
+            if self.undo.len() > 1 || self.redo.is_empty() {
+                self.undo.pop_front();
+            } else {
+                // The oldest undone edit is the bottom of the redo stack
+                self.redo.remove(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material_at(simulation: &Simulation, x: usize, y: usize) -> Option<MaterialType> {
+        simulation.get_particle(x, y).map(|particle| particle.material_type)
+    }
+
+    #[test]
+    fn test_stroke_undo_and_redo() {
+        let mut simulation = Simulation::new(20, 20);
+        simulation.add_particle(5, 5, MaterialType::Stone, None);
+        let mut undo = UndoManager::new();
+
+        // One stroke of two dabs, the second over the stone
+        undo.paint(&mut simulation, 2, 5, MaterialType::Wood, 1);
+        undo.paint(&mut simulation, 5, 5, MaterialType::Wood, 1);
+        undo.end_edit(&simulation);
+        assert_eq!(material_at(&simulation, 5, 5), Some(MaterialType::Wood));
+
+        assert!(undo.undo(&mut simulation));
+        assert_eq!(material_at(&simulation, 5, 5), Some(MaterialType::Stone));
+        assert_eq!(material_at(&simulation, 2, 5), None);
+        assert!(!undo.can_undo());
+
+        assert!(undo.redo(&mut simulation));
+        assert_eq!(material_at(&simulation, 2, 5), Some(MaterialType::Wood));
+        assert_eq!(material_at(&simulation, 5, 5), Some(MaterialType::Wood));
+        assert!(!undo.redo(&mut simulation));
+
+        // A new edit drops whatever could have been redone
+        undo.undo(&mut simulation);
+        undo.edit(&mut simulation, "clear", [(5, 5)], |simulation| simulation.remove_particle(5, 5));
+        assert!(!undo.can_redo());
+        assert_eq!(simulation.iter_particles().count(), 0);
+    }
+
+    #[test]
+    fn test_memory_limit_drops_oldest_edits() {
+        let mut simulation = Simulation::new(20, 20);
+        let mut undo = UndoManager::with_memory_limit(std::mem::size_of::<CellRecord>() * 2);
+        for x in 0..4 {
+            undo.edit(&mut simulation, "dot", [(x, 0)], |simulation| simulation.add_particle(x, 0, MaterialType::Stone, None));
+        }
+        assert!(undo.memory_used() <= undo.memory_limit());
+
+        assert!(undo.undo(&mut simulation));
+        assert!(undo.undo(&mut simulation));
+        assert!(!undo.undo(&mut simulation));
+        assert_eq!(material_at(&simulation, 1, 0), Some(MaterialType::Stone));
+        assert_eq!(material_at(&simulation, 2, 0), None);
+
+        // Edits that change nothing aren't kept
+        undo.edit(&mut simulation, "noop", [(10, 10)], |_| ());
+        assert!(undo.can_redo());
+    }
+
+    #[test]
+    fn test_editors_finish_their_own_strokes() {
+        let mut simulation = Simulation::new(20, 20);
+        let mut undo = UndoManager::new();
+        let dab = Brush::circle(0);
+        undo.apply_brush_as(1, &mut simulation, &dab, (2, 2), MaterialType::Wood);
+        undo.apply_brush_as(2, &mut simulation, &dab, (8, 8), MaterialType::Stone);
+        undo.end_stroke(1, &simulation);
+        // The second editor's stroke carries on into the same undo step
+        undo.apply_brush_as(2, &mut simulation, &dab, (9, 8), MaterialType::Stone);
+        undo.apply_brush_as(1, &mut simulation, &dab, (3, 2), MaterialType::Wood);
+        undo.end_stroke(2, &simulation);
+
+        assert!(undo.undo(&mut simulation));
+        assert_eq!((material_at(&simulation, 8, 8), material_at(&simulation, 9, 8)), (None, None));
+        assert_eq!(material_at(&simulation, 2, 2), Some(MaterialType::Wood));
+        undo.end_stroke(1, &simulation);
+        assert!(undo.undo(&mut simulation));
+        assert_eq!(material_at(&simulation, 3, 2), None);
+        assert!(undo.undo(&mut simulation));
+        assert_eq!(simulation.iter_particles().count(), 0);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::brush::Brush;
+use crate::render::MaterialRenderer;
+use crate::simulation::Simulation;
+use wasm_bindgen::prelude::*;
+
+/// A grid simulation for running in a browser. Its RGBA frame can be blitted to a canvas
+/// without copying: view `frame_len()` bytes of the module's memory from `frame_ptr()`
+/// as a `Uint8ClampedArray` and wrap it in an `ImageData`. Recreate the view whenever
+/// the memory has grown, which detaches the old one.
+#[wasm_bindgen]
+pub struct WasmSimulation {
+    simulation: Simulation,
+    renderer: MaterialRenderer,
+    frame: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl WasmSimulation {
+    #[wasm_bindgen(constructor)]
+    pub fn new(width: usize, height: usize) -> WasmSimulation {
+        let mut wasm = Self {
+            simulation: Simulation::new(width, height),
+            renderer: MaterialRenderer::new(),
+            frame: vec![0; MaterialRenderer::frame_len(width, height)],
+        };
+        wasm.render();
+        wasm
+    }
+
+    /// Reseed the simulation so the same edits replay the same way
+    pub fn set_seed(&mut self, seed: u32) {
+        self.simulation.set_seed(seed.into());
+    }
+
+    /// Advance by `delta_time` seconds and redraw the frame
+    pub fn step(&mut self, delta_time: f32) {
+        self.simulation.update(delta_time);
+        self.render();
+    }
+
+    /// Paint a disc of the material named `material` (e.g. "Sand", or "Eraser" to clear
+    /// cells), returning how many cells changed; unknown names paint nothing
+    pub fn paint(&mut self, x: usize, y: usize, material: &str, radius: usize) -> usize {
+        let Some(material) = self.simulation.materials().lookup(material) else {
+            return 0;
+        };
+        let painted = self.simulation.apply_brush(&Brush::circle(radius), (x, y), material);
+        self.render();
+        painted
+    }
+
+    pub fn width(&self) -> usize {
+        self.simulation.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.simulation.height
+    }
+
+    pub fn particle_count(&self) -> usize {
+        self.simulation.iter_particles().count()
+    }
+
+    /// Start of the RGBA8 frame, row-major, in the module's linear memory
+    pub fn frame_ptr(&self) -> *const u8 {
+        self.frame.as_ptr()
+    }
+
+    pub fn frame_len(&self) -> usize {
+        self.frame.len()
+    }
+}
+
+impl WasmSimulation {
+    fn render(&mut self) {
+        self.renderer.render(&self.simulation, &mut self.frame);
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint_and_step_update_the_frame() {
+        let mut wasm = WasmSimulation::new(32, 32);
+        wasm.set_seed(1);
+        let empty = wasm.frame.clone();
+        assert_eq!(wasm.frame_len(), 32 * 32 * 4);
+
+        assert!(wasm.paint(16, 4, "sand", 2) > 0);
+        assert_eq!(wasm.paint(16, 4, "no such material", 2), 0);
+        assert_ne!(wasm.frame, empty);
+
+        let painted = wasm.particle_count();
+        for _ in 0..60 {
+            wasm.step(1.0 / 60.0);
+        }
+        assert_eq!(wasm.particle_count(), painted);
+        assert!(wasm.simulation().iter_particles().all(|p| p.y > 4));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::event_bus::SimulationEvent;
+use crate::materials::MaterialType;
+use crate::simulation::Simulation;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Rows at the top of the grid that rain and snow fall from
+pub const SKY_ROWS: usize = 3;
+/// Drops or flakes per column per second at full intensity
+pub const PRECIPITATION_PER_COLUMN: f32 = 0.25;
+/// Chance per second of a lightning strike during a storm at full intensity
+pub const LIGHTNING_CHANCE_PER_SEC: f32 = 0.2;
+/// Temperature snowflakes form at
+pub const SNOWFLAKE_TEMP: f32 = -5.0;
+/// How far past its ignition temperature lightning heats what it hits, enough for it to
+/// catch on its own
+const LIGHTNING_OVERHEAT: f32 = 150.0;
+/// Sparks thrown where lightning lands, when effects are on
+const LIGHTNING_SPARKS: usize = 6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherKind {
+    #[default]
+    Clear,
+    Rain,
+    Snow,
+    /// Rain with the odd lightning strike
+    Storm,
+}
+
+impl WeatherKind {
+    /// What falls from the sky, if anything
+    pub fn precipitation(self) -> Option<MaterialType> {
+        match self {
+            WeatherKind::Clear => None,
+            WeatherKind::Rain | WeatherKind::Storm => Some(MaterialType::Water),
+            WeatherKind::Snow => Some(MaterialType::Snow),
+        }
+    }
+}
+
+/// The weather over a world, saved in `WorldMetadata`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeatherState {
+    pub kind: WeatherKind,
+    /// How hard it rains or snows and how often lightning strikes, from 0 to 1
+    pub intensity: f32,
+}
+
+impl WeatherState {
+    pub fn new(kind: WeatherKind, intensity: f32) -> Self {
+        Self { kind, intensity: intensity.clamp(0.0, 1.0) }
+    }
+
+    pub fn is_clear(&self) -> bool {
+        self.kind == WeatherKind::Clear || self.intensity <= 0.0
+    }
+}
+
+impl Default for WeatherState {
+    fn default() -> Self {
+        Self { kind: WeatherKind::Clear, intensity: 0.5 }
+    }
+}
+
+impl Simulation {
+    /// Let it rain, snow or storm: precipitation appears in empty cells of the top
+    /// `SKY_ROWS` rows, snow piles up and melts like any Snow, and storms now and then
+    /// strike the highest solid surface in a column, setting it alight if it burns
+    pub fn enable_weather(&mut self, weather: WeatherState) {
+        self.weather = Some(weather);
+    }
+
+    pub fn disable_weather(&mut self) {
+        self.weather = None;
+    }
+
+    /// The current weather; None while it is off
+    pub fn weather(&self) -> Option<WeatherState> {
+        self.weather
+    }
+
+    pub(crate) fn step_weather(&mut self, delta_time: f32) {
+        let Some(weather) = self.weather.filter(|weather| !weather.is_clear()) else {
+            return;
+        };
+
+        if let Some(material) = weather.kind.precipitation() {
+            let chance = weather.intensity * PRECIPITATION_PER_COLUMN * delta_time;
+            let temp = (material == MaterialType::Snow).then_some(SNOWFLAKE_TEMP);
+            let rows = SKY_ROWS.min(self.height);
+            for x in 0..self.width {
+                if rows == 0 || self.rng.gen::<f32>() >= chance {
+                    continue;
+                }
+                let y = self.rng.gen_range(0..rows);
+                if self.get_particle(x, y).is_none() {
+                    self.add_particle(x, y, material, temp);
+                }
+            }
+        }
+
+        if weather.kind == WeatherKind::Storm
+            && self.width > 0
+            && self.rng.gen::<f32>() < weather.intensity * LIGHTNING_CHANCE_PER_SEC * delta_time
+        {
+            let x = self.rng.gen_range(0..self.width);
+            self.strike_lightning(x);
+        }
+    }
+
+    /// Strike the highest surface in column `x`, passing through falling rain, snow and
+    /// gases, and heat it past its ignition temperature if it burns. Returns where the
+    /// bolt landed.
+    pub fn strike_lightning(&mut self, x: usize) -> Option<(usize, usize)> {
+        if x >= self.width {
+            return None;
+        }
+        let y = (0..self.height).find(|&y| {
+            self.get_particle(x, y).is_some_and(|particle| {
+                !matches!(particle.material_type, MaterialType::Water | MaterialType::Snow)
+                    && !self.is_diffusing_gas(particle.material_type)
+            })
+        })?;
+
+        let mut struck = self.get_particle(x, y)?.clone();
+        let props = self.materials().get(struck.material_type);
+        if let Some(ignition_temp) = props.ignition_temp.filter(|_| props.flammability > 0.0) {
+            struck.temp = struck.temp.max(ignition_temp + LIGHTNING_OVERHEAT);
+            self.set_particle(x, y, struck);
+        }
+        if let Some(effects) = &mut self.effects {
+            for _ in 0..LIGHTNING_SPARKS {
+                effects.spark(x as f32 + 0.5, y as f32);
+            }
+        }
+        self.emit_event(SimulationEvent::LightningStrike { x: x as i64, y: y as i64 });
+        Some((x, y))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rain_and_snow_fall_from_the_sky() {
+        let mut simulation = Simulation::new(40, 30);
+        simulation.set_seed(3);
+        simulation.enable_weather(WeatherState::new(WeatherKind::Snow, 1.0));
+        for _ in 0..120 {
+            simulation.update(1.0 / 30.0);
+        }
+        let snow: Vec<_> = simulation.iter_particles().filter(|p| p.material_type == MaterialType::Snow).collect();
+        assert!(snow.len() > 20);
+        // Flakes pile up on the floor, still frozen
+        assert!(snow.iter().any(|p| p.y == 29 && p.temp < 0.0));
+
+        simulation.clear();
+        simulation.enable_weather(WeatherState::new(WeatherKind::Rain, 1.0));
+        simulation.update(1.0);
+        assert!(simulation.iter_particles().all(|p| p.material_type == MaterialType::Water && p.y <= SKY_ROWS));
+        assert!(simulation.iter_particles().count() > 0);
+
+        simulation.clear();
+        simulation.disable_weather();
+        simulation.update(1.0);
+        assert_eq!(simulation.iter_particles().count(), 0);
+    }
+
+    #[test]
+    fn test_lightning_ignites_what_it_hits() {
+        let mut simulation = Simulation::new(10, 20);
+        simulation.enable_event_bus();
+        simulation.add_particle(4, 2, MaterialType::Steam, None);
+        simulation.add_particle(4, 5, MaterialType::Water, None);
+        simulation.add_particle(4, 12, MaterialType::Wood, None);
+        simulation.add_particle(6, 12, MaterialType::Stone, None);
+
+        assert_eq!(simulation.strike_lightning(4), Some((4, 12)));
+        let ignition = simulation.materials().get(MaterialType::Wood).ignition_temp.unwrap();
+        assert!(simulation.get_particle(4, 12).unwrap().temp > ignition + 100.0);
+        assert_eq!(simulation.strike_lightning(6), Some((6, 12)));
+        assert_eq!(simulation.get_particle(6, 12).unwrap().temp, 20.0);
+        assert_eq!(simulation.strike_lightning(0), None);
+        assert_eq!(simulation.drain_events()[0], SimulationEvent::LightningStrike { x: 4, y: 12 });
+
+        for _ in 0..10 {
+            simulation.update(1.0 / 60.0);
+        }
+        assert!(simulation.iter_particles().any(|p| p.burning || p.material_type == MaterialType::Fire));
+
+        let json = serde_json::to_string(&WeatherState::new(WeatherKind::Storm, 2.0)).unwrap();
+        assert_eq!(json, r#"{"kind":"storm","intensity":1.0}"#);
+        let legacy: WeatherState = serde_json::from_str("{}").unwrap();
+        assert!(legacy.is_clear());
+    }
+}
@@ -0,0 +1,322 @@
+use crate::materials::Material;
+use crate::particle::Particle;
+use crate::simulation::{GridRect, Simulation};
+use rand::Rng;
+
+/// Side of the square of cells that share one wind vector
+pub const WIND_CELL_SIZE: usize = 8;
+/// Strongest wind in cells per tick; at 1 a gas particle drifts a cell every tick
+pub const MAX_WIND: f32 = 2.0;
+/// Fraction of its speed free wind loses per second
+const WIND_DAMPING: f32 = 1.5;
+/// How fast wind spreads into neighbouring wind cells, per second
+const WIND_DIFFUSION: f32 = 4.0;
+/// Upward wind each burning Fire particle adds to its wind cell, per second
+const FIRE_UPDRAFT: f32 = 0.05;
+/// Outward wind at the centre of an explosion; the gust reaches twice the blast radius
+const EXPLOSION_GUST: f32 = 1.5;
+/// Powders denser than this ignore the wind
+const MAX_DRIFTING_POWDER_DENSITY: f32 = 2.0;
+/// Sideways drift of a falling powder of density 1 relative to a gas in the same wind
+const POWDER_DRIFT: f32 = 0.5;
+/// Weaker wind snaps to still air so a settled field costs nothing
+const WIND_EPSILON: f32 = 1e-3;
+
+/// Coarse airflow over a simulation grid, one vector per `WIND_CELL_SIZE` square of
+/// cells. Fire pushes it upwards, explosions blow it outwards and fans hold it steady
+/// over a region; free wind spreads out and dies down over time.
+#[derive(Debug, Clone)]
+pub struct WindField {
+    cells_x: usize,
+    cells_y: usize,
+    vectors: Vec<(f32, f32)>,
+    fans: Vec<(GridRect, (f32, f32))>,
+    calm: bool,
+}
+
+impl WindField {
+    /// Still air over a `width` x `height` grid of particles
+    pub fn new(width: usize, height: usize) -> Self {
+        let cells_x = width.div_ceil(WIND_CELL_SIZE);
+        let cells_y = height.div_ceil(WIND_CELL_SIZE);
+        Self {
+            cells_x,
+            cells_y,
+            vectors: vec![(0.0, 0.0); cells_x * cells_y],
+            fans: Vec::new(),
+            calm: true,
+        }
+    }
+
+    /// Wind over particle cell `(x, y)` in cells per tick; +y blows downwards
+    pub fn at(&self, x: usize, y: usize) -> (f32, f32) {
+        self.index(x, y).map_or((0.0, 0.0), |index| self.vectors[index])
+    }
+
+    /// Whether no wind blows anywhere and no fan is running
+    pub fn is_calm(&self) -> bool {
+        self.calm && self.fans.is_empty()
+    }
+
+    /// Add `(vx, vy)` to the wind over `(x, y)`
+    pub fn add(&mut self, x: usize, y: usize, (vx, vy): (f32, f32)) {
+        if let Some(index) = self.index(x, y) {
+            let vector = &mut self.vectors[index];
+            *vector = clamp_wind((vector.0 + vx, vector.1 + vy));
+            self.calm = false;
+        }
+    }
+
+    /// Heat rising off a burning cell over `delta_time` seconds
+    pub fn add_updraft(&mut self, x: usize, y: usize, delta_time: f32) {
+        self.add(x, y, (0.0, -FIRE_UPDRAFT * delta_time));
+    }
+
+    /// Blow outwards from `(x, y)`, strongest at the centre and gone at twice `radius`
+    pub fn gust(&mut self, x: usize, y: usize, radius: f32) {
+        let reach = radius * 2.0;
+        if reach <= 0.0 {
+            return;
+        }
+        let half = WIND_CELL_SIZE as f32 / 2.0;
+        for cy in 0..self.cells_y {
+            for cx in 0..self.cells_x {
+                let dx = (cx * WIND_CELL_SIZE) as f32 + half - x as f32;
+                let dy = (cy * WIND_CELL_SIZE) as f32 + half - y as f32;
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance >= reach || distance < f32::EPSILON {
+                    continue;
+                }
+                let strength = EXPLOSION_GUST * (1.0 - distance / reach);
+                let vector = &mut self.vectors[cy * self.cells_x + cx];
+                *vector = clamp_wind((vector.0 + dx / distance * strength, vector.1 + dy / distance * strength));
+                self.calm = false;
+            }
+        }
+    }
+
+    /// Hold the wind over every wind cell touching `region` at `vector` until the fan is
+    /// replaced; a zero vector removes the fan for that region
+    pub fn set_fan(&mut self, region: GridRect, vector: (f32, f32)) {
+        self.fans.retain(|(fan_region, _)| *fan_region != region);
+        if vector != (0.0, 0.0) {
+            self.fans.push((region, clamp_wind(vector)));
+            self.apply_fans();
+        }
+    }
+
+    pub fn fans(&self) -> &[(GridRect, (f32, f32))] {
+        &self.fans
+    }
+
+    /// Stop every fan and still the air
+    pub fn clear(&mut self) {
+        self.fans.clear();
+        self.vectors.fill((0.0, 0.0));
+        self.calm = true;
+    }
+
+    /// Spread, damp and re-apply fans over `delta_time` seconds
+    pub fn step(&mut self, delta_time: f32) {
+        if self.is_calm() {
+            return;
+        }
+
+        let spread = (WIND_DIFFUSION * delta_time).min(1.0);
+        let keep = (1.0 - WIND_DAMPING * delta_time).max(0.0);
+        let previous = self.vectors.clone();
+        let mut calm = true;
+        for cy in 0..self.cells_y {
+            for cx in 0..self.cells_x {
+                let (mut sum, mut count) = ((0.0, 0.0), 0.0);
+                for (nx, ny) in [(cx.wrapping_sub(1), cy), (cx + 1, cy), (cx, cy.wrapping_sub(1)), (cx, cy + 1)] {
+                    if nx < self.cells_x && ny < self.cells_y {
+                        let neighbour = previous[ny * self.cells_x + nx];
+                        sum = (sum.0 + neighbour.0, sum.1 + neighbour.1);
+                        count += 1.0;
+                    }
+                }
+                let own = previous[cy * self.cells_x + cx];
+                let mut vector = own;
+                if count > 0.0 {
+                    vector.0 += (sum.0 / count - own.0) * spread;
+                    vector.1 += (sum.1 / count - own.1) * spread;
+                }
+                vector = (vector.0 * keep, vector.1 * keep);
+                if vector.0.abs() < WIND_EPSILON && vector.1.abs() < WIND_EPSILON {
+                    vector = (0.0, 0.0);
+                } else {
+                    calm = false;
+                }
+                self.vectors[cy * self.cells_x + cx] = vector;
+            }
+        }
+        self.calm = calm;
+        self.apply_fans();
+    }
+
+    fn apply_fans(&mut self) {
+        for &(region, vector) in &self.fans {
+            if region.width == 0 || region.height == 0 {
+                continue;
+            }
+            let last_x = ((region.x + region.width - 1) / WIND_CELL_SIZE).min(self.cells_x.saturating_sub(1));
+            let last_y = ((region.y + region.height - 1) / WIND_CELL_SIZE).min(self.cells_y.saturating_sub(1));
+            for cy in region.y / WIND_CELL_SIZE..=last_y {
+                for cx in region.x / WIND_CELL_SIZE..=last_x {
+                    self.vectors[cy * self.cells_x + cx] = vector;
+                    self.calm = false;
+                }
+            }
+        }
+    }
+
+    /// The wind over particle rows `top..bottom`, with `top` a multiple of
+    /// `WIND_CELL_SIZE`; `set_rows` writes it back
+    #[cfg(feature = "parallel")]
+    pub(crate) fn rows(&self, top: usize, bottom: usize) -> WindField {
+        let (first, last) = (top / WIND_CELL_SIZE, bottom.div_ceil(WIND_CELL_SIZE).min(self.cells_y));
+        WindField {
+            cells_x: self.cells_x,
+            cells_y: last - first,
+            vectors: self.vectors[first * self.cells_x..last * self.cells_x].to_vec(),
+            fans: Vec::new(),
+            calm: self.is_calm(),
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    pub(crate) fn set_rows(&mut self, top: usize, rows: &WindField) {
+        let start = top / WIND_CELL_SIZE * self.cells_x;
+        self.vectors[start..start + rows.vectors.len()].copy_from_slice(&rows.vectors);
+        self.calm &= rows.calm;
+    }
+
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        let (cx, cy) = (x / WIND_CELL_SIZE, y / WIND_CELL_SIZE);
+        (cx < self.cells_x && cy < self.cells_y).then_some(cy * self.cells_x + cx)
+    }
+}
+
+fn clamp_wind((vx, vy): (f32, f32)) -> (f32, f32) {
+    (vx.clamp(-MAX_WIND, MAX_WIND), vy.clamp(-MAX_WIND, MAX_WIND))
+}
+
+impl Simulation {
+    /// Run a fan: the wind over `region` blows at `vector` cells per tick until set
+    /// again, and a zero vector turns the fan off
+    pub fn set_wind(&mut self, region: GridRect, vector: (f32, f32)) {
+        self.wind.set_fan(region, vector);
+    }
+
+    /// Wind over `(x, y)` in cells per tick
+    pub fn wind_at(&self, x: usize, y: usize) -> (f32, f32) {
+        self.wind.at(x, y)
+    }
+
+    pub fn wind(&self) -> &WindField {
+        &self.wind
+    }
+
+    pub fn wind_mut(&mut self) -> &mut WindField {
+        &mut self.wind
+    }
+
+    /// Where the wind carries a gas or a falling light powder this tick, if anywhere.
+    /// Gas drifts a cell along each axis with chance equal to the wind's speed on that
+    /// axis; powder in free fall is pushed diagonally, less the denser it is.
+    pub(crate) fn wind_drift(&mut self, particle: &Particle, props: &Material) -> Option<(usize, usize)> {
+        if self.wind.is_calm() {
+            return None;
+        }
+        let (x, y) = (particle.x, particle.y);
+        let (vx, vy) = self.wind.at(x, y);
+        if vx == 0.0 && vy == 0.0 {
+            return None;
+        }
+
+        let (dx, dy) = if self.is_diffusing_gas(particle.material_type) {
+            (self.wind_step(vx), self.wind_step(vy))
+        } else if props.is_powder(particle.material_type) && props.density <= MAX_DRIFTING_POWDER_DENSITY {
+            let falling = self.offset_cell(x, y, 0, 1).is_some_and(|(bx, by)| self.is_open(bx, by));
+            if !falling {
+                return None;
+            }
+            // Powder that isn't pushed sideways just falls as usual
+            match self.wind_step(vx * POWDER_DRIFT / props.density.max(1.0)) {
+                0 => return None,
+                dx => (dx, 1),
+            }
+        } else {
+            return None;
+        };
+        if (dx, dy) == (0, 0) {
+            return None;
+        }
+
+        self.offset_cell(x, y, dx, dy).filter(|&(nx, ny)| self.is_open(nx, ny))
+    }
+
+    /// One cell in the direction of `speed` with chance `|speed|`, otherwise none
+    fn wind_step(&mut self, speed: f32) -> i32 {
+        if self.rng.gen::<f32>() < speed.abs() {
+            speed.signum() as i32
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::MaterialType;
+
+    #[test]
+    fn test_fans_hold_and_free_wind_dies_down() {
+        let mut wind = WindField::new(64, 64);
+        assert!(wind.is_calm());
+        wind.set_fan(GridRect::new(0, 0, 16, 8), (1.0, 0.0));
+        for _ in 0..120 {
+            wind.step(1.0 / 60.0);
+        }
+        assert_eq!(wind.at(12, 4), (1.0, 0.0));
+        // Spilled over next to the fan, but weaker
+        let beside = wind.at(20, 4).0;
+        assert!(beside > 0.0 && beside < 1.0);
+
+        wind.set_fan(GridRect::new(0, 0, 16, 8), (0.0, 0.0));
+        for _ in 0..600 {
+            wind.step(1.0 / 60.0);
+        }
+        assert!(wind.is_calm());
+        assert_eq!(wind.at(12, 4), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_explosion_gust_blows_outwards() {
+        let mut wind = WindField::new(64, 64);
+        wind.gust(32, 32, 10.0);
+        assert!(wind.at(44, 32).0 > 0.0);
+        assert!(wind.at(20, 32).0 < 0.0);
+        assert!(wind.at(32, 20).1 < 0.0);
+        assert_eq!(wind.at(0, 0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_wind_carries_smoke_and_falling_sand() {
+        let mut simulation = Simulation::new(64, 64);
+        simulation.set_seed(2);
+        simulation.set_wind(GridRect::new(0, 0, 64, 64), (1.0, 0.0));
+        simulation.add_particle(10, 40, MaterialType::Smoke, None);
+        simulation.add_particle(10, 0, MaterialType::Sand, None);
+        for _ in 0..30 {
+            simulation.update(1.0 / 60.0);
+        }
+
+        let smoke = simulation.iter_particles().find(|p| p.material_type == MaterialType::Smoke).unwrap();
+        assert!(smoke.x > 25);
+        let sand = simulation.iter_particles().find(|p| p.material_type == MaterialType::Sand).unwrap();
+        assert!(sand.x > 10);
+    }
+}
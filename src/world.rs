@@ -0,0 +1,495 @@
+use crate::bookmarks::{CameraBookmark, CameraBookmarks, CameraTour};
+use crate::brush::Brush;
+use crate::emitters::EdgeEmitters;
+use crate::events::{WorldEvent, WorldEventKind, WorldEventScheduler};
+use crate::inspector::CellInfo;
+use crate::materials::MaterialType;
+use crate::render::{Camera, ChunkActivityMap, ChunkOverlay, EffectOverlay, FrameCache, MaterialRenderer, RenderMode, RigidBodyOverlay};
+use crate::save_load::{SaveLoadError, SimulationSave};
+use crate::simulation::{GridRect, Simulation};
+use crate::undo::UndoManager;
+
+/// Default fixed timestep used by `World::step`
+pub const DEFAULT_TIME_STEP: f32 = 1.0 / 60.0;
+// Camera shake applied automatically when world events start
+const EARTHQUAKE_SHAKE: f32 = 3.0;
+const METEOR_SHAKE: f32 = 2.0;
+const METEOR_SHAKE_SECONDS: f32 = 0.4;
+// Powders near a meteor impact get knocked loose
+const METEOR_JOSTLE_RADIUS: usize = 12;
+const METEOR_JOSTLE_STRENGTH: f32 = 2.0;
+
+/// Headless facade over the grid simulation for tests, benchmarks and servers.
+///
+/// Unlike `PhysicsEngine` it never reads the wall clock: every `step()` advances the
+/// simulation by a fixed timestep, so runs are reproducible tick for tick.
+pub struct World {
+    simulation: Simulation,
+    renderer: MaterialRenderer,
+    frame_cache: FrameCache,
+    camera: Camera,
+    bookmarks: CameraBookmarks,
+    // Bookmark the camera last jumped to, for cycling through them
+    current_bookmark: Option<String>,
+    tour: Option<CameraTour>,
+    events: Option<WorldEventScheduler>,
+    last_events: Vec<WorldEvent>,
+    emitters: Option<EdgeEmitters>,
+    chunk_overlay: Option<ChunkOverlay>,
+    // Simulation tick before the latest step; chunks written since then show as dirty
+    step_started_at: u64,
+    undo: UndoManager,
+    time_step: f32,
+    tick: u64,
+}
+
+impl World {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self::from_simulation(Simulation::new(width, height))
+    }
+
+    /// Wrap an existing simulation
+    pub fn from_simulation(simulation: Simulation) -> Self {
+        Self {
+            simulation,
+            renderer: MaterialRenderer::new(),
+            frame_cache: FrameCache::default(),
+            camera: Camera::new(),
+            bookmarks: CameraBookmarks::new(),
+            current_bookmark: None,
+            tour: None,
+            events: None,
+            last_events: Vec::new(),
+            emitters: None,
+            chunk_overlay: None,
+            step_started_at: 0,
+            undo: UndoManager::new(),
+            time_step: DEFAULT_TIME_STEP,
+            tick: 0,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.simulation.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.simulation.height
+    }
+
+    /// Number of ticks stepped so far
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn time_step(&self) -> f32 {
+        self.time_step
+    }
+
+    pub fn set_time_step(&mut self, time_step: f32) {
+        self.time_step = time_step;
+    }
+
+    /// Advance the simulation by one fixed timestep
+    pub fn step(&mut self) {
+        self.step_with_delta(self.time_step);
+    }
+
+    /// Advance the simulation by `ticks` fixed timesteps
+    pub fn step_n(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    /// Advance the simulation by an arbitrary delta time (counts as one tick)
+    pub fn step_with_delta(&mut self, delta_time: f32) {
+        self.step_started_at = self.simulation.tick();
+        self.last_events = match &mut self.events {
+            Some(events) => events.update(&mut self.simulation, delta_time),
+            None => Vec::new(),
+        };
+        for event in &self.last_events {
+            match event.kind {
+                WorldEventKind::Earthquake => self.camera.shake(EARTHQUAKE_SHAKE, event.duration),
+                WorldEventKind::Meteor => {
+                    self.camera.shake(METEOR_SHAKE, METEOR_SHAKE_SECONDS);
+                    if let Some(x) = event.x {
+                        let ground = self.surface_below(x, 0);
+                        self.simulation.jostle(x, ground, METEOR_JOSTLE_RADIUS, METEOR_JOSTLE_STRENGTH);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if let Some(emitters) = &mut self.emitters {
+            emitters.update(&mut self.simulation, delta_time);
+        }
+
+        self.simulation.update(delta_time);
+        self.camera.update(delta_time);
+        if let Some(tour) = &mut self.tour {
+            if !tour.update(delta_time, &mut self.camera) {
+                self.tour = None;
+            }
+        }
+        self.tick += 1;
+    }
+
+    /// Paint a filled circle of material, returning the number of cells written. Paints
+    /// up to the next `end_stroke` are undone together.
+    pub fn paint(&mut self, x: usize, y: usize, material: MaterialType, radius: usize) -> usize {
+        self.undo.paint(&mut self.simulation, x, y, material, radius)
+    }
+
+    /// Paint with any brush shape; joins the current stroke like `paint`
+    pub fn apply_brush(&mut self, brush: &Brush, pos: (usize, usize), material: MaterialType) -> usize {
+        self.undo.apply_brush(&mut self.simulation, brush, pos, material)
+    }
+
+    /// Finish the current paint stroke as one undo step
+    pub fn end_stroke(&mut self) {
+        self.undo.end_edit(&self.simulation);
+    }
+
+    /// Empty the grid; this can be undone
+    pub fn clear(&mut self) {
+        let everything = GridRect::new(0, 0, self.width(), self.height());
+        self.undo.begin_edit(&self.simulation, "clear");
+        self.undo.record_region(&self.simulation, everything);
+        self.simulation.clear();
+        self.undo.end_edit(&self.simulation);
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.undo.undo(&mut self.simulation)
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.undo.redo(&mut self.simulation)
+    }
+
+    /// Snapshot of the grid that `load` resumes exactly
+    pub fn save(&self) -> SimulationSave {
+        SimulationSave::capture(&self.simulation)
+    }
+
+    /// Replace the grid with a save of the same size; undo history is forgotten
+    pub fn load(&mut self, save: &SimulationSave) -> Result<(), SaveLoadError> {
+        save.apply_to(&mut self.simulation)?;
+        self.undo.clear();
+        self.tick = save.tick;
+        Ok(())
+    }
+
+    pub fn undo_manager(&self) -> &UndoManager {
+        &self.undo
+    }
+
+    pub fn undo_manager_mut(&mut self) -> &mut UndoManager {
+        &mut self.undo
+    }
+
+    /// Knock settled powders loose around (x, y); see `Simulation::jostle`
+    pub fn jostle(&mut self, x: usize, y: usize, radius: usize, strength: f32) -> usize {
+        self.simulation.jostle(x, y, radius, strength)
+    }
+
+    /// Shake the camera by up to `intensity` cells for `duration` seconds
+    pub fn shake_camera(&mut self, intensity: f32, duration: f32) {
+        self.camera.shake(intensity, duration);
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    pub fn bookmarks(&self) -> &CameraBookmarks {
+        &self.bookmarks
+    }
+
+    /// Replace the bookmarks, e.g. with `WorldMetadata::camera_bookmarks` from a save
+    pub fn set_bookmarks(&mut self, bookmarks: CameraBookmarks) {
+        self.bookmarks = bookmarks;
+        self.current_bookmark = None;
+    }
+
+    /// Save the current view under `name`, replacing any bookmark already called that
+    pub fn bookmark_camera(&mut self, name: impl Into<String>) {
+        let bookmark = CameraBookmark::from_camera(name, &self.camera);
+        self.current_bookmark = Some(bookmark.name.clone());
+        self.bookmarks.set(bookmark);
+    }
+
+    /// Move the camera to a bookmark, cancelling any tour; false if there is none by that name
+    pub fn jump_to_bookmark(&mut self, name: &str) -> bool {
+        let Some(bookmark) = self.bookmarks.get(name) else {
+            return false;
+        };
+        bookmark.apply(&mut self.camera);
+        self.current_bookmark = Some(bookmark.name.clone());
+        self.tour = None;
+        true
+    }
+
+    /// Jump `step` bookmarks on from the last one visited, wrapping around; returns the
+    /// name of the bookmark now shown
+    pub fn cycle_bookmark(&mut self, step: isize) -> Option<String> {
+        let name = self.bookmarks.cycle(self.current_bookmark.as_deref(), step)?.name.clone();
+        self.jump_to_bookmark(&name);
+        Some(name)
+    }
+
+    /// Fly the camera through every bookmark in order; false if there are none
+    pub fn start_tour(&mut self, looping: bool) -> bool {
+        self.tour = CameraTour::through(&self.bookmarks).map(|mut tour| {
+            tour.looping = looping;
+            tour
+        });
+        self.tour.is_some()
+    }
+
+    /// Play a custom tour, e.g. with different timings or stops
+    pub fn play_tour(&mut self, tour: CameraTour) {
+        self.tour = Some(tour);
+    }
+
+    pub fn stop_tour(&mut self) {
+        self.tour = None;
+    }
+
+    pub fn is_touring(&self) -> bool {
+        self.tour.is_some()
+    }
+
+    /// Render the current grid into an RGBA8 buffer of `width * height * 4` bytes,
+    /// redrawing only the chunks changed since the last call
+    pub fn render(&mut self, frame: &mut [u8]) {
+        self.renderer.render_cached_with_camera(&self.simulation, &mut self.frame_cache, frame, &self.camera);
+        match self.render_mode() {
+            RenderMode::Chunks if self.chunk_overlay.is_none() => {
+                ChunkOverlay::new().draw(frame, self.width(), self.height(), &self.chunk_activity(), &self.camera);
+            }
+            RenderMode::RigidBodies => {
+                RigidBodyOverlay::new().draw(frame, self.width(), self.height(), &self.simulation.rigid_regions(), &self.camera);
+            }
+            _ => {}
+        }
+        if let Some(effects) = self.simulation.effects() {
+            EffectOverlay::new().draw(frame, self.width(), self.height(), effects, &self.camera);
+        }
+        if let Some(overlay) = &self.chunk_overlay {
+            overlay.draw(frame, self.width(), self.height(), &self.chunk_activity(), &self.camera);
+        }
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.renderer.mode
+    }
+
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.renderer.mode = mode;
+    }
+
+    /// Switch to the next render mode and return it
+    pub fn cycle_render_mode(&mut self) -> RenderMode {
+        self.renderer.mode = self.renderer.mode.next();
+        self.renderer.mode
+    }
+
+    /// Show the chunk activity debug overlay on top of rendered frames, or hide it
+    pub fn set_chunk_overlay(&mut self, overlay: Option<ChunkOverlay>) {
+        self.chunk_overlay = overlay;
+    }
+
+    pub fn chunk_overlay(&self) -> Option<&ChunkOverlay> {
+        self.chunk_overlay.as_ref()
+    }
+
+    /// Switch the chunk overlay on with default settings, or off; returns whether it is now shown
+    pub fn toggle_chunk_overlay(&mut self) -> bool {
+        self.chunk_overlay = match self.chunk_overlay {
+            Some(_) => None,
+            None => Some(ChunkOverlay::new()),
+        };
+        self.chunk_overlay.is_some()
+    }
+
+    /// State of every chunk, with chunks written during the latest step as dirty
+    pub fn chunk_activity(&self) -> ChunkActivityMap {
+        ChunkActivityMap::capture(&self.simulation, self.step_started_at)
+    }
+
+    /// Details of the cell at `(x, y)`, with its chunk dirty if written during the
+    /// latest step; None off the grid
+    pub fn inspect(&self, x: usize, y: usize) -> Option<CellInfo> {
+        CellInfo::capture(&self.simulation, x, y, self.step_started_at)
+    }
+
+    /// Export the current grid as a freshly allocated RGBA8 buffer
+    pub fn frame_rgba(&mut self) -> Vec<u8> {
+        let mut frame = vec![0; MaterialRenderer::frame_len(self.width(), self.height())];
+        self.render(&mut frame);
+        frame
+    }
+
+    /// Attach a world event scheduler; it runs before the simulation each step
+    pub fn set_events(&mut self, events: Option<WorldEventScheduler>) {
+        self.events = events;
+    }
+
+    pub fn events(&self) -> Option<&WorldEventScheduler> {
+        self.events.as_ref()
+    }
+
+    pub fn events_mut(&mut self) -> Option<&mut WorldEventScheduler> {
+        self.events.as_mut()
+    }
+
+    /// Attach ambient edge emitters; they spawn particles before the simulation each step
+    pub fn set_edge_emitters(&mut self, emitters: Option<EdgeEmitters>) {
+        self.emitters = emitters;
+    }
+
+    pub fn edge_emitters(&self) -> Option<&EdgeEmitters> {
+        self.emitters.as_ref()
+    }
+
+    pub fn edge_emitters_mut(&mut self) -> Option<&mut EdgeEmitters> {
+        self.emitters.as_mut()
+    }
+
+    /// World events that started during the most recent step
+    pub fn last_events(&self) -> &[WorldEvent] {
+        &self.last_events
+    }
+
+    pub fn renderer(&self) -> &MaterialRenderer {
+        &self.renderer
+    }
+
+    pub fn renderer_mut(&mut self) -> &mut MaterialRenderer {
+        &mut self.renderer
+    }
+
+    /// The frame kept between `render` calls, with how long the last one took
+    pub fn frame_cache(&self) -> &FrameCache {
+        &self.frame_cache
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    pub fn simulation_mut(&mut self) -> &mut Simulation {
+        &mut self.simulation
+    }
+
+    pub fn into_simulation(self) -> Simulation {
+        self.simulation
+    }
+
+    /// First occupied cell at or below `y` in column `x` (the bottom row if none)
+    fn surface_below(&self, x: usize, y: usize) -> usize {
+        (y..self.height())
+            .find(|&row| self.simulation.get_particle(x, row).is_some())
+            .unwrap_or(self.height().saturating_sub(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_world_step_counts_ticks() {
+        let mut world = World::new(20, 20);
+        world.paint(10, 2, MaterialType::Sand, 0);
+
+        world.step_n(5);
+
+        assert_eq!(world.tick(), 5);
+        assert!(world.simulation().get_particle(10, 2).is_none());
+    }
+
+    #[test]
+    fn test_frame_export() {
+        let mut world = World::new(8, 4);
+        world.paint(3, 1, MaterialType::Stone, 0);
+
+        let frame = world.frame_rgba();
+        assert_eq!(frame.len(), 8 * 4 * 4);
+
+        let index = (8 + 3) * 4;
+        assert_ne!(&frame[index..index + 3], &[0, 0, 0]);
+        assert_eq!(&frame[0..4], &[0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_jostle_wakes_settled_powder() {
+        let mut world = World::new(30, 20);
+        for y in 17..20 {
+            for x in 0..30 {
+                world.simulation_mut().add_particle(x, y, MaterialType::Sand, None);
+            }
+        }
+        world.step_n(60);
+        let above_surface = |world: &World| (0..30).any(|x| (0..17).any(|y| world.simulation().get_particle(x, y).is_some()));
+        assert!(!above_surface(&world));
+
+        assert!(world.jostle(15, 17, 5, 3.0) > 0);
+        world.shake_camera(3.0, 0.5);
+        assert!(world.camera().is_shaking());
+        world.step();
+        assert!(above_surface(&world));
+
+        // Impulses decay and the grains come to rest
+        world.step_n(120);
+        let state = world.simulation().get_state();
+        assert_eq!(state.particles.len(), 90);
+        assert!(state.particles.values().all(|p| !p.has_velocity()));
+        assert!(!world.camera().is_shaking());
+        assert_eq!(world.camera().offset(), (0, 0));
+    }
+
+    #[test]
+    fn test_bookmarks_and_tour() {
+        let mut world = World::new(20, 20);
+        world.camera_mut().x = 12;
+        world.bookmark_camera("ridge");
+        world.camera_mut().x = 0;
+        world.bookmark_camera("home");
+
+        assert_eq!(world.cycle_bookmark(1).as_deref(), Some("ridge"));
+        assert_eq!(world.camera().x, 12);
+        assert!(!world.jump_to_bookmark("missing"));
+
+        assert!(world.start_tour(false));
+        world.step_n(30);
+        assert!(world.is_touring());
+        world.step_n(600);
+        assert!(!world.is_touring());
+        assert_eq!(world.camera().x, 0);
+    }
+
+    #[test]
+    fn test_undo_clear_and_stroke() {
+        let mut world = World::new(20, 20);
+        world.paint(5, 19, MaterialType::Stone, 0);
+        world.paint(6, 19, MaterialType::Stone, 0);
+        world.end_stroke();
+        world.clear();
+        assert!(world.simulation().get_particle(5, 19).is_none());
+
+        assert!(world.undo());
+        assert!(world.simulation().get_particle(6, 19).is_some());
+        assert!(world.undo());
+        assert_eq!(world.simulation().iter_particles().count(), 0);
+        assert!(world.redo());
+        assert_eq!(world.simulation().iter_particles().count(), 2);
+    }
+}
@@ -73,10 +73,7 @@ impl WorldGenerator {
                 let height = ((height_noise + 1.0) * 0.5 * 30.0) as i32 + 20; // Height between 20-50
                 heightmap[local_y][local_x] = height;
                 
-                // Determine biome based on temperature and humidity
-                let temperature = self.noise_temperature.get([world_x * 0.005, world_y * 0.005]);
-                let humidity = self.noise_humidity.get([world_x * 0.005, world_y * 0.005]);
-                biome_map[local_y][local_x] = self.determine_biome(temperature, humidity);
+                biome_map[local_y][local_x] = self.biome_at(world_x as i64, world_y as i64);
             }
         }
         
@@ -127,6 +124,18 @@ impl WorldGenerator {
         
         // Generate structures and features
         self.generate_features(chunk_key, chunk_manager, tile_entity_manager, &biome_map, &mut rng);
+
+        // Solid rock and open sky collapse into a palette until something changes them
+        chunk_manager.compress_chunk(chunk_key);
+    }
+
+    /// Biome at a world position, from the same temperature and humidity noise that
+    /// shapes generated terrain
+    pub fn biome_at(&self, world_x: i64, world_y: i64) -> BiomeType {
+        let point = [world_x as f64 * 0.005, world_y as f64 * 0.005];
+        let temperature = self.noise_temperature.get(point);
+        let humidity = self.noise_humidity.get(point);
+        self.determine_biome(temperature, humidity)
     }
 
     fn determine_biome(&self, temperature: f64, humidity: f64) -> BiomeType {
@@ -272,7 +281,7 @@ impl WorldGenerator {
                 match biome {
                     BiomeType::Forest => {
                         if rng.gen::<f32>() < 0.1 {
-                            self.generate_tree(world_x, world_y, chunk_manager);
+                            self.generate_tree(world_x, world_y, chunk_manager, rng);
                         }
                     },
                     BiomeType::Desert => {
@@ -291,7 +300,7 @@ impl WorldGenerator {
                     BiomeType::Tundra => {
                         if rng.gen::<f32>() < 0.03 {
                             // Generate ice formations
-                            self.generate_ice_formation(world_x, world_y, chunk_manager);
+                            self.generate_ice_formation(world_x, world_y, chunk_manager, rng);
                         }
                     },
                     _ => {},
@@ -314,7 +323,7 @@ impl WorldGenerator {
         }
     }
 
-    fn generate_tree(&self, center_x: i64, center_y: i64, chunk_manager: &mut ChunkManager) {
+    fn generate_tree(&self, center_x: i64, center_y: i64, chunk_manager: &mut ChunkManager, rng: &mut ChaCha8Rng) {
         // Simple tree generation
         let trunk_height = 5;
         let crown_radius = 3;
@@ -332,7 +341,7 @@ impl WorldGenerator {
                     let leaf_x = center_x + dx;
                     let leaf_y = center_y + trunk_height + dy;
                     
-                    if rand::random::<f32>() < 0.7 {
+                    if rng.gen::<f32>() < 0.7 {
                         let particle = Particle::new(leaf_x as usize, leaf_y as usize, MaterialType::Plant, None);
                         chunk_manager.set_particle(leaf_x, leaf_y, particle);
                     }
@@ -364,8 +373,8 @@ impl WorldGenerator {
         }
     }
 
-    fn generate_ice_formation(&self, center_x: i64, center_y: i64, chunk_manager: &mut ChunkManager) {
-        let height = rand::random::<i64>() % 5 + 3;
+    fn generate_ice_formation(&self, center_x: i64, center_y: i64, chunk_manager: &mut ChunkManager, rng: &mut ChaCha8Rng) {
+        let height = rng.gen_range(3..8);
         
         for y in 0..height {
             let width = ((height - y) as f64 * 0.5 + 1.0) as i64;
@@ -461,6 +470,7 @@ impl Default for BiomeRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::chunk::CHUNK_AREA;
     use crate::tile_entity::TileEntityManager;
 
     #[test]
@@ -494,4 +504,26 @@ mod tests {
         // Should have some chunks
         assert!(chunk_manager.chunk_count() > 0);
     }
+
+    #[test]
+    fn test_generated_chunks_are_compressed() {
+        let generator = WorldGenerator::new(7);
+        let mut chunk_manager = ChunkManager::new();
+        let mut tile_entity_manager = TileEntityManager::new();
+        for chunk_y in 0..8 {
+            for chunk_x in 0..8 {
+                generator.generate_chunk((chunk_x, chunk_y), &mut chunk_manager, &mut tile_entity_manager);
+            }
+        }
+        // Trees and ores spill into chunks already generated, expanding them again
+        for chunk_y in 0..8 {
+            for chunk_x in 0..8 {
+                chunk_manager.compress_chunk((chunk_x, chunk_y));
+            }
+        }
+
+        // Air, stone and the deep layers collapse into palettes
+        let dense_bytes = chunk_manager.chunk_count() * CHUNK_AREA * 12;
+        assert!(chunk_manager.memory_bytes() * 5 <= dense_bytes);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+use crate::chunk::CHUNK_SIZE;
+use crate::engine_v2::AdvancedPhysicsEngine;
+use crate::material_registry::{MaterialRegistry, MaterialRegistryError};
+use crate::materials::MaterialType;
+use crate::reactions::ReactionRegistry;
+use crate::rigidbody::DEFAULT_GRAVITY;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Scales the probability of every reaction between one ordered pair of materials
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReactionMultiplier {
+    pub reactant: MaterialType,
+    pub neighbor: MaterialType,
+    pub multiplier: f32,
+}
+
+/// Physics overrides carried by a world save so it behaves the same on every host.
+///
+/// Applying the rules replaces the target's material and reaction tables with the
+/// built-ins plus these overrides; the host's own material packs are not consulted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldRules {
+    /// Multiplier on gravity. Below 1 grid particles fall on only that fraction of
+    /// ticks; rigid bodies scale linearly.
+    pub gravity: f32,
+    /// Multiplier on every reaction probability
+    pub reaction_rate: f32,
+    /// Per-pair multipliers, applied on top of `reaction_rate`
+    pub reaction_multipliers: Vec<ReactionMultiplier>,
+    /// Materials that can't be placed; existing particles are removed when applied
+    pub disabled_materials: Vec<MaterialType>,
+    /// Material pack TOML sources embedded in the save, loaded in order
+    pub material_packs: Vec<String>,
+}
+
+impl Default for WorldRules {
+    fn default() -> Self {
+        Self {
+            gravity: 1.0,
+            reaction_rate: 1.0,
+            reaction_multipliers: Vec::new(),
+            disabled_materials: Vec::new(),
+            material_packs: Vec::new(),
+        }
+    }
+}
+
+impl WorldRules {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn allows(&self, material_type: MaterialType) -> bool {
+        !self.disabled_materials.contains(&material_type)
+    }
+
+    /// Built-in materials plus the embedded packs
+    pub fn material_registry(&self) -> Result<MaterialRegistry, MaterialRegistryError> {
+        let mut registry = MaterialRegistry::new();
+        for pack in &self.material_packs {
+            registry.load_toml_str(pack)?;
+        }
+        Ok(registry)
+    }
+
+    /// The built-in reaction table with the multipliers applied
+    pub fn reaction_registry(&self) -> ReactionRegistry {
+        let mut reactions = ReactionRegistry::with_defaults();
+        reactions.scale_all(self.reaction_rate);
+        for multiplier in &self.reaction_multipliers {
+            reactions.scale(multiplier.reactant, multiplier.neighbor, multiplier.multiplier);
+        }
+        reactions
+    }
+
+    /// Apply the rules to a grid simulation, removing particles of disabled materials
+    pub fn apply_to_simulation(&self, simulation: &mut Simulation) -> Result<(), MaterialRegistryError> {
+        simulation.set_materials(Arc::new(self.material_registry()?));
+        simulation.set_reactions(Arc::new(self.reaction_registry()));
+        simulation.set_gravity(self.gravity);
+        simulation.set_disabled_materials(self.disabled_materials.iter().copied());
+
+        for y in 0..simulation.height {
+            for x in 0..simulation.width {
+                if simulation.get_particle(x, y).is_some_and(|p| !self.allows(p.material_type)) {
+                    simulation.remove_particle(x, y);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the rules to the chunked engine, removing particles of disabled materials
+    pub fn apply_to_engine(&self, engine: &mut AdvancedPhysicsEngine) -> Result<(), MaterialRegistryError> {
+        engine.physics_state.set_materials(Arc::new(self.material_registry()?));
+        engine.reactions = self.reaction_registry();
+        engine.rigidbody_manager.gravity.y = DEFAULT_GRAVITY * self.gravity;
+        engine.disabled_materials = self.disabled_materials.iter().copied().collect();
+
+        let mut disabled = Vec::new();
+        for (_, chunk) in engine.chunk_manager.chunks_iter() {
+            for y in 0..CHUNK_SIZE {
+                for x in 0..CHUNK_SIZE {
+                    if chunk.get_particle(x, y).is_some_and(|p| !self.allows(p.material_type)) {
+                        disabled.push(chunk.world_pos(x, y));
+                    }
+                }
+            }
+        }
+        for (world_x, world_y) in disabled {
+            engine.remove_particle(world_x, world_y);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_rules_deserialize_to_defaults() {
+        let rules: WorldRules = serde_json::from_str(r#"{"gravity": 0.5}"#).unwrap();
+        assert_eq!(rules.gravity, 0.5);
+        assert_eq!(rules.reaction_rate, 1.0);
+        assert!(rules.disabled_materials.is_empty());
+        assert!(WorldRules::default().is_default());
+    }
+
+    #[test]
+    fn test_apply_to_simulation() {
+        let rules = WorldRules {
+            reaction_rate: 0.5,
+            reaction_multipliers: vec![ReactionMultiplier {
+                reactant: MaterialType::Water,
+                neighbor: MaterialType::Lava,
+                multiplier: 0.0,
+            }],
+            disabled_materials: vec![MaterialType::Gunpowder],
+            material_packs: vec!["[[material]]\nname = \"Glowdust\"\nkind = \"powder\"\n".to_string()],
+            ..WorldRules::default()
+        };
+
+        let mut simulation = Simulation::new(10, 10);
+        simulation.add_particle(2, 9, MaterialType::Gunpowder, None);
+        rules.apply_to_simulation(&mut simulation).unwrap();
+
+        assert!(simulation.get_particle(2, 9).is_none());
+        assert!(!simulation.add_particle(3, 9, MaterialType::Gunpowder, None));
+        assert!(simulation.materials().lookup("glowdust").is_some());
+
+        let reactions = simulation.reactions();
+        assert_eq!(reactions.reactions_for(MaterialType::Lava, MaterialType::Water)[0].probability, 0.25);
+        assert_eq!(reactions.reactions_for(MaterialType::Water, MaterialType::Lava)[0].probability, 0.0);
+    }
+
+    #[test]
+    fn test_apply_to_engine() {
+        let rules = WorldRules {
+            gravity: 0.0,
+            disabled_materials: vec![MaterialType::Sand],
+            ..WorldRules::default()
+        };
+
+        let mut engine = AdvancedPhysicsEngine::new();
+        engine.add_particle(5, 5, MaterialType::Sand, None);
+        rules.apply_to_engine(&mut engine).unwrap();
+
+        assert!(engine.get_particle(5, 5).is_none());
+        assert!(!engine.add_particle(5, 5, MaterialType::Sand, None));
+        assert_eq!(engine.rigidbody_manager.gravity.y, 0.0);
+    }
+}